@@ -5,7 +5,8 @@ use syn::{
     parse_macro_input,
     punctuated::Punctuated,
     token::Comma,
-    FnArg, GenericParam, Generics, ItemFn, Pat, PatType, TypeParam, WhereClause,
+    Data, DeriveInput, Expr, Fields, FnArg, GenericParam, Generics, ItemFn, Lit, LitStr, Meta, Pat,
+    PatType, TypeParam, WhereClause,
 };
 
 // Parse attribute arguments like #[byot(T0: Display + Debug, T1: Clone, R: Serialize)]
@@ -139,3 +140,143 @@ pub fn byot(args: TokenStream, item: TokenStream) -> TokenStream {
 
     expanded.into()
 }
+
+/// Derives `async_openai::types::structured::StructuredOutput`, collecting field descriptions
+/// from `#[describe("...")]` attributes, or else from doc comments.
+///
+/// Field names follow `#[serde(rename = "...")]` and `#[serde(rename_all = "...")]`.
+#[proc_macro_derive(StructuredOutput, attributes(describe))]
+pub fn derive_structured_output(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match field_descriptions(&input) {
+        Ok(descriptions) => {
+            let name = &input.ident;
+            let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+            let (fields, texts): (Vec<_>, Vec<_>) = descriptions.into_iter().unzip();
+
+            quote! {
+                impl #impl_generics ::async_openai::types::structured::StructuredOutput for #name #ty_generics #where_clause {
+                    fn field_descriptions() -> ::std::vec::Vec<(&'static str, &'static str)> {
+                        ::std::vec![#((#fields, #texts)),*]
+                    }
+                }
+            }
+            .into()
+        }
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn field_descriptions(input: &DeriveInput) -> syn::Result<Vec<(String, String)>> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "StructuredOutput can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "StructuredOutput can only be derived for structs",
+            ))
+        }
+    };
+    let rename_all = serde_attribute(&input.attrs, "rename_all")?;
+
+    let mut descriptions = Vec::new();
+    for field in fields {
+        let mut described = None;
+        let mut doc = Vec::new();
+        for attr in &field.attrs {
+            if attr.path().is_ident("describe") {
+                described = Some(attr.parse_args::<LitStr>()?.value());
+            } else if let Meta::NameValue(meta) = &attr.meta {
+                if meta.path.is_ident("doc") {
+                    if let Expr::Lit(expr) = &meta.value {
+                        if let Lit::Str(line) = &expr.lit {
+                            doc.push(line.value().trim().to_string());
+                        }
+                    }
+                }
+            }
+        }
+
+        let description = match described {
+            Some(description) => description,
+            None => doc
+                .into_iter()
+                .filter(|line| !line.is_empty())
+                .collect::<Vec<_>>()
+                .join(" "),
+        };
+        if description.is_empty() {
+            continue;
+        }
+
+        let ident = field.ident.as_ref().unwrap().to_string();
+        let ident = ident.strip_prefix("r#").unwrap_or(&ident).to_string();
+        let name = match serde_attribute(&field.attrs, "rename")? {
+            Some(name) => name,
+            None => match &rename_all {
+                Some(rule) => rename(&ident, rule),
+                None => ident,
+            },
+        };
+        descriptions.push((name, description));
+    }
+    Ok(descriptions)
+}
+
+/// Value of `#[serde(key = "...")]`, ignoring the `serialize`/`deserialize` forms.
+fn serde_attribute(attrs: &[syn::Attribute], key: &str) -> syn::Result<Option<String>> {
+    let mut value = None;
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident(key) {
+                if let Ok(lit) = meta.value().and_then(|v| v.parse::<LitStr>()) {
+                    value = Some(lit.value());
+                }
+            } else if meta.input.peek(syn::Token![=]) {
+                let _ = meta.value()?.parse::<Expr>()?;
+            } else if meta.input.peek(syn::token::Paren) {
+                let _ = meta.input.parse::<proc_macro2::Group>()?;
+            }
+            Ok(())
+        })?;
+    }
+    Ok(value)
+}
+
+/// Apply a serde `rename_all` rule to a snake_case field name.
+fn rename(field: &str, rule: &str) -> String {
+    let words = field.split('_').filter(|word| !word.is_empty());
+    let capitalize = |word: &str| {
+        let mut chars = word.chars();
+        chars
+            .next()
+            .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+            .unwrap_or_default()
+    };
+
+    match rule {
+        "lowercase" => field.to_lowercase(),
+        "UPPERCASE" | "SCREAMING_SNAKE_CASE" => field.to_uppercase(),
+        "PascalCase" => words.map(capitalize).collect(),
+        "camelCase" => {
+            let pascal: String = words.map(capitalize).collect();
+            let mut chars = pascal.chars();
+            chars
+                .next()
+                .map(|first| first.to_lowercase().chain(chars).collect())
+                .unwrap_or_default()
+        }
+        "kebab-case" => field.replace('_', "-"),
+        "SCREAMING-KEBAB-CASE" => field.replace('_', "-").to_uppercase(),
+        _ => field.to_string(),
+    }
+}
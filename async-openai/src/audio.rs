@@ -3,6 +3,8 @@ use bytes::Bytes;
 use crate::{
     config::Config,
     error::OpenAIError,
+    events::ClientEvent,
+    speech_cache::SpeechCacheKey,
     types::{
         CreateSpeechRequest, CreateSpeechResponse, CreateTranscriptionRequest,
         CreateTranscriptionResponseJson, CreateTranscriptionResponseVerboseJson,
@@ -100,11 +102,34 @@ impl<'c, C: Config> Audio<'c, C> {
     }
 
     /// Generates audio from the input text.
+    ///
+    /// With a speech cache, see [Client::with_speech_cache], audio already synthesized for
+    /// the same text, model, voice, speed and format is returned without a request.
     pub async fn speech(
         &self,
         request: CreateSpeechRequest,
     ) -> Result<CreateSpeechResponse, OpenAIError> {
+        let Some(store) = self.client.speech_cache() else {
+            let bytes = self.client.post_raw("/audio/speech", request).await?;
+            return Ok(CreateSpeechResponse { bytes });
+        };
+
+        let key = SpeechCacheKey::new(&request);
+        let events = self.client.events();
+        match store.get(&key).await {
+            Ok(Some(bytes)) => {
+                events.emit(|| ClientEvent::SpeechCacheHit { key });
+                return Ok(CreateSpeechResponse { bytes });
+            }
+            Ok(None) => {}
+            Err(e) => tracing::warn!("speech cache lookup failed: {e}"),
+        }
+        events.emit(|| ClientEvent::SpeechCacheMiss { key: key.clone() });
+
         let bytes = self.client.post_raw("/audio/speech", request).await?;
+        if let Err(e) = store.put(&key, bytes.clone()).await {
+            tracing::warn!("speech cache store failed: {e}");
+        }
 
         Ok(CreateSpeechResponse { bytes })
     }
@@ -0,0 +1,271 @@
+//! Recording of chat completion requests and responses in an append-only JSONL log, for
+//! regulated deployments and for building fine-tuning datasets later on.
+//!
+//! A client with an [AuditRecorder] writes an [AuditRecord] line for every
+//! [crate::Chat::create] and [crate::Chat::create_raw] call, successful or not. Each record
+//! holds the SHA-256 hash of the one before it, so that editing, removing or reordering lines
+//! is detected by [verify].
+//!
+//! ```no_run
+//! use async_openai::{audit_trail::AuditRecorder, Client};
+//!
+//! let recorder = AuditRecorder::open("audit/chat.jsonl")
+//!     .unwrap()
+//!     .redact_field("user")
+//!     .redact_pattern(regex::Regex::new(r"\b\d{3}-\d{2}-\d{4}\b").unwrap());
+//! let client = Client::new().with_audit_recorder(recorder);
+//! ```
+//!
+//! Streams are not recorded. A record that can't be written is logged as an error through
+//! `tracing`, and the call it records still returns its outcome, as the request was sent.
+use std::{
+    fs::OpenOptions,
+    io::{BufRead, BufReader, Write},
+    path::Path,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::error::OpenAIError;
+
+/// Replaces redacted values and parts of strings.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// A line of the audit log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// Position in the log, from 0
+    pub sequence: u64,
+    /// Seconds since the Unix epoch when the exchange ended
+    pub timestamp: u64,
+    /// API path, e.g. `/chat/completions`
+    pub path: String,
+    /// Request, redacted
+    pub request: Value,
+    /// Response, redacted, `None` when the call failed
+    pub response: Option<Value>,
+    /// Error of a failed call
+    pub error: Option<String>,
+    /// Hash of the previous record, empty for the first one
+    pub previous_hash: String,
+    /// Lowercase hex SHA-256 hash of the record with an empty `hash`
+    pub hash: String,
+}
+
+impl AuditRecord {
+    /// The hash the record should have.
+    pub fn compute_hash(&self) -> String {
+        let unhashed = AuditRecord {
+            hash: String::new(),
+            ..self.clone()
+        };
+        let json = serde_json::to_string(&unhashed).unwrap_or_default();
+        format!("{:x}", Sha256::digest(json.as_bytes()))
+    }
+}
+
+struct Chain {
+    writer: Box<dyn Write + Send>,
+    sequence: u64,
+    last_hash: String,
+}
+
+/// Appends [AuditRecord]s to a JSONL log, see [crate::audit_trail].
+pub struct AuditRecorder {
+    chain: Mutex<Chain>,
+    fields: Vec<String>,
+    patterns: Vec<Regex>,
+}
+
+impl std::fmt::Debug for AuditRecorder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AuditRecorder")
+            .field("fields", &self.fields)
+            .field("patterns", &self.patterns)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AuditRecorder {
+    /// Start a new log in `writer`.
+    pub fn new<W: Write + Send + 'static>(writer: W) -> Self {
+        Self::resume(writer, 0, String::new())
+    }
+
+    /// Append to the log at `path`, continuing the hash chain of its records. The file and
+    /// its directory are created when missing.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, OpenAIError> {
+        let path = path.as_ref();
+        let last = match std::fs::File::open(path) {
+            Ok(file) => last_record(BufReader::new(file))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => {
+                return Err(OpenAIError::FileReadError(format!(
+                    "{}: {e}",
+                    path.display()
+                )))
+            }
+        };
+
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| OpenAIError::FileSaveError(format!("{}: {e}", dir.display())))?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| OpenAIError::FileSaveError(format!("{}: {e}", path.display())))?;
+
+        Ok(match last {
+            Some(record) => Self::resume(file, record.sequence + 1, record.hash),
+            None => Self::new(file),
+        })
+    }
+
+    fn resume<W: Write + Send + 'static>(writer: W, sequence: u64, last_hash: String) -> Self {
+        Self {
+            chain: Mutex::new(Chain {
+                writer: Box::new(writer),
+                sequence,
+                last_hash,
+            }),
+            fields: Vec::new(),
+            patterns: Vec::new(),
+        }
+    }
+
+    /// Replace the value of every object field named `name` with [REDACTED], at any depth of
+    /// requests and responses.
+    pub fn redact_field(mut self, name: impl Into<String>) -> Self {
+        self.fields.push(name.into());
+        self
+    }
+
+    /// Replace the matches of `pattern` in every string of requests and responses with
+    /// [REDACTED].
+    pub fn redact_pattern(mut self, pattern: Regex) -> Self {
+        self.patterns.push(pattern);
+        self
+    }
+
+    /// Append the record of an exchange with `path`.
+    pub(crate) fn record(
+        &self,
+        path: &str,
+        request: &Value,
+        result: Result<&Value, &OpenAIError>,
+    ) -> Result<AuditRecord, OpenAIError> {
+        let mut request = request.clone();
+        self.redact(&mut request);
+        let (response, error) = match result {
+            Ok(response) => {
+                let mut response = response.clone();
+                self.redact(&mut response);
+                (Some(response), None)
+            }
+            Err(e) => (None, Some(e.to_string())),
+        };
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+
+        let mut chain = self.chain.lock().unwrap_or_else(|e| e.into_inner());
+        let mut record = AuditRecord {
+            sequence: chain.sequence,
+            timestamp,
+            path: path.to_string(),
+            request,
+            response,
+            error,
+            previous_hash: chain.last_hash.clone(),
+            hash: String::new(),
+        };
+        record.hash = record.compute_hash();
+
+        let save = |e: std::io::Error| OpenAIError::FileSaveError(format!("audit log: {e}"));
+        let mut line = serde_json::to_string(&record)
+            .map_err(|e| OpenAIError::FileSaveError(format!("audit log: {e}")))?;
+        line.push('\n');
+        chain.writer.write_all(line.as_bytes()).map_err(save)?;
+        chain.writer.flush().map_err(save)?;
+
+        chain.sequence += 1;
+        chain.last_hash = record.hash.clone();
+        Ok(record)
+    }
+
+    fn redact(&self, value: &mut Value) {
+        match value {
+            Value::Object(map) => {
+                for (field, value) in map.iter_mut() {
+                    if self.fields.iter().any(|name| name == field) {
+                        *value = Value::String(REDACTED.to_string());
+                    } else {
+                        self.redact(value);
+                    }
+                }
+            }
+            Value::Array(items) => items.iter_mut().for_each(|item| self.redact(item)),
+            Value::String(text) => {
+                for pattern in &self.patterns {
+                    if let std::borrow::Cow::Owned(redacted) = pattern.replace_all(text, REDACTED) {
+                        *text = redacted;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The last record of a log.
+fn last_record(reader: impl BufRead) -> Result<Option<AuditRecord>, OpenAIError> {
+    let mut last = None;
+    for line in reader.lines() {
+        let line = line.map_err(|e| OpenAIError::FileReadError(format!("audit log: {e}")))?;
+        if !line.trim().is_empty() {
+            last = Some(line);
+        }
+    }
+    last.map(|line| serde_json::from_str(&line).map_err(OpenAIError::JSONDeserialize))
+        .transpose()
+}
+
+/// Check the hash chain of a log, returning the number of records. Fails at the first record
+/// that was edited, or that doesn't follow the one before it.
+pub fn verify(reader: impl BufRead) -> Result<u64, OpenAIError> {
+    let mut count = 0;
+    let mut previous_hash = String::new();
+    for line in reader.lines() {
+        let line = line.map_err(|e| OpenAIError::FileReadError(format!("audit log: {e}")))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record: AuditRecord =
+            serde_json::from_str(&line).map_err(OpenAIError::JSONDeserialize)?;
+        let tampered = |reason: &str| {
+            Err(OpenAIError::InvalidArgument(format!(
+                "audit record {}: {reason}",
+                record.sequence
+            )))
+        };
+        if record.sequence != count {
+            return tampered(&format!("expected sequence {count}"));
+        }
+        if record.previous_hash != previous_hash {
+            return tampered("previous hash doesn't match");
+        }
+        if record.hash != record.compute_hash() {
+            return tampered("hash doesn't match its content");
+        }
+        previous_hash = record.hash;
+        count += 1;
+    }
+    Ok(count)
+}
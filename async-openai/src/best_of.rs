@@ -0,0 +1,299 @@
+//! Best-of-N sampling for chat completions: ask for several candidates, score each of them,
+//! and keep the best one.
+//!
+//! Candidates are requested with `n` in a single request, or with as many parallel requests
+//! for providers and models that don't support `n`. They are scored by a function, or by a
+//! grader model asked to rate each candidate.
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), async_openai::error::OpenAIError> {
+//! use async_openai::{
+//!     best_of::BestOf,
+//!     types::{ChatCompletionRequestUserMessage, CreateChatCompletionRequestArgs},
+//!     Client,
+//! };
+//!
+//! let request = CreateChatCompletionRequestArgs::default()
+//!     .model("gpt-4o")
+//!     .messages([ChatCompletionRequestUserMessage::from("Write a haiku about the sea").into()])
+//!     .build()?;
+//! let policy = BestOf::graded(4, "gpt-4o-mini");
+//! let best = Client::new().chat().create_best_of(request, &policy).await?;
+//! println!("{} ({})", best.choice.message.content.unwrap_or_default(), best.score);
+//! # Ok(())
+//! # }
+//! ```
+use std::sync::Arc;
+
+use crate::{
+    config::Config,
+    error::OpenAIError,
+    types::{
+        ChatChoice, ChatCompletionRequestAssistantMessage, ChatCompletionRequestUserMessage,
+        CompletionUsage, CreateChatCompletionRequest, CreateChatCompletionResponse,
+    },
+    Client,
+};
+
+/// Prompt sent to the grader after the conversation and the candidate.
+pub const DEFAULT_GRADER_PROMPT: &str = "Rate the quality of your last answer to the \
+    conversation above from 0 to 10. Reply with the number only.";
+
+/// How candidates are requested.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Sampling {
+    /// A single request with `n` set to the number of candidates.
+    #[default]
+    N,
+    /// One request per candidate, sent concurrently.
+    Parallel,
+}
+
+type ScoreFn = Arc<dyn Fn(&ChatChoice) -> f64 + Send + Sync>;
+
+#[derive(Clone)]
+enum Scorer {
+    Function(ScoreFn),
+    Grader { model: String, prompt: String },
+}
+
+/// Number of candidates, how they are requested and how they are scored.
+#[derive(Clone)]
+pub struct BestOf {
+    candidates: u8,
+    sampling: Sampling,
+    scorer: Scorer,
+}
+
+impl std::fmt::Debug for BestOf {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut debug = f.debug_struct("BestOf");
+        debug
+            .field("candidates", &self.candidates)
+            .field("sampling", &self.sampling);
+        match &self.scorer {
+            Scorer::Function(_) => debug.field("scorer", &"function"),
+            Scorer::Grader { model, .. } => debug.field("grader", model),
+        };
+        debug.finish()
+    }
+}
+
+impl BestOf {
+    /// `candidates` candidates scored by `score`, higher is better.
+    pub fn scored_by<F>(candidates: u8, score: F) -> Self
+    where
+        F: Fn(&ChatChoice) -> f64 + Send + Sync + 'static,
+    {
+        Self {
+            candidates,
+            sampling: Sampling::default(),
+            scorer: Scorer::Function(Arc::new(score)),
+        }
+    }
+
+    /// `candidates` candidates scored by `model`, which is sent the conversation, the
+    /// candidate and [DEFAULT_GRADER_PROMPT].
+    pub fn graded(candidates: u8, model: impl Into<String>) -> Self {
+        Self {
+            candidates,
+            sampling: Sampling::default(),
+            scorer: Scorer::Grader {
+                model: model.into(),
+                prompt: DEFAULT_GRADER_PROMPT.to_string(),
+            },
+        }
+    }
+
+    /// How candidates are requested. Default is [Sampling::N].
+    pub fn sampling(mut self, sampling: Sampling) -> Self {
+        self.sampling = sampling;
+        self
+    }
+
+    /// Prompt asking the grader for a score, used instead of [DEFAULT_GRADER_PROMPT]. The
+    /// first number of the grader's answer is the score. Has no effect with a scoring function.
+    pub fn grader_prompt(mut self, prompt: impl Into<String>) -> Self {
+        if let Scorer::Grader {
+            prompt: current, ..
+        } = &mut self.scorer
+        {
+            *current = prompt.into();
+        }
+        self
+    }
+
+    /// Request the candidates for `request`, score them and return the best one.
+    pub(crate) async fn create<C: Config>(
+        &self,
+        client: &Client<C>,
+        request: CreateChatCompletionRequest,
+    ) -> Result<BestOfResponse, OpenAIError> {
+        if self.candidates == 0 {
+            return Err(OpenAIError::InvalidArgument(
+                "best of: at least one candidate is required".into(),
+            ));
+        }
+        let (choices, usage) = self.sample(client, request.clone()).await?;
+        if choices.is_empty() {
+            return Err(OpenAIError::InvalidArgument(
+                "best of: the response has no choices".into(),
+            ));
+        }
+
+        let scores = match &self.scorer {
+            Scorer::Function(score) => choices.iter().map(|choice| score(choice)).collect(),
+            Scorer::Grader { model, prompt } => {
+                futures::future::try_join_all(
+                    choices
+                        .iter()
+                        .map(|choice| grade(client, &request, choice, model, prompt)),
+                )
+                .await?
+            }
+        };
+
+        let candidates: Vec<Candidate> = choices
+            .into_iter()
+            .zip(scores)
+            .map(|(choice, score)| Candidate { choice, score })
+            .collect();
+        // The first of equally scored candidates wins
+        let best = candidates
+            .iter()
+            .enumerate()
+            .fold(0, |best, (index, candidate)| {
+                if candidate.score > candidates[best].score {
+                    index
+                } else {
+                    best
+                }
+            });
+        let Candidate { choice, score } = candidates[best].clone();
+
+        Ok(BestOfResponse {
+            choice,
+            score,
+            candidates,
+            usage,
+        })
+    }
+
+    async fn sample<C: Config>(
+        &self,
+        client: &Client<C>,
+        mut request: CreateChatCompletionRequest,
+    ) -> Result<(Vec<ChatChoice>, Option<CompletionUsage>), OpenAIError> {
+        match self.sampling {
+            Sampling::N => {
+                request.n = Some(self.candidates);
+                let response = client.chat().create(request).await?;
+                Ok((response.choices, response.usage))
+            }
+            Sampling::Parallel => {
+                request.n = None;
+                let chat = client.chat();
+                let responses: Vec<CreateChatCompletionResponse> = futures::future::try_join_all(
+                    (0..self.candidates).map(|_| chat.create(request.clone())),
+                )
+                .await?;
+
+                let mut choices = Vec::new();
+                let mut usage: Option<CompletionUsage> = None;
+                for response in responses {
+                    choices.extend(response.choices);
+                    if let Some(more) = response.usage {
+                        usage = Some(match usage {
+                            Some(total) => add_usage(total, more),
+                            None => more,
+                        });
+                    }
+                }
+                for (index, choice) in choices.iter_mut().enumerate() {
+                    choice.index = index as u32;
+                }
+                Ok((choices, usage))
+            }
+        }
+    }
+}
+
+fn add_usage(mut total: CompletionUsage, more: CompletionUsage) -> CompletionUsage {
+    total.prompt_tokens += more.prompt_tokens;
+    total.completion_tokens += more.completion_tokens;
+    total.total_tokens += more.total_tokens;
+    total
+}
+
+/// Ask `model` to score `choice` as an answer to `request`.
+async fn grade<C: Config>(
+    client: &Client<C>,
+    request: &CreateChatCompletionRequest,
+    choice: &ChatChoice,
+    model: &str,
+    prompt: &str,
+) -> Result<f64, OpenAIError> {
+    let answer = choice.message.content.clone().unwrap_or_default();
+    let mut messages = request.messages.clone();
+    messages.push(ChatCompletionRequestAssistantMessage::from(answer).into());
+    messages.push(ChatCompletionRequestUserMessage::from(prompt).into());
+    let grading = CreateChatCompletionRequest {
+        model: model.to_string(),
+        messages,
+        ..Default::default()
+    };
+
+    let response = client.chat().create(grading).await?;
+    let reply = response
+        .choices
+        .into_iter()
+        .next()
+        .and_then(|choice| choice.message.content)
+        .unwrap_or_default();
+    parse_score(&reply).ok_or_else(|| {
+        OpenAIError::InvalidArgument(format!("best of: grader replied without a score: {reply}"))
+    })
+}
+
+/// The first number of `reply`.
+fn parse_score(reply: &str) -> Option<f64> {
+    let start = reply.find(|c: char| c.is_ascii_digit())?;
+    let number: String = reply[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '.')
+        .collect();
+    number.trim_end_matches('.').parse().ok()
+}
+
+/// A candidate and its score.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    pub choice: ChatChoice,
+    pub score: f64,
+}
+
+/// The best candidate, along with all of them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BestOfResponse {
+    /// The candidate with the highest score
+    pub choice: ChatChoice,
+    /// Its score
+    pub score: f64,
+    /// Every candidate in the order they were returned, the best one included
+    pub candidates: Vec<Candidate>,
+    /// Tokens used to generate the candidates, not counting the grader
+    pub usage: Option<CompletionUsage>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scores_are_the_first_number_of_the_reply() {
+        assert_eq!(parse_score("8"), Some(8.0));
+        assert_eq!(parse_score("Score: 7.5/10."), Some(7.5));
+        assert_eq!(parse_score("9."), Some(9.0));
+        assert_eq!(parse_score("excellent"), None);
+    }
+}
@@ -0,0 +1,268 @@
+//! A blocking facade over the async [crate::Client], for CLI tools and build scripts.
+//!
+//! Every call drives a current-thread Tokio runtime owned by the [Client] until the
+//! request completes. Streaming endpoints return a [BlockingStream], a plain
+//! [Iterator] over the parsed events.
+//!
+//! Like `reqwest::blocking`, this must not be used from within an async runtime:
+//! calls made on a thread that is already driving a Tokio runtime will panic.
+//!
+//! ```no_run
+//! use async_openai::{blocking::Client, types::CreateChatCompletionRequestArgs};
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let client = Client::new()?;
+//!
+//! let request = CreateChatCompletionRequestArgs::default()
+//!     .model("gpt-4o-mini")
+//!     .messages([async_openai::types::ChatCompletionRequestUserMessage::from("Hello!").into()])
+//!     .build()?;
+//!
+//! for chunk in client.chat().create_stream(request)? {
+//!     for choice in chunk?.choices {
+//!         print!("{}", choice.delta.content.unwrap_or_default());
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use std::{future::Future, pin::Pin, sync::Arc};
+
+use futures::{Stream, StreamExt};
+use tokio::runtime::Runtime;
+
+#[cfg(feature = "chat")]
+use crate::types::{
+    CreateChatCompletionRequest, CreateChatCompletionResponse, CreateChatCompletionStreamResponse,
+};
+#[cfg(feature = "embeddings")]
+use crate::types::{CreateEmbeddingRequest, CreateEmbeddingResponse};
+use crate::{
+    config::{Config, OpenAIConfig},
+    error::OpenAIError,
+    types::{
+        CreateCompletionRequest, CreateCompletionResponse, CreateModerationRequest,
+        CreateModerationResponse, DeleteModelResponse, ListModelResponse, Model,
+    },
+};
+
+/// Blocking counterpart of [crate::Client].
+#[derive(Debug, Clone)]
+pub struct Client<C: Config> {
+    inner: crate::Client<C>,
+    runtime: Arc<Runtime>,
+}
+
+impl Client<OpenAIConfig> {
+    /// Client with default [OpenAIConfig]
+    pub fn new() -> Result<Self, OpenAIError> {
+        Self::from_async(crate::Client::new())
+    }
+}
+
+impl<C: Config> Client<C> {
+    /// Create client with [OpenAIConfig] or [crate::config::AzureConfig]
+    pub fn with_config(config: C) -> Result<Self, OpenAIError> {
+        Self::from_async(crate::Client::with_config(config))
+    }
+
+    /// Wrap an already configured async client, keeping its HTTP client and backoff.
+    pub fn from_async(client: crate::Client<C>) -> Result<Self, OpenAIError> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| OpenAIError::InvalidArgument(format!("failed to start runtime: {e}")))?;
+
+        Ok(Self {
+            inner: client,
+            runtime: Arc::new(runtime),
+        })
+    }
+
+    /// The async client used under the hood, for API groups without a blocking facade.
+    pub fn async_client(&self) -> &crate::Client<C> {
+        &self.inner
+    }
+
+    /// Run any future, such as a call on [Client::async_client], to completion.
+    ///
+    /// ```no_run
+    /// # use async_openai::blocking::Client;
+    /// # let client = Client::new().unwrap();
+    /// let files = client.block_on(client.async_client().files().list(&[("purpose", "batch")]));
+    /// ```
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+
+    // API groups
+
+    /// To call [Models] group related APIs using this client.
+    pub fn models(&self) -> Models<'_, C> {
+        Models { client: self }
+    }
+
+    /// To call [Completions] group related APIs using this client.
+    pub fn completions(&self) -> Completions<'_, C> {
+        Completions { client: self }
+    }
+
+    /// To call [Chat] group related APIs using this client.
+    #[cfg(feature = "chat")]
+    pub fn chat(&self) -> Chat<'_, C> {
+        Chat { client: self }
+    }
+
+    /// To call [Embeddings] group related APIs using this client.
+    #[cfg(feature = "embeddings")]
+    pub fn embeddings(&self) -> Embeddings<'_, C> {
+        Embeddings { client: self }
+    }
+
+    /// To call [Moderations] group related APIs using this client.
+    pub fn moderations(&self) -> Moderations<'_, C> {
+        Moderations { client: self }
+    }
+}
+
+/// Iterator over a streaming response, blocking the current thread for each event.
+pub struct BlockingStream<T> {
+    stream: Pin<Box<dyn Stream<Item = Result<T, OpenAIError>> + Send>>,
+    runtime: Arc<Runtime>,
+}
+
+impl<T> std::fmt::Debug for BlockingStream<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BlockingStream").finish_non_exhaustive()
+    }
+}
+
+impl<T> Iterator for BlockingStream<T> {
+    type Item = Result<T, OpenAIError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.runtime.block_on(self.stream.next())
+    }
+}
+
+/// Blocking counterpart of [crate::Chat].
+#[cfg(feature = "chat")]
+pub struct Chat<'c, C: Config> {
+    client: &'c Client<C>,
+}
+
+#[cfg(feature = "chat")]
+impl<C: Config> Chat<'_, C> {
+    /// See [crate::Chat::create].
+    pub fn create(
+        &self,
+        request: CreateChatCompletionRequest,
+    ) -> Result<CreateChatCompletionResponse, OpenAIError> {
+        self.client
+            .block_on(self.client.inner.chat().create(request))
+    }
+
+    /// See [crate::Chat::create_stream].
+    pub fn create_stream(
+        &self,
+        request: CreateChatCompletionRequest,
+    ) -> Result<BlockingStream<CreateChatCompletionStreamResponse>, OpenAIError> {
+        let stream = self
+            .client
+            .block_on(self.client.inner.chat().create_stream(request))?;
+
+        Ok(BlockingStream {
+            stream,
+            runtime: self.client.runtime.clone(),
+        })
+    }
+}
+
+/// Blocking counterpart of [crate::Completions].
+pub struct Completions<'c, C: Config> {
+    client: &'c Client<C>,
+}
+
+impl<C: Config> Completions<'_, C> {
+    /// See [crate::Completions::create].
+    pub fn create(
+        &self,
+        request: CreateCompletionRequest,
+    ) -> Result<CreateCompletionResponse, OpenAIError> {
+        self.client
+            .block_on(self.client.inner.completions().create(request))
+    }
+
+    /// See [crate::Completions::create_stream].
+    pub fn create_stream(
+        &self,
+        request: CreateCompletionRequest,
+    ) -> Result<BlockingStream<CreateCompletionResponse>, OpenAIError> {
+        let stream = self
+            .client
+            .block_on(self.client.inner.completions().create_stream(request))?;
+
+        Ok(BlockingStream {
+            stream,
+            runtime: self.client.runtime.clone(),
+        })
+    }
+}
+
+/// Blocking counterpart of [crate::Embeddings].
+#[cfg(feature = "embeddings")]
+pub struct Embeddings<'c, C: Config> {
+    client: &'c Client<C>,
+}
+
+#[cfg(feature = "embeddings")]
+impl<C: Config> Embeddings<'_, C> {
+    /// See [crate::Embeddings::create].
+    pub fn create(
+        &self,
+        request: CreateEmbeddingRequest,
+    ) -> Result<CreateEmbeddingResponse, OpenAIError> {
+        self.client
+            .block_on(self.client.inner.embeddings().create(request))
+    }
+}
+
+/// Blocking counterpart of [crate::Models].
+pub struct Models<'c, C: Config> {
+    client: &'c Client<C>,
+}
+
+impl<C: Config> Models<'_, C> {
+    /// See [crate::Models::list].
+    pub fn list(&self) -> Result<ListModelResponse, OpenAIError> {
+        self.client.block_on(self.client.inner.models().list())
+    }
+
+    /// See [crate::Models::retrieve].
+    pub fn retrieve(&self, id: &str) -> Result<Model, OpenAIError> {
+        self.client
+            .block_on(self.client.inner.models().retrieve(id))
+    }
+
+    /// See [crate::Models::delete].
+    pub fn delete(&self, model: &str) -> Result<DeleteModelResponse, OpenAIError> {
+        self.client
+            .block_on(self.client.inner.models().delete(model))
+    }
+}
+
+/// Blocking counterpart of [crate::Moderations].
+pub struct Moderations<'c, C: Config> {
+    client: &'c Client<C>,
+}
+
+impl<C: Config> Moderations<'_, C> {
+    /// See [crate::Moderations::create].
+    pub fn create(
+        &self,
+        request: CreateModerationRequest,
+    ) -> Result<CreateModerationResponse, OpenAIError> {
+        self.client
+            .block_on(self.client.inner.moderations().create(request))
+    }
+}
@@ -0,0 +1,105 @@
+//! Stable identity of requests, for caches and idempotency layers.
+//!
+//! Two requests asking for the same thing get the same [canonical_hash], however they were
+//! built: keys are sorted, `null` fields are dropped as if unset, floats are written in their
+//! shortest form (so `0.7_f32` and `0.7_f64` agree) and the top-level [VOLATILE_FIELDS] that
+//! don't change the answer, such as `user`, are left out.
+//!
+//! ```
+//! use async_openai::{canonical::canonical_hash, types::CreateChatCompletionRequestArgs};
+//!
+//! let request = CreateChatCompletionRequestArgs::default()
+//!     .model("gpt-4o-mini")
+//!     .messages(vec![])
+//!     .temperature(0.7)
+//!     .build()
+//!     .unwrap();
+//! let tagged = CreateChatCompletionRequestArgs::default()
+//!     .model("gpt-4o-mini")
+//!     .messages(vec![])
+//!     .temperature(0.7)
+//!     .user("user-1234")
+//!     .build()
+//!     .unwrap();
+//!
+//! assert_eq!(canonical_hash(&request), canonical_hash(&tagged));
+//! ```
+use serde::Serialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+/// Top-level fields left out of the canonical form: they identify the caller or control
+/// storage and streaming of the response, not the answer.
+pub const VOLATILE_FIELDS: &[&str] = &["user", "metadata", "store", "stream_options"];
+
+/// Canonical JSON of `value`: compact, with sorted keys, without `null` fields and the
+/// top-level [VOLATILE_FIELDS], and with floats in their shortest form.
+///
+/// Values that fail to serialize are written as `null`.
+pub fn canonical_json(value: &impl Serialize) -> String {
+    let mut value = serde_json::to_value(value).unwrap_or_default();
+    if let Value::Object(fields) = &mut value {
+        for field in VOLATILE_FIELDS {
+            fields.remove(*field);
+        }
+    }
+    let mut json = String::new();
+    write(&value, &mut json);
+    json
+}
+
+/// Lowercase hex SHA-256 hash of the [canonical_json] of `value`.
+///
+/// The canonical form is part of the public API and only changes in major releases, so the
+/// hashes can be persisted.
+pub fn canonical_hash(value: &impl Serialize) -> String {
+    format!("{:x}", Sha256::digest(canonical_json(value).as_bytes()))
+}
+
+fn write(value: &Value, json: &mut String) {
+    match value {
+        Value::Object(fields) => {
+            let mut fields: Vec<_> = fields.iter().filter(|(_, v)| !v.is_null()).collect();
+            fields.sort_by_key(|(key, _)| *key);
+            json.push('{');
+            for (index, (key, value)) in fields.into_iter().enumerate() {
+                if index > 0 {
+                    json.push(',');
+                }
+                json.push_str(&Value::String(key.clone()).to_string());
+                json.push(':');
+                write(value, json);
+            }
+            json.push('}');
+        }
+        Value::Array(items) => {
+            json.push('[');
+            for (index, item) in items.iter().enumerate() {
+                if index > 0 {
+                    json.push(',');
+                }
+                write(item, json);
+            }
+            json.push(']');
+        }
+        Value::Number(number) => match number.as_f64() {
+            Some(float) if number.is_f64() => json.push_str(&float_text(float)),
+            _ => json.push_str(&number.to_string()),
+        },
+        value => json.push_str(&value.to_string()),
+    }
+}
+
+/// Integral floats as integers, and floats an `f32` holds exactly as that `f32`, whose
+/// shortest form is the literal it was written as.
+fn float_text(float: f64) -> String {
+    if float.fract() == 0.0 && float.abs() < 1e15 {
+        // Also turns -0.0 into 0
+        return format!("{}", float as i64);
+    }
+    let single = float as f32;
+    if f64::from(single) == float {
+        return single.to_string();
+    }
+    float.to_string()
+}
@@ -0,0 +1,296 @@
+//! Registry of model capabilities used to validate requests client side.
+//!
+//! Validation is opt-in: use [CreateChatCompletionRequestArgs::build_validated] instead of
+//! `build()`, or call [CreateChatCompletionRequest::validate_for_model] on an existing request.
+//! Models not present in the registry are not validated.
+use crate::{
+    error::OpenAIError,
+    types::{
+        ChatCompletionModalities, ChatCompletionRequestMessage,
+        ChatCompletionRequestUserMessageContent, ChatCompletionRequestUserMessageContentPart,
+        CreateChatCompletionRequest, CreateChatCompletionRequestArgs, ResponseFormat,
+    },
+};
+
+/// What a model accepts in a chat completion request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModelCapabilities {
+    /// Sampling parameters: `temperature`, `top_p`, `presence_penalty`, `frequency_penalty`, `logprobs`.
+    pub sampling: bool,
+    /// `tools`, `tool_choice` and `parallel_tool_calls`.
+    pub tools: bool,
+    /// Image content parts in user messages.
+    pub vision: bool,
+    /// Audio content parts and audio output.
+    pub audio: bool,
+    /// `reasoning_effort`.
+    pub reasoning_effort: bool,
+    /// The deprecated `max_tokens`; reasoning models only accept `max_completion_tokens`.
+    pub max_tokens: bool,
+    /// `response_format` of type `json_schema`.
+    pub structured_outputs: bool,
+}
+
+impl ModelCapabilities {
+    pub(crate) const CHAT: Self = Self {
+        sampling: true,
+        tools: true,
+        vision: false,
+        audio: false,
+        reasoning_effort: false,
+        max_tokens: true,
+        structured_outputs: false,
+    };
+
+    pub(crate) const MULTIMODAL: Self = Self {
+        vision: true,
+        structured_outputs: true,
+        ..Self::CHAT
+    };
+
+    pub(crate) const REASONING: Self = Self {
+        sampling: false,
+        tools: true,
+        vision: true,
+        audio: false,
+        reasoning_effort: true,
+        max_tokens: false,
+        structured_outputs: true,
+    };
+}
+
+/// Maps model id prefixes to [ModelCapabilities]; the longest matching prefix wins.
+#[derive(Debug, Clone)]
+pub struct ModelRegistry {
+    entries: Vec<(String, ModelCapabilities)>,
+}
+
+/// The audio previews of gpt-4o, which take audio instead of images.
+const AUDIO_PREVIEW: ModelCapabilities = ModelCapabilities {
+    vision: false,
+    audio: true,
+    structured_outputs: false,
+    ..ModelCapabilities::CHAT
+};
+
+/// gpt-4 with vision, before gpt-4-turbo.
+const VISION_PREVIEW: ModelCapabilities = ModelCapabilities {
+    tools: false,
+    vision: true,
+    ..ModelCapabilities::CHAT
+};
+
+impl Default for ModelRegistry {
+    fn default() -> Self {
+        let entries = [
+            ("gpt-3.5-turbo", ModelCapabilities::CHAT),
+            ("gpt-4", ModelCapabilities::CHAT),
+            ("gpt-4-turbo", ModelCapabilities::MULTIMODAL),
+            // The previews before gpt-4-turbo only read images in their vision variant, which
+            // doesn't support tools
+            ("gpt-4-turbo-preview", ModelCapabilities::CHAT),
+            ("gpt-4-vision-preview", VISION_PREVIEW),
+            ("gpt-4-1106-vision-preview", VISION_PREVIEW),
+            ("gpt-4o", ModelCapabilities::MULTIMODAL),
+            ("gpt-4.1", ModelCapabilities::MULTIMODAL),
+            ("gpt-4.5-preview", ModelCapabilities::MULTIMODAL),
+            ("chatgpt-4o", ModelCapabilities::MULTIMODAL),
+            ("gpt-4o-audio-preview", AUDIO_PREVIEW),
+            ("gpt-4o-mini-audio-preview", AUDIO_PREVIEW),
+            ("o1", ModelCapabilities::REASONING),
+            (
+                "o1-mini",
+                ModelCapabilities {
+                    tools: false,
+                    vision: false,
+                    reasoning_effort: false,
+                    structured_outputs: false,
+                    ..ModelCapabilities::REASONING
+                },
+            ),
+            (
+                "o1-preview",
+                ModelCapabilities {
+                    tools: false,
+                    vision: false,
+                    reasoning_effort: false,
+                    structured_outputs: false,
+                    ..ModelCapabilities::REASONING
+                },
+            ),
+            (
+                "o3-mini",
+                ModelCapabilities {
+                    vision: false,
+                    ..ModelCapabilities::REASONING
+                },
+            ),
+            ("o3", ModelCapabilities::REASONING),
+            ("o4-mini", ModelCapabilities::REASONING),
+            ("deepseek-chat", ModelCapabilities::CHAT),
+            (
+                "deepseek-reasoner",
+                ModelCapabilities {
+                    tools: false,
+                    vision: false,
+                    reasoning_effort: false,
+                    max_tokens: true,
+                    structured_outputs: false,
+                    ..ModelCapabilities::REASONING
+                },
+            ),
+        ];
+
+        Self {
+            entries: entries
+                .into_iter()
+                .map(|(prefix, capabilities)| (prefix.to_string(), capabilities))
+                .collect(),
+        }
+    }
+}
+
+impl ModelRegistry {
+    /// Registry without any entries.
+    pub fn empty() -> Self {
+        Self { entries: vec![] }
+    }
+
+    /// Add or replace the capabilities for models whose id starts with `prefix`.
+    pub fn register<S: Into<String>>(mut self, prefix: S, capabilities: ModelCapabilities) -> Self {
+        let prefix = prefix.into();
+        self.entries.retain(|(p, _)| *p != prefix);
+        self.entries.push((prefix, capabilities));
+        self
+    }
+
+    /// Capabilities of `model`, if it is known to the registry.
+    pub fn lookup(&self, model: &str) -> Option<ModelCapabilities> {
+        self.entries
+            .iter()
+            .filter(|(prefix, _)| model.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, capabilities)| *capabilities)
+    }
+}
+
+impl CreateChatCompletionRequest {
+    /// Check the request against the capabilities of its model, returning every violation
+    /// at once in [OpenAIError::UnsupportedByModel]. Unknown models always pass.
+    pub fn validate_for_model(&self, registry: &ModelRegistry) -> Result<(), OpenAIError> {
+        let Some(capabilities) = registry.lookup(&self.model) else {
+            return Ok(());
+        };
+
+        let mut violations = vec![];
+
+        if !capabilities.sampling {
+            for (name, is_set) in [
+                ("temperature", self.temperature.is_some()),
+                ("top_p", self.top_p.is_some()),
+                ("presence_penalty", self.presence_penalty.is_some()),
+                ("frequency_penalty", self.frequency_penalty.is_some()),
+                ("logprobs", self.logprobs.is_some()),
+                ("top_logprobs", self.top_logprobs.is_some()),
+            ] {
+                if is_set {
+                    violations.push(format!("`{name}` is not supported"));
+                }
+            }
+        }
+
+        if !capabilities.tools {
+            #[allow(deprecated)]
+            let uses_functions = self.functions.is_some() || self.function_call.is_some();
+            if self.tools.is_some() || self.tool_choice.is_some() || uses_functions {
+                violations.push("tools and functions are not supported".to_string());
+            }
+            if self.parallel_tool_calls.is_some() {
+                violations.push("`parallel_tool_calls` is not supported".to_string());
+            }
+        }
+
+        let parts = self
+            .messages
+            .iter()
+            .filter_map(|message| match message {
+                ChatCompletionRequestMessage::User(message) => match &message.content {
+                    ChatCompletionRequestUserMessageContent::Array(parts) => Some(parts),
+                    ChatCompletionRequestUserMessageContent::Text(_) => None,
+                },
+                _ => None,
+            })
+            .flatten();
+
+        let (mut has_image, mut has_audio) = (false, false);
+        for part in parts {
+            match part {
+                ChatCompletionRequestUserMessageContentPart::ImageUrl(_) => has_image = true,
+                ChatCompletionRequestUserMessageContentPart::InputAudio(_) => has_audio = true,
+                ChatCompletionRequestUserMessageContentPart::Text(_) => {}
+            }
+        }
+
+        if has_image && !capabilities.vision {
+            violations.push("image content parts are not supported".to_string());
+        }
+
+        if !capabilities.audio {
+            if has_audio {
+                violations.push("audio content parts are not supported".to_string());
+            }
+            let audio_output = self.audio.is_some()
+                || self.modalities.as_ref().is_some_and(|modalities| {
+                    modalities.contains(&ChatCompletionModalities::Audio)
+                });
+            if audio_output {
+                violations.push("audio output is not supported".to_string());
+            }
+        }
+
+        #[allow(deprecated)]
+        if !capabilities.max_tokens && self.max_tokens.is_some() {
+            violations
+                .push("`max_tokens` is not supported, use `max_completion_tokens`".to_string());
+        }
+
+        if !capabilities.reasoning_effort && self.reasoning_effort.is_some() {
+            violations.push("`reasoning_effort` is not supported".to_string());
+        }
+
+        if !capabilities.structured_outputs
+            && matches!(
+                self.response_format,
+                Some(ResponseFormat::JsonSchema { .. })
+            )
+        {
+            violations.push("`response_format` of type `json_schema` is not supported".to_string());
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(OpenAIError::UnsupportedByModel {
+                model: self.model.clone(),
+                violations,
+            })
+        }
+    }
+}
+
+impl CreateChatCompletionRequestArgs {
+    /// Build the request and validate it against the default [ModelRegistry].
+    pub fn build_validated(&self) -> Result<CreateChatCompletionRequest, OpenAIError> {
+        self.build_validated_with(&ModelRegistry::default())
+    }
+
+    /// Build the request and validate it against `registry`.
+    pub fn build_validated_with(
+        &self,
+        registry: &ModelRegistry,
+    ) -> Result<CreateChatCompletionRequest, OpenAIError> {
+        let request = self.build()?;
+        request.validate_for_model(registry)?;
+        Ok(request)
+    }
+}
@@ -1,9 +1,26 @@
+use std::collections::VecDeque;
+
+use futures::StreamExt;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
 use crate::{
+    best_of::{BestOf, BestOfResponse},
     config::Config,
+    context_length::ContextLengthPolicy,
     error::OpenAIError,
+    events::ClientEvent,
+    flex::FlexPolicy,
+    response_cache::ResponseCache,
+    structured::Generator,
     types::{
-        ChatCompletionResponseStream, CreateChatCompletionRequest, CreateChatCompletionResponse,
+        structured::{Response, Structured},
+        ChatCompletionDeleted, ChatCompletionList, ChatCompletionMessageList,
+        ChatCompletionRequestSystemMessage, ChatCompletionResponseStream,
+        CreateChatCompletionRequest, CreateChatCompletionResponse,
+        CreateChatCompletionStreamResponse, RawResponseStream, UpdateChatCompletionRequest,
     },
+    util::prepare_raw_request,
     Client,
 };
 
@@ -51,8 +68,108 @@ impl<'c, C: Config> Chat<'c, C> {
                     "When stream is true, use Chat::create_stream".into(),
                 ));
             }
+            if let Some(tool_choice) = &request.tool_choice {
+                tool_choice.check_against(request.tools.as_deref())?;
+            }
+        }
+        self.client.scan_input(&request)?;
+        self.client.post_audited("/chat/completions", request).await
+    }
+
+    /// Same as [Chat::create], but when the conversation does not fit in the model's context
+    /// window the oldest turns are trimmed (or summarized) according to `policy` and the
+    /// request is retried.
+    pub async fn create_with_context_policy(
+        &self,
+        request: CreateChatCompletionRequest,
+        policy: &ContextLengthPolicy,
+    ) -> Result<CreateChatCompletionResponse, OpenAIError> {
+        policy.create(self.client, request).await
+    }
+
+    /// Same as [Chat::create], but when the flex tier has no capacity the request is retried
+    /// with the longer backoff of `policy`, and optionally sent to the default tier once the
+    /// retries are used up.
+    pub async fn create_with_flex_policy(
+        &self,
+        request: CreateChatCompletionRequest,
+        policy: &FlexPolicy,
+    ) -> Result<CreateChatCompletionResponse, OpenAIError> {
+        policy.create(self.client, request).await
+    }
+
+    /// Requests several candidates for `request` and returns the one scored highest by
+    /// `policy`, along with the scores of all of them.
+    pub async fn create_best_of(
+        &self,
+        request: CreateChatCompletionRequest,
+        policy: &BestOf,
+    ) -> Result<BestOfResponse, OpenAIError> {
+        policy.create(self.client, request).await
+    }
+
+    /// Same as [Chat::create], with the instruction of `generator` appended to the
+    /// conversation as a system message, and the first choice parsed into `T`.
+    ///
+    /// A response that can't be parsed, or a refusal, is returned as
+    /// [OpenAIError::StructuredOutput].
+    pub async fn create_structured<T>(
+        &self,
+        mut request: CreateChatCompletionRequest,
+        generator: &Generator<T>,
+    ) -> Result<Response<T>, OpenAIError>
+    where
+        T: Structured + for<'de> Deserialize<'de> + JsonSchema,
+    {
+        request.messages.push(
+            ChatCompletionRequestSystemMessage::from(generator.build_instruction_text()).into(),
+        );
+
+        let response = self.create(request).await?;
+        generator
+            .parse_completion(&response)
+            .map_err(|e| OpenAIError::StructuredOutput(Box::new(e)))
+    }
+
+    /// Same as [Chat::create_structured], with the completions kept in `cache`: a request
+    /// identical to one already answered, same model, instruction and input, is parsed from
+    /// the cached completion without calling the API. Only completions that parse are
+    /// cached, and failures of the cache are logged and treated as misses.
+    ///
+    /// Requests are keyed on their [crate::canonical::canonical_hash], see
+    /// [crate::response_cache].
+    pub async fn create_structured_cached<T>(
+        &self,
+        mut request: CreateChatCompletionRequest,
+        generator: &Generator<T>,
+        cache: &dyn ResponseCache,
+    ) -> Result<Response<T>, OpenAIError>
+    where
+        T: Structured + for<'de> Deserialize<'de> + JsonSchema,
+    {
+        request.messages.push(
+            ChatCompletionRequestSystemMessage::from(generator.build_instruction_text()).into(),
+        );
+
+        let key = crate::canonical::canonical_hash(&request);
+        match cache.get(&key).await {
+            Ok(Some(response)) => {
+                if let Ok(parsed) = generator.parse_completion(&response) {
+                    return Ok(parsed);
+                }
+            }
+            Ok(None) => {}
+            Err(e) => tracing::warn!("structured output cache lookup failed: {e}"),
         }
-        self.client.post("/chat/completions", request).await
+
+        let response = self.create(request).await?;
+        let parsed = generator
+            .parse_completion(&response)
+            .map_err(|e| OpenAIError::StructuredOutput(Box::new(e)))?;
+        if let Err(e) = cache.put(&key, &response).await {
+            tracing::warn!("structured output cache store failed: {e}");
+        }
+        Ok(parsed)
     }
 
     /// Creates a completion for the chat message
@@ -81,8 +198,230 @@ impl<'c, C: Config> Chat<'c, C> {
                 ));
             }
 
+            if let Some(tool_choice) = &request.tool_choice {
+                tool_choice.check_against(request.tools.as_deref())?;
+            }
             request.stream = Some(true);
         }
+        self.client.scan_input(&request)?;
         Ok(self.client.post_stream("/chat/completions", request).await)
     }
+
+    /// Same as [Chat::create_stream], for turns of a tool calling loop: when the stream
+    /// drops before the turn produced any text, e.g. in the middle of the arguments of a
+    /// tool call, the turn is requested again, at most `max_retries` times. This is safe
+    /// because no tool can have run on an incomplete turn. Each retry emits
+    /// [ClientEvent::StreamRetried].
+    ///
+    /// Chunks are held back until a chunk with text or a refusal arrives, so tool calls are
+    /// yielded all at once when the turn is complete. Once text has been yielded, errors
+    /// are passed on as with [Chat::create_stream]. Only dropped connections are retried,
+    /// not errors reported by the API.
+    pub async fn create_stream_with_tool_retry(
+        &self,
+        request: CreateChatCompletionRequest,
+        max_retries: u32,
+    ) -> Result<ChatCompletionResponseStream, OpenAIError>
+    where
+        C: Send + Sync + 'static,
+    {
+        let stream = self.create_stream(request.clone()).await?;
+        let turn = ToolTurn {
+            client: self.client.clone(),
+            request,
+            stream,
+            retries: 0,
+            max_retries,
+            held: Vec::new(),
+            ready: VecDeque::new(),
+            committed: false,
+            finished: false,
+        };
+        Ok(Box::pin(futures::stream::unfold(turn, ToolTurn::next)))
+    }
+
+    /// Same as [Chat::create], with the request and response as untyped JSON, so that
+    /// parameters the typed request doesn't know about yet can be sent. Authentication,
+    /// retries, input scanners and error parsing apply as usual.
+    pub async fn create_raw(
+        &self,
+        mut request: serde_json::Value,
+    ) -> Result<serde_json::Value, OpenAIError> {
+        prepare_raw_request(&mut request, Some((false, "Chat::create_stream_raw")))?;
+        self.client.scan_input(&request)?;
+        self.client.post_audited("/chat/completions", request).await
+    }
+
+    /// Same as [Chat::create_stream], with the request and the chunks as untyped JSON.
+    /// `stream` is set to `true` when absent.
+    pub async fn create_stream_raw(
+        &self,
+        mut request: serde_json::Value,
+    ) -> Result<RawResponseStream, OpenAIError> {
+        prepare_raw_request(&mut request, Some((true, "Chat::create_raw")))?;
+        self.client.scan_input(&request)?;
+        Ok(self.client.post_stream("/chat/completions", request).await)
+    }
+
+    /// List stored chat completions. Only completions created with `store: true` are
+    /// returned.
+    #[crate::byot(T0 = serde::Serialize, R = serde::de::DeserializeOwned)]
+    pub async fn list<Q>(&self, query: &Q) -> Result<ChatCompletionList, OpenAIError>
+    where
+        Q: Serialize + ?Sized,
+    {
+        self.client
+            .get_with_query("/chat/completions", &query)
+            .await
+    }
+
+    /// Get a stored chat completion.
+    #[crate::byot(T0 = std::fmt::Display, R = serde::de::DeserializeOwned)]
+    pub async fn retrieve(
+        &self,
+        completion_id: &str,
+    ) -> Result<CreateChatCompletionResponse, OpenAIError> {
+        self.client
+            .get(&format!("/chat/completions/{completion_id}"))
+            .await
+    }
+
+    /// List the messages of a stored chat completion.
+    #[crate::byot(T0 = std::fmt::Display, T1 = serde::Serialize, R = serde::de::DeserializeOwned)]
+    pub async fn messages<Q>(
+        &self,
+        completion_id: &str,
+        query: &Q,
+    ) -> Result<ChatCompletionMessageList, OpenAIError>
+    where
+        Q: Serialize + ?Sized,
+    {
+        self.client
+            .get_with_query(
+                &format!("/chat/completions/{completion_id}/messages"),
+                &query,
+            )
+            .await
+    }
+
+    /// Modify a stored chat completion. Only the metadata can be updated.
+    #[crate::byot(T0 = std::fmt::Display, T1 = serde::Serialize, R = serde::de::DeserializeOwned)]
+    pub async fn update(
+        &self,
+        completion_id: &str,
+        request: UpdateChatCompletionRequest,
+    ) -> Result<CreateChatCompletionResponse, OpenAIError> {
+        self.client
+            .post(&format!("/chat/completions/{completion_id}"), request)
+            .await
+    }
+
+    /// Delete a stored chat completion.
+    #[crate::byot(T0 = std::fmt::Display, R = serde::de::DeserializeOwned)]
+    pub async fn delete(&self, completion_id: &str) -> Result<ChatCompletionDeleted, OpenAIError> {
+        self.client
+            .delete(&format!("/chat/completions/{completion_id}"))
+            .await
+    }
+}
+
+/// State of [Chat::create_stream_with_tool_retry].
+struct ToolTurn<C: Config> {
+    client: Client<C>,
+    request: CreateChatCompletionRequest,
+    stream: ChatCompletionResponseStream,
+    retries: u32,
+    max_retries: u32,
+    /// Chunks received before any text
+    held: Vec<CreateChatCompletionStreamResponse>,
+    /// Items to yield
+    ready: VecDeque<Result<CreateChatCompletionStreamResponse, OpenAIError>>,
+    /// Whether text was received, after which the turn is not retried
+    committed: bool,
+    /// Whether the turn ends once `ready` is empty
+    finished: bool,
+}
+
+impl<C: Config + Send + Sync + 'static> ToolTurn<C> {
+    async fn next(
+        mut self,
+    ) -> Option<(
+        Result<CreateChatCompletionStreamResponse, OpenAIError>,
+        Self,
+    )> {
+        loop {
+            if let Some(item) = self.ready.pop_front() {
+                return Some((item, self));
+            }
+            if self.finished {
+                return None;
+            }
+
+            match self.stream.next().await {
+                Some(Ok(chunk)) if self.committed => return Some((Ok(chunk), self)),
+                Some(Ok(chunk)) => {
+                    let has_text = chunk.choices.iter().any(|choice| {
+                        let delta = &choice.delta;
+                        delta.content.as_deref().is_some_and(|c| !c.is_empty())
+                            || delta.refusal.as_deref().is_some_and(|r| !r.is_empty())
+                    });
+                    self.held.push(chunk);
+                    if has_text {
+                        self.commit();
+                    }
+                }
+                Some(Err(error))
+                    if !self.committed
+                        && self.retries < self.max_retries
+                        && is_disconnect(&error) =>
+                {
+                    self.retries += 1;
+                    tracing::warn!(
+                        "stream of a tool turn dropped, retry {}: {error}",
+                        self.retries
+                    );
+                    let retry = self.retries;
+                    self.client.events().emit(|| ClientEvent::StreamRetried {
+                        path: "/chat/completions".to_string(),
+                        retry,
+                        error: error.to_string(),
+                    });
+                    self.held.clear();
+                    match self.client.chat().create_stream(self.request.clone()).await {
+                        Ok(stream) => self.stream = stream,
+                        Err(error) => {
+                            self.committed = true;
+                            return Some((Err(error), self));
+                        }
+                    }
+                }
+                Some(Err(error)) if self.committed => return Some((Err(error), self)),
+                Some(Err(error)) => {
+                    // Out of retries, or not a dropped connection: end the turn rather
+                    // than let the stream reconnect
+                    self.commit();
+                    self.ready.push_back(Err(error));
+                    self.finished = true;
+                }
+                None => {
+                    self.commit();
+                    self.finished = true;
+                }
+            }
+        }
+    }
+
+    /// Yield the held chunks, and every chunk from now on as it arrives.
+    fn commit(&mut self) {
+        self.committed = true;
+        self.ready.extend(self.held.drain(..).map(Ok));
+    }
+}
+
+/// Whether `error` is a dropped connection rather than an error reported by the API.
+fn is_disconnect(error: &OpenAIError) -> bool {
+    matches!(
+        error,
+        OpenAIError::StreamDisconnected(_) | OpenAIError::Reqwest(_)
+    )
 }
@@ -1,4 +1,4 @@
-use std::pin::Pin;
+use std::{future::Future, pin::Pin, sync::Arc, time::Duration};
 
 use bytes::Bytes;
 use futures::{stream::StreamExt, Stream};
@@ -6,16 +6,36 @@ use reqwest::multipart::Form;
 use reqwest_eventsource::{Event, EventSource, RequestBuilderExt};
 use serde::{de::DeserializeOwned, Serialize};
 
+#[cfg(feature = "embeddings")]
+use crate::Embeddings;
+#[cfg(feature = "images")]
+use crate::Images;
+#[cfg(feature = "chat")]
+use crate::{audit_trail::AuditRecorder, Chat};
 use crate::{
     config::{Config, OpenAIConfig},
-    error::{map_deserialization_error, OpenAIError, WrappedError},
+    error::{
+        map_api_error, map_deserialization_error, map_event_source_error, map_stream_error_event,
+        OpenAIError, WrappedError,
+    },
+    events::{ClientEvent, Events, StreamEvents},
     file::Files,
-    image::Images,
     moderation::Moderations,
+    scanning::{InputScanner, InputScanners},
+    shutdown::{InFlight, Lifecycle, ShutdownOutcome},
+    tls::TlsConfig,
     traits::AsyncTryFrom,
-    Assistants, Audio, AuditLogs, Batches, Chat, Completions, Embeddings, FineTuning, Invites,
-    Models, Projects, Threads, Uploads, Users, VectorStores,
+    Batches, Completions, FineTuning, Models, Uploads,
 };
+#[cfg(feature = "audio")]
+use crate::{
+    speech_cache::{SpeechCache, SpeechStore},
+    Audio,
+};
+#[cfg(feature = "assistants")]
+use crate::{Assistants, Threads, VectorStores};
+#[cfg(feature = "admin")]
+use crate::{AuditLogs, Invites, Projects, Users};
 
 #[derive(Debug, Clone, Default)]
 /// Client is a container for config, backoff and http_client
@@ -24,6 +44,15 @@ pub struct Client<C: Config> {
     http_client: reqwest::Client,
     config: C,
     backoff: backoff::ExponentialBackoff,
+    lifecycle: Arc<Lifecycle>,
+    events: Arc<Events>,
+    input_scanners: InputScanners,
+    #[cfg(feature = "audio")]
+    speech_cache: SpeechCache,
+    #[cfg(feature = "chat")]
+    audit_recorder: Option<Arc<AuditRecorder>>,
+    #[cfg(feature = "http3")]
+    http3: Arc<crate::http3::Http3Transport>,
 }
 
 impl Client<OpenAIConfig> {
@@ -44,6 +73,15 @@ impl<C: Config> Client<C> {
             http_client,
             config,
             backoff,
+            lifecycle: Default::default(),
+            events: Default::default(),
+            input_scanners: Default::default(),
+            #[cfg(feature = "audio")]
+            speech_cache: Default::default(),
+            #[cfg(feature = "chat")]
+            audit_recorder: None,
+            #[cfg(feature = "http3")]
+            http3: Default::default(),
         }
     }
 
@@ -53,6 +91,15 @@ impl<C: Config> Client<C> {
             http_client: reqwest::Client::new(),
             config,
             backoff: Default::default(),
+            lifecycle: Default::default(),
+            events: Default::default(),
+            input_scanners: Default::default(),
+            #[cfg(feature = "audio")]
+            speech_cache: Default::default(),
+            #[cfg(feature = "chat")]
+            audit_recorder: None,
+            #[cfg(feature = "http3")]
+            http3: Default::default(),
         }
     }
 
@@ -64,12 +111,86 @@ impl<C: Config> Client<C> {
         self
     }
 
+    /// Replace the HTTP client with one built from `tls`: custom CA bundles, certificate
+    /// pinning or an explicit TLS backend. Use [crate::tls::TlsConfig::apply] instead to
+    /// combine TLS settings with other [reqwest::ClientBuilder] options.
+    pub fn with_tls(mut self, tls: &TlsConfig) -> Result<Self, OpenAIError> {
+        self.http_client = tls.build_http_client()?;
+        Ok(self)
+    }
+
     /// Exponential backoff for retrying [rate limited](https://platform.openai.com/docs/guides/rate-limits) requests.
     pub fn with_backoff(mut self, backoff: backoff::ExponentialBackoff) -> Self {
         self.backoff = backoff;
         self
     }
 
+    /// Scan user-supplied content of chat and completion requests with `scanner` before
+    /// they are sent, see [crate::scanning]. Scanners run in the order they are added.
+    pub fn with_input_scanner<S: InputScanner + 'static>(mut self, scanner: S) -> Self {
+        self.input_scanners.push(Arc::new(scanner));
+        self
+    }
+
+    /// Look up speech requests in `store` before sending them, and store the audio of the
+    /// requests sent, see [crate::speech_cache].
+    #[cfg(feature = "audio")]
+    pub fn with_speech_cache<S: SpeechStore + 'static>(mut self, store: S) -> Self {
+        self.speech_cache = SpeechCache::new(Arc::new(store));
+        self
+    }
+
+    /// Record chat completion requests and responses with `recorder`, see
+    /// [crate::audit_trail].
+    #[cfg(feature = "chat")]
+    pub fn with_audit_recorder(mut self, recorder: AuditRecorder) -> Self {
+        self.audit_recorder = Some(Arc::new(recorder));
+        self
+    }
+
+    /// Run `hook` at the end of [Client::shutdown], after in-flight requests are done.
+    /// Use it to flush usage accounting or metrics.
+    pub fn with_shutdown_hook<F, Fut>(self, hook: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.lifecycle.add_hook(Arc::new(move || Box::pin(hook())));
+        self
+    }
+
+    /// Emit [ClientEvent::BudgetThresholdCrossed] when the tokens reported in the usage of
+    /// responses, summed over this client and its clones, reach each of `thresholds`.
+    /// Only non-streaming responses are counted.
+    pub fn with_token_budget_thresholds(self, thresholds: impl IntoIterator<Item = u64>) -> Self {
+        self.events.set_thresholds(thresholds);
+        self
+    }
+
+    /// Lifecycle events of this client and all of its clones, see [crate::events].
+    pub fn events(&self) -> &Events {
+        &self.events
+    }
+
+    #[cfg(feature = "audio")]
+    pub(crate) fn speech_cache(&self) -> Option<&dyn SpeechStore> {
+        self.speech_cache.store()
+    }
+
+    /// Gracefully shut down this client and all of its clones.
+    ///
+    /// New requests fail immediately with [OpenAIError::ClientShutdown]. In-flight requests
+    /// and streams are given until `deadline` to finish, after which they are aborted with
+    /// the same error. Shutdown hooks run last.
+    pub async fn shutdown(&self, deadline: Duration) -> ShutdownOutcome {
+        self.lifecycle.shutdown(deadline).await
+    }
+
+    /// Whether [Client::shutdown] has been called on this client or one of its clones.
+    pub fn is_shut_down(&self) -> bool {
+        self.lifecycle.is_closed()
+    }
+
     // API groups
 
     /// To call [Models] group related APIs using this client.
@@ -83,11 +204,13 @@ impl<C: Config> Client<C> {
     }
 
     /// To call [Chat] group related APIs using this client.
+    #[cfg(feature = "chat")]
     pub fn chat(&self) -> Chat<C> {
         Chat::new(self)
     }
 
     /// To call [Images] group related APIs using this client.
+    #[cfg(feature = "images")]
     pub fn images(&self) -> Images<C> {
         Images::new(self)
     }
@@ -113,26 +236,31 @@ impl<C: Config> Client<C> {
     }
 
     /// To call [Embeddings] group related APIs using this client.
+    #[cfg(feature = "embeddings")]
     pub fn embeddings(&self) -> Embeddings<C> {
         Embeddings::new(self)
     }
 
     /// To call [Audio] group related APIs using this client.
+    #[cfg(feature = "audio")]
     pub fn audio(&self) -> Audio<C> {
         Audio::new(self)
     }
 
     /// To call [Assistants] group related APIs using this client.
+    #[cfg(feature = "assistants")]
     pub fn assistants(&self) -> Assistants<C> {
         Assistants::new(self)
     }
 
     /// To call [Threads] group related APIs using this client.
+    #[cfg(feature = "assistants")]
     pub fn threads(&self) -> Threads<C> {
         Threads::new(self)
     }
 
     /// To call [VectorStores] group related APIs using this client.
+    #[cfg(feature = "assistants")]
     pub fn vector_stores(&self) -> VectorStores<C> {
         VectorStores::new(self)
     }
@@ -143,21 +271,25 @@ impl<C: Config> Client<C> {
     }
 
     /// To call [AuditLogs] group related APIs using this client.
+    #[cfg(feature = "admin")]
     pub fn audit_logs(&self) -> AuditLogs<C> {
         AuditLogs::new(self)
     }
 
     /// To call [Invites] group related APIs using this client.
+    #[cfg(feature = "admin")]
     pub fn invites(&self) -> Invites<C> {
         Invites::new(self)
     }
 
     /// To call [Users] group related APIs using this client.
+    #[cfg(feature = "admin")]
     pub fn users(&self) -> Users<C> {
         Users::new(self)
     }
 
     /// To call [Projects] group related APIs using this client.
+    #[cfg(feature = "admin")]
     pub fn projects(&self) -> Projects<C> {
         Projects::new(self)
     }
@@ -166,6 +298,11 @@ impl<C: Config> Client<C> {
         &self.config
     }
 
+    /// Run the input scanners on a chat or completion request.
+    pub(crate) fn scan_input<I: Serialize>(&self, request: &I) -> Result<(), OpenAIError> {
+        self.input_scanners.check(request).map(|_| ())
+    }
+
     /// Make a GET request to {path} and deserialize the response body
     pub(crate) async fn get<O>(&self, path: &str) -> Result<O, OpenAIError>
     where
@@ -234,6 +371,7 @@ impl<C: Config> Client<C> {
     }
 
     /// Make a POST request to {path} and return the response body
+    #[cfg(feature = "audio")]
     pub(crate) async fn post_raw<I>(&self, path: &str, request: I) -> Result<Bytes, OpenAIError>
     where
         I: Serialize,
@@ -270,7 +408,30 @@ impl<C: Config> Client<C> {
         self.execute(request_maker).await
     }
 
+    /// Same as [Client::post], recording the request and the response or error with the
+    /// audit recorder, if any.
+    #[cfg(feature = "chat")]
+    pub(crate) async fn post_audited<I, O>(&self, path: &str, request: I) -> Result<O, OpenAIError>
+    where
+        I: Serialize,
+        O: DeserializeOwned,
+    {
+        let Some(recorder) = &self.audit_recorder else {
+            return self.post(path, request).await;
+        };
+
+        let request_json = serde_json::to_value(&request)
+            .map_err(|e| OpenAIError::InvalidArgument(format!("invalid request: {e}")))?;
+        let result: Result<serde_json::Value, OpenAIError> = self.post(path, request).await;
+        // The request was sent, and a completion paid for, so the outcome is returned anyway
+        if let Err(e) = recorder.record(path, &request_json, result.as_ref()) {
+            tracing::error!("audit record of {path} not written: {e}");
+        }
+        serde_json::from_value(result?).map_err(OpenAIError::JSONDeserialize)
+    }
+
     /// POST a form at {path} and return the response body
+    #[cfg(feature = "audio")]
     pub(crate) async fn post_form_raw<F>(&self, path: &str, form: F) -> Result<Bytes, OpenAIError>
     where
         Form: AsyncTryFrom<F, Error = OpenAIError>,
@@ -309,6 +470,57 @@ impl<C: Config> Client<C> {
         self.execute(request_maker).await
     }
 
+    /// Send a single request, over HTTP/3 when the origin supports it and the `http3` feature
+    /// is enabled, falling back to the default transport if no HTTP/3 connection could be
+    /// established.
+    async fn send<M, Fut>(
+        &self,
+        request_maker: &M,
+        on_request: &(dyn Fn(&reqwest::Request) + Sync),
+    ) -> Result<reqwest::Response, OpenAIError>
+    where
+        M: Fn() -> Fut,
+        Fut: core::future::Future<Output = Result<reqwest::Request, OpenAIError>>,
+    {
+        #[allow(unused_mut)]
+        let mut request = request_maker().await?;
+        on_request(&request);
+
+        #[cfg(feature = "http3")]
+        {
+            let url = request.url().clone();
+            if let Some(version) = self.http3.version_for(&url, false) {
+                *request.version_mut() = version;
+                match self.http_client.execute(request).await {
+                    Ok(response) => {
+                        self.http3
+                            .observe(&url, response.version(), response.headers());
+                        return Ok(response);
+                    }
+                    Err(e) if crate::http3::failed_to_connect(&e) => {
+                        tracing::debug!("HTTP/3 connection failed: {e}");
+                        self.http3.mark_broken(&url);
+                        // The request body may not be reusable, so make it again
+                        request = request_maker().await?;
+                    }
+                    Err(e) => return Err(OpenAIError::Reqwest(e)),
+                }
+            }
+        }
+
+        let response = self
+            .http_client
+            .execute(request)
+            .await
+            .map_err(OpenAIError::Reqwest)?;
+
+        #[cfg(feature = "http3")]
+        self.http3
+            .observe(response.url(), response.version(), response.headers());
+
+        Ok(response)
+    }
+
     /// Execute a HTTP request and retry on rate limit
     ///
     /// request_maker serves one purpose: to be able to create request again
@@ -319,14 +531,36 @@ impl<C: Config> Client<C> {
         M: Fn() -> Fut,
         Fut: core::future::Future<Output = Result<reqwest::Request, OpenAIError>>,
     {
-        let client = self.http_client.clone();
+        let in_flight = self.lifecycle.enter()?;
+
+        let events = &self.events;
+        let id = events.next_id();
+        let started = std::time::Instant::now();
+        let path = std::sync::OnceLock::new();
+        let on_request = |request: &reqwest::Request| {
+            if path.set(request.url().path().to_string()).is_ok() {
+                events.emit(|| ClientEvent::RequestStarted {
+                    id,
+                    method: request.method().to_string(),
+                    path: request.url().path().to_string(),
+                });
+            }
+        };
+        let mut retries = 0;
+        let on_retry = |error: OpenAIError, delay: Duration| {
+            retries += 1;
+            events.emit(|| ClientEvent::RetryScheduled {
+                id,
+                retry: retries,
+                delay,
+                error: error.to_string(),
+            });
+        };
 
-        backoff::future::retry(self.backoff.clone(), || async {
-            let request = request_maker().await.map_err(backoff::Error::Permanent)?;
-            let response = client
-                .execute(request)
+        let operation = || async {
+            let response = self
+                .send(&request_maker, &on_request)
                 .await
-                .map_err(OpenAIError::Reqwest)
                 .map_err(backoff::Error::Permanent)?;
 
             let status = response.status();
@@ -341,28 +575,51 @@ impl<C: Config> Client<C> {
                 let wrapped_error: WrappedError = serde_json::from_slice(bytes.as_ref())
                     .map_err(|e| map_deserialization_error(e, bytes.as_ref()))
                     .map_err(backoff::Error::Permanent)?;
+                let details = serde_json::from_slice::<serde_json::Value>(bytes.as_ref())
+                    .ok()
+                    .and_then(|mut body| body.get_mut("error").map(serde_json::Value::take));
+                let message = wrapped_error.error.message.clone();
+                let error = map_api_error(wrapped_error.error, details.as_ref());
 
                 if status.as_u16() == 429
                     // API returns 429 also when:
                     // "You exceeded your current quota, please check your plan and billing details."
-                    && wrapped_error.error.r#type != Some("insufficient_quota".to_string())
+                    && !error.is_permanent()
+                    // or when the flex tier has no capacity, which takes much longer than
+                    // a rate limit to recover from, see crate::flex::FlexPolicy
+                    && !matches!(error, OpenAIError::ResourceUnavailable(_))
                 {
                     // Rate limited retry...
-                    tracing::warn!("Rate limited: {}", wrapped_error.error.message);
+                    tracing::warn!("Rate limited: {message}");
+                    events.emit(|| ClientEvent::RateLimited { id, message });
                     return Err(backoff::Error::Transient {
-                        err: OpenAIError::ApiError(wrapped_error.error),
+                        err: error,
                         retry_after: None,
                     });
                 } else {
-                    return Err(backoff::Error::Permanent(OpenAIError::ApiError(
-                        wrapped_error.error,
-                    )));
+                    return Err(backoff::Error::Permanent(error));
                 }
             }
 
             Ok(bytes)
-        })
-        .await
+        };
+        let request = backoff::future::retry_notify(self.backoff.clone(), operation, on_retry);
+
+        let result = tokio::select! {
+            result = request => result,
+            _ = in_flight.aborted() => Err(OpenAIError::ClientShutdown),
+        };
+
+        if let Ok(bytes) = &result {
+            events.record_usage(bytes);
+        }
+        events.emit(|| ClientEvent::RequestFinished {
+            id,
+            path: path.get().cloned().unwrap_or_default(),
+            duration: started.elapsed(),
+            error: result.as_ref().err().map(ToString::to_string),
+        });
+        result
     }
 
     /// Execute a HTTP request and retry on rate limit
@@ -384,6 +641,23 @@ impl<C: Config> Client<C> {
         Ok(response)
     }
 
+    /// Streams use HTTP/3 only for origins where it already worked for a regular request.
+    #[allow(unused_variables)]
+    fn streaming_version(
+        &self,
+        builder: reqwest::RequestBuilder,
+        path: &str,
+    ) -> reqwest::RequestBuilder {
+        #[cfg(feature = "http3")]
+        if let Some(version) = reqwest::Url::parse(&self.config.url(path))
+            .ok()
+            .and_then(|url| self.http3.version_for(&url, true))
+        {
+            return builder.version(version);
+        }
+        builder
+    }
+
     /// Make HTTP POST request to receive SSE
     pub(crate) async fn post_stream<I, O>(
         &self,
@@ -394,18 +668,32 @@ impl<C: Config> Client<C> {
         I: Serialize,
         O: DeserializeOwned + std::marker::Send + 'static,
     {
+        let in_flight = match self.lifecycle.enter() {
+            Ok(in_flight) => in_flight,
+            Err(e) => return Box::pin(futures::stream::once(async { Err(e) })),
+        };
+
         let event_source = self
             .http_client
             .post(self.config.url(path))
             .query(&self.config.query())
             .headers(self.config.headers())
-            .json(&request)
+            .json(&request);
+
+        let event_source = self
+            .streaming_version(event_source, path)
             .eventsource()
             .unwrap();
 
-        stream(event_source).await
+        stream(
+            event_source,
+            in_flight,
+            StreamEvents::open(&self.events, &self.config.url(path)),
+        )
+        .await
     }
 
+    #[cfg(feature = "assistants")]
     pub(crate) async fn post_stream_mapped_raw_events<I, O>(
         &self,
         path: &str,
@@ -416,16 +704,30 @@ impl<C: Config> Client<C> {
         I: Serialize,
         O: DeserializeOwned + std::marker::Send + 'static,
     {
+        let in_flight = match self.lifecycle.enter() {
+            Ok(in_flight) => in_flight,
+            Err(e) => return Box::pin(futures::stream::once(async { Err(e) })),
+        };
+
         let event_source = self
             .http_client
             .post(self.config.url(path))
             .query(&self.config.query())
             .headers(self.config.headers())
-            .json(&request)
+            .json(&request);
+
+        let event_source = self
+            .streaming_version(event_source, path)
             .eventsource()
             .unwrap();
 
-        stream_mapped_raw_events(event_source, event_mapper).await
+        stream_mapped_raw_events(
+            event_source,
+            event_mapper,
+            in_flight,
+            StreamEvents::open(&self.events, &self.config.url(path)),
+        )
+        .await
     }
 
     /// Make HTTP GET request to receive SSE
@@ -438,16 +740,29 @@ impl<C: Config> Client<C> {
         Q: Serialize + ?Sized,
         O: DeserializeOwned + std::marker::Send + 'static,
     {
+        let in_flight = match self.lifecycle.enter() {
+            Ok(in_flight) => in_flight,
+            Err(e) => return Box::pin(futures::stream::once(async { Err(e) })),
+        };
+
         let event_source = self
             .http_client
             .get(self.config.url(path))
             .query(query)
             .query(&self.config.query())
-            .headers(self.config.headers())
+            .headers(self.config.headers());
+
+        let event_source = self
+            .streaming_version(event_source, path)
             .eventsource()
             .unwrap();
 
-        stream(event_source).await
+        stream(
+            event_source,
+            in_flight,
+            StreamEvents::open(&self.events, &self.config.url(path)),
+        )
+        .await
     }
 }
 
@@ -455,6 +770,8 @@ impl<C: Config> Client<C> {
 /// [server-sent events](https://developer.mozilla.org/en-US/docs/Web/API/Server-sent_events/Using_server-sent_events#event_stream_format)
 pub(crate) async fn stream<O>(
     mut event_source: EventSource,
+    in_flight: InFlight,
+    mut events: StreamEvents,
 ) -> Pin<Box<dyn Stream<Item = Result<O, OpenAIError>> + Send>>
 where
     O: DeserializeOwned + std::marker::Send + 'static,
@@ -462,10 +779,24 @@ where
     let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
 
     tokio::spawn(async move {
-        while let Some(ev) = event_source.next().await {
+        loop {
+            let ev = tokio::select! {
+                ev = event_source.next() => match ev {
+                    Some(ev) => ev,
+                    None => break,
+                },
+                _ = in_flight.aborted() => {
+                    events.failed(&OpenAIError::ClientShutdown);
+                    let _ = tx.send(Err(OpenAIError::ClientShutdown));
+                    break;
+                }
+            };
+
             match ev {
                 Err(e) => {
-                    if let Err(_e) = tx.send(Err(OpenAIError::StreamError(e.to_string()))) {
+                    let error = map_event_source_error(e);
+                    events.failed(&error);
+                    if let Err(_e) = tx.send(Err(error)) {
                         // rx dropped
                         break;
                     }
@@ -476,10 +807,20 @@ where
                             break;
                         }
 
+                        if let Some(error) = map_stream_error_event(&message.event, &message.data) {
+                            tracing::warn!("stream ended by an error event: {error}");
+                            events.failed(&error);
+                            let _ = tx.send(Err(error));
+                            break;
+                        }
+
                         let response = match serde_json::from_str::<O>(&message.data) {
                             Err(e) => Err(map_deserialization_error(e, message.data.as_bytes())),
                             Ok(output) => Ok(output),
                         };
+                        if let Err(error) = &response {
+                            events.failed(error);
+                        }
 
                         if let Err(_e) = tx.send(response) {
                             // rx dropped
@@ -497,9 +838,12 @@ where
     Box::pin(tokio_stream::wrappers::UnboundedReceiverStream::new(rx))
 }
 
+#[cfg(feature = "assistants")]
 pub(crate) async fn stream_mapped_raw_events<O>(
     mut event_source: EventSource,
     event_mapper: impl Fn(eventsource_stream::Event) -> Result<O, OpenAIError> + Send + 'static,
+    in_flight: InFlight,
+    mut events: StreamEvents,
 ) -> Pin<Box<dyn Stream<Item = Result<O, OpenAIError>> + Send>>
 where
     O: DeserializeOwned + std::marker::Send + 'static,
@@ -507,10 +851,24 @@ where
     let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
 
     tokio::spawn(async move {
-        while let Some(ev) = event_source.next().await {
+        loop {
+            let ev = tokio::select! {
+                ev = event_source.next() => match ev {
+                    Some(ev) => ev,
+                    None => break,
+                },
+                _ = in_flight.aborted() => {
+                    events.failed(&OpenAIError::ClientShutdown);
+                    let _ = tx.send(Err(OpenAIError::ClientShutdown));
+                    break;
+                }
+            };
+
             match ev {
                 Err(e) => {
-                    if let Err(_e) = tx.send(Err(OpenAIError::StreamError(e.to_string()))) {
+                    let error = map_event_source_error(e);
+                    events.failed(&error);
+                    if let Err(_e) = tx.send(Err(error)) {
                         // rx dropped
                         break;
                     }
@@ -524,6 +882,9 @@ where
                         }
 
                         let response = event_mapper(message);
+                        if let Err(error) = &response {
+                            events.failed(error);
+                        }
 
                         if let Err(_e) = tx.send(response) {
                             // rx dropped
@@ -2,7 +2,11 @@ use crate::{
     client::Client,
     config::Config,
     error::OpenAIError,
-    types::{CompletionResponseStream, CreateCompletionRequest, CreateCompletionResponse},
+    types::{
+        CompletionResponseStream, CreateCompletionRequest, CreateCompletionResponse,
+        RawResponseStream,
+    },
+    util::prepare_raw_request,
 };
 
 /// Given a prompt, the model will return one or more predicted completions,
@@ -39,6 +43,7 @@ impl<'c, C: Config> Completions<'c, C> {
                 ));
             }
         }
+        self.client.scan_input(&request)?;
         self.client.post("/completions", request).await
     }
 
@@ -72,6 +77,32 @@ impl<'c, C: Config> Completions<'c, C> {
 
             request.stream = Some(true);
         }
+        self.client.scan_input(&request)?;
+        Ok(self.client.post_stream("/completions", request).await)
+    }
+
+    /// Same as [Completions::create], with the request and response as untyped JSON, so that
+    /// parameters the typed request doesn't know about yet can be sent.
+    pub async fn create_raw(
+        &self,
+        mut request: serde_json::Value,
+    ) -> Result<serde_json::Value, OpenAIError> {
+        prepare_raw_request(
+            &mut request,
+            Some((false, "Completions::create_stream_raw")),
+        )?;
+        self.client.scan_input(&request)?;
+        self.client.post("/completions", request).await
+    }
+
+    /// Same as [Completions::create_stream], with the request and the chunks as untyped JSON.
+    /// `stream` is set to `true` when absent.
+    pub async fn create_stream_raw(
+        &self,
+        mut request: serde_json::Value,
+    ) -> Result<RawResponseStream, OpenAIError> {
+        prepare_raw_request(&mut request, Some((true, "Completions::create_raw")))?;
+        self.client.scan_input(&request)?;
         Ok(self.client.post_stream("/completions", request).await)
     }
 }
@@ -0,0 +1,239 @@
+//! Opt-in recovery from [OpenAIError::ContextLengthExceeded] for chat completions.
+//!
+//! A [ContextLengthPolicy] shrinks the conversation by trimming (or summarizing)
+//! the oldest turns and retries the request. Messages matched by the protected
+//! predicate (system and developer messages by default) and the most recent
+//! message are never removed. A summary replaces the previous one, which it folds in, so
+//! that summaries don't pile up over the retries.
+use std::sync::Arc;
+
+use crate::{
+    config::Config,
+    error::OpenAIError,
+    types::{
+        ChatCompletionRequestMessage, ChatCompletionRequestSystemMessage,
+        ChatCompletionRequestUserMessage, CreateChatCompletionRequest,
+        CreateChatCompletionResponse,
+    },
+    Client,
+};
+
+/// How messages are removed from a conversation that exceeds the context window.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ContextLengthStrategy {
+    /// Drop the oldest unprotected messages.
+    TrimOldest,
+    /// Replace the oldest unprotected messages with a summary generated by `model`.
+    Summarize { model: String },
+}
+
+/// Predicate selecting messages that must never be dropped.
+pub type ProtectedMessage = Arc<dyn Fn(&ChatCompletionRequestMessage) -> bool + Send + Sync>;
+
+/// Policy applied when a chat completion fails with [OpenAIError::ContextLengthExceeded].
+#[derive(Clone)]
+pub struct ContextLengthPolicy {
+    strategy: ContextLengthStrategy,
+    max_retries: u32,
+    messages_per_retry: usize,
+    protected: ProtectedMessage,
+}
+
+impl std::fmt::Debug for ContextLengthPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ContextLengthPolicy")
+            .field("strategy", &self.strategy)
+            .field("max_retries", &self.max_retries)
+            .field("messages_per_retry", &self.messages_per_retry)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for ContextLengthPolicy {
+    fn default() -> Self {
+        Self {
+            strategy: ContextLengthStrategy::TrimOldest,
+            max_retries: 3,
+            messages_per_retry: 2,
+            protected: Arc::new(|message| {
+                matches!(
+                    message,
+                    ChatCompletionRequestMessage::System(_)
+                        | ChatCompletionRequestMessage::Developer(_)
+                )
+            }),
+        }
+    }
+}
+
+impl ContextLengthPolicy {
+    /// Policy which drops the oldest unprotected messages on every retry.
+    pub fn trim_oldest() -> Self {
+        Self::default()
+    }
+
+    /// Policy which summarizes the oldest unprotected messages with a (cheap) `model`.
+    pub fn summarize<S: Into<String>>(model: S) -> Self {
+        Self {
+            strategy: ContextLengthStrategy::Summarize {
+                model: model.into(),
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Maximum number of retries before the error is returned to the caller. Default is 3.
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Number of messages removed (or summarized) on each retry. Default is 2.
+    pub fn messages_per_retry(mut self, messages_per_retry: usize) -> Self {
+        self.messages_per_retry = messages_per_retry.max(1);
+        self
+    }
+
+    /// Replace the predicate selecting messages that are never dropped.
+    /// The default protects system and developer messages.
+    pub fn protect<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&ChatCompletionRequestMessage) -> bool + Send + Sync + 'static,
+    {
+        self.protected = Arc::new(predicate);
+        self
+    }
+
+    /// How messages are removed on every retry.
+    pub fn strategy(&self) -> &ContextLengthStrategy {
+        &self.strategy
+    }
+
+    /// Send `request`, shrinking the conversation and retrying whenever the API
+    /// responds with [OpenAIError::ContextLengthExceeded].
+    pub(crate) async fn create<C: Config>(
+        &self,
+        client: &Client<C>,
+        mut request: CreateChatCompletionRequest,
+    ) -> Result<CreateChatCompletionResponse, OpenAIError> {
+        let mut retries = 0;
+        // Position of the summary inserted by the previous retry
+        let mut summary_at = None;
+        loop {
+            match client.chat().create(request.clone()).await {
+                Err(OpenAIError::ContextLengthExceeded(error)) if retries < self.max_retries => {
+                    let mut evicted = self.evict(&mut request.messages);
+                    if evicted.is_empty() {
+                        return Err(OpenAIError::ContextLengthExceeded(error));
+                    }
+
+                    tracing::warn!(
+                        "context length exceeded, retrying with {} fewer messages",
+                        evicted.len()
+                    );
+
+                    if let ContextLengthStrategy::Summarize { model } = &self.strategy {
+                        // Fold the previous summary into the new one, unless it was evicted
+                        // already by a predicate that doesn't protect system messages
+                        if let Some(at) = summary_at.take() {
+                            if !evicted.iter().any(|(position, _)| *position == at) {
+                                evicted.insert(0, (at, request.messages.remove(at)));
+                            }
+                        }
+                        let summary = summarize(client, model, &evicted).await?;
+                        let position = evicted[0].0;
+                        summary_at = Some(position);
+                        request.messages.insert(
+                            position,
+                            ChatCompletionRequestSystemMessage::from(format!(
+                                "Summary of the earlier conversation:\n{summary}"
+                            ))
+                            .into(),
+                        );
+                    }
+
+                    retries += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Remove up to `messages_per_retry` of the oldest unprotected messages, together with
+    /// the tool results answering a removed assistant message, and return them along with
+    /// their original position.
+    fn evict(
+        &self,
+        messages: &mut Vec<ChatCompletionRequestMessage>,
+    ) -> Vec<(usize, ChatCompletionRequestMessage)> {
+        let mut evicted = vec![];
+        let mut index = 0;
+
+        // The most recent message is never dropped
+        while evicted.len() < self.messages_per_retry && index + 1 < messages.len() {
+            if (self.protected)(&messages[index]) {
+                index += 1;
+                continue;
+            }
+
+            let position = index + evicted.len();
+            evicted.push((position, messages.remove(index)));
+
+            // Tool results without their assistant message are rejected by the API
+            while index + 1 < messages.len()
+                && matches!(
+                    messages[index],
+                    ChatCompletionRequestMessage::Tool(_)
+                        | ChatCompletionRequestMessage::Function(_)
+                )
+            {
+                let position = index + evicted.len();
+                evicted.push((position, messages.remove(index)));
+            }
+        }
+
+        evicted
+    }
+}
+
+pub(crate) async fn summarize<C: Config>(
+    client: &Client<C>,
+    model: &str,
+    messages: &[(usize, ChatCompletionRequestMessage)],
+) -> Result<String, OpenAIError> {
+    let transcript = messages
+        .iter()
+        .map(|(_, message)| format!("{}: {}", role(message), message.text()))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let request = CreateChatCompletionRequest {
+        model: model.to_string(),
+        messages: vec![ChatCompletionRequestUserMessage::from(format!(
+            "Summarize the following conversation in a few sentences, keeping facts, \
+             decisions and open questions:\n\n{transcript}"
+        ))
+        .into()],
+        ..Default::default()
+    };
+
+    let response = client.chat().create(request).await?;
+
+    Ok(response
+        .choices
+        .into_iter()
+        .next()
+        .and_then(|choice| choice.message.content)
+        .unwrap_or_default())
+}
+
+pub(crate) fn role(message: &ChatCompletionRequestMessage) -> &'static str {
+    match message {
+        ChatCompletionRequestMessage::Developer(_) => "developer",
+        ChatCompletionRequestMessage::System(_) => "system",
+        ChatCompletionRequestMessage::User(_) => "user",
+        ChatCompletionRequestMessage::Assistant(_) => "assistant",
+        ChatCompletionRequestMessage::Tool(_) => "tool",
+        ChatCompletionRequestMessage::Function(_) => "function",
+    }
+}
@@ -0,0 +1,460 @@
+//! Graceful degradation of requests for backends that don't support all their parameters.
+//!
+//! Where [crate::capabilities] only reports what a model doesn't support, a
+//! [DegradationPolicy] rewrites the request to fit: each unsupported [Parameter] is stripped,
+//! converted to something the model does support, or reported as an error, as configured per
+//! provider in a [DegradationMatrix]. The same code can then target OpenAI and
+//! OpenAI-compatible backends with predictable results.
+//!
+//! ```
+//! use async_openai::{
+//!     degradation::DegradationMatrix,
+//!     types::{ChatCompletionRequestUserMessageArgs, CreateChatCompletionRequestArgs},
+//! };
+//!
+//! let matrix = DegradationMatrix::default();
+//! let request = CreateChatCompletionRequestArgs::default()
+//!     .model("o3-mini")
+//!     .temperature(0.2)
+//!     .max_tokens(512_u32)
+//!     .messages([ChatCompletionRequestUserMessageArgs::default()
+//!         .content("Name three prime numbers")
+//!         .build()
+//!         .unwrap()
+//!         .into()])
+//!     .build_degraded(matrix.policy("openai").unwrap())
+//!     .unwrap();
+//!
+//! assert_eq!(request.temperature, None);
+//! assert_eq!(request.max_completion_tokens, Some(512));
+//! ```
+use crate::{
+    capabilities::{ModelCapabilities, ModelRegistry},
+    error::OpenAIError,
+    types::{
+        ChatCompletionModalities, ChatCompletionRequestMessage, ChatCompletionRequestSystemMessage,
+        ChatCompletionRequestUserMessageContent, ChatCompletionRequestUserMessageContentPart,
+        CreateChatCompletionRequest, CreateChatCompletionRequestArgs, ResponseFormat,
+    },
+};
+
+/// A group of request parameters a model may not support, one per field of
+/// [ModelCapabilities].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Parameter {
+    /// `temperature`, `top_p`, `presence_penalty`, `frequency_penalty`, `logprobs` and
+    /// `top_logprobs`.
+    Sampling,
+    /// `tools`, `tool_choice`, `parallel_tool_calls` and the deprecated functions.
+    Tools,
+    /// Image content parts in user messages.
+    Vision,
+    /// Audio content parts and audio output.
+    Audio,
+    /// `reasoning_effort`.
+    ReasoningEffort,
+    /// The deprecated `max_tokens`.
+    MaxTokens,
+    /// `response_format` of type `json_schema`.
+    StructuredOutputs,
+}
+
+impl Parameter {
+    const ALL: [Parameter; 7] = [
+        Parameter::Sampling,
+        Parameter::Tools,
+        Parameter::Vision,
+        Parameter::Audio,
+        Parameter::ReasoningEffort,
+        Parameter::MaxTokens,
+        Parameter::StructuredOutputs,
+    ];
+
+    fn is_supported(self, capabilities: &ModelCapabilities) -> bool {
+        match self {
+            Parameter::Sampling => capabilities.sampling,
+            Parameter::Tools => capabilities.tools,
+            Parameter::Vision => capabilities.vision,
+            Parameter::Audio => capabilities.audio,
+            Parameter::ReasoningEffort => capabilities.reasoning_effort,
+            Parameter::MaxTokens => capabilities.max_tokens,
+            Parameter::StructuredOutputs => capabilities.structured_outputs,
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Parameter::Sampling => "sampling parameters",
+            Parameter::Tools => "tools and functions",
+            Parameter::Vision => "image content parts",
+            Parameter::Audio => "audio content parts and output",
+            Parameter::ReasoningEffort => "`reasoning_effort`",
+            Parameter::MaxTokens => "`max_tokens`",
+            Parameter::StructuredOutputs => "`response_format` of type `json_schema`",
+        }
+    }
+}
+
+/// What to do with a parameter the model doesn't support.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Degradation {
+    /// Remove it from the request.
+    Strip,
+    /// Replace it with an equivalent the model supports: `max_tokens` becomes
+    /// `max_completion_tokens`, and a `json_schema` response format becomes `json_object`
+    /// with the schema in a system message. Parameters without an equivalent are stripped.
+    Convert,
+    /// Fail with [OpenAIError::UnsupportedByModel].
+    Error,
+}
+
+/// How requests are degraded for the models of one provider.
+#[derive(Debug, Clone)]
+pub struct DegradationPolicy {
+    registry: ModelRegistry,
+    fallback: Option<ModelCapabilities>,
+    actions: Vec<(Parameter, Degradation)>,
+    strict: bool,
+}
+
+impl DegradationPolicy {
+    /// Degrade requests for the models of `registry`, with the default actions: sampling
+    /// parameters and `reasoning_effort` are stripped, `max_tokens` and structured outputs
+    /// are converted, and tools, images and audio are errors. Unknown models are sent as is.
+    pub fn new(registry: ModelRegistry) -> Self {
+        Self {
+            registry,
+            fallback: None,
+            actions: vec![
+                (Parameter::Sampling, Degradation::Strip),
+                (Parameter::Tools, Degradation::Error),
+                (Parameter::Vision, Degradation::Error),
+                (Parameter::Audio, Degradation::Error),
+                (Parameter::ReasoningEffort, Degradation::Strip),
+                (Parameter::MaxTokens, Degradation::Convert),
+                (Parameter::StructuredOutputs, Degradation::Convert),
+            ],
+            strict: false,
+        }
+    }
+
+    /// Capabilities of the models missing from the registry, instead of sending their
+    /// requests as is.
+    pub fn fallback(mut self, capabilities: ModelCapabilities) -> Self {
+        self.fallback = Some(capabilities);
+        self
+    }
+
+    /// What to do with `parameter` when the model doesn't support it.
+    pub fn on(mut self, parameter: Parameter, degradation: Degradation) -> Self {
+        for (p, action) in self.actions.iter_mut() {
+            if *p == parameter {
+                *action = degradation;
+            }
+        }
+        self
+    }
+
+    /// Treat every unsupported parameter as [Degradation::Error], whatever the configured
+    /// actions. Useful to find out in tests which requests would be degraded.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// The action for `parameter`, taking strictness into account.
+    pub fn action(&self, parameter: Parameter) -> Degradation {
+        if self.strict {
+            return Degradation::Error;
+        }
+        self.actions
+            .iter()
+            .find(|(p, _)| *p == parameter)
+            .map(|(_, action)| *action)
+            .unwrap_or(Degradation::Error)
+    }
+
+    fn capabilities(&self, model: &str) -> Option<ModelCapabilities> {
+        self.registry.lookup(model).or(self.fallback)
+    }
+}
+
+impl Default for DegradationPolicy {
+    fn default() -> Self {
+        Self::new(ModelRegistry::default())
+    }
+}
+
+/// Degradation policies by provider name.
+///
+/// The default matrix has `openai` and `azure`, which degrade for the default
+/// [ModelRegistry], and `deepseek`, which additionally treats unknown models as chat models
+/// without structured outputs.
+#[derive(Debug, Clone)]
+pub struct DegradationMatrix {
+    providers: Vec<(String, DegradationPolicy)>,
+}
+
+impl Default for DegradationMatrix {
+    fn default() -> Self {
+        let deepseek = DegradationPolicy::default().fallback(ModelCapabilities {
+            structured_outputs: false,
+            ..ModelCapabilities::CHAT
+        });
+
+        Self::empty()
+            .register("openai", DegradationPolicy::default())
+            .register("azure", DegradationPolicy::default())
+            .register("deepseek", deepseek)
+    }
+}
+
+impl DegradationMatrix {
+    /// Matrix without any providers.
+    pub fn empty() -> Self {
+        Self { providers: vec![] }
+    }
+
+    /// Add or replace the policy of `provider`.
+    pub fn register<S: Into<String>>(mut self, provider: S, policy: DegradationPolicy) -> Self {
+        let provider = provider.into();
+        self.providers.retain(|(p, _)| *p != provider);
+        self.providers.push((provider, policy));
+        self
+    }
+
+    /// Policy of `provider`, if it is in the matrix.
+    pub fn policy(&self, provider: &str) -> Option<&DegradationPolicy> {
+        self.providers
+            .iter()
+            .find(|(p, _)| p == provider)
+            .map(|(_, policy)| policy)
+    }
+}
+
+impl CreateChatCompletionRequest {
+    /// Rewrite the request to fit its model according to `policy`, returning a description
+    /// of every change made. If any unsupported parameter is an error, the request is left
+    /// untouched and all of them are returned at once in [OpenAIError::UnsupportedByModel].
+    pub fn degrade(&mut self, policy: &DegradationPolicy) -> Result<Vec<String>, OpenAIError> {
+        let Some(capabilities) = policy.capabilities(&self.model) else {
+            return Ok(vec![]);
+        };
+
+        let unsupported: Vec<_> = Parameter::ALL
+            .into_iter()
+            .filter(|parameter| !parameter.is_supported(&capabilities) && self.uses(*parameter))
+            .collect();
+
+        let violations: Vec<_> = unsupported
+            .iter()
+            .filter(|parameter| policy.action(**parameter) == Degradation::Error)
+            .map(|parameter| format!("{} are not supported", parameter.description()))
+            .collect();
+        if !violations.is_empty() {
+            return Err(OpenAIError::UnsupportedByModel {
+                model: self.model.clone(),
+                violations,
+            });
+        }
+
+        let mut changes = vec![];
+        for parameter in unsupported {
+            if policy.action(parameter) == Degradation::Convert {
+                if let Some(change) = self.convert(parameter) {
+                    changes.push(change);
+                    continue;
+                }
+            }
+            self.strip(parameter);
+            changes.push(format!("stripped {}", parameter.description()));
+        }
+        Ok(changes)
+    }
+
+    fn uses(&self, parameter: Parameter) -> bool {
+        match parameter {
+            Parameter::Sampling => {
+                self.temperature.is_some()
+                    || self.top_p.is_some()
+                    || self.presence_penalty.is_some()
+                    || self.frequency_penalty.is_some()
+                    || self.logprobs.is_some()
+                    || self.top_logprobs.is_some()
+            }
+            #[allow(deprecated)]
+            Parameter::Tools => {
+                self.tools.is_some()
+                    || self.tool_choice.is_some()
+                    || self.parallel_tool_calls.is_some()
+                    || self.functions.is_some()
+                    || self.function_call.is_some()
+            }
+            Parameter::Vision => self.has_part(|part| {
+                matches!(
+                    part,
+                    ChatCompletionRequestUserMessageContentPart::ImageUrl(_)
+                )
+            }),
+            Parameter::Audio => {
+                self.audio.is_some()
+                    || self.modalities.as_ref().is_some_and(|modalities| {
+                        modalities.contains(&ChatCompletionModalities::Audio)
+                    })
+                    || self.has_part(|part| {
+                        matches!(
+                            part,
+                            ChatCompletionRequestUserMessageContentPart::InputAudio(_)
+                        )
+                    })
+            }
+            Parameter::ReasoningEffort => self.reasoning_effort.is_some(),
+            #[allow(deprecated)]
+            Parameter::MaxTokens => self.max_tokens.is_some(),
+            Parameter::StructuredOutputs => matches!(
+                self.response_format,
+                Some(ResponseFormat::JsonSchema { .. })
+            ),
+        }
+    }
+
+    fn has_part(
+        &self,
+        predicate: impl Fn(&ChatCompletionRequestUserMessageContentPart) -> bool,
+    ) -> bool {
+        self.messages.iter().any(|message| match message {
+            ChatCompletionRequestMessage::User(message) => match &message.content {
+                ChatCompletionRequestUserMessageContent::Array(parts) => {
+                    parts.iter().any(&predicate)
+                }
+                ChatCompletionRequestUserMessageContent::Text(_) => false,
+            },
+            _ => false,
+        })
+    }
+
+    fn retain_parts(
+        &mut self,
+        keep: impl Fn(&ChatCompletionRequestUserMessageContentPart) -> bool,
+    ) {
+        for message in self.messages.iter_mut() {
+            if let ChatCompletionRequestMessage::User(message) = message {
+                if let ChatCompletionRequestUserMessageContent::Array(parts) = &mut message.content
+                {
+                    parts.retain(&keep);
+                }
+            }
+        }
+    }
+
+    fn strip(&mut self, parameter: Parameter) {
+        match parameter {
+            Parameter::Sampling => {
+                self.temperature = None;
+                self.top_p = None;
+                self.presence_penalty = None;
+                self.frequency_penalty = None;
+                self.logprobs = None;
+                self.top_logprobs = None;
+            }
+            #[allow(deprecated)]
+            Parameter::Tools => {
+                self.tools = None;
+                self.tool_choice = None;
+                self.parallel_tool_calls = None;
+                self.functions = None;
+                self.function_call = None;
+            }
+            Parameter::Vision => self.retain_parts(|part| {
+                !matches!(
+                    part,
+                    ChatCompletionRequestUserMessageContentPart::ImageUrl(_)
+                )
+            }),
+            Parameter::Audio => {
+                self.audio = None;
+                if let Some(modalities) = &mut self.modalities {
+                    modalities.retain(|modality| *modality != ChatCompletionModalities::Audio);
+                }
+                self.retain_parts(|part| {
+                    !matches!(
+                        part,
+                        ChatCompletionRequestUserMessageContentPart::InputAudio(_)
+                    )
+                });
+            }
+            Parameter::ReasoningEffort => self.reasoning_effort = None,
+            #[allow(deprecated)]
+            Parameter::MaxTokens => self.max_tokens = None,
+            Parameter::StructuredOutputs => self.response_format = None,
+        }
+    }
+
+    /// Replace `parameter` with its equivalent, if it has one.
+    fn convert(&mut self, parameter: Parameter) -> Option<String> {
+        match parameter {
+            #[allow(deprecated)]
+            Parameter::MaxTokens => {
+                let max_tokens = self.max_tokens.take();
+                self.max_completion_tokens = self.max_completion_tokens.or(max_tokens);
+                Some("converted `max_tokens` to `max_completion_tokens`".to_string())
+            }
+            Parameter::StructuredOutputs => {
+                let Some(ResponseFormat::JsonSchema { json_schema }) = self.response_format.take()
+                else {
+                    return None;
+                };
+                let schema = json_schema
+                    .schema
+                    .map(|schema| serde_json::to_string_pretty(&schema).unwrap_or_default())
+                    .unwrap_or_else(|| "{}".to_string());
+                let mut instruction = format!(
+                    "Respond only with a JSON object named `{}` that conforms to this JSON Schema:",
+                    json_schema.name
+                );
+                if let Some(description) = json_schema.description {
+                    instruction = format!("{description}\n\n{instruction}");
+                }
+                let instruction = format!("{instruction}\n```json\n{schema}\n```");
+
+                // After the leading instructions, before the conversation
+                let index = self
+                    .messages
+                    .iter()
+                    .position(|message| {
+                        !matches!(
+                            message,
+                            ChatCompletionRequestMessage::System(_)
+                                | ChatCompletionRequestMessage::Developer(_)
+                        )
+                    })
+                    .unwrap_or(self.messages.len());
+                self.messages.insert(
+                    index,
+                    ChatCompletionRequestSystemMessage::from(instruction).into(),
+                );
+                self.response_format = Some(ResponseFormat::JsonObject);
+                Some(
+                    "converted `response_format` of type `json_schema` to `json_object` with \
+                     the schema in a system message"
+                        .to_string(),
+                )
+            }
+            _ => None,
+        }
+    }
+}
+
+impl CreateChatCompletionRequestArgs {
+    /// Build the request and degrade it according to `policy`. Every change made is logged.
+    pub fn build_degraded(
+        &self,
+        policy: &DegradationPolicy,
+    ) -> Result<CreateChatCompletionRequest, OpenAIError> {
+        let mut request = self.build()?;
+        for change in request.degrade(policy)? {
+            tracing::info!("degraded request for model {}: {change}", request.model);
+        }
+        Ok(request)
+    }
+}
@@ -4,7 +4,7 @@ use base64::{engine::general_purpose, Engine as _};
 use rand::{distributions::Alphanumeric, Rng};
 use reqwest::Url;
 
-use crate::error::OpenAIError;
+use crate::{error::OpenAIError, types::ImageFormat};
 
 fn create_paths<P: AsRef<Path>>(url: &Url, base_dir: P) -> (PathBuf, PathBuf) {
     let mut dir = PathBuf::from(base_dir.as_ref());
@@ -63,18 +63,19 @@ pub(crate) async fn save_b64<P: AsRef<Path>>(b64: &str, dir: P) -> Result<PathBu
         .map(char::from)
         .collect();
 
-    let filename = format!("{filename}.png");
+    let bytes = general_purpose::STANDARD
+        .decode(b64)
+        .map_err(|e| OpenAIError::FileSaveError(e.to_string()))?;
+
+    // PNG unless the bytes tell otherwise
+    let extension = ImageFormat::detect(&bytes).map_or("png", ImageFormat::extension);
+    let filename = format!("{filename}.{extension}");
 
     let path = PathBuf::from(dir.as_ref()).join(filename);
 
-    tokio::fs::write(
-        path.as_path(),
-        general_purpose::STANDARD
-            .decode(b64)
-            .map_err(|e| OpenAIError::FileSaveError(e.to_string()))?,
-    )
-    .await
-    .map_err(|e| OpenAIError::FileSaveError(format!("{}, path: {}", e, path.display())))?;
+    tokio::fs::write(path.as_path(), bytes)
+        .await
+        .map_err(|e| OpenAIError::FileSaveError(format!("{}, path: {}", e, path.display())))?;
 
     Ok(path)
 }
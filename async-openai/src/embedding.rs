@@ -1,7 +1,12 @@
 use crate::{
     config::Config,
     error::OpenAIError,
-    types::{CreateBase64EmbeddingResponse, CreateEmbeddingRequest, CreateEmbeddingResponse},
+    tokens::{Tokenizer, TruncationStrategy},
+    types::{
+        CreateBase64EmbeddingResponse, CreateEmbeddingRequest, CreateEmbeddingResponse,
+        EmbeddingInput,
+    },
+    util::prepare_raw_request,
     Client,
 };
 
@@ -60,11 +65,157 @@ impl<'c, C: Config> Embeddings<'c, C> {
         }
         self.client.post("/embeddings", request).await
     }
+
+    /// Same as [Embeddings::create], but every input is first checked against the token
+    /// limit of `preflight`, so that a single oversized input is rejected (or truncated)
+    /// before the request is sent instead of failing the whole batch server-side.
+    pub async fn create_with_preflight(
+        &self,
+        mut request: CreateEmbeddingRequest,
+        preflight: &EmbeddingPreflight,
+    ) -> Result<CreateEmbeddingResponse, OpenAIError> {
+        preflight.apply(&mut request)?;
+        self.create(request).await
+    }
+
+    /// Same as [Embeddings::create], with the request and response as untyped JSON, so that
+    /// parameters the typed request doesn't know about yet can be sent.
+    pub async fn create_raw(
+        &self,
+        mut request: serde_json::Value,
+    ) -> Result<serde_json::Value, OpenAIError> {
+        prepare_raw_request(&mut request, None)?;
+        self.client.post("/embeddings", request).await
+    }
+}
+
+/// Maximum number of tokens in an input of the OpenAI embedding models.
+pub const MAX_INPUT_TOKENS: usize = 8191;
+
+/// What [EmbeddingPreflight] does with an input over the token limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OversizedInput {
+    /// Fail with [OpenAIError::EmbeddingInputTooLong], listing the offending inputs.
+    Reject,
+    /// Shorten the input to the limit, keeping the part selected by the strategy.
+    Truncate(TruncationStrategy),
+}
+
+/// Client side check of the length of embeddings inputs, see
+/// [Embeddings::create_with_preflight].
+///
+/// Text inputs are counted with the tokenizer of the request's model, exact with the
+/// `tiktoken` feature and estimated otherwise; inputs given as token arrays are counted by
+/// their length.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmbeddingPreflight {
+    max_tokens: usize,
+    on_oversized: OversizedInput,
+}
+
+impl Default for EmbeddingPreflight {
+    fn default() -> Self {
+        Self {
+            max_tokens: MAX_INPUT_TOKENS,
+            on_oversized: OversizedInput::Reject,
+        }
+    }
+}
+
+impl EmbeddingPreflight {
+    /// Preflight which rejects requests with an oversized input.
+    pub fn reject() -> Self {
+        Self::default()
+    }
+
+    /// Preflight which truncates oversized inputs with `strategy`.
+    pub fn truncate(strategy: TruncationStrategy) -> Self {
+        Self {
+            on_oversized: OversizedInput::Truncate(strategy),
+            ..Default::default()
+        }
+    }
+
+    /// Token limit of an input. Default is [MAX_INPUT_TOKENS].
+    pub fn max_tokens(mut self, max_tokens: usize) -> Self {
+        self.max_tokens = max_tokens;
+        self
+    }
+
+    /// Check the inputs of `request`, truncating them in place when configured to, and
+    /// return the indices of the inputs that were over the limit.
+    pub fn apply(&self, request: &mut CreateEmbeddingRequest) -> Result<Vec<usize>, OpenAIError> {
+        let tokenizer = Tokenizer::for_model(&request.model);
+        let max_tokens = self.max_tokens;
+
+        let indices: Vec<usize> = match &request.input {
+            EmbeddingInput::String(text) => {
+                Vec::from_iter((tokenizer.count(text) > max_tokens).then_some(0))
+            }
+            EmbeddingInput::StringArray(texts) => texts
+                .iter()
+                .enumerate()
+                .filter(|(_, text)| tokenizer.count(text) > max_tokens)
+                .map(|(index, _)| index)
+                .collect(),
+            EmbeddingInput::IntegerArray(tokens) => {
+                Vec::from_iter((tokens.len() > max_tokens).then_some(0))
+            }
+            EmbeddingInput::ArrayOfIntegerArray(arrays) => arrays
+                .iter()
+                .enumerate()
+                .filter(|(_, tokens)| tokens.len() > max_tokens)
+                .map(|(index, _)| index)
+                .collect(),
+        };
+        if indices.is_empty() {
+            return Ok(indices);
+        }
+
+        let strategy = match self.on_oversized {
+            OversizedInput::Reject => {
+                return Err(OpenAIError::EmbeddingInputTooLong {
+                    max_tokens,
+                    indices,
+                })
+            }
+            OversizedInput::Truncate(strategy) => strategy,
+        };
+
+        let truncate_text =
+            |text: &mut String| *text = tokenizer.truncate(text, max_tokens, strategy).into_owned();
+        match &mut request.input {
+            EmbeddingInput::String(text) => truncate_text(text),
+            EmbeddingInput::StringArray(texts) => indices
+                .iter()
+                .for_each(|&index| truncate_text(&mut texts[index])),
+            EmbeddingInput::IntegerArray(tokens) => truncate_tokens(tokens, max_tokens, strategy),
+            EmbeddingInput::ArrayOfIntegerArray(arrays) => indices
+                .iter()
+                .for_each(|&index| truncate_tokens(&mut arrays[index], max_tokens, strategy)),
+        }
+        tracing::warn!("truncated embeddings inputs at indices {indices:?} to {max_tokens} tokens");
+        Ok(indices)
+    }
+}
+
+/// Shorten an input given as token ids. Token ids have no ellipsis to join them with, so
+/// [TruncationStrategy::MiddleEllipsis] keeps both ends as they are.
+fn truncate_tokens(tokens: &mut Vec<u32>, max_tokens: usize, strategy: TruncationStrategy) {
+    match strategy {
+        TruncationStrategy::Head => tokens.truncate(max_tokens),
+        TruncationStrategy::Tail => {
+            tokens.drain(..tokens.len() - max_tokens);
+        }
+        TruncationStrategy::MiddleEllipsis => {
+            let tail = max_tokens / 2;
+            tokens.drain(max_tokens - tail..tokens.len() - tail);
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::error::OpenAIError;
     use crate::types::{CreateEmbeddingResponse, Embedding, EncodingFormat};
     use crate::{types::CreateEmbeddingRequestArgs, Client};
 
@@ -179,7 +330,10 @@ mod tests {
             .build()
             .unwrap();
         let b64_response = client.embeddings().create(b64_request).await;
-        assert!(matches!(b64_response, Err(OpenAIError::InvalidArgument(_))));
+        assert!(matches!(
+            b64_response,
+            Err(crate::error::OpenAIError::InvalidArgument(_))
+        ));
     }
 
     #[tokio::test]
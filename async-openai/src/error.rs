@@ -2,8 +2,10 @@
 use serde::Deserialize;
 
 #[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
 pub enum OpenAIError {
     /// Underlying error from reqwest library after an API call was made
+    #[cfg(feature = "client")]
     #[error("http error: {0}")]
     Reqwest(#[from] reqwest::Error),
     /// OpenAI returns error object with details of API call failure
@@ -21,10 +23,91 @@ pub enum OpenAIError {
     /// Error on SSE streaming
     #[error("stream failed: {0}")]
     StreamError(String),
+    /// The connection of a stream dropped, or it ended before the API finished, rather than
+    /// the API answering with an error
+    #[error("stream disconnected: {0}")]
+    StreamDisconnected(String),
     /// Error from client side validation
     /// or when builder fails to build request before making API call
     #[error("invalid args: {0}")]
     InvalidArgument(String),
+    /// Request uses parameters or content the selected model does not support,
+    /// see [crate::capabilities::ModelRegistry]
+    #[error("request not supported by model {model}: {}", violations.join("; "))]
+    UnsupportedByModel {
+        model: String,
+        violations: Vec<String>,
+    },
+    /// The request did not fit in the model's context window (`context_length_exceeded`)
+    #[error("{0}")]
+    ContextLengthExceeded(ApiError),
+    /// The flex service tier had no capacity for the request (`resource_unavailable`). These
+    /// are not retried with the client's backoff, see [crate::flex::FlexPolicy]
+    #[error("{0}")]
+    ResourceUnavailable(ApiError),
+    /// The account ran out of credits or reached its spending limit (`insufficient_quota`).
+    /// Retrying doesn't help until the plan or billing details are updated, so these are
+    /// never retried, see [OpenAIError::is_permanent]
+    #[error("{0}")]
+    QuotaExceeded(Box<QuotaError>),
+    /// Embeddings inputs were longer than the token limit checked by
+    /// [crate::embedding::EmbeddingPreflight], before the request was sent
+    #[error("embeddings inputs at indices {indices:?} exceed {max_tokens} tokens")]
+    EmbeddingInputTooLong {
+        max_tokens: usize,
+        /// Indices of the offending inputs
+        indices: Vec<usize>,
+    },
+    /// The client was shut down with [crate::Client::shutdown], either before the request
+    /// was made or while it was in flight
+    #[error("client has been shut down")]
+    ClientShutdown,
+    /// User-supplied content was blocked by an [crate::scanning::InputScanner] before the
+    /// request was sent
+    #[error("input rejected by scanner: {0}")]
+    InputRejected(Box<crate::scanning::ScanReport>),
+    /// A stream ran longer than allowed by [crate::stream::StreamDeadlineExt::deadline]
+    /// and was aborted
+    #[error("stream aborted after exceeding its {limit:?} deadline")]
+    StreamDeadlineExceeded {
+        limit: std::time::Duration,
+        /// Content received before the deadline
+        partial_content: String,
+    },
+    /// A response could not be parsed as the structured output that was asked for
+    #[error("failed to parse structured output: {0}")]
+    StructuredOutput(Box<crate::types::structured::ParseError>),
+    /// The API reported an error in an event of a stream that had already started, instead of
+    /// with an HTTP error status. `error` is mapped like errors of non-streaming requests
+    #[error("stream ended with an error: {error}")]
+    StreamApiError {
+        error: Box<OpenAIError>,
+        /// Usage reported with the error, if any
+        usage: Option<Box<crate::types::CompletionUsage>>,
+    },
+}
+
+impl OpenAIError {
+    /// Whether sending the same request again is bound to fail the same way, e.g. when the
+    /// quota is exhausted, the request is invalid, the API key is rejected or the model
+    /// doesn't exist. Rate limits, unavailable capacity, server and network errors are not
+    /// permanent.
+    ///
+    /// The client's backoff retries rate limited requests only when this is `false`.
+    pub fn is_permanent(&self) -> bool {
+        match self {
+            OpenAIError::QuotaExceeded(_)
+            | OpenAIError::InvalidArgument(_)
+            | OpenAIError::UnsupportedByModel { .. }
+            | OpenAIError::ContextLengthExceeded(_)
+            | OpenAIError::EmbeddingInputTooLong { .. }
+            | OpenAIError::ClientShutdown
+            | OpenAIError::InputRejected(_) => true,
+            OpenAIError::ApiError(error) => error.is_permanent(),
+            OpenAIError::StreamApiError { error, .. } => error.is_permanent(),
+            _ => false,
+        }
+    }
 }
 
 /// OpenAI API returns error object on failure
@@ -36,6 +119,35 @@ pub struct ApiError {
     pub code: Option<String>,
 }
 
+impl ApiError {
+    /// Whether the error is about the request itself or the credentials, by its type
+    /// (`invalid_request_error`, `authentication_error`, ...) or its code (`invalid_api_key`,
+    /// `model_not_found`, ...), rather than the load of the API. Rate limits never are,
+    /// whatever their type.
+    fn is_permanent(&self) -> bool {
+        if self.code.as_deref() == Some("rate_limit_exceeded") {
+            return false;
+        }
+        matches!(
+            self.r#type.as_deref(),
+            Some(
+                "invalid_request_error"
+                    | "authentication_error"
+                    | "permission_error"
+                    | "not_found_error"
+            )
+        ) || matches!(
+            self.code.as_deref(),
+            Some(
+                "invalid_api_key"
+                    | "invalid_organization"
+                    | "model_not_found"
+                    | "unsupported_country_region_territory"
+            )
+        )
+    }
+}
+
 impl std::fmt::Display for ApiError {
     /// If all fields are available, `ApiError` is formatted as:
     /// `{type}: {message} (param: {param}) (code: {code})`
@@ -61,12 +173,141 @@ impl std::fmt::Display for ApiError {
     }
 }
 
+/// An `insufficient_quota` error, with the plan and billing hints found in it.
+#[derive(Debug, Clone)]
+pub struct QuotaError {
+    pub error: ApiError,
+    /// Plan of the account, for providers that report it in a `plan` or `plan_type` field
+    pub plan: Option<String>,
+    /// Where to update the billing details: the `billing_url` field when the provider sends
+    /// one, the first link of the message otherwise
+    pub billing_url: Option<String>,
+}
+
+impl QuotaError {
+    /// Hints of `error`, read from the fields of the error object `details` the typed
+    /// [ApiError] doesn't have, and from the message.
+    #[cfg(feature = "client")]
+    pub(crate) fn new(error: ApiError, details: Option<&serde_json::Value>) -> Self {
+        let field = |name: &str| {
+            details
+                .and_then(|details| details.get(name))
+                .and_then(serde_json::Value::as_str)
+                .map(str::to_string)
+        };
+        let plan = field("plan").or_else(|| field("plan_type"));
+        let billing_url = field("billing_url").or_else(|| {
+            let start = error.message.find("https://")?;
+            let url = error.message[start..]
+                .split(char::is_whitespace)
+                .next()?
+                .trim_end_matches(['.', ',', ')']);
+            Some(url.to_string())
+        });
+        Self {
+            error,
+            plan,
+            billing_url,
+        }
+    }
+}
+
+impl std::fmt::Display for QuotaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.error)?;
+        if let Some(plan) = &self.plan {
+            write!(f, " (plan: {plan})")?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether `error` reports an exhausted quota rather than a rate limit, both sent with 429.
+#[cfg(feature = "client")]
+pub(crate) fn is_insufficient_quota(error: &ApiError) -> bool {
+    error.r#type.as_deref() == Some("insufficient_quota")
+        || error.code.as_deref() == Some("insufficient_quota")
+}
+
 /// Wrapper to deserialize the error object nested in "error" JSON key
+#[cfg(feature = "client")]
 #[derive(Debug, Deserialize)]
 pub(crate) struct WrappedError {
     pub(crate) error: ApiError,
 }
 
+/// Maps an error object returned by the API to the most specific [OpenAIError] variant.
+/// `details` is the untyped error object, for fields [ApiError] doesn't have.
+#[cfg(feature = "client")]
+pub(crate) fn map_api_error(error: ApiError, details: Option<&serde_json::Value>) -> OpenAIError {
+    if is_insufficient_quota(&error) {
+        return OpenAIError::QuotaExceeded(Box::new(QuotaError::new(error, details)));
+    }
+    match error.code.as_deref() {
+        Some("context_length_exceeded") => OpenAIError::ContextLengthExceeded(error),
+        Some("resource_unavailable") => OpenAIError::ResourceUnavailable(error),
+        _ => OpenAIError::ApiError(error),
+    }
+}
+
+/// Recognizes an error sent as an SSE event, either an `error` event or a payload with an
+/// `error` object, as some providers do after the response status has been sent
+#[cfg(feature = "client")]
+pub(crate) fn map_stream_error_event(event: &str, data: &str) -> Option<OpenAIError> {
+    if event != "error" && !data.contains("\"error\"") {
+        return None;
+    }
+
+    let payload: serde_json::Value = serde_json::from_str(data).ok()?;
+    let error = match payload.get("error") {
+        Some(error) if !error.is_null() => error,
+        _ if event == "error" => &payload,
+        _ => return None,
+    };
+
+    // Providers are loose about the types, e.g. numeric codes
+    let field = |name: &str| match error.get(name)? {
+        serde_json::Value::String(value) => Some(value.clone()),
+        serde_json::Value::Null => None,
+        value => Some(value.to_string()),
+    };
+    let error = match error {
+        serde_json::Value::String(message) => ApiError {
+            message: message.clone(),
+            r#type: None,
+            param: None,
+            code: None,
+        },
+        _ => ApiError {
+            message: field("message").unwrap_or_else(|| error.to_string()),
+            r#type: field("type"),
+            param: field("param"),
+            code: field("code"),
+        },
+    };
+
+    Some(OpenAIError::StreamApiError {
+        error: Box::new(map_api_error(error, payload.get("error"))),
+        usage: payload
+            .get("usage")
+            .and_then(|usage| serde_json::from_value(usage.clone()).ok())
+            .map(Box::new),
+    })
+}
+
+/// Tells a dropped connection apart from a response the stream could not start with, e.g.
+/// an error status or a content type other than `text/event-stream`
+#[cfg(feature = "client")]
+pub(crate) fn map_event_source_error(e: reqwest_eventsource::Error) -> OpenAIError {
+    match e {
+        reqwest_eventsource::Error::Transport(_) | reqwest_eventsource::Error::StreamEnded => {
+            OpenAIError::StreamDisconnected(e.to_string())
+        }
+        e => OpenAIError::StreamError(e.to_string()),
+    }
+}
+
+#[cfg(feature = "client")]
 pub(crate) fn map_deserialization_error(e: serde_json::Error, bytes: &[u8]) -> OpenAIError {
     tracing::error!(
         "failed deserialization of: {}",
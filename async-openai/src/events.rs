@@ -0,0 +1,252 @@
+//! Lifecycle events of a [crate::Client] and all of its clones.
+//!
+//! Subscribe with [crate::Client::events] to observe requests, retries, rate limits and
+//! streams in one place, e.g. for metrics or a status indicator:
+//!
+//! ```no_run
+//! # async fn example() {
+//! use async_openai::{events::ClientEvent, Client};
+//!
+//! let client = Client::new().with_token_budget_thresholds([100_000, 500_000]);
+//! let mut events = client.events().subscribe();
+//!
+//! tokio::spawn(async move {
+//!     while let Some(event) = events.recv().await {
+//!         if let ClientEvent::BudgetThresholdCrossed { threshold, .. } = event {
+//!             println!("used more than {threshold} tokens");
+//!         }
+//!     }
+//! });
+//! # }
+//! ```
+//!
+//! Events are broadcast: every subscriber receives every event emitted after it subscribed.
+//! A subscriber that falls more than [CAPACITY] events behind misses the oldest ones.
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use futures::Stream;
+use serde::Deserialize;
+use tokio::sync::broadcast;
+
+use crate::error::OpenAIError;
+#[cfg(feature = "audio")]
+use crate::speech_cache::SpeechCacheKey;
+
+/// Number of events buffered for each subscriber.
+pub const CAPACITY: usize = 256;
+
+/// Event emitted by a client. `id` identifies the request or stream an event belongs to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClientEvent {
+    /// A request is about to be sent for the first time.
+    RequestStarted {
+        id: u64,
+        method: String,
+        path: String,
+    },
+    /// A request completed, after any retries. `error` is set when it failed.
+    RequestFinished {
+        id: u64,
+        path: String,
+        duration: Duration,
+        error: Option<String>,
+    },
+    /// A failed attempt will be retried after `delay`. `retry` starts at 1.
+    RetryScheduled {
+        id: u64,
+        retry: u32,
+        delay: Duration,
+        error: String,
+    },
+    /// The API responded with a rate limit error (HTTP 429).
+    RateLimited { id: u64, message: String },
+    /// A streaming request was opened.
+    StreamOpened { id: u64, path: String },
+    /// A stream dropped before it was complete and its request is sent again, see
+    /// [crate::Chat::create_stream_with_tool_retry]. `retry` starts at 1.
+    StreamRetried {
+        path: String,
+        retry: u32,
+        error: String,
+    },
+    /// A stream ended, was dropped or was aborted. `error` is the last error it yielded.
+    StreamClosed {
+        id: u64,
+        path: String,
+        duration: Duration,
+        error: Option<String>,
+    },
+    /// The tokens reported in the usage of responses reached a threshold set with
+    /// [crate::Client::with_token_budget_thresholds]. Emitted once per threshold.
+    BudgetThresholdCrossed { threshold: u64, total_tokens: u64 },
+    /// The audio of a speech request was found in the speech cache, see
+    /// [crate::speech_cache]. No request is sent.
+    #[cfg(feature = "audio")]
+    SpeechCacheHit { key: SpeechCacheKey },
+    /// The audio of a speech request was not found in the speech cache and is synthesized.
+    #[cfg(feature = "audio")]
+    SpeechCacheMiss { key: SpeechCacheKey },
+}
+
+/// Event hub shared by a client and its clones, see [crate::Client::events].
+#[derive(Debug)]
+pub struct Events {
+    sender: broadcast::Sender<ClientEvent>,
+    next_id: AtomicU64,
+    total_tokens: AtomicU64,
+    /// Thresholds not crossed yet, in increasing order
+    thresholds: Mutex<Vec<u64>>,
+}
+
+impl Default for Events {
+    fn default() -> Self {
+        Self {
+            sender: broadcast::channel(CAPACITY).0,
+            next_id: AtomicU64::new(1),
+            total_tokens: AtomicU64::new(0),
+            thresholds: Mutex::new(vec![]),
+        }
+    }
+}
+
+impl Events {
+    /// Receive the events emitted from now on.
+    pub fn subscribe(&self) -> EventSubscription {
+        EventSubscription {
+            receiver: self.sender.subscribe(),
+        }
+    }
+
+    pub(crate) fn set_thresholds(&self, thresholds: impl IntoIterator<Item = u64>) {
+        let mut thresholds: Vec<u64> = thresholds.into_iter().collect();
+        thresholds.sort_unstable();
+        thresholds.dedup();
+        *self.thresholds.lock().unwrap() = thresholds;
+    }
+
+    pub(crate) fn next_id(&self) -> u64 {
+        self.next_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Emit the event made by `event`, which is only called when there are subscribers.
+    pub(crate) fn emit(&self, event: impl FnOnce() -> ClientEvent) {
+        if self.sender.receiver_count() > 0 {
+            let _ = self.sender.send(event());
+        }
+    }
+
+    /// Add the usage of a response `body` to the total, emitting an event for every
+    /// threshold crossed.
+    pub(crate) fn record_usage(&self, body: &[u8]) {
+        #[derive(Deserialize)]
+        struct Usage {
+            total_tokens: u64,
+        }
+        #[derive(Deserialize)]
+        struct Body {
+            usage: Option<Usage>,
+        }
+
+        let mut thresholds = self.thresholds.lock().unwrap();
+        if thresholds.is_empty() {
+            return;
+        }
+        let Ok(Body {
+            usage: Some(Usage { total_tokens }),
+        }) = serde_json::from_slice(body)
+        else {
+            return;
+        };
+
+        let total_tokens =
+            self.total_tokens.fetch_add(total_tokens, Ordering::Relaxed) + total_tokens;
+        let crossed = thresholds.partition_point(|&threshold| threshold <= total_tokens);
+        for threshold in thresholds.drain(..crossed) {
+            tracing::warn!("token budget threshold {threshold} crossed: {total_tokens} tokens");
+            self.emit(|| ClientEvent::BudgetThresholdCrossed {
+                threshold,
+                total_tokens,
+            });
+        }
+    }
+}
+
+/// Receiver of [ClientEvent]s, see [Events::subscribe].
+#[derive(Debug)]
+pub struct EventSubscription {
+    receiver: broadcast::Receiver<ClientEvent>,
+}
+
+impl EventSubscription {
+    /// The next event, or `None` once the client and all of its clones are dropped.
+    /// Events missed by a lagging subscriber are skipped.
+    pub async fn recv(&mut self) -> Option<ClientEvent> {
+        loop {
+            match self.receiver.recv().await {
+                Ok(event) => return Some(event),
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    tracing::warn!("event subscriber lagged, {missed} events missed");
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+
+    /// The subscription as a stream of events.
+    pub fn into_stream(self) -> impl Stream<Item = ClientEvent> {
+        futures::stream::unfold(self, |mut subscription| async move {
+            let event = subscription.recv().await?;
+            Some((event, subscription))
+        })
+    }
+}
+
+/// Emits [ClientEvent::StreamClosed] when the task forwarding a stream ends.
+pub(crate) struct StreamEvents {
+    events: Arc<Events>,
+    id: u64,
+    path: String,
+    opened: Instant,
+    error: Option<String>,
+}
+
+impl StreamEvents {
+    pub(crate) fn open(events: &Arc<Events>, url: &str) -> Self {
+        let path =
+            reqwest::Url::parse(url).map_or_else(|_| url.to_string(), |url| url.path().to_string());
+        let id = events.next_id();
+        events.emit(|| ClientEvent::StreamOpened {
+            id,
+            path: path.clone(),
+        });
+        Self {
+            events: events.clone(),
+            id,
+            path,
+            opened: Instant::now(),
+            error: None,
+        }
+    }
+
+    /// Record an error yielded by the stream.
+    pub(crate) fn failed(&mut self, error: &OpenAIError) {
+        self.error = Some(error.to_string());
+    }
+}
+
+impl Drop for StreamEvents {
+    fn drop(&mut self) {
+        self.events.emit(|| ClientEvent::StreamClosed {
+            id: self.id,
+            path: std::mem::take(&mut self.path),
+            duration: self.opened.elapsed(),
+            error: self.error.take(),
+        });
+    }
+}
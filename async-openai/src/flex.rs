@@ -0,0 +1,99 @@
+//! Opt-in recovery from [OpenAIError::ResourceUnavailable] for chat completions using the
+//! flex service tier.
+//!
+//! Flex processing is cheaper but may have no capacity for a while. The client's backoff is
+//! tuned for rate limits, so these errors are returned right away instead; a [FlexPolicy]
+//! retries them with a longer backoff and can fall back to the default tier, so that batch
+//! workloads degrade to slower (or more expensive) processing instead of failing.
+use std::time::Duration;
+
+use crate::{
+    config::Config,
+    error::OpenAIError,
+    types::{CreateChatCompletionRequest, CreateChatCompletionResponse, ServiceTier},
+    Client,
+};
+
+/// Policy applied when a chat completion fails with [OpenAIError::ResourceUnavailable].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlexPolicy {
+    max_retries: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    fallback_to_default: bool,
+}
+
+impl Default for FlexPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_secs(10),
+            max_backoff: Duration::from_secs(120),
+            fallback_to_default: false,
+        }
+    }
+}
+
+impl FlexPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Maximum number of retries on the flex tier. Default is 3.
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Delay before the first retry, doubled on every following retry. Default is 10 seconds.
+    pub fn initial_backoff(mut self, initial_backoff: Duration) -> Self {
+        self.initial_backoff = initial_backoff;
+        self
+    }
+
+    /// Upper bound of the delay between retries. Default is 2 minutes.
+    pub fn max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// Once the retries are used up, send the request once more with the default service
+    /// tier instead of returning the error. Default is `false`.
+    pub fn fallback_to_default(mut self, fallback_to_default: bool) -> Self {
+        self.fallback_to_default = fallback_to_default;
+        self
+    }
+
+    /// Delay before retry number `retry`, starting at 0.
+    fn backoff(&self, retry: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_backoff)
+    }
+
+    /// Send `request`, retrying whenever the API responds with
+    /// [OpenAIError::ResourceUnavailable].
+    pub(crate) async fn create<C: Config>(
+        &self,
+        client: &Client<C>,
+        mut request: CreateChatCompletionRequest,
+    ) -> Result<CreateChatCompletionResponse, OpenAIError> {
+        let mut retries = 0;
+        loop {
+            match client.chat().create(request.clone()).await {
+                Err(OpenAIError::ResourceUnavailable(error)) if retries < self.max_retries => {
+                    let delay = self.backoff(retries);
+                    tracing::warn!("flex tier unavailable, retrying in {delay:?}: {error}");
+                    tokio::time::sleep(delay).await;
+                    retries += 1;
+                }
+                Err(OpenAIError::ResourceUnavailable(error)) if self.fallback_to_default => {
+                    tracing::warn!("flex tier unavailable, falling back to default tier: {error}");
+                    request.service_tier = Some(ServiceTier::Default);
+                    return client.chat().create(request).await;
+                }
+                result => return result,
+            }
+        }
+    }
+}
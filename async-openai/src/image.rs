@@ -4,6 +4,7 @@ use crate::{
     types::{
         CreateImageEditRequest, CreateImageRequest, CreateImageVariationRequest, ImagesResponse,
     },
+    util::prepare_raw_request,
     Client,
 };
 
@@ -25,6 +26,16 @@ impl<'c, C: Config> Images<'c, C> {
         self.client.post("/images/generations", request).await
     }
 
+    /// Same as [Images::create], with the request and response as untyped JSON, so that
+    /// parameters the typed request doesn't know about yet can be sent.
+    pub async fn create_raw(
+        &self,
+        mut request: serde_json::Value,
+    ) -> Result<serde_json::Value, OpenAIError> {
+        prepare_raw_request(&mut request, None)?;
+        self.client.post("/images/generations", request).await
+    }
+
     /// Creates an edited or extended image given an original image and a prompt.
     #[crate::byot(
         T0 = Clone,
@@ -0,0 +1,479 @@
+//! Track long-running jobs across process restarts.
+//!
+//! Batches, fine-tuning jobs, background responses and uploads can take minutes to days to
+//! finish. A [JobManager] records submitted jobs in a serializable [JobState] so that, after a
+//! restart, polling resumes from the persisted state instead of losing track of them.
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), async_openai::error::OpenAIError> {
+//! use async_openai::{jobs::JobManager, types::BatchRequest, Client};
+//!
+//! let client = Client::new();
+//! // Loads previously tracked jobs if the file exists, and saves after every change
+//! let jobs = JobManager::open(client.clone(), "jobs.json").await?;
+//!
+//! # let request = BatchRequest::default();
+//! let batch = client.batches().create(request).await?;
+//! jobs.track_batch(&batch).await?;
+//!
+//! for job in jobs.wait_all().await? {
+//!     println!("{} {:?} {:?}", job.id, job.status, job.result);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::Config,
+    error::OpenAIError,
+    types::{Batch, BatchStatus, FineTuningJob, FineTuningJobStatus, Upload, UploadStatus},
+    Client,
+};
+
+/// Kind of operation a [TrackedJob] refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobKind {
+    Batch,
+    FineTuning,
+    /// A response created with `background: true`.
+    Response,
+    Upload,
+}
+
+/// Status of a job, normalized across job kinds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    /// Submitted, not started yet.
+    Pending,
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+    Expired,
+}
+
+impl JobStatus {
+    /// Whether the job will not change status anymore.
+    pub fn is_terminal(&self) -> bool {
+        !matches!(self, JobStatus::Pending | JobStatus::Running)
+    }
+}
+
+impl From<&BatchStatus> for JobStatus {
+    fn from(status: &BatchStatus) -> Self {
+        match status {
+            BatchStatus::Validating => JobStatus::Pending,
+            BatchStatus::InProgress | BatchStatus::Finalizing | BatchStatus::Cancelling => {
+                JobStatus::Running
+            }
+            BatchStatus::Completed => JobStatus::Succeeded,
+            BatchStatus::Failed => JobStatus::Failed,
+            BatchStatus::Cancelled => JobStatus::Cancelled,
+            BatchStatus::Expired => JobStatus::Expired,
+        }
+    }
+}
+
+impl From<&FineTuningJobStatus> for JobStatus {
+    fn from(status: &FineTuningJobStatus) -> Self {
+        match status {
+            FineTuningJobStatus::ValidatingFiles | FineTuningJobStatus::Queued => {
+                JobStatus::Pending
+            }
+            FineTuningJobStatus::Running => JobStatus::Running,
+            FineTuningJobStatus::Succeeded => JobStatus::Succeeded,
+            FineTuningJobStatus::Failed => JobStatus::Failed,
+            FineTuningJobStatus::Cancelled => JobStatus::Cancelled,
+        }
+    }
+}
+
+impl From<&UploadStatus> for JobStatus {
+    fn from(status: &UploadStatus) -> Self {
+        match status {
+            UploadStatus::Pending => JobStatus::Running,
+            UploadStatus::Completed => JobStatus::Succeeded,
+            UploadStatus::Cancelled => JobStatus::Cancelled,
+            UploadStatus::Expired => JobStatus::Expired,
+        }
+    }
+}
+
+/// A job known to a [JobManager].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrackedJob {
+    pub id: String,
+    pub kind: JobKind,
+    pub status: JobStatus,
+    /// Unix timestamp (in seconds) of when the job was first tracked.
+    pub tracked_at: u64,
+    /// Unix timestamp (in seconds) of the last status update.
+    pub updated_at: u64,
+    /// Output of a successful job: the output file of a batch, the fine-tuned model or the
+    /// file created by an upload.
+    pub result: Option<String>,
+    /// Error message of a failed job.
+    pub error: Option<String>,
+    /// Part ids added to an upload so far, so that it can be completed after a restart.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub upload_parts: Vec<String>,
+    /// Application defined labels, e.g. what to do with the result.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+}
+
+impl TrackedJob {
+    fn new(kind: JobKind, id: String) -> Self {
+        let now = unix_now();
+        Self {
+            id,
+            kind,
+            status: JobStatus::Pending,
+            tracked_at: now,
+            updated_at: now,
+            result: None,
+            error: None,
+            upload_parts: vec![],
+            labels: BTreeMap::new(),
+        }
+    }
+}
+
+/// Serializable state of a [JobManager].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct JobState {
+    pub jobs: Vec<TrackedJob>,
+}
+
+/// Tracks long-running jobs and polls them until they finish.
+///
+/// Cloning a manager shares the tracked jobs.
+#[derive(Debug, Clone)]
+pub struct JobManager<C: Config> {
+    client: Client<C>,
+    jobs: Arc<Mutex<BTreeMap<String, TrackedJob>>>,
+    state_file: Option<PathBuf>,
+    /// Held while the state file is written, so that an older state never replaces a newer one
+    persisting: Arc<tokio::sync::Mutex<()>>,
+    poll_interval: Duration,
+}
+
+impl<C: Config> JobManager<C> {
+    /// A manager keeping its state in memory only, see [JobManager::state].
+    pub fn new(client: Client<C>) -> Self {
+        Self::from_state(client, JobState::default())
+    }
+
+    /// Resume tracking the jobs of a previously saved [JobState].
+    pub fn from_state(client: Client<C>, state: JobState) -> Self {
+        Self {
+            client,
+            jobs: Arc::new(Mutex::new(
+                state
+                    .jobs
+                    .into_iter()
+                    .map(|job| (job.id.clone(), job))
+                    .collect(),
+            )),
+            state_file: None,
+            persisting: Arc::default(),
+            poll_interval: Duration::from_secs(30),
+        }
+    }
+
+    /// A manager persisting its state as JSON in `path` after every change. Jobs already
+    /// saved in `path` are loaded.
+    pub async fn open<P: AsRef<Path>>(client: Client<C>, path: P) -> Result<Self, OpenAIError> {
+        let path = path.as_ref().to_path_buf();
+        let state = match tokio::fs::read(&path).await {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map_err(|e| OpenAIError::FileReadError(format!("{}: {e}", path.display())))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => JobState::default(),
+            Err(e) => {
+                return Err(OpenAIError::FileReadError(format!(
+                    "{}: {e}",
+                    path.display()
+                )))
+            }
+        };
+
+        let mut manager = Self::from_state(client, state);
+        manager.state_file = Some(path);
+        Ok(manager)
+    }
+
+    /// Time between two polls in [JobManager::wait] and [JobManager::wait_all]. Default is
+    /// 30 seconds.
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Snapshot of all tracked jobs.
+    pub fn state(&self) -> JobState {
+        JobState {
+            jobs: self.jobs.lock().unwrap().values().cloned().collect(),
+        }
+    }
+
+    /// A tracked job by id.
+    pub fn get(&self, id: &str) -> Option<TrackedJob> {
+        self.jobs.lock().unwrap().get(id).cloned()
+    }
+
+    /// Jobs that are not finished yet.
+    pub fn pending(&self) -> Vec<TrackedJob> {
+        self.jobs
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|job| !job.status.is_terminal())
+            .cloned()
+            .collect()
+    }
+
+    /// Start tracking a job by id, e.g. a background response. Tracking a known id keeps
+    /// its current state.
+    pub async fn track(&self, kind: JobKind, id: &str) -> Result<TrackedJob, OpenAIError> {
+        self.upsert(kind, id, |_| {}).await
+    }
+
+    /// Start tracking a batch, or update it from a freshly retrieved copy.
+    pub async fn track_batch(&self, batch: &Batch) -> Result<TrackedJob, OpenAIError> {
+        self.upsert(JobKind::Batch, &batch.id, |job| apply_batch(job, batch))
+            .await
+    }
+
+    /// Start tracking a fine-tuning job, or update it from a freshly retrieved copy.
+    pub async fn track_fine_tuning(
+        &self,
+        fine_tuning_job: &FineTuningJob,
+    ) -> Result<TrackedJob, OpenAIError> {
+        self.upsert(JobKind::FineTuning, &fine_tuning_job.id, |job| {
+            apply_fine_tuning(job, fine_tuning_job)
+        })
+        .await
+    }
+
+    /// Start tracking an upload, or update it after it was completed or cancelled.
+    pub async fn track_upload(&self, upload: &Upload) -> Result<TrackedJob, OpenAIError> {
+        self.upsert(JobKind::Upload, &upload.id, |job| {
+            job.status = (&upload.status).into();
+            job.result = upload.file.as_ref().map(|file| file.id.clone());
+            job.updated_at = unix_now();
+        })
+        .await
+    }
+
+    /// Record a part added to a tracked upload.
+    pub async fn add_upload_part(
+        &self,
+        upload_id: &str,
+        part_id: &str,
+    ) -> Result<TrackedJob, OpenAIError> {
+        self.update_existing(upload_id, |job| {
+            job.upload_parts.push(part_id.into());
+            job.updated_at = unix_now();
+        })
+        .await
+    }
+
+    /// Attach an application defined label to a tracked job.
+    pub async fn label(&self, id: &str, key: &str, value: &str) -> Result<TrackedJob, OpenAIError> {
+        self.update_existing(id, |job| {
+            job.labels.insert(key.into(), value.into());
+        })
+        .await
+    }
+
+    /// Stop tracking a job.
+    pub async fn forget(&self, id: &str) -> Result<Option<TrackedJob>, OpenAIError> {
+        let removed = self.jobs.lock().unwrap().remove(id);
+        if removed.is_some() {
+            self.persist().await?;
+        }
+        Ok(removed)
+    }
+
+    /// Retrieve the current status of a tracked job from the API.
+    ///
+    /// Uploads have no retrieve endpoint; their state only changes with
+    /// [JobManager::track_upload].
+    pub async fn poll(&self, id: &str) -> Result<TrackedJob, OpenAIError> {
+        let job = self.get(id).ok_or_else(|| unknown_job(id))?;
+        if job.status.is_terminal() {
+            return Ok(job);
+        }
+
+        match job.kind {
+            JobKind::Batch => {
+                let batch = self.client.batches().retrieve(id).await?;
+                self.track_batch(&batch).await
+            }
+            JobKind::FineTuning => {
+                let fine_tuning_job = self.client.fine_tuning().retrieve(id).await?;
+                self.track_fine_tuning(&fine_tuning_job).await
+            }
+            JobKind::Response => {
+                let response: serde_json::Value =
+                    self.client.get(&format!("/responses/{id}")).await?;
+                self.update_existing(id, |job| apply_response(job, &response))
+                    .await
+            }
+            JobKind::Upload => Ok(job),
+        }
+    }
+
+    /// Poll every unfinished job once.
+    pub async fn poll_pending(&self) -> Result<Vec<TrackedJob>, OpenAIError> {
+        let mut polled = vec![];
+        for job in self.pending() {
+            polled.push(self.poll(&job.id).await?);
+        }
+        Ok(polled)
+    }
+
+    /// Poll a job until it finishes.
+    pub async fn wait(&self, id: &str) -> Result<TrackedJob, OpenAIError> {
+        loop {
+            let job = self.poll(id).await?;
+            if job.status.is_terminal() || job.kind == JobKind::Upload {
+                return Ok(job);
+            }
+            tokio::time::sleep(self.poll_interval).await;
+        }
+    }
+
+    /// Poll all unfinished jobs, except uploads, until they finish, and return every
+    /// tracked job.
+    pub async fn wait_all(&self) -> Result<Vec<TrackedJob>, OpenAIError> {
+        loop {
+            self.poll_pending().await?;
+            if self.pending().iter().all(|job| job.kind == JobKind::Upload) {
+                return Ok(self.state().jobs);
+            }
+            tokio::time::sleep(self.poll_interval).await;
+        }
+    }
+
+    async fn upsert<F>(&self, kind: JobKind, id: &str, f: F) -> Result<TrackedJob, OpenAIError>
+    where
+        F: FnOnce(&mut TrackedJob),
+    {
+        let job = {
+            let mut jobs = self.jobs.lock().unwrap();
+            let job = jobs
+                .entry(id.into())
+                .or_insert_with(|| TrackedJob::new(kind, id.into()));
+            f(job);
+            job.clone()
+        };
+        self.persist().await?;
+        Ok(job)
+    }
+
+    async fn update_existing<F>(&self, id: &str, f: F) -> Result<TrackedJob, OpenAIError>
+    where
+        F: FnOnce(&mut TrackedJob),
+    {
+        let job = {
+            let mut jobs = self.jobs.lock().unwrap();
+            let job = jobs.get_mut(id).ok_or_else(|| unknown_job(id))?;
+            f(job);
+            job.clone()
+        };
+        self.persist().await?;
+        Ok(job)
+    }
+
+    /// Write the state to the state file, through a temporary file so that a crash never
+    /// leaves a truncated file behind.
+    async fn persist(&self) -> Result<(), OpenAIError> {
+        let Some(path) = &self.state_file else {
+            return Ok(());
+        };
+
+        // Temporary files are unique to each write, in case other managers share the path
+        static WRITES: AtomicU64 = AtomicU64::new(0);
+
+        let _persisting = self.persisting.lock().await;
+        let json = serde_json::to_vec_pretty(&self.state())
+            .map_err(|e| OpenAIError::InvalidArgument(e.to_string()))?;
+        let mut temporary = path.clone().into_os_string();
+        temporary.push(format!(
+            ".{}.{}.tmp",
+            std::process::id(),
+            WRITES.fetch_add(1, Ordering::Relaxed)
+        ));
+        let write_error =
+            |e: std::io::Error| OpenAIError::FileSaveError(format!("{}: {e}", path.display()));
+
+        tokio::fs::write(&temporary, json)
+            .await
+            .map_err(write_error)?;
+        tokio::fs::rename(&temporary, path)
+            .await
+            .map_err(write_error)
+    }
+}
+
+fn apply_batch(job: &mut TrackedJob, batch: &Batch) {
+    job.status = (&batch.status).into();
+    job.result = batch.output_file_id.clone();
+    job.error = batch.errors.as_ref().and_then(|errors| {
+        errors
+            .data
+            .first()
+            .map(|error| format!("{}: {}", error.code, error.message))
+    });
+    job.updated_at = unix_now();
+}
+
+fn apply_fine_tuning(job: &mut TrackedJob, fine_tuning_job: &FineTuningJob) {
+    job.status = (&fine_tuning_job.status).into();
+    job.result = fine_tuning_job.fine_tuned_model.clone();
+    job.error = fine_tuning_job
+        .error
+        .as_ref()
+        .map(|error| format!("{}: {}", error.code, error.message));
+    job.updated_at = unix_now();
+}
+
+fn apply_response(job: &mut TrackedJob, response: &serde_json::Value) {
+    job.status = match response["status"].as_str() {
+        Some("queued") => JobStatus::Pending,
+        Some("completed") => JobStatus::Succeeded,
+        Some("failed") | Some("incomplete") => JobStatus::Failed,
+        Some("cancelled") => JobStatus::Cancelled,
+        _ => JobStatus::Running,
+    };
+    job.error = response["error"]["message"]
+        .as_str()
+        .or(response["incomplete_details"]["reason"].as_str())
+        .map(Into::into);
+    job.updated_at = unix_now();
+}
+
+fn unknown_job(id: &str) -> OpenAIError {
+    OpenAIError::InvalidArgument(format!("job {id} is not tracked"))
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
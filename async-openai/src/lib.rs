@@ -94,6 +94,44 @@
 //! # });
 //!```
 //!
+//! ## Types only
+//!
+//! Servers and proxies implementing OpenAI compatible APIs can reuse the request, response and
+//! structured output types without the HTTP client (reqwest, tokio) by disabling default features:
+//!
+//! ```toml
+//! async-openai = { version = "*", default-features = false }
+//! ```
+//!
+//! The client and API groups are enabled with the `client` feature, which every TLS feature enables.
+//!
+//! ## API groups
+//!
+//! The endpoint groups `chat`, `embeddings`, `audio`, `images`, `assistants` and `admin` are
+//! features enabled by default. Deployments that only use some of them can compile just those,
+//! for smaller binaries and shorter builds:
+//!
+//! ```toml
+//! async-openai = { version = "*", default-features = false, features = ["rustls", "chat", "embeddings"] }
+//! ```
+//!
+//! Models, files, uploads, batches, fine-tuning, moderations and legacy completions come with
+//! the client. Request and response types of every group are always compiled.
+//!
+//! ## Blocking client
+//!
+//! CLI tools and build scripts that don't want async can enable the `blocking` feature and use
+//! [blocking::Client](crate::blocking), which drives a runtime internally and returns plain
+//! iterators for streaming responses.
+//!
+//! ## HTTP/3
+//!
+//! The experimental `http3` feature sends requests over QUIC to servers advertising HTTP/3 in
+//! `Alt-Svc`, falling back to HTTP/2 or HTTP/1.1 when an HTTP/3 attempt fails. It relies on
+//! reqwest's unstable HTTP/3 support and must be built with `RUSTFLAGS="--cfg reqwest_unstable"`.
+//! quinn, which provides QUIC, needs Rust 1.85, newer than the 1.75 the rest of the crate
+//! supports.
+//!
 //! ## Microsoft Azure
 //!
 //! ```
@@ -118,71 +156,198 @@
 //!
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
-#[cfg(feature = "byot")]
+#[cfg(all(feature = "client", feature = "byot"))]
 pub(crate) use async_openai_macros::byot;
 
-#[cfg(not(feature = "byot"))]
+#[cfg(all(feature = "client", not(feature = "byot")))]
 pub(crate) use async_openai_macros::byot_passthrough as byot;
 
+#[cfg_attr(docsrs, doc(cfg(feature = "assistants")))]
+#[cfg(feature = "assistants")]
 pub mod assistants;
+#[cfg_attr(docsrs, doc(cfg(feature = "audio")))]
+#[cfg(feature = "audio")]
 pub mod audio;
+#[cfg_attr(docsrs, doc(cfg(feature = "admin")))]
+#[cfg(feature = "admin")]
 pub mod audit_logs;
+#[cfg_attr(docsrs, doc(cfg(feature = "chat")))]
+#[cfg(feature = "chat")]
+pub mod audit_trail;
+#[cfg(feature = "client")]
 pub mod batches;
+#[cfg_attr(docsrs, doc(cfg(feature = "chat")))]
+#[cfg(feature = "chat")]
+pub mod best_of;
+#[cfg_attr(docsrs, doc(cfg(feature = "blocking")))]
+#[cfg(feature = "blocking")]
+pub mod blocking;
+#[cfg(feature = "client")]
+pub mod canonical;
+pub mod capabilities;
+#[cfg_attr(docsrs, doc(cfg(feature = "chat")))]
+#[cfg(feature = "chat")]
 pub mod chat;
+#[cfg(feature = "client")]
 pub mod client;
+#[cfg(feature = "client")]
 pub mod completion;
+#[cfg(feature = "client")]
 pub mod config;
+#[cfg_attr(docsrs, doc(cfg(feature = "chat")))]
+#[cfg(feature = "chat")]
+pub mod context_length;
+pub mod degradation;
+#[cfg(feature = "client")]
 pub mod download;
+#[cfg_attr(docsrs, doc(cfg(feature = "embeddings")))]
+#[cfg(feature = "embeddings")]
 pub mod embedding;
 pub mod error;
+#[cfg(feature = "client")]
+pub mod events;
+#[cfg(feature = "client")]
 pub mod file;
+#[cfg(feature = "client")]
 pub mod fine_tuning;
+#[cfg_attr(docsrs, doc(cfg(feature = "chat")))]
+#[cfg(feature = "chat")]
+pub mod flex;
+#[cfg(feature = "http3")]
+mod http3;
+#[cfg_attr(docsrs, doc(cfg(feature = "images")))]
+#[cfg(feature = "images")]
 pub mod image;
+#[cfg_attr(docsrs, doc(cfg(feature = "admin")))]
+#[cfg(feature = "admin")]
 pub mod invites;
+#[cfg(feature = "client")]
+pub mod jobs;
+#[cfg_attr(docsrs, doc(cfg(feature = "chat")))]
+#[cfg(feature = "chat")]
+pub mod memory;
+#[cfg_attr(docsrs, doc(cfg(feature = "assistants")))]
+#[cfg(feature = "assistants")]
 pub mod messages;
+#[cfg(feature = "client")]
 pub mod model;
+#[cfg(feature = "client")]
 pub mod moderation;
+#[cfg(feature = "client")]
+pub mod profile;
+#[cfg_attr(docsrs, doc(cfg(feature = "admin")))]
+#[cfg(feature = "admin")]
 pub mod project_api_keys;
+#[cfg_attr(docsrs, doc(cfg(feature = "admin")))]
+#[cfg(feature = "admin")]
 pub mod project_service_accounts;
+#[cfg_attr(docsrs, doc(cfg(feature = "admin")))]
+#[cfg(feature = "admin")]
 pub mod project_users;
+#[cfg_attr(docsrs, doc(cfg(feature = "admin")))]
+#[cfg(feature = "admin")]
 pub mod projects;
+#[cfg_attr(docsrs, doc(cfg(feature = "realtime")))]
+#[cfg(feature = "realtime")]
+pub mod realtime;
+pub mod reproducibility;
+#[cfg_attr(docsrs, doc(cfg(feature = "chat")))]
+#[cfg(feature = "chat")]
+pub mod response_cache;
+#[cfg_attr(docsrs, doc(cfg(feature = "assistants")))]
+#[cfg(feature = "assistants")]
 pub mod runs;
+pub mod scanning;
+#[cfg(feature = "client")]
+pub mod shutdown;
+#[cfg_attr(docsrs, doc(cfg(feature = "audio")))]
+#[cfg(feature = "audio")]
+pub mod speech_cache;
+#[cfg_attr(docsrs, doc(cfg(feature = "assistants")))]
+#[cfg(feature = "assistants")]
 pub mod steps;
+pub mod stream;
 pub mod structured;
+pub mod template;
+#[cfg_attr(docsrs, doc(cfg(feature = "assistants")))]
+#[cfg(feature = "assistants")]
 pub mod threads;
+#[cfg(feature = "client")]
+pub mod tls;
+pub mod tokens;
 pub mod traits;
 pub mod types;
+#[cfg(feature = "client")]
 pub mod uploads;
+#[cfg_attr(docsrs, doc(cfg(feature = "admin")))]
+#[cfg(feature = "admin")]
 pub mod users;
+#[cfg(feature = "client")]
 pub mod util;
+#[cfg_attr(docsrs, doc(cfg(feature = "assistants")))]
+#[cfg(feature = "assistants")]
 pub mod vector_store_file_batches;
+#[cfg_attr(docsrs, doc(cfg(feature = "assistants")))]
+#[cfg(feature = "assistants")]
 pub mod vector_store_files;
+#[cfg_attr(docsrs, doc(cfg(feature = "assistants")))]
+#[cfg(feature = "assistants")]
 pub mod vector_stores;
+#[cfg_attr(docsrs, doc(cfg(all(feature = "audio", feature = "chat"))))]
+#[cfg(all(feature = "audio", feature = "chat"))]
+pub mod voice;
 
+#[cfg(feature = "assistants")]
 pub use assistants::Assistants;
+#[cfg(feature = "audio")]
 pub use audio::Audio;
+#[cfg(feature = "admin")]
 pub use audit_logs::AuditLogs;
+#[cfg(feature = "client")]
 pub use batches::Batches;
+#[cfg(feature = "chat")]
 pub use chat::Chat;
+#[cfg(feature = "client")]
 pub use client::Client;
+#[cfg(feature = "client")]
 pub use completion::Completions;
+#[cfg(feature = "embeddings")]
 pub use embedding::Embeddings;
+#[cfg(feature = "client")]
 pub use file::Files;
+#[cfg(feature = "client")]
 pub use fine_tuning::FineTuning;
+#[cfg(feature = "images")]
 pub use image::Images;
+#[cfg(feature = "admin")]
 pub use invites::Invites;
+#[cfg(feature = "assistants")]
 pub use messages::Messages;
+#[cfg(feature = "client")]
 pub use model::Models;
+#[cfg(feature = "client")]
 pub use moderation::Moderations;
+#[cfg(feature = "admin")]
 pub use project_api_keys::ProjectAPIKeys;
+#[cfg(feature = "admin")]
 pub use project_service_accounts::ProjectServiceAccounts;
+#[cfg(feature = "admin")]
 pub use project_users::ProjectUsers;
+#[cfg(feature = "admin")]
 pub use projects::Projects;
+#[cfg(feature = "assistants")]
 pub use runs::Runs;
+#[cfg(feature = "assistants")]
 pub use steps::Steps;
+#[cfg(feature = "assistants")]
 pub use threads::Threads;
+#[cfg(feature = "client")]
 pub use uploads::Uploads;
+#[cfg(feature = "admin")]
 pub use users::Users;
+#[cfg(feature = "assistants")]
 pub use vector_store_file_batches::VectorStoreFileBatches;
+#[cfg(feature = "assistants")]
 pub use vector_store_files::VectorStoreFiles;
+#[cfg(feature = "assistants")]
 pub use vector_stores::VectorStores;
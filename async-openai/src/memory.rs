@@ -0,0 +1,361 @@
+//! Compression of the history of long-running conversations.
+//!
+//! A [MemoryStrategy] is applied to the conversation before every request, and evicts the
+//! oldest turns once the conversation outgrows a token budget: [SlidingWindow] drops them,
+//! [RollingSummary] folds them into a summary written by a (cheap) model and [EntityMemory]
+//! keeps what they say about the people, places and things mentioned. System and developer
+//! messages and the most recent message are never evicted.
+//!
+//! Unlike a [crate::context_length::ContextLengthPolicy], which only acts once a request
+//! failed, a strategy keeps every request under budget.
+//!
+//! ```no_run
+//! use async_openai::{memory::RollingSummary, voice::VoicePipeline, Client};
+//!
+//! let client = Client::new();
+//! let assistant = VoicePipeline::new(&client)
+//!     .with_memory(RollingSummary::new(client.clone(), 4000).model("gpt-4o-mini"));
+//! ```
+use std::{collections::BTreeMap, future::Future, pin::Pin};
+
+use crate::{
+    config::Config,
+    context_length::{role, summarize},
+    error::OpenAIError,
+    tokens::Tokenizer,
+    types::{
+        ChatCompletionRequestMessage, ChatCompletionRequestSystemMessage,
+        ChatCompletionRequestUserMessage, CreateChatCompletionRequest,
+    },
+    Client,
+};
+
+/// Future returned by [MemoryStrategy::compact].
+pub type MemoryFuture<'a> = Pin<Box<dyn Future<Output = Result<(), OpenAIError>> + Send + 'a>>;
+
+/// Tokens counted per message on top of its text, for the role and separators.
+const MESSAGE_OVERHEAD: usize = 4;
+
+/// Start of the system message holding the summary of a [RollingSummary].
+const SUMMARY_HEADER: &str = "Summary of the earlier conversation:";
+
+/// Start of the system message holding the entities of an [EntityMemory].
+const ENTITIES_HEADER: &str = "Known facts about entities of the earlier conversation:";
+
+/// Keeps a conversation within a budget by rewriting its history.
+pub trait MemoryStrategy: Send {
+    /// Shrink `messages` before they are sent, if they are over budget.
+    fn compact<'a>(
+        &'a mut self,
+        messages: &'a mut Vec<ChatCompletionRequestMessage>,
+    ) -> MemoryFuture<'a>;
+}
+
+/// Token budget of a conversation and how its tokens are counted.
+#[derive(Debug, Clone)]
+struct Budget {
+    max_tokens: usize,
+    tokenizer_model: String,
+}
+
+impl Budget {
+    fn new(max_tokens: usize) -> Self {
+        Self {
+            max_tokens,
+            tokenizer_model: "gpt-4o".into(),
+        }
+    }
+
+    fn tokens(&self, messages: &[ChatCompletionRequestMessage]) -> usize {
+        let tokenizer = Tokenizer::for_model(&self.tokenizer_model);
+        messages
+            .iter()
+            .map(|message| tokenizer.count(&message.text()) + MESSAGE_OVERHEAD)
+            .sum()
+    }
+
+    /// Remove the oldest turns until `messages` and `reserved` more tokens fit the budget,
+    /// returning them with their original position. Tool results go with the assistant
+    /// message they answer.
+    fn evict(
+        &self,
+        messages: &mut Vec<ChatCompletionRequestMessage>,
+        reserved: usize,
+    ) -> Vec<(usize, ChatCompletionRequestMessage)> {
+        let mut evicted = vec![];
+        let mut index = 0;
+
+        // The most recent message is never dropped
+        while self.tokens(messages) + reserved > self.max_tokens && index + 1 < messages.len() {
+            if is_protected(&messages[index]) {
+                index += 1;
+                continue;
+            }
+
+            let position = index + evicted.len();
+            evicted.push((position, messages.remove(index)));
+            while index + 1 < messages.len()
+                && matches!(
+                    messages[index],
+                    ChatCompletionRequestMessage::Tool(_)
+                        | ChatCompletionRequestMessage::Function(_)
+                )
+            {
+                let position = index + evicted.len();
+                evicted.push((position, messages.remove(index)));
+            }
+        }
+
+        evicted
+    }
+}
+
+fn is_protected(message: &ChatCompletionRequestMessage) -> bool {
+    matches!(
+        message,
+        ChatCompletionRequestMessage::System(_) | ChatCompletionRequestMessage::Developer(_)
+    )
+}
+
+/// Replace the system message starting with `header` by `text`, or insert it after the
+/// leading system and developer messages.
+fn set_note(messages: &mut Vec<ChatCompletionRequestMessage>, header: &str, text: String) {
+    let note = ChatCompletionRequestSystemMessage::from(format!("{header}\n{text}")).into();
+    match messages.iter().position(|message| is_note(message, header)) {
+        Some(index) => messages[index] = note,
+        None => {
+            let index = messages
+                .iter()
+                .position(|message| !is_protected(message))
+                .unwrap_or(messages.len());
+            messages.insert(index, note);
+        }
+    }
+}
+
+fn is_note(message: &ChatCompletionRequestMessage, header: &str) -> bool {
+    matches!(message, ChatCompletionRequestMessage::System(_)) && message.text().starts_with(header)
+}
+
+/// Drops the oldest turns once the conversation is over budget.
+#[derive(Debug, Clone)]
+pub struct SlidingWindow {
+    budget: Budget,
+}
+
+impl SlidingWindow {
+    /// Keep the conversation within `max_tokens`.
+    pub fn new(max_tokens: usize) -> Self {
+        Self {
+            budget: Budget::new(max_tokens),
+        }
+    }
+
+    /// Model whose tokenizer counts the tokens of the conversation. Default is `gpt-4o`.
+    pub fn tokenizer_model(mut self, model: impl Into<String>) -> Self {
+        self.budget.tokenizer_model = model.into();
+        self
+    }
+}
+
+impl MemoryStrategy for SlidingWindow {
+    fn compact<'a>(
+        &'a mut self,
+        messages: &'a mut Vec<ChatCompletionRequestMessage>,
+    ) -> MemoryFuture<'a> {
+        self.budget.evict(messages, 0);
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// Replaces the oldest turns with a summary once the conversation is over budget. The
+/// summary is a system message after the instructions, and is summarized again together with
+/// the turns evicted next.
+///
+/// Summaries are written by `gpt-4o-mini` unless configured otherwise.
+pub struct RollingSummary<C: Config> {
+    client: Client<C>,
+    model: String,
+    budget: Budget,
+    reserved: usize,
+}
+
+impl<C: Config> RollingSummary<C> {
+    /// Keep the conversation within `max_tokens`, a quarter of which is kept for the summary.
+    pub fn new(client: Client<C>, max_tokens: usize) -> Self {
+        Self {
+            client,
+            model: "gpt-4o-mini".into(),
+            budget: Budget::new(max_tokens),
+            reserved: max_tokens / 4,
+        }
+    }
+
+    /// Model writing the summaries.
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+
+    /// Model whose tokenizer counts the tokens of the conversation. Default is `gpt-4o`.
+    pub fn tokenizer_model(mut self, model: impl Into<String>) -> Self {
+        self.budget.tokenizer_model = model.into();
+        self
+    }
+
+    /// Tokens of the budget kept free for the summary.
+    pub fn reserved_tokens(mut self, tokens: usize) -> Self {
+        self.reserved = tokens;
+        self
+    }
+}
+
+impl<C: Config + Send + Sync> MemoryStrategy for RollingSummary<C> {
+    fn compact<'a>(
+        &'a mut self,
+        messages: &'a mut Vec<ChatCompletionRequestMessage>,
+    ) -> MemoryFuture<'a> {
+        Box::pin(async move {
+            if self.budget.tokens(messages) <= self.budget.max_tokens {
+                return Ok(());
+            }
+            let previous = messages
+                .iter()
+                .position(|message| is_note(message, SUMMARY_HEADER))
+                .map(|index| messages.remove(index));
+
+            let mut evicted = self.budget.evict(messages, self.reserved);
+            if let Some(previous) = previous {
+                evicted.insert(0, (0, previous));
+            }
+            if evicted.is_empty() {
+                return Ok(());
+            }
+
+            let summary = summarize(&self.client, &self.model, &evicted).await?;
+            set_note(messages, SUMMARY_HEADER, summary);
+            Ok(())
+        })
+    }
+}
+
+/// Keeps what the oldest turns say about the people, places, organizations and other
+/// entities they mention once the conversation is over budget, and drops the turns. The facts
+/// are a system message after the instructions, updated with every eviction.
+///
+/// Facts are extracted by `gpt-4o-mini` unless configured otherwise.
+pub struct EntityMemory<C: Config> {
+    client: Client<C>,
+    model: String,
+    budget: Budget,
+    reserved: usize,
+    entities: BTreeMap<String, String>,
+}
+
+impl<C: Config> EntityMemory<C> {
+    /// Keep the conversation within `max_tokens`, a quarter of which is kept for the facts.
+    pub fn new(client: Client<C>, max_tokens: usize) -> Self {
+        Self {
+            client,
+            model: "gpt-4o-mini".into(),
+            budget: Budget::new(max_tokens),
+            reserved: max_tokens / 4,
+            entities: BTreeMap::new(),
+        }
+    }
+
+    /// Model extracting the facts.
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+
+    /// Model whose tokenizer counts the tokens of the conversation. Default is `gpt-4o`.
+    pub fn tokenizer_model(mut self, model: impl Into<String>) -> Self {
+        self.budget.tokenizer_model = model.into();
+        self
+    }
+
+    /// Tokens of the budget kept free for the facts.
+    pub fn reserved_tokens(mut self, tokens: usize) -> Self {
+        self.reserved = tokens;
+        self
+    }
+
+    /// What is known about each entity, by name.
+    pub fn entities(&self) -> &BTreeMap<String, String> {
+        &self.entities
+    }
+
+    /// Ask the model to update the known facts with the `evicted` turns.
+    async fn update(
+        &mut self,
+        evicted: &[(usize, ChatCompletionRequestMessage)],
+    ) -> Result<(), OpenAIError> {
+        let transcript = evicted
+            .iter()
+            .map(|(_, message)| format!("{}: {}", role(message), message.text()))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let known = serde_json::to_string(&self.entities).unwrap_or_default();
+
+        let request = CreateChatCompletionRequest {
+            model: self.model.clone(),
+            messages: vec![ChatCompletionRequestUserMessage::from(format!(
+                "Update the known facts with the people, places, organizations and other \
+                 entities of the following conversation. Answer with a JSON object mapping each \
+                 name to one or two sentences of what is known about it.\n\n\
+                 Known facts: {known}\n\nConversation:\n{transcript}"
+            ))
+            .into()],
+            ..Default::default()
+        };
+        let response = self.client.chat().create(request).await?;
+        let content = response
+            .choices
+            .into_iter()
+            .next()
+            .and_then(|choice| choice.message.content)
+            .unwrap_or_default();
+
+        // The object may be fenced or surrounded by text
+        let object = match (content.find('{'), content.rfind('}')) {
+            (Some(start), Some(end)) if start < end => &content[start..=end],
+            _ => content.as_str(),
+        };
+        match serde_json::from_str::<BTreeMap<String, String>>(object) {
+            Ok(entities) => self.entities.extend(entities),
+            Err(e) => {
+                tracing::warn!("entity memory: unreadable facts, keeping the known ones: {e}")
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<C: Config + Send + Sync> MemoryStrategy for EntityMemory<C> {
+    fn compact<'a>(
+        &'a mut self,
+        messages: &'a mut Vec<ChatCompletionRequestMessage>,
+    ) -> MemoryFuture<'a> {
+        Box::pin(async move {
+            if self.budget.tokens(messages) <= self.budget.max_tokens {
+                return Ok(());
+            }
+            let evicted = self.budget.evict(messages, self.reserved);
+            if evicted.is_empty() {
+                return Ok(());
+            }
+
+            self.update(&evicted).await?;
+            let facts = self
+                .entities
+                .iter()
+                .map(|(name, fact)| format!("- {name}: {fact}"))
+                .collect::<Vec<_>>()
+                .join("\n");
+            set_note(messages, ENTITIES_HEADER, facts);
+            Ok(())
+        })
+    }
+}
@@ -1,10 +1,18 @@
+use futures::{StreamExt, TryStreamExt};
+
 use crate::{
     config::Config,
     error::OpenAIError,
-    types::{CreateModerationRequest, CreateModerationResponse},
+    types::{
+        ContentModerationResult, CreateModerationRequest, CreateModerationResponse, ModerationInput,
+    },
+    util::prepare_raw_request,
     Client,
 };
 
+/// Texts sent per request by [Moderations::create_bulk].
+pub const MODERATION_BATCH_SIZE: usize = 32;
+
 /// Given text and/or image inputs, classifies if those inputs are potentially harmful across several categories.
 ///
 /// Related guide: [Moderations](https://platform.openai.com/docs/guides/moderation)
@@ -26,4 +34,60 @@ impl<'c, C: Config> Moderations<'c, C> {
     ) -> Result<CreateModerationResponse, OpenAIError> {
         self.client.post("/moderations", request).await
     }
+
+    /// Classify any number of texts, [MODERATION_BATCH_SIZE] per request with at most
+    /// `concurrency` requests in flight, returning one result per text in the order of
+    /// `texts`.
+    ///
+    /// Rate limited requests are retried with the client's backoff while the other requests
+    /// wait for a slot, so a large backlog slows down instead of failing. The first request
+    /// that still fails fails the whole call and no further requests are sent.
+    pub async fn create_bulk<I>(
+        &self,
+        texts: I,
+        concurrency: usize,
+    ) -> Result<Vec<ContentModerationResult>, OpenAIError>
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let texts: Vec<String> = texts.into_iter().map(Into::into).collect();
+        let batches: Vec<Vec<String>> = texts
+            .chunks(MODERATION_BATCH_SIZE)
+            .map(<[String]>::to_vec)
+            .collect();
+
+        let responses: Vec<Vec<ContentModerationResult>> = futures::stream::iter(batches)
+            .map(|batch| async move {
+                let inputs = batch.len();
+                let request = CreateModerationRequest {
+                    input: ModerationInput::StringArray(batch),
+                    model: None,
+                };
+                let response = self.create(request).await?;
+                if response.results.len() != inputs {
+                    return Err(OpenAIError::InvalidArgument(format!(
+                        "moderation returned {} results for {} inputs",
+                        response.results.len(),
+                        inputs
+                    )));
+                }
+                Ok(response.results)
+            })
+            .buffered(concurrency.max(1))
+            .try_collect()
+            .await?;
+
+        Ok(responses.into_iter().flatten().collect())
+    }
+
+    /// Same as [Moderations::create], with the request and response as untyped JSON, so that
+    /// parameters the typed request doesn't know about yet can be sent.
+    pub async fn create_raw(
+        &self,
+        mut request: serde_json::Value,
+    ) -> Result<serde_json::Value, OpenAIError> {
+        prepare_raw_request(&mut request, None)?;
+        self.client.post("/moderations", request).await
+    }
 }
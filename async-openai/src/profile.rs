@@ -0,0 +1,329 @@
+//! Named connection profiles, so that switching between production, staging or a local
+//! OpenAI compatible server is a matter of environment, not code.
+//!
+//! The profile named by `OPENAI_PROFILE` (`default` when unset) is read from environment
+//! variables prefixed with `OPENAI_PROFILE_<NAME>_`, the name uppercased and other characters
+//! than letters and digits replaced by `_`:
+//!
+//! | Variable | Field |
+//! |----------|-------|
+//! | `OPENAI_PROFILE_<NAME>_PROVIDER` | [EnvProfile::provider], `openai` or `azure` |
+//! | `OPENAI_PROFILE_<NAME>_API_BASE` | [EnvProfile::api_base] |
+//! | `OPENAI_PROFILE_<NAME>_API_KEY` | [EnvProfile::api_key], `OPENAI_API_KEY` when unset |
+//! | `OPENAI_PROFILE_<NAME>_ORG_ID` | [EnvProfile::org_id] |
+//! | `OPENAI_PROFILE_<NAME>_PROJECT_ID` | [EnvProfile::project_id] |
+//! | `OPENAI_PROFILE_<NAME>_API_VERSION` | [EnvProfile::api_version], Azure only |
+//! | `OPENAI_PROFILE_<NAME>_DEPLOYMENT_ID` | [EnvProfile::deployment_id], Azure only |
+//! | `OPENAI_PROFILE_<NAME>_MODEL` | [EnvProfile::default_model] |
+//!
+//! With the `toml` feature, profiles can also be kept in a file with a table per profile,
+//! environment variables taking precedence over its values:
+//!
+//! ```toml
+//! [staging]
+//! api_base = "https://staging.example.com/v1"
+//! default_model = "gpt-4o-mini"
+//!
+//! [local]
+//! api_base = "http://localhost:8080/v1"
+//! api_key = "none"
+//! default_model = "llama-3.1-8b-instruct"
+//! # Servers rejecting the Assistants beta header
+//! beta_header = false
+//! headers = { "X-Tenant" = "dev" }
+//! ```
+//!
+//! ```no_run
+//! use async_openai::profile::EnvProfile;
+//!
+//! // OPENAI_PROFILE=local
+//! let profile = EnvProfile::from_env().unwrap();
+//! let client = profile.client();
+//! let model = profile.default_model().unwrap_or("gpt-4o-mini");
+//! ```
+use std::collections::BTreeMap;
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+
+use crate::{
+    config::{AzureConfig, Config, OpenAIConfig, OPENAI_BETA_HEADER},
+    error::OpenAIError,
+    Client,
+};
+
+/// Name of the environment variable selecting the profile.
+pub const PROFILE_ENV: &str = "OPENAI_PROFILE";
+
+/// Profile used when [PROFILE_ENV] is unset.
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// API flavor of a profile.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+    /// OpenAI or an OpenAI compatible server, see [OpenAIConfig]
+    #[default]
+    OpenAI,
+    /// Azure OpenAI Service, see [AzureConfig]
+    Azure,
+}
+
+/// A named set of connection settings, see [crate::profile].
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct EnvProfile {
+    /// Name of the profile
+    #[serde(skip)]
+    pub name: String,
+    pub provider: Provider,
+    /// API base url, the default of the provider when `None`
+    pub api_base: Option<String>,
+    /// API key, `OPENAI_API_KEY` when `None`
+    pub api_key: Option<SecretString>,
+    pub org_id: Option<String>,
+    pub project_id: Option<String>,
+    /// API version of Azure
+    pub api_version: Option<String>,
+    /// Deployment of Azure
+    pub deployment_id: Option<String>,
+    /// Model for requests of the application that don't pick one
+    pub default_model: Option<String>,
+    /// Whether the Assistants beta header is sent, which some compatible servers reject
+    pub beta_header: bool,
+    /// Headers added to every request, e.g. for gateways
+    pub headers: BTreeMap<String, String>,
+}
+
+impl Default for EnvProfile {
+    fn default() -> Self {
+        Self {
+            name: DEFAULT_PROFILE.to_string(),
+            provider: Provider::default(),
+            api_base: None,
+            api_key: None,
+            org_id: None,
+            project_id: None,
+            api_version: None,
+            deployment_id: None,
+            default_model: None,
+            beta_header: true,
+            headers: BTreeMap::new(),
+        }
+    }
+}
+
+impl EnvProfile {
+    /// The profile named by [PROFILE_ENV], read from environment variables.
+    pub fn from_env() -> Result<Self, OpenAIError> {
+        Self::from_env_named(&selected_name())
+    }
+
+    /// The profile `name`, read from environment variables.
+    pub fn from_env_named(name: &str) -> Result<Self, OpenAIError> {
+        let mut profile = Self {
+            name: name.to_string(),
+            ..Default::default()
+        };
+        profile.apply_env()?;
+        Ok(profile)
+    }
+
+    /// The profile named by [PROFILE_ENV], read from the TOML file at `path` and then from
+    /// environment variables.
+    #[cfg(feature = "toml")]
+    pub fn from_toml_file(path: impl AsRef<std::path::Path>) -> Result<Self, OpenAIError> {
+        let text = std::fs::read_to_string(path.as_ref())
+            .map_err(|e| OpenAIError::FileReadError(format!("{}: {e}", path.as_ref().display())))?;
+        let mut profile = Self::from_toml_str(&text, &selected_name())?;
+        profile.apply_env()?;
+        Ok(profile)
+    }
+
+    /// The profile `name` of the TOML document `text`, without environment variables.
+    /// Fails if the document has no table for it.
+    #[cfg(feature = "toml")]
+    pub fn from_toml_str(text: &str, name: &str) -> Result<Self, OpenAIError> {
+        let mut profiles: BTreeMap<String, EnvProfile> = toml::from_str(text)
+            .map_err(|e| OpenAIError::InvalidArgument(format!("invalid profiles: {e}")))?;
+        let mut profile = profiles
+            .remove(name)
+            .ok_or_else(|| OpenAIError::InvalidArgument(format!("profile `{name}` not found")))?;
+        profile.name = name.to_string();
+        Ok(profile)
+    }
+
+    /// Override the fields set by environment variables of the profile.
+    fn apply_env(&mut self) -> Result<(), OpenAIError> {
+        let prefix = env_prefix(&self.name);
+        let var = |field: &str| std::env::var(format!("{prefix}{field}")).ok();
+
+        if let Some(provider) = var("PROVIDER") {
+            self.provider = match provider.to_ascii_lowercase().as_str() {
+                "openai" => Provider::OpenAI,
+                "azure" => Provider::Azure,
+                _ => {
+                    return Err(OpenAIError::InvalidArgument(format!(
+                        "unknown provider `{provider}` in {prefix}PROVIDER"
+                    )))
+                }
+            };
+        }
+        let fields = [
+            ("API_BASE", &mut self.api_base),
+            ("ORG_ID", &mut self.org_id),
+            ("PROJECT_ID", &mut self.project_id),
+            ("API_VERSION", &mut self.api_version),
+            ("DEPLOYMENT_ID", &mut self.deployment_id),
+            ("MODEL", &mut self.default_model),
+        ];
+        for (field, value) in fields {
+            if let Some(var) = var(field) {
+                *value = Some(var);
+            }
+        }
+        if let Some(api_key) = var("API_KEY") {
+            self.api_key = Some(api_key.into());
+        }
+        Ok(())
+    }
+
+    /// Model for requests that don't pick one.
+    pub fn default_model(&self) -> Option<&str> {
+        self.default_model.as_deref()
+    }
+
+    /// Configuration of the provider of the profile.
+    pub fn config(&self) -> ProfileConfig {
+        let inner = match self.provider {
+            Provider::OpenAI => {
+                let mut config = OpenAIConfig::new();
+                if let Some(api_base) = &self.api_base {
+                    config = config.with_api_base(api_base);
+                }
+                if let Some(api_key) = &self.api_key {
+                    config = config.with_api_key(api_key.expose_secret());
+                }
+                if let Some(org_id) = &self.org_id {
+                    config = config.with_org_id(org_id);
+                }
+                if let Some(project_id) = &self.project_id {
+                    config = config.with_project_id(project_id);
+                }
+                ProviderConfig::OpenAI(config)
+            }
+            Provider::Azure => {
+                let mut config = AzureConfig::new();
+                if let Some(api_base) = &self.api_base {
+                    config = config.with_api_base(api_base);
+                }
+                if let Some(api_key) = &self.api_key {
+                    config = config.with_api_key(api_key.expose_secret());
+                }
+                if let Some(api_version) = &self.api_version {
+                    config = config.with_api_version(api_version);
+                }
+                if let Some(deployment_id) = &self.deployment_id {
+                    config = config.with_deployment_id(deployment_id);
+                }
+                ProviderConfig::Azure(config)
+            }
+        };
+
+        ProfileConfig {
+            inner,
+            beta_header: self.beta_header,
+            headers: self.headers.clone(),
+        }
+    }
+
+    /// Client with the configuration of the profile.
+    pub fn client(&self) -> Client<ProfileConfig> {
+        Client::with_config(self.config())
+    }
+}
+
+/// Name of the profile selected by [PROFILE_ENV].
+fn selected_name() -> String {
+    std::env::var(PROFILE_ENV)
+        .ok()
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| DEFAULT_PROFILE.to_string())
+}
+
+/// `OPENAI_PROFILE_<NAME>_`
+fn env_prefix(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{PROFILE_ENV}_{name}_")
+}
+
+#[derive(Debug, Clone)]
+enum ProviderConfig {
+    OpenAI(OpenAIConfig),
+    Azure(AzureConfig),
+}
+
+/// Configuration built from an [EnvProfile], for whichever provider it names.
+#[derive(Debug, Clone)]
+pub struct ProfileConfig {
+    inner: ProviderConfig,
+    beta_header: bool,
+    headers: BTreeMap<String, String>,
+}
+
+/// Call `$method` on the configuration of the provider.
+macro_rules! delegate {
+    ($self:ident.$method:ident($($arg:expr),*)) => {
+        match &$self.inner {
+            ProviderConfig::OpenAI(config) => config.$method($($arg),*),
+            ProviderConfig::Azure(config) => config.$method($($arg),*),
+        }
+    };
+}
+
+impl Config for ProfileConfig {
+    fn headers(&self) -> HeaderMap {
+        let mut headers = delegate!(self.headers());
+        if !self.beta_header {
+            headers.remove(OPENAI_BETA_HEADER);
+        }
+        for (name, value) in &self.headers {
+            match (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(value),
+            ) {
+                (Ok(name), Ok(value)) => {
+                    headers.insert(name, value);
+                }
+                _ => tracing::warn!("invalid profile header `{name}` ignored"),
+            }
+        }
+        headers
+    }
+
+    fn url(&self, path: &str) -> String {
+        delegate!(self.url(path))
+    }
+
+    fn query(&self) -> Vec<(&str, &str)> {
+        delegate!(self.query())
+    }
+
+    fn api_base(&self) -> &str {
+        delegate!(self.api_base())
+    }
+
+    fn api_key(&self) -> &SecretString {
+        delegate!(self.api_key())
+    }
+}
@@ -0,0 +1,323 @@
+//! Helpers to drive a Realtime API session.
+//!
+//! [AudioInputSink] accepts raw audio as a [Sink] of [Bytes] and turns it into
+//! `input_audio_buffer.append` / `input_audio_buffer.commit` client events, so capture code
+//! never deals with base64, frame sizes or commit cadence. [AudioCallback] adapts the
+//! synchronous sample callbacks of audio libraries (e.g. cpal) to such a sink.
+//!
+//! ```no_run
+//! # use tokio_tungstenite::tungstenite::{Error, Message};
+//! # async fn run(ws_write: impl futures::Sink<Message, Error = Error> + Unpin) -> Result<(), Error> {
+//! use async_openai::realtime::{AudioCallback, AudioInputSink};
+//! use futures::StreamExt;
+//!
+//! // 48kHz stereo microphone, converted to 24kHz mono pcm16
+//! let (callback, audio) = AudioCallback::new(48_000, 2).expect("non-zero sample rate");
+//! // Move `callback` into the audio library's data callback and call `callback.push_f32(data)`
+//!
+//! let sink = AudioInputSink::new(ws_write);
+//! audio.map(Ok).forward(sink).await?;
+//! # Ok(())
+//! # }
+//! ```
+use std::{
+    collections::VecDeque,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use base64::{engine::general_purpose, Engine as _};
+use bytes::{Bytes, BytesMut};
+use futures::{
+    channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender},
+    Sink, SinkExt,
+};
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::{
+    error::OpenAIError,
+    types::realtime::{
+        AudioFormat, ClientEvent, InputAudioBufferAppendEvent, InputAudioBufferCommitEvent,
+    },
+};
+
+/// Sample rate of `pcm16` audio expected by the Realtime API.
+pub const PCM16_SAMPLE_RATE: u32 = 24_000;
+
+fn bytes_per_second(format: &AudioFormat) -> usize {
+    match format {
+        // 16 bit mono at 24kHz
+        AudioFormat::PCM16 => PCM16_SAMPLE_RATE as usize * 2,
+        // 8 bit mono at 8kHz
+        AudioFormat::G711ULAW | AudioFormat::G711ALAW => 8_000,
+    }
+}
+
+/// A [Sink] of raw audio bytes writing Realtime client events into an inner sink,
+/// typically the write half of the websocket.
+///
+/// Audio is buffered and sent in `input_audio_buffer.append` events of a fixed duration.
+/// When a commit interval is configured, `input_audio_buffer.commit` is sent every time that
+/// much audio has been appended; leave it unset when the session uses server VAD.
+/// Flushing only sends complete frames, so that `send` can be called with arbitrarily small
+/// chunks; closing sends the last partial frame and commits any uncommitted audio.
+pub struct AudioInputSink<S> {
+    inner: S,
+    buffer: BytesMut,
+    pending: VecDeque<ClientEvent>,
+    frame_bytes: usize,
+    commit_bytes: Option<usize>,
+    uncommitted: usize,
+    format: AudioFormat,
+}
+
+impl<S> std::fmt::Debug for AudioInputSink<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AudioInputSink")
+            .field("buffered", &self.buffer.len())
+            .field("pending", &self.pending.len())
+            .field("frame_bytes", &self.frame_bytes)
+            .field("commit_bytes", &self.commit_bytes)
+            .field("format", &self.format)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S> AudioInputSink<S> {
+    /// Sink for `pcm16` audio, sent in frames of 100ms, without manual commits.
+    pub fn new(inner: S) -> Self {
+        let mut sink = Self {
+            inner,
+            buffer: BytesMut::new(),
+            pending: VecDeque::new(),
+            frame_bytes: 0,
+            commit_bytes: None,
+            uncommitted: 0,
+            format: AudioFormat::PCM16,
+        };
+        sink.frame_bytes = sink.bytes_for(Duration::from_millis(100));
+        sink
+    }
+
+    /// Format of the incoming audio, must match `input_audio_format` of the session.
+    /// Default is `pcm16`. Resets the frame duration to 100ms.
+    pub fn format(mut self, format: AudioFormat) -> Self {
+        self.format = format;
+        self.frame_bytes = self.bytes_for(Duration::from_millis(100));
+        self
+    }
+
+    /// Duration of audio in each `input_audio_buffer.append` event.
+    pub fn frame_duration(mut self, duration: Duration) -> Self {
+        self.frame_bytes = self.bytes_for(duration);
+        self
+    }
+
+    /// Send `input_audio_buffer.commit` after every `interval` of audio.
+    pub fn commit_every(mut self, interval: Duration) -> Self {
+        self.commit_bytes = Some(self.bytes_for(interval));
+        self
+    }
+
+    /// The wrapped sink.
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    /// Consume the adapter, dropping audio that was not sent yet.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    fn bytes_for(&self, duration: Duration) -> usize {
+        let sample_bytes = match self.format {
+            AudioFormat::PCM16 => 2,
+            _ => 1,
+        };
+        let bytes = (bytes_per_second(&self.format) as u128 * duration.as_millis() / 1000) as usize;
+        // Whole samples only, and never an empty frame
+        (bytes - bytes % sample_bytes).max(sample_bytes)
+    }
+
+    fn queue_frames(&mut self, include_partial: bool) {
+        while self.buffer.len() >= self.frame_bytes || (include_partial && !self.buffer.is_empty())
+        {
+            let len = self.frame_bytes.min(self.buffer.len());
+            let frame = self.buffer.split_to(len);
+            self.pending.push_back(ClientEvent::InputAudioBufferAppend(
+                InputAudioBufferAppendEvent {
+                    event_id: None,
+                    audio: general_purpose::STANDARD.encode(&frame),
+                },
+            ));
+
+            self.uncommitted += len;
+            if let Some(commit_bytes) = self.commit_bytes {
+                if self.uncommitted >= commit_bytes {
+                    self.queue_commit();
+                }
+            }
+        }
+    }
+
+    fn queue_commit(&mut self) {
+        self.uncommitted = 0;
+        self.pending.push_back(ClientEvent::InputAudioBufferCommit(
+            InputAudioBufferCommitEvent { event_id: None },
+        ));
+    }
+}
+
+impl<S> AudioInputSink<S>
+where
+    S: Sink<Message> + Unpin,
+{
+    fn poll_send_pending(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        while !self.pending.is_empty() {
+            futures::ready!(self.inner.poll_ready_unpin(cx))?;
+            let event = self.pending.pop_front().expect("pending is not empty");
+            self.inner.start_send_unpin(Message::from(event))?;
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S> Sink<Bytes> for AudioInputSink<S>
+where
+    S: Sink<Message> + Unpin,
+{
+    type Error = S::Error;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.poll_send_pending(cx)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, item: Bytes) -> Result<(), Self::Error> {
+        self.buffer.extend_from_slice(&item);
+        self.queue_frames(false);
+        Ok(())
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        futures::ready!(self.poll_send_pending(cx))?;
+        self.inner.poll_flush_unpin(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.queue_frames(true);
+        if self.commit_bytes.is_some() && self.uncommitted > 0 {
+            self.queue_commit();
+        }
+        futures::ready!(self.poll_send_pending(cx))?;
+        self.inner.poll_close_unpin(cx)
+    }
+}
+
+/// Adapter from synchronous audio callbacks to a stream of 24kHz mono `pcm16` [Bytes],
+/// ready to be forwarded into an [AudioInputSink].
+///
+/// Samples are downmixed to mono and linearly resampled from the input sample rate.
+/// The callback never blocks on the receiving side. It does allocate the chunk it sends on
+/// every call, so audio threads with hard real-time constraints should hand their samples
+/// to another thread instead.
+#[derive(Debug)]
+pub struct AudioCallback {
+    sender: UnboundedSender<Bytes>,
+    channels: usize,
+    step: f64,
+    position: f64,
+    last: Option<f32>,
+    /// Downmixed samples of the current call, kept to reuse its allocation
+    mono: Vec<f32>,
+}
+
+impl AudioCallback {
+    /// Create the callback side for audio captured at `sample_rate` with interleaved
+    /// `channels`, and the stream of converted audio. Fails when `sample_rate` is 0.
+    pub fn new(
+        sample_rate: u32,
+        channels: u16,
+    ) -> Result<(Self, UnboundedReceiver<Bytes>), OpenAIError> {
+        if sample_rate == 0 {
+            return Err(OpenAIError::InvalidArgument(
+                "audio sample rate must be greater than 0".into(),
+            ));
+        }
+
+        let (sender, receiver) = unbounded();
+        Ok((
+            Self {
+                sender,
+                channels: channels.max(1) as usize,
+                step: sample_rate as f64 / PCM16_SAMPLE_RATE as f64,
+                position: 0.0,
+                last: None,
+                mono: Vec::new(),
+            },
+            receiver,
+        ))
+    }
+
+    /// Push interleaved `f32` samples in the range `-1.0..=1.0`.
+    pub fn push_f32(&mut self, data: &[f32]) {
+        self.push_frames(data, |sample| sample);
+    }
+
+    /// Push interleaved `i16` samples.
+    pub fn push_i16(&mut self, data: &[i16]) {
+        self.push_frames(data, |sample| sample as f32 / i16::MAX as f32);
+    }
+
+    /// Whether the receiving side is gone, in which case pushed audio is discarded.
+    pub fn is_closed(&self) -> bool {
+        self.sender.is_closed()
+    }
+
+    fn push_frames<S: Copy>(&mut self, data: &[S], to_f32: impl Fn(S) -> f32) {
+        let mut mono = std::mem::take(&mut self.mono);
+        mono.clear();
+        mono.extend(data.chunks(self.channels).map(|frame| {
+            frame.iter().map(|sample| to_f32(*sample)).sum::<f32>() / frame.len() as f32
+        }));
+        self.push_mono(&mono);
+        self.mono = mono;
+    }
+
+    fn push_mono(&mut self, samples: &[f32]) {
+        if samples.is_empty() {
+            return;
+        }
+
+        // `position` is relative to the first new sample; -1 refers to the last sample
+        // of the previous call, which is needed to interpolate across calls.
+        let sample_at = |index: isize| -> Option<f32> {
+            if index < 0 {
+                self.last.or(samples.first().copied())
+            } else {
+                samples.get(index as usize).copied()
+            }
+        };
+
+        let mut out = Vec::with_capacity((samples.len() as f64 / self.step) as usize * 2 + 2);
+        let mut position = self.position;
+        while position < (samples.len() - 1) as f64 + f64::EPSILON {
+            let index = position.floor() as isize;
+            let fraction = (position - index as f64) as f32;
+            let (Some(a), Some(b)) = (sample_at(index), sample_at(index + 1).or(sample_at(index)))
+            else {
+                break;
+            };
+            let sample = (a + (b - a) * fraction).clamp(-1.0, 1.0);
+            out.extend_from_slice(&((sample * i16::MAX as f32) as i16).to_le_bytes());
+            position += self.step;
+        }
+
+        self.position = position - samples.len() as f64;
+        self.last = samples.last().copied();
+
+        if !out.is_empty() {
+            let _ = self.sender.unbounded_send(Bytes::from(out));
+        }
+    }
+}
@@ -0,0 +1,203 @@
+//! Seed and `system_fingerprint` bookkeeping for reproducible outputs.
+//!
+//! Sampling with the same `seed` is only (mostly) deterministic while the backend
+//! configuration, identified by the `system_fingerprint` of responses, stays the same. A
+//! [ReproducibilityContext] stamps requests with one seed and records the fingerprints of the
+//! responses, warning when they drift.
+//!
+//! ```
+//! use async_openai::{
+//!     reproducibility::ReproducibilityContext,
+//!     types::{CreateChatCompletionRequestArgs, CreateChatCompletionResponse},
+//! };
+//!
+//! let context = ReproducibilityContext::with_seed(42);
+//!
+//! let mut request = CreateChatCompletionRequestArgs::default()
+//!     .model("gpt-4o-mini")
+//!     .messages(vec![])
+//!     .build()
+//!     .unwrap();
+//! context.stamp(&mut request);
+//! assert_eq!(request.seed, Some(42));
+//!
+//! # let response: CreateChatCompletionResponse = serde_json::from_value(serde_json::json!({
+//! #     "id": "chatcmpl-1", "object": "chat.completion", "created": 1, "model": "gpt-4o-mini",
+//! #     "system_fingerprint": "fp_44709d6fcb", "choices": []
+//! # })).unwrap();
+//! // let response = client.chat().create(request).await?;
+//! if let Some(drift) = context.record(&response) {
+//!     println!("results may differ: {} -> {}", drift.previous, drift.current);
+//! }
+//! ```
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::{
+    CreateChatCompletionRequest, CreateChatCompletionResponse, CreateChatCompletionStreamResponse,
+    CreateCompletionRequest, CreateCompletionResponse,
+};
+
+/// Request types accepting a `seed`.
+pub trait SeededRequest {
+    fn seed_mut(&mut self) -> &mut Option<i64>;
+}
+
+impl SeededRequest for CreateChatCompletionRequest {
+    fn seed_mut(&mut self) -> &mut Option<i64> {
+        &mut self.seed
+    }
+}
+
+impl SeededRequest for CreateCompletionRequest {
+    fn seed_mut(&mut self) -> &mut Option<i64> {
+        &mut self.seed
+    }
+}
+
+/// Response types reporting the backend configuration they were generated with.
+pub trait Fingerprinted {
+    fn id(&self) -> &str;
+    fn model(&self) -> &str;
+    fn system_fingerprint(&self) -> Option<&str>;
+}
+
+macro_rules! impl_fingerprinted {
+    ($($response:ty),*) => {
+        $(impl Fingerprinted for $response {
+            fn id(&self) -> &str {
+                &self.id
+            }
+
+            fn model(&self) -> &str {
+                &self.model
+            }
+
+            fn system_fingerprint(&self) -> Option<&str> {
+                self.system_fingerprint.as_deref()
+            }
+        })*
+    };
+}
+
+impl_fingerprinted!(
+    CreateChatCompletionResponse,
+    CreateChatCompletionStreamResponse,
+    CreateCompletionResponse
+);
+
+/// A response recorded by [ReproducibilityContext::record].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Observation {
+    pub response_id: String,
+    pub model: String,
+    pub system_fingerprint: Option<String>,
+}
+
+/// The `system_fingerprint` of a response differs from the one of an earlier response of the
+/// same model.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FingerprintDrift {
+    pub model: String,
+    pub previous: String,
+    pub current: String,
+    pub response_id: String,
+}
+
+/// Everything a [ReproducibilityContext] recorded, for audit logs.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReproducibilityRecord {
+    pub seed: Option<i64>,
+    pub observations: Vec<Observation>,
+    pub drifts: Vec<FingerprintDrift>,
+}
+
+/// Shares one seed across requests and tracks the fingerprints of their responses.
+///
+/// Methods take `&self`, so a context can be shared between tasks behind an `Arc`.
+#[derive(Debug, Default)]
+pub struct ReproducibilityContext {
+    record: Mutex<ReproducibilityRecord>,
+}
+
+impl ReproducibilityContext {
+    /// A context without a seed: the first stamped request that sets one provides it.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_seed(seed: i64) -> Self {
+        Self::from_record(ReproducibilityRecord {
+            seed: Some(seed),
+            ..Default::default()
+        })
+    }
+
+    /// Continue from an earlier [ReproducibilityContext::snapshot], e.g. in a later run of
+    /// the same pipeline.
+    pub fn from_record(record: ReproducibilityRecord) -> Self {
+        Self {
+            record: Mutex::new(record),
+        }
+    }
+
+    pub fn seed(&self) -> Option<i64> {
+        self.record.lock().unwrap().seed
+    }
+
+    /// Set the seed of `request` to the seed of the context. Without a context seed, the
+    /// request's own seed, if any, becomes the context seed.
+    pub fn stamp<R: SeededRequest>(&self, request: &mut R) {
+        let mut record = self.record.lock().unwrap();
+        match record.seed {
+            Some(seed) => *request.seed_mut() = Some(seed),
+            None => record.seed = *request.seed_mut(),
+        }
+    }
+
+    /// Record the fingerprint of `response`, returning the drift when it differs from the
+    /// fingerprint of the previous response of the same model.
+    pub fn record<R: Fingerprinted>(&self, response: &R) -> Option<FingerprintDrift> {
+        let mut record = self.record.lock().unwrap();
+
+        let previous = record
+            .observations
+            .iter()
+            .rev()
+            .filter(|observation| observation.model == response.model())
+            .find_map(|observation| observation.system_fingerprint.clone());
+        record.observations.push(Observation {
+            response_id: response.id().to_string(),
+            model: response.model().to_string(),
+            system_fingerprint: response.system_fingerprint().map(str::to_string),
+        });
+
+        let (previous, current) = (previous?, response.system_fingerprint()?);
+        if previous == current {
+            return None;
+        }
+
+        tracing::warn!(
+            model = response.model(),
+            "system_fingerprint changed from {previous} to {current}, outputs for the same seed may differ"
+        );
+        let drift = FingerprintDrift {
+            model: response.model().to_string(),
+            previous,
+            current: current.to_string(),
+            response_id: response.id().to_string(),
+        };
+        record.drifts.push(drift.clone());
+        Some(drift)
+    }
+
+    /// Whether every fingerprint recorded so far matches the previous one of its model.
+    pub fn is_consistent(&self) -> bool {
+        self.record.lock().unwrap().drifts.is_empty()
+    }
+
+    pub fn snapshot(&self) -> ReproducibilityRecord {
+        self.record.lock().unwrap().clone()
+    }
+}
@@ -0,0 +1,157 @@
+//! Caching of structured outputs, so that iterating on an extraction pipeline doesn't pay for
+//! the same completion twice.
+//!
+//! [crate::Chat::create_structured_cached] looks up every request in a [ResponseCache] before
+//! sending it. Requests are keyed on the [canonical_hash] of the request sent, instruction
+//! included, so the model, the instruction and the input all take part in the key, and
+//! changing any of them is a miss. The completion is stored, and parsed again on hits, so
+//! hits return the same [crate::types::structured::Response] as the original call.
+//!
+//! ```no_run
+//! use async_openai::{response_cache::FileResponseCache, structured::Generator, Client};
+//! # use async_openai::types::CreateChatCompletionRequest;
+//! # #[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+//! # struct Invoice { number: String }
+//! # async fn run(request: CreateChatCompletionRequest) -> Result<(), Box<dyn std::error::Error>> {
+//! let cache = FileResponseCache::new("cache/structured");
+//! let generator = Generator::json(Invoice::default());
+//! let invoice = Client::new()
+//!     .chat()
+//!     .create_structured_cached(request, &generator, &cache)
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Failures of the cache are logged and don't fail requests: a lookup that fails is a miss.
+//!
+//! [canonical_hash]: crate::canonical::canonical_hash
+use std::{
+    collections::HashMap,
+    future::Future,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::Mutex,
+};
+
+use crate::{error::OpenAIError, types::CreateChatCompletionResponse};
+
+/// Future returned by [ResponseCache] methods.
+pub type CacheFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, OpenAIError>> + Send + 'a>>;
+
+/// Storage for the completions of structured requests, keyed by the lowercase hex
+/// [crate::canonical::canonical_hash] of the request.
+pub trait ResponseCache: Send + Sync {
+    /// The completion stored for `key`, `None` if there is none.
+    fn get<'a>(&'a self, key: &'a str) -> CacheFuture<'a, Option<CreateChatCompletionResponse>>;
+
+    /// Store `response` for `key`, replacing any completion stored for it.
+    fn put<'a>(
+        &'a self,
+        key: &'a str,
+        response: &'a CreateChatCompletionResponse,
+    ) -> CacheFuture<'a, ()>;
+}
+
+/// Keeps completions in memory, for the lifetime of the cache.
+#[derive(Debug, Default)]
+pub struct InMemoryResponseCache {
+    responses: Mutex<HashMap<String, CreateChatCompletionResponse>>,
+}
+
+impl InMemoryResponseCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of cached completions.
+    pub fn len(&self) -> usize {
+        self.responses.lock().unwrap().len()
+    }
+
+    /// Whether no completion is cached.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Forget every cached completion.
+    pub fn clear(&self) {
+        self.responses.lock().unwrap().clear();
+    }
+}
+
+impl ResponseCache for InMemoryResponseCache {
+    fn get<'a>(&'a self, key: &'a str) -> CacheFuture<'a, Option<CreateChatCompletionResponse>> {
+        let response = self.responses.lock().unwrap().get(key).cloned();
+        Box::pin(async move { Ok(response) })
+    }
+
+    fn put<'a>(
+        &'a self,
+        key: &'a str,
+        response: &'a CreateChatCompletionResponse,
+    ) -> CacheFuture<'a, ()> {
+        self.responses
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), response.clone());
+        Box::pin(async move { Ok(()) })
+    }
+}
+
+/// Stores completions as `<key>.json` files in a directory, which is created when the first
+/// completion is stored. The files survive restarts, and can be deleted to invalidate entries.
+#[derive(Debug, Clone)]
+pub struct FileResponseCache {
+    dir: PathBuf,
+}
+
+impl FileResponseCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// The directory of the files.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{key}.json"))
+    }
+}
+
+impl ResponseCache for FileResponseCache {
+    fn get<'a>(&'a self, key: &'a str) -> CacheFuture<'a, Option<CreateChatCompletionResponse>> {
+        Box::pin(async move {
+            let json = match tokio::fs::read(self.path(key)).await {
+                Ok(json) => json,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+                Err(e) => return Err(OpenAIError::FileReadError(e.to_string())),
+            };
+            serde_json::from_slice(&json)
+                .map(Some)
+                .map_err(|e| OpenAIError::FileReadError(e.to_string()))
+        })
+    }
+
+    fn put<'a>(
+        &'a self,
+        key: &'a str,
+        response: &'a CreateChatCompletionResponse,
+    ) -> CacheFuture<'a, ()> {
+        Box::pin(async move {
+            let save = |e: std::io::Error| OpenAIError::FileSaveError(e.to_string());
+            let json = serde_json::to_vec_pretty(response)
+                .map_err(|e| OpenAIError::FileSaveError(e.to_string()))?;
+            tokio::fs::create_dir_all(&self.dir).await.map_err(save)?;
+
+            // Written next to the final file and renamed, so that readers never see a
+            // partial file
+            let path = self.path(key);
+            let partial = path.with_extension("json.partial");
+            tokio::fs::write(&partial, json).await.map_err(save)?;
+            tokio::fs::rename(&partial, &path).await.map_err(save)
+        })
+    }
+}
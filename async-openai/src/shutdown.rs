@@ -0,0 +1,128 @@
+//! Graceful shutdown of a [crate::Client] and all of its clones.
+//!
+//! See [crate::Client::shutdown].
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use tokio::sync::Notify;
+use tokio_util::sync::CancellationToken;
+
+use crate::error::OpenAIError;
+
+/// Hook run by [crate::Client::shutdown] once in-flight requests are done, e.g. to flush
+/// usage or metrics.
+pub type ShutdownHook = Arc<dyn Fn() -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync>;
+
+/// How [crate::Client::shutdown] ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShutdownOutcome {
+    /// Every in-flight request and stream finished before the deadline.
+    Drained,
+    /// This many requests or streams were still in flight at the deadline and were aborted
+    /// with [OpenAIError::ClientShutdown].
+    Aborted(usize),
+}
+
+/// State shared by a client and its clones.
+#[derive(Default)]
+pub(crate) struct Lifecycle {
+    closed: AtomicBool,
+    in_flight: AtomicUsize,
+    idle: Notify,
+    abort: CancellationToken,
+    hooks: Mutex<Vec<ShutdownHook>>,
+}
+
+impl std::fmt::Debug for Lifecycle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Lifecycle")
+            .field("closed", &self.closed)
+            .field("in_flight", &self.in_flight)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Lifecycle {
+    /// Register a request or stream, failing once shutdown has started.
+    pub(crate) fn enter(self: &Arc<Self>) -> Result<InFlight, OpenAIError> {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        let guard = InFlight {
+            lifecycle: self.clone(),
+        };
+        // Checked after registering so that `shutdown` can't miss a request
+        if self.closed.load(Ordering::SeqCst) {
+            return Err(OpenAIError::ClientShutdown);
+        }
+        Ok(guard)
+    }
+
+    pub(crate) fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn add_hook(&self, hook: ShutdownHook) {
+        self.hooks.lock().unwrap().push(hook);
+    }
+
+    pub(crate) async fn shutdown(&self, deadline: Duration) -> ShutdownOutcome {
+        self.closed.store(true, Ordering::SeqCst);
+
+        let outcome = match tokio::time::timeout(deadline, self.drained()).await {
+            Ok(()) => ShutdownOutcome::Drained,
+            Err(_) => {
+                let in_flight = self.in_flight.load(Ordering::SeqCst);
+                tracing::warn!("aborting {in_flight} in-flight requests at shutdown deadline");
+                self.abort.cancel();
+                self.drained().await;
+                ShutdownOutcome::Aborted(in_flight)
+            }
+        };
+
+        let hooks = self.hooks.lock().unwrap().clone();
+        for hook in hooks {
+            hook().await;
+        }
+
+        outcome
+    }
+
+    async fn drained(&self) {
+        loop {
+            let notified = self.idle.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+
+            if self.in_flight.load(Ordering::SeqCst) == 0 {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+/// Guard held for the duration of a request or stream.
+pub(crate) struct InFlight {
+    lifecycle: Arc<Lifecycle>,
+}
+
+impl InFlight {
+    /// Completes when the request must be aborted because the shutdown deadline passed.
+    pub(crate) async fn aborted(&self) {
+        self.lifecycle.abort.cancelled().await
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        if self.lifecycle.in_flight.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.lifecycle.idle.notify_waiters();
+        }
+    }
+}
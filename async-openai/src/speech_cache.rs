@@ -0,0 +1,165 @@
+//! Caching of synthesized speech, so that phrases a voice app says again and again are only
+//! synthesized once.
+//!
+//! A client with a [SpeechStore] looks up every [crate::Audio::speech] request in the store
+//! before sending it, and stores the audio of the requests it sends. Requests are keyed on
+//! their model, voice, speed and format and a SHA-256 hash of their text, see
+//! [SpeechCacheKey]. Hits and misses are emitted as [crate::events::ClientEvent]s.
+//!
+//! ```no_run
+//! use async_openai::{speech_cache::FileSpeechStore, Client};
+//!
+//! let client = Client::new().with_speech_cache(FileSpeechStore::new("cache/speech"));
+//! ```
+//!
+//! Failures of the store are logged and don't fail requests: a lookup that fails is a miss.
+use std::{
+    future::Future,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::Arc,
+};
+
+use bytes::Bytes;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    error::OpenAIError,
+    types::{CreateSpeechRequest, SpeechResponseFormat},
+};
+
+/// Future returned by [SpeechStore] methods.
+pub type StoreFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, OpenAIError>> + Send + 'a>>;
+
+/// Identifies the audio of a speech request.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SpeechCacheKey {
+    /// Model, e.g. `tts-1`
+    pub model: String,
+    /// Voice, e.g. `alloy`
+    pub voice: String,
+    /// Speed, e.g. `1` or `1.25`
+    pub speed: String,
+    /// Audio format, e.g. `mp3`
+    pub format: String,
+    /// Lowercase hex SHA-256 hash of the text
+    pub text_hash: String,
+}
+
+impl SpeechCacheKey {
+    /// Key of the audio `request` synthesizes.
+    pub fn new(request: &CreateSpeechRequest) -> Self {
+        Self {
+            model: name(&request.model),
+            voice: name(&request.voice),
+            speed: request.speed.unwrap_or(1.0).to_string(),
+            format: name(&request.response_format.unwrap_or(SpeechResponseFormat::Mp3)),
+            text_hash: format!("{:x}", Sha256::digest(request.input.as_bytes())),
+        }
+    }
+
+    /// File name of the audio, e.g. `tts-1-alloy-1-<hash>.mp3`. Characters other than ASCII
+    /// letters, digits, `.`, `-` and `_` in custom model and voice names are replaced by `_`.
+    pub fn file_name(&self) -> String {
+        let name = format!(
+            "{}-{}-{}-{}.{}",
+            self.model, self.voice, self.speed, self.text_hash, self.format
+        );
+        name.chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect()
+    }
+}
+
+/// Name of `value` in requests.
+fn name<T: Serialize>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(name)) => name,
+        Ok(value) => value.to_string(),
+        Err(_) => String::new(),
+    }
+}
+
+/// Storage for synthesized speech.
+pub trait SpeechStore: Send + Sync {
+    /// The audio stored for `key`, `None` if there is none.
+    fn get<'a>(&'a self, key: &'a SpeechCacheKey) -> StoreFuture<'a, Option<Bytes>>;
+
+    /// Store `audio` for `key`, replacing any audio stored for it.
+    fn put<'a>(&'a self, key: &'a SpeechCacheKey, audio: Bytes) -> StoreFuture<'a, ()>;
+}
+
+/// Stores audio as files named after [SpeechCacheKey::file_name] in a directory, which is
+/// created when the first audio is stored.
+#[derive(Debug, Clone)]
+pub struct FileSpeechStore {
+    dir: PathBuf,
+}
+
+impl FileSpeechStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// The directory of the files.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn path(&self, key: &SpeechCacheKey) -> PathBuf {
+        self.dir.join(key.file_name())
+    }
+}
+
+impl SpeechStore for FileSpeechStore {
+    fn get<'a>(&'a self, key: &'a SpeechCacheKey) -> StoreFuture<'a, Option<Bytes>> {
+        Box::pin(async move {
+            match tokio::fs::read(self.path(key)).await {
+                Ok(audio) => Ok(Some(audio.into())),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(OpenAIError::FileReadError(e.to_string())),
+            }
+        })
+    }
+
+    fn put<'a>(&'a self, key: &'a SpeechCacheKey, audio: Bytes) -> StoreFuture<'a, ()> {
+        Box::pin(async move {
+            let save = |e: std::io::Error| OpenAIError::FileSaveError(e.to_string());
+            tokio::fs::create_dir_all(&self.dir).await.map_err(save)?;
+
+            // Written next to the final file and renamed, so that readers never see a
+            // partial file
+            let path = self.path(key);
+            let partial = path.with_extension(format!("{}.partial", key.format));
+            tokio::fs::write(&partial, &audio).await.map_err(save)?;
+            tokio::fs::rename(&partial, &path).await.map_err(save)
+        })
+    }
+}
+
+/// The speech store of a client, if any.
+#[derive(Clone, Default)]
+pub(crate) struct SpeechCache(Option<Arc<dyn SpeechStore>>);
+
+impl std::fmt::Debug for SpeechCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SpeechCache({})", self.0.is_some())
+    }
+}
+
+impl SpeechCache {
+    pub(crate) fn new(store: Arc<dyn SpeechStore>) -> Self {
+        Self(Some(store))
+    }
+
+    pub(crate) fn store(&self) -> Option<&dyn SpeechStore> {
+        self.0.as_deref()
+    }
+}
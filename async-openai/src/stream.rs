@@ -0,0 +1,200 @@
+//! Adapters for streaming responses such as [crate::types::ChatCompletionResponseStream].
+//!
+//! [StreamTapExt::tap] lets a second consumer observe every item by reference while the
+//! caller keeps consuming the stream, and [StreamTapExt::fork] hands successful items to an
+//! independent receiver. Neither buffers nor delays the original stream: items are
+//! produced only as fast as the primary consumer polls for them.
+//!
+//! With the `client` feature, [StreamRecorder] saves a stream as an SSE fixture and
+//! [replay_chat_file] turns such a fixture back into a
+//! [ChatCompletionResponseStream](crate::types::ChatCompletionResponseStream), optionally
+//! with the recorded pacing, for demos, tests and UI work without network access.
+//! [StreamDeadlineExt::deadline] caps the total duration of a stream, regardless of how
+//! steadily chunks arrive, and [StreamPacingExt::pace] evens out the chunks handed to the
+//! consumer, for typewriter effects or to protect downstream websockets.
+//!
+//! ```
+//! # tokio_test::block_on(async {
+//! use async_openai::{error::OpenAIError, stream::StreamTapExt};
+//! use futures::StreamExt;
+//!
+//! let chunks = futures::stream::iter(["Hel", "lo"].map(|c| Ok::<_, OpenAIError>(c.to_string())));
+//!
+//! let mut received = 0;
+//! let (stream, log) = chunks.tap(|_| received += 1).fork();
+//!
+//! let rendered: Vec<_> = stream.map(Result::unwrap).collect().await;
+//! let logged: Vec<_> = log.collect().await;
+//! assert_eq!(rendered, logged);
+//! # });
+//! ```
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::{
+    channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender},
+    Stream, StreamExt,
+};
+
+use crate::{
+    error::OpenAIError,
+    types::{CreateChatCompletionStreamResponse, CreateCompletionResponse},
+};
+
+#[cfg(feature = "client")]
+mod deadline;
+#[cfg(feature = "client")]
+mod pacing;
+#[cfg(feature = "client")]
+mod replay;
+
+#[cfg(feature = "client")]
+pub use deadline::{StreamDeadline, StreamDeadlineExt};
+#[cfg(feature = "client")]
+pub use pacing::{PacedStream, Pacing, StreamPacingExt};
+#[cfg(feature = "client")]
+pub use replay::{replay_chat_file, replay_sse, ReplayTiming, StreamRecorder};
+
+/// Text carried by a streamed chunk, for adapters that accumulate the generated content.
+///
+/// Only the first choice (`index` 0) is considered.
+pub trait ContentDelta {
+    fn content_delta(&self) -> Option<&str>;
+
+    /// Refusal text carried by the chunk, for models that can decline to answer.
+    fn refusal_delta(&self) -> Option<&str> {
+        None
+    }
+}
+
+impl ContentDelta for CreateChatCompletionStreamResponse {
+    fn content_delta(&self) -> Option<&str> {
+        self.choices
+            .iter()
+            .find(|choice| choice.index == 0)
+            .and_then(|choice| choice.delta.content.as_deref())
+    }
+
+    fn refusal_delta(&self) -> Option<&str> {
+        self.choices
+            .iter()
+            .find(|choice| choice.index == 0)
+            .and_then(|choice| choice.delta.refusal.as_deref())
+    }
+}
+
+impl ContentDelta for CreateCompletionResponse {
+    fn content_delta(&self) -> Option<&str> {
+        self.choices
+            .iter()
+            .find(|choice| choice.index == 0)
+            .map(|choice| choice.text.as_str())
+    }
+}
+
+impl ContentDelta for String {
+    fn content_delta(&self) -> Option<&str> {
+        Some(self)
+    }
+}
+
+/// Stream returned by [StreamTapExt::tap].
+#[derive(Debug)]
+pub struct StreamTap<S, F> {
+    stream: S,
+    tap: F,
+}
+
+impl<S, F> Stream for StreamTap<S, F>
+where
+    S: Stream + Unpin,
+    F: FnMut(&S::Item) + Unpin,
+{
+    type Item = S::Item;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        let item = this.stream.poll_next_unpin(cx);
+        if let Poll::Ready(Some(item)) = &item {
+            (this.tap)(item);
+        }
+        item
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.stream.size_hint()
+    }
+}
+
+/// Stream returned by [StreamTapExt::fork].
+#[derive(Debug)]
+pub struct StreamFork<S, T> {
+    stream: S,
+    sender: Option<UnboundedSender<T>>,
+}
+
+impl<S, T> Stream for StreamFork<S, T>
+where
+    S: Stream<Item = Result<T, OpenAIError>> + Unpin,
+    T: Clone,
+{
+    type Item = S::Item;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        let item = this.stream.poll_next_unpin(cx);
+        match &item {
+            Poll::Ready(Some(Ok(value))) => {
+                if let Some(sender) = &this.sender {
+                    // A dropped receiver only means nobody is observing anymore
+                    if sender.unbounded_send(value.clone()).is_err() {
+                        this.sender = None;
+                    }
+                }
+            }
+            // Close the receiving side as soon as the stream ends
+            Poll::Ready(None) => this.sender = None,
+            _ => {}
+        }
+        item
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.stream.size_hint()
+    }
+}
+
+/// Extension methods to observe a stream without taking it over.
+pub trait StreamTapExt: Stream + Sized {
+    /// Call `tap` with a reference to every item as it is yielded.
+    fn tap<F>(self, tap: F) -> StreamTap<Self, F>
+    where
+        F: FnMut(&Self::Item),
+    {
+        StreamTap { stream: self, tap }
+    }
+
+    /// Split off a receiver that gets a clone of every successful item. Errors are only
+    /// yielded by the returned stream, and the receiver ends when the stream does.
+    ///
+    /// The receiver is unbounded so that a slow observer never holds back the primary
+    /// consumer; it only buffers what the primary consumer has already pulled.
+    fn fork<T>(self) -> (StreamFork<Self, T>, UnboundedReceiver<T>)
+    where
+        Self: Stream<Item = Result<T, OpenAIError>>,
+        T: Clone,
+    {
+        let (sender, receiver) = unbounded();
+        (
+            StreamFork {
+                stream: self,
+                sender: Some(sender),
+            },
+            receiver,
+        )
+    }
+}
+
+impl<S: Stream> StreamTapExt for S {}
@@ -0,0 +1,91 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures::{Stream, StreamExt};
+use tokio::time::{Instant, Sleep};
+
+use super::ContentDelta;
+use crate::error::OpenAIError;
+
+/// Stream returned by [StreamDeadlineExt::deadline].
+#[derive(Debug)]
+pub struct StreamDeadline<S> {
+    /// Dropped at the deadline, which cancels the underlying request.
+    stream: Option<S>,
+    sleep: Pin<Box<Sleep>>,
+    limit: Duration,
+    partial_content: String,
+}
+
+impl<S, T> Stream for StreamDeadline<S>
+where
+    S: Stream<Item = Result<T, OpenAIError>> + Unpin,
+    T: ContentDelta,
+{
+    type Item = S::Item;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        let Some(stream) = this.stream.as_mut() else {
+            return Poll::Ready(None);
+        };
+
+        // Compared with the clock rather than polled, so that a stream that is always ready
+        // can't starve the timer
+        if Instant::now() >= this.sleep.deadline() {
+            return Poll::Ready(Some(Err(this.abort())));
+        }
+
+        if let Poll::Ready(item) = stream.poll_next_unpin(cx) {
+            match &item {
+                Some(Ok(chunk)) => {
+                    if let Some(content) = chunk.content_delta() {
+                        this.partial_content.push_str(content);
+                    }
+                }
+                None => this.stream = None,
+                Some(Err(_)) => {}
+            }
+            return Poll::Ready(item);
+        }
+
+        if this.sleep.as_mut().poll(cx).is_ready() {
+            return Poll::Ready(Some(Err(this.abort())));
+        }
+
+        Poll::Pending
+    }
+}
+
+impl<S> StreamDeadline<S> {
+    /// Drop the underlying stream, returning the error to end with.
+    fn abort(&mut self) -> OpenAIError {
+        tracing::warn!("aborting stream after {:?}", self.limit);
+        self.stream = None;
+        OpenAIError::StreamDeadlineExceeded {
+            limit: self.limit,
+            partial_content: std::mem::take(&mut self.partial_content),
+        }
+    }
+}
+
+/// Extension methods to cap the total duration of a stream.
+pub trait StreamDeadlineExt: Stream + Sized {
+    /// End the stream with [OpenAIError::StreamDeadlineExceeded] once `limit` has passed
+    /// since this call, however steadily chunks keep arriving. The error carries the content
+    /// received so far, and the underlying request is cancelled.
+    fn deadline(self, limit: Duration) -> StreamDeadline<Self> {
+        StreamDeadline {
+            stream: Some(self),
+            sleep: Box::pin(tokio::time::sleep_until(Instant::now() + limit)),
+            limit,
+            partial_content: String::new(),
+        }
+    }
+}
+
+impl<S: Stream> StreamDeadlineExt for S {}
@@ -0,0 +1,113 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures::{Stream, StreamExt};
+use tokio::time::{Instant, Sleep};
+
+use super::ContentDelta;
+use crate::{error::OpenAIError, tokens::Tokenizer};
+
+/// How [StreamPacingExt::pace] spaces out the chunks of a stream.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pacing {
+    /// At most this many tokens per second, the tokens of a chunk being counted with
+    /// [Tokenizer], for typewriter effects and to protect downstream connections.
+    TokensPerSecond(f64),
+    /// At least this long between two chunks with content, spreading out chunks that
+    /// arrive in bursts.
+    Interval(Duration),
+}
+
+impl Pacing {
+    /// Delay to leave after a chunk with `content`.
+    fn delay(&self, tokenizer: &Tokenizer, content: &str) -> Duration {
+        match *self {
+            Pacing::TokensPerSecond(rate) if rate > 0.0 => {
+                let tokens = tokenizer.count(content).max(1);
+                // Rates close to zero give delays too long for a Duration
+                Duration::try_from_secs_f64(tokens as f64 / rate).unwrap_or(Duration::MAX)
+            }
+            Pacing::TokensPerSecond(_) => Duration::ZERO,
+            Pacing::Interval(interval) => interval,
+        }
+    }
+}
+
+/// Stream returned by [StreamPacingExt::pace].
+#[derive(Debug)]
+pub struct PacedStream<S, T> {
+    stream: S,
+    pacing: Pacing,
+    tokenizer: Tokenizer,
+    /// Chunk waiting for its turn
+    pending: Option<T>,
+    /// Earliest time the next chunk with content may be emitted
+    sleep: Pin<Box<Sleep>>,
+}
+
+impl<S, T> Stream for PacedStream<S, T>
+where
+    S: Stream<Item = Result<T, OpenAIError>> + Unpin,
+    T: ContentDelta + Unpin,
+{
+    type Item = S::Item;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+
+        if this.pending.is_none() {
+            match this.stream.poll_next_unpin(cx) {
+                Poll::Ready(Some(Ok(chunk))) => {
+                    // Chunks without content, such as the final one with the finish reason
+                    // or usage, are not delayed
+                    if chunk.content_delta().map_or(true, str::is_empty) {
+                        return Poll::Ready(Some(Ok(chunk)));
+                    }
+                    this.pending = Some(chunk);
+                }
+                other => return other,
+            }
+        }
+
+        if this.sleep.as_mut().poll(cx).is_pending() {
+            return Poll::Pending;
+        }
+
+        let chunk = this.pending.take().expect("pending chunk");
+        let content = chunk.content_delta().unwrap_or_default();
+        let delay = this.pacing.delay(&this.tokenizer, content);
+        // Delays too long for an Instant hold the next chunk back for good, as far in the
+        // future as tokio sleeps
+        let deadline = Instant::now()
+            .checked_add(delay)
+            .unwrap_or_else(|| Instant::now() + Duration::from_secs(86400 * 365 * 30));
+        this.sleep.as_mut().reset(deadline);
+        Poll::Ready(Some(Ok(chunk)))
+    }
+}
+
+/// Extension methods to pace the chunks of a stream.
+pub trait StreamPacingExt: Stream + Sized {
+    /// Delay chunks with content so that they are emitted no faster than `pacing` allows.
+    ///
+    /// Chunks are held back one at a time, so a slow consumer still slows down the
+    /// request; chunks without content and errors pass through right away.
+    fn pace<T>(self, pacing: Pacing) -> PacedStream<Self, T>
+    where
+        Self: Stream<Item = Result<T, OpenAIError>>,
+    {
+        PacedStream {
+            stream: self,
+            pacing,
+            tokenizer: Tokenizer::for_model(""),
+            pending: None,
+            sleep: Box::pin(tokio::time::sleep_until(Instant::now())),
+        }
+    }
+}
+
+impl<S: Stream> StreamPacingExt for S {}
@@ -0,0 +1,205 @@
+use std::{
+    path::Path,
+    pin::Pin,
+    time::{Duration, Instant},
+};
+
+use futures::{Stream, StreamExt};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    error::{map_deserialization_error, OpenAIError},
+    types::ChatCompletionResponseStream,
+};
+
+/// Prefix of the SSE comment lines holding the delay before the next event, e.g. `: +120ms`.
+const DELAY_PREFIX: &str = ": +";
+
+/// Pacing of a replayed stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReplayTiming {
+    /// Yield every event as soon as it is polled.
+    #[default]
+    Immediate,
+    /// Wait the same time before every event.
+    Fixed(Duration),
+    /// Wait the delays written by [StreamRecorder]; events without one are yielded immediately.
+    Recorded,
+}
+
+/// Records stream chunks as an SSE fixture for [replay_sse] and [replay_chat_file].
+///
+/// Each event is preceded by a `: +<ms>ms` comment with the time elapsed since the previous
+/// chunk, which servers ignore and [ReplayTiming::Recorded] uses to reproduce the pacing.
+///
+/// ```no_run
+/// # async fn run(stream: async_openai::types::ChatCompletionResponseStream) -> Result<(), async_openai::error::OpenAIError> {
+/// use async_openai::stream::{StreamRecorder, StreamTapExt};
+/// use futures::StreamExt;
+///
+/// let mut recorder = StreamRecorder::new();
+/// let mut stream = stream.tap(|chunk| {
+///     if let Ok(chunk) = chunk {
+///         recorder.record(chunk);
+///     }
+/// });
+/// while let Some(_chunk) = stream.next().await {}
+/// drop(stream);
+///
+/// recorder.save("fixtures/haiku.sse")?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct StreamRecorder {
+    sse: String,
+    last: Instant,
+}
+
+impl Default for StreamRecorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StreamRecorder {
+    pub fn new() -> Self {
+        Self {
+            sse: String::new(),
+            last: Instant::now(),
+        }
+    }
+
+    /// Append a chunk, timed from the previous one or from the creation of the recorder.
+    pub fn record<T: Serialize>(&mut self, chunk: &T) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last);
+        self.last = now;
+
+        // Serializing the types of this crate can't fail
+        let data = serde_json::to_string(chunk).unwrap_or_default();
+        self.sse.push_str(&format!(
+            "{DELAY_PREFIX}{}ms\ndata: {data}\n\n",
+            elapsed.as_millis()
+        ));
+    }
+
+    /// The recorded events, terminated by `data: [DONE]`.
+    pub fn to_sse(&self) -> String {
+        format!("{}data: [DONE]\n\n", self.sse)
+    }
+
+    /// Write the fixture to `path`.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), OpenAIError> {
+        std::fs::write(path.as_ref(), self.to_sse())
+            .map_err(|e| OpenAIError::FileSaveError(format!("{}: {e}", path.as_ref().display())))
+    }
+}
+
+/// One `data` event of a fixture, with the recorded delay before it.
+#[derive(Debug, PartialEq)]
+struct Event {
+    delay: Option<Duration>,
+    data: String,
+}
+
+/// Parse the `data` events of an SSE document up to `[DONE]`.
+fn parse_events(sse: &str) -> Vec<Event> {
+    let mut events = vec![];
+    let mut delay = None;
+    let mut data: Vec<&str> = vec![];
+
+    // A trailing empty line dispatches the last event even if the file lacks one
+    for line in sse.lines().chain(std::iter::once("")) {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        if line.is_empty() {
+            if !data.is_empty() {
+                let event = data.join("\n");
+                if event == "[DONE]" {
+                    break;
+                }
+                events.push(Event {
+                    delay: delay.take(),
+                    data: event,
+                });
+                data.clear();
+            }
+        } else if let Some(ms) = line
+            .strip_prefix(DELAY_PREFIX)
+            .and_then(|rest| rest.strip_suffix("ms"))
+        {
+            delay = ms.parse().ok().map(Duration::from_millis);
+        } else if let Some(value) = line.strip_prefix("data:") {
+            data.push(value.strip_prefix(' ').unwrap_or(value));
+        }
+        // Other fields (`event`, `id`, `retry`) and comments carry nothing to replay
+    }
+
+    events
+}
+
+/// Replay an SSE document, such as a fixture saved by [StreamRecorder] or the body of a
+/// streaming response captured with `curl`, as a stream of `O`.
+pub fn replay_sse<O>(
+    sse: &str,
+    timing: ReplayTiming,
+) -> Pin<Box<dyn Stream<Item = Result<O, OpenAIError>> + Send>>
+where
+    O: DeserializeOwned + Send + 'static,
+{
+    let events = parse_events(sse);
+
+    futures::stream::iter(events)
+        .then(move |event| async move {
+            let delay = match timing {
+                ReplayTiming::Immediate => None,
+                ReplayTiming::Fixed(delay) => Some(delay),
+                ReplayTiming::Recorded => event.delay,
+            };
+            if let Some(delay) = delay.filter(|delay| !delay.is_zero()) {
+                tokio::time::sleep(delay).await;
+            }
+
+            serde_json::from_str::<O>(&event.data)
+                .map_err(|e| map_deserialization_error(e, event.data.as_bytes()))
+        })
+        .boxed()
+}
+
+/// Replay a chat completion stream from an SSE fixture file, without network access.
+pub async fn replay_chat_file<P: AsRef<Path>>(
+    path: P,
+    timing: ReplayTiming,
+) -> Result<ChatCompletionResponseStream, OpenAIError> {
+    let sse = tokio::fs::read_to_string(path.as_ref())
+        .await
+        .map_err(|e| OpenAIError::FileReadError(format!("{}: {e}", path.as_ref().display())))?;
+    Ok(replay_sse(&sse, timing))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_recorded_and_captured_events() {
+        let sse = ": +0ms\ndata: {\"a\":1}\n\n: keep-alive\r\n\r\n: +120ms\r\nevent: message\r\ndata: {\"a\":\r\ndata: 2}\r\n\r\ndata: [DONE]\n\ndata: {\"a\":3}";
+        assert_eq!(
+            parse_events(sse),
+            vec![
+                Event {
+                    delay: Some(Duration::ZERO),
+                    data: "{\"a\":1}".into()
+                },
+                Event {
+                    delay: Some(Duration::from_millis(120)),
+                    data: "{\"a\":\n2}".into()
+                },
+            ]
+        );
+
+        let unterminated = parse_events("data: {\"a\":1}");
+        assert_eq!(unterminated.len(), 1);
+        assert_eq!(unterminated[0].delay, None);
+    }
+}
@@ -1,6 +1,16 @@
 use crate::types::structured::{
-    Config, Instruction, OutputFormat, ParseError, Response, Structured, ValidationOptions,
+    ArrayConstraints, Candidate, Candidates, Config, ExtractionStrategy, FewShotExample, Instruction, InstructionTemplate, Locale, OutputFormat, ParseError, ReasoningFilter, ExtractionMethod, Response, ResponseMetadata,
+    SchemaDialect, SchemaSource, Severity, Structured, ValidationOptions, XmlMapping,
 };
+use crate::error::OpenAIError;
+use crate::stream::ContentDelta;
+use crate::types::structured::StreamedOutput;
+use crate::types::{
+    ChatChoice, ChatCompletionMessageToolCall, ChatCompletionTokenLogprob, ChatCompletionTool,
+    ChatCompletionToolType, CreateChatCompletionResponse, FunctionObject, ResponseFormat,
+    ResponseFormatJsonSchema,
+};
+use futures::{Stream, StreamExt};
 use regex::Regex;
 #[allow(unused_imports)]
 use serde::{Deserialize, Serialize};
@@ -9,49 +19,136 @@ use serde_json;
 use std::collections::HashMap;
 use indexmap::IndexMap;
 
-use std::sync::LazyLock;
+use std::sync::{Arc, OnceLock};
 
 // Import validation libraries by default
 use {
-    jsonschema::{JSONSchema, SchemaResolver, SchemaResolverError},
-    schemars::{schema_for, JsonSchema},
-    std::sync::Arc,
-    url::Url,
+    jsonschema::Validator,
+    schemars::{gen::SchemaSettings, schema_for, JsonSchema},
 };
 
-#[cfg(feature = "yaml")]
-use serde_yaml;
-
 #[cfg(feature = "xml")]
 use quick_xml::de::from_str as xml_from_str;
 
-/// Regular expressions for extracting structured data
-static JSON_REGEX: LazyLock<Regex> =
-    LazyLock::new(|| Regex::new(r"```(?:json)?\s*([\s\S]*?)\s*```").unwrap());
+pub use crate::types::structured::StructuredOutput;
+/// Derive [StructuredOutput](trait@StructuredOutput) from `#[describe("...")]` attributes or,
+/// without one, the doc comment of each field:
+///
+/// ```
+/// use async_openai::structured::{Generator, StructuredOutput};
+/// use schemars::JsonSchema;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema, StructuredOutput)]
+/// struct Invoice {
+///     /// Invoice number as printed on the document
+///     number: String,
+///     #[describe("Total amount in cents")]
+///     total: u64,
+/// }
+///
+/// let generator = Generator::json(Invoice::default()).with_field_descriptions();
+/// assert!(generator.build_instruction_text().contains("Total amount in cents"));
+/// ```
+pub use async_openai_macros::StructuredOutput;
 
+mod arrays;
+mod composite;
+mod dynamic;
+pub mod extract;
+mod extraction;
+mod format;
+mod openapi;
+mod validation;
+mod validators;
+mod confidence;
+pub(crate) mod diff;
+pub(crate) mod enums;
+pub(crate) mod regions;
+mod partial;
+mod reasoning;
+mod repair;
+pub mod sanitize;
+mod strict;
+pub(crate) mod tabular;
+pub(crate) mod proto;
+mod tool_calls;
+#[cfg(feature = "xml")]
+mod xml;
 #[cfg(feature = "yaml")]
-static YAML_REGEX: LazyLock<Regex> =
-    LazyLock::new(|| Regex::new(r"```(?:ya?ml)?\s*([\s\S]*?)\s*```").unwrap());
+mod yaml;
+pub(crate) mod typescript;
+#[cfg(feature = "client")]
+mod checked;
+#[cfg(feature = "chat")]
+mod batch;
+#[cfg(feature = "chat")]
+mod experiment;
+#[cfg(feature = "chat")]
+mod retry;
+#[cfg(feature = "chat")]
+mod session;
+#[cfg(feature = "chat")]
+mod vision;
 
-#[cfg(feature = "xml")]
-static XML_REGEX: LazyLock<Regex> =
-    LazyLock::new(|| Regex::new(r"```(?:xml)?\s*(<[\s\S]*?>)\s*```").unwrap());
+pub use composite::{CompositeGenerator, CompositeResponse, GeneratorPair};
+pub use dynamic::{DynGenerator, SchemaRegistry};
+pub use format::{CustomFormat, Format};
+pub use tool_calls::{FinishedToolCall, ToolCallAccumulator};
+#[cfg(feature = "chat")]
+pub use batch::{BatchGenerator, DEFAULT_BATCH_CONCURRENCY};
+#[cfg(feature = "chat")]
+pub use experiment::{ExperimentReport, ExperimentRunner, Sample, VariantReport};
+#[cfg(feature = "chat")]
+pub use retry::ParseAttempt;
+#[cfg(feature = "chat")]
+pub use session::{StructuredSession, DEFAULT_SESSION_CONTEXT_WINDOW};
 
-/// Empty schema resolver for JSON Schema validation
-struct EmptyResolver;
+/// A fenced JSON block
+fn json_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r"```(?:json)?\s*([\s\S]*?)\s*```").unwrap())
+}
 
-impl SchemaResolver for EmptyResolver {
-    fn resolve(
-        &self,
-        _value: &serde_json::Value,
-        _url: &Url,
-        _fragment: &str,
-    ) -> Result<Arc<serde_json::Value>, SchemaResolverError> {
-        Err(SchemaResolverError::from(std::io::Error::new(
-            std::io::ErrorKind::NotFound,
-            "Schema not found",
-        )))
-    }
+/// Every fenced code block, with its language
+fn fence_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r"```([\w+-]*)[ \t]*\r?\n([\s\S]*?)```").unwrap())
+}
+
+/// A fenced YAML block
+#[cfg(feature = "yaml")]
+fn yaml_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r"```(?:ya?ml)?\s*([\s\S]*?)\s*```").unwrap())
+}
+
+/// A fenced TOML block
+#[cfg(feature = "toml")]
+fn toml_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r"```(?:toml)?\s*([\s\S]*?)\s*```").unwrap())
+}
+
+/// A fenced CSV block
+#[cfg(feature = "csv")]
+fn csv_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r"```(?:csv)?\s*([\s\S]*?)\s*```").unwrap())
+}
+
+/// A fenced text format protobuf message
+#[cfg(feature = "protobuf")]
+fn textproto_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r"```(?:textproto|pbtxt|prototext)?\s*([\s\S]*?)\s*```").unwrap())
+}
+
+/// A fenced XML document
+#[cfg(feature = "xml")]
+fn xml_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r"```(?:xml)?\s*(<[\s\S]*?>)\s*```").unwrap())
 }
 
 /// Generator for structured instructions and responses
@@ -60,7 +157,15 @@ where
     T: Structured + for<'de> Deserialize<'de> + JsonSchema,
 {
     config: Config<T>,
-    validator: Option<JSONSchema>,
+    /// Compiled JSON Schema of the output when validation is enabled, or the reason it
+    /// doesn't compile
+    validator: Option<Result<Arc<Validator>, String>>,
+    /// Instruction rendered from the config, cleared whenever the config changes
+    instruction: OnceLock<Instruction>,
+    /// Checks of field paths, run after the response is converted to `T`
+    field_validators: Vec<(String, validators::FieldCheck)>,
+    /// Checks of the output, run after the field checks
+    struct_validators: Vec<validators::OutputCheck<T>>,
 }
 
 // Common implementation for all generators
@@ -75,10 +180,11 @@ where
         &self.config
     }
 
-    /// Generate structured instruction
+    /// Generate structured instruction. The instruction is rendered once and cached until
+    /// the configuration changes, so this is cheap on hot request paths
     #[inline]
     pub fn build_instruction(&self) -> Instruction {
-        self.config.to_instruction()
+        self.instruction.get_or_init(|| self.config.to_instruction()).clone()
     }
 
     /// Generate instruction and immediately convert to string
@@ -87,6 +193,23 @@ where
         self.build_instruction().text().to_string()
     }
 
+    /// Whether the instruction, with its schema and demonstrations, takes at most `budget`
+    /// tokens of `model`, to catch instructions that would crowd out the conversation
+    /// before a request is sent. See [Instruction::estimated_tokens]
+    pub fn fits_within(&self, model: &str, budget: usize) -> bool {
+        self.build_instruction().estimated_tokens(model) <= budget
+    }
+
+    /// The schema of `T` as a `.proto` message definition, with a message or enum per nested
+    /// type and the field descriptions as comments, e.g. to describe the output in
+    /// instructions written by hand. An array is a message with a `repeated` field `items`
+    pub fn to_proto_schema(&self) -> String {
+        match &self.config.json_schema {
+            Some(schema) => proto::render(schema),
+            None => proto::render(&serde_json::to_value(schemars::schema_for!(T)).unwrap_or_default()),
+        }
+    }
+
     /// Create a new generator with just a schema
     #[inline]
     pub fn with_schema(schema: T) -> Self {
@@ -99,150 +222,874 @@ where
         Self::new(Config::with_prefix_schema(prefix, schema))
     }
 
+    /// Configuration to modify, clearing the cached instruction
+    fn config_mut(&mut self) -> &mut Config<T> {
+        self.instruction.take();
+        &mut self.config
+    }
+
     /// Add a prefix to the generator's configuration
     pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
-        self.config.prefix = Some(prefix.into());
+        self.config_mut().prefix = Some(prefix.into());
         self
     }
 
     /// Add a suffix to the generator's configuration
     pub fn suffix(mut self, suffix: impl Into<String>) -> Self {
-        self.config.suffix = Some(suffix.into());
+        self.config_mut().suffix = Some(suffix.into());
         self
     }
 
     /// Set the output format
     pub fn format(mut self, format: OutputFormat) -> Self {
-        self.config.format = format;
+        self.config_mut().format = format;
+        self
+    }
+
+    /// Clean up every string of the parsed data with `sanitizer`, see [sanitize]
+    pub fn sanitize(mut self, sanitizer: sanitize::Sanitizer) -> Self {
+        self.config_mut().sanitize = Some(sanitizer);
+        self
+    }
+
+    /// Answer in `format`, a format defined outside the crate, see [Format]
+    pub fn custom_format(mut self, format: Box<dyn Format>) -> Self {
+        let config = self.config_mut();
+        config.format = OutputFormat::Custom;
+        config.custom_format = Some(format.into());
         self
     }
 
-    /// Add a field description
+    /// Add a field description. Nested fields are addressed by their path, e.g. `author.name`,
+    /// and fields of array items with `[]`, e.g. `items[].price`
     pub fn describe(mut self, field: impl Into<String>, description: impl Into<String>) -> Self {
-        let descriptions = self.config.descriptions.get_or_insert_with(IndexMap::new);
+        let descriptions = self.config_mut().descriptions.get_or_insert_with(IndexMap::new);
         descriptions.insert(field.into(), description.into());
         self
     }
 
+    /// Describe the position `index` of a tuple answer, e.g. `(String, u32)`, or the value of
+    /// a newtype answer at position 0
+    pub fn describe_index(mut self, index: usize, description: impl Into<String>) -> Self {
+        self.config_mut().index_descriptions.insert(index, description.into());
+        self
+    }
+
+    /// Set the order of the fields in the example, the field descriptions and the schema, by
+    /// path as with [Generator::describe]. The listed fields come first, in this order, and
+    /// the others follow in the order of their declaration. Needs the `structured-field-order`
+    /// feature, without which the example and the schema list fields sorted by name
+    pub fn field_order<S: Into<String>>(mut self, fields: impl IntoIterator<Item = S>) -> Self {
+        self.config_mut().field_order = fields.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Describe every field listed by [StructuredOutput::field_descriptions], keeping
+    /// descriptions already set with [Generator::describe]
+    pub fn with_field_descriptions(mut self) -> Self
+    where
+        T: StructuredOutput,
+    {
+        for (field, description) in T::field_descriptions() {
+            let exists = self
+                .config
+                .descriptions
+                .as_ref()
+                .is_some_and(|descriptions| descriptions.contains_key(field));
+            if !exists {
+                self = self.describe(field, description);
+            }
+        }
+        self
+    }
+
+    /// Set the source of the JSON Schema block of the instruction
+    pub fn schema_source(mut self, source: SchemaSource) -> Self {
+        self.config_mut().schema_source = source;
+        self
+    }
+
+    /// Set how the expected structure is presented in the instruction
+    pub fn schema_dialect(mut self, dialect: SchemaDialect) -> Self {
+        self.config_mut().schema_dialect = dialect;
+        self
+    }
+
+    /// Set how JSON answers are located in the response, see [ExtractionStrategy]
+    pub fn extraction(mut self, strategy: ExtractionStrategy) -> Self {
+        self.config_mut().extraction = strategy;
+        self
+    }
+
+    /// Strip the reasoning of thinking models from responses before extracting the answer,
+    /// see [ReasoningFilter]
+    pub fn reasoning_filter(mut self, filter: ReasoningFilter) -> Self {
+        self.config_mut().reasoning_filter = filter;
+        self
+    }
+
     /// Enable validation
     pub fn validate(mut self, enable: bool) -> Self {
-        self.config.validate = enable;
+        self.config_mut().validate = enable;
+        self.compile_validator();
         self
     }
 
     /// Set validation options
     pub fn validation_options(mut self, options: ValidationOptions) -> Self {
-        self.config.validation_options = Some(options);
+        self.config_mut().validation_options = Some(options);
+        self.compile_validator();
+        self
+    }
+
+    /// Add a demonstration of the `output` expected for `input`, rendered in the output
+    /// format after the format description
+    pub fn example(mut self, input: impl Into<String>, output: T) -> Self {
+        self.config_mut().examples.push(FewShotExample {
+            input: input.into(),
+            output,
+        });
+        self
+    }
+
+    /// Add several demonstrations of `(input, output)` pairs
+    pub fn examples<S: Into<String>>(mut self, examples: impl IntoIterator<Item = (S, T)>) -> Self {
+        for (input, output) in examples {
+            self = self.example(input, output);
+        }
+        self
+    }
+
+    /// Set the language of the phrases of the instruction
+    pub fn locale(mut self, locale: Locale) -> Self {
+        self.config_mut().locale = locale;
+        self
+    }
+
+    /// Show the schema of the JSON formats minified, see [Config::compact_schema]
+    pub fn compact_schema(mut self, compact: bool) -> Self {
+        self.config_mut().compact_schema = compact;
         self
     }
 
+    /// Set how data maps to XML, see [Config::xml_mapping]
+    pub fn xml_mapping(mut self, mapping: XmlMapping) -> Self {
+        self.config_mut().xml_mapping = mapping;
+        self
+    }
+
+    /// Ask for and check the number and uniqueness of items, see [Config::array_constraints]
+    pub fn array_constraints(mut self, min: usize, max: usize, unique_by: &str) -> Self {
+        let config = self.config_mut();
+        let mut constraints = ArrayConstraints::new(min, max);
+        if !unique_by.is_empty() {
+            constraints = constraints.unique_by(unique_by);
+        }
+        constraints.trim = config.array_constraints.as_ref().is_some_and(|c| c.trim);
+        config.array_constraints = Some(constraints);
+        self
+    }
+
+    /// Drop duplicates and extra items instead of failing, see [Config::trim_array]
+    pub fn trim_array(mut self, trim: bool) -> Self {
+        self.config_mut().array_constraints.get_or_insert_with(ArrayConstraints::default).trim = trim;
+        self
+    }
+
+    /// Check the values at `field` after the response is converted to `T`, e.g. `email` or
+    /// `lines[].total`. An `Err` is reported as a validation message with the keyword
+    /// `validateField`, and fails the parse when the validation options require it, see
+    /// [ValidationOptions::require_all_required_properties] and [ValidationOptions::severity]
+    pub fn validate_field<F>(mut self, field: impl Into<String>, check: F) -> Self
+    where
+        F: Fn(&serde_json::Value) -> Result<(), String> + Send + Sync + 'static,
+    {
+        self.field_validators.push((field.into(), Arc::new(check)));
+        self
+    }
+
+    /// Check the output after the response is converted to `T`, e.g. for rules spanning
+    /// several fields. Reported like [Generator::validate_field], with the keyword
+    /// `validateStruct`
+    pub fn validate_struct<F>(mut self, check: F) -> Self
+    where
+        F: Fn(&T) -> Result<(), String> + Send + Sync + 'static,
+    {
+        self.struct_validators.push(Arc::new(check));
+        self
+    }
+
+    /// Set the phrases of the instruction, overriding those of the locale
+    pub fn instruction_template(mut self, template: InstructionTemplate) -> Self {
+        self.config_mut().instruction_template = Some(template);
+        self
+    }
+
+    /// Set the layout of the instruction, see [Config::template]
+    pub fn template(mut self, template: impl Into<String>) -> Self {
+        self.config_mut().template = Some(template.into());
+        self
+    }
+
+    /// `value` written in the output format the way the instruction shows examples, fenced,
+    /// e.g. for the assistant messages of few-shot conversations. Values the format can't
+    /// hold, such as nested objects in CSV, are written as JSON
+    pub fn render_value(&self, value: &T) -> String {
+        self.config.render_example(value).unwrap_or_else(|| {
+            format!("```json\n{}\n```\n", serde_json::to_string_pretty(value).unwrap_or_default())
+        })
+    }
+
     /// Parse model response
     pub fn parse_response(&self, response: &str) -> Result<Response<T>, ParseError> {
-        match self.config.format {
+        self.parse_as(self.config.format, response)
+    }
+
+    /// Parse every answer of a model response that contains several, such as one JSON block
+    /// per item.
+    ///
+    /// Each fenced code block in the configured format (or without a language) is parsed.
+    /// Without such blocks, JSON formats parse every top-level JSON value of the response,
+    /// and other formats the whole response. Fails with the error of the first answer that
+    /// doesn't parse.
+    pub fn parse_all(&self, response: &str) -> Result<Vec<Response<T>>, ParseError> {
+        let (response, _) = reasoning::strip(&self.config.reasoning_filter, response)?;
+        let response = response.as_ref();
+        let format = self.config.format;
+        let blocks = extract::blocks(format, response);
+        if blocks.is_empty() {
+            return Err(ParseError::Extraction(
+                "Unable to extract JSON data: no JSON value in response".to_string(),
+            ));
+        }
+
+        blocks
+            .into_iter()
+            .map(|block| self.parse_as(format, block))
+            .collect()
+    }
+
+    /// Parse model response in whichever format it is written, ignoring the configured
+    /// [OutputFormat]. The format is picked with [OutputFormat::detect] and reported in
+    /// [Response::metadata].
+    pub fn parse_auto(&self, response: &str) -> Result<Response<T>, ParseError> {
+        let (answer, _) = reasoning::strip(&self.config.reasoning_filter, response)?;
+        let format = OutputFormat::detect(&answer);
+        let mut parsed = self.parse_as(format, response)?;
+        parsed.metadata.auto_detected = true;
+        Ok(parsed)
+    }
+
+    /// Parse model response and score every parsed field with the log probabilities of the
+    /// tokens its value was generated from, see [ResponseMetadata::field_confidence].
+    ///
+    /// `logprobs` are the content logprobs of the choice `response` was taken from, returned
+    /// when the request sets `logprobs: true`.
+    pub fn parse_with_logprobs(
+        &self,
+        response: &str,
+        logprobs: &[ChatCompletionTokenLogprob],
+    ) -> Result<Response<T>, ParseError> {
+        let mut parsed = self.parse_response(response)?;
+        let data = serde_json::to_value(&parsed.data)
+            .map_err(|e| ParseError::Other(format!("Serialization failed: {}", e)))?;
+        parsed.metadata.field_confidence =
+            confidence::field_confidence(response, parsed.metadata.format, &data, logprobs);
+        parsed.metadata.confidence = parsed.metadata.span.clone()
+            .and_then(|span| confidence::span_confidence(response, span, logprobs));
+        Ok(parsed)
+    }
+
+    /// Parse the first choice of a chat completion, see [Generator::parse_choice]
+    pub fn parse_completion(&self, completion: &CreateChatCompletionResponse) -> Result<Response<T>, ParseError> {
+        let choice = completion
+            .choices
+            .first()
+            .ok_or_else(|| ParseError::Extraction("Completion has no choices".to_string()))?;
+        self.parse_choice(choice)
+    }
+
+    /// Parse every choice of a chat completion, e.g. one requested with `n` above 1, see
+    /// [Generator::parse_choice]. Pick one of the parsed candidates with [Candidates::best] or
+    /// [Candidates::best_by]
+    pub fn parse_choices(&self, completion: &CreateChatCompletionResponse) -> Candidates<T> {
+        let mut candidates = Candidates { parsed: Vec::new(), failed: Vec::new() };
+        for choice in &completion.choices {
+            match self.parse_choice(choice) {
+                Ok(response) => candidates.parsed.push(Candidate { index: choice.index, response }),
+                Err(e) => candidates.failed.push((choice.index, e)),
+            }
+        }
+        candidates
+    }
+
+    /// Parse the message of a chat completion choice, with field confidence when the choice
+    /// carries logprobs
+    pub fn parse_choice(&self, choice: &ChatChoice) -> Result<Response<T>, ParseError> {
+        if let Some(refusal) = &choice.message.refusal {
+            return Err(ParseError::Refusal(refusal.clone()));
+        }
+        let content = choice
+            .message
+            .content
+            .as_deref()
+            .ok_or_else(|| ParseError::Extraction("Choice has no content".to_string()))?;
+
+        match choice.logprobs.as_ref().and_then(|logprobs| logprobs.content.as_deref()) {
+            Some(logprobs) => self.parse_with_logprobs(content, logprobs),
+            None => self.parse_response(content),
+        }
+    }
+
+    /// A `json_schema` response format built from the schemars schema of `T`, so the API
+    /// enforces the structure instead of the instruction asking for it.
+    ///
+    /// The schema is adjusted for strict mode: objects don't allow additional properties and
+    /// require all of their fields, `Option` fields staying nullable. Strict mode requires an
+    /// object at the top level.
+    ///
+    /// ```
+    /// # #[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+    /// # struct Contact { name: String, email: Option<String> }
+    /// use async_openai::{structured::Generator, types::CreateChatCompletionRequestArgs};
+    ///
+    /// let generator = Generator::json(Contact::default());
+    /// let request = CreateChatCompletionRequestArgs::default()
+    ///     .model("gpt-4o-mini")
+    ///     .messages(vec![])
+    ///     .response_format(generator.to_response_format())
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn to_response_format(&self) -> ResponseFormat {
+        ResponseFormat::JsonSchema {
+            json_schema: self.json_schema_format(),
+        }
+    }
+
+    fn json_schema_format(&self) -> ResponseFormatJsonSchema {
+        let root = schema_for!(T);
+        let description = root
+            .schema
+            .metadata
+            .as_ref()
+            .and_then(|metadata| metadata.description.clone());
+        let name = T::schema_name()
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-'))
+            .take(64)
+            .collect();
+
+        let mut schema = serde_json::to_value(root).unwrap_or_default();
+        self.config.order_schema_fields(&mut schema, "");
+        strict::make_strict(&mut schema);
+
+        ResponseFormatJsonSchema {
+            description,
+            name,
+            schema: Some(schema),
+            strict: Some(true),
+        }
+    }
+
+    /// The `text.format` configuration of a Responses API request asking for `T`, the
+    /// strict `json_schema` format of [Generator::to_response_format].
+    ///
+    /// ```
+    /// # #[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+    /// # struct Contact { name: String }
+    /// use async_openai::structured::Generator;
+    ///
+    /// let generator = Generator::json(Contact::default());
+    /// let request = serde_json::json!({
+    ///     "model": "gpt-4o-mini",
+    ///     "input": "Ada Lovelace, ada@example.com",
+    ///     "text": { "format": generator.into_text_format() },
+    /// });
+    /// assert_eq!(request["text"]["format"]["name"], "Contact");
+    /// ```
+    pub fn into_text_format(&self) -> serde_json::Value {
+        let mut format = serde_json::to_value(self.json_schema_format()).unwrap_or_default();
+        format["type"] = serde_json::Value::String("json_schema".to_string());
+        format
+    }
+
+    /// A function tool whose parameters are the strict schema of [Generator::to_response_format],
+    /// named after `T`. Offering it as the only tool and forcing it with `tool_choice` gets the
+    /// answer as tool call arguments, which models follow more reliably than free text.
+    ///
+    /// ```
+    /// # #[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+    /// # struct Contact { name: String }
+    /// use async_openai::{structured::Generator, types::CreateChatCompletionRequestArgs};
+    ///
+    /// let generator = Generator::json(Contact::default());
+    /// let request = CreateChatCompletionRequestArgs::default()
+    ///     .model("gpt-4o-mini")
+    ///     .messages(vec![])
+    ///     .tools(vec![generator.to_tool()])
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(request.tools.unwrap()[0].function.name, "Contact");
+    /// ```
+    pub fn to_tool(&self) -> ChatCompletionTool {
+        let format = self.json_schema_format();
+        ChatCompletionTool {
+            r#type: ChatCompletionToolType::Function,
+            function: FunctionObject {
+                name: format.name,
+                description: format.description,
+                parameters: format.schema,
+                strict: format.strict,
+            },
+        }
+    }
+
+    /// Parse the arguments of a call of the tool of [Generator::to_tool] as JSON, whatever the
+    /// output format. Calls of other functions fail with [ParseError::Extraction].
+    pub fn parse_tool_call(&self, call: &ChatCompletionMessageToolCall) -> Result<Response<T>, ParseError> {
+        let name = self.json_schema_format().name;
+        if call.function.name != name {
+            return Err(ParseError::Extraction(format!(
+                "Tool call is of `{}`, not `{}`",
+                call.function.name, name
+            )));
+        }
+        self.parse_as(OutputFormat::Json, &call.function.arguments)
+    }
+
+    /// Parse the body of a Responses API response, taking the text of its `message` output
+    /// items. Other output items, such as reasoning and tool calls, are skipped.
+    ///
+    /// A refusal is reported as a [ParseError::Refusal].
+    pub fn parse_response_output(&self, response: &serde_json::Value) -> Result<Response<T>, ParseError> {
+        let output = response
+            .get("output")
+            .and_then(serde_json::Value::as_array)
+            .ok_or_else(|| ParseError::Extraction("Response has no output".to_string()))?;
+
+        let mut text = String::new();
+        let messages = output
+            .iter()
+            .filter(|item| item.get("type").and_then(serde_json::Value::as_str) == Some("message"));
+        for message in messages {
+            let parts = message
+                .get("content")
+                .and_then(serde_json::Value::as_array)
+                .map_or(&[][..], Vec::as_slice);
+            for part in parts {
+                match part.get("type").and_then(serde_json::Value::as_str) {
+                    Some("output_text") => {
+                        text.push_str(part.get("text").and_then(serde_json::Value::as_str).unwrap_or_default());
+                    }
+                    Some("refusal") => {
+                        let refusal = part.get("refusal").and_then(serde_json::Value::as_str).unwrap_or_default();
+                        return Err(ParseError::Refusal(refusal.to_string()));
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        if text.is_empty() {
+            return Err(ParseError::Extraction("Response has no output text".to_string()));
+        }
+        self.parse_response(&text)
+    }
+
+    /// Parse a streamed response while it arrives, e.g. to render fields progressively.
+    ///
+    /// For JSON formats, a [StreamedOutput::Partial] is yielded whenever the JSON received so
+    /// far changes; other formats are only parsed at the end. The last item is the
+    /// [StreamedOutput::Complete] response, or an [OpenAIError::StructuredOutput] when it
+    /// can't be parsed or the model refused to answer. The first error of `stream` ends the
+    /// output.
+    pub fn parse_stream<'a, S, D>(
+        &'a self,
+        stream: S,
+    ) -> impl Stream<Item = Result<StreamedOutput<T>, OpenAIError>> + 'a
+    where
+        S: Stream<Item = Result<D, OpenAIError>> + Unpin + 'a,
+        D: ContentDelta,
+    {
+        let incremental = matches!(
+            self.config.format,
+            OutputFormat::Json | OutputFormat::JsonArray
+        );
+
+        futures::stream::unfold(
+            Some((stream, String::new(), None, String::new())),
+            move |state| async move {
+                let (mut stream, mut buffer, mut last, mut refusal) = state?;
+                loop {
+                    match stream.next().await {
+                        Some(Ok(chunk)) => {
+                            if let Some(delta) = chunk.refusal_delta() {
+                                refusal.push_str(delta);
+                            }
+                            let Some(delta) = chunk.content_delta() else {
+                                continue;
+                            };
+                            buffer.push_str(delta);
+                            if !incremental {
+                                continue;
+                            }
+
+                            let Some(value) = partial::complete_partial_json(&buffer) else {
+                                continue;
+                            };
+                            if last.as_ref() == Some(&value) {
+                                continue;
+                            }
+                            last = Some(value.clone());
+                            let data = serde_json::from_value(value.clone()).ok();
+                            let output = StreamedOutput::Partial { value, data };
+                            return Some((Ok(output), Some((stream, buffer, last, refusal))));
+                        }
+                        Some(Err(e)) => return Some((Err(e), None)),
+                        None if !refusal.is_empty() => {
+                            let error = OpenAIError::StructuredOutput(Box::new(ParseError::Refusal(refusal)));
+                            return Some((Err(error), None));
+                        }
+                        None => {
+                            let output = self
+                                .parse_response(&buffer)
+                                .map(StreamedOutput::Complete)
+                                .map_err(|e| OpenAIError::StructuredOutput(Box::new(e)));
+                            return Some((output, None));
+                        }
+                    }
+                }
+            },
+        )
+    }
+
+    fn parse_as(&self, format: OutputFormat, response: &str) -> Result<Response<T>, ParseError> {
+        let (answer, reasoning) = reasoning::strip(&self.config.reasoning_filter, response)?;
+        let mut parsed = self.parse_answer(format, &answer)?;
+        if reasoning.is_some() {
+            parsed.raw_response = response.to_string();
+            parsed.metadata.reasoning = reasoning;
+        }
+        let (method, block) = self.locate_block(format, &answer);
+        parsed.metadata.extraction = Some(method);
+        parsed.metadata.span = span_in(response, block);
+        Ok(parsed)
+    }
+
+    /// The block of `response` the data in `format` is read from, and how it was found
+    fn locate_block<'a>(&self, format: OutputFormat, response: &'a str) -> (ExtractionMethod, &'a str) {
+        let regex: Option<&Regex> = match format {
+            OutputFormat::Json | OutputFormat::JsonArray => {
+                return extraction::locate_with_method(self.config.extraction, response)
+            }
+            #[cfg(feature = "yaml")]
+            OutputFormat::Yaml => return yaml::locate(response),
+            #[cfg(feature = "xml")]
+            OutputFormat::Xml => Some(xml_regex()),
+            #[cfg(feature = "toml")]
+            OutputFormat::Toml => Some(toml_regex()),
+            #[cfg(feature = "csv")]
+            OutputFormat::Csv => Some(csv_regex()),
+            #[cfg(feature = "protobuf")]
+            OutputFormat::TextProto => Some(textproto_regex()),
+            OutputFormat::MarkdownTable | OutputFormat::Custom => None,
+        };
+        match regex.and_then(|regex| fenced(regex, response)) {
+            Some(block) => (ExtractionMethod::CodeFence, block),
+            None => (ExtractionMethod::WholeResponse, response),
+        }
+    }
+
+    /// Parse `response`, stripped of reasoning, as `format`
+    fn parse_answer(&self, format: OutputFormat, response: &str) -> Result<Response<T>, ParseError> {
+        if self.config.validate {
+            return self.extract_value(format, response)
+                .and_then(|value| self.validate_value(value, response))
+                .and_then(|parsed| self.sanitize_data(parsed))
+                .and_then(|parsed| self.enforce_array_constraints(parsed))
+                .and_then(|parsed| self.run_validators(parsed))
+                .map(|mut parsed| {
+                    parsed.metadata.format = format;
+                    parsed
+                });
+        }
+
+        let parsed = match format {
             OutputFormat::Json | OutputFormat::JsonArray => self.parse_json_response(response),
             #[cfg(feature = "yaml")]
             OutputFormat::Yaml => self.parse_yaml_response(response),
             #[cfg(feature = "xml")]
             OutputFormat::Xml => self.parse_xml_response(response),
-            #[cfg(not(any(feature = "yaml", feature = "xml")))]
-            #[allow(unreachable_patterns)]
-            _ => Err(ParseError::Other(format!(
-                "Unsupported format: {:?}, enable required feature",
-                self.config.format
-            ))),
+            #[cfg(feature = "toml")]
+            OutputFormat::Toml => self.parse_toml_response(response),
+            #[cfg(feature = "csv")]
+            OutputFormat::Csv => self.parse_csv_response(response),
+            #[cfg(feature = "protobuf")]
+            OutputFormat::TextProto => self.parse_textproto_response(response),
+            OutputFormat::MarkdownTable => self.parse_markdown_table_response(response),
+            OutputFormat::Custom => self.parse_custom_response(response),
+        };
+
+        parsed
+            .and_then(|parsed| self.sanitize_data(parsed))
+            .and_then(|parsed| self.enforce_array_constraints(parsed))
+            .and_then(|parsed| self.run_validators(parsed))
+            .map(|mut parsed| {
+            parsed.metadata.format = format;
+            parsed
+        })
+    }
+
+    /// Apply the sanitizer of the config to the strings of the parsed data
+    fn sanitize_data(&self, mut parsed: Response<T>) -> Result<Response<T>, ParseError> {
+        let Some(sanitizer) = &self.config.sanitize else {
+            return Ok(parsed);
+        };
+        let mut value = serde_json::to_value(&parsed.data)
+            .map_err(|e| ParseError::Other(format!("Unable to sanitize the data: {}", e)))?;
+        sanitizer.apply_to_value(&mut value);
+        parsed.data = serde_json::from_value(value)
+            .map_err(|e| ParseError::Other(format!("Unable to sanitize the data: {}", e)))?;
+        Ok(parsed)
+    }
+
+    /// Check an array output against the array constraints of the config, failing with
+    /// [ParseError::ValidationError] or dropping the extra items
+    fn enforce_array_constraints(&self, mut parsed: Response<T>) -> Result<Response<T>, ParseError> {
+        let Some(constraints) = &self.config.array_constraints else {
+            return Ok(parsed);
+        };
+        let mut value = serde_json::to_value(&parsed.data)
+            .map_err(|e| ParseError::Other(format!("Unable to check the array output: {}", e)))?;
+        if arrays::enforce(&mut value, constraints).map_err(ParseError::ValidationError)? {
+            parsed.data = serde_json::from_value(value)
+                .map_err(|e| ParseError::Other(format!("Unable to trim the array output: {}", e)))?;
         }
+        Ok(parsed)
+    }
+
+    /// Run the field and output checks, failing with [ParseError::ValidationError] when the
+    /// validation options are strict and a check of [Severity::Error] fails
+    fn run_validators(&self, mut parsed: Response<T>) -> Result<Response<T>, ParseError> {
+        if self.field_validators.is_empty() && self.struct_validators.is_empty() {
+            return Ok(parsed);
+        }
+        let options = self.effective_validation_options();
+        let issues = validators::issues(&parsed.data, &self.field_validators, &self.struct_validators, &options);
+        if issues.is_empty() {
+            return Ok(parsed);
+        }
+
+        if options.require_all_required_properties && issues.iter().any(|issue| issue.severity == Severity::Error) {
+            let errors: Vec<&str> = issues
+                .iter()
+                .filter(|issue| issue.severity == Severity::Error)
+                .map(|issue| issue.message.as_str())
+                .collect();
+            return Err(ParseError::ValidationError(format!(
+                "Validation failed: {:?}",
+                errors
+            )));
+        }
+        parsed.validation_messages.get_or_insert_with(Vec::new).extend(issues.iter().map(|issue| issue.message.clone()));
+        parsed.validation_issues.extend(issues);
+        Ok(parsed)
     }
 
     /// Create a new structured generator with validation
-    pub fn new(config: Config<T>) -> Self {
-        let validator = if config.validate {
-            config
-                .schema
-                .as_ref()
-                .and_then(|_| serde_json::to_value(schema_for!(T)).ok())
-                .and_then(|schema| {
-                    JSONSchema::options()
-                        .with_resolver(EmptyResolver)
-                        .compile(&schema)
-                        .ok()
-                })
-        } else {
-            None
+    pub fn new(mut config: Config<T>) -> Self {
+        if config.json_schema.is_none() {
+            let schema = SchemaSettings::draft07()
+                .with(|settings| settings.inline_subschemas = true)
+                .into_generator()
+                .into_root_schema_for::<T>();
+            config.json_schema = serde_json::to_value(schema).ok();
+        }
+
+        let mut generator = Self {
+            config,
+            validator: None,
+            instruction: OnceLock::new(),
+            field_validators: Vec::new(),
+            struct_validators: Vec::new(),
         };
+        generator.compile_validator();
+        generator
+    }
+
+    /// Same as [Generator::new], but fails when validation is enabled and the JSON Schema of
+    /// the output doesn't compile, instead of failing every parse
+    pub fn try_new(config: Config<T>) -> Result<Self, ParseError> {
+        let generator = Self::new(config);
+        if let Some(Err(e)) = &generator.validator {
+            return Err(ParseError::ValidationError(e.clone()));
+        }
+        Ok(generator)
+    }
+
+    /// Options applied to validation. Without options validation errors are reported but
+    /// don't fail the parse
+    fn effective_validation_options(&self) -> ValidationOptions {
+        self.config.validation_options.clone().unwrap_or(ValidationOptions {
+            require_all_required_properties: false,
+            ..Default::default()
+        })
+    }
 
-        Self { config, validator }
+    /// Compile the JSON Schema of the output when validation is enabled
+    fn compile_validator(&mut self) {
+        self.validator = self.config.validate.then(|| {
+            let schema = self.config.json_schema.as_ref()
+                .ok_or_else(|| "No JSON Schema to validate against".to_string())?;
+            validation::compile(schema, &self.effective_validation_options())
+        });
     }
 
+
     /// Parse JSON response with validation
     fn parse_json_response(&self, response: &str) -> Result<Response<T>, ParseError> {
-        let data = extract_json_data(response)?;
-        self.validate_and_create_response(data, response)
+        let data = extract_json_with(self.config.extraction, response)?;
+        self.create_response(data, response)
     }
 
     #[cfg(feature = "yaml")]
     fn parse_yaml_response(&self, response: &str) -> Result<Response<T>, ParseError> {
         let data = extract_yaml(response)?;
-        self.validate_and_create_response(data, response)
+        self.create_response(data, response)
     }
 
     #[cfg(feature = "xml")]
     fn parse_xml_response(&self, response: &str) -> Result<Response<T>, ParseError> {
-        let data = extract_xml(response)?;
-        self.validate_and_create_response(data, response)
+        let data = extract_xml(response, &self.config.xml_mapping)?;
+        self.create_response(data, response)
+    }
+
+    #[cfg(feature = "toml")]
+    fn parse_toml_response(&self, response: &str) -> Result<Response<T>, ParseError> {
+        let data = extract_toml(response)?;
+        self.create_response(data, response)
+    }
+
+    #[cfg(feature = "csv")]
+    fn parse_csv_response(&self, response: &str) -> Result<Response<T>, ParseError> {
+        let data = extract_csv(response, self.config.json_schema.as_ref())?;
+        self.create_response(data, response)
+    }
+
+    #[cfg(feature = "protobuf")]
+    fn parse_textproto_response(&self, response: &str) -> Result<Response<T>, ParseError> {
+        let value = extract_textproto(response, self.config.json_schema.as_ref())?;
+        let data = serde_json::from_value(value)
+            .map_err(|e| ParseError::Extraction(format!("Unable to extract textproto: {}", e)))?;
+        self.create_response(data, response)
+    }
+
+    fn parse_markdown_table_response(&self, response: &str) -> Result<Response<T>, ParseError> {
+        let data = tabular::parse_markdown(response, self.config.json_schema.as_ref())?;
+        self.create_response(data, response)
+    }
+
+    /// Parse response in the custom format
+    fn parse_custom_response(&self, response: &str) -> Result<Response<T>, ParseError> {
+        let data = serde_json::from_value(self.extract_custom(response)?)
+            .map_err(|e| ParseError::Extraction(format!("Unable to extract custom format: {}", e)))?;
+        self.create_response(data, response)
     }
 
-    /// Validate data and create response
-    fn validate_and_create_response(
+    /// Answer of `response` in the custom format as a JSON value
+    fn extract_custom(&self, response: &str) -> Result<serde_json::Value, ParseError> {
+        match &self.config.custom_format {
+            Some(format) => format.extract(response),
+            None => Err(ParseError::Extraction("No custom format is configured".to_string())),
+        }
+    }
+
+    /// Create response from unvalidated data
+    fn create_response(
         &self,
         data: T,
         response: &str,
     ) -> Result<Response<T>, ParseError> {
-        if !self.config.validate || self.validator.is_none() {
-            return Ok(Response {
-                data,
-                raw_response: response.to_string(),
-                validation_messages: None,
-            });
-        }
+        Ok(Response {
+            data,
+            raw_response: response.to_string(),
+            validation_messages: None,
+            validation_issues: Vec::new(),
+            metadata: ResponseMetadata::default(),
+        })
+    }
 
-        let validator = self.validator.as_ref().unwrap();
-        let data_value = serde_json::to_value(&data).map_err(|e| {
-            ParseError::ValidationError(format!("Serialization for validation failed: {}", e))
-        })?;
+    /// Answer of `response` in `format` as a JSON value, to be validated before it is
+    /// converted to `T`
+    fn extract_value(&self, format: OutputFormat, response: &str) -> Result<serde_json::Value, ParseError> {
+        match format {
+            OutputFormat::Json | OutputFormat::JsonArray => extract_json_with(self.config.extraction, response),
+            #[cfg(feature = "yaml")]
+            OutputFormat::Yaml => yaml::read_as::<T, serde_json::Value>(response),
+            // XML and TOML are read into `T`, which types the values and picks the layout
+            #[cfg(feature = "xml")]
+            OutputFormat::Xml => to_json_value(extract_xml::<T>(response, &self.config.xml_mapping)?),
+            #[cfg(feature = "toml")]
+            OutputFormat::Toml => to_json_value(extract_toml::<T>(response)?),
+            #[cfg(feature = "csv")]
+            OutputFormat::Csv => extract_csv(response, self.config.json_schema.as_ref()),
+            #[cfg(feature = "protobuf")]
+            OutputFormat::TextProto => extract_textproto(response, self.config.json_schema.as_ref()),
+            OutputFormat::MarkdownTable => tabular::parse_markdown(response, self.config.json_schema.as_ref()),
+            OutputFormat::Custom => self.extract_custom(response),
+        }
+    }
 
-        // Execute validation first and store the result
-        let validation_result = validator.validate(&data_value);
-
-        // Build response based on validation result
-        match validation_result {
-            Ok(_) => Ok(Response {
-                data,
-                raw_response: response.to_string(),
-                validation_messages: None,
-            }),
-            Err(errors) => {
-                let validation_messages: Vec<_> =
-                    errors.into_iter().map(|e| e.to_string()).collect();
-
-                if self
-                    .config
-                    .validation_options
-                    .as_ref()
-                    .map_or(false, |opts| opts.require_all_required_properties)
-                {
-                    return Err(ParseError::ValidationError(format!(
-                        "Validation failed: {:?}",
-                        validation_messages
-                    )));
-                }
+    /// Validate `value` against the JSON Schema of the output and convert it to `T`
+    fn validate_value(&self, mut value: serde_json::Value, response: &str) -> Result<Response<T>, ParseError> {
+        let validator = match &self.validator {
+            Some(Ok(validator)) => validator,
+            Some(Err(e)) => return Err(ParseError::ValidationError(e.clone())),
+            None => return Err(ParseError::ValidationError("Validator not compiled".to_string())),
+        };
+        let options = self.effective_validation_options();
 
-                Ok(Response {
-                    data,
-                    raw_response: response.to_string(),
-                    validation_messages: Some(validation_messages),
-                })
+        if options.coerce_numeric_strings {
+            if let Some(schema) = &self.config.json_schema {
+                validation::coerce_numeric_strings(&mut value, schema);
             }
         }
+
+        let mut validation_issues = validation::issues(validator, &value, &options);
+        if let Some(schema) = &self.config.json_schema {
+            let regions = regions::region_fields(schema);
+            validation_issues.extend(regions::issues(&value, &regions, &options));
+        }
+        validation_issues.truncate(options.max_errors.unwrap_or(usize::MAX));
+        let validation_messages: Vec<String> = validation_issues.iter().map(|issue| issue.message.clone()).collect();
+        if options.require_all_required_properties && validation_issues.iter().any(|issue| issue.severity == Severity::Error) {
+            let errors: Vec<&str> = validation_issues
+                .iter()
+                .filter(|issue| issue.severity == Severity::Error)
+                .map(|issue| issue.message.as_str())
+                .collect();
+            return Err(ParseError::ValidationError(format!(
+                "Validation failed: {:?}",
+                errors
+            )));
+        }
+
+        let data = serde_json::from_value(value).map_err(|e| {
+            if validation_messages.is_empty() {
+                ParseError::Extraction(format!("Unable to extract data: {}", e))
+            } else {
+                ParseError::ValidationError(format!("Validation failed: {:?}", validation_messages))
+            }
+        })?;
+
+        Ok(Response {
+            data,
+            raw_response: response.to_string(),
+            validation_messages: (!validation_messages.is_empty()).then_some(validation_messages),
+            validation_issues,
+            metadata: ResponseMetadata::default(),
+        })
     }
 
     /// Parse response and return only the data if successful
@@ -275,19 +1122,86 @@ where
 // Extract common parsing functions to reduce code duplication
 /// Extract JSON data from a response string
 /// This function can handle both single JSON objects and JSON arrays
+/// `data` as a JSON value
+#[cfg(any(feature = "xml", feature = "toml"))]
+fn to_json_value<D: Serialize>(data: D) -> Result<serde_json::Value, ParseError> {
+    serde_json::to_value(data)
+        .map_err(|e| ParseError::ValidationError(format!("Serialization for validation failed: {}", e)))
+}
+
 fn extract_json_data<T: for<'de> Deserialize<'de>>(response: &str) -> Result<T, ParseError> {
-    // First try to extract JSON from code blocks
-    let json_str = JSON_REGEX
-        .captures(response)
-        .and_then(|captures| captures.get(1))
-        .map(|m| m.as_str())
-        .unwrap_or(response);
+    extract_json_with(ExtractionStrategy::default(), response)
+}
+
+/// Extract JSON data located with `strategy`
+fn extract_json_with<T: for<'de> Deserialize<'de>>(
+    strategy: ExtractionStrategy,
+    response: &str,
+) -> Result<T, ParseError> {
+    let json_str = extraction::locate(strategy, response);
 
     // Parse the JSON string, which can be either an object or an array
     serde_json::from_str(json_str)
         .map_err(|e| ParseError::Extraction(format!("Unable to extract JSON data: {}", e)))
 }
 
+/// Byte range of `block` in `response`, `block` being a slice of it or of a copy with the
+/// reasoning stripped
+fn span_in(response: &str, block: &str) -> Option<std::ops::Range<usize>> {
+    let start = (block.as_ptr() as usize)
+        .checked_sub(response.as_ptr() as usize)
+        .filter(|start| start + block.len() <= response.len())
+        .or_else(|| response.find(block))?;
+    Some(start..start + block.len())
+}
+
+/// Content of the first code fence `regex` matches in `response`
+fn fenced<'a>(regex: &Regex, response: &'a str) -> Option<&'a str> {
+    regex
+        .captures(response)
+        .and_then(|captures| captures.get(1))
+        .map(|m| m.as_str())
+}
+
+/// Languages of the code fences written in `format`
+fn fence_languages(format: OutputFormat) -> &'static [&'static str] {
+    match format {
+        OutputFormat::Json | OutputFormat::JsonArray => &["json", "jsonc"],
+        #[cfg(feature = "yaml")]
+        OutputFormat::Yaml => &["yaml", "yml"],
+        #[cfg(feature = "xml")]
+        OutputFormat::Xml => &["xml"],
+        #[cfg(feature = "toml")]
+        OutputFormat::Toml => &["toml"],
+        #[cfg(feature = "csv")]
+        OutputFormat::Csv => &["csv"],
+        #[cfg(feature = "protobuf")]
+        OutputFormat::TextProto => &["textproto", "pbtxt", "prototext"],
+        OutputFormat::MarkdownTable => &["markdown", "md"],
+        OutputFormat::Custom => &[],
+    }
+}
+
+/// Every top-level JSON object or array in `response`, in order
+fn json_values(response: &str) -> Vec<&str> {
+    let mut values = vec![];
+    let mut offset = 0;
+    while let Some(start) = response[offset..].find(['{', '[']) {
+        let start = offset + start;
+        let mut values_at = serde_json::Deserializer::from_str(&response[start..])
+            .into_iter::<serde::de::IgnoredAny>();
+        match values_at.next() {
+            Some(Ok(_)) => {
+                let end = start + values_at.byte_offset();
+                values.push(&response[start..end]);
+                offset = end;
+            }
+            _ => offset = start + 1,
+        }
+    }
+    values
+}
+
 /// Kept for backward compatibility, delegates to extract_json_data
 fn extract_json<T: for<'de> Deserialize<'de>>(response: &str) -> Result<T, ParseError> {
     extract_json_data(response)
@@ -301,33 +1215,71 @@ fn extract_json_array<T: for<'de> Deserialize<'de>>(response: &str) -> Result<T,
 #[cfg(feature = "yaml")]
 /// Extract YAML data from a response string
 fn extract_yaml<T: for<'de> Deserialize<'de>>(response: &str) -> Result<T, ParseError> {
-    YAML_REGEX
-        .captures(response)
-        .and_then(|captures| captures.get(1))
-        .map(|yaml_str| serde_yaml::from_str(yaml_str.as_str()))
-        .unwrap_or_else(|| serde_yaml::from_str(response))
-        .map_err(|e| ParseError::Extraction(format!("Unable to extract YAML: {}", e)))
+    yaml::read(response)
 }
 
 #[cfg(feature = "xml")]
-/// Extract XML data from a response string
-fn extract_xml<T: for<'de> Deserialize<'de>>(response: &str) -> Result<T, ParseError> {
-    XML_REGEX
-        .captures(response)
-        .and_then(|captures| captures.get(1))
-        .map(|xml_str| {
-            xml_from_str(xml_str.as_str()).map_err(|e| ParseError::XmlParse(e.to_string()))
-        })
+/// Extract XML data from a response string, with attributes read like child elements and
+/// namespaces handled as `mapping` says
+fn extract_xml<T: for<'de> Deserialize<'de>>(response: &str, mapping: &XmlMapping) -> Result<T, ParseError> {
+    let read = |xml: &str| {
+        let normalized = xml::normalize(xml, mapping);
+        xml_from_str(normalized.as_deref().unwrap_or(xml))
+    };
+    fenced(xml_regex(), response)
+        .map(|xml_str| read(xml_str).map_err(|e| ParseError::XmlParse(e.to_string())))
         .unwrap_or_else(|| {
-            xml_from_str(response)
+            read(response)
                 .map_err(|e| ParseError::XmlParse(format!("Unable to extract XML: {}", e)))
         })
 }
 
+#[cfg(feature = "toml")]
+/// Extract TOML data from a response string, unwrapping arrays written as `items`
+fn extract_toml<T: for<'de> Deserialize<'de>>(response: &str) -> Result<T, ParseError> {
+    let toml_str = fenced(toml_regex(), response).unwrap_or(response);
+
+    let table: toml::Table = toml::from_str(toml_str)
+        .map_err(|e| ParseError::Extraction(format!("Unable to extract TOML: {}", e)))?;
+    let items = match table.get("items") {
+        Some(items @ toml::Value::Array(_)) if table.len() == 1 => Some(items.clone()),
+        _ => None,
+    };
+    toml::Value::Table(table)
+        .try_into()
+        .or_else(|e| items.map_or(Err(e), |items| items.try_into()))
+        .map_err(|e| ParseError::Extraction(format!("Unable to extract TOML: {}", e)))
+}
+
+#[cfg(feature = "csv")]
+/// Extract a CSV table from a response string, typing its cells after `schema`
+fn extract_csv<T: for<'de> Deserialize<'de>>(
+    response: &str,
+    schema: Option<&serde_json::Value>,
+) -> Result<T, ParseError> {
+    let table = fenced(csv_regex(), response).unwrap_or(response);
+    tabular::parse_csv(table, schema)
+}
+
+#[cfg(feature = "protobuf")]
+/// Extract a protobuf text format message from a response string, with the fields `schema`
+/// declares as arrays read as arrays
+fn extract_textproto(
+    response: &str,
+    schema: Option<&serde_json::Value>,
+) -> Result<serde_json::Value, ParseError> {
+    let message = fenced(textproto_regex(), response).unwrap_or(response);
+    proto::parse_text(message, schema)
+        .map_err(|e| ParseError::Extraction(format!("Unable to extract textproto: {}", e)))
+}
+
 /// Implementation of Default trait for single object types
 ///
 /// This allows users to create generator instances in a more concise way:
 /// ```
+/// # use async_openai::structured::Generator;
+/// # #[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+/// # struct MyType { name: String }
 /// let generator = Generator::<MyType>::default();
 /// ```
 ///
@@ -368,6 +1320,32 @@ where
     pub fn xml(schema: T) -> Self {
         Self::with_schema(schema).format(OutputFormat::Xml)
     }
+
+    #[cfg(feature = "toml")]
+    /// Create a generator with TOML format output
+    pub fn toml(schema: T) -> Self {
+        Self::with_schema(schema).format(OutputFormat::Toml)
+    }
+
+    #[cfg(feature = "csv")]
+    /// Create a generator with CSV format output, for lists of flat objects such as
+    /// `Vec<Row>`. Tables take far fewer tokens than JSON for large lists
+    pub fn csv(schema: T) -> Self {
+        Self::with_schema(schema).format(OutputFormat::Csv)
+    }
+
+    #[cfg(feature = "protobuf")]
+    /// Create a generator with protobuf text format output, describing the schema as a
+    /// `.proto` message
+    pub fn textproto(schema: T) -> Self {
+        Self::with_schema(schema).format(OutputFormat::TextProto)
+    }
+
+    /// Create a generator with markdown table output, for lists of flat objects such as
+    /// `Vec<Row>`
+    pub fn markdown_table(schema: T) -> Self {
+        Self::with_schema(schema).format(OutputFormat::MarkdownTable)
+    }
 }
 
 /// Convenience constructors for common data structures
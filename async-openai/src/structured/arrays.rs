@@ -0,0 +1,63 @@
+//! Checking and trimming of array outputs against their [ArrayConstraints].
+use std::collections::HashSet;
+
+use serde_json::Value;
+
+use crate::types::structured::ArrayConstraints;
+
+/// Check the items of the array `value` against `constraints`, dropping duplicates and the
+/// items past the maximum when the constraints trim. Returns whether `value` changed.
+///
+/// Values that aren't arrays are left alone.
+pub(super) fn enforce(value: &mut Value, constraints: &ArrayConstraints) -> Result<bool, String> {
+    let Value::Array(items) = value else {
+        return Ok(false);
+    };
+    let count = items.len();
+
+    if let Some(field) = &constraints.unique_by {
+        let pointer = format!("/{}", field.replace('.', "/"));
+        let mut seen = HashSet::new();
+        let mut duplicates = Vec::new();
+        for (index, item) in items.iter().enumerate() {
+            // Items without the field can't collide
+            let Some(key) = item.pointer(&pointer) else {
+                continue;
+            };
+            if !seen.insert(key.to_string()) {
+                duplicates.push((index, key.to_string()));
+            }
+        }
+
+        if !duplicates.is_empty() && !constraints.trim {
+            let (index, key) = &duplicates[0];
+            return Err(format!(
+                "/{index}: `{field}` {key} is not unique ({} duplicates)",
+                duplicates.len()
+            ));
+        }
+        for (index, _) in duplicates.iter().rev() {
+            items.remove(*index);
+        }
+    }
+
+    if items.len() > constraints.max_items {
+        if !constraints.trim {
+            return Err(format!(
+                "expected at most {} items, got {}",
+                constraints.max_items,
+                items.len()
+            ));
+        }
+        items.truncate(constraints.max_items);
+    }
+    if items.len() < constraints.min_items {
+        return Err(format!(
+            "expected at least {} items, got {}",
+            constraints.min_items,
+            items.len()
+        ));
+    }
+
+    Ok(items.len() != count)
+}
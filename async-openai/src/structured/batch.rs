@@ -0,0 +1,136 @@
+//! Structured extraction over many inputs with bounded concurrency.
+use std::sync::Arc;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tokio::sync::Semaphore;
+
+use super::Generator;
+use crate::{
+    config::Config,
+    error::OpenAIError,
+    types::{
+        structured::{Response, Structured},
+        ChatCompletionRequestSystemMessage, ChatCompletionRequestUserMessage,
+        CreateChatCompletionRequestArgs,
+    },
+    Client,
+};
+
+/// Requests in flight at once unless configured otherwise.
+pub const DEFAULT_BATCH_CONCURRENCY: usize = 8;
+
+/// Sends one request per input and parses every response with the same [Generator].
+///
+/// Each request sends the generator's instruction as the system message and the input as the
+/// user message. At most [BatchGenerator::concurrency] requests are in flight at once, or as
+/// many as a shared [Semaphore] allows, and results come back in the order of the inputs.
+///
+/// ```no_run
+/// # async fn run() {
+/// use async_openai::{
+///     structured::{BatchGenerator, Generator},
+///     Client,
+/// };
+/// # #[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+/// # struct Contact { name: String, email: String }
+///
+/// let client = Client::new();
+/// let documents = vec!["Reach me at ada@example.com - Ada".to_string()];
+/// let results = BatchGenerator::new(&client, "gpt-4o-mini", Generator::json(Contact::default()))
+///     .concurrency(16)
+///     .run(documents)
+///     .await;
+///
+/// for result in results {
+///     match result {
+///         Ok(response) => println!("{:?}", response.data),
+///         Err(e) => eprintln!("{e}"),
+///     }
+/// }
+/// # }
+/// ```
+pub struct BatchGenerator<'c, C: Config, T>
+where
+    T: Structured + for<'de> Deserialize<'de> + JsonSchema,
+{
+    client: &'c Client<C>,
+    model: String,
+    temperature: Option<f32>,
+    generator: Generator<T>,
+    semaphore: Arc<Semaphore>,
+}
+
+impl<'c, C: Config, T> BatchGenerator<'c, C, T>
+where
+    T: Structured + for<'de> Deserialize<'de> + JsonSchema,
+{
+    pub fn new(client: &'c Client<C>, model: impl Into<String>, generator: Generator<T>) -> Self {
+        Self {
+            client,
+            model: model.into(),
+            temperature: None,
+            generator,
+            semaphore: Arc::new(Semaphore::new(DEFAULT_BATCH_CONCURRENCY)),
+        }
+    }
+
+    /// Sampling temperature of every request, the model default otherwise.
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    /// Requests in flight at once, [DEFAULT_BATCH_CONCURRENCY] by default. Zero is treated as
+    /// one.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.semaphore = Arc::new(Semaphore::new(concurrency.max(1)));
+        self
+    }
+
+    /// Take permits from `semaphore` instead, e.g. to share a limit between several batches.
+    pub fn with_semaphore(mut self, semaphore: Arc<Semaphore>) -> Self {
+        self.semaphore = semaphore;
+        self
+    }
+
+    /// Send a request per input and parse the responses, in the order of `inputs`.
+    ///
+    /// A failed request or a response that doesn't parse only fails its own entry, the latter
+    /// as [OpenAIError::StructuredOutput]. Responses with validation messages are returned as
+    /// they are.
+    pub async fn run<I>(&self, inputs: I) -> Vec<Result<Response<T>, OpenAIError>>
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let instruction = self.generator.build_instruction_text();
+        futures::future::join_all(
+            inputs
+                .into_iter()
+                .map(|input| self.extract(&instruction, input.into())),
+        )
+        .await
+    }
+
+    async fn extract(&self, instruction: &str, input: String) -> Result<Response<T>, OpenAIError> {
+        let mut request = CreateChatCompletionRequestArgs::default();
+        request.model(&self.model).messages([
+            ChatCompletionRequestSystemMessage::from(instruction).into(),
+            ChatCompletionRequestUserMessage::from(input).into(),
+        ]);
+        if let Some(temperature) = self.temperature {
+            request.temperature(temperature);
+        }
+        let request = request.build()?;
+
+        let _permit =
+            self.semaphore.acquire().await.map_err(|_| {
+                OpenAIError::InvalidArgument("batch: the semaphore is closed".into())
+            })?;
+        let response = self.client.chat().create(request).await?;
+        self.generator
+            .parse_completion(&response)
+            .map_err(|e| OpenAIError::StructuredOutput(Box::new(e)))
+    }
+}
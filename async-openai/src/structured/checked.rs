@@ -0,0 +1,69 @@
+//! A single gate over model output: schema validation, a validation function and moderation.
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use super::Generator;
+use crate::{
+    config::Config,
+    error::OpenAIError,
+    types::{
+        structured::{CheckFlag, Checked, Response, Structured},
+        CreateModerationRequest,
+    },
+    Client,
+};
+
+impl<T> Generator<T>
+where
+    T: Structured + for<'de> Deserialize<'de> + JsonSchema,
+{
+    /// Run a parsed response through moderation and through `validate`, collecting the
+    /// validation messages of the response and every problem found into the flags of a
+    /// single [Checked] result.
+    ///
+    /// The raw response is moderated, so that text the data leaves out is checked too.
+    /// `validate` returns why the data can't be used, if anything. Errors of the moderation
+    /// request are returned as they are.
+    pub async fn check<C: Config, F>(
+        &self,
+        client: &Client<C>,
+        response: Response<T>,
+        validate: F,
+    ) -> Result<Checked<T>, OpenAIError>
+    where
+        F: Fn(&T) -> Result<(), String>,
+    {
+        let mut flags: Vec<CheckFlag> = response
+            .validation_messages
+            .iter()
+            .flatten()
+            .map(|message| CheckFlag::Schema {
+                message: message.clone(),
+            })
+            .collect();
+        if let Err(message) = validate(&response.data) {
+            flags.push(CheckFlag::Validation { message });
+        }
+
+        let moderation = client
+            .moderations()
+            .create(CreateModerationRequest {
+                input: response.raw_response.as_str().into(),
+                model: None,
+            })
+            .await?;
+        let mut categories: Vec<String> = Vec::new();
+        for result in moderation.results.iter().filter(|result| result.flagged) {
+            for category in result.categories.flagged() {
+                if !categories.contains(&category) {
+                    categories.push(category);
+                }
+            }
+        }
+        if moderation.results.iter().any(|result| result.flagged) {
+            flags.push(CheckFlag::Moderation { categories });
+        }
+
+        Ok(Checked { response, flags })
+    }
+}
@@ -0,0 +1,328 @@
+//! Several [Generator]s answered in one response, each under its own top-level key.
+use std::{any::Any, marker::PhantomData};
+
+use indexmap::IndexMap;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use super::{extract_json_data, Generator};
+use crate::types::structured::{Instruction, ParseError, Response, Structured};
+
+/// Type erased [Generator] of a section.
+trait Section: Send + Sync {
+    fn prefix(&self) -> Option<&str>;
+    fn example(&self) -> Option<Value>;
+    fn schema(&self) -> Option<Value>;
+    fn parse(&self, section: &str) -> Result<Box<dyn Any + Send>, ParseError>;
+}
+
+impl<T> Section for Generator<T>
+where
+    T: Structured + for<'de> Deserialize<'de> + JsonSchema + Send + Sync + 'static,
+{
+    fn prefix(&self) -> Option<&str> {
+        self.config.prefix.as_deref()
+    }
+
+    fn example(&self) -> Option<Value> {
+        serde_json::to_value(self.config.schema.as_ref()?).ok()
+    }
+
+    fn schema(&self) -> Option<Value> {
+        self.config.presented_json_schema()
+    }
+
+    fn parse(&self, section: &str) -> Result<Box<dyn Any + Send>, ParseError> {
+        let parsed = self.parse_json_response(section)?;
+        Ok(Box::new(parsed))
+    }
+}
+
+/// Extracts the data of several generators from a single JSON response, e.g. entities and
+/// sentiment of the same text in one request.
+///
+/// The prefix of each generator describes its key; formats of the generators are ignored, the
+/// response is always one JSON object.
+///
+/// ```
+/// # #[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+/// # struct Entities { people: Vec<String> }
+/// # #[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+/// # struct Sentiment { score: f32 }
+/// use async_openai::structured::{CompositeGenerator, Generator};
+///
+/// let composite = CompositeGenerator::new()
+///     .with("entities", Generator::json(Entities::default()).prefix("People mentioned"))
+///     .with("sentiment", Generator::json(Sentiment::default()));
+/// let instruction = composite.build_instruction_text();
+///
+/// # let content = r#"{"entities": {"people": ["Ada"]}, "sentiment": {"score": 0.8}}"#;
+/// let mut parsed = composite.parse_response(content).unwrap();
+/// let entities = parsed.take::<Entities>("entities").unwrap();
+/// let sentiment = parsed.take::<Sentiment>("sentiment").unwrap();
+/// assert_eq!(entities.data.people, ["Ada"]);
+/// ```
+#[derive(Default)]
+pub struct CompositeGenerator {
+    prefix: Option<String>,
+    sections: IndexMap<String, Box<dyn Section>>,
+}
+
+impl CompositeGenerator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Text put before the description of the sections
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = Some(prefix.into());
+        self
+    }
+
+    /// Add a section answered under `key`, replacing an earlier section with the same key
+    pub fn with<T>(mut self, key: impl Into<String>, generator: Generator<T>) -> Self
+    where
+        T: Structured + for<'de> Deserialize<'de> + JsonSchema + Send + Sync + 'static,
+    {
+        self.sections.insert(key.into(), Box::new(generator));
+        self
+    }
+
+    /// Rename the section at `index`, keeping its position
+    fn rename(&mut self, index: usize, key: String) {
+        self.sections = std::mem::take(&mut self.sections)
+            .into_iter()
+            .enumerate()
+            .map(|(i, (old, section))| (if i == index { key.clone() } else { old }, section))
+            .collect();
+    }
+
+    /// Instruction describing the combined object, with the example and schema of every
+    /// section
+    pub fn build_instruction(&self) -> Instruction {
+        let mut content = String::new();
+        if let Some(prefix) = &self.prefix {
+            content.push_str(prefix);
+            content.push_str("\n\n");
+        }
+
+        content.push_str("Please return the response as one JSON object with these keys:\n");
+        let mut example = Map::new();
+        let mut properties = Map::new();
+        for (key, section) in &self.sections {
+            match section.prefix() {
+                Some(prefix) => content.push_str(&format!("- {}: {}\n", key, prefix)),
+                None => content.push_str(&format!("- {}\n", key)),
+            }
+            example.insert(key.clone(), section.example().unwrap_or(Value::Null));
+            properties.insert(key.clone(), section.schema().unwrap_or_default());
+        }
+
+        let keys: Vec<_> = self.sections.keys().cloned().map(Value::String).collect();
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": properties,
+            "required": keys,
+        });
+        if let (Ok(example), Ok(schema)) = (
+            serde_json::to_string_pretty(&example),
+            serde_json::to_string_pretty(&schema),
+        ) {
+            content.push_str(&format!("\nExample format:\n```json\n{}\n```\n", example));
+            content.push_str(&format!(
+                "\nJSON Schema information:\n```json\n{}\n```\n",
+                schema
+            ));
+        }
+
+        Instruction {
+            content: content.into(),
+        }
+    }
+
+    pub fn build_instruction_text(&self) -> String {
+        self.build_instruction().content.to_string()
+    }
+
+    /// Split the response into its sections and parse each with its generator.
+    ///
+    /// Fails only when the response has no JSON object; sections that are missing or don't
+    /// parse report their error from [CompositeResponse::take].
+    pub fn parse_response(&self, response: &str) -> Result<CompositeResponse, ParseError> {
+        let envelope: Map<String, Value> = extract_json_data(response)?;
+
+        let sections = self
+            .sections
+            .iter()
+            .map(|(key, section)| {
+                let parsed = match envelope.get(key) {
+                    Some(value) => section.parse(&value.to_string()),
+                    None => Err(ParseError::Extraction(format!("Missing section `{}`", key))),
+                };
+                (key.clone(), parsed)
+            })
+            .collect();
+
+        Ok(CompositeResponse {
+            raw_response: response.to_string(),
+            sections,
+        })
+    }
+}
+
+/// Sections of a response parsed by a [CompositeGenerator].
+pub struct CompositeResponse {
+    /// Raw response
+    pub raw_response: String,
+    sections: IndexMap<String, Result<Box<dyn Any + Send>, ParseError>>,
+}
+
+impl CompositeResponse {
+    /// Keys of the sections not taken yet, in the order they were added
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.sections.keys().map(String::as_str)
+    }
+
+    /// Take the parsed section `key`, `T` being the type of its generator
+    pub fn take<T>(&mut self, key: &str) -> Result<Response<T>, ParseError>
+    where
+        T: Structured + for<'de> Deserialize<'de> + 'static,
+    {
+        let parsed = self
+            .sections
+            .shift_remove(key)
+            .ok_or_else(|| ParseError::Other(format!("Unknown section `{}`", key)))??;
+
+        parsed
+            .downcast::<Response<T>>()
+            .map(|parsed| *parsed)
+            .map_err(|_| {
+                ParseError::Other(format!(
+                    "Section `{}` is not a {}",
+                    key,
+                    std::any::type_name::<T>()
+                ))
+            })
+    }
+}
+
+impl<A> Generator<A>
+where
+    A: Structured + for<'de> Deserialize<'de> + JsonSchema + Send + Sync + 'static,
+{
+    /// Ask for the data of this generator and of the default generator of `B` in one
+    /// response, see [GeneratorPair]
+    pub fn and<B>(self) -> GeneratorPair<A, B>
+    where
+        B: Structured + for<'de> Deserialize<'de> + JsonSchema + Default + Send + Sync + 'static,
+    {
+        self.and_with(Generator::default())
+    }
+
+    /// Ask for the data of this generator and of `other` in one response, see
+    /// [GeneratorPair]
+    pub fn and_with<B>(self, other: Generator<B>) -> GeneratorPair<A, B>
+    where
+        B: Structured + for<'de> Deserialize<'de> + JsonSchema + Send + Sync + 'static,
+    {
+        let first = section_key::<A>();
+        let mut second = section_key::<B>();
+        if second == first {
+            second.push_str("_2");
+        }
+        GeneratorPair {
+            composite: CompositeGenerator::new()
+                .with(first, self)
+                .with(second, other),
+            types: PhantomData,
+        }
+    }
+}
+
+/// Two generators answered in one response, each under its own key, with both sections
+/// parsed into their types at once. Built by [Generator::and].
+///
+/// The keys default to the schema names of the types in snake case, e.g. `summary` for
+/// `Summary`; [GeneratorPair::keys] names them after what they hold.
+///
+/// ```
+/// # #[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+/// # struct Summary { text: String }
+/// # #[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+/// # struct Entities { people: Vec<String> }
+/// use async_openai::structured::Generator;
+///
+/// let pair = Generator::<Summary>::default().and::<Entities>();
+/// let instruction = pair.build_instruction_text();
+///
+/// # let content = r#"{"summary": {"text": "Ada wrote a program"}, "entities": {"people": ["Ada"]}}"#;
+/// let (summary, entities) = pair.parse_composite(content).unwrap();
+/// assert_eq!(entities.data.people, ["Ada"]);
+/// ```
+pub struct GeneratorPair<A, B> {
+    composite: CompositeGenerator,
+    types: PhantomData<fn() -> (A, B)>,
+}
+
+impl<A, B> GeneratorPair<A, B>
+where
+    A: Structured + for<'de> Deserialize<'de> + 'static,
+    B: Structured + for<'de> Deserialize<'de> + 'static,
+{
+    /// Answer the sections under `first` and `second`
+    pub fn keys(mut self, first: impl Into<String>, second: impl Into<String>) -> Self {
+        self.composite.rename(0, first.into());
+        self.composite.rename(1, second.into());
+        self
+    }
+
+    /// Text put before the description of the sections
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.composite = self.composite.prefix(prefix);
+        self
+    }
+
+    pub fn build_instruction(&self) -> Instruction {
+        self.composite.build_instruction()
+    }
+
+    pub fn build_instruction_text(&self) -> String {
+        self.composite.build_instruction_text()
+    }
+
+    /// Parse both sections of the response, failing when either is missing or doesn't parse
+    pub fn parse_composite(
+        &self,
+        response: &str,
+    ) -> Result<(Response<A>, Response<B>), ParseError> {
+        let mut parsed = self.composite.parse_response(response)?;
+        let keys: Vec<String> = parsed.keys().map(String::from).collect();
+        let first = parsed.take::<A>(&keys[0])?;
+        let second = parsed.take::<B>(&keys[1])?;
+        Ok((first, second))
+    }
+
+    /// The underlying [CompositeGenerator], e.g. to add more sections
+    pub fn into_composite(self) -> CompositeGenerator {
+        self.composite
+    }
+}
+
+/// Default key of the section of `T`: its schema name in snake case
+fn section_key<T: JsonSchema>() -> String {
+    let mut key = String::new();
+    for c in T::schema_name().chars() {
+        if c.is_ascii_uppercase() {
+            if !key.is_empty() && !key.ends_with('_') {
+                key.push('_');
+            }
+            key.push(c.to_ascii_lowercase());
+        } else if c.is_ascii_alphanumeric() {
+            key.push(c);
+        } else if !key.ends_with('_') {
+            key.push('_');
+        }
+    }
+    key
+}
@@ -0,0 +1,288 @@
+//! Mapping of token log probabilities onto the fields of a parsed response.
+use std::{collections::BTreeMap, ops::Range};
+
+use serde_json::Value;
+
+use crate::types::{
+    structured::{FieldConfidence, OutputFormat},
+    ChatCompletionTokenLogprob,
+};
+
+/// Confidence of every leaf of `data` that can be located in `response`.
+///
+/// `logprobs` must be the tokens `response` was generated from; when they don't add up to it,
+/// nothing is reported.
+pub(super) fn field_confidence(
+    response: &str,
+    format: OutputFormat,
+    data: &Value,
+    logprobs: &[ChatCompletionTokenLogprob],
+) -> BTreeMap<String, FieldConfidence> {
+    let Some(tokens) = token_ranges(response, logprobs) else {
+        return BTreeMap::new();
+    };
+
+    let json_spans = match format {
+        OutputFormat::Json | OutputFormat::JsonArray => json_spans(response),
+        #[allow(unreachable_patterns)]
+        _ => BTreeMap::new(),
+    };
+
+    let mut leaves = Vec::new();
+    collect_leaves(data, String::new(), &mut leaves);
+
+    leaves
+        .into_iter()
+        .filter_map(|(path, value)| {
+            let span = match json_spans.get(&path) {
+                Some(span) => span.clone(),
+                None => search_span(response, &path, value)?,
+            };
+            let logprobs: Vec<f32> = tokens
+                .iter()
+                .filter(|(range, _)| range.start < span.end && range.end > span.start)
+                .map(|(_, logprob)| *logprob)
+                .collect();
+            if logprobs.is_empty() {
+                return None;
+            }
+
+            let mean = logprobs.iter().sum::<f32>() / logprobs.len() as f32;
+            let min = logprobs.iter().copied().fold(f32::INFINITY, f32::min);
+            Some((
+                path,
+                FieldConfidence {
+                    confidence: mean.exp(),
+                    min_probability: min.exp(),
+                    tokens: logprobs.len(),
+                },
+            ))
+        })
+        .collect()
+}
+
+/// Geometric mean of the probabilities of the tokens overlapping `span` of `response`.
+pub(super) fn span_confidence(
+    response: &str,
+    span: Range<usize>,
+    logprobs: &[ChatCompletionTokenLogprob],
+) -> Option<f32> {
+    let logprobs: Vec<f32> = token_ranges(response, logprobs)?
+        .into_iter()
+        .filter(|(range, _)| range.start < span.end && range.end > span.start)
+        .map(|(_, logprob)| logprob)
+        .collect();
+    if logprobs.is_empty() {
+        return None;
+    }
+    Some((logprobs.iter().sum::<f32>() / logprobs.len() as f32).exp())
+}
+
+/// Byte range of each token in `response` with its logprob, `None` when the tokens don't
+/// add up to `response`.
+fn token_ranges(
+    response: &str,
+    logprobs: &[ChatCompletionTokenLogprob],
+) -> Option<Vec<(Range<usize>, f32)>> {
+    let mut tokens = Vec::with_capacity(logprobs.len());
+    let mut offset = 0;
+    for logprob in logprobs {
+        let len = logprob
+            .bytes
+            .as_ref()
+            .map_or(logprob.token.len(), |bytes| bytes.len());
+        tokens.push((offset..offset + len, logprob.logprob));
+        offset += len;
+    }
+    if offset != response.len() {
+        tracing::warn!(
+            "logprobs cover {offset} bytes but the response has {}, skipping confidence",
+            response.len()
+        );
+        return None;
+    }
+    Some(tokens)
+}
+
+/// Scalar values of `value` with their paths, `null`s excluded.
+fn collect_leaves<'a>(value: &'a Value, path: String, leaves: &mut Vec<(String, &'a Value)>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                collect_leaves(value, child_path(&path, key), leaves);
+            }
+        }
+        Value::Array(items) => {
+            for (index, value) in items.iter().enumerate() {
+                collect_leaves(value, format!("{path}[{index}]"), leaves);
+            }
+        }
+        Value::Null => {}
+        _ => leaves.push((path, value)),
+    }
+}
+
+fn child_path(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{path}.{key}")
+    }
+}
+
+/// Byte spans of the scalar values of the JSON document in `response`, string values without
+/// their quotes.
+fn json_spans(response: &str) -> BTreeMap<String, Range<usize>> {
+    let (text, offset) = match super::json_regex()
+        .captures(response)
+        .and_then(|captures| captures.get(1))
+    {
+        Some(block) => (block.as_str(), block.start()),
+        None => (response, 0),
+    };
+
+    let mut scanner = JsonScanner {
+        text: text.as_bytes(),
+        pos: 0,
+        offset,
+        spans: BTreeMap::new(),
+    };
+    // A malformed document still keeps the spans found before the error
+    let _ = scanner.value(String::new());
+    scanner.spans
+}
+
+struct JsonScanner<'a> {
+    text: &'a [u8],
+    pos: usize,
+    offset: usize,
+    spans: BTreeMap<String, Range<usize>>,
+}
+
+impl JsonScanner<'_> {
+    fn value(&mut self, path: String) -> Option<()> {
+        self.skip_whitespace();
+        match *self.text.get(self.pos)? {
+            b'{' => {
+                self.pos += 1;
+                loop {
+                    self.skip_whitespace();
+                    match *self.text.get(self.pos)? {
+                        b'}' => break,
+                        b',' => self.pos += 1,
+                        _ => {
+                            let key = self.string()?;
+                            let key: String =
+                                serde_json::from_slice(&self.text[key.start - 1..key.end + 1])
+                                    .ok()?;
+                            self.skip_whitespace();
+                            self.expect(b':')?;
+                            self.value(child_path(&path, &key))?;
+                        }
+                    }
+                }
+                self.pos += 1;
+            }
+            b'[' => {
+                self.pos += 1;
+                let mut index = 0;
+                loop {
+                    self.skip_whitespace();
+                    match *self.text.get(self.pos)? {
+                        b']' => break,
+                        b',' => self.pos += 1,
+                        _ => {
+                            self.value(format!("{path}[{index}]"))?;
+                            index += 1;
+                        }
+                    }
+                }
+                self.pos += 1;
+            }
+            b'"' => {
+                let span = self.string()?;
+                self.spans
+                    .insert(path, span.start + self.offset..span.end + self.offset);
+            }
+            _ => {
+                let start = self.pos;
+                while self.pos < self.text.len()
+                    && !matches!(self.text[self.pos], b',' | b'}' | b']')
+                    && !self.text[self.pos].is_ascii_whitespace()
+                {
+                    self.pos += 1;
+                }
+                if self.pos == start {
+                    return None;
+                }
+                self.spans
+                    .insert(path, start + self.offset..self.pos + self.offset);
+            }
+        }
+        Some(())
+    }
+
+    /// Consume a string literal, returning the span of its contents.
+    fn string(&mut self) -> Option<Range<usize>> {
+        self.expect(b'"')?;
+        let start = self.pos;
+        while *self.text.get(self.pos)? != b'"' {
+            self.pos += if self.text[self.pos] == b'\\' { 2 } else { 1 };
+        }
+        self.pos += 1;
+        Some(start..self.pos - 1)
+    }
+
+    fn expect(&mut self, byte: u8) -> Option<()> {
+        (self.text.get(self.pos) == Some(&byte)).then(|| self.pos += 1)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self
+            .text
+            .get(self.pos)
+            .is_some_and(|byte| byte.is_ascii_whitespace())
+        {
+            self.pos += 1;
+        }
+    }
+}
+
+/// Best effort span of a value in a YAML or XML response: the first occurrence of its text
+/// after the name of its field.
+fn search_span(response: &str, path: &str, value: &Value) -> Option<Range<usize>> {
+    let text = match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    };
+    if text.is_empty() {
+        return None;
+    }
+
+    let key = path
+        .rsplit('.')
+        .next()
+        .map(|key| key.split('[').next().unwrap_or(key))
+        .filter(|key| !key.is_empty());
+    let from = key
+        .and_then(|key| response.find(key).map(|at| at + key.len()))
+        .unwrap_or(0);
+    let start = from + response[from..].find(&text)?;
+    Some(start..start + text.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_spans_cover_nested_values() {
+        let response = "Sure:\n```json\n{\"name\": \"Ada \\\"L\\\"\", \"tags\": [\"a\", 1], \"ok\": true}\n```";
+        let spans = json_spans(response);
+
+        assert_eq!(&response[spans["name"].clone()], "Ada \\\"L\\\"");
+        assert_eq!(&response[spans["tags[0]"].clone()], "a");
+        assert_eq!(&response[spans["tags[1]"].clone()], "1");
+        assert_eq!(&response[spans["ok"].clone()], "true");
+    }
+}
@@ -0,0 +1,136 @@
+//! Field level comparison of parsed data with the data expected, see
+//! [crate::types::structured::Response::diff_against].
+use serde_json::Value;
+
+use crate::types::structured::FieldDiff;
+
+/// Differences of `actual` from `expected`, the fields of `expected` first, followed by the
+/// extra fields of `actual`.
+pub(crate) fn diff(expected: &Value, actual: &Value) -> Vec<FieldDiff> {
+    let mut diffs = Vec::new();
+    compare(String::new(), expected, actual, &mut diffs);
+    diffs
+}
+
+fn compare(path: String, expected: &Value, actual: &Value, diffs: &mut Vec<FieldDiff>) {
+    match (expected, actual) {
+        (Value::Object(expected), Value::Object(actual)) => {
+            for (key, expected) in expected {
+                let path = child_path(&path, key);
+                match actual.get(key) {
+                    Some(actual) => compare(path, expected, actual, diffs),
+                    // An absent field and a `null` one mean the same to most types
+                    None if expected.is_null() => {}
+                    None => diffs.push(FieldDiff::Missing {
+                        path,
+                        expected: expected.clone(),
+                    }),
+                }
+            }
+            for (key, actual) in actual {
+                if !expected.contains_key(key) && !actual.is_null() {
+                    diffs.push(FieldDiff::Extra {
+                        path: child_path(&path, key),
+                        actual: actual.clone(),
+                    });
+                }
+            }
+        }
+        (Value::Array(expected), Value::Array(actual)) => {
+            for (index, expected) in expected.iter().enumerate() {
+                let path = format!("{path}[{index}]");
+                match actual.get(index) {
+                    Some(actual) => compare(path, expected, actual, diffs),
+                    None => diffs.push(FieldDiff::Missing {
+                        path,
+                        expected: expected.clone(),
+                    }),
+                }
+            }
+            for (index, actual) in actual.iter().enumerate().skip(expected.len()) {
+                diffs.push(FieldDiff::Extra {
+                    path: format!("{path}[{index}]"),
+                    actual: actual.clone(),
+                });
+            }
+        }
+        // `1` and `1.0` are the same number
+        (Value::Number(a), Value::Number(b)) if a.as_f64() == b.as_f64() => {}
+        (expected, actual) if expected != actual => diffs.push(FieldDiff::Mismatch {
+            path,
+            expected: expected.clone(),
+            actual: actual.clone(),
+        }),
+        _ => {}
+    }
+}
+
+fn child_path(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{path}.{key}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn reports_missing_extra_and_mismatched_fields() {
+        // Keys sorted, so that the differences come in the same order with and without the
+        // preserve_order feature of serde_json
+        let expected = json!({
+            "mayor": {"name": "Anne", "since": 2014},
+            "name": "Paris",
+            "population": 2100000,
+            "tags": ["capital", "france"],
+            "twin": null
+        });
+        let actual = json!({
+            "country": "FR",
+            "mayor": {"name": "Rachida", "party": "LR", "since": 2014},
+            "name": "Paris",
+            "population": 2100000.0,
+            "tags": ["capital"]
+        });
+
+        assert_eq!(
+            diff(&expected, &actual),
+            vec![
+                FieldDiff::Mismatch {
+                    path: "mayor.name".into(),
+                    expected: json!("Anne"),
+                    actual: json!("Rachida")
+                },
+                FieldDiff::Extra {
+                    path: "mayor.party".into(),
+                    actual: json!("LR")
+                },
+                FieldDiff::Missing {
+                    path: "tags[1]".into(),
+                    expected: json!("france")
+                },
+                FieldDiff::Extra {
+                    path: "country".into(),
+                    actual: json!("FR")
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn different_types_are_mismatches() {
+        assert_eq!(
+            diff(&json!([1]), &json!({"a": 1})),
+            vec![FieldDiff::Mismatch {
+                path: String::new(),
+                expected: json!([1]),
+                actual: json!({"a": 1})
+            }]
+        );
+    }
+}
@@ -0,0 +1,363 @@
+//! Generators for JSON Schemas known only at runtime, such as schemas users define.
+use std::collections::BTreeMap;
+
+use serde_json::{Map, Value};
+
+use super::{openapi, strict, Format, Generator};
+use crate::types::structured::{
+    Config, ExtractionStrategy, Instruction, Locale, OutputFormat, ParseError, ReasoningFilter,
+    Response, ValidationOptions,
+};
+use crate::types::{
+    ChatChoice, CreateChatCompletionResponse, ResponseFormat, ResponseFormatJsonSchema,
+};
+
+/// Depth past which the example of a recursive schema stops
+const MAX_EXAMPLE_DEPTH: usize = 8;
+
+/// [Generator] for a JSON Schema loaded at runtime, from a file or a database, instead of
+/// derived from a type. Data is returned as [serde_json::Value].
+///
+/// The instruction shows the schema and an example made from it. Responses are validated
+/// against the schema, with the issues reported in [Response::validation_issues] unless the
+/// validation options are strict. XML answers are not supported, as they are typed by the
+/// output type.
+///
+/// ```
+/// use async_openai::structured::DynGenerator;
+///
+/// let schema = serde_json::json!({
+///     "title": "Invoice",
+///     "type": "object",
+///     "properties": {
+///         "number": { "type": "string" },
+///         "total": { "type": "number" }
+///     },
+///     "required": ["number", "total"]
+/// });
+/// let generator = DynGenerator::try_new(schema).unwrap();
+///
+/// assert!(generator.build_instruction_text().contains("\"total\""));
+/// let invoice = generator.parse_data("{\"number\": \"F-17\", \"total\": 99.5}").unwrap();
+/// assert_eq!(invoice["total"], 99.5);
+/// ```
+pub struct DynGenerator {
+    generator: Generator<Value>,
+}
+
+impl DynGenerator {
+    /// Generator asking for JSON matching `schema`, with validation enabled
+    pub fn new(schema: Value) -> Self {
+        let config = Config {
+            schema: Some(example(&schema, &schema, 0)),
+            json_schema: Some(schema),
+            ..Config::default()
+        };
+        Self {
+            generator: Generator::new(config).validate(true),
+        }
+    }
+
+    /// Same as [DynGenerator::new], but fails when `schema` isn't a valid JSON Schema
+    pub fn try_new(schema: Value) -> Result<Self, ParseError> {
+        let generator = Self::new(schema);
+        if let Some(Err(e)) = &generator.generator.validator {
+            return Err(ParseError::ValidationError(e.clone()));
+        }
+        Ok(generator)
+    }
+
+    /// Generator for the JSON Schema in `schema`
+    pub fn from_json_str(schema: &str) -> Result<Self, ParseError> {
+        let schema = serde_json::from_str(schema)
+            .map_err(|e| ParseError::Other(format!("Invalid JSON Schema: {}", e)))?;
+        Self::try_new(schema)
+    }
+
+    /// Generator for the schema at `pointer` in `spec`, an OpenAPI document or any JSON
+    /// document holding JSON Schemas, such as `#/components/schemas/Invoice`.
+    ///
+    /// References inside the document are inlined, except recursive ones, which point into
+    /// the `definitions` of the schema. The `nullable` fields of OpenAPI 3.0 accept `null`,
+    /// and the title defaults to the name of the component. References to other documents
+    /// are not supported.
+    pub fn from_openapi(spec: &Value, pointer: &str) -> Result<Self, ParseError> {
+        Self::try_new(openapi::component(spec, pointer)?)
+    }
+
+    /// The JSON Schema of the output
+    pub fn schema(&self) -> &Value {
+        self.generator
+            .config()
+            .json_schema
+            .as_ref()
+            .expect("set by DynGenerator::new")
+    }
+
+    /// The underlying generator
+    pub fn generator(&self) -> &Generator<Value> {
+        &self.generator
+    }
+
+    /// Add a prefix to the instruction
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.generator = self.generator.prefix(prefix);
+        self
+    }
+
+    /// Add a suffix to the instruction
+    pub fn suffix(mut self, suffix: impl Into<String>) -> Self {
+        self.generator = self.generator.suffix(suffix);
+        self
+    }
+
+    /// Set the output format
+    pub fn format(mut self, format: OutputFormat) -> Self {
+        self.generator = self.generator.format(format);
+        self
+    }
+
+    /// Answer in `format`, a format defined outside the crate
+    pub fn custom_format(mut self, format: Box<dyn Format>) -> Self {
+        self.generator = self.generator.custom_format(format);
+        self
+    }
+
+    /// Describe a field, e.g. `total` or `lines[].amount`
+    pub fn describe(mut self, field: impl Into<String>, description: impl Into<String>) -> Self {
+        self.generator = self.generator.describe(field, description);
+        self
+    }
+
+    /// Describe the position `index` of a tuple answer
+    pub fn describe_index(mut self, index: usize, description: impl Into<String>) -> Self {
+        self.generator = self.generator.describe_index(index, description);
+        self
+    }
+
+    /// Set the order of the fields in the example, the field descriptions and the schema, see
+    /// [Generator::field_order]
+    pub fn field_order<S: Into<String>>(mut self, fields: impl IntoIterator<Item = S>) -> Self {
+        self.generator = self.generator.field_order(fields);
+        self
+    }
+
+    /// Set the language of the phrases of the instruction
+    pub fn locale(mut self, locale: Locale) -> Self {
+        self.generator = self.generator.locale(locale);
+        self
+    }
+
+    /// Set how JSON answers are located in the response
+    pub fn extraction(mut self, strategy: ExtractionStrategy) -> Self {
+        self.generator = self.generator.extraction(strategy);
+        self
+    }
+
+    /// Strip the reasoning of thinking models from responses before extracting the answer
+    pub fn reasoning_filter(mut self, filter: ReasoningFilter) -> Self {
+        self.generator = self.generator.reasoning_filter(filter);
+        self
+    }
+
+    /// Set the validation options, e.g. to fail on schema violations
+    pub fn validation_options(mut self, options: ValidationOptions) -> Self {
+        self.generator = self.generator.validation_options(options);
+        self
+    }
+
+    /// Add a demonstration of the `output` expected for `input`
+    pub fn example(mut self, input: impl Into<String>, output: Value) -> Self {
+        self.generator = self.generator.example(input, output);
+        self
+    }
+
+    /// Instruction asking for data matching the schema
+    pub fn build_instruction(&self) -> Instruction {
+        self.generator.build_instruction()
+    }
+
+    /// Text of the instruction
+    pub fn build_instruction_text(&self) -> String {
+        self.generator.build_instruction_text()
+    }
+
+    /// `response_format` asking for the schema with structured outputs. The name is the
+    /// `title` of the schema, `output` without one
+    pub fn to_response_format(&self) -> ResponseFormat {
+        let mut schema = self.schema().clone();
+        self.generator.config().order_schema_fields(&mut schema, "");
+        strict::make_strict(&mut schema);
+        let name: String = schema
+            .get("title")
+            .and_then(Value::as_str)
+            .unwrap_or("output")
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-'))
+            .take(64)
+            .collect();
+        let description = schema
+            .get("description")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+
+        ResponseFormat::JsonSchema {
+            json_schema: ResponseFormatJsonSchema {
+                description,
+                name,
+                schema: Some(schema),
+                strict: Some(true),
+            },
+        }
+    }
+
+    /// Parse a model response
+    pub fn parse_response(&self, response: &str) -> Result<Response<Value>, ParseError> {
+        self.generator.parse_response(response)
+    }
+
+    /// Parse a model response and return only the data
+    pub fn parse_data(&self, response: &str) -> Result<Value, ParseError> {
+        self.generator.parse_data(response)
+    }
+
+    /// Parse every answer of a model response that contains several
+    pub fn parse_all(&self, response: &str) -> Result<Vec<Response<Value>>, ParseError> {
+        self.generator.parse_all(response)
+    }
+
+    /// Parse the first choice of a chat completion
+    pub fn parse_completion(
+        &self,
+        completion: &CreateChatCompletionResponse,
+    ) -> Result<Response<Value>, ParseError> {
+        self.generator.parse_completion(completion)
+    }
+
+    /// Parse the message of a chat completion choice
+    pub fn parse_choice(&self, choice: &ChatChoice) -> Result<Response<Value>, ParseError> {
+        self.generator.parse_choice(choice)
+    }
+}
+
+/// Value of the shape of `schema` shown as the example of the instruction: empty strings,
+/// zeros, the first allowed value of enums, and arrays of one item
+fn example(schema: &Value, root: &Value, depth: usize) -> Value {
+    if depth > MAX_EXAMPLE_DEPTH {
+        return Value::Null;
+    }
+    let Value::Object(map) = schema else {
+        return Value::Null;
+    };
+
+    if let Some(target) = map.get("$ref").and_then(Value::as_str) {
+        return match target
+            .strip_prefix('#')
+            .and_then(|pointer| root.pointer(pointer))
+        {
+            Some(schema) => example(schema, root, depth + 1),
+            None => Value::Null,
+        };
+    }
+    if let Some(value) = map.get("const").or_else(|| map.get("default")) {
+        return value.clone();
+    }
+    if let Some(Value::Array(values)) = map.get("enum").or_else(|| map.get("examples")) {
+        if let Some(value) = values.iter().find(|value| !value.is_null()) {
+            return value.clone();
+        }
+    }
+    for combinator in ["anyOf", "oneOf"] {
+        if let Some(Value::Array(variants)) = map.get(combinator) {
+            let variant = variants
+                .iter()
+                .find(|variant| variant.get("type").and_then(Value::as_str) != Some("null"));
+            return variant.map_or(Value::Null, |variant| example(variant, root, depth + 1));
+        }
+    }
+    if let Some(Value::Array(parts)) = map.get("allOf") {
+        let mut merged = Map::new();
+        for part in parts {
+            if let Value::Object(fields) = example(part, root, depth + 1) {
+                merged.extend(fields);
+            }
+        }
+        return Value::Object(merged);
+    }
+
+    let kind = match map.get("type") {
+        Some(Value::String(kind)) => kind.as_str(),
+        Some(Value::Array(kinds)) => kinds
+            .iter()
+            .filter_map(Value::as_str)
+            .find(|kind| *kind != "null")
+            .unwrap_or("null"),
+        _ if map.contains_key("properties") => "object",
+        _ if map.contains_key("items") => "array",
+        _ => "null",
+    };
+    match kind {
+        "object" => {
+            let fields = map
+                .get("properties")
+                .and_then(Value::as_object)
+                .map(|properties| {
+                    properties
+                        .iter()
+                        .map(|(name, schema)| (name.clone(), example(schema, root, depth + 1)))
+                        .collect()
+                })
+                .unwrap_or_default();
+            Value::Object(fields)
+        }
+        "array" => match map.get("items") {
+            Some(items) => Value::Array(vec![example(items, root, depth + 1)]),
+            None => Value::Array(vec![]),
+        },
+        "string" => Value::String(String::new()),
+        "integer" => Value::from(0),
+        "number" => Value::from(0.0),
+        "boolean" => Value::Bool(false),
+        _ => Value::Null,
+    }
+}
+
+/// Runtime schemas by name, each compiled once into a [DynGenerator]
+#[derive(Default)]
+pub struct SchemaRegistry {
+    generators: BTreeMap<String, DynGenerator>,
+}
+
+impl SchemaRegistry {
+    /// Registry without schemas
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add or replace the schema `name`, failing when it isn't a valid JSON Schema
+    pub fn register(&mut self, name: impl Into<String>, schema: Value) -> Result<(), ParseError> {
+        self.generators
+            .insert(name.into(), DynGenerator::try_new(schema)?);
+        Ok(())
+    }
+
+    /// Add or replace the schema `name` with a configured generator
+    pub fn insert(&mut self, name: impl Into<String>, generator: DynGenerator) {
+        self.generators.insert(name.into(), generator);
+    }
+
+    /// Generator of the schema `name`
+    pub fn get(&self, name: &str) -> Option<&DynGenerator> {
+        self.generators.get(name)
+    }
+
+    /// Remove the schema `name`, returning its generator
+    pub fn remove(&mut self, name: &str) -> Option<DynGenerator> {
+        self.generators.remove(name)
+    }
+
+    /// Names of the registered schemas, in order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.generators.keys().map(String::as_str)
+    }
+}
@@ -0,0 +1,219 @@
+//! Values allowed by the enums of a JSON Schema, so that instructions can list them instead
+//! of leaving the model to guess from the one variant of the example.
+use serde_json::Value;
+
+/// Depth at which recursive schemas stop being followed.
+const MAX_DEPTH: usize = 16;
+
+/// The values allowed for the fields of `schema` restricted to a set of values, by description
+/// path such as `items[].status`, in the order of the schema. Top level enums, and enum items
+/// of a top level array, have an empty path.
+///
+/// Unions of unit variants (`enum`, `const`, `oneOf` of those) are a set of values. Internally
+/// tagged unions give the values of their tag. Unions mixing unit variants with variants holding
+/// data are left out, as their values can't be listed.
+pub(crate) fn allowed_values(schema: &Value) -> Vec<(String, Vec<Value>)> {
+    let mut fields = Vec::new();
+    let resolved = resolve(schema, schema);
+    // Items of a top level array are described like top level fields
+    let node = match resolved.get("items") {
+        Some(items) if resolved.get("type").and_then(Value::as_str) == Some("array") => items,
+        _ => schema,
+    };
+    collect(schema, node, "", 0, &mut fields);
+    fields
+}
+
+fn collect(
+    root: &Value,
+    schema: &Value,
+    path: &str,
+    depth: usize,
+    fields: &mut Vec<(String, Vec<Value>)>,
+) {
+    if depth > MAX_DEPTH {
+        return;
+    }
+    let schema = resolve(root, schema);
+    if let Some(values) = values(root, schema, depth) {
+        add(fields, path, values);
+        return;
+    }
+
+    if let Some(Value::Object(properties)) = schema.get("properties") {
+        for (name, property) in properties {
+            let path = if path.is_empty() {
+                name.clone()
+            } else {
+                format!("{path}.{name}")
+            };
+            collect(root, property, &path, depth + 1, fields);
+        }
+    }
+    if let Some(items) = schema.get("items").filter(|items| items.is_object()) {
+        collect(root, items, &format!("{path}[]"), depth + 1, fields);
+    }
+    for key in ["anyOf", "oneOf", "allOf"] {
+        for variant in schema
+            .get(key)
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            // Unit variants can't be listed next to variants holding data
+            if values(root, resolve(root, variant), depth).is_none() {
+                collect(root, variant, path, depth + 1, fields);
+            }
+        }
+    }
+}
+
+/// The values `schema` allows, if it is a set of values.
+fn values(root: &Value, schema: &Value, depth: usize) -> Option<Vec<Value>> {
+    if depth > MAX_DEPTH {
+        return None;
+    }
+    if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+        return Some(
+            values
+                .iter()
+                .filter(|value| !value.is_null())
+                .cloned()
+                .collect(),
+        );
+    }
+    if let Some(value) = schema.get("const") {
+        return Some(vec![value.clone()]);
+    }
+    if let Some(variants) = schema
+        .get("oneOf")
+        .or_else(|| schema.get("anyOf"))
+        .and_then(Value::as_array)
+    {
+        let mut all = Vec::new();
+        let mut any = false;
+        for variant in variants.iter().map(|variant| resolve(root, variant)) {
+            // The `None` of an `Option`
+            if variant.get("type").and_then(Value::as_str) == Some("null") {
+                continue;
+            }
+            for value in values(root, variant, depth + 1)? {
+                if !all.contains(&value) {
+                    all.push(value);
+                }
+            }
+            any = true;
+        }
+        return any.then_some(all);
+    }
+    match schema
+        .get("allOf")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+    {
+        Some([part]) => values(root, resolve(root, part), depth + 1),
+        _ => None,
+    }
+}
+
+/// Add `values` to those of `path`, for the variants of tagged unions.
+fn add(fields: &mut Vec<(String, Vec<Value>)>, path: &str, values: Vec<Value>) {
+    match fields.iter_mut().find(|(field, _)| field == path) {
+        Some((_, known)) => {
+            for value in values {
+                if !known.contains(&value) {
+                    known.push(value);
+                }
+            }
+        }
+        None => fields.push((path.to_string(), values)),
+    }
+}
+
+/// `schema`, or the definition of `root` it refers to.
+fn resolve<'a>(root: &'a Value, schema: &'a Value) -> &'a Value {
+    let mut schema = schema;
+    // Bounded, definitions may refer to each other
+    for _ in 0..MAX_DEPTH {
+        let Some(reference) = schema.get("$ref").and_then(Value::as_str) else {
+            break;
+        };
+        match reference
+            .strip_prefix('#')
+            .and_then(|pointer| root.pointer(pointer))
+        {
+            Some(definition) => schema = definition,
+            None => break,
+        }
+    }
+    schema
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn lists_enums_through_references_and_options() {
+        let schema = json!({
+            "type": "object",
+            // Sorted, as serde_json keeps them without preserve_order
+            "properties": {
+                "name": {"type": "string"},
+                "priority": {"anyOf": [{"$ref": "#/definitions/Priority"}, {"type": "null"}]},
+                "status": {"$ref": "#/definitions/Status"},
+                "tasks": {"type": "array", "items": {
+                    "type": "object",
+                    "properties": {"state": {"allOf": [{"$ref": "#/definitions/Status"}]}}
+                }}
+            },
+            "definitions": {
+                "Status": {"type": "string", "enum": ["pending", "active", "closed"]},
+                "Priority": {"oneOf": [
+                    {"type": "string", "enum": ["low"], "description": "Whenever"},
+                    {"type": "string", "const": "high"}
+                ]}
+            }
+        });
+
+        assert_eq!(
+            allowed_values(&schema),
+            vec![
+                ("priority".to_string(), vec![json!("low"), json!("high")]),
+                (
+                    "status".to_string(),
+                    vec![json!("pending"), json!("active"), json!("closed")]
+                ),
+                (
+                    "tasks[].state".to_string(),
+                    vec![json!("pending"), json!("active"), json!("closed")]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn tagged_unions_give_the_values_of_their_tag() {
+        let schema = json!({"oneOf": [
+            {"type": "object", "properties": {"kind": {"type": "string", "enum": ["circle"]}, "radius": {"type": "number"}}},
+            {"type": "object", "properties": {"kind": {"type": "string", "enum": ["square"]}, "side": {"type": "number"}}}
+        ]});
+
+        assert_eq!(
+            allowed_values(&schema),
+            vec![("kind".to_string(), vec![json!("circle"), json!("square")])]
+        );
+    }
+
+    #[test]
+    fn mixed_unions_are_left_out() {
+        let schema = json!({"type": "array", "items": {"oneOf": [
+            {"type": "string", "enum": ["none"]},
+            {"type": "object", "properties": {"some": {"type": "integer"}}, "required": ["some"]}
+        ]}});
+
+        assert!(allowed_values(&schema).is_empty());
+    }
+}
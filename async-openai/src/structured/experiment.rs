@@ -0,0 +1,261 @@
+//! Comparison of [Generator] configurations against a labeled sample set.
+use std::collections::BTreeMap;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::Generator;
+use crate::{
+    config::Config,
+    types::{
+        structured::{ParseError, Structured},
+        ChatCompletionRequestSystemMessage, ChatCompletionRequestUserMessage,
+        CreateChatCompletionRequestArgs, HasUsage, Usage,
+    },
+    Client,
+};
+
+/// An input of an experiment, with the data it should produce when known.
+#[derive(Debug, Clone)]
+pub struct Sample<T> {
+    pub input: String,
+    pub expected: Option<T>,
+}
+
+impl<T> Sample<T> {
+    /// A sample without a label, only counted for parse and validation results.
+    pub fn new(input: impl Into<String>) -> Self {
+        Self {
+            input: input.into(),
+            expected: None,
+        }
+    }
+
+    /// A sample whose parsed data is compared with `expected`.
+    pub fn labeled(input: impl Into<String>, expected: T) -> Self {
+        Self {
+            input: input.into(),
+            expected: Some(expected),
+        }
+    }
+}
+
+/// Runs every variant against every sample and reports how each one did.
+///
+/// Each request sends the variant's instruction as the system message and the sample input as
+/// the user message.
+///
+/// ```no_run
+/// # async fn run() {
+/// use async_openai::{
+///     structured::{ExperimentRunner, Generator, Sample},
+///     Client,
+/// };
+/// # #[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+/// # struct Contact { name: String, email: String }
+///
+/// let client = Client::new();
+/// let report = ExperimentRunner::new(&client, "gpt-4o-mini")
+///     .with_variant("terse", Generator::json(Contact::default()))
+///     .with_variant(
+///         "guided",
+///         Generator::json(Contact::default())
+///             .prefix("Extract the contact details from the message.")
+///             .validate(true),
+///     )
+///     .with_sample(Sample::labeled(
+///         "Reach me at ada@example.com - Ada",
+///         Contact { name: "Ada".into(), email: "ada@example.com".into() },
+///     ))
+///     .run()
+///     .await;
+///
+/// for variant in &report.variants {
+///     println!("{}: {:.0}% parsed, accuracy {:?}", variant.name, variant.parse_success_rate() * 100.0, variant.accuracy());
+/// }
+/// # }
+/// ```
+pub struct ExperimentRunner<'c, C: Config, T>
+where
+    T: Structured + for<'de> Deserialize<'de> + JsonSchema,
+{
+    client: &'c Client<C>,
+    model: String,
+    temperature: Option<f32>,
+    variants: Vec<(String, Generator<T>)>,
+    samples: Vec<Sample<T>>,
+}
+
+impl<'c, C: Config, T> ExperimentRunner<'c, C, T>
+where
+    T: Structured + for<'de> Deserialize<'de> + JsonSchema,
+{
+    pub fn new(client: &'c Client<C>, model: impl Into<String>) -> Self {
+        Self {
+            client,
+            model: model.into(),
+            temperature: None,
+            variants: Vec::new(),
+            samples: Vec::new(),
+        }
+    }
+
+    /// Sampling temperature of every request, the model default otherwise.
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    pub fn with_variant(mut self, name: impl Into<String>, generator: Generator<T>) -> Self {
+        self.variants.push((name.into(), generator));
+        self
+    }
+
+    pub fn with_sample(mut self, sample: Sample<T>) -> Self {
+        self.samples.push(sample);
+        self
+    }
+
+    pub fn with_samples(mut self, samples: impl IntoIterator<Item = Sample<T>>) -> Self {
+        self.samples.extend(samples);
+        self
+    }
+
+    /// Run the variants one after another. Failed requests are counted in
+    /// [VariantReport::request_failures] rather than ending the experiment.
+    pub async fn run(&self) -> ExperimentReport {
+        let mut variants = Vec::with_capacity(self.variants.len());
+        for (name, generator) in &self.variants {
+            let instruction = generator.build_instruction_text();
+            let mut report = VariantReport {
+                name: name.clone(),
+                ..Default::default()
+            };
+
+            for sample in &self.samples {
+                report.samples += 1;
+                report.labeled += usize::from(sample.expected.is_some());
+
+                let content = match self.complete(&instruction, &sample.input).await {
+                    Ok((content, usage)) => {
+                        if let Some(usage) = usage {
+                            report.prompt_tokens += usage.prompt_tokens;
+                            report.completion_tokens += usage.completion_tokens;
+                        }
+                        content
+                    }
+                    Err(e) => {
+                        tracing::warn!("experiment variant {name}: {e}");
+                        report.request_failures += 1;
+                        continue;
+                    }
+                };
+
+                match generator.parse_response(&content) {
+                    Ok(parsed) => {
+                        report.parsed += 1;
+                        for message in parsed.validation_messages.unwrap_or_default() {
+                            *report.validation_failures.entry(message).or_default() += 1;
+                        }
+                        if let Some(expected) = &sample.expected {
+                            if serde_json::to_value(expected).ok()
+                                == serde_json::to_value(&parsed.data).ok()
+                            {
+                                report.correct += 1;
+                            }
+                        }
+                    }
+                    Err(ParseError::ValidationError(message)) => {
+                        *report.validation_failures.entry(message).or_default() += 1;
+                    }
+                    Err(_) => report.parse_failures += 1,
+                }
+            }
+
+            variants.push(report);
+        }
+
+        ExperimentReport { variants }
+    }
+
+    async fn complete(
+        &self,
+        instruction: &str,
+        input: &str,
+    ) -> Result<(String, Option<Usage>), crate::error::OpenAIError> {
+        let mut request = CreateChatCompletionRequestArgs::default();
+        request.model(&self.model).messages([
+            ChatCompletionRequestSystemMessage::from(instruction).into(),
+            ChatCompletionRequestUserMessage::from(input).into(),
+        ]);
+        if let Some(temperature) = self.temperature {
+            request.temperature(temperature);
+        }
+
+        let response = self.client.chat().create(request.build()?).await?;
+        let usage = response.unified_usage();
+        let content = response
+            .choices
+            .into_iter()
+            .next()
+            .and_then(|choice| choice.message.content)
+            .unwrap_or_default();
+        Ok((content, usage))
+    }
+}
+
+/// Results of an [ExperimentRunner], one entry per variant in the order they were added.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ExperimentReport {
+    pub variants: Vec<VariantReport>,
+}
+
+impl ExperimentReport {
+    pub fn variant(&self, name: &str) -> Option<&VariantReport> {
+        self.variants.iter().find(|variant| variant.name == name)
+    }
+}
+
+/// How one variant did over the sample set.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct VariantReport {
+    pub name: String,
+    /// Samples run
+    pub samples: usize,
+    /// Samples whose request failed, e.g. on a network or API error
+    pub request_failures: usize,
+    /// Responses that were parsed, with or without validation messages
+    pub parsed: usize,
+    /// Responses no data could be extracted from
+    pub parse_failures: usize,
+    /// Number of responses per validation message
+    pub validation_failures: BTreeMap<String, usize>,
+    /// Samples with an expected value
+    pub labeled: usize,
+    /// Labeled samples whose parsed data equals the expected value
+    pub correct: usize,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+impl VariantReport {
+    /// Share of samples that were parsed, between 0 and 1.
+    pub fn parse_success_rate(&self) -> f64 {
+        if self.samples == 0 {
+            return 0.0;
+        }
+        self.parsed as f64 / self.samples as f64
+    }
+
+    /// Share of labeled samples parsed to the expected value, `None` without labeled samples.
+    pub fn accuracy(&self) -> Option<f64> {
+        (self.labeled > 0).then(|| self.correct as f64 / self.labeled as f64)
+    }
+
+    /// Cost of the run given the prices per million prompt and completion tokens.
+    pub fn cost(&self, prompt_price_per_million: f64, completion_price_per_million: f64) -> f64 {
+        (self.prompt_tokens as f64 * prompt_price_per_million
+            + self.completion_tokens as f64 * completion_price_per_million)
+            / 1_000_000.0
+    }
+}
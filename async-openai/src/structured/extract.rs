@@ -0,0 +1,213 @@
+//! Extraction of structured data from arbitrary text, the machinery [Generator] parses
+//! responses with, for text that didn't come from a [Generator] instruction.
+//!
+//! Each format has a typed function that reads the first fenced block of the format, or the
+//! whole text without one. [value] and [typed] pick the function by [OutputFormat], and
+//! [auto] detects the format first.
+//!
+//! ```
+//! use async_openai::structured::extract;
+//!
+//! #[derive(Debug, PartialEq, serde::Deserialize)]
+//! struct City {
+//!     name: String,
+//! }
+//!
+//! let text = "Sure! {\"name\": \"Berlin\"} is the capital.";
+//! let city: City = extract::json(text).unwrap();
+//! assert_eq!(city.name, "Berlin");
+//!
+//! let (format, value) = extract::auto("```json\n{\"name\": \"Paris\"}\n```").unwrap();
+//! assert_eq!(format, extract::OutputFormat::Json);
+//! assert_eq!(value["name"], "Paris");
+//! ```
+//!
+//! [Generator]: super::Generator
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+#[cfg(feature = "xml")]
+pub use crate::types::structured::XmlMapping;
+pub use crate::types::structured::{ExtractionStrategy, OutputFormat, ParseError};
+
+use super::{extraction, fence_languages, fence_regex, tabular};
+
+/// JSON in `text`, located with the default [ExtractionStrategy]
+pub fn json<T: DeserializeOwned>(text: &str) -> Result<T, ParseError> {
+    super::extract_json_with(ExtractionStrategy::default(), text)
+}
+
+/// JSON in `text`, located with `strategy`
+pub fn json_with<T: DeserializeOwned>(
+    strategy: ExtractionStrategy,
+    text: &str,
+) -> Result<T, ParseError> {
+    super::extract_json_with(strategy, text)
+}
+
+/// The part of `text` `strategy` takes for the JSON answer, without parsing it. Falls back
+/// to the first code fence, then to the whole text
+pub fn locate_json(strategy: ExtractionStrategy, text: &str) -> &str {
+    extraction::locate(strategy, text)
+}
+
+/// Every top-level JSON object or array in `text`, in order
+pub fn json_values(text: &str) -> Vec<&str> {
+    super::json_values(text)
+}
+
+/// Every fenced code block of `text` as its language, possibly empty, and its content
+pub fn fences(text: &str) -> Vec<(&str, &str)> {
+    fence_regex()
+        .captures_iter(text)
+        .filter_map(|captures| Some((captures.get(1)?.as_str(), captures.get(2)?.as_str())))
+        .collect()
+}
+
+/// The answers of `text` in `format`, as [super::Generator::parse_all] splits them: the
+/// fenced blocks in the format or without a language, else every top-level JSON value for
+/// the JSON formats, else the whole text. Each document of YAML blocks is an answer
+pub fn blocks(format: OutputFormat, text: &str) -> Vec<&str> {
+    #[cfg(feature = "yaml")]
+    if format == OutputFormat::Yaml {
+        // Text starting with a separator is a whole YAML answer, with no fences to look for
+        let mut blocks = Vec::new();
+        if !super::yaml::starts_with_separator(text) {
+            blocks = fenced_blocks(format, text);
+        }
+        if blocks.is_empty() {
+            blocks.push(text);
+        }
+        return blocks
+            .into_iter()
+            .flat_map(super::yaml::documents)
+            .collect();
+    }
+
+    let blocks = fenced_blocks(format, text);
+    if !blocks.is_empty() {
+        return blocks;
+    }
+
+    match format {
+        OutputFormat::Json | OutputFormat::JsonArray => json_values(text),
+        #[allow(unreachable_patterns)]
+        _ => vec![text],
+    }
+}
+
+/// The fenced blocks of `text` in `format` or without a language
+fn fenced_blocks(format: OutputFormat, text: &str) -> Vec<&str> {
+    let languages = fence_languages(format);
+    fences(text)
+        .into_iter()
+        .filter(|(language, _)| {
+            let language = language.to_ascii_lowercase();
+            language.is_empty() || languages.contains(&language.as_str())
+        })
+        .map(|(_, block)| block)
+        .collect()
+}
+
+/// YAML in `text`. Text starting with `---` is read from the top, ignoring the code fences
+/// of the markdown that may follow front matter. With several `---` separated documents,
+/// the first one is read when it is a `T`, and all of them as an array otherwise, so that
+/// a `Vec` reads one item per document
+#[cfg(feature = "yaml")]
+pub fn yaml<T: DeserializeOwned>(text: &str) -> Result<T, ParseError> {
+    super::extract_yaml(text)
+}
+
+/// Every `---` separated YAML document in `text`, each read as a `T`
+#[cfg(feature = "yaml")]
+pub fn yaml_documents<T: DeserializeOwned>(text: &str) -> Result<Vec<T>, ParseError> {
+    super::yaml::read_all(text)
+}
+
+/// XML in `text`, read as `mapping` says
+#[cfg(feature = "xml")]
+pub fn xml<T: DeserializeOwned>(text: &str, mapping: &XmlMapping) -> Result<T, ParseError> {
+    super::extract_xml(text, mapping)
+}
+
+/// TOML in `text`, with arrays written as `items`
+#[cfg(feature = "toml")]
+pub fn toml<T: DeserializeOwned>(text: &str) -> Result<T, ParseError> {
+    super::extract_toml(text)
+}
+
+/// CSV table in `text`, with its cells typed after `schema`, a JSON Schema
+#[cfg(feature = "csv")]
+pub fn csv<T: DeserializeOwned>(text: &str, schema: Option<&Value>) -> Result<T, ParseError> {
+    super::extract_csv(text, schema)
+}
+
+/// Protobuf text format message in `text`, with the fields `schema` declares as arrays read
+/// as arrays
+#[cfg(feature = "protobuf")]
+pub fn textproto(text: &str, schema: Option<&Value>) -> Result<Value, ParseError> {
+    super::extract_textproto(text, schema)
+}
+
+/// First markdown table in `text`, with its cells typed after `schema`, a JSON Schema
+pub fn markdown_table<T: DeserializeOwned>(
+    text: &str,
+    schema: Option<&Value>,
+) -> Result<T, ParseError> {
+    tabular::parse_markdown(text, schema)
+}
+
+/// Data in `format` in `text`, as an untyped value. XML values are all strings, and tables
+/// are arrays of rows with their cells typed by content
+pub fn value(format: OutputFormat, text: &str) -> Result<Value, ParseError> {
+    #[allow(unused_mut)]
+    let mut value = typed(format, text)?;
+    #[cfg(feature = "xml")]
+    if format == OutputFormat::Xml {
+        unwrap_text(&mut value);
+    }
+    Ok(value)
+}
+
+/// Replace the `{"$text": ..}` objects the XML reader makes of elements with their text
+#[cfg(feature = "xml")]
+fn unwrap_text(value: &mut Value) {
+    match value {
+        Value::Object(fields) if fields.len() == 1 && fields.contains_key("$text") => {
+            *value = fields.remove("$text").unwrap_or_default();
+        }
+        Value::Object(fields) => fields.values_mut().for_each(unwrap_text),
+        Value::Array(items) => items.iter_mut().for_each(unwrap_text),
+        _ => {}
+    }
+}
+
+/// Data in `format` in `text`, read with the defaults of each format: the default
+/// [ExtractionStrategy] and XML mapping, and no schema for tables and textproto. Fails for
+/// [OutputFormat::Custom], use [Format::extract](super::Format::extract)
+pub fn typed<T: DeserializeOwned>(format: OutputFormat, text: &str) -> Result<T, ParseError> {
+    match format {
+        OutputFormat::Json | OutputFormat::JsonArray => json(text),
+        #[cfg(feature = "yaml")]
+        OutputFormat::Yaml => yaml(text),
+        #[cfg(feature = "xml")]
+        OutputFormat::Xml => xml(text, &XmlMapping::default()),
+        #[cfg(feature = "toml")]
+        OutputFormat::Toml => toml(text),
+        #[cfg(feature = "csv")]
+        OutputFormat::Csv => csv(text, None),
+        #[cfg(feature = "protobuf")]
+        OutputFormat::TextProto => serde_json::from_value(textproto(text, None)?)
+            .map_err(|e| ParseError::Extraction(format!("Unable to extract textproto: {}", e))),
+        OutputFormat::MarkdownTable => markdown_table(text, None),
+        OutputFormat::Custom => Err(ParseError::Extraction(
+            "Custom formats are read with their Format".to_string(),
+        )),
+    }
+}
+
+/// Data in `text` as an untyped value, in the format [OutputFormat::detect] finds
+pub fn auto(text: &str) -> Result<(OutputFormat, Value), ParseError> {
+    let format = OutputFormat::detect(text);
+    Ok((format, value(format, text)?))
+}
@@ -0,0 +1,143 @@
+//! Locating the JSON answer in a response, see [ExtractionStrategy].
+use serde::de::IgnoredAny;
+
+use super::{fenced, json_regex, json_values};
+use crate::types::structured::{ExtractionMethod, ExtractionStrategy};
+
+/// The part of `response` holding the answer according to `strategy`. Falls back to the
+/// first code fence, then to the whole response, so that parse errors point at the most
+/// likely answer.
+pub(super) fn locate(strategy: ExtractionStrategy, response: &str) -> &str {
+    locate_with_method(strategy, response).1
+}
+
+/// [locate], with how the answer was found.
+pub(super) fn locate_with_method(
+    strategy: ExtractionStrategy,
+    response: &str,
+) -> (ExtractionMethod, &str) {
+    let located = match strategy {
+        ExtractionStrategy::FirstCodeFence => None,
+        ExtractionStrategy::LastCodeFence => json_regex()
+            .captures_iter(response)
+            .last()
+            .and_then(|captures| captures.get(1))
+            .map(|m| m.as_str()),
+        ExtractionStrategy::LargestJsonValue => json_values(response)
+            .into_iter()
+            .rev()
+            .max_by_key(|value| value.len()),
+        ExtractionStrategy::BalancedBraceScan => balanced_json(response),
+        ExtractionStrategy::Custom(locate) => locate(response),
+    };
+
+    if let Some(located) = located {
+        return (ExtractionMethod::Strategy, located);
+    }
+    match fenced(json_regex(), response) {
+        Some(block) => (ExtractionMethod::CodeFence, block),
+        None => (ExtractionMethod::WholeResponse, response),
+    }
+}
+
+/// The first span from an opening brace or bracket to its matching closer which is valid
+/// JSON.
+fn balanced_json(response: &str) -> Option<&str> {
+    let bytes = response.as_bytes();
+    (0..bytes.len())
+        .filter(|&start| matches!(bytes[start], b'{' | b'['))
+        .filter_map(|start| {
+            let end = balanced_end(&bytes[start..])?;
+            Some(&response[start..start + end])
+        })
+        .find(|candidate| serde_json::from_str::<IgnoredAny>(candidate).is_ok())
+}
+
+/// Length of the balanced span at the start of `bytes`, braces in strings being ignored.
+fn balanced_end(bytes: &[u8]) -> Option<usize> {
+    let mut closers = vec![];
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (i, &byte) in bytes.iter().enumerate() {
+        if in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+
+        match byte {
+            b'"' => in_string = true,
+            b'{' => closers.push(b'}'),
+            b'[' => closers.push(b']'),
+            b'}' | b']' => {
+                if closers.pop() != Some(byte) {
+                    return None;
+                }
+                if closers.is_empty() {
+                    return Some(i + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::locate;
+    use crate::types::structured::ExtractionStrategy;
+
+    const RESPONSE: &str = "Use {placeholders} like this:\n\
+        ```json\n{\"a\": 1}\n```\n\
+        Final answer: {\"a\": 2, \"note\": \"braces } in strings\", \"list\": [1, 2, 3]}\n\
+        Hope that {helps}!";
+
+    #[test]
+    fn locates_answer_per_strategy() {
+        assert_eq!(
+            locate(ExtractionStrategy::FirstCodeFence, RESPONSE),
+            "{\"a\": 1}"
+        );
+        assert_eq!(
+            locate(ExtractionStrategy::LastCodeFence, RESPONSE),
+            "{\"a\": 1}"
+        );
+        assert_eq!(
+            locate(ExtractionStrategy::LargestJsonValue, RESPONSE),
+            "{\"a\": 2, \"note\": \"braces } in strings\", \"list\": [1, 2, 3]}"
+        );
+        assert_eq!(
+            locate(ExtractionStrategy::BalancedBraceScan, RESPONSE),
+            "{\"a\": 1}"
+        );
+        assert_eq!(
+            locate(
+                ExtractionStrategy::Custom(|response| response
+                    .rsplit_once("answer: ")
+                    .map(|(_, rest)| rest)),
+                "The answer: [1]"
+            ),
+            "[1]"
+        );
+    }
+
+    #[test]
+    fn balanced_scan_ignores_commentary() {
+        let response = "Sure! {\"name\": \"Berlin\"} is the city, and [sic] it has {many} people.";
+        assert_eq!(
+            locate(ExtractionStrategy::BalancedBraceScan, response),
+            "{\"name\": \"Berlin\"}"
+        );
+        // Unbalanced answers fall back to the whole response
+        assert_eq!(
+            locate(ExtractionStrategy::BalancedBraceScan, "{\"name\": "),
+            "{\"name\": "
+        );
+    }
+}
@@ -0,0 +1,105 @@
+//! Output formats defined outside the crate, such as KDL, S-expressions or a custom DSL.
+use std::{fmt, ops::Deref, sync::Arc};
+
+use serde_json::Value;
+
+use crate::types::structured::ParseError;
+
+/// An output format of [OutputFormat::Custom], set with
+/// [Generator::custom_format](super::Generator::custom_format).
+///
+/// Data passes through the format as JSON values: the example and the demonstrations are
+/// rendered from theirs, and the answer is extracted as one, then validated and converted
+/// like the answers of the built-in formats.
+///
+/// ```
+/// use async_openai::{structured::{Format, Generator}, types::structured::ParseError};
+/// use serde_json::{Map, Value};
+///
+/// /// `key = value` lines
+/// struct KeyValue;
+///
+/// impl Format for KeyValue {
+///     fn name(&self) -> &str {
+///         "key = value lines"
+///     }
+///
+///     fn render_example(&self, value: &Value) -> Option<String> {
+///         let lines = value.as_object()?.iter().map(|(key, value)| format!("{key} = {value}\n"));
+///         Some(lines.collect())
+///     }
+///
+///     fn extract(&self, response: &str) -> Result<Value, ParseError> {
+///         let mut map = Map::new();
+///         for line in response.lines().filter(|line| line.contains('=')) {
+///             let (key, value) = line.split_once('=').unwrap();
+///             let value = serde_json::from_str(value.trim())
+///                 .map_err(|e| ParseError::Extraction(e.to_string()))?;
+///             map.insert(key.trim().to_string(), value);
+///         }
+///         Ok(Value::Object(map))
+///     }
+/// }
+///
+/// # #[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+/// # struct City { name: String, population: u64 }
+/// let generator = Generator::json(City::default()).custom_format(Box::new(KeyValue));
+/// let city = generator.parse_response("name = \"Berlin\"\npopulation = 3700000").unwrap();
+/// assert_eq!(city.data.population, 3_700_000);
+/// ```
+///
+/// [OutputFormat::Custom]: crate::types::structured::OutputFormat::Custom
+pub trait Format: Send + Sync {
+    /// Name of the format in the instruction, e.g. `KDL`
+    fn name(&self) -> &str;
+
+    /// `value` written in the format as the instruction shows it, e.g. in a code fence. `None`
+    /// leaves the example out
+    fn render_example(&self, value: &Value) -> Option<String>;
+
+    /// The JSON Schema `schema` in the notation of the format, shown after the example. No
+    /// schema is shown by default
+    fn render_schema(&self, schema: &Value) -> Option<String> {
+        let _ = schema;
+        None
+    }
+
+    /// The answer of `response`, stripped of reasoning, as a JSON value
+    fn extract(&self, response: &str) -> Result<Value, ParseError>;
+}
+
+/// A shared [Format], equal to its clones only.
+#[derive(Clone)]
+pub struct CustomFormat(Arc<dyn Format>);
+
+impl CustomFormat {
+    pub fn new(format: impl Format + 'static) -> Self {
+        Self(Arc::new(format))
+    }
+}
+
+impl From<Box<dyn Format>> for CustomFormat {
+    fn from(format: Box<dyn Format>) -> Self {
+        Self(Arc::from(format))
+    }
+}
+
+impl Deref for CustomFormat {
+    type Target = dyn Format;
+
+    fn deref(&self) -> &Self::Target {
+        &*self.0
+    }
+}
+
+impl fmt::Debug for CustomFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("CustomFormat").field(&self.0.name()).finish()
+    }
+}
+
+impl PartialEq for CustomFormat {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
@@ -0,0 +1,148 @@
+//! Schemas of OpenAPI components, see [super::DynGenerator::from_openapi].
+use serde_json::{Map, Value};
+
+use crate::types::structured::ParseError;
+
+/// The schema at `pointer` in `spec`, an OpenAPI or JSON Schema document, as a standalone
+/// JSON Schema: references are inlined, recursive ones point into `definitions`, and the
+/// `nullable` of OpenAPI 3.0 becomes a `null` type. The title defaults to the last segment
+/// of the pointer.
+pub(super) fn component(spec: &Value, pointer: &str) -> Result<Value, ParseError> {
+    let mut resolver = Resolver {
+        spec,
+        stack: Vec::new(),
+        names: Vec::new(),
+        definitions: Map::new(),
+    };
+    let mut schema = resolver.resolve(pointer)?;
+
+    if let Value::Object(map) = &mut schema {
+        if !map.contains_key("title") {
+            let title = pointer.rsplit('/').next().unwrap_or_default();
+            map.insert("title".to_string(), Value::String(unescape(title)));
+        }
+        if !resolver.definitions.is_empty() {
+            map.insert(
+                "definitions".to_string(),
+                Value::Object(resolver.definitions),
+            );
+        }
+    }
+    Ok(schema)
+}
+
+struct Resolver<'a> {
+    spec: &'a Value,
+    /// References being inlined, a reference to one of them is recursive
+    stack: Vec<String>,
+    /// Definition name of each recursive reference
+    names: Vec<(String, String)>,
+    definitions: Map<String, Value>,
+}
+
+impl Resolver<'_> {
+    /// The schema `reference` points at, with its references resolved
+    fn resolve(&mut self, reference: &str) -> Result<Value, ParseError> {
+        let pointer = reference.strip_prefix('#').ok_or_else(|| {
+            ParseError::Other(format!(
+                "Unsupported reference `{}`, only references inside the document are resolved",
+                reference
+            ))
+        })?;
+        let mut schema = self
+            .spec
+            .pointer(pointer)
+            .cloned()
+            .ok_or_else(|| ParseError::Other(format!("No schema at `{}`", reference)))?;
+
+        self.stack.push(reference.to_string());
+        let inlined = self.inline(&mut schema);
+        self.stack.pop();
+        inlined.map(|_| schema)
+    }
+
+    fn inline(&mut self, schema: &mut Value) -> Result<(), ParseError> {
+        match schema {
+            Value::Object(map) => {
+                for (key, value) in map.iter_mut() {
+                    if key != "$ref" {
+                        self.inline(value)?;
+                    }
+                }
+                if map.remove("nullable") == Some(Value::Bool(true)) {
+                    make_nullable(map);
+                }
+
+                let Some(Value::String(reference)) = map.remove("$ref") else {
+                    return Ok(());
+                };
+                if self.stack.contains(&reference) {
+                    let name = self.definition(&reference)?;
+                    map.insert(
+                        "$ref".to_string(),
+                        Value::String(format!("#/definitions/{}", name)),
+                    );
+                    return Ok(());
+                }
+
+                // Keywords next to the reference, such as a description, take precedence
+                match self.resolve(&reference)? {
+                    Value::Object(resolved) => {
+                        for (key, value) in resolved {
+                            map.entry(key).or_insert(value);
+                        }
+                    }
+                    resolved => *schema = resolved,
+                }
+                Ok(())
+            }
+            Value::Array(items) => items.iter_mut().try_for_each(|item| self.inline(item)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Name of the definition of the recursive `reference`, resolved the first time
+    fn definition(&mut self, reference: &str) -> Result<String, ParseError> {
+        if let Some((_, name)) = self.names.iter().find(|(r, _)| r == reference) {
+            return Ok(name.clone());
+        }
+
+        let base = unescape(reference.rsplit('/').next().unwrap_or_default());
+        let mut name = base.clone();
+        let mut suffix = 2;
+        while self.definitions.contains_key(&name) {
+            name = format!("{}_{}", base, suffix);
+            suffix += 1;
+        }
+        self.names.push((reference.to_string(), name.clone()));
+        self.definitions.insert(name.clone(), Value::Null);
+
+        let schema = self.resolve(reference)?;
+        self.definitions.insert(name.clone(), schema);
+        Ok(name)
+    }
+}
+
+/// Accept `null` in addition to the type or values of the schema
+fn make_nullable(map: &mut Map<String, Value>) {
+    match map.get_mut("type") {
+        Some(Value::String(kind)) => {
+            let kind = std::mem::take(kind);
+            map.insert("type".to_string(), serde_json::json!([kind, "null"]));
+        }
+        Some(Value::Array(kinds)) if !kinds.contains(&Value::from("null")) => {
+            kinds.push(Value::from("null"));
+        }
+        _ => {}
+    }
+    if let Some(Value::Array(values)) = map.get_mut("enum") {
+        if !values.contains(&Value::Null) {
+            values.push(Value::Null);
+        }
+    }
+}
+
+/// A JSON Pointer segment with `~1` and `~0` unescaped
+fn unescape(segment: &str) -> String {
+    segment.replace("~1", "/").replace("~0", "~")
+}
@@ -0,0 +1,125 @@
+//! Best effort parsing of a JSON document that is still being generated.
+use serde_json::Value;
+
+/// Parse the beginning of a JSON document, closing the open string, arrays and objects.
+///
+/// Incomplete keys, literals and dangling separators are cut back to the last complete value.
+/// Returns `None` until the first array or object has started.
+pub(super) fn complete_partial_json(response: &str) -> Option<Value> {
+    let text = document_start(response)?;
+
+    let mut stack = Vec::new();
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut string_is_value = false;
+    let mut previous = None;
+    // Prefix length and open containers at the end of the last complete value
+    let mut last_complete = None;
+
+    for (index, c) in text.char_indices() {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+                if string_is_value {
+                    last_complete = Some((index + 1, stack.clone()));
+                }
+                previous = Some(c);
+            }
+            continue;
+        }
+
+        match c {
+            '"' => {
+                in_string = true;
+                string_is_value = previous == Some(':') || stack.last() == Some(&'[');
+            }
+            '{' | '[' => {
+                stack.push(c);
+                last_complete = Some((index + 1, stack.clone()));
+            }
+            '}' | ']' => {
+                stack.pop();
+                if stack.is_empty() {
+                    return serde_json::from_str(&text[..=index]).ok();
+                }
+                last_complete = Some((index + 1, stack.clone()));
+            }
+            ',' => last_complete = Some((index, stack.clone())),
+            _ => {}
+        }
+        if !c.is_whitespace() {
+            previous = Some(c);
+        }
+    }
+
+    // Close everything at the end, e.g. inside a value string or after a number
+    let mut candidate = text.to_string();
+    if in_string {
+        if escaped {
+            candidate.pop();
+        }
+        candidate.push('"');
+    }
+    if let Some(value) = close(&candidate, &stack) {
+        return Some(value);
+    }
+
+    let (end, stack) = last_complete?;
+    close(&text[..end], &stack)
+}
+
+/// The response from its first `{` or `[`, past the opening of a code fence if any.
+fn document_start(response: &str) -> Option<&str> {
+    let body = match response.find("```") {
+        Some(fence) => {
+            let after = &response[fence + 3..];
+            &after[after.find('\n').map_or(after.len(), |newline| newline + 1)..]
+        }
+        None => response,
+    };
+    let start = body.find(['{', '['])?;
+    Some(&body[start..])
+}
+
+fn close(prefix: &str, stack: &[char]) -> Option<Value> {
+    let mut document = prefix.trim_end().trim_end_matches(',').to_string();
+    for open in stack.iter().rev() {
+        document.push(if *open == '{' { '}' } else { ']' });
+    }
+    serde_json::from_str(&document).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::complete_partial_json;
+
+    #[test]
+    fn completes_prefixes() {
+        let cases = [
+            ("Here you go:\n```json\n{\"na", Some(json!({}))),
+            ("{\"name\": \"Ber", Some(json!({ "name": "Ber" }))),
+            (
+                "{\"name\": \"Berlin\", \"tags\": [\"a\", tr",
+                Some(json!({ "name": "Berlin", "tags": ["a"] })),
+            ),
+            ("{\"population\": 37", Some(json!({ "population": 37 }))),
+            (
+                "{\"a\": {\"b\": [1, 2], \"c\":",
+                Some(json!({ "a": { "b": [1, 2] } })),
+            ),
+            ("{\"quote\": \"say \\", Some(json!({ "quote": "say " }))),
+            ("{\"done\": true} trailing", Some(json!({ "done": true }))),
+            ("Thinking...", None),
+        ];
+
+        for (text, expected) in cases {
+            assert_eq!(complete_partial_json(text), expected, "{text}");
+        }
+    }
+}
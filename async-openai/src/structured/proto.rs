@@ -0,0 +1,576 @@
+//! Rendering of JSON Schemas as `.proto` message definitions, and the protobuf text format
+//! of answers.
+use serde_json::{Map, Value};
+
+/// Depth at which recursive schemas stop being followed.
+const MAX_DEPTH: usize = 16;
+
+/// Declare the message of `schema`, named after its `title` (`Output` without one), preceded
+/// by the messages and enums of its `definitions`.
+///
+/// An array is declared as a message with a `repeated` field `items`.
+pub(crate) fn render(schema: &Value) -> String {
+    let mut declarations = vec!["syntax = \"proto3\";".to_string()];
+
+    let definitions = schema
+        .get("definitions")
+        .or_else(|| schema.get("$defs"))
+        .and_then(Value::as_object);
+    for (name, definition) in definitions.into_iter().flatten() {
+        declarations.push(declaration(&type_name(name), definition));
+    }
+
+    let name = schema
+        .get("title")
+        .and_then(Value::as_str)
+        .map_or_else(|| "Output".to_string(), type_name);
+    if is_type(schema, "array") {
+        let items = Map::from_iter([(
+            "properties".to_string(),
+            serde_json::json!({ "items": schema }),
+        )]);
+        declarations.push(message(&name, &Value::Object(items), 0));
+    } else {
+        declarations.push(declaration(&name, schema));
+    }
+
+    declarations.join("\n\n")
+}
+
+fn declaration(name: &str, schema: &Value) -> String {
+    match string_values(schema) {
+        Some(values) => enumeration(name, &values, schema, 0),
+        None => message(name, schema, 0),
+    }
+}
+
+fn message(name: &str, schema: &Value, depth: usize) -> String {
+    let indent = "  ".repeat(depth);
+    let mut declaration = comment(schema, &indent);
+    declaration.push_str(&format!("{indent}message {name} {{\n"));
+
+    let mut nested = Vec::new();
+    let properties = schema.get("properties").and_then(Value::as_object);
+    for (number, (field, property)) in properties.into_iter().flatten().enumerate() {
+        declaration.push_str(&comment(property, &format!("{indent}  ")));
+        let (label, kind) = field_type(field, property, depth + 1, &mut nested);
+        declaration.push_str(&format!(
+            "{indent}  {label}{kind} {} = {};\n",
+            field_name(field),
+            number + 1
+        ));
+    }
+    for nested in nested {
+        declaration.push('\n');
+        declaration.push_str(&nested);
+        declaration.push('\n');
+    }
+
+    declaration.push_str(&format!("{indent}}}"));
+    declaration
+}
+
+fn enumeration(name: &str, values: &[String], schema: &Value, depth: usize) -> String {
+    let indent = "  ".repeat(depth);
+    let mut declaration = comment(schema, &indent);
+    declaration.push_str(&format!("{indent}enum {name} {{\n"));
+    for (number, value) in values.iter().enumerate() {
+        declaration.push_str(&format!("{indent}  {value} = {number};\n"));
+    }
+    declaration.push_str(&format!("{indent}}}"));
+    declaration
+}
+
+/// The label and type of the field `field` of schema `schema`, declaring the messages and
+/// enums of inline objects and enums in `nested`.
+fn field_type(
+    field: &str,
+    schema: &Value,
+    depth: usize,
+    nested: &mut Vec<String>,
+) -> (&'static str, String) {
+    if depth > MAX_DEPTH {
+        return ("", "google.protobuf.Value".to_string());
+    }
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        return (
+            "",
+            type_name(reference.rsplit('/').next().unwrap_or(reference)),
+        );
+    }
+    if let Some(values) = string_values(schema) {
+        let name = nested_name(field, schema);
+        nested.push(enumeration(&name, &values, &Value::Null, depth));
+        return ("", name);
+    }
+    if let Some(variants) = schema
+        .get("anyOf")
+        .or_else(|| schema.get("oneOf"))
+        .and_then(Value::as_array)
+    {
+        let mut present = variants.iter().filter(|variant| !is_type(variant, "null"));
+        return match (present.next(), present.next()) {
+            // Options
+            (Some(variant), None) => match field_type(field, variant, depth, nested) {
+                ("", kind) => ("optional ", kind),
+                typed => typed,
+            },
+            _ => ("", "google.protobuf.Value".to_string()),
+        };
+    }
+    if let Some([part]) = schema
+        .get("allOf")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+    {
+        return field_type(field, part, depth, nested);
+    }
+
+    let kinds: Vec<&str> = match schema.get("type") {
+        Some(Value::String(kind)) => vec![kind.as_str()],
+        Some(Value::Array(kinds)) => kinds.iter().filter_map(Value::as_str).collect(),
+        _ if schema.get("properties").is_some() => vec!["object"],
+        _ => vec![],
+    };
+    let optional = kinds.contains(&"null");
+    let kind = match kinds.iter().find(|kind| **kind != "null") {
+        Some(&"string") => "string".to_string(),
+        Some(&"boolean") => "bool".to_string(),
+        Some(&"integer") => integer(schema).to_string(),
+        Some(&"number") => match schema.get("format").and_then(Value::as_str) {
+            Some("float") => "float".to_string(),
+            _ => "double".to_string(),
+        },
+        Some(&"array") => {
+            let items = schema.get("items").filter(|items| items.is_object());
+            let kind = match items {
+                Some(items) => field_type(field, items, depth, nested).1,
+                None => "google.protobuf.Value".to_string(),
+            };
+            return ("repeated ", kind);
+        }
+        Some(&"object") => match schema.get("properties") {
+            Some(_) => {
+                let name = nested_name(field, schema);
+                nested.push(message(&name, schema, depth));
+                name
+            }
+            None => match schema.get("additionalProperties").filter(|s| s.is_object()) {
+                Some(values) => format!(
+                    "map<string, {}>",
+                    field_type(field, values, depth, nested).1
+                ),
+                None => "google.protobuf.Struct".to_string(),
+            },
+        },
+        _ => "google.protobuf.Value".to_string(),
+    };
+    (if optional { "optional " } else { "" }, kind)
+}
+
+/// The protobuf integer type of the `format` schemars gives Rust integers.
+fn integer(schema: &Value) -> &'static str {
+    match schema.get("format").and_then(Value::as_str) {
+        Some("uint8" | "uint16" | "uint32") => "uint32",
+        Some("uint64" | "uint" | "uint128") => "uint64",
+        Some("int8" | "int16" | "int32") => "int32",
+        _ => "int64",
+    }
+}
+
+/// The values of a schema allowing a set of strings that are valid enum value names.
+fn string_values(schema: &Value) -> Option<Vec<String>> {
+    let values: Vec<&Value> = if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+        values.iter().collect()
+    } else {
+        // Unit variants with doc comments
+        let variants = schema.get("oneOf").and_then(Value::as_array)?;
+        variants
+            .iter()
+            .map(
+                |variant| match variant.get("enum").and_then(Value::as_array) {
+                    Some(values) if values.len() == 1 => Some(&values[0]),
+                    _ => variant.get("const"),
+                },
+            )
+            .collect::<Option<_>>()?
+    };
+    values
+        .into_iter()
+        .map(|value| {
+            value
+                .as_str()
+                .filter(|value| is_identifier(value))
+                .map(String::from)
+        })
+        .collect::<Option<Vec<_>>>()
+        .filter(|values| !values.is_empty())
+}
+
+/// Whether the `type` of `schema` is or includes `kind`.
+fn is_type(schema: &Value, kind: &str) -> bool {
+    match schema.get("type") {
+        Some(Value::String(kinds)) => kinds == kind,
+        Some(Value::Array(kinds)) => kinds.iter().any(|k| k == kind),
+        _ => false,
+    }
+}
+
+fn comment(schema: &Value, indent: &str) -> String {
+    schema
+        .get("description")
+        .and_then(Value::as_str)
+        .map(|description| {
+            description
+                .lines()
+                .map(|line| format!("{indent}// {line}\n"))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// The field name, with the characters protobuf doesn't allow replaced by `_`.
+fn field_name(field: &str) -> String {
+    let name: String = field
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{name}")
+    } else {
+        name
+    }
+}
+
+/// A type name from a schema title or definition name such as `Option_for_Author`.
+fn type_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '_')
+        .collect()
+}
+
+/// The name of the message or enum declared for the inline `schema` of `field`: its title,
+/// or `line_items` as `LineItems`.
+fn nested_name(field: &str, schema: &Value) -> String {
+    match schema.get("title").and_then(Value::as_str) {
+        Some(title) => type_name(title),
+        None => type_name(&pascal_case(field)),
+    }
+}
+
+/// `line_items` as `LineItems`.
+fn pascal_case(field: &str) -> String {
+    field
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            chars
+                .next()
+                .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+/// `value` in the protobuf text format, an array as repeated `items` fields.
+#[cfg(feature = "protobuf")]
+pub(crate) fn render_text(value: &Value) -> String {
+    let mut text = String::new();
+    match value {
+        Value::Object(fields) => write_fields(fields, 0, &mut text),
+        Value::Array(items) => {
+            for item in items {
+                write_field("items", item, 0, &mut text);
+            }
+        }
+        value => write_field("value", value, 0, &mut text),
+    }
+    text
+}
+
+#[cfg(feature = "protobuf")]
+fn write_fields(fields: &Map<String, Value>, depth: usize, text: &mut String) {
+    for (field, value) in fields {
+        write_field(field, value, depth, text);
+    }
+}
+
+#[cfg(feature = "protobuf")]
+fn write_field(field: &str, value: &Value, depth: usize, text: &mut String) {
+    let indent = "  ".repeat(depth);
+    match value {
+        // Unset
+        Value::Null => {}
+        Value::Array(items) => {
+            for item in items {
+                write_field(field, item, depth, text);
+            }
+        }
+        Value::Object(fields) => {
+            text.push_str(&format!("{indent}{field} {{\n"));
+            write_fields(fields, depth + 1, text);
+            text.push_str(&format!("{indent}}}\n"));
+        }
+        // JSON string escapes are valid in the text format
+        value => text.push_str(&format!("{indent}{field}: {value}\n")),
+    }
+}
+
+/// Parse the protobuf text format `text` as a JSON value. Fields that `schema` declares as
+/// arrays are always arrays, others only when they are repeated. A message of an array schema
+/// gives the values of its `items` field.
+#[cfg(feature = "protobuf")]
+pub(crate) fn parse_text(text: &str, schema: Option<&Value>) -> Result<Value, String> {
+    let mut parser = TextParser {
+        tokens: tokenize(text)?,
+        position: 0,
+        root: schema,
+    };
+    let root = schema.map(|schema| parser.resolve(schema));
+    let array = root.is_some_and(|root| is_type(root, "array"));
+    let message_schema = if array { None } else { root };
+    let mut fields = parser.message(message_schema, None)?;
+    if array {
+        return Ok(match fields.remove("items") {
+            Some(Value::Array(items)) => Value::Array(items),
+            Some(item) => Value::Array(vec![item]),
+            None => Value::Array(vec![]),
+        });
+    }
+    Ok(Value::Object(fields))
+}
+
+#[cfg(feature = "protobuf")]
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Identifier(String),
+    String(String),
+    Number(String),
+    Punct(char),
+}
+
+#[cfg(feature = "protobuf")]
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '#' => while chars.next_if(|&c| c != '\n').is_some() {},
+            ':' | '{' | '}' | '<' | '>' | '[' | ']' | ',' | ';' => {
+                chars.next();
+                tokens.push(Token::Punct(c));
+            }
+            '"' | '\'' => {
+                chars.next();
+                let mut string = String::new();
+                loop {
+                    match chars.next() {
+                        None => return Err("unterminated string".to_string()),
+                        Some(end) if end == c => break,
+                        Some('\\') => string.push(match chars.next() {
+                            Some('n') => '\n',
+                            Some('t') => '\t',
+                            Some('r') => '\r',
+                            Some(escaped) => escaped,
+                            None => return Err("unterminated string".to_string()),
+                        }),
+                        Some(c) => string.push(c),
+                    }
+                }
+                // Adjacent strings are concatenated
+                match tokens.last_mut() {
+                    Some(Token::String(previous)) => previous.push_str(&string),
+                    _ => tokens.push(Token::String(string)),
+                }
+            }
+            c if c.is_ascii_digit() || c == '-' || c == '+' || c == '.' => {
+                let mut number = String::new();
+                while let Some(c) =
+                    chars.next_if(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '+' | '.'))
+                {
+                    number.push(c);
+                }
+                tokens.push(Token::Number(number));
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut identifier = String::new();
+                while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || *c == '_') {
+                    identifier.push(c);
+                }
+                tokens.push(Token::Identifier(identifier));
+            }
+            c => return Err(format!("unexpected character '{c}'")),
+        }
+    }
+    Ok(tokens)
+}
+
+#[cfg(feature = "protobuf")]
+struct TextParser<'a> {
+    tokens: Vec<Token>,
+    position: usize,
+    root: Option<&'a Value>,
+}
+
+#[cfg(feature = "protobuf")]
+impl<'a> TextParser<'a> {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn eat(&mut self, punct: char) -> bool {
+        let found = self.peek() == Some(&Token::Punct(punct));
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    /// The fields of a message up to `close`, or to the end of the text.
+    fn message(
+        &mut self,
+        schema: Option<&'a Value>,
+        close: Option<char>,
+    ) -> Result<Map<String, Value>, String> {
+        let mut fields = Map::new();
+        loop {
+            while self.eat(',') || self.eat(';') {}
+            match self.next() {
+                None if close.is_none() => return Ok(fields),
+                None => return Err("unexpected end of message".to_string()),
+                Some(Token::Punct(c)) if Some(c) == close => return Ok(fields),
+                Some(Token::Identifier(name)) => {
+                    let field = schema.and_then(|schema| self.property(schema, &name));
+                    let repeated = field.is_some_and(|field| is_type(field, "array"));
+                    let item = match field {
+                        Some(field) if repeated => {
+                            field.get("items").map(|items| self.resolve(items))
+                        }
+                        field => field,
+                    };
+
+                    self.eat(':');
+                    let values = if self.eat('[') {
+                        let mut values = Vec::new();
+                        while !self.eat(']') {
+                            values.push(self.value(item)?);
+                            self.eat(',');
+                        }
+                        values
+                    } else {
+                        vec![self.value(item)?]
+                    };
+                    add(&mut fields, name, values, repeated);
+                }
+                Some(token) => return Err(format!("expected a field name, found {token:?}")),
+            }
+        }
+    }
+
+    fn value(&mut self, schema: Option<&'a Value>) -> Result<Value, String> {
+        match self.next() {
+            Some(Token::Punct('{')) => Ok(Value::Object(self.message(schema, Some('}'))?)),
+            Some(Token::Punct('<')) => Ok(Value::Object(self.message(schema, Some('>'))?)),
+            Some(Token::String(string)) => Ok(Value::String(string)),
+            Some(Token::Number(number)) => {
+                let number = number.trim_start_matches('+');
+                // Floats may have an `f` suffix
+                let trimmed = number.trim_end_matches(['f', 'F']);
+                serde_json::from_str::<serde_json::Number>(trimmed)
+                    .or_else(|_| {
+                        trimmed
+                            .parse::<f64>()
+                            .ok()
+                            .and_then(serde_json::Number::from_f64)
+                            .ok_or(())
+                    })
+                    .map(Value::Number)
+                    .map_err(|_| format!("invalid number {number}"))
+            }
+            Some(Token::Identifier(identifier)) => Ok(match identifier.as_str() {
+                "true" | "True" | "t" => Value::Bool(true),
+                "false" | "False" | "f" => Value::Bool(false),
+                // Enum values
+                _ => Value::String(identifier),
+            }),
+            Some(token) => Err(format!("expected a value, found {token:?}")),
+            None => Err("unexpected end of message".to_string()),
+        }
+    }
+
+    /// The schema of the field `name` of the message of `schema`, without its `null` variant.
+    fn property(&self, schema: &'a Value, name: &str) -> Option<&'a Value> {
+        let property = self.resolve(schema).get("properties")?.get(name)?;
+        let property = self.resolve(property);
+        let present = property
+            .get("anyOf")
+            .and_then(Value::as_array)
+            .and_then(|variants| {
+                let mut present = variants.iter().filter(|variant| !is_type(variant, "null"));
+                match (present.next(), present.next()) {
+                    (Some(variant), None) => Some(self.resolve(variant)),
+                    _ => None,
+                }
+            });
+        Some(present.unwrap_or(property))
+    }
+
+    /// `schema`, or the definition it refers to.
+    fn resolve(&self, schema: &'a Value) -> &'a Value {
+        let mut schema = schema;
+        for _ in 0..MAX_DEPTH {
+            let Some(reference) = schema.get("$ref").and_then(Value::as_str) else {
+                break;
+            };
+            match reference
+                .strip_prefix('#')
+                .and_then(|pointer| self.root?.pointer(pointer))
+            {
+                Some(definition) => schema = definition,
+                None => break,
+            }
+        }
+        schema
+    }
+}
+
+/// Add `values` to `field`, as an array when the field is `repeated` or already set.
+#[cfg(feature = "protobuf")]
+fn add(fields: &mut Map<String, Value>, field: String, values: Vec<Value>, repeated: bool) {
+    match fields.get_mut(&field) {
+        Some(Value::Array(items)) if repeated => items.extend(values),
+        Some(existing) => {
+            let previous = existing.take();
+            let mut items = match previous {
+                Value::Array(items) if repeated => items,
+                value => vec![value],
+            };
+            items.extend(values);
+            *existing = Value::Array(items);
+        }
+        None if repeated || values.len() > 1 => {
+            fields.insert(field, Value::Array(values));
+        }
+        None => {
+            fields.insert(field, values.into_iter().next().unwrap_or(Value::Null));
+        }
+    }
+}
@@ -0,0 +1,80 @@
+//! Stripping the reasoning of thinking models from responses, see [ReasoningFilter].
+use std::borrow::Cow;
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+use crate::types::structured::{ParseError, ReasoningFilter};
+
+/// Tags around the reasoning of the common reasoning models
+const REASONING_TAGS: [&str; 4] = ["think", "thinking", "reasoning", "reflection"];
+
+/// Closed reasoning sections and the analysis channel of the gpt-oss chat format, then the
+/// reasoning whose opening tag was part of the prompt, as with the chat template of
+/// DeepSeek-R1, up to the first remaining closing tag
+fn builtin_regexes() -> &'static [Regex] {
+    static REGEXES: OnceLock<Vec<Regex>> = OnceLock::new();
+    REGEXES.get_or_init(|| {
+        let mut regexes: Vec<Regex> = REASONING_TAGS
+            .iter()
+            .map(|tag| Regex::new(&format!(r"(?is)<{tag}>.*?</{tag}>")).unwrap())
+            .collect();
+        regexes.push(
+            Regex::new(
+                r"(?s)(?:<\|start\|>assistant)?<\|channel\|>analysis<\|message\|>.*?<\|end\|>",
+            )
+            .unwrap(),
+        );
+        regexes.push(Regex::new(r"(?is)^.*?</(?:think|thinking|reasoning)>").unwrap());
+        regexes
+    })
+}
+
+/// Header of the final channel of the gpt-oss chat format, left once the analysis is removed
+fn final_channel_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r"(?:<\|start\|>assistant)?<\|channel\|>final<\|message\|>").unwrap()
+    })
+}
+
+/// `response` without the reasoning `filter` removes, and the removed reasoning. Fails when a
+/// pattern of the filter isn't a valid regular expression
+pub(super) fn strip<'a>(
+    filter: &ReasoningFilter,
+    response: &'a str,
+) -> Result<(Cow<'a, str>, Option<String>), ParseError> {
+    let patterns = match filter {
+        ReasoningFilter::Off => return Ok((Cow::Borrowed(response), None)),
+        ReasoningFilter::BuiltIn => &[][..],
+        ReasoningFilter::Patterns(patterns) => patterns.as_slice(),
+    };
+    let custom = patterns
+        .iter()
+        .map(|pattern| {
+            Regex::new(pattern).map_err(|e| {
+                ParseError::Other(format!("Invalid reasoning pattern `{}`: {}", pattern, e))
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut answer = response.to_string();
+    let mut reasoning = Vec::new();
+    for regex in builtin_regexes().iter().chain(custom.iter()) {
+        answer = regex
+            .replace_all(&answer, |captures: &regex::Captures| {
+                reasoning.push(captures[0].to_string());
+                ""
+            })
+            .into_owned();
+    }
+    answer = final_channel_regex().replace_all(&answer, "").into_owned();
+
+    if reasoning.is_empty() {
+        return Ok((Cow::Borrowed(response), None));
+    }
+    Ok((
+        Cow::Owned(answer.trim().to_string()),
+        Some(reasoning.join("\n")),
+    ))
+}
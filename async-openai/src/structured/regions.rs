@@ -0,0 +1,115 @@
+//! Fields holding the [BoundingBox] of the image region a value was read from, so that
+//! instructions can ask for normalized coordinates and validation can check them.
+use serde_json::Value;
+
+use crate::types::structured::{BoundingBox, ValidationIssue, ValidationOptions};
+
+/// Depth at which recursive schemas stop being followed.
+const MAX_DEPTH: usize = 16;
+
+/// Keyword of the issues of bounding boxes reaching past the image, for
+/// [ValidationOptions::severity].
+pub(crate) const KEYWORD: &str = "boundingBox";
+
+/// Description paths, such as `lines[].total.region`, of the fields of `schema` that are a
+/// [BoundingBox], in the order of the schema.
+pub(crate) fn region_fields(schema: &Value) -> Vec<String> {
+    let mut fields = Vec::new();
+    collect(schema, schema, "", 0, &mut fields);
+    fields
+}
+
+fn collect(root: &Value, schema: &Value, path: &str, depth: usize, fields: &mut Vec<String>) {
+    if depth > MAX_DEPTH {
+        return;
+    }
+    if is_bounding_box(root, schema) {
+        if !path.is_empty() && !fields.iter().any(|field| field == path) {
+            fields.push(path.to_string());
+        }
+        return;
+    }
+    let schema = resolve(root, schema);
+
+    if let Some(Value::Object(properties)) = schema.get("properties") {
+        for (name, property) in properties {
+            let path = if path.is_empty() {
+                name.clone()
+            } else {
+                format!("{path}.{name}")
+            };
+            collect(root, property, &path, depth + 1, fields);
+        }
+    }
+    if let Some(items) = schema.get("items").filter(|items| items.is_object()) {
+        collect(root, items, &format!("{path}[]"), depth + 1, fields);
+    }
+    for key in ["anyOf", "oneOf", "allOf"] {
+        for variant in schema
+            .get(key)
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            collect(root, variant, path, depth + 1, fields);
+        }
+    }
+}
+
+/// Whether `schema` is the schema of [BoundingBox], or refers to its definition.
+fn is_bounding_box(root: &Value, schema: &Value) -> bool {
+    let reference = schema.get("$ref").and_then(Value::as_str);
+    reference.is_some_and(|reference| reference.ends_with("/BoundingBox"))
+        || resolve(root, schema).get("title").and_then(Value::as_str) == Some("BoundingBox")
+}
+
+/// `schema`, or the definition of `root` it refers to.
+fn resolve<'a>(root: &'a Value, schema: &'a Value) -> &'a Value {
+    let mut schema = schema;
+    // Bounded, definitions may refer to each other
+    for _ in 0..MAX_DEPTH {
+        let Some(reference) = schema.get("$ref").and_then(Value::as_str) else {
+            break;
+        };
+        match reference
+            .strip_prefix('#')
+            .and_then(|pointer| root.pointer(pointer))
+        {
+            Some(definition) => schema = definition,
+            None => break,
+        }
+    }
+    schema
+}
+
+/// An issue for every bounding box of `value` at the `fields` of [region_fields] that reaches
+/// past the right or bottom edge of the image. Coordinates out of the 0 to 1 range are
+/// reported by the JSON Schema.
+pub(crate) fn issues(
+    value: &Value,
+    fields: &[String],
+    options: &ValidationOptions,
+) -> Vec<ValidationIssue> {
+    let boxes = fields
+        .iter()
+        .flat_map(|field| super::validators::values_at(value, field));
+
+    boxes
+        .filter_map(|(path, value)| {
+            let region: BoundingBox = serde_json::from_value(value.clone()).ok()?;
+            if region.fits() {
+                return None;
+            }
+            let (right, bottom) = (region.x + region.width, region.y + region.height);
+            let message = format!(
+                "{path}: bounding box reaches past the image (x + width = {right}, y + height = {bottom})"
+            );
+            Some(ValidationIssue {
+                path,
+                keyword: KEYWORD.to_string(),
+                message,
+                severity: options.severity_of(KEYWORD),
+            })
+        })
+        .collect()
+}
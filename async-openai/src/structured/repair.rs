@@ -0,0 +1,199 @@
+//! Follow-up prompts asking the model to fix a response that failed to parse or validate.
+use jsonschema::{error::ValidationErrorKind, paths::LocationSegment, Validator};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::{extraction, validation, Generator};
+use crate::types::structured::{InstructionTemplate, OutputFormat, ParseError, Structured};
+
+/// Characters of the response shown before the position of a syntax error.
+const FRAGMENT_BEFORE: usize = 30;
+/// Characters of the response shown after the position of a syntax error.
+const FRAGMENT_AFTER: usize = 10;
+
+impl<T> Generator<T>
+where
+    T: Structured + for<'de> Deserialize<'de> + JsonSchema,
+{
+    /// A follow-up prompt asking the model to fix `original_output`, which failed to parse
+    /// with `error`.
+    ///
+    /// Missing and invalid fields are listed one by one, by path such as `items[0].price`,
+    /// with the validation error that echoes the invalid value. When the output isn't valid in
+    /// the format at all, the parser error is given with the fragment of JSON output it
+    /// stopped at. The phrases come from the locale or the instruction template.
+    ///
+    /// ```
+    /// # #[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+    /// # struct Contact { name: String, email: String }
+    /// use async_openai::structured::Generator;
+    ///
+    /// let generator = Generator::with_validation(Contact::default());
+    /// let output = r#"{"name": "Ada"}"#;
+    /// let error = generator.parse_response(output).unwrap_err();
+    ///
+    /// let repair = generator.build_repair_instruction(&error, output);
+    /// assert!(repair.contains("- email is missing"));
+    /// ```
+    pub fn build_repair_instruction(&self, error: &ParseError, original_output: &str) -> String {
+        let phrases = self.config.phrases();
+        let format = match &self.config.custom_format {
+            Some(custom) if self.config.format == OutputFormat::Custom => custom.name(),
+            _ => format_name(self.config.format),
+        };
+
+        let mut problems = Vec::new();
+        if let ParseError::Refusal(_) = error {
+            problems.push(error.to_string());
+        } else {
+            match self.extract_value(self.config.format, original_output) {
+                Ok(value) => problems.extend(self.schema_problems(&value, phrases)),
+                Err(syntax) => {
+                    // For JSON, the error of the document rather than of the whole response
+                    let (syntax, fragment) = match self.broken_json(original_output) {
+                        Some((error, fragment)) => (error, Some(fragment)),
+                        None => match syntax {
+                            ParseError::Extraction(message) => (message, None),
+                            other => (other.to_string(), None),
+                        },
+                    };
+                    problems.push(
+                        phrases
+                            .repair_syntax
+                            .replace("{format}", format)
+                            .replace("{error}", &syntax),
+                    );
+                    if let Some(fragment) = fragment {
+                        problems.push(phrases.repair_near.replace("{fragment}", &fragment));
+                    }
+                }
+            }
+        }
+        // The value is valid, but doesn't deserialize into `T`
+        if problems.is_empty() {
+            problems.push(error.to_string());
+        }
+
+        let mut instruction = phrases.repair_intro.replace("{format}", format);
+        for problem in problems {
+            instruction.push_str("\n- ");
+            instruction.push_str(&problem);
+        }
+        instruction
+    }
+
+    /// A line per validation error of `value`.
+    fn schema_problems(&self, value: &Value, phrases: &InstructionTemplate) -> Vec<String> {
+        let compiled;
+        let validator = match &self.validator {
+            Some(Ok(validator)) => validator,
+            _ => {
+                let Some(schema) = &self.config.json_schema else {
+                    return Vec::new();
+                };
+                let Ok(validator) =
+                    validation::compile(schema, &self.effective_validation_options())
+                else {
+                    return Vec::new();
+                };
+                compiled = validator;
+                &compiled
+            }
+        };
+        problems(validator, value, phrases)
+    }
+
+    /// The error of the JSON document of `output`, and the text around the position where
+    /// parsing it fails.
+    fn broken_json(&self, output: &str) -> Option<(String, String)> {
+        if !matches!(
+            self.config.format,
+            OutputFormat::Json | OutputFormat::JsonArray
+        ) {
+            return None;
+        }
+        let located = extraction::locate(self.config.extraction, output);
+        let json = &located[located.find(['{', '['])?..];
+        let error = serde_json::from_str::<Value>(json).err()?;
+        let line_start: usize = json
+            .split_inclusive('\n')
+            .take(error.line().saturating_sub(1))
+            .map(str::len)
+            .sum();
+        let mut position = (line_start + error.column()).min(json.len());
+        while !json.is_char_boundary(position) {
+            position -= 1;
+        }
+        let before: String = {
+            let mut chars: Vec<char> = json[..position]
+                .chars()
+                .rev()
+                .take(FRAGMENT_BEFORE)
+                .collect();
+            chars.reverse();
+            chars.into_iter().collect()
+        };
+        let after: String = json[position..].chars().take(FRAGMENT_AFTER).collect();
+        let fragment = format!("{before}{after}");
+        Some((error.to_string(), format!("`{}`", fragment.trim())))
+    }
+}
+
+fn problems(validator: &Validator, value: &Value, phrases: &InstructionTemplate) -> Vec<String> {
+    validator
+        .iter_errors(value)
+        .map(|error| {
+            let mut path = String::new();
+            for segment in &error.instance_path {
+                match segment {
+                    LocationSegment::Property(name) => push_property(&mut path, name),
+                    LocationSegment::Index(index) => path.push_str(&format!("[{index}]")),
+                }
+            }
+            match &error.kind {
+                ValidationErrorKind::Required { property } => {
+                    push_property(&mut path, property.as_str().unwrap_or_default());
+                    phrases.repair_missing.replace("{field}", &path)
+                }
+                _ => {
+                    let field = if path.is_empty() {
+                        "(root)"
+                    } else {
+                        path.as_str()
+                    };
+                    phrases
+                        .repair_invalid
+                        .replace("{field}", field)
+                        .replace("{error}", &error.to_string())
+                }
+            }
+        })
+        .collect()
+}
+
+fn push_property(path: &mut String, name: &str) {
+    if !path.is_empty() {
+        path.push('.');
+    }
+    path.push_str(name);
+}
+
+/// Name of `format` in prompts.
+fn format_name(format: OutputFormat) -> &'static str {
+    match format {
+        OutputFormat::Json | OutputFormat::JsonArray => "JSON",
+        #[cfg(feature = "yaml")]
+        OutputFormat::Yaml => "YAML",
+        #[cfg(feature = "xml")]
+        OutputFormat::Xml => "XML",
+        #[cfg(feature = "toml")]
+        OutputFormat::Toml => "TOML",
+        #[cfg(feature = "csv")]
+        OutputFormat::Csv => "CSV",
+        #[cfg(feature = "protobuf")]
+        OutputFormat::TextProto => "protobuf text format",
+        OutputFormat::MarkdownTable => "markdown table",
+        OutputFormat::Custom => "the requested format",
+    }
+}
@@ -0,0 +1,98 @@
+//! Asking the model to fix responses that fail to parse or validate.
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::Generator;
+use crate::{
+    config::Config,
+    error::OpenAIError,
+    types::{
+        structured::{ParseError, Response, Structured},
+        ChatCompletionRequestAssistantMessage, ChatCompletionRequestSystemMessage,
+        ChatCompletionRequestUserMessage, CreateChatCompletionRequest,
+    },
+    Client,
+};
+
+/// A response that could not be used, recorded by [Generator::parse_with_retry].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParseAttempt {
+    /// Content of the first choice
+    pub content: String,
+    /// Why the content was rejected: the parse error or the validation messages
+    pub error: String,
+}
+
+impl<T> Generator<T>
+where
+    T: Structured + for<'de> Deserialize<'de> + JsonSchema,
+{
+    /// Send `request` with the instruction appended as a system message and parse the first
+    /// choice. When the response doesn't parse or fails validation, the error and the
+    /// instruction are sent back to the model asking it to fix its output, for at most
+    /// `max_attempts` requests in total.
+    ///
+    /// Returns the first usable response with the rejected attempts before it. Once the attempts
+    /// are used up, a response with validation messages is returned as is, and a response that
+    /// doesn't parse as [OpenAIError::StructuredOutput]. Refusals are not retried.
+    pub async fn parse_with_retry<C: Config>(
+        &self,
+        client: &Client<C>,
+        mut request: CreateChatCompletionRequest,
+        max_attempts: usize,
+    ) -> Result<(Response<T>, Vec<ParseAttempt>), OpenAIError> {
+        let instruction = self.build_instruction_text();
+        request
+            .messages
+            .push(ChatCompletionRequestSystemMessage::from(instruction.as_str()).into());
+
+        let mut attempts = Vec::new();
+        loop {
+            let response = client.chat().create(request.clone()).await?;
+            let content = response
+                .choices
+                .first()
+                .and_then(|choice| choice.message.content.clone())
+                .unwrap_or_default();
+
+            let parsed = match self.parse_completion(&response) {
+                Err(error @ ParseError::Refusal(_)) => {
+                    return Err(OpenAIError::StructuredOutput(Box::new(error)))
+                }
+                parsed => parsed,
+            };
+            let error = match &parsed {
+                Ok(parsed) => parsed
+                    .validation_messages
+                    .as_ref()
+                    .filter(|messages| !messages.is_empty())
+                    .map(|messages| messages.join("; ")),
+                Err(error) => Some(error.to_string()),
+            };
+            let Some(error) = error.filter(|_| attempts.len() + 1 < max_attempts) else {
+                return parsed
+                    .map(|mut parsed| {
+                        parsed.metadata.repairs = attempts.len();
+                        (parsed, attempts)
+                    })
+                    .map_err(|e| OpenAIError::StructuredOutput(Box::new(e)));
+            };
+
+            tracing::warn!(
+                "structured response rejected on attempt {}: {error}",
+                attempts.len() + 1
+            );
+            request
+                .messages
+                .push(ChatCompletionRequestAssistantMessage::from(content.as_str()).into());
+            request.messages.push(
+                ChatCompletionRequestUserMessage::from(format!(
+                    "Your previous response could not be used: {error}\n\n\
+                     Answer again, following these instructions exactly:\n\n{instruction}"
+                ))
+                .into(),
+            );
+            attempts.push(ParseAttempt { content, error });
+        }
+    }
+}
@@ -0,0 +1,170 @@
+//! Clean-ups of generated text: markdown fences, HTML entities, smart quotes, zero-width
+//! characters and repeated whitespace.
+//!
+//! Each helper can be used on its own, and a [Sanitizer] applies a selection of them to every
+//! string of the parsed data when set with
+//! [Generator::sanitize](crate::structured::Generator::sanitize).
+//!
+//! ```
+//! use async_openai::structured::sanitize::{self, Sanitizer};
+//!
+//! assert_eq!(sanitize::normalize_quotes("“Hi” it’s me"), "\"Hi\" it's me");
+//! assert_eq!(Sanitizer::all().apply("Fish &amp;  chips\u{200B}"), "Fish & chips");
+//! ```
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Characters with no width, often left in generated text.
+const ZERO_WIDTH: [char; 5] = ['\u{200B}', '\u{200C}', '\u{200D}', '\u{2060}', '\u{FEFF}'];
+
+/// The clean-ups applied to the strings of parsed data. [Sanitizer::default] applies none.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Sanitizer {
+    /// Remove the lines opening and closing markdown code fences, see [strip_fences]
+    pub strip_fences: bool,
+    /// Replace HTML entities by their characters, see [unescape_html]
+    pub unescape_html: bool,
+    /// Replace typographic quotes by ASCII quotes, see [normalize_quotes]
+    pub normalize_quotes: bool,
+    /// Remove zero-width characters, see [remove_zero_width]
+    pub remove_zero_width: bool,
+    /// Collapse repeated whitespace, see [collapse_whitespace]
+    pub collapse_whitespace: bool,
+}
+
+impl Sanitizer {
+    /// Every clean-up.
+    pub fn all() -> Self {
+        Self {
+            strip_fences: true,
+            unescape_html: true,
+            normalize_quotes: true,
+            remove_zero_width: true,
+            collapse_whitespace: true,
+        }
+    }
+
+    /// `text` with the selected clean-ups applied, entities being unescaped before
+    /// whitespace is collapsed.
+    pub fn apply(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        if self.strip_fences {
+            text = strip_fences(&text);
+        }
+        if self.remove_zero_width {
+            text = remove_zero_width(&text);
+        }
+        if self.unescape_html {
+            text = unescape_html(&text);
+        }
+        if self.normalize_quotes {
+            text = normalize_quotes(&text);
+        }
+        if self.collapse_whitespace {
+            text = collapse_whitespace(&text);
+        }
+        text
+    }
+
+    /// Apply the selected clean-ups to every string of `value`, object keys excepted.
+    pub fn apply_to_value(&self, value: &mut Value) {
+        match value {
+            Value::String(text) => *text = self.apply(text),
+            Value::Array(items) => items.iter_mut().for_each(|item| self.apply_to_value(item)),
+            Value::Object(fields) => fields
+                .values_mut()
+                .for_each(|field| self.apply_to_value(field)),
+            _ => {}
+        }
+    }
+}
+
+/// `text` without the lines opening and closing markdown code fences, keeping their content.
+pub fn strip_fences(text: &str) -> String {
+    let lines: Vec<&str> = text
+        .split_inclusive('\n')
+        .filter(|line| !line.trim_start().starts_with("```"))
+        .collect();
+    if lines.len() == text.split_inclusive('\n').count() {
+        return text.to_string();
+    }
+    lines.concat().trim_end_matches(['\r', '\n']).to_string()
+}
+
+/// `text` with the named entities of XML, `&nbsp;`, and numeric entities replaced by their
+/// characters. Unknown entities are kept.
+pub fn unescape_html(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest[1..]
+            .find(';')
+            .filter(|end| *end <= 10)
+            .and_then(|end| Some((end, entity_char(&rest[1..end + 1])?)));
+        match entity {
+            Some((end, c)) => {
+                unescaped.push(c);
+                rest = &rest[end + 2..];
+            }
+            None => {
+                unescaped.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+/// The character of the entity `name`, written without `&` and `;`.
+fn entity_char(name: &str) -> Option<char> {
+    let code = match name {
+        "amp" => return Some('&'),
+        "lt" => return Some('<'),
+        "gt" => return Some('>'),
+        "quot" => return Some('"'),
+        "apos" => return Some('\''),
+        "nbsp" => return Some('\u{A0}'),
+        _ => match name.strip_prefix('#')? {
+            hex if hex.starts_with(['x', 'X']) => u32::from_str_radix(&hex[1..], 16).ok()?,
+            decimal => decimal.parse().ok()?,
+        },
+    };
+    char::from_u32(code)
+}
+
+/// `text` with typographic single and double quotes replaced by `'` and `"`.
+pub fn normalize_quotes(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}' | '\u{2032}' => '\'',
+            '\u{201C}' | '\u{201D}' | '\u{201E}' | '\u{201F}' | '\u{2033}' => '"',
+            c => c,
+        })
+        .collect()
+}
+
+/// `text` without zero-width spaces, joiners and byte order marks.
+pub fn remove_zero_width(text: &str) -> String {
+    text.chars().filter(|c| !ZERO_WIDTH.contains(c)).collect()
+}
+
+/// `text` with runs of spaces in a line collapsed to one space, trailing spaces and blank
+/// lines at either end removed, and runs of blank lines collapsed to one.
+pub fn collapse_whitespace(text: &str) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.lines() {
+        let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        let after_blank = lines.last().map_or(true, |last| last.is_empty());
+        if line.is_empty() && after_blank {
+            continue;
+        }
+        lines.push(line);
+    }
+    while lines.last().is_some_and(|line| line.is_empty()) {
+        lines.pop();
+    }
+    lines.join("\n")
+}
@@ -0,0 +1,150 @@
+//! Multi-turn structured extraction, refining the same answer over several turns.
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use super::Generator;
+use crate::{
+    config::Config,
+    error::OpenAIError,
+    memory::{MemoryStrategy, SlidingWindow},
+    types::{
+        structured::{Response, Structured},
+        ChatCompletionRequestAssistantMessage, ChatCompletionRequestMessage,
+        ChatCompletionRequestSystemMessage, ChatCompletionRequestUserMessage,
+        CreateChatCompletionRequest,
+    },
+    Client,
+};
+
+/// Tokens of history kept unless configured otherwise, the context window of `gpt-4o`.
+pub const DEFAULT_SESSION_CONTEXT_WINDOW: usize = 128_000;
+
+/// A conversation whose every answer is parsed with the same [Generator], for follow-up turns
+/// such as "now fix item 3" that refine the previous answer.
+///
+/// The instruction of the generator is sent once, as the first system message, and each turn
+/// adds the input and the answer to the history. Before every request the oldest turns are
+/// dropped once the history outgrows the context window; the instruction and the input of the
+/// turn are always kept.
+///
+/// ```no_run
+/// # async fn run() -> Result<(), async_openai::error::OpenAIError> {
+/// use async_openai::{
+///     structured::{Generator, StructuredSession},
+///     Client,
+/// };
+/// # #[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+/// # struct Invoice { items: Vec<String> }
+///
+/// let client = Client::new();
+/// let mut session = StructuredSession::new(&client, "gpt-4o", Generator::json(Invoice::default()));
+/// let draft = session.ask("Extract the invoice: ...").await?;
+/// let fixed = session.ask("Item 3 is a discount, drop it").await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct StructuredSession<'c, C: Config, T>
+where
+    T: Structured + for<'de> Deserialize<'de> + JsonSchema,
+{
+    client: &'c Client<C>,
+    model: String,
+    temperature: Option<f32>,
+    generator: Generator<T>,
+    memory: Box<dyn MemoryStrategy + 'c>,
+    messages: Vec<ChatCompletionRequestMessage>,
+}
+
+impl<'c, C: Config, T> StructuredSession<'c, C, T>
+where
+    T: Structured + for<'de> Deserialize<'de> + JsonSchema,
+{
+    pub fn new(client: &'c Client<C>, model: impl Into<String>, generator: Generator<T>) -> Self {
+        let model = model.into();
+        let instruction =
+            ChatCompletionRequestSystemMessage::from(generator.build_instruction_text()).into();
+        Self {
+            client,
+            memory: Box::new(
+                SlidingWindow::new(DEFAULT_SESSION_CONTEXT_WINDOW).tokenizer_model(&model),
+            ),
+            model,
+            temperature: None,
+            generator,
+            messages: vec![instruction],
+        }
+    }
+
+    /// Sampling temperature of every request, the model default otherwise.
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    /// Tokens of history sent with every request, [DEFAULT_SESSION_CONTEXT_WINDOW] by default.
+    /// Keep it below the context window of the model, by the length of the answers.
+    pub fn with_context_window(mut self, tokens: usize) -> Self {
+        self.memory = Box::new(SlidingWindow::new(tokens).tokenizer_model(&self.model));
+        self
+    }
+
+    /// Compress the history with `memory` instead of dropping the oldest turns, see
+    /// [crate::memory].
+    pub fn with_memory(mut self, memory: impl MemoryStrategy + 'c) -> Self {
+        self.memory = Box::new(memory);
+        self
+    }
+
+    /// The generator parsing the answers.
+    pub fn generator(&self) -> &Generator<T> {
+        &self.generator
+    }
+
+    /// The conversation so far, starting with the instruction.
+    pub fn messages(&self) -> &[ChatCompletionRequestMessage] {
+        &self.messages
+    }
+
+    /// Forget every turn, keeping the instruction.
+    pub fn clear(&mut self) {
+        self.messages
+            .retain(|message| matches!(message, ChatCompletionRequestMessage::System(_)));
+    }
+
+    /// Send `input` after the conversation so far and parse the answer. The turn is added to
+    /// the conversation only when the answer parses, so that a failed turn can be asked again.
+    ///
+    /// An answer that can't be parsed, or a refusal, is returned as
+    /// [OpenAIError::StructuredOutput].
+    pub async fn ask(&mut self, input: impl Into<String>) -> Result<Response<T>, OpenAIError> {
+        let mut messages = self.messages.clone();
+        messages.push(ChatCompletionRequestUserMessage::from(input.into()).into());
+        self.memory.compact(&mut messages).await?;
+
+        let response = self.client.chat().create(self.request(&messages)).await?;
+        let parsed = self
+            .generator
+            .parse_completion(&response)
+            .map_err(|e| OpenAIError::StructuredOutput(Box::new(e)))?;
+
+        let answer = response
+            .choices
+            .into_iter()
+            .next()
+            .and_then(|choice| choice.message.content)
+            .unwrap_or_default();
+        messages.push(ChatCompletionRequestAssistantMessage::from(answer).into());
+        self.messages = messages;
+
+        Ok(parsed)
+    }
+
+    fn request(&self, messages: &[ChatCompletionRequestMessage]) -> CreateChatCompletionRequest {
+        CreateChatCompletionRequest {
+            model: self.model.clone(),
+            messages: messages.to_vec(),
+            temperature: self.temperature,
+            ..Default::default()
+        }
+    }
+}
@@ -0,0 +1,59 @@
+//! Fixups making a schemars schema acceptable to the strict mode of structured outputs.
+use serde_json::Value;
+
+/// Formats strict mode accepts, other formats (such as schemars' `uint64`) are dropped.
+const SUPPORTED_FORMATS: &[&str] = &[
+    "date-time",
+    "time",
+    "date",
+    "duration",
+    "email",
+    "hostname",
+    "ipv4",
+    "ipv6",
+    "uuid",
+];
+
+/// Close every object with `additionalProperties: false` and require all of its properties,
+/// optional fields staying nullable, and drop the keywords strict mode rejects.
+pub(super) fn make_strict(schema: &mut Value) {
+    let Value::Object(map) = schema else {
+        return;
+    };
+
+    map.remove("$schema");
+    map.remove("default");
+    let unsupported_format = map
+        .get("format")
+        .and_then(Value::as_str)
+        .is_some_and(|format| !SUPPORTED_FORMATS.contains(&format));
+    if unsupported_format {
+        map.remove("format");
+    }
+
+    if let Some(Value::Object(properties)) = map.get_mut("properties") {
+        let required = properties.keys().cloned().map(Value::String).collect();
+        properties.values_mut().for_each(make_strict);
+        map.insert("required".to_string(), Value::Array(required));
+        map.insert("additionalProperties".to_string(), Value::Bool(false));
+    }
+
+    for key in ["definitions", "$defs"] {
+        if let Some(Value::Object(definitions)) = map.get_mut(key) {
+            definitions.values_mut().for_each(make_strict);
+        }
+    }
+    for key in ["items", "additionalProperties", "not"] {
+        if let Some(subschema) = map.get_mut(key) {
+            match subschema {
+                Value::Array(items) => items.iter_mut().for_each(make_strict),
+                subschema => make_strict(subschema),
+            }
+        }
+    }
+    for key in ["anyOf", "oneOf", "allOf"] {
+        if let Some(Value::Array(variants)) = map.get_mut(key) {
+            variants.iter_mut().for_each(make_strict);
+        }
+    }
+}
@@ -0,0 +1,247 @@
+//! Rendering and parsing of structured output written as a table, in CSV or as a markdown
+//! table.
+//!
+//! Rows are converted to JSON objects, so the parsed data goes through the same
+//! deserialization and validation as the other formats. Header names are matched to fields
+//! ignoring case, spaces and underscores, and since cells have no types, each cell is typed
+//! after its column in the JSON Schema of the output.
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use crate::types::structured::ParseError;
+
+/// Header and cells of `example`, an array of objects or a single object. Nested values
+/// are written as JSON.
+fn cells(example: &Value) -> Option<(Vec<String>, Vec<Vec<String>>)> {
+    let rows: Vec<&Map<String, Value>> = match example {
+        Value::Array(items) => items.iter().filter_map(Value::as_object).collect(),
+        Value::Object(map) => vec![map],
+        _ => return None,
+    };
+    let header: Vec<String> = rows.first()?.keys().cloned().collect();
+
+    let rows = rows
+        .into_iter()
+        .map(|row| {
+            header
+                .iter()
+                .map(|column| match row.get(column) {
+                    None | Some(Value::Null) => String::new(),
+                    Some(Value::String(value)) => value.clone(),
+                    Some(value) => value.to_string(),
+                })
+                .collect()
+        })
+        .collect();
+    Some((header, rows))
+}
+
+/// Render `example` as a CSV table with a header row.
+#[cfg(feature = "csv")]
+pub(crate) fn render_csv(example: &Value) -> Option<String> {
+    let (header, rows) = cells(example)?;
+
+    let mut writer = csv::Writer::from_writer(vec![]);
+    writer.write_record(&header).ok()?;
+    for row in rows {
+        writer.write_record(row).ok()?;
+    }
+    String::from_utf8(writer.into_inner().ok()?).ok()
+}
+
+/// Render `example` as a GitHub-style markdown table.
+pub(crate) fn render_markdown(example: &Value) -> Option<String> {
+    let (header, rows) = cells(example)?;
+
+    let line = |cells: &[String]| {
+        let cells: Vec<_> = cells.iter().map(|cell| cell.replace('|', "\\|")).collect();
+        format!("| {} |\n", cells.join(" | "))
+    };
+    let mut table = line(&header);
+    table.push_str(&format!("|{}\n", " --- |".repeat(header.len())));
+    for row in rows {
+        table.push_str(&line(&row));
+    }
+    Some(table)
+}
+
+/// Parse a CSV `table` into an array of rows, or into a single object when the `schema` is
+/// not an array.
+#[cfg(feature = "csv")]
+pub(crate) fn parse_csv<T: for<'de> Deserialize<'de>>(
+    table: &str,
+    schema: Option<&Value>,
+) -> Result<T, ParseError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(table.as_bytes());
+    let header: Vec<String> = reader
+        .headers()
+        .map_err(|e| ParseError::Extraction(format!("Unable to extract CSV: {}", e)))?
+        .iter()
+        .map(str::to_string)
+        .collect();
+
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record =
+            record.map_err(|e| ParseError::Extraction(format!("Unable to extract CSV: {}", e)))?;
+        rows.push(record.iter().map(str::to_string).collect());
+    }
+
+    to_data(&header, rows, schema)
+}
+
+/// Parse the first markdown table of `response`, the header row followed by a
+/// `| --- |` separator row and the data rows.
+pub(crate) fn parse_markdown<T: for<'de> Deserialize<'de>>(
+    response: &str,
+    schema: Option<&Value>,
+) -> Result<T, ParseError> {
+    let lines: Vec<&str> = response.lines().map(str::trim).collect();
+    let start = lines
+        .windows(2)
+        .position(|pair| pair[0].contains('|') && is_separator(pair[1]))
+        .ok_or_else(|| ParseError::Extraction("No markdown table found".to_string()))?;
+
+    let header = markdown_cells(lines[start]);
+    let rows = lines[start + 2..]
+        .iter()
+        .take_while(|line| line.contains('|'))
+        .map(|line| markdown_cells(line))
+        .collect();
+
+    to_data(&header, rows, schema)
+}
+
+/// Whether `line` is the row between the header and the data, such as `|---|:--:|`.
+fn is_separator(line: &str) -> bool {
+    line.contains('-')
+        && line.contains('|')
+        && line
+            .chars()
+            .all(|c| matches!(c, '|' | '-' | ':' | ' ' | '\t'))
+}
+
+/// Cells of a markdown table row, with the outer pipes and escaped pipes handled.
+fn markdown_cells(line: &str) -> Vec<String> {
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let line = line.strip_suffix('|').unwrap_or(line);
+
+    let mut cells = vec![String::new()];
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&'|') => {
+                cells.last_mut().unwrap().push('|');
+                chars.next();
+            }
+            '|' => cells.push(String::new()),
+            c => cells.last_mut().unwrap().push(c),
+        }
+    }
+    cells.iter().map(|cell| cell.trim().to_string()).collect()
+}
+
+/// Deserialize rows of `cells` under `header`, as an array of rows, or as a single object
+/// when the `schema` is not an array.
+fn to_data<T: for<'de> Deserialize<'de>>(
+    header: &[String],
+    rows: Vec<Vec<String>>,
+    schema: Option<&Value>,
+) -> Result<T, ParseError> {
+    let is_array = schema.map_or(true, |schema| {
+        schema.get("type").and_then(Value::as_str) == Some("array")
+    });
+    let columns = schema
+        .map(|schema| if is_array { &schema["items"] } else { schema })
+        .and_then(|row| row.get("properties"))
+        .and_then(Value::as_object);
+    let fields: Vec<String> = header
+        .iter()
+        .map(|column| field_name(column, columns))
+        .collect();
+
+    let rows: Vec<Value> = rows
+        .into_iter()
+        .map(|row| {
+            let row: Map<String, Value> = fields
+                .iter()
+                .zip(row)
+                .map(|(field, cell)| {
+                    let schema = columns.and_then(|columns| columns.get(field));
+                    (field.clone(), typed_cell(&cell, schema))
+                })
+                .collect();
+            Value::Object(row)
+        })
+        .collect();
+
+    let data = if is_array {
+        Value::Array(rows)
+    } else {
+        rows.into_iter()
+            .next()
+            .ok_or_else(|| ParseError::Extraction("Table has no rows".to_string()))?
+    };
+    serde_json::from_value(data)
+        .map_err(|e| ParseError::Extraction(format!("Unable to extract table data: {}", e)))
+}
+
+/// The field a header names: a property of the schema whose name is the same ignoring
+/// case and separators (`Postal Code`, `postalCode` and `postal_code` all match
+/// `postal_code`), otherwise the header in snake case.
+fn field_name(column: &str, columns: Option<&Map<String, Value>>) -> String {
+    let normalize = |name: &str| -> String {
+        name.chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+            .collect()
+    };
+
+    let key = normalize(column);
+    if let Some(field) = columns.and_then(|columns| {
+        columns
+            .keys()
+            .find(|field| *field == column)
+            .or_else(|| columns.keys().find(|field| normalize(field) == key))
+    }) {
+        return field.clone();
+    }
+
+    let mut snake = String::new();
+    let mut previous_lower = false;
+    for c in column.chars() {
+        if c.is_alphanumeric() {
+            if c.is_uppercase() && previous_lower {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+            previous_lower = c.is_lowercase() || c.is_numeric();
+        } else {
+            if !snake.is_empty() && !snake.ends_with('_') {
+                snake.push('_');
+            }
+            previous_lower = false;
+        }
+    }
+    snake.trim_end_matches('_').to_string()
+}
+
+/// The value of a cell, kept as a string when its column is a string, otherwise parsed as
+/// JSON when possible.
+fn typed_cell(cell: &str, schema: Option<&Value>) -> Value {
+    let types: Vec<&str> = match schema.and_then(|schema| schema.get("type")) {
+        Some(Value::String(kind)) => vec![kind.as_str()],
+        Some(Value::Array(kinds)) => kinds.iter().filter_map(Value::as_str).collect(),
+        _ => vec![],
+    };
+
+    if cell.is_empty() && (types.is_empty() || types.contains(&"null")) {
+        return Value::Null;
+    }
+    if types.contains(&"string") {
+        return Value::String(cell.to_string());
+    }
+    serde_json::from_str(cell).unwrap_or_else(|_| Value::String(cell.to_string()))
+}
@@ -0,0 +1,180 @@
+//! Reassembly of tool calls streamed in fragments, parsed as they finish.
+use std::collections::BTreeMap;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use super::Generator;
+use crate::types::{
+    structured::{ParseError, Response, Structured},
+    ChatCompletionMessageToolCall, ChatCompletionMessageToolCallChunk, ChatCompletionToolType,
+    CreateChatCompletionStreamResponse, FunctionCall,
+};
+
+/// A tool call received in full, with its arguments parsed by
+/// [Generator::parse_tool_call].
+#[derive(Debug)]
+pub struct FinishedToolCall<T>
+where
+    T: Structured + for<'de> Deserialize<'de>,
+{
+    /// Index of the call in the message
+    pub index: u32,
+    /// The call, with its whole arguments
+    pub call: ChatCompletionMessageToolCall,
+    /// The parsed arguments
+    pub parsed: Result<Response<T>, ParseError>,
+}
+
+/// Collects the [ChatCompletionMessageToolCallChunk]s of a streamed message by index, and
+/// parses every call once it is finished.
+///
+/// Calls are streamed one after the other, so a call is finished when a chunk of a later
+/// call arrives, when the choice has a finish reason, or at [ToolCallAccumulator::finish].
+///
+/// ```
+/// # #[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+/// # struct Weather { city: String }
+/// use async_openai::{
+///     structured::{Generator, ToolCallAccumulator},
+///     types::{ChatCompletionMessageToolCallChunk, FunctionCallStream},
+/// };
+///
+/// let generator = Generator::json(Weather::default());
+/// let mut calls = ToolCallAccumulator::new(&generator);
+/// for (id, name, arguments) in [
+///     (Some("call_1"), Some("Weather"), r#"{"city""#),
+///     (None, None, r#": "Paris"}"#),
+/// ] {
+///     calls.push_chunk(&ChatCompletionMessageToolCallChunk {
+///         index: 0,
+///         id: id.map(String::from),
+///         r#type: None,
+///         function: Some(FunctionCallStream {
+///             name: name.map(String::from),
+///             arguments: Some(arguments.to_string()),
+///         }),
+///     });
+/// }
+///
+/// let finished = calls.finish();
+/// assert_eq!(finished[0].parsed.as_ref().unwrap().data.city, "Paris");
+/// ```
+pub struct ToolCallAccumulator<'a, T>
+where
+    T: Structured + for<'de> Deserialize<'de> + JsonSchema,
+{
+    generator: &'a Generator<T>,
+    pending: BTreeMap<u32, ChatCompletionMessageToolCall>,
+    /// Index of the last finished call
+    finished: Option<u32>,
+}
+
+impl<'a, T> ToolCallAccumulator<'a, T>
+where
+    T: Structured + for<'de> Deserialize<'de> + JsonSchema,
+{
+    /// Parse the calls with `generator`.
+    pub fn new(generator: &'a Generator<T>) -> Self {
+        Self {
+            generator,
+            pending: BTreeMap::new(),
+            finished: None,
+        }
+    }
+
+    /// Add a fragment of a call, returning the calls it finishes.
+    ///
+    /// Fragments of calls that were already returned are ignored.
+    pub fn push_chunk(
+        &mut self,
+        chunk: &ChatCompletionMessageToolCallChunk,
+    ) -> Vec<FinishedToolCall<T>> {
+        if self
+            .finished
+            .is_some_and(|finished| chunk.index <= finished)
+        {
+            return Vec::new();
+        }
+        let call =
+            self.pending
+                .entry(chunk.index)
+                .or_insert_with(|| ChatCompletionMessageToolCall {
+                    id: String::new(),
+                    r#type: ChatCompletionToolType::Function,
+                    function: FunctionCall {
+                        name: String::new(),
+                        arguments: String::new(),
+                    },
+                });
+        if let Some(id) = &chunk.id {
+            call.id.push_str(id);
+        }
+        if let Some(function) = &chunk.function {
+            if let Some(name) = &function.name {
+                call.function.name.push_str(name);
+            }
+            if let Some(arguments) = &function.arguments {
+                call.function.arguments.push_str(arguments);
+            }
+        }
+
+        self.finish_before(chunk.index)
+    }
+
+    /// Add the tool call fragments of the first choice of a streamed response, returning the
+    /// calls it finishes. All calls are finished once the choice has a finish reason.
+    pub fn push(
+        &mut self,
+        response: &CreateChatCompletionStreamResponse,
+    ) -> Vec<FinishedToolCall<T>> {
+        let Some(choice) = response.choices.iter().find(|choice| choice.index == 0) else {
+            return Vec::new();
+        };
+        let mut finished: Vec<_> = choice
+            .delta
+            .tool_calls
+            .iter()
+            .flatten()
+            .flat_map(|chunk| self.push_chunk(chunk))
+            .collect();
+        if choice.finish_reason.is_some() {
+            finished.extend(self.finish());
+        }
+        finished
+    }
+
+    /// Finish every pending call, e.g. at the end of the stream.
+    pub fn finish(&mut self) -> Vec<FinishedToolCall<T>> {
+        let pending = std::mem::take(&mut self.pending);
+        self.parse(pending)
+    }
+
+    /// Whether calls were started and not finished yet.
+    pub fn is_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    fn finish_before(&mut self, index: u32) -> Vec<FinishedToolCall<T>> {
+        let later = self.pending.split_off(&index);
+        let finished = std::mem::replace(&mut self.pending, later);
+        self.parse(finished)
+    }
+
+    fn parse(
+        &mut self,
+        calls: BTreeMap<u32, ChatCompletionMessageToolCall>,
+    ) -> Vec<FinishedToolCall<T>> {
+        calls
+            .into_iter()
+            .map(|(index, call)| {
+                self.finished = Some(index);
+                FinishedToolCall {
+                    index,
+                    parsed: self.generator.parse_tool_call(&call),
+                    call,
+                }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,245 @@
+//! Rendering of JSON Schemas as TypeScript type declarations.
+use serde_json::{Map, Value};
+
+/// Declare the type of `schema`, named after its `title` (`Output` without one), preceded by
+/// the types of its `definitions`.
+pub(crate) fn render(schema: &Value) -> String {
+    let mut declarations = Vec::new();
+
+    let definitions = schema
+        .get("definitions")
+        .or_else(|| schema.get("$defs"))
+        .and_then(Value::as_object);
+    for (name, definition) in definitions.into_iter().flatten() {
+        declarations.push(declaration(&type_name(name), definition));
+    }
+
+    let name = schema
+        .get("title")
+        .and_then(Value::as_str)
+        .map_or_else(|| "Output".to_string(), type_name);
+    declarations.push(declaration(&name, schema));
+
+    declarations.join("\n\n")
+}
+
+fn declaration(name: &str, schema: &Value) -> String {
+    let mut declaration = String::new();
+    if let Some(description) = schema.get("description").and_then(Value::as_str) {
+        for line in description.lines() {
+            declaration.push_str(&format!("// {}\n", line));
+        }
+    }
+    declaration.push_str(&format!("type {} = {};", name, typescript(schema, 0)));
+    declaration
+}
+
+/// TypeScript type of `schema`, with nested objects indented by `depth` levels.
+fn typescript(schema: &Value, depth: usize) -> String {
+    let Some(schema) = schema.as_object() else {
+        // `true` and `false` schemas
+        return "any".to_string();
+    };
+
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        return type_name(reference.rsplit('/').next().unwrap_or(reference));
+    }
+    if let Some(value) = schema.get("const") {
+        return value.to_string();
+    }
+    if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+        return union(values.iter().map(Value::to_string).collect());
+    }
+    if let Some(variants) = schema
+        .get("anyOf")
+        .or_else(|| schema.get("oneOf"))
+        .and_then(Value::as_array)
+    {
+        return union(
+            variants
+                .iter()
+                .map(|variant| typescript(variant, depth))
+                .collect(),
+        );
+    }
+    if let Some(parts) = schema.get("allOf").and_then(Value::as_array) {
+        let parts: Vec<_> = parts.iter().map(|part| typescript(part, depth)).collect();
+        return if parts.len() == 1 {
+            parts.into_iter().next().unwrap_or_default()
+        } else {
+            parts.join(" & ")
+        };
+    }
+
+    match schema.get("type") {
+        Some(Value::String(kind)) => typed(kind, schema, depth),
+        Some(Value::Array(kinds)) => union(
+            kinds
+                .iter()
+                .filter_map(Value::as_str)
+                .map(|kind| typed(kind, schema, depth))
+                .collect(),
+        ),
+        _ if schema.contains_key("properties") => typed("object", schema, depth),
+        _ => "any".to_string(),
+    }
+}
+
+fn typed(kind: &str, schema: &Map<String, Value>, depth: usize) -> String {
+    match kind {
+        "string" => "string".to_string(),
+        "integer" | "number" => "number".to_string(),
+        "boolean" => "boolean".to_string(),
+        "null" => "null".to_string(),
+        "array" => match schema.get("items") {
+            // Tuples
+            Some(Value::Array(items)) => format!(
+                "[{}]",
+                items
+                    .iter()
+                    .map(|item| typescript(item, depth))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Some(items) => {
+                let item = typescript(items, depth);
+                if item.contains(" | ") || item.contains(" & ") {
+                    format!("({})[]", item)
+                } else {
+                    format!("{}[]", item)
+                }
+            }
+            None => "any[]".to_string(),
+        },
+        "object" => object(schema, depth),
+        _ => "any".to_string(),
+    }
+}
+
+fn object(schema: &Map<String, Value>, depth: usize) -> String {
+    let properties = schema.get("properties").and_then(Value::as_object);
+    let Some(properties) = properties.filter(|properties| !properties.is_empty()) else {
+        return match schema.get("additionalProperties") {
+            Some(Value::Object(_)) => format!(
+                "Record<string, {}>",
+                typescript(&schema["additionalProperties"], depth)
+            ),
+            _ => "Record<string, any>".to_string(),
+        };
+    };
+
+    // Without a `required` list, e.g. in schemas inferred from an example, every field is
+    // expected
+    let required = schema.get("required").and_then(Value::as_array);
+    let is_required = |field: &str| {
+        required.map_or(true, |required| {
+            required.iter().any(|name| name.as_str() == Some(field))
+        })
+    };
+
+    let indent = "  ".repeat(depth + 1);
+    let mut members = String::from("{\n");
+    for (field, property) in properties {
+        let mut notes = vec![];
+        if let Some(description) = property.get("description").and_then(Value::as_str) {
+            notes.push(description.replace('\n', " "));
+        }
+        if let Some(format) = property.get("format").and_then(Value::as_str) {
+            notes.push(format!("format: {}", format));
+        }
+        if !notes.is_empty() {
+            members.push_str(&format!("{}// {}\n", indent, notes.join(", ")));
+        }
+
+        let optional = if is_required(field) { "" } else { "?" };
+        members.push_str(&format!(
+            "{}{}{}: {};\n",
+            indent,
+            property_name(field),
+            optional,
+            typescript(property, depth + 1)
+        ));
+    }
+    members.push_str(&"  ".repeat(depth));
+    members.push('}');
+    members
+}
+
+fn union(mut variants: Vec<String>) -> String {
+    variants.dedup();
+    match variants.len() {
+        0 => "never".to_string(),
+        _ => variants.join(" | "),
+    }
+}
+
+/// The field name, quoted unless it is a valid identifier.
+fn property_name(field: &str) -> String {
+    let mut chars = field.chars();
+    let identifier = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+    if identifier {
+        field.to_string()
+    } else {
+        Value::String(field.to_string()).to_string()
+    }
+}
+
+/// A type name from a schema title or definition name such as `Option_for_Author`.
+fn type_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '_')
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::render;
+
+    #[test]
+    fn renders_declarations() {
+        let schema = json!({
+            "title": "Ticket",
+            "type": "object",
+            "required": ["title", "tags"],
+            // Sorted, so that the declaration doesn't depend on preserve_order of serde_json
+            "properties": {
+                "author": { "$ref": "#/definitions/Author" },
+                "due": { "type": "string", "format": "date" },
+                "extra-data": { "type": "object", "additionalProperties": { "type": "integer" } },
+                "priority": { "enum": ["low", "high"] },
+                "tags": { "type": "array", "items": { "type": ["string", "null"] } },
+                "title": { "type": "string", "description": "One line summary" }
+            },
+            "definitions": {
+                "Author": {
+                    "type": "object",
+                    "required": ["name"],
+                    "properties": { "name": { "type": "string" } }
+                }
+            }
+        });
+
+        assert_eq!(
+            render(&schema),
+            r#"type Author = {
+  name: string;
+};
+
+type Ticket = {
+  author?: Author;
+  // format: date
+  due?: string;
+  "extra-data"?: Record<string, number>;
+  priority?: "low" | "high";
+  tags: (string | null)[];
+  // One line summary
+  title: string;
+};"#
+        );
+    }
+}
@@ -0,0 +1,252 @@
+//! Validation of parsed answers against the JSON Schema of the output.
+//!
+//! Answers are validated as JSON values, before they are converted to the output type, so
+//! that constraints the type can't express (ranges, lengths, patterns and formats from
+//! `#[validate(...)]` or `#[schemars(...)]` attributes) and properties the type would
+//! ignore are reported.
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use jsonschema::{paths::LocationSegment, Validator};
+use serde_json::{Number, Value};
+
+use crate::types::structured::{ValidationIssue, ValidationOptions};
+
+/// Most validators kept in [compiled]. Generators own their validator, so once the cache is
+/// full, e.g. by the runtime schemas of [DynGenerator](super::DynGenerator)s, new schemas are
+/// compiled for their generator only
+const MAX_COMPILED: usize = 128;
+
+/// Validators compiled so far, by the text of the schema they were compiled from. The schema
+/// of a type is the same for every generator of it, so each type is compiled once per process
+fn compiled() -> &'static Mutex<HashMap<String, Arc<Validator>>> {
+    static COMPILED: OnceLock<Mutex<HashMap<String, Arc<Validator>>>> = OnceLock::new();
+    COMPILED.get_or_init(Default::default)
+}
+
+/// Compile `schema` with `options` applied, or reuse the validator compiled for the same
+/// schema and options.
+///
+/// References inside the schema, such as the `#/definitions/..` and `#/$defs/..` of
+/// schemars, are resolved. References to other documents fail, as they are never fetched.
+pub(super) fn compile(
+    schema: &Value,
+    options: &ValidationOptions,
+) -> Result<Arc<Validator>, String> {
+    let mut schema = schema.clone();
+    if !options.allow_additional_properties {
+        deny_additional_properties(&mut schema);
+    }
+
+    let key = schema.to_string();
+    if let Some(validator) = compiled().lock().unwrap().get(&key) {
+        return Ok(validator.clone());
+    }
+    let validator = jsonschema::options()
+        .should_validate_formats(true)
+        .build(&schema)
+        .map(Arc::new)
+        .map_err(|e| format!("Invalid JSON Schema: {}", e))?;
+
+    // Another thread may have compiled the same schema meanwhile, share the first validator
+    let mut cache = compiled().lock().unwrap();
+    if cache.len() >= MAX_COMPILED && !cache.contains_key(&key) {
+        return Ok(validator);
+    }
+    Ok(cache.entry(key).or_insert(validator).clone())
+}
+
+/// Issues of `value`, at most [ValidationOptions::max_errors] of them, each message prefixed
+/// with the path of the invalid value.
+pub(super) fn issues(
+    validator: &Validator,
+    value: &Value,
+    options: &ValidationOptions,
+) -> Vec<ValidationIssue> {
+    validator
+        .iter_errors(value)
+        .take(options.max_errors.unwrap_or(usize::MAX))
+        .map(|e| {
+            let path = e.instance_path.to_string();
+            let keyword = e
+                .schema_path
+                .into_iter()
+                .rev()
+                // The schema path ends with the keyword, possibly followed by an index
+                .find_map(|segment| match segment {
+                    LocationSegment::Property(keyword) => Some(keyword.to_string()),
+                    LocationSegment::Index(_) => None,
+                })
+                .unwrap_or_default();
+            let message = if path.is_empty() {
+                e.to_string()
+            } else {
+                format!("{}: {}", path, e)
+            };
+            ValidationIssue {
+                severity: options.severity_of(&keyword),
+                path,
+                keyword,
+                message,
+            }
+        })
+        .collect()
+}
+
+/// Set `additionalProperties` to `false` in every object schema which doesn't say otherwise.
+fn deny_additional_properties(schema: &mut Value) {
+    let Some(object) = schema.as_object_mut() else {
+        return;
+    };
+
+    if object.contains_key("properties") && !object.contains_key("additionalProperties") {
+        object.insert("additionalProperties".to_string(), Value::Bool(false));
+    }
+
+    for key in ["properties", "definitions", "$defs"] {
+        if let Some(Value::Object(schemas)) = object.get_mut(key) {
+            schemas.values_mut().for_each(deny_additional_properties);
+        }
+    }
+    for key in ["items", "additionalProperties", "not"] {
+        if let Some(schema) = object.get_mut(key) {
+            match schema {
+                Value::Array(schemas) => schemas.iter_mut().for_each(deny_additional_properties),
+                schema => deny_additional_properties(schema),
+            }
+        }
+    }
+    for key in ["anyOf", "oneOf", "allOf"] {
+        if let Some(Value::Array(schemas)) = object.get_mut(key) {
+            schemas.iter_mut().for_each(deny_additional_properties);
+        }
+    }
+}
+
+/// Replace strings holding a number, such as `"42"`, with the number where `schema` expects
+/// a number and not a string.
+pub(super) fn coerce_numeric_strings(value: &mut Value, schema: &Value) {
+    match value {
+        Value::String(text) if !accepts(schema, "string") => {
+            let integer = accepts(schema, "integer");
+            if integer || accepts(schema, "number") {
+                if let Ok(number) = text.trim().parse::<Number>() {
+                    if !integer || accepts(schema, "number") || !number.is_f64() {
+                        *value = Value::Number(number);
+                    }
+                }
+            }
+        }
+        Value::Object(map) => {
+            let properties = schema.get("properties").and_then(Value::as_object);
+            let additional = schema.get("additionalProperties").filter(|s| s.is_object());
+            for (key, value) in map.iter_mut() {
+                if let Some(schema) = properties.and_then(|p| p.get(key)).or(additional) {
+                    coerce_numeric_strings(value, schema);
+                }
+            }
+        }
+        Value::Array(items) => match schema.get("items") {
+            Some(Value::Array(schemas)) => items
+                .iter_mut()
+                .zip(schemas)
+                .for_each(|(item, schema)| coerce_numeric_strings(item, schema)),
+            Some(schema) => items
+                .iter_mut()
+                .for_each(|item| coerce_numeric_strings(item, schema)),
+            None => {}
+        },
+        _ => {}
+    }
+
+    for key in ["anyOf", "oneOf", "allOf"] {
+        if let Some(Value::Array(schemas)) = schema.get(key) {
+            for schema in schemas {
+                coerce_numeric_strings(value, schema);
+            }
+        }
+    }
+}
+
+/// Whether the `type` of `schema` includes `kind`.
+fn accepts(schema: &Value, kind: &str) -> bool {
+    match schema.get("type") {
+        Some(Value::String(kinds)) => kinds == kind,
+        Some(Value::Array(kinds)) => kinds.iter().any(|k| k == kind),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn coerces_numbers_by_schema() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "age": {"type": "integer"},
+                "score": {"type": ["number", "null"]},
+                "zip": {"type": "string"},
+                "tags": {"type": "array", "items": {"type": "integer"}}
+            }
+        });
+        let mut value = json!({"age": " 42", "score": "0.5", "zip": "01234", "tags": ["1", "x"]});
+
+        coerce_numeric_strings(&mut value, &schema);
+
+        assert_eq!(
+            value,
+            json!({"age": 42, "score": 0.5, "zip": "01234", "tags": [1, "x"]})
+        );
+    }
+
+    #[test]
+    fn integers_are_not_coerced_from_decimals() {
+        let mut value = json!("1.5");
+        coerce_numeric_strings(&mut value, &json!({"type": "integer"}));
+        assert_eq!(value, json!("1.5"));
+    }
+
+    #[test]
+    fn additional_properties_are_denied_in_nested_objects() {
+        let mut schema = json!({
+            "type": "object",
+            "properties": {
+                "inner": {"type": "object", "properties": {"a": {"type": "string"}}},
+                "map": {"type": "object", "additionalProperties": {"type": "string"}}
+            }
+        });
+
+        deny_additional_properties(&mut schema);
+
+        assert_eq!(schema["additionalProperties"], json!(false));
+        assert_eq!(
+            schema["properties"]["inner"]["additionalProperties"],
+            json!(false)
+        );
+        assert_eq!(
+            schema["properties"]["map"]["additionalProperties"],
+            json!({"type": "string"})
+        );
+    }
+
+    #[test]
+    fn compiled_validators_are_shared_and_bounded() {
+        let options = ValidationOptions::default();
+        let schema = |n: usize| json!({"type": "object", "title": format!("cache-{n}")});
+
+        let first = compile(&schema(0), &options).unwrap();
+        assert!(Arc::ptr_eq(&first, &compile(&schema(0), &options).unwrap()));
+
+        for n in 1..=MAX_COMPILED {
+            compile(&schema(n), &options).unwrap();
+        }
+        assert!(compiled().lock().unwrap().len() <= MAX_COMPILED);
+    }
+}
@@ -0,0 +1,87 @@
+//! Checks registered for a field path or for the whole output, run after the response is
+//! converted to the output type.
+use std::sync::Arc;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::types::structured::{ValidationIssue, ValidationOptions};
+
+/// Check of the values at a field path, see [crate::structured::Generator::validate_field].
+pub(super) type FieldCheck = Arc<dyn Fn(&Value) -> Result<(), String> + Send + Sync>;
+
+/// Check of the output, see [crate::structured::Generator::validate_struct].
+pub(super) type OutputCheck<T> = Arc<dyn Fn(&T) -> Result<(), String> + Send + Sync>;
+
+/// Keyword of the issues of field checks, for [ValidationOptions::severity].
+pub(crate) const FIELD_KEYWORD: &str = "validateField";
+
+/// Keyword of the issues of output checks, for [ValidationOptions::severity].
+pub(crate) const OUTPUT_KEYWORD: &str = "validateStruct";
+
+/// An issue for every failed check of `data`. Field checks run on every value at their path,
+/// and paths that match no value are skipped.
+pub(super) fn issues<T: Serialize>(
+    data: &T,
+    fields: &[(String, FieldCheck)],
+    checks: &[OutputCheck<T>],
+    options: &ValidationOptions,
+) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+    let issue = |path: String, keyword: &str, message: String| ValidationIssue {
+        message: if path.is_empty() {
+            message
+        } else {
+            format!("{path}: {message}")
+        },
+        path,
+        keyword: keyword.to_string(),
+        severity: options.severity_of(keyword),
+    };
+
+    if !fields.is_empty() {
+        let value = serde_json::to_value(data).unwrap_or_default();
+        for (field, check) in fields {
+            for (path, value) in values_at(&value, field) {
+                if let Err(message) = check(value) {
+                    issues.push(issue(path, FIELD_KEYWORD, message));
+                }
+            }
+        }
+    }
+    for check in checks {
+        if let Err(message) = check(data) {
+            issues.push(issue(String::new(), OUTPUT_KEYWORD, message));
+        }
+    }
+
+    issues
+}
+
+/// The values at the description `path` of `value`, such as `lines[].total`, with their JSON
+/// pointer.
+pub(super) fn values_at<'a>(value: &'a Value, path: &str) -> Vec<(String, &'a Value)> {
+    let mut found = Vec::new();
+    find(value, path, String::new(), &mut found);
+    found
+}
+
+fn find<'a>(value: &'a Value, path: &str, pointer: String, found: &mut Vec<(String, &'a Value)>) {
+    if path.is_empty() {
+        found.push((pointer, value));
+        return;
+    }
+    if let Some(rest) = path.strip_prefix("[]") {
+        let rest = rest.strip_prefix('.').unwrap_or(rest);
+        for (index, item) in value.as_array().into_iter().flatten().enumerate() {
+            find(item, rest, format!("{pointer}/{index}"), found);
+        }
+        return;
+    }
+    let end = path.find(['.', '[']).unwrap_or(path.len());
+    let (name, rest) = path.split_at(end);
+    let rest = rest.strip_prefix('.').unwrap_or(rest);
+    if let Some(value) = value.get(name) {
+        find(value, rest, format!("{pointer}/{name}"), found);
+    }
+}
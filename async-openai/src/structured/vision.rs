@@ -0,0 +1,51 @@
+//! Structured extraction from images, such as receipts or forms.
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use super::Generator;
+use crate::{
+    config::Config,
+    error::OpenAIError,
+    types::{
+        structured::{Response, Structured},
+        ChatCompletionRequestMessageContentPartImage, ChatCompletionRequestMessageContentPartText,
+        ChatCompletionRequestUserMessage, ChatCompletionRequestUserMessageContent,
+        ChatCompletionRequestUserMessageContentPart, CreateChatCompletionRequest, ImageUrl,
+    },
+    Client,
+};
+
+impl<T> Generator<T>
+where
+    T: Structured + for<'de> Deserialize<'de> + JsonSchema,
+{
+    /// Ask a vision capable `model` to extract `T` from `image`, a URL or a base64
+    /// `data:` URL, following `prompt` and the instruction of this generator.
+    ///
+    /// Errors are those of [crate::Chat::create_structured].
+    pub async fn generate_from_image<C: Config>(
+        &self,
+        client: &Client<C>,
+        model: impl Into<String>,
+        image: impl Into<ImageUrl>,
+        prompt: &str,
+    ) -> Result<Response<T>, OpenAIError> {
+        let content: Vec<ChatCompletionRequestUserMessageContentPart> = vec![
+            ChatCompletionRequestMessageContentPartText::from(prompt).into(),
+            ChatCompletionRequestMessageContentPartImage {
+                image_url: image.into(),
+            }
+            .into(),
+        ];
+        let request = CreateChatCompletionRequest {
+            model: model.into(),
+            messages: vec![ChatCompletionRequestUserMessage::from(
+                ChatCompletionRequestUserMessageContent::from(content),
+            )
+            .into()],
+            ..Default::default()
+        };
+
+        client.chat().create_structured(request, self).await
+    }
+}
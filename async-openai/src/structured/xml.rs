@@ -0,0 +1,90 @@
+//! Rewriting of XML answers into the shape `quick_xml::de` reads: attributes become child
+//! elements, and namespaces are removed if the mapping says so.
+use quick_xml::{
+    events::{BytesEnd, BytesStart, BytesText, Event},
+    name::QName,
+    Reader, Writer,
+};
+
+use crate::types::structured::XmlMapping;
+
+/// `xml` with every attribute written as the first child elements of its element, so that
+/// fields are read the same whether the model wrote them as attributes or as elements, and
+/// without namespace prefixes and declarations when [XmlMapping::strip_namespaces] is set.
+///
+/// `None` when `xml` isn't well-formed.
+pub(super) fn normalize(xml: &str, mapping: &XmlMapping) -> Option<String> {
+    let mut reader = Reader::from_str(xml);
+    let mut writer = Writer::new(Vec::new());
+    let strip = mapping.strip_namespaces;
+
+    loop {
+        match reader.read_event().ok()? {
+            Event::Eof => break,
+            Event::Start(start) => write_start(&mut writer, &start, strip, false)?,
+            Event::Empty(start) => write_start(&mut writer, &start, strip, true)?,
+            Event::End(end) => {
+                let name = name(end.name(), strip)?;
+                writer.write_event(Event::End(BytesEnd::new(name))).ok()?;
+            }
+            event => writer.write_event(event).ok()?,
+        }
+    }
+
+    String::from_utf8(writer.into_inner()).ok()
+}
+
+/// Write the opening tag of `start` and its attributes as elements, and the closing tag when
+/// the element is `empty`.
+fn write_start(
+    writer: &mut Writer<Vec<u8>>,
+    start: &BytesStart,
+    strip: bool,
+    empty: bool,
+) -> Option<()> {
+    let element = name(start.name(), strip)?;
+    let mut tag = BytesStart::new(element.clone());
+    let mut attributes = Vec::new();
+    for attribute in start.attributes() {
+        let attribute = attribute.ok()?;
+        let key = attribute.key;
+        let declaration =
+            key.as_ref() == b"xmlns" || key.prefix().is_some_and(|p| p.as_ref() == b"xmlns");
+        if declaration {
+            // Declarations are not data, they stay attributes unless removed
+            if !strip {
+                tag.push_attribute(attribute);
+            }
+            continue;
+        }
+        let value = attribute.unescape_value().ok()?.into_owned();
+        attributes.push((name(key, strip)?, value));
+    }
+
+    writer.write_event(Event::Start(tag)).ok()?;
+    for (key, value) in attributes {
+        writer
+            .write_event(Event::Start(BytesStart::new(key.clone())))
+            .ok()?;
+        writer
+            .write_event(Event::Text(BytesText::new(&value)))
+            .ok()?;
+        writer.write_event(Event::End(BytesEnd::new(key))).ok()?;
+    }
+    if empty {
+        writer
+            .write_event(Event::End(BytesEnd::new(element)))
+            .ok()?;
+    }
+    Some(())
+}
+
+/// `name`, without its namespace prefix when `strip` is set.
+fn name(name: QName, strip: bool) -> Option<String> {
+    let bytes = if strip {
+        name.local_name().into_inner()
+    } else {
+        name.into_inner()
+    };
+    String::from_utf8(bytes.to_vec()).ok()
+}
@@ -0,0 +1,93 @@
+//! YAML answers spread over several `---` separated documents, or written as the front matter
+//! of a markdown answer.
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use super::{fenced, yaml_regex};
+use crate::types::structured::{ExtractionMethod, ParseError};
+
+/// The YAML of `response`: the whole response when it starts with a document separator, as
+/// with front matter, so that code fences in the markdown below are ignored, else the first
+/// code fence, else the whole response
+pub(super) fn locate(response: &str) -> (ExtractionMethod, &str) {
+    if starts_with_separator(response) {
+        return (ExtractionMethod::WholeResponse, response);
+    }
+    match fenced(yaml_regex(), response) {
+        Some(block) => (ExtractionMethod::CodeFence, block),
+        None => (ExtractionMethod::WholeResponse, response),
+    }
+}
+
+/// The YAML of `response` as `T`, see [read_as]
+pub(super) fn read<T: DeserializeOwned>(response: &str) -> Result<T, ParseError> {
+    read_as::<T, T>(response)
+}
+
+/// The YAML of `response` as a `V` laid out as `T` expects. With several documents, the
+/// first one is read when it is a `T`, such as the front matter of a markdown answer, and
+/// the documents are read as an array otherwise, such as one document per item of a `Vec`
+pub(super) fn read_as<T: DeserializeOwned, V: DeserializeOwned>(
+    response: &str,
+) -> Result<V, ParseError> {
+    let (_, yaml) = locate(response);
+    let documents = documents(yaml);
+    if documents.len() < 2 {
+        return from_str(yaml);
+    }
+    if from_str::<T>(documents[0]).is_ok() {
+        return from_str(documents[0]);
+    }
+
+    let values = documents
+        .iter()
+        .map(|document| from_str::<Value>(document))
+        .collect::<Result<Vec<_>, _>>()?;
+    serde_json::from_value(Value::Array(values))
+        .map_err(|e| ParseError::Extraction(format!("Unable to extract YAML: {}", e)))
+}
+
+/// Every document of the YAML in `response` as a `T`
+pub(super) fn read_all<T: DeserializeOwned>(response: &str) -> Result<Vec<T>, ParseError> {
+    let (_, yaml) = locate(response);
+    documents(yaml).into_iter().map(from_str).collect()
+}
+
+/// The documents of `yaml`, split at the `---` and `...` lines, without the empty ones
+pub(super) fn documents(yaml: &str) -> Vec<&str> {
+    let mut documents = Vec::new();
+    let mut start = 0;
+    let mut offset = 0;
+    for line in yaml.split_inclusive('\n') {
+        if is_separator(line) {
+            documents.push(&yaml[start..offset]);
+            start = offset + line.len();
+        }
+        offset += line.len();
+    }
+    documents.push(&yaml[start..]);
+    documents.retain(|document| {
+        document
+            .lines()
+            .any(|line| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+    });
+    documents
+}
+
+/// Whether the first line of `text` is a document separator
+pub(super) fn starts_with_separator(text: &str) -> bool {
+    is_separator(text.trim_start().lines().next().unwrap_or_default())
+}
+
+/// Whether `line` starts or ends a document, e.g. `---`, `--- # Item` or `...`
+fn is_separator(line: &str) -> bool {
+    ["---", "..."].iter().any(|marker| {
+        line.strip_prefix(marker)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with(char::is_whitespace))
+    })
+}
+
+fn from_str<T: DeserializeOwned>(yaml: &str) -> Result<T, ParseError> {
+    serde_yaml::from_str(yaml)
+        .map_err(|e| ParseError::Extraction(format!("Unable to extract YAML: {}", e)))
+}
@@ -0,0 +1,153 @@
+//! Prompt templates: chat requests with named `{{placeholder}}`s in their messages.
+//!
+//! Rendering checks the values against the placeholders, so a prompt is never sent with a
+//! placeholder left unfilled, and a value that no placeholder uses (usually a typo in
+//! either name) is reported instead of silently ignored.
+//!
+//! ```
+//! use async_openai::{
+//!     template::RequestTemplate,
+//!     types::{ChatCompletionRequestUserMessage, CreateChatCompletionRequestArgs},
+//! };
+//!
+//! let request = CreateChatCompletionRequestArgs::default()
+//!     .model("gpt-4o-mini")
+//!     .messages([ChatCompletionRequestUserMessage::from(
+//!         "Translate {{ text }} into {{language}}.",
+//!     )
+//!     .into()])
+//!     .build()
+//!     .unwrap();
+//! let template = RequestTemplate::new(request);
+//!
+//! let request = template
+//!     .render([("text", "Guten Tag"), ("language", "English")])
+//!     .unwrap();
+//! assert_eq!(
+//!     request.messages[0].text(),
+//!     "Translate Guten Tag into English."
+//! );
+//!
+//! let error = template.render([("text", "Guten Tag")]).unwrap_err();
+//! assert_eq!(error.missing, ["language"]);
+//! ```
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::OnceLock,
+};
+
+use regex::{Captures, Regex};
+
+use crate::{error::OpenAIError, types::CreateChatCompletionRequest};
+
+/// `{{name}}`, with optional spaces inside the braces. Names start with a letter or an
+/// underscore and may contain letters, digits, `_`, `.` and `-`.
+fn placeholder_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r"\{\{\s*([A-Za-z_][A-Za-z0-9_.-]*)\s*\}\}").unwrap())
+}
+
+/// Names of the placeholders of `text`.
+pub fn placeholders(text: &str) -> BTreeSet<String> {
+    placeholder_regex()
+        .captures_iter(text)
+        .map(|captures| captures[1].to_string())
+        .collect()
+}
+
+/// Values of a render did not match the placeholders of the template.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error(
+    "template placeholders without a value: {missing:?}, values without a placeholder: {unused:?}"
+)]
+pub struct TemplateError {
+    /// Placeholders no value was given for
+    pub missing: Vec<String>,
+    /// Values no placeholder uses
+    pub unused: Vec<String>,
+}
+
+impl From<TemplateError> for OpenAIError {
+    fn from(value: TemplateError) -> Self {
+        OpenAIError::InvalidArgument(value.to_string())
+    }
+}
+
+/// A chat request with placeholders in the text of its messages, see [crate::template].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestTemplate {
+    request: CreateChatCompletionRequest,
+    placeholders: BTreeSet<String>,
+}
+
+impl RequestTemplate {
+    /// Template of `request`, whose message texts may contain `{{name}}` placeholders.
+    pub fn new(mut request: CreateChatCompletionRequest) -> Self {
+        let placeholders = request
+            .messages
+            .iter_mut()
+            .flat_map(|message| message.texts_mut())
+            .flat_map(|text| placeholders(text))
+            .collect();
+        Self {
+            request,
+            placeholders,
+        }
+    }
+
+    /// Names of the placeholders of the template.
+    pub fn placeholders(&self) -> &BTreeSet<String> {
+        &self.placeholders
+    }
+
+    /// The request with every placeholder replaced by its value in `vars`.
+    ///
+    /// Fails if a placeholder has no value, or a value has no placeholder. Values are
+    /// inserted as they are: placeholders inside values are not expanded.
+    pub fn render<I, K, V>(&self, vars: I) -> Result<CreateChatCompletionRequest, TemplateError>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        let vars: BTreeMap<String, String> = vars
+            .into_iter()
+            .map(|(name, value)| (name.into(), value.into()))
+            .collect();
+
+        let missing: Vec<String> = self
+            .placeholders
+            .iter()
+            .filter(|name| !vars.contains_key(*name))
+            .cloned()
+            .collect();
+        let unused: Vec<String> = vars
+            .keys()
+            .filter(|name| !self.placeholders.contains(*name))
+            .cloned()
+            .collect();
+        if !missing.is_empty() || !unused.is_empty() {
+            return Err(TemplateError { missing, unused });
+        }
+
+        let mut request = self.request.clone();
+        for text in request
+            .messages
+            .iter_mut()
+            .flat_map(|message| message.texts_mut())
+        {
+            let rendered = placeholder_regex()
+                .replace_all(text, |captures: &Captures| vars[&captures[1]].clone());
+            if let std::borrow::Cow::Owned(rendered) = rendered {
+                *text = rendered;
+            }
+        }
+        Ok(request)
+    }
+}
+
+impl From<CreateChatCompletionRequest> for RequestTemplate {
+    fn from(request: CreateChatCompletionRequest) -> Self {
+        Self::new(request)
+    }
+}
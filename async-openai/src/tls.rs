@@ -0,0 +1,338 @@
+//! TLS settings for the underlying HTTP client: backend selection, custom CA bundles and
+//! certificate pinning.
+//!
+//! The TLS backend is chosen with crate features: `rustls` (default, OS root store),
+//! `rustls-webpki-roots` (bundled Mozilla roots), `native-tls` or `native-tls-vendored`.
+//! When several are enabled, [TlsConfig::with_backend] picks one explicitly.
+//!
+//! ```no_run
+//! # fn main() -> Result<(), async_openai::error::OpenAIError> {
+//! use async_openai::{tls::TlsConfig, Client};
+//!
+//! let tls = TlsConfig::new()
+//!     .with_ca_bundle_file("/etc/ssl/internal-gateway-ca.pem")?
+//!     .with_built_in_roots(false)
+//!     .with_pinned_public_key_sha256("r/mIkG3eEpVdm+u/ko/cwxzOMo1bk4TyHIlByibiA5E=")?;
+//!
+//! let client = Client::new().with_tls(&tls)?;
+//! # Ok(())
+//! # }
+//! ```
+use std::path::Path;
+
+use base64::{engine::general_purpose, Engine as _};
+
+use crate::error::OpenAIError;
+
+/// TLS implementation used by the HTTP client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TlsBackend {
+    /// rustls, available with the `rustls` or `rustls-webpki-roots` features.
+    Rustls,
+    /// The platform TLS library, available with the `native-tls` or `native-tls-vendored` features.
+    NativeTls,
+}
+
+/// TLS settings applied to the [reqwest::Client] used by [crate::Client].
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    backend: Option<TlsBackend>,
+    ca_certificates: Vec<Vec<u8>>,
+    built_in_roots: bool,
+    pinned_certificates: Vec<[u8; 32]>,
+    pinned_public_keys: Vec<[u8; 32]>,
+}
+
+impl Default for TlsConfig {
+    fn default() -> Self {
+        Self {
+            backend: None,
+            ca_certificates: vec![],
+            built_in_roots: true,
+            pinned_certificates: vec![],
+            pinned_public_keys: vec![],
+        }
+    }
+}
+
+impl TlsConfig {
+    /// Default settings: the backend selected by crate features and its built-in roots.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Select the TLS backend when more than one TLS feature is enabled.
+    pub fn with_backend(mut self, backend: TlsBackend) -> Self {
+        self.backend = Some(backend);
+        self
+    }
+
+    /// Trust the certificates in a PEM encoded CA bundle, in addition to the built-in roots.
+    pub fn with_ca_bundle_pem<B: Into<Vec<u8>>>(mut self, pem: B) -> Self {
+        self.ca_certificates.push(pem.into());
+        self
+    }
+
+    /// Trust the certificates in a PEM encoded CA bundle file.
+    pub fn with_ca_bundle_file<P: AsRef<Path>>(self, path: P) -> Result<Self, OpenAIError> {
+        let pem = std::fs::read(path.as_ref())
+            .map_err(|e| OpenAIError::FileReadError(format!("{}: {e}", path.as_ref().display())))?;
+        Ok(self.with_ca_bundle_pem(pem))
+    }
+
+    /// Whether to trust the backend's built-in root certificates. Disable to trust only
+    /// the configured CA bundles. Default is `true`.
+    pub fn with_built_in_roots(mut self, enabled: bool) -> Self {
+        self.built_in_roots = enabled;
+        self
+    }
+
+    /// Pin the base64 encoded SHA-256 digest of a DER certificate in the server's chain.
+    ///
+    /// Once any pin is configured, connections are only accepted if the chain contains a
+    /// pinned certificate or public key. Pinning requires a rustls backend.
+    pub fn with_pinned_certificate_sha256(mut self, digest: &str) -> Result<Self, OpenAIError> {
+        self.pinned_certificates.push(decode_pin(digest)?);
+        Ok(self)
+    }
+
+    /// Pin the base64 encoded SHA-256 digest of a DER SubjectPublicKeyInfo in the server's
+    /// chain (the `pin-sha256` format of HPKP). Pinning requires a rustls backend.
+    pub fn with_pinned_public_key_sha256(mut self, digest: &str) -> Result<Self, OpenAIError> {
+        self.pinned_public_keys.push(decode_pin(digest)?);
+        Ok(self)
+    }
+
+    fn has_pins(&self) -> bool {
+        !self.pinned_certificates.is_empty() || !self.pinned_public_keys.is_empty()
+    }
+
+    /// Apply these settings to a [reqwest::ClientBuilder].
+    pub fn apply(
+        &self,
+        mut builder: reqwest::ClientBuilder,
+    ) -> Result<reqwest::ClientBuilder, OpenAIError> {
+        match self.backend {
+            #[cfg(any(feature = "rustls", feature = "rustls-webpki-roots"))]
+            Some(TlsBackend::Rustls) => builder = builder.use_rustls_tls(),
+            #[cfg(any(feature = "native-tls", feature = "native-tls-vendored"))]
+            Some(TlsBackend::NativeTls) => builder = builder.use_native_tls(),
+            // Reachable unless every TLS feature is enabled
+            #[allow(unreachable_patterns)]
+            Some(backend) => {
+                return Err(OpenAIError::InvalidArgument(format!(
+                    "TLS backend {backend:?} is not enabled, see the crate features"
+                )))
+            }
+            None => {}
+        }
+
+        if self.has_pins() {
+            return self.apply_pinned(builder);
+        }
+
+        for pem in &self.ca_certificates {
+            for certificate in reqwest::Certificate::from_pem_bundle(pem)? {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+
+        Ok(builder.tls_built_in_root_certs(self.built_in_roots))
+    }
+
+    #[cfg(any(feature = "rustls", feature = "rustls-webpki-roots"))]
+    fn apply_pinned(
+        &self,
+        builder: reqwest::ClientBuilder,
+    ) -> Result<reqwest::ClientBuilder, OpenAIError> {
+        if self.backend == Some(TlsBackend::NativeTls) {
+            return Err(OpenAIError::InvalidArgument(
+                "certificate pinning requires the rustls backend".into(),
+            ));
+        }
+        Ok(builder.use_preconfigured_tls(pinning::client_config(self)?))
+    }
+
+    #[cfg(not(any(feature = "rustls", feature = "rustls-webpki-roots")))]
+    fn apply_pinned(
+        &self,
+        _builder: reqwest::ClientBuilder,
+    ) -> Result<reqwest::ClientBuilder, OpenAIError> {
+        Err(OpenAIError::InvalidArgument(
+            "certificate pinning requires the `rustls` or `rustls-webpki-roots` feature".into(),
+        ))
+    }
+
+    /// Build a [reqwest::Client] with these settings.
+    pub fn build_http_client(&self) -> Result<reqwest::Client, OpenAIError> {
+        Ok(self.apply(reqwest::Client::builder())?.build()?)
+    }
+}
+
+fn decode_pin(digest: &str) -> Result<[u8; 32], OpenAIError> {
+    let digest = digest.strip_prefix("sha256/").unwrap_or(digest);
+    general_purpose::STANDARD
+        .decode(digest)
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .ok_or_else(|| {
+            OpenAIError::InvalidArgument(format!("invalid base64 SHA-256 pin: {digest}"))
+        })
+}
+
+#[cfg(any(feature = "rustls", feature = "rustls-webpki-roots"))]
+mod pinning {
+    use std::sync::Arc;
+
+    use rustls::{
+        client::{
+            danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+            WebPkiServerVerifier,
+        },
+        pki_types::{pem::PemObject, CertificateDer, ServerName, UnixTime},
+        ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme,
+    };
+    use sha2::{Digest, Sha256};
+
+    use super::TlsConfig;
+    use crate::error::OpenAIError;
+
+    pub(super) fn client_config(tls: &TlsConfig) -> Result<ClientConfig, OpenAIError> {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+
+        let mut roots = RootCertStore::empty();
+        if tls.built_in_roots {
+            add_built_in_roots(&mut roots);
+        }
+        for pem in &tls.ca_certificates {
+            for certificate in CertificateDer::pem_slice_iter(pem) {
+                let certificate = certificate
+                    .map_err(|e| OpenAIError::InvalidArgument(format!("invalid CA bundle: {e}")))?;
+                roots.add(certificate).map_err(|e| {
+                    OpenAIError::InvalidArgument(format!("invalid CA certificate: {e}"))
+                })?;
+            }
+        }
+
+        let inner = WebPkiServerVerifier::builder_with_provider(Arc::new(roots), provider.clone())
+            .build()
+            .map_err(|e| OpenAIError::InvalidArgument(format!("invalid TLS roots: {e}")))?;
+
+        let verifier = PinningVerifier {
+            inner,
+            certificates: tls.pinned_certificates.clone(),
+            public_keys: tls.pinned_public_keys.clone(),
+        };
+
+        let mut config = ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .map_err(|e| OpenAIError::InvalidArgument(e.to_string()))?
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(verifier))
+            .with_no_client_auth();
+        // reqwest leaves ALPN to preconfigured TLS. Offer what its own configuration would: it
+        // is built without its `http2` feature, so a server picking `h2` would get HTTP/1.1
+        config.alpn_protocols = vec![b"http/1.1".to_vec()];
+        Ok(config)
+    }
+
+    #[cfg(feature = "rustls")]
+    fn add_built_in_roots(roots: &mut RootCertStore) {
+        let native = rustls_native_certs::load_native_certs();
+        for error in native.errors {
+            tracing::warn!("failed to load native root certificates: {error}");
+        }
+        roots.add_parsable_certificates(native.certs);
+    }
+
+    #[cfg(not(feature = "rustls"))]
+    fn add_built_in_roots(roots: &mut RootCertStore) {
+        roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    }
+
+    /// Regular WebPKI verification, followed by a check that the chain contains a pin.
+    #[derive(Debug)]
+    struct PinningVerifier {
+        inner: Arc<WebPkiServerVerifier>,
+        certificates: Vec<[u8; 32]>,
+        public_keys: Vec<[u8; 32]>,
+    }
+
+    impl PinningVerifier {
+        fn is_pinned(&self, certificate: &CertificateDer<'_>) -> bool {
+            let digest: [u8; 32] = Sha256::digest(certificate.as_ref()).into();
+            if self.certificates.contains(&digest) {
+                return true;
+            }
+
+            webpki::EndEntityCert::try_from(certificate).is_ok_and(|cert| {
+                let spki = cert.subject_public_key_info();
+                let digest: [u8; 32] = Sha256::digest(spki.as_ref()).into();
+                self.public_keys.contains(&digest)
+            })
+        }
+    }
+
+    impl ServerCertVerifier for PinningVerifier {
+        fn verify_server_cert(
+            &self,
+            end_entity: &CertificateDer<'_>,
+            intermediates: &[CertificateDer<'_>],
+            server_name: &ServerName<'_>,
+            ocsp_response: &[u8],
+            now: UnixTime,
+        ) -> Result<ServerCertVerified, rustls::Error> {
+            let verified = self.inner.verify_server_cert(
+                end_entity,
+                intermediates,
+                server_name,
+                ocsp_response,
+                now,
+            )?;
+
+            if std::iter::once(end_entity)
+                .chain(intermediates)
+                .any(|certificate| self.is_pinned(certificate))
+            {
+                Ok(verified)
+            } else {
+                Err(rustls::Error::General(
+                    "server certificate chain does not match any pin".into(),
+                ))
+            }
+        }
+
+        fn verify_tls12_signature(
+            &self,
+            message: &[u8],
+            cert: &CertificateDer<'_>,
+            dss: &DigitallySignedStruct,
+        ) -> Result<HandshakeSignatureValid, rustls::Error> {
+            self.inner.verify_tls12_signature(message, cert, dss)
+        }
+
+        fn verify_tls13_signature(
+            &self,
+            message: &[u8],
+            cert: &CertificateDer<'_>,
+            dss: &DigitallySignedStruct,
+        ) -> Result<HandshakeSignatureValid, rustls::Error> {
+            self.inner.verify_tls13_signature(message, cert, dss)
+        }
+
+        fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+            self.inner.supported_verify_schemes()
+        }
+    }
+}
+
+#[cfg(all(test, any(feature = "rustls", feature = "rustls-webpki-roots")))]
+mod tests {
+    use super::{pinning, TlsConfig};
+
+    #[test]
+    fn pinned_config_negotiates_http1() {
+        let config = pinning::client_config(&TlsConfig::new()).unwrap();
+        assert_eq!(config.alpn_protocols, [b"http/1.1".to_vec()]);
+    }
+}
@@ -0,0 +1,173 @@
+//! Token counting and token-aware text truncation.
+//!
+//! With the `tiktoken` feature counts are exact for OpenAI models, using the encoding of
+//! the model (`o200k_base` for `gpt-4o`, `cl100k_base` for `gpt-4`, ...); unknown models use
+//! `cl100k_base`. Without it, counts are estimated at four characters per token.
+//!
+//! ```
+//! use async_openai::tokens::{truncate_to_tokens, count_tokens, TruncationStrategy};
+//!
+//! let document = "The quick brown fox jumps over the lazy dog. ".repeat(100);
+//! let excerpt = truncate_to_tokens(&document, "gpt-4o", 50, TruncationStrategy::MiddleEllipsis);
+//! assert!(count_tokens(&excerpt, "gpt-4o") <= 50);
+//! ```
+use std::borrow::Cow;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Separator inserted by [TruncationStrategy::MiddleEllipsis].
+pub const ELLIPSIS: &str = " … ";
+
+/// Which part of a text [truncate_to_tokens] keeps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TruncationStrategy {
+    /// Keep the beginning, e.g. for documents whose summary comes first.
+    #[default]
+    Head,
+    /// Keep the end, e.g. for logs or the latest part of a conversation.
+    Tail,
+    /// Keep the beginning and the end, joined by [ELLIPSIS].
+    MiddleEllipsis,
+}
+
+/// Token counter for a model.
+#[derive(Clone, Copy)]
+pub struct Tokenizer {
+    #[cfg(feature = "tiktoken")]
+    bpe: &'static tiktoken_rs::CoreBPE,
+}
+
+impl std::fmt::Debug for Tokenizer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Tokenizer")
+            .field("exact", &self.is_exact())
+            .finish()
+    }
+}
+
+impl Tokenizer {
+    /// Tokenizer for `model`.
+    #[cfg(feature = "tiktoken")]
+    pub fn for_model(model: &str) -> Self {
+        use tiktoken_rs::tokenizer::{get_tokenizer, Tokenizer as Encoding};
+
+        let bpe = match get_tokenizer(model) {
+            Some(Encoding::O200kBase) => tiktoken_rs::o200k_base_singleton(),
+            Some(Encoding::P50kBase) => tiktoken_rs::p50k_base_singleton(),
+            Some(Encoding::P50kEdit) => tiktoken_rs::p50k_edit_singleton(),
+            Some(Encoding::R50kBase) | Some(Encoding::Gpt2) => tiktoken_rs::r50k_base_singleton(),
+            _ => tiktoken_rs::cl100k_base_singleton(),
+        };
+        Self { bpe }
+    }
+
+    /// Tokenizer for `model`.
+    #[cfg(not(feature = "tiktoken"))]
+    pub fn for_model(_model: &str) -> Self {
+        Self {}
+    }
+
+    /// Whether counts are exact rather than estimated, see the [module](self) docs.
+    pub fn is_exact(&self) -> bool {
+        cfg!(feature = "tiktoken")
+    }
+
+    /// Number of tokens in `text`, special tokens being counted as plain text.
+    pub fn count(&self, text: &str) -> usize {
+        #[cfg(feature = "tiktoken")]
+        {
+            self.bpe.encode_ordinary(text).len()
+        }
+        #[cfg(not(feature = "tiktoken"))]
+        {
+            text.chars().count().div_ceil(4)
+        }
+    }
+
+    /// See [truncate_to_tokens].
+    pub fn truncate<'a>(
+        &self,
+        text: &'a str,
+        max_tokens: usize,
+        strategy: TruncationStrategy,
+    ) -> Cow<'a, str> {
+        if self.count(text) <= max_tokens {
+            return Cow::Borrowed(text);
+        }
+
+        let boundaries: Vec<usize> = text
+            .grapheme_indices(true)
+            .map(|(index, _)| index)
+            .chain(std::iter::once(text.len()))
+            .collect();
+
+        match strategy {
+            TruncationStrategy::Head => Cow::Borrowed(self.head(text, &boundaries, max_tokens)),
+            TruncationStrategy::Tail => Cow::Borrowed(self.tail(text, &boundaries, max_tokens)),
+            TruncationStrategy::MiddleEllipsis => {
+                let Some(mut budget) = max_tokens.checked_sub(self.count(ELLIPSIS)) else {
+                    return Cow::Borrowed(self.head(text, &boundaries, max_tokens));
+                };
+
+                // Tokens may merge across the joins, so shrink until the result fits
+                loop {
+                    let head = self.head(text, &boundaries, budget.div_ceil(2));
+                    let tail = self.tail(text, &boundaries, budget - self.count(head));
+                    let joined = format!("{}{ELLIPSIS}{}", head.trim_end(), tail.trim_start());
+                    if self.count(&joined) <= max_tokens || budget == 0 {
+                        return Cow::Owned(joined);
+                    }
+                    budget -= 1;
+                }
+            }
+        }
+    }
+
+    /// Longest grapheme aligned prefix within `max_tokens`.
+    fn head<'a>(&self, text: &'a str, boundaries: &[usize], max_tokens: usize) -> &'a str {
+        // Largest k such that text[..boundaries[k]] fits
+        let (mut low, mut high) = (0, boundaries.len() - 1);
+        while low < high {
+            let mid = (low + high).div_ceil(2);
+            if self.count(&text[..boundaries[mid]]) <= max_tokens {
+                low = mid;
+            } else {
+                high = mid - 1;
+            }
+        }
+        &text[..boundaries[low]]
+    }
+
+    /// Longest grapheme aligned suffix within `max_tokens`.
+    fn tail<'a>(&self, text: &'a str, boundaries: &[usize], max_tokens: usize) -> &'a str {
+        // Smallest k such that text[boundaries[k]..] fits
+        let (mut low, mut high) = (0, boundaries.len() - 1);
+        while low < high {
+            let mid = (low + high) / 2;
+            if self.count(&text[boundaries[mid]..]) <= max_tokens {
+                high = mid;
+            } else {
+                low = mid + 1;
+            }
+        }
+        &text[boundaries[low]..]
+    }
+}
+
+/// Number of tokens in `text` for `model`.
+pub fn count_tokens(text: &str, model: &str) -> usize {
+    Tokenizer::for_model(model).count(text)
+}
+
+/// Shorten `text` to at most `max_tokens` tokens of `model`, cutting only at grapheme
+/// cluster boundaries so that emoji, combining marks and the like are never split.
+///
+/// Text that already fits is returned unchanged.
+pub fn truncate_to_tokens<'a>(
+    text: &'a str,
+    model: &str,
+    max_tokens: usize,
+    strategy: TruncationStrategy,
+) -> Cow<'a, str> {
+    Tokenizer::for_model(model).truncate(text, max_tokens, strategy)
+}
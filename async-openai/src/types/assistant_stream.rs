@@ -3,7 +3,9 @@ use std::pin::Pin;
 use futures::Stream;
 use serde::Deserialize;
 
-use crate::error::{map_deserialization_error, ApiError, OpenAIError};
+#[cfg(feature = "client")]
+use crate::error::map_deserialization_error;
+use crate::error::{ApiError, OpenAIError};
 
 use super::{
     MessageDeltaObject, MessageObject, RunObject, RunStepDeltaObject, RunStepObject, ThreadObject,
@@ -113,6 +115,7 @@ pub enum AssistantStreamEvent {
 pub type AssistantEventStream =
     Pin<Box<dyn Stream<Item = Result<AssistantStreamEvent, OpenAIError>> + Send>>;
 
+#[cfg(feature = "client")]
 impl TryFrom<eventsource_stream::Event> for AssistantStreamEvent {
     type Error = OpenAIError;
     fn try_from(value: eventsource_stream::Event) -> Result<Self, Self::Error> {
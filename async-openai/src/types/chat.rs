@@ -6,6 +6,8 @@ use serde::{Deserialize, Serialize};
 
 use crate::error::OpenAIError;
 
+use super::{ContentFilterResults, PromptFilterResult};
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 #[serde(untagged)]
 pub enum Prompt {
@@ -31,13 +33,8 @@ pub struct Logprobs {
     pub text_offset: Vec<u32>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
-#[serde(rename_all = "snake_case")]
-pub enum CompletionFinishReason {
-    Stop,
-    Length,
-    ContentFilter,
-}
+/// Finish reason of a legacy completion, which uses the same values as chat completions.
+pub type CompletionFinishReason = FinishReason;
 
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 pub struct Choice {
@@ -45,6 +42,13 @@ pub struct Choice {
     pub index: u32,
     pub logprobs: Option<Logprobs>,
     pub finish_reason: Option<CompletionFinishReason>,
+    /// Azure OpenAI content filter results for this choice.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_filter_results: Option<ContentFilterResults>,
+    /// Fields this type doesn't declare, see [crate::types].
+    #[cfg_attr(feature = "extra-fields", serde(flatten))]
+    #[cfg_attr(not(feature = "extra-fields"), serde(skip))]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
@@ -97,6 +101,10 @@ pub struct CompletionUsage {
     pub prompt_tokens_details: Option<PromptTokensDetails>,
     /// Breakdown of tokens used in a completion.
     pub completion_tokens_details: Option<CompletionTokensDetails>,
+    /// Fields this type doesn't declare, see [crate::types].
+    #[cfg_attr(feature = "extra-fields", serde(flatten))]
+    #[cfg_attr(not(feature = "extra-fields"), serde(skip))]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 /// Breakdown of tokens used in a completion.
@@ -433,6 +441,10 @@ pub struct ChatCompletionResponseMessage {
 
     /// If the audio output modality is requested, this object contains data about the audio response from the model. [Learn more](https://platform.openai.com/docs/guides/audio).
     pub audio: Option<ChatCompletionResponseMessageAudio>,
+    /// Fields this type doesn't declare, see [crate::types].
+    #[cfg_attr(feature = "extra-fields", serde(flatten))]
+    #[cfg_attr(not(feature = "extra-fields"), serde(skip))]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 #[derive(Clone, Serialize, Default, Debug, Deserialize, Builder, PartialEq)]
@@ -555,6 +567,43 @@ pub enum ChatCompletionToolChoiceOption {
     Required,
     #[serde(untagged)]
     Named(ChatCompletionNamedToolChoice),
+    #[serde(untagged)]
+    AllowedTools(ChatCompletionAllowedToolsChoice),
+}
+
+/// Restricts the model to some of the tools of the request, so that the list of tools, and
+/// with it the prompt cache, stays the same from one request to the next.
+#[derive(Clone, Serialize, Default, Debug, Deserialize, PartialEq)]
+pub struct ChatCompletionAllowedToolsChoice {
+    /// Always `allowed_tools`.
+    pub r#type: ChatCompletionAllowedToolsType,
+
+    pub allowed_tools: ChatCompletionAllowedTools,
+}
+
+#[derive(Clone, Serialize, Default, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChatCompletionAllowedToolsType {
+    #[default]
+    AllowedTools,
+}
+
+#[derive(Clone, Serialize, Default, Debug, Deserialize, PartialEq)]
+pub struct ChatCompletionAllowedTools {
+    /// `auto` lets the model pick among the allowed tools or answer with a message,
+    /// `required` makes it call one or more of them.
+    pub mode: ChatCompletionAllowedToolsMode,
+
+    /// The tools the model may call.
+    pub tools: Vec<ChatCompletionNamedToolChoice>,
+}
+
+#[derive(Clone, Copy, Serialize, Default, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChatCompletionAllowedToolsMode {
+    #[default]
+    Auto,
+    Required,
 }
 
 #[derive(Clone, Serialize, Debug, Deserialize, PartialEq)]
@@ -562,6 +611,10 @@ pub enum ChatCompletionToolChoiceOption {
 pub enum ServiceTier {
     Auto,
     Default,
+    /// Cheaper, slower processing which may be unavailable at times, see
+    /// [crate::flex::FlexPolicy]
+    Flex,
+    Priority,
 }
 
 #[derive(Clone, Serialize, Debug, Deserialize, PartialEq)]
@@ -569,6 +622,8 @@ pub enum ServiceTier {
 pub enum ServiceTierResponse {
     Scale,
     Default,
+    Flex,
+    Priority,
 }
 
 #[derive(Clone, Serialize, Debug, Deserialize, PartialEq)]
@@ -647,7 +702,7 @@ pub struct ChatCompletionAudio {
 #[builder(pattern = "mutable")]
 #[builder(setter(into, strip_option), default)]
 #[builder(derive(Debug))]
-#[builder(build_fn(error = "OpenAIError"))]
+#[builder(build_fn(error = "OpenAIError", validate = "Self::validate"))]
 pub struct CreateChatCompletionRequest {
     /// A list of messages comprising the conversation so far. Depending on the [model](https://platform.openai.com/docs/models) you use, different message types (modalities) are supported, like [text](https://platform.openai.com/docs/guides/text-generation), [images](https://platform.openai.com/docs/guides/vision), and [audio](https://platform.openai.com/docs/guides/audio).
     pub messages: Vec<ChatCompletionRequestMessage>, // min: 1
@@ -751,6 +806,8 @@ pub struct CreateChatCompletionRequest {
     /// Specifies the latency tier to use for processing the request. This parameter is relevant for customers subscribed to the scale tier service:
     /// - If set to 'auto', the system will utilize scale tier credits until they are exhausted.
     /// - If set to 'default', the request will be processed using the default service tier with a lower uptime SLA and no latency guarentee.
+    /// - If set to 'flex', the request will be processed with the flex tier, which responds with
+    ///   [crate::error::OpenAIError::ResourceUnavailable] when it has no capacity.
     /// - When not set, the default behavior is 'auto'.
     ///
     /// When this parameter is set, the response body will include the `service_tier` utilized.
@@ -821,6 +878,18 @@ pub struct CreateChatCompletionRequest {
     pub functions: Option<Vec<ChatCompletionFunctions>>,
 }
 
+impl CreateChatCompletionRequestArgs {
+    /// Check `tool_choice` against `tools`, see [ChatCompletionToolChoiceOption::check_against]
+    fn validate(&self) -> Result<(), OpenAIError> {
+        match &self.tool_choice {
+            Some(Some(tool_choice)) => {
+                tool_choice.check_against(self.tools.as_ref().and_then(|tools| tools.as_deref()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
 /// Options for streaming response. Only set this when you set `stream: true`.
 #[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
 pub struct ChatCompletionStreamOptions {
@@ -828,14 +897,21 @@ pub struct ChatCompletionStreamOptions {
     pub include_usage: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+/// Why the model stopped generating tokens.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
 #[serde(rename_all = "snake_case")]
 pub enum FinishReason {
+    /// A natural stop point or a provided stop sequence was reached.
     Stop,
+    /// The token limit of the request or the context window was reached.
     Length,
     ToolCalls,
     ContentFilter,
+    /// Deprecated in favor of `tool_calls`.
     FunctionCall,
+    /// A reason not listed above, e.g. `insufficient_system_resource` from DeepSeek.
+    #[serde(untagged)]
+    Other(String),
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
@@ -879,6 +955,13 @@ pub struct ChatChoice {
     pub finish_reason: Option<FinishReason>,
     /// Log probability information for the choice.
     pub logprobs: Option<ChatChoiceLogprobs>,
+    /// Azure OpenAI content filter results for this choice.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_filter_results: Option<ContentFilterResults>,
+    /// Fields this type doesn't declare, see [crate::types].
+    #[cfg_attr(feature = "extra-fields", serde(flatten))]
+    #[cfg_attr(not(feature = "extra-fields"), serde(skip))]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 /// Represents a chat completion response returned by model, based on the provided input.
@@ -903,6 +986,56 @@ pub struct CreateChatCompletionResponse {
     /// The object type, which is always `chat.completion`.
     pub object: String,
     pub usage: Option<CompletionUsage>,
+    /// Azure OpenAI content filter results for the prompts of the request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt_filter_results: Option<Vec<PromptFilterResult>>,
+    /// Fields this type doesn't declare, see [crate::types].
+    #[cfg_attr(feature = "extra-fields", serde(flatten))]
+    #[cfg_attr(not(feature = "extra-fields"), serde(skip))]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// A page of chat completions stored with `store: true`.
+#[derive(Debug, Deserialize, Clone, PartialEq, Serialize)]
+pub struct ChatCompletionList {
+    pub object: String,
+    pub data: Vec<CreateChatCompletionResponse>,
+    pub first_id: Option<String>,
+    pub last_id: Option<String>,
+    pub has_more: bool,
+}
+
+/// A message of a stored chat completion.
+#[derive(Debug, Deserialize, Clone, PartialEq, Serialize)]
+pub struct ChatCompletionStoreMessage {
+    /// The identifier of the message.
+    pub id: String,
+    #[serde(flatten)]
+    pub message: ChatCompletionResponseMessage,
+}
+
+/// A page of the messages of a stored chat completion.
+#[derive(Debug, Deserialize, Clone, PartialEq, Serialize)]
+pub struct ChatCompletionMessageList {
+    pub object: String,
+    pub data: Vec<ChatCompletionStoreMessage>,
+    pub first_id: Option<String>,
+    pub last_id: Option<String>,
+    pub has_more: bool,
+}
+
+#[derive(Clone, Serialize, Default, Debug, Deserialize, PartialEq)]
+pub struct UpdateChatCompletionRequest {
+    /// Set of 16 key-value pairs that can be attached to the stored completion, replacing its
+    /// current metadata.
+    pub metadata: Option<HashMap<String, String>>,
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq, Serialize)]
+pub struct ChatCompletionDeleted {
+    pub id: String,
+    pub object: String,
+    pub deleted: bool,
 }
 
 /// Parsed server side events stream until an \[DONE\] is received from server.
@@ -945,6 +1078,10 @@ pub struct ChatCompletionStreamResponseDelta {
     pub role: Option<Role>,
     /// The refusal message generated by the model.
     pub refusal: Option<String>,
+    /// Fields this type doesn't declare, see [crate::types].
+    #[cfg_attr(feature = "extra-fields", serde(flatten))]
+    #[cfg_attr(not(feature = "extra-fields"), serde(skip))]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
@@ -965,6 +1102,13 @@ pub struct ChatChoiceStream {
     pub finish_reason: Option<FinishReason>,
     /// Log probability information for the choice.
     pub logprobs: Option<ChatChoiceLogprobs>,
+    /// Azure OpenAI content filter results for this choice.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_filter_results: Option<ContentFilterResults>,
+    /// Fields this type doesn't declare, see [crate::types].
+    #[cfg_attr(feature = "extra-fields", serde(flatten))]
+    #[cfg_attr(not(feature = "extra-fields"), serde(skip))]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 #[derive(Debug, Deserialize, Clone, PartialEq, Serialize)]
@@ -990,4 +1134,11 @@ pub struct CreateChatCompletionStreamResponse {
     /// An optional field that will only be present when you set `stream_options: {"include_usage": true}` in your request.
     /// When present, it contains a null value except for the last chunk which contains the token usage statistics for the entire request.
     pub usage: Option<CompletionUsage>,
+    /// Azure OpenAI content filter results for the prompts of the request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt_filter_results: Option<Vec<PromptFilterResult>>,
+    /// Fields this type doesn't declare, see [crate::types].
+    #[cfg_attr(feature = "extra-fields", serde(flatten))]
+    #[cfg_attr(not(feature = "extra-fields"), serde(skip))]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
@@ -1,8 +1,16 @@
-use std::path::PathBuf;
+use std::{path::PathBuf, pin::Pin};
 
 use bytes::Bytes;
+use futures::Stream;
 use serde::{Deserialize, Serialize};
 
+use crate::error::OpenAIError;
+
+/// Parsed server side events of a raw request, as untyped JSON, until an \[DONE\] is received
+/// from server.
+pub type RawResponseStream =
+    Pin<Box<dyn Stream<Item = Result<serde_json::Value, OpenAIError>> + Send>>;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum InputSource {
     Path { path: PathBuf },
@@ -6,7 +6,9 @@ use serde::{Deserialize, Serialize};
 
 use crate::error::OpenAIError;
 
-use super::{ChatCompletionStreamOptions, Choice, CompletionUsage, Prompt, Stop};
+use super::{
+    ChatCompletionStreamOptions, Choice, CompletionUsage, Prompt, PromptFilterResult, Stop,
+};
 
 #[derive(Clone, Serialize, Deserialize, Default, Debug, Builder, PartialEq)]
 #[builder(name = "CreateCompletionRequestArgs")]
@@ -134,6 +136,13 @@ pub struct CreateCompletionResponse {
     /// The object type, which is always "text_completion"
     pub object: String,
     pub usage: Option<CompletionUsage>,
+    /// Azure OpenAI content filter results for the prompts of the request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt_filter_results: Option<Vec<PromptFilterResult>>,
+    /// Fields this type doesn't declare, see [crate::types].
+    #[cfg_attr(feature = "extra-fields", serde(flatten))]
+    #[cfg_attr(not(feature = "extra-fields"), serde(skip))]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 /// Parsed server side events stream until an \[DONE\] is received from server.
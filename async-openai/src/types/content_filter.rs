@@ -0,0 +1,104 @@
+use serde::{Deserialize, Serialize};
+
+/// Severity assigned by the Azure OpenAI content filters.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentFilterSeverity {
+    Safe,
+    Low,
+    Medium,
+    High,
+}
+
+/// Result of a severity based filter category, such as `hate` or `violence`.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct ContentFilterSeverityResult {
+    /// Whether the content was filtered for this category.
+    pub filtered: bool,
+    pub severity: ContentFilterSeverity,
+}
+
+/// Result of a detection based filter, such as `jailbreak` or `profanity`.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct ContentFilterDetectionResult {
+    /// Whether the content was filtered because of the detection.
+    pub filtered: bool,
+    pub detected: bool,
+}
+
+/// Source of protected code found in a completion.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ContentFilterCitation {
+    #[serde(rename = "URL", alias = "url")]
+    pub url: Option<String>,
+    pub license: Option<String>,
+}
+
+/// Result of the protected material code filter, with the source of the match.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ContentFilterCitedDetectionResult {
+    pub filtered: bool,
+    pub detected: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub citation: Option<ContentFilterCitation>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ContentFilterBlocklistResult {
+    /// Id of the custom blocklist.
+    pub id: String,
+    pub filtered: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ContentFilterBlocklistResults {
+    pub filtered: bool,
+    #[serde(default)]
+    pub details: Vec<ContentFilterBlocklistResult>,
+}
+
+/// Reported in place of results when the content filters could not run.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ContentFilterError {
+    pub code: String,
+    pub message: String,
+}
+
+/// Azure OpenAI content filter results for a prompt or a completion. Categories that were not
+/// evaluated are `None`.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct ContentFilterResults {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hate: Option<ContentFilterSeverityResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sexual: Option<ContentFilterSeverityResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub violence: Option<ContentFilterSeverityResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub self_harm: Option<ContentFilterSeverityResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profanity: Option<ContentFilterDetectionResult>,
+    /// Prompt only: user prompt attacks.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jailbreak: Option<ContentFilterDetectionResult>,
+    /// Prompt only: attacks embedded in documents.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub indirect_attack: Option<ContentFilterDetectionResult>,
+    /// Completion only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub protected_material_text: Option<ContentFilterDetectionResult>,
+    /// Completion only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub protected_material_code: Option<ContentFilterCitedDetectionResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub custom_blocklists: Option<ContentFilterBlocklistResults>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ContentFilterError>,
+}
+
+/// Content filter results of one prompt of the request.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct PromptFilterResult {
+    pub prompt_index: u32,
+    pub content_filter_results: ContentFilterResults,
+}
@@ -63,6 +63,10 @@ pub struct Embedding {
     /// The embedding vector, which is a list of floats. The length of vector
     /// depends on the model as listed in the [embedding guide](https://platform.openai.com/docs/guides/embeddings).
     pub embedding: Vec<f32>,
+    /// Fields this type doesn't declare, see [crate::types].
+    #[cfg_attr(feature = "extra-fields", serde(flatten))]
+    #[cfg_attr(not(feature = "extra-fields"), serde(skip))]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
@@ -97,6 +101,10 @@ pub struct EmbeddingUsage {
     pub prompt_tokens: u32,
     /// The total number of tokens used by the request.
     pub total_tokens: u32,
+    /// Fields this type doesn't declare, see [crate::types].
+    #[cfg_attr(feature = "extra-fields", serde(flatten))]
+    #[cfg_attr(not(feature = "extra-fields"), serde(skip))]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 #[derive(Debug, Deserialize, Clone, PartialEq, Serialize)]
@@ -108,6 +116,10 @@ pub struct CreateEmbeddingResponse {
     pub data: Vec<Embedding>,
     /// The usage information for the request.
     pub usage: EmbeddingUsage,
+    /// Fields this type doesn't declare, see [crate::types].
+    #[cfg_attr(feature = "extra-fields", serde(flatten))]
+    #[cfg_attr(not(feature = "extra-fields"), serde(skip))]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 #[derive(Debug, Deserialize, Clone, PartialEq, Serialize)]
@@ -119,4 +131,8 @@ pub struct CreateBase64EmbeddingResponse {
     pub data: Vec<Base64Embedding>,
     /// The usage information for the request.
     pub usage: EmbeddingUsage,
+    /// Fields this type doesn't declare, see [crate::types].
+    #[cfg_attr(feature = "extra-fields", serde(flatten))]
+    #[cfg_attr(not(feature = "extra-fields"), serde(skip))]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
@@ -0,0 +1,184 @@
+use half::f16;
+use serde::{Deserialize, Serialize};
+
+use super::{CreateEmbeddingResponse, Embedding};
+
+/// An embedding stored as IEEE 754 half precision floats, half the size of `Vec<f32>`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct HalfEmbedding(pub Vec<f16>);
+
+impl HalfEmbedding {
+    pub fn from_f32(vector: &[f32]) -> Self {
+        Self(vector.iter().copied().map(f16::from_f32).collect())
+    }
+
+    pub fn to_f32(&self) -> Vec<f32> {
+        self.0.iter().copied().map(f16::to_f32).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Little endian bytes, two per dimension.
+    pub fn to_le_bytes(&self) -> Vec<u8> {
+        self.0.iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+
+    /// Inverse of [HalfEmbedding::to_le_bytes]; a trailing odd byte is ignored.
+    pub fn from_le_bytes(bytes: &[u8]) -> Self {
+        Self(
+            bytes
+                .chunks_exact(2)
+                .map(|b| f16::from_le_bytes([b[0], b[1]]))
+                .collect(),
+        )
+    }
+}
+
+/// An embedding quantized to `i8` with a single symmetric scale, a quarter of the size
+/// of `Vec<f32>`. Each value is restored as `value as f32 * scale`.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct QuantizedEmbedding {
+    pub values: Vec<i8>,
+    pub scale: f32,
+}
+
+impl QuantizedEmbedding {
+    /// Quantize using the largest absolute component as the scale, so no value is clipped.
+    pub fn from_f32(vector: &[f32]) -> Self {
+        let max_abs = vector.iter().fold(0.0f32, |max, v| max.max(v.abs()));
+        let scale = if max_abs > 0.0 { max_abs / 127.0 } else { 1.0 };
+
+        Self {
+            values: vector
+                .iter()
+                .map(|v| (v / scale).round().clamp(-127.0, 127.0) as i8)
+                .collect(),
+            scale,
+        }
+    }
+
+    pub fn to_f32(&self) -> Vec<f32> {
+        self.values.iter().map(|v| *v as f32 * self.scale).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Dot product computed on the quantized values, without restoring either vector.
+    pub fn dot(&self, other: &QuantizedEmbedding) -> f32 {
+        let sum: i64 = self
+            .values
+            .iter()
+            .zip(&other.values)
+            .map(|(a, b)| *a as i64 * *b as i64)
+            .sum();
+        sum as f32 * self.scale * other.scale
+    }
+}
+
+/// How far a restored embedding is from the original one.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct QuantizationError {
+    /// Largest absolute difference of a single component.
+    pub max_abs: f32,
+    /// Mean absolute difference.
+    pub mean_abs: f32,
+    /// Root mean square difference.
+    pub rmse: f32,
+    /// Cosine similarity between original and restored vectors, 1.0 being identical.
+    pub cosine_similarity: f32,
+}
+
+impl QuantizationError {
+    /// Compare `restored` against `original`, component by component.
+    pub fn between(original: &[f32], restored: &[f32]) -> Self {
+        let n = original.len().min(restored.len());
+        if n == 0 {
+            return Self {
+                max_abs: 0.0,
+                mean_abs: 0.0,
+                rmse: 0.0,
+                cosine_similarity: 1.0,
+            };
+        }
+
+        let (mut max_abs, mut sum_abs, mut sum_sq) = (0.0f64, 0.0f64, 0.0f64);
+        let (mut dot, mut norm_a, mut norm_b) = (0.0f64, 0.0f64, 0.0f64);
+        for (a, b) in original.iter().zip(restored) {
+            let (a, b) = (*a as f64, *b as f64);
+            let diff = (a - b).abs();
+            max_abs = max_abs.max(diff);
+            sum_abs += diff;
+            sum_sq += diff * diff;
+            dot += a * b;
+            norm_a += a * a;
+            norm_b += b * b;
+        }
+
+        let cosine_similarity = if norm_a == 0.0 || norm_b == 0.0 {
+            if norm_a == norm_b {
+                1.0
+            } else {
+                0.0
+            }
+        } else {
+            dot / (norm_a.sqrt() * norm_b.sqrt())
+        };
+
+        Self {
+            max_abs: max_abs as f32,
+            mean_abs: (sum_abs / n as f64) as f32,
+            rmse: (sum_sq / n as f64).sqrt() as f32,
+            cosine_similarity: cosine_similarity as f32,
+        }
+    }
+}
+
+impl Embedding {
+    /// Convert the vector to half precision.
+    pub fn to_f16(&self) -> HalfEmbedding {
+        HalfEmbedding::from_f32(&self.embedding)
+    }
+
+    /// Quantize the vector to `i8`.
+    pub fn quantize_i8(&self) -> QuantizedEmbedding {
+        QuantizedEmbedding::from_f32(&self.embedding)
+    }
+
+    /// Quantize the vector to `i8` and report the error introduced by the round trip.
+    pub fn quantize_i8_with_error(&self) -> (QuantizedEmbedding, QuantizationError) {
+        let quantized = self.quantize_i8();
+        let error = QuantizationError::between(&self.embedding, &quantized.to_f32());
+        (quantized, error)
+    }
+
+    /// Convert the vector to half precision and report the error introduced by the round trip.
+    pub fn to_f16_with_error(&self) -> (HalfEmbedding, QuantizationError) {
+        let half = self.to_f16();
+        let error = QuantizationError::between(&self.embedding, &half.to_f32());
+        (half, error)
+    }
+}
+
+impl CreateEmbeddingResponse {
+    /// Half precision copies of all embeddings, in the order of `data`.
+    pub fn to_f16(&self) -> Vec<HalfEmbedding> {
+        self.data.iter().map(Embedding::to_f16).collect()
+    }
+
+    /// `i8` quantized copies of all embeddings, in the order of `data`.
+    pub fn quantize_i8(&self) -> Vec<QuantizedEmbedding> {
+        self.data.iter().map(Embedding::quantize_i8).collect()
+    }
+}
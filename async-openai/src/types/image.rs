@@ -133,6 +133,24 @@ pub struct ImagesResponse {
     pub data: Vec<std::sync::Arc<Image>>,
 }
 
+/// Encoding of image bytes, detected from their signature.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFormat {
+    Png,
+    Jpeg,
+    Gif,
+    Webp,
+}
+
+/// The bytes of a base64-encoded [Image], see [Image::decode].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageData {
+    pub bytes: bytes::Bytes,
+    /// `None` when the bytes are in none of the [ImageFormat]s.
+    pub format: Option<ImageFormat>,
+}
+
 #[derive(Debug, Default, Clone, PartialEq)]
 pub struct ImageInput {
     pub source: InputSource,
@@ -3,34 +3,45 @@ use std::{
     path::{Path, PathBuf},
 };
 
+#[cfg(feature = "client")]
 use crate::{
     download::{download_url, save_b64},
-    error::OpenAIError,
     traits::AsyncTryFrom,
-    types::InputSource,
     util::{create_all_dir, create_file_part},
 };
+use crate::{error::OpenAIError, types::InputSource};
 
+use base64::{engine::general_purpose, Engine as _};
 use bytes::Bytes;
 
 use super::{
-    AddUploadPartRequest, AudioInput, AudioResponseFormat, ChatCompletionFunctionCall,
-    ChatCompletionFunctions, ChatCompletionNamedToolChoice, ChatCompletionRequestAssistantMessage,
-    ChatCompletionRequestAssistantMessageContent, ChatCompletionRequestDeveloperMessage,
-    ChatCompletionRequestDeveloperMessageContent, ChatCompletionRequestFunctionMessage,
-    ChatCompletionRequestMessage, ChatCompletionRequestMessageContentPartAudio,
-    ChatCompletionRequestMessageContentPartImage, ChatCompletionRequestMessageContentPartText,
-    ChatCompletionRequestSystemMessage, ChatCompletionRequestSystemMessageContent,
+    AudioInput, AudioResponseFormat, Category, ChatChoice, ChatChoiceStream,
+    ChatCompletionAllowedTools, ChatCompletionAllowedToolsChoice, ChatCompletionAllowedToolsMode,
+    ChatCompletionAllowedToolsType, ChatCompletionFunctionCall, ChatCompletionFunctions,
+    ChatCompletionNamedToolChoice, ChatCompletionRequestAssistantMessage,
+    ChatCompletionRequestAssistantMessageContent, ChatCompletionRequestAssistantMessageContentPart,
+    ChatCompletionRequestDeveloperMessage, ChatCompletionRequestDeveloperMessageContent,
+    ChatCompletionRequestFunctionMessage, ChatCompletionRequestMessage,
+    ChatCompletionRequestMessageContentPartAudio, ChatCompletionRequestMessageContentPartImage,
+    ChatCompletionRequestMessageContentPartText, ChatCompletionRequestSystemMessage,
+    ChatCompletionRequestSystemMessageContent, ChatCompletionRequestSystemMessageContentPart,
     ChatCompletionRequestToolMessage, ChatCompletionRequestToolMessageContent,
-    ChatCompletionRequestUserMessage, ChatCompletionRequestUserMessageContent,
-    ChatCompletionRequestUserMessageContentPart, ChatCompletionToolChoiceOption, CreateFileRequest,
-    CreateImageEditRequest, CreateImageVariationRequest, CreateMessageRequestContent,
-    CreateSpeechResponse, CreateTranscriptionRequest, CreateTranslationRequest, DallE2ImageSize,
-    EmbeddingInput, FileInput, FilePurpose, FunctionName, Image, ImageInput, ImageModel,
+    ChatCompletionRequestToolMessageContentPart, ChatCompletionRequestUserMessage,
+    ChatCompletionRequestUserMessageContent, ChatCompletionRequestUserMessageContentPart,
+    ChatCompletionTool, ChatCompletionToolChoiceOption, Choice, ContentFilterResults,
+    ContentFilterSeverity, CreateChatCompletionResponse, CreateCompletionResponse,
+    CreateMessageRequestContent, DallE2ImageSize, EmbeddingInput, FileInput, FilePurpose,
+    FinishReason, FunctionName, Image, ImageData, ImageFormat, ImageInput, ImageModel,
     ImageResponseFormat, ImageSize, ImageUrl, ImagesResponse, ModerationInput, Prompt, Role, Stop,
     TimestampGranularity,
 };
 
+#[cfg(feature = "client")]
+use super::{
+    AddUploadPartRequest, CreateFileRequest, CreateImageEditRequest, CreateImageVariationRequest,
+    CreateSpeechResponse, CreateTranscriptionRequest, CreateTranslationRequest,
+};
+
 /// for `impl_from!(T, Enum)`, implements
 /// - `From<T>`
 /// - `From<Vec<T>>`
@@ -277,6 +288,7 @@ impl Display for FilePurpose {
     }
 }
 
+#[cfg(feature = "client")]
 impl ImagesResponse {
     /// Save each image in a dedicated Tokio task and return paths to saved files.
     /// For [ResponseFormat::Url] each file is downloaded in dedicated Tokio task.
@@ -317,6 +329,7 @@ impl ImagesResponse {
     }
 }
 
+#[cfg(feature = "client")]
 impl CreateSpeechResponse {
     pub async fn save<P: AsRef<Path>>(&self, file_path: P) -> Result<(), OpenAIError> {
         let dir = file_path.as_ref().parent();
@@ -333,6 +346,7 @@ impl CreateSpeechResponse {
     }
 }
 
+#[cfg(feature = "client")]
 impl Image {
     async fn save<P: AsRef<Path>>(&self, dir: P) -> Result<PathBuf, OpenAIError> {
         match self {
@@ -342,6 +356,174 @@ impl Image {
     }
 }
 
+impl Image {
+    /// Decode a `b64_json` image. Fails for images returned as a URL, which
+    /// [ImagesResponse::save] downloads.
+    pub fn decode(&self) -> Result<ImageData, OpenAIError> {
+        match self {
+            Image::Url { .. } => Err(OpenAIError::InvalidArgument(
+                "image is a URL, request `b64_json` to decode it".to_string(),
+            )),
+            Image::B64Json { b64_json, .. } => {
+                let bytes = general_purpose::STANDARD
+                    .decode(b64_json.as_str())
+                    .map_err(|e| {
+                        OpenAIError::InvalidArgument(format!("invalid base64 image: {e}"))
+                    })?;
+                Ok(ImageData {
+                    format: ImageFormat::detect(&bytes),
+                    bytes: bytes.into(),
+                })
+            }
+        }
+    }
+}
+
+impl ImagesResponse {
+    /// Decode every image, see [Image::decode].
+    pub fn decode_all(&self) -> Result<Vec<ImageData>, OpenAIError> {
+        self.data.iter().map(|image| image.decode()).collect()
+    }
+}
+
+impl ImageFormat {
+    /// Format of `bytes`, from their signature.
+    pub fn detect(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(Self::Png)
+        } else if bytes.starts_with(&[0xff, 0xd8, 0xff]) {
+            Some(Self::Jpeg)
+        } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+            Some(Self::Gif)
+        } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+            Some(Self::Webp)
+        } else {
+            None
+        }
+    }
+
+    /// Format named by a file extension such as `jpg`, ignoring case.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "png" => Some(Self::Png),
+            "jpg" | "jpeg" => Some(Self::Jpeg),
+            "gif" => Some(Self::Gif),
+            "webp" => Some(Self::Webp),
+            _ => None,
+        }
+    }
+
+    /// File extension of the format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Jpeg => "jpg",
+            Self::Gif => "gif",
+            Self::Webp => "webp",
+        }
+    }
+}
+
+impl Display for ImageFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.extension())
+    }
+}
+
+impl ImageData {
+    /// Width and height in pixels, read from the header without decoding the image. `None`
+    /// when the format is unknown or the header is cut short.
+    pub fn dimensions(&self) -> Option<(u32, u32)> {
+        let bytes = &self.bytes[..];
+        let be16 =
+            |at: usize| Some(u16::from_be_bytes([*bytes.get(at)?, *bytes.get(at + 1)?]) as u32);
+        let le16 =
+            |at: usize| Some(u16::from_le_bytes([*bytes.get(at)?, *bytes.get(at + 1)?]) as u32);
+        let le32 = |at: usize| Some(u32::from_le_bytes(bytes.get(at..at + 4)?.try_into().ok()?));
+
+        match self.format? {
+            ImageFormat::Png if bytes.get(12..16)? == b"IHDR" => {
+                let be32 =
+                    |at: usize| Some(u32::from_be_bytes(bytes.get(at..at + 4)?.try_into().ok()?));
+                Some((be32(16)?, be32(20)?))
+            }
+            ImageFormat::Png => None,
+            ImageFormat::Gif => Some((le16(6)?, le16(8)?)),
+            ImageFormat::Jpeg => {
+                // Segments up to the start of frame, which holds the height and then the width
+                let mut at = 2;
+                loop {
+                    if *bytes.get(at)? != 0xff {
+                        return None;
+                    }
+                    let marker = *bytes.get(at + 1)?;
+                    if marker == 0xff {
+                        // Fill byte
+                        at += 1;
+                        continue;
+                    }
+                    if (0xc0..=0xcf).contains(&marker) && !matches!(marker, 0xc4 | 0xc8 | 0xcc) {
+                        return Some((be16(at + 7)?, be16(at + 5)?));
+                    }
+                    at += 2 + be16(at + 2)? as usize;
+                }
+            }
+            ImageFormat::Webp => match bytes.get(12..16)? {
+                // Lossy
+                b"VP8 " => Some((le16(26)? & 0x3fff, le16(28)? & 0x3fff)),
+                // Lossless, 14 bits each for width and height minus one
+                b"VP8L" => {
+                    let bits = le32(21)?;
+                    Some(((bits & 0x3fff) + 1, ((bits >> 14) & 0x3fff) + 1))
+                }
+                // Extended, 24 bits each for width and height minus one
+                b"VP8X" => {
+                    let le24 = |at: usize| {
+                        let [a, b, c] = bytes.get(at..at + 3)?.try_into().ok()?;
+                        Some(u32::from_le_bytes([a, b, c, 0]))
+                    };
+                    Some((le24(24)? + 1, le24(27)? + 1))
+                }
+                _ => None,
+            },
+        }
+    }
+
+    /// Lowercase hex SHA-256 hash of the bytes, e.g. to find duplicate images.
+    #[cfg(feature = "client")]
+    pub fn sha256(&self) -> String {
+        use sha2::{Digest, Sha256};
+        format!("{:x}", Sha256::digest(&self.bytes))
+    }
+
+    /// Save the image to `file_path`, creating its directory. Fails when the extension of
+    /// `file_path` names another [ImageFormat], as the bytes are written as they are:
+    /// converting between formats takes an image codec, which this crate doesn't depend on.
+    #[cfg(feature = "client")]
+    pub async fn save<P: AsRef<Path>>(&self, file_path: P) -> Result<(), OpenAIError> {
+        let file_path = file_path.as_ref();
+        let requested = file_path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(ImageFormat::from_extension);
+        if let (Some(requested), Some(format)) = (requested, self.format) {
+            if requested != format {
+                return Err(OpenAIError::InvalidArgument(format!(
+                    "can't save a {format} image as {requested}, converting images isn't supported"
+                )));
+            }
+        }
+
+        if let Some(dir) = file_path.parent() {
+            create_all_dir(dir)?;
+        }
+
+        tokio::fs::write(file_path, &self.bytes).await.map_err(|e| {
+            OpenAIError::FileSaveError(format!("{}, path: {}", e, file_path.display()))
+        })
+    }
+}
+
 macro_rules! impl_from_for_integer_array {
     ($from_typ:ty, $to_typ:ty) => {
         impl<const N: usize> From<[$from_typ; N]> for $to_typ {
@@ -515,6 +697,7 @@ impl From<&str> for ChatCompletionToolChoiceOption {
         match value {
             "auto" => Self::Auto,
             "none" => Self::None,
+            "required" => Self::Required,
             _ => Self::Named(value.into()),
         }
     }
@@ -523,13 +706,73 @@ impl From<&str> for ChatCompletionToolChoiceOption {
 impl From<String> for ChatCompletionToolChoiceOption {
     fn from(value: String) -> Self {
         match value.as_str() {
-            "auto" => Self::Auto,
-            "none" => Self::None,
+            "auto" | "none" | "required" => value.as_str().into(),
             _ => Self::Named(value.into()),
         }
     }
 }
 
+impl ChatCompletionToolChoiceOption {
+    /// Make the model call the function `name`.
+    pub fn function(name: impl Into<String>) -> Self {
+        Self::Named(name.into().into())
+    }
+
+    /// Let the model call only the functions `names` of the tools of the request.
+    pub fn allowed_tools<I, S>(names: I, mode: ChatCompletionAllowedToolsMode) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self::AllowedTools(ChatCompletionAllowedToolsChoice {
+            r#type: ChatCompletionAllowedToolsType::AllowedTools,
+            allowed_tools: ChatCompletionAllowedTools {
+                mode,
+                tools: names.into_iter().map(|name| name.into().into()).collect(),
+            },
+        })
+    }
+
+    /// Check that the choice can be met with `tools`: the functions it names are among
+    /// them, and there are tools to require.
+    pub fn check_against(&self, tools: Option<&[ChatCompletionTool]>) -> Result<(), OpenAIError> {
+        let tools = tools.unwrap_or_default();
+        let names: Vec<&str> = match self {
+            Self::None | Self::Auto => return Ok(()),
+            Self::Required => vec![],
+            Self::Named(choice) => vec![choice.function.name.as_str()],
+            Self::AllowedTools(choice) => choice
+                .allowed_tools
+                .tools
+                .iter()
+                .map(|tool| tool.function.name.as_str())
+                .collect(),
+        };
+
+        if tools.is_empty() {
+            return Err(OpenAIError::InvalidArgument(
+                "tool_choice requires a tool call but the request has no tools".to_string(),
+            ));
+        }
+        let known: Vec<&str> = tools
+            .iter()
+            .map(|tool| tool.function.name.as_str())
+            .collect();
+        let unknown: Vec<&str> = names
+            .into_iter()
+            .filter(|name| !known.contains(name))
+            .collect();
+        if !unknown.is_empty() {
+            return Err(OpenAIError::InvalidArgument(format!(
+                "tool_choice names {}, which the tools of the request ({}) don't define",
+                unknown.join(", "),
+                known.join(", ")
+            )));
+        }
+        Ok(())
+    }
+}
+
 impl From<(String, serde_json::Value)> for ChatCompletionFunctions {
     fn from(value: (String, serde_json::Value)) -> Self {
         Self {
@@ -578,6 +821,128 @@ impl From<ChatCompletionRequestToolMessage> for ChatCompletionRequestMessage {
     }
 }
 
+impl ChatCompletionRequestMessage {
+    /// Text content of the message, with text parts joined by newlines.
+    /// Non-text parts (images, audio) and tool calls are not included.
+    pub fn text(&self) -> String {
+        match self {
+            Self::Developer(message) => match &message.content {
+                ChatCompletionRequestDeveloperMessageContent::Text(text) => text.clone(),
+                ChatCompletionRequestDeveloperMessageContent::Array(parts) => parts
+                    .iter()
+                    .map(|part| part.text.as_str())
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            },
+            Self::System(message) => match &message.content {
+                ChatCompletionRequestSystemMessageContent::Text(text) => text.clone(),
+                ChatCompletionRequestSystemMessageContent::Array(parts) => parts
+                    .iter()
+                    .map(
+                        |ChatCompletionRequestSystemMessageContentPart::Text(part)| {
+                            part.text.as_str()
+                        },
+                    )
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            },
+            Self::User(message) => match &message.content {
+                ChatCompletionRequestUserMessageContent::Text(text) => text.clone(),
+                ChatCompletionRequestUserMessageContent::Array(parts) => parts
+                    .iter()
+                    .filter_map(|part| match part {
+                        ChatCompletionRequestUserMessageContentPart::Text(part) => {
+                            Some(part.text.as_str())
+                        }
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            },
+            Self::Assistant(message) => match &message.content {
+                Some(ChatCompletionRequestAssistantMessageContent::Text(text)) => text.clone(),
+                Some(ChatCompletionRequestAssistantMessageContent::Array(parts)) => parts
+                    .iter()
+                    .map(|part| match part {
+                        ChatCompletionRequestAssistantMessageContentPart::Text(part) => {
+                            part.text.as_str()
+                        }
+                        ChatCompletionRequestAssistantMessageContentPart::Refusal(part) => {
+                            part.refusal.as_str()
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                None => String::new(),
+            },
+            Self::Tool(message) => match &message.content {
+                ChatCompletionRequestToolMessageContent::Text(text) => text.clone(),
+                ChatCompletionRequestToolMessageContent::Array(parts) => parts
+                    .iter()
+                    .map(|ChatCompletionRequestToolMessageContentPart::Text(part)| {
+                        part.text.as_str()
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            },
+            Self::Function(message) => message.content.clone().unwrap_or_default(),
+        }
+    }
+
+    /// Text content of the message, and the text of each text part, to be modified in place.
+    /// Refusals are not included.
+    pub(crate) fn texts_mut(&mut self) -> Vec<&mut String> {
+        match self {
+            Self::Developer(message) => match &mut message.content {
+                ChatCompletionRequestDeveloperMessageContent::Text(text) => vec![text],
+                ChatCompletionRequestDeveloperMessageContent::Array(parts) => {
+                    parts.iter_mut().map(|part| &mut part.text).collect()
+                }
+            },
+            Self::System(message) => match &mut message.content {
+                ChatCompletionRequestSystemMessageContent::Text(text) => vec![text],
+                ChatCompletionRequestSystemMessageContent::Array(parts) => parts
+                    .iter_mut()
+                    .map(|ChatCompletionRequestSystemMessageContentPart::Text(part)| &mut part.text)
+                    .collect(),
+            },
+            Self::User(message) => match &mut message.content {
+                ChatCompletionRequestUserMessageContent::Text(text) => vec![text],
+                ChatCompletionRequestUserMessageContent::Array(parts) => parts
+                    .iter_mut()
+                    .filter_map(|part| match part {
+                        ChatCompletionRequestUserMessageContentPart::Text(part) => {
+                            Some(&mut part.text)
+                        }
+                        _ => None,
+                    })
+                    .collect(),
+            },
+            Self::Assistant(message) => match &mut message.content {
+                Some(ChatCompletionRequestAssistantMessageContent::Text(text)) => vec![text],
+                Some(ChatCompletionRequestAssistantMessageContent::Array(parts)) => parts
+                    .iter_mut()
+                    .filter_map(|part| match part {
+                        ChatCompletionRequestAssistantMessageContentPart::Text(part) => {
+                            Some(&mut part.text)
+                        }
+                        ChatCompletionRequestAssistantMessageContentPart::Refusal(_) => None,
+                    })
+                    .collect(),
+                None => vec![],
+            },
+            Self::Tool(message) => match &mut message.content {
+                ChatCompletionRequestToolMessageContent::Text(text) => vec![text],
+                ChatCompletionRequestToolMessageContent::Array(parts) => parts
+                    .iter_mut()
+                    .map(|ChatCompletionRequestToolMessageContentPart::Text(part)| &mut part.text)
+                    .collect(),
+            },
+            Self::Function(message) => message.content.iter_mut().collect(),
+        }
+    }
+}
+
 impl From<ChatCompletionRequestUserMessageContent> for ChatCompletionRequestUserMessage {
     fn from(value: ChatCompletionRequestUserMessageContent) -> Self {
         Self {
@@ -828,6 +1193,7 @@ impl Default for ChatCompletionRequestToolMessageContent {
 
 // start: types to multipart from
 
+#[cfg(feature = "client")]
 impl AsyncTryFrom<CreateTranscriptionRequest> for reqwest::multipart::Form {
     type Error = OpenAIError;
 
@@ -864,6 +1230,7 @@ impl AsyncTryFrom<CreateTranscriptionRequest> for reqwest::multipart::Form {
     }
 }
 
+#[cfg(feature = "client")]
 impl AsyncTryFrom<CreateTranslationRequest> for reqwest::multipart::Form {
     type Error = OpenAIError;
 
@@ -889,6 +1256,7 @@ impl AsyncTryFrom<CreateTranslationRequest> for reqwest::multipart::Form {
     }
 }
 
+#[cfg(feature = "client")]
 impl AsyncTryFrom<CreateImageEditRequest> for reqwest::multipart::Form {
     type Error = OpenAIError;
 
@@ -930,6 +1298,7 @@ impl AsyncTryFrom<CreateImageEditRequest> for reqwest::multipart::Form {
     }
 }
 
+#[cfg(feature = "client")]
 impl AsyncTryFrom<CreateImageVariationRequest> for reqwest::multipart::Form {
     type Error = OpenAIError;
 
@@ -964,6 +1333,7 @@ impl AsyncTryFrom<CreateImageVariationRequest> for reqwest::multipart::Form {
     }
 }
 
+#[cfg(feature = "client")]
 impl AsyncTryFrom<CreateFileRequest> for reqwest::multipart::Form {
     type Error = OpenAIError;
 
@@ -976,6 +1346,7 @@ impl AsyncTryFrom<CreateFileRequest> for reqwest::multipart::Form {
     }
 }
 
+#[cfg(feature = "client")]
 impl AsyncTryFrom<AddUploadPartRequest> for reqwest::multipart::Form {
     type Error = OpenAIError;
 
@@ -987,3 +1358,136 @@ impl AsyncTryFrom<AddUploadPartRequest> for reqwest::multipart::Form {
 }
 
 // end: types to multipart form
+
+impl FinishReason {
+    /// The value sent by the API, e.g. `tool_calls`.
+    pub fn as_str(&self) -> &str {
+        match self {
+            FinishReason::Stop => "stop",
+            FinishReason::Length => "length",
+            FinishReason::ToolCalls => "tool_calls",
+            FinishReason::ContentFilter => "content_filter",
+            FinishReason::FunctionCall => "function_call",
+            FinishReason::Other(reason) => reason,
+        }
+    }
+
+    /// Output was cut off by the token limit, so it is likely incomplete: retrying with a
+    /// larger limit or asking the model to continue helps, fixing the content does not.
+    pub fn was_truncated(&self) -> bool {
+        matches!(self, FinishReason::Length)
+    }
+
+    /// The model finished on its own.
+    pub fn is_stop(&self) -> bool {
+        matches!(self, FinishReason::Stop)
+    }
+
+    /// The model stopped to call tools, or a function with the deprecated `function_call`.
+    pub fn is_tool_call(&self) -> bool {
+        matches!(self, FinishReason::ToolCalls | FinishReason::FunctionCall)
+    }
+
+    /// Content was omitted by a content filter; retrying the same request rarely helps.
+    pub fn is_content_filtered(&self) -> bool {
+        matches!(self, FinishReason::ContentFilter)
+    }
+}
+
+impl Display for FinishReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl ChatChoice {
+    /// See [FinishReason::was_truncated].
+    pub fn was_truncated(&self) -> bool {
+        self.finish_reason
+            .as_ref()
+            .is_some_and(FinishReason::was_truncated)
+    }
+}
+
+impl ChatChoiceStream {
+    /// See [FinishReason::was_truncated]; only the last chunk of a choice has a finish reason.
+    pub fn was_truncated(&self) -> bool {
+        self.finish_reason
+            .as_ref()
+            .is_some_and(FinishReason::was_truncated)
+    }
+}
+
+impl Choice {
+    /// See [FinishReason::was_truncated].
+    pub fn was_truncated(&self) -> bool {
+        self.finish_reason
+            .as_ref()
+            .is_some_and(FinishReason::was_truncated)
+    }
+}
+
+impl CreateChatCompletionResponse {
+    /// Whether any choice was cut off by the token limit.
+    pub fn was_truncated(&self) -> bool {
+        self.choices.iter().any(ChatChoice::was_truncated)
+    }
+}
+
+impl CreateCompletionResponse {
+    /// Whether any choice was cut off by the token limit.
+    pub fn was_truncated(&self) -> bool {
+        self.choices.iter().any(Choice::was_truncated)
+    }
+}
+
+impl ContentFilterResults {
+    /// Whether any category, detection or blocklist filtered the content.
+    pub fn is_filtered(&self) -> bool {
+        [&self.hate, &self.sexual, &self.violence, &self.self_harm]
+            .into_iter()
+            .flatten()
+            .any(|result| result.filtered)
+            || [
+                &self.profanity,
+                &self.jailbreak,
+                &self.indirect_attack,
+                &self.protected_material_text,
+            ]
+            .into_iter()
+            .flatten()
+            .any(|result| result.filtered)
+            || self
+                .protected_material_code
+                .as_ref()
+                .is_some_and(|result| result.filtered)
+            || self
+                .custom_blocklists
+                .as_ref()
+                .is_some_and(|result| result.filtered)
+    }
+
+    /// Highest severity of the `hate`, `sexual`, `violence` and `self_harm` categories.
+    pub fn max_severity(&self) -> Option<ContentFilterSeverity> {
+        [&self.hate, &self.sexual, &self.violence, &self.self_harm]
+            .into_iter()
+            .flatten()
+            .map(|result| result.severity)
+            .max()
+    }
+}
+
+impl Category {
+    /// Names of the flagged categories as in the API, e.g. `hate/threatening`, in alphabetical
+    /// order.
+    pub fn flagged(&self) -> Vec<String> {
+        let Ok(serde_json::Value::Object(categories)) = serde_json::to_value(self) else {
+            return Vec::new();
+        };
+        categories
+            .into_iter()
+            .filter(|(_, flagged)| flagged.as_bool() == Some(true))
+            .map(|(name, _)| name)
+            .collect()
+    }
+}
@@ -1,5 +1,11 @@
 //! Types used in OpenAI API requests and responses.
 //! These types are created from component schemas in the [OpenAPI spec](https://github.com/openai/openai-openapi)
+//!
+//! With the `extra-fields` feature, the response types of chat completions, completions and
+//! embeddings keep the fields they don't declare, such as extensions of other providers, in
+//! their `extra` map. Serializing such a response again then gives back every field received.
+//! The `extra` field exists without the feature too, and stays empty, so that enabling it
+//! anywhere in a dependency graph doesn't break code building these types.
 mod assistant;
 mod assistant_impls;
 mod assistant_stream;
@@ -9,13 +15,17 @@ mod batch;
 mod chat;
 mod common;
 mod completion;
+mod content_filter;
 mod embedding;
+#[cfg(feature = "compact-embeddings")]
+mod embedding_compact;
 mod file;
 mod fine_tuning;
 mod image;
 mod invites;
 mod message;
 mod model;
+mod model_name;
 mod moderation;
 mod project_api_key;
 mod project_service_account;
@@ -29,6 +39,7 @@ mod step;
 pub mod structured;
 mod thread;
 mod upload;
+mod usage;
 mod users;
 mod vector_store;
 
@@ -40,13 +51,17 @@ pub use batch::*;
 pub use chat::*;
 pub use common::*;
 pub use completion::*;
+pub use content_filter::*;
 pub use embedding::*;
+#[cfg(feature = "compact-embeddings")]
+pub use embedding_compact::*;
 pub use file::*;
 pub use fine_tuning::*;
 pub use image::*;
 pub use invites::*;
 pub use message::*;
 pub use model::*;
+pub use model_name::*;
 pub use moderation::*;
 pub use project_api_key::*;
 pub use project_service_account::*;
@@ -57,6 +72,7 @@ pub use step::*;
 pub use structured::*;
 pub use thread::*;
 pub use upload::*;
+pub use usage::*;
 pub use users::*;
 pub use vector_store::*;
 
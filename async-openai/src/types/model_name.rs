@@ -0,0 +1,181 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+macro_rules! model_names {
+    ($($variant:ident => $id:literal,)*) => {
+        /// Well known model ids, checked at compile time.
+        ///
+        /// Accepted wherever a model id is expected, as builders take `impl Into<String>`:
+        ///
+        /// ```
+        /// use async_openai::types::{CreateChatCompletionRequestArgs, ModelName};
+        ///
+        /// let request = CreateChatCompletionRequestArgs::default()
+        ///     .model(ModelName::Gpt4oMini)
+        ///     .messages(vec![])
+        ///     .build()
+        ///     .unwrap();
+        /// assert_eq!(request.model, "gpt-4o-mini");
+        /// ```
+        ///
+        /// Any other id, such as a fine-tuned model or a deployment name, is expressed
+        /// with [ModelName::Custom].
+        ///
+        /// Named `ModelName` as [Model](super::Model) is the object the models API describes a model with.
+        #[derive(Debug, Clone)]
+        #[non_exhaustive]
+        pub enum ModelName {
+            $(#[doc = concat!("`", $id, "`")] $variant,)*
+            /// A model id not listed above.
+            Custom(String),
+        }
+
+        impl ModelName {
+            /// Every listed model, without [ModelName::Custom].
+            pub const KNOWN: &'static [ModelName] = &[$(ModelName::$variant,)*];
+
+            /// The model id sent to the API.
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $(ModelName::$variant => $id,)*
+                    ModelName::Custom(id) => id,
+                }
+            }
+        }
+
+        impl From<&str> for ModelName {
+            fn from(id: &str) -> Self {
+                match id {
+                    $($id => ModelName::$variant,)*
+                    _ => ModelName::Custom(id.to_string()),
+                }
+            }
+        }
+    };
+}
+
+model_names! {
+    // OpenAI chat and reasoning models
+    Gpt4o => "gpt-4o",
+    Gpt4oMini => "gpt-4o-mini",
+    Gpt4oAudioPreview => "gpt-4o-audio-preview",
+    Gpt4oRealtimePreview => "gpt-4o-realtime-preview",
+    Gpt4oMiniRealtimePreview => "gpt-4o-mini-realtime-preview",
+    ChatGpt4oLatest => "chatgpt-4o-latest",
+    Gpt41 => "gpt-4.1",
+    Gpt41Mini => "gpt-4.1-mini",
+    Gpt41Nano => "gpt-4.1-nano",
+    Gpt4Turbo => "gpt-4-turbo",
+    Gpt4 => "gpt-4",
+    Gpt35Turbo => "gpt-3.5-turbo",
+    Gpt35TurboInstruct => "gpt-3.5-turbo-instruct",
+    O1 => "o1",
+    O1Mini => "o1-mini",
+    O3 => "o3",
+    O3Mini => "o3-mini",
+    O4Mini => "o4-mini",
+    // OpenAI embedding, moderation, audio and image models
+    TextEmbedding3Small => "text-embedding-3-small",
+    TextEmbedding3Large => "text-embedding-3-large",
+    TextEmbeddingAda002 => "text-embedding-ada-002",
+    OmniModerationLatest => "omni-moderation-latest",
+    TextModerationLatest => "text-moderation-latest",
+    Whisper1 => "whisper-1",
+    Gpt4oTranscribe => "gpt-4o-transcribe",
+    Gpt4oMiniTranscribe => "gpt-4o-mini-transcribe",
+    Tts1 => "tts-1",
+    Tts1Hd => "tts-1-hd",
+    Gpt4oMiniTts => "gpt-4o-mini-tts",
+    DallE2 => "dall-e-2",
+    DallE3 => "dall-e-3",
+    GptImage1 => "gpt-image-1",
+    // DeepSeek API
+    DeepSeekChat => "deepseek-chat",
+    DeepSeekReasoner => "deepseek-reasoner",
+    // Open weight models, by their Hugging Face ids as served by vLLM, TGI and most hosts
+    Llama33_70bInstruct => "meta-llama/Llama-3.3-70B-Instruct",
+    Llama31_8bInstruct => "meta-llama/Llama-3.1-8B-Instruct",
+    Qwen25_72bInstruct => "Qwen/Qwen2.5-72B-Instruct",
+    Qwen25Coder32bInstruct => "Qwen/Qwen2.5-Coder-32B-Instruct",
+    Mistral7bInstruct => "mistralai/Mistral-7B-Instruct-v0.3",
+    Mixtral8x7bInstruct => "mistralai/Mixtral-8x7B-Instruct-v0.1",
+    DeepSeekR1 => "deepseek-ai/DeepSeek-R1",
+    DeepSeekV3 => "deepseek-ai/DeepSeek-V3",
+    Gemma2_27bIt => "google/gemma-2-27b-it",
+}
+
+impl From<String> for ModelName {
+    fn from(id: String) -> Self {
+        match ModelName::from(id.as_str()) {
+            ModelName::Custom(_) => ModelName::Custom(id),
+            known => known,
+        }
+    }
+}
+
+impl From<ModelName> for String {
+    fn from(model: ModelName) -> Self {
+        match model {
+            ModelName::Custom(id) => id,
+            known => known.as_str().to_string(),
+        }
+    }
+}
+
+impl From<&ModelName> for String {
+    fn from(model: &ModelName) -> Self {
+        model.as_str().to_string()
+    }
+}
+
+impl AsRef<str> for ModelName {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl Display for ModelName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// Compared by id, so that a `Custom` holding a listed id equals its variant
+impl PartialEq for ModelName {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for ModelName {}
+
+impl std::hash::Hash for ModelName {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl PartialEq<str> for ModelName {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for ModelName {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl Serialize for ModelName {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for ModelName {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(ModelName::from)
+    }
+}
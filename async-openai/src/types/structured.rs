@@ -1,7 +1,12 @@
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::marker::PhantomData;
+use std::sync::Arc;
 use indexmap::IndexMap;
 
+use crate::structured::{sanitize::Sanitizer, CustomFormat, Format};
+
 #[allow(unused_imports)]
 use schemars::{schema_for, JsonSchema};
 
@@ -11,6 +16,15 @@ use schemars::{schema_for, JsonSchema};
 /// It requires the type to be serializable, deserializable, cloneable, and debuggable
 pub trait Structured: Clone + std::fmt::Debug + Serialize {}
 
+/// Field descriptions of a type, usually derived with
+/// [`#[derive(StructuredOutput)]`](crate::structured::StructuredOutput) from `#[describe("...")]`
+/// attributes or doc comments, and applied with
+/// [crate::structured::Generator::with_field_descriptions]
+pub trait StructuredOutput {
+    /// `(field name, description)` pairs in declaration order
+    fn field_descriptions() -> Vec<(&'static str, &'static str)>;
+}
+
 // Implement the trait for all types that meet the requirements
 impl<T> Structured for T where T: Clone + std::fmt::Debug + Serialize {}
 
@@ -27,6 +41,20 @@ pub enum OutputFormat {
     /// XML format (requires xml feature)
     #[cfg(feature = "xml")]
     Xml,
+    /// TOML format (requires toml feature)
+    #[cfg(feature = "toml")]
+    Toml,
+    /// CSV table with a header row, for lists of flat objects (requires csv feature)
+    #[cfg(feature = "csv")]
+    Csv,
+    /// Protobuf text format, with the schema given as a `.proto` message (requires protobuf
+    /// feature)
+    #[cfg(feature = "protobuf")]
+    TextProto,
+    /// GitHub-style markdown table, for lists of flat objects
+    MarkdownTable,
+    /// The [Format](crate::structured::Format) set with [Config::custom_format]
+    Custom,
 }
 
 impl Default for OutputFormat {
@@ -35,19 +63,853 @@ impl Default for OutputFormat {
     }
 }
 
+impl OutputFormat {
+    /// Guess the format of a model response from the language of its first code fence, or
+    /// from the leading characters of its content.
+    ///
+    /// Formats whose feature is disabled are never detected; JSON is the fallback.
+    pub fn detect(response: &str) -> Self {
+        let (language, body) = match response.find("```") {
+            Some(start) => {
+                let fenced = &response[start + 3..];
+                let (language, rest) = fenced.split_once('\n').unwrap_or(("", fenced));
+                (language.trim().to_ascii_lowercase(), rest)
+            }
+            None => (String::new(), response),
+        };
+        let body = body.trim_start();
+
+        match language.as_str() {
+            #[cfg(feature = "yaml")]
+            "yaml" | "yml" => return OutputFormat::Yaml,
+            #[cfg(feature = "xml")]
+            "xml" => return OutputFormat::Xml,
+            #[cfg(feature = "toml")]
+            "toml" => return OutputFormat::Toml,
+            #[cfg(feature = "csv")]
+            "csv" => return OutputFormat::Csv,
+            #[cfg(feature = "protobuf")]
+            "textproto" | "pbtxt" | "prototext" => return OutputFormat::TextProto,
+            _ => {}
+        }
+
+        if body.starts_with('[') {
+            return OutputFormat::JsonArray;
+        }
+        if body.starts_with('{') || language.starts_with("json") {
+            return OutputFormat::Json;
+        }
+        #[cfg(feature = "xml")]
+        if body.starts_with('<') {
+            return OutputFormat::Xml;
+        }
+        // Tables are often introduced by a sentence
+        if Self::contains_markdown_table(body) {
+            return OutputFormat::MarkdownTable;
+        }
+        #[cfg(feature = "toml")]
+        if Self::looks_like_toml_pair(body) {
+            return OutputFormat::Toml;
+        }
+        #[cfg(feature = "yaml")]
+        if body.starts_with("---") || Self::looks_like_yaml_mapping(body) {
+            return OutputFormat::Yaml;
+        }
+        OutputFormat::Json
+    }
+
+    /// Whether the first line is a `key: value` pair or a `key:` opening a nested mapping.
+    #[cfg(feature = "yaml")]
+    fn looks_like_yaml_mapping(body: &str) -> bool {
+        let line = body.lines().next().unwrap_or_default();
+        line.split_once(':').is_some_and(|(key, value)| {
+            let key = key.trim_start_matches("- ").trim_matches(|c| c == '"' || c == '\'');
+            !key.is_empty()
+                && key.chars().all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | ' '))
+                && (value.is_empty() || value.starts_with(' '))
+        })
+    }
+
+    /// Whether a line with pipes is followed by a `| --- |` separator row.
+    fn contains_markdown_table(body: &str) -> bool {
+        let lines: Vec<&str> = body.lines().map(str::trim).collect();
+        lines.windows(2).any(|pair| {
+            pair[0].starts_with('|')
+                && pair[1].starts_with('|')
+                && pair[1].contains('-')
+                && pair[1].chars().all(|c| matches!(c, '|' | '-' | ':' | ' ' | '\t'))
+        })
+    }
+
+    /// Whether the first line is a `key = value` pair.
+    #[cfg(feature = "toml")]
+    fn looks_like_toml_pair(body: &str) -> bool {
+        let line = body.lines().next().unwrap_or_default();
+        line.split_once(" = ").is_some_and(|(key, _)| {
+            let key = key.trim_matches('"');
+            !key.is_empty()
+                && key.chars().all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'))
+        })
+    }
+}
+
+/// Where the JSON Schema block of an instruction comes from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SchemaSource {
+    /// The schemars schema of the type, with `required`, `enum`, `format` and the
+    /// descriptions of its doc comments
+    #[default]
+    JsonSchema,
+    /// Types inferred from the serialized example
+    Example,
+}
+
+/// How the expected structure is presented in an instruction
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SchemaDialect {
+    /// A JSON Schema block
+    #[default]
+    JsonSchema,
+    /// TypeScript type declarations, shorter than JSON Schema and familiar to models from
+    /// tool definitions
+    TypeScript,
+}
+
+/// How the JSON answer is located in a response with other text around it
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum ExtractionStrategy {
+    /// The content of the first code fence, or the whole response without one
+    FirstCodeFence,
+    /// The content of the last code fence, or the whole response without one
+    LastCodeFence,
+    /// The longest top-level JSON object or array of the response
+    LargestJsonValue,
+    /// The first object or array whose braces balance and which is valid JSON, ignoring
+    /// braces in strings. Survives commentary before and after the answer, with or
+    /// without a code fence
+    #[default]
+    BalancedBraceScan,
+    /// A function picking the answer out of the response, the first code fence being used
+    /// when it returns `None`. Not serialized
+    #[serde(skip)]
+    Custom(fn(&str) -> Option<&str>),
+}
+
+/// Reasoning removed from a response before its answer is extracted, for models that think
+/// out loud in the answer, such as DeepSeek-R1 and QwQ
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReasoningFilter {
+    /// Keep the whole response
+    #[default]
+    Off,
+    /// Strip the reasoning of the common reasoning models: `<think>`, `<thinking>`,
+    /// `<reasoning>` and `<reflection>` sections, the text before a `</think>` whose opening
+    /// tag was part of the prompt, and the analysis channel of gpt-oss. Don't use it with XML
+    /// answers that have elements with these names
+    BuiltIn,
+    /// Strip the built-in sections, then the matches of these regular expressions, e.g.
+    /// `(?s)^Reasoning:.*?\n\n` for a reasoning prefix
+    Patterns(Vec<String>),
+}
+
+impl PartialEq for ExtractionStrategy {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            // Same function, as far as addresses tell
+            (Self::Custom(a), Self::Custom(b)) => *a as usize == *b as usize,
+            _ => std::mem::discriminant(self) == std::mem::discriminant(other),
+        }
+    }
+}
+
+/// How much a validation issue matters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Worth knowing, the data is usable as is
+    Info,
+    /// The data is usable but imperfect
+    Warning,
+    /// The data is not usable. Fails the parse when
+    /// [ValidationOptions::require_all_required_properties] is set
+    Error,
+}
+
+/// A schema violation found by validation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidationIssue {
+    /// JSON pointer of the invalid value, empty for the root
+    pub path: String,
+    /// JSON Schema keyword that was violated, such as `type`, `required` or
+    /// `additionalProperties`
+    pub keyword: String,
+    /// The message, prefixed with the path like those of [Response::validation_messages]
+    pub message: String,
+    pub severity: Severity,
+}
+
 /// Configuration for validating structured data
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ValidationOptions {
-    /// Whether all required properties must be present
+    /// Whether all required properties must be present. When set, any validation error of
+    /// [Severity::Error] fails the parse, otherwise the errors are returned in
+    /// [Response::validation_messages]
     pub require_all_required_properties: bool,
+    /// Whether objects may have properties their schema doesn't list
+    pub allow_additional_properties: bool,
+    /// Whether strings holding a number, such as `"42"`, are accepted where the schema
+    /// expects a number
+    pub coerce_numeric_strings: bool,
+    /// Maximum number of validation errors reported, all of them if `None`
+    pub max_errors: Option<usize>,
+    /// Severity of violations of a JSON Schema keyword, e.g. `additionalProperties` or
+    /// `format`. Violations of other keywords have [ValidationOptions::default_severity]
+    pub severities: BTreeMap<String, Severity>,
+    /// Severity of violations of keywords without one in [ValidationOptions::severities]
+    pub default_severity: Severity,
 }
 
 impl Default for ValidationOptions {
     fn default() -> Self {
         Self {
             require_all_required_properties: true,
+            allow_additional_properties: true,
+            coerce_numeric_strings: false,
+            max_errors: None,
+            severities: BTreeMap::new(),
+            default_severity: Severity::Error,
+        }
+    }
+}
+
+impl ValidationOptions {
+    /// Set whether all required properties must be present
+    pub fn require_all_required_properties(mut self, require: bool) -> Self {
+        self.require_all_required_properties = require;
+        self
+    }
+
+    /// Set whether objects may have properties their schema doesn't list
+    pub fn allow_additional_properties(mut self, allow: bool) -> Self {
+        self.allow_additional_properties = allow;
+        self
+    }
+
+    /// Set whether strings holding a number are accepted where a number is expected
+    pub fn coerce_numeric_strings(mut self, coerce: bool) -> Self {
+        self.coerce_numeric_strings = coerce;
+        self
+    }
+
+    /// Set the maximum number of validation errors reported
+    pub fn max_errors(mut self, max_errors: usize) -> Self {
+        self.max_errors = Some(max_errors);
+        self
+    }
+
+    /// Set the severity of violations of the JSON Schema `keyword`, e.g. to accept unknown
+    /// fields with `severity("additionalProperties", Severity::Warning)` while a wrong type
+    /// still fails the parse
+    pub fn severity(mut self, keyword: impl Into<String>, severity: Severity) -> Self {
+        self.severities.insert(keyword.into(), severity);
+        self
+    }
+
+    /// Set the severity of violations of keywords without their own
+    pub fn default_severity(mut self, severity: Severity) -> Self {
+        self.default_severity = severity;
+        self
+    }
+
+    /// Severity of violations of `keyword`
+    pub fn severity_of(&self, keyword: &str) -> Severity {
+        self.severities
+            .get(keyword)
+            .copied()
+            .unwrap_or(self.default_severity)
+    }
+}
+
+/// Where a field goes in XML output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum XmlNode {
+    /// A child element, `<id>1</id>`
+    #[default]
+    Element,
+    /// An attribute of the element of the object, `<item id="1">`
+    Attribute,
+}
+
+/// How data maps to XML (requires xml feature)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct XmlMapping {
+    /// Name of the root element shown in the instruction
+    pub root: String,
+    /// Node of each field by name, at any depth. Fields not listed are elements
+    pub fields: BTreeMap<String, XmlNode>,
+    /// Whether namespace prefixes and `xmlns` declarations are removed from the response
+    /// before it is parsed
+    pub strip_namespaces: bool,
+}
+
+impl Default for XmlMapping {
+    fn default() -> Self {
+        Self {
+            root: "root".to_string(),
+            fields: BTreeMap::new(),
+            strip_namespaces: true,
+        }
+    }
+}
+
+impl XmlMapping {
+    /// Set the name of the root element
+    pub fn root(mut self, root: impl Into<String>) -> Self {
+        self.root = root.into();
+        self
+    }
+
+    /// Write `field` as an attribute
+    pub fn attribute(mut self, field: impl Into<String>) -> Self {
+        self.fields.insert(field.into(), XmlNode::Attribute);
+        self
+    }
+
+    /// Set whether namespaces are removed before parsing
+    pub fn strip_namespaces(mut self, strip: bool) -> Self {
+        self.strip_namespaces = strip;
+        self
+    }
+
+    /// Node of `field`
+    pub fn node(&self, field: &str) -> XmlNode {
+        self.fields.get(field).copied().unwrap_or_default()
+    }
+}
+
+/// Number of items and uniqueness of an array output, asked for in the instruction and
+/// checked when the response is parsed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ArrayConstraints {
+    /// Fewest items accepted
+    pub min_items: usize,
+    /// Most items accepted
+    pub max_items: usize,
+    /// Field whose value no two items may share, e.g. `id` or `author.name`
+    pub unique_by: Option<String>,
+    /// Whether duplicates and the items past `max_items` are dropped instead of failing the
+    /// response. Too few items always fail it
+    pub trim: bool,
+}
+
+impl Default for ArrayConstraints {
+    fn default() -> Self {
+        Self {
+            min_items: 0,
+            max_items: usize::MAX,
+            unique_by: None,
+            trim: false,
+        }
+    }
+}
+
+impl ArrayConstraints {
+    /// Between `min_items` and `max_items` items
+    pub fn new(min_items: usize, max_items: usize) -> Self {
+        Self {
+            min_items,
+            max_items,
+            ..Default::default()
         }
     }
+
+    /// Items must differ in `field`
+    pub fn unique_by(mut self, field: impl Into<String>) -> Self {
+        self.unique_by = Some(field.into());
+        self
+    }
+
+    /// Drop duplicates and extra items instead of failing
+    pub fn trim(mut self, trim: bool) -> Self {
+        self.trim = trim;
+        self
+    }
+}
+
+/// Region of an image, as fractions of the image size from its top left corner. A field of
+/// this type asks the model for the region a value was read from, see [Located]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[schemars(
+    title = "BoundingBox",
+    description = "Region of the image, as fractions of its size from the top left corner"
+)]
+pub struct BoundingBox {
+    #[schemars(range(min = 0, max = 1))]
+    pub x: f64,
+    #[schemars(range(min = 0, max = 1))]
+    pub y: f64,
+    #[schemars(range(min = 0, max = 1))]
+    pub width: f64,
+    #[schemars(range(min = 0, max = 1))]
+    pub height: f64,
+}
+
+impl BoundingBox {
+    /// Whether the region lies within the image
+    pub fn fits(&self) -> bool {
+        // Leeway for the rounding of the model
+        const EPSILON: f64 = 1e-6;
+        [self.x, self.y, self.width, self.height]
+            .iter()
+            .all(|value| (0.0..=1.0).contains(value))
+            && self.x + self.width <= 1.0 + EPSILON
+            && self.y + self.height <= 1.0 + EPSILON
+    }
+}
+
+/// A value read from an image, with the region it was read from, e.g. the total of a
+/// receipt. The instruction asks for the region of fields of this type, and validation
+/// reports regions reaching past the image under the `boundingBox` keyword
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "A value read from the image, with the region it was read from")]
+pub struct Located<T> {
+    pub value: T,
+    pub region: BoundingBox,
+}
+
+/// An input paired with the output expected for it, shown in the instruction as a
+/// demonstration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(bound(deserialize = "T: for<'a> Deserialize<'a>"))]
+pub struct FewShotExample<T> {
+    /// Input of the demonstration
+    pub input: String,
+    /// Expected output, rendered in the output format
+    pub output: T,
+}
+
+/// Language of the phrases of an instruction
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Locale {
+    /// English
+    #[default]
+    #[serde(rename = "en")]
+    English,
+    /// Simplified Chinese
+    #[serde(rename = "zh")]
+    Chinese,
+    /// Japanese
+    #[serde(rename = "ja")]
+    Japanese,
+    /// Spanish
+    #[serde(rename = "es")]
+    Spanish,
+    /// German
+    #[serde(rename = "de")]
+    German,
+}
+
+impl Locale {
+    /// Built-in phrases of the locale
+    pub fn template(self) -> &'static InstructionTemplate {
+        match self {
+            Locale::English => &InstructionTemplate::ENGLISH,
+            Locale::Chinese => &InstructionTemplate::CHINESE,
+            Locale::Japanese => &InstructionTemplate::JAPANESE,
+            Locale::Spanish => &InstructionTemplate::SPANISH,
+            Locale::German => &InstructionTemplate::GERMAN,
+        }
+    }
+}
+
+/// Phrases of the boilerplate of an instruction. `{format}` is replaced with the name of the
+/// output format and `{type}` with the type of array items.
+///
+/// Start from the phrases of a locale and override some of them:
+///
+/// ```
+/// use async_openai::types::structured::{InstructionTemplate, Locale};
+///
+/// let template = InstructionTemplate {
+///     example_format: "Answer like this:".into(),
+///     ..Locale::English.template().clone()
+/// };
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstructionTemplate {
+    /// Asks for the answer in a format, e.g. "Please return the response in {format} format."
+    pub return_in_format: Cow<'static, str>,
+    /// Asks for the answer as a JSON array
+    pub return_json_array: Cow<'static, str>,
+    /// Asks for the answer as a table with a header row in a `{format}`
+    pub return_table: Cow<'static, str>,
+    /// Introduces the example of the answer
+    pub example_format: Cow<'static, str>,
+    /// Introduces the field descriptions
+    pub response_should_include: Cow<'static, str>,
+    /// Describes a top level array of `{type}` items
+    pub array_of_items: Cow<'static, str>,
+    /// Introduces the fields of array items
+    pub each_item_should_have: Cow<'static, str>,
+    /// Describes an empty top level array
+    pub empty_array: Cow<'static, str>,
+    /// Introduces the JSON Schema block
+    pub json_schema_information: Cow<'static, str>,
+    /// Introduces the TypeScript type declarations
+    pub typescript_type: Cow<'static, str>,
+    /// Notes that the example is an array of objects
+    pub array_of_objects_note: Cow<'static, str>,
+    /// Notes that the example is an array of `{type}` values
+    pub array_of_values_note: Cow<'static, str>,
+    /// Notes that the example is an empty array
+    pub empty_array_note: Cow<'static, str>,
+    /// Asks for the items of an array in the `items` table of a TOML document
+    pub toml_items: Cow<'static, str>,
+    /// Asks for a table row per item
+    pub one_row_per_item: Cow<'static, str>,
+    /// Asks for a table with a single row
+    pub single_row: Cow<'static, str>,
+    /// How to write the cells of a CSV table
+    pub csv_cells: Cow<'static, str>,
+    /// How to write the cells of a markdown table
+    pub markdown_cells: Cow<'static, str>,
+    /// XML comment standing for an empty array
+    pub xml_empty_array: Cow<'static, str>,
+    /// XML comment standing for the items after the first one
+    pub xml_more_items: Cow<'static, str>,
+    /// Introduces the demonstrations
+    pub examples: Cow<'static, str>,
+    /// Introduces the input of a demonstration
+    pub example_input: Cow<'static, str>,
+    /// Introduces the output of a demonstration
+    pub example_output: Cow<'static, str>,
+    /// Lists the `{values}` allowed for a `{field}`
+    #[serde(default = "InstructionTemplate::default_one_of")]
+    pub one_of: Cow<'static, str>,
+    /// Lists the `{values}` allowed for the answer, or for the items of an array answer
+    #[serde(default = "InstructionTemplate::default_answer_one_of")]
+    pub answer_one_of: Cow<'static, str>,
+    /// Marks a field the JSON Schema requires
+    #[serde(default = "InstructionTemplate::default_required")]
+    pub required: Cow<'static, str>,
+    /// Marks a field the JSON Schema doesn't require
+    #[serde(default = "InstructionTemplate::default_optional")]
+    pub optional: Cow<'static, str>,
+    /// Introduces the problems of a response to repair, asking for the whole `{format}` answer again
+    #[serde(default = "InstructionTemplate::default_repair_intro")]
+    pub repair_intro: Cow<'static, str>,
+    /// Reports a missing `{field}`
+    #[serde(default = "InstructionTemplate::default_repair_missing")]
+    pub repair_missing: Cow<'static, str>,
+    /// Reports the validation `{error}` of a `{field}`
+    #[serde(default = "InstructionTemplate::default_repair_invalid")]
+    pub repair_invalid: Cow<'static, str>,
+    /// Reports that the response is not valid `{format}`, with the parser `{error}`
+    #[serde(default = "InstructionTemplate::default_repair_syntax")]
+    pub repair_syntax: Cow<'static, str>,
+    /// Shows the `{fragment}` of the response where parsing failed
+    #[serde(default = "InstructionTemplate::default_repair_near")]
+    pub repair_near: Cow<'static, str>,
+    /// Asks for the bounding box `{field}` of the image region a value was read from
+    #[serde(default = "InstructionTemplate::default_region")]
+    pub region: Cow<'static, str>,
+    /// Introduces the `.proto` message the protobuf text format answer is an instance of
+    #[serde(default = "InstructionTemplate::default_proto_message")]
+    pub proto_message: Cow<'static, str>,
+    /// Asks for between `{min}` and `{max}` items
+    #[serde(default = "InstructionTemplate::default_item_count")]
+    pub item_count: Cow<'static, str>,
+    /// Asks for items that differ in `{field}`
+    #[serde(default = "InstructionTemplate::default_unique_by")]
+    pub unique_by: Cow<'static, str>,
+    /// Describes a tuple answer of `{count}` positions, listed in `{items}`
+    #[serde(default = "InstructionTemplate::default_tuple_items")]
+    pub tuple_items: Cow<'static, str>,
+    /// Describes an answer that is a single `{type}` value
+    #[serde(default = "InstructionTemplate::default_single_value")]
+    pub single_value: Cow<'static, str>,
+}
+
+impl Default for InstructionTemplate {
+    fn default() -> Self {
+        Self::ENGLISH
+    }
+}
+
+impl InstructionTemplate {
+    /// English phrases
+    pub const ENGLISH: Self = Self {
+        return_in_format: Cow::Borrowed("Please return the response in {format} format."),
+        return_json_array: Cow::Borrowed("Please return the response as a JSON array of items."),
+        return_table: Cow::Borrowed("Please return the response as a {format} table with a header row."),
+        example_format: Cow::Borrowed("Example format:"),
+        response_should_include: Cow::Borrowed("The response should include:"),
+        array_of_items: Cow::Borrowed("An array of {type} items"),
+        each_item_should_have: Cow::Borrowed("Each item should have:"),
+        empty_array: Cow::Borrowed("An empty array"),
+        json_schema_information: Cow::Borrowed("JSON Schema information:"),
+        typescript_type: Cow::Borrowed("The response must match this TypeScript type:"),
+        array_of_objects_note: Cow::Borrowed("This is an array of objects. Each item should follow the above structure."),
+        array_of_values_note: Cow::Borrowed("This is an array of {type} values."),
+        empty_array_note: Cow::Borrowed("This is an empty array."),
+        toml_items: Cow::Borrowed("Put every item of the array in `items`."),
+        one_row_per_item: Cow::Borrowed("Write one row per item."),
+        single_row: Cow::Borrowed("Write a single row."),
+        csv_cells: Cow::Borrowed("Quote cells containing commas, and write nested values as JSON."),
+        markdown_cells: Cow::Borrowed("Escape pipes in cells as `\\|`, and write nested values as JSON."),
+        xml_empty_array: Cow::Borrowed("Empty array - no items"),
+        xml_more_items: Cow::Borrowed("Additional items here"),
+        examples: Cow::Borrowed("Examples:"),
+        example_input: Cow::Borrowed("Input:"),
+        example_output: Cow::Borrowed("Output:"),
+        one_of: Cow::Borrowed("{field} must be one of: {values}"),
+        answer_one_of: Cow::Borrowed("The answer must be one of: {values}"),
+        required: Cow::Borrowed("required"),
+        optional: Cow::Borrowed("optional"),
+        repair_intro: Cow::Borrowed("Your previous response could not be used. Fix these problems and answer again with the complete {format} output:"),
+        repair_missing: Cow::Borrowed("{field} is missing"),
+        repair_invalid: Cow::Borrowed("{field}: {error}"),
+        repair_syntax: Cow::Borrowed("The response is not valid {format}: {error}"),
+        repair_near: Cow::Borrowed("The problem is near: {fragment}"),
+        region: Cow::Borrowed("{field} is the region of the image the value was read from: x and y of its top left corner, width and height, as fractions of the image size from 0 to 1"),
+        proto_message: Cow::Borrowed("The response must be an instance of this protobuf message:"),
+        item_count: Cow::Borrowed("Return between {min} and {max} items."),
+        unique_by: Cow::Borrowed("No two items may have the same `{field}`."),
+        tuple_items: Cow::Borrowed("A {count}-element array: [{items}]"),
+        single_value: Cow::Borrowed("A single {type} value"),
+    };
+
+    /// Simplified Chinese phrases
+    pub const CHINESE: Self = Self {
+        return_in_format: Cow::Borrowed("请以 {format} 格式返回响应。"),
+        return_json_array: Cow::Borrowed("请以 JSON 数组的形式返回响应。"),
+        return_table: Cow::Borrowed("请以带表头行的 {format} 表格形式返回响应。"),
+        example_format: Cow::Borrowed("示例格式："),
+        response_should_include: Cow::Borrowed("响应应包含："),
+        array_of_items: Cow::Borrowed("由 {type} 类型元素组成的数组"),
+        each_item_should_have: Cow::Borrowed("每个元素应包含："),
+        empty_array: Cow::Borrowed("空数组"),
+        json_schema_information: Cow::Borrowed("JSON Schema 信息："),
+        typescript_type: Cow::Borrowed("响应必须符合以下 TypeScript 类型："),
+        array_of_objects_note: Cow::Borrowed("这是一个对象数组，每个元素都应遵循上述结构。"),
+        array_of_values_note: Cow::Borrowed("这是一个 {type} 类型值的数组。"),
+        empty_array_note: Cow::Borrowed("这是一个空数组。"),
+        toml_items: Cow::Borrowed("将数组的所有元素放在 `items` 中。"),
+        one_row_per_item: Cow::Borrowed("每个元素写一行。"),
+        single_row: Cow::Borrowed("只写一行。"),
+        csv_cells: Cow::Borrowed("包含逗号的单元格需加引号，嵌套值以 JSON 书写。"),
+        markdown_cells: Cow::Borrowed("单元格中的竖线需转义为 `\\|`，嵌套值以 JSON 书写。"),
+        xml_empty_array: Cow::Borrowed("空数组 - 没有元素"),
+        xml_more_items: Cow::Borrowed("此处为更多元素"),
+        examples: Cow::Borrowed("示例："),
+        example_input: Cow::Borrowed("输入："),
+        example_output: Cow::Borrowed("输出："),
+        one_of: Cow::Borrowed("{field} 必须是以下值之一：{values}"),
+        answer_one_of: Cow::Borrowed("答案必须是以下值之一：{values}"),
+        required: Cow::Borrowed("必填"),
+        optional: Cow::Borrowed("可选"),
+        repair_intro: Cow::Borrowed("你之前的响应无法使用。请修正以下问题，并重新给出完整的 {format} 输出："),
+        repair_missing: Cow::Borrowed("缺少 {field}"),
+        repair_invalid: Cow::Borrowed("{field}：{error}"),
+        repair_syntax: Cow::Borrowed("响应不是有效的 {format}：{error}"),
+        repair_near: Cow::Borrowed("问题出现在此处附近：{fragment}"),
+        region: Cow::Borrowed("{field} 是读取该值的图像区域：左上角的 x 和 y 以及宽度和高度，均为 0 到 1 之间相对于图像尺寸的比例"),
+        proto_message: Cow::Borrowed("响应必须是以下 protobuf 消息的实例："),
+        item_count: Cow::Borrowed("返回 {min} 到 {max} 个元素。"),
+        unique_by: Cow::Borrowed("任意两个元素的 `{field}` 不得相同。"),
+        tuple_items: Cow::Borrowed("包含 {count} 个元素的数组：[{items}]"),
+        single_value: Cow::Borrowed("单个 {type} 类型的值"),
+    };
+
+    /// Japanese phrases
+    pub const JAPANESE: Self = Self {
+        return_in_format: Cow::Borrowed("レスポンスは {format} 形式で返してください。"),
+        return_json_array: Cow::Borrowed("レスポンスは JSON 配列として返してください。"),
+        return_table: Cow::Borrowed("レスポンスはヘッダー行付きの {format} 表として返してください。"),
+        example_format: Cow::Borrowed("出力例："),
+        response_should_include: Cow::Borrowed("レスポンスには以下を含めてください："),
+        array_of_items: Cow::Borrowed("{type} 型の要素の配列"),
+        each_item_should_have: Cow::Borrowed("各要素には以下を含めてください："),
+        empty_array: Cow::Borrowed("空の配列"),
+        json_schema_information: Cow::Borrowed("JSON Schema 情報："),
+        typescript_type: Cow::Borrowed("レスポンスは次の TypeScript 型に従う必要があります："),
+        array_of_objects_note: Cow::Borrowed("これはオブジェクトの配列です。各要素は上記の構造に従ってください。"),
+        array_of_values_note: Cow::Borrowed("これは {type} 型の値の配列です。"),
+        empty_array_note: Cow::Borrowed("これは空の配列です。"),
+        toml_items: Cow::Borrowed("配列のすべての要素を `items` に入れてください。"),
+        one_row_per_item: Cow::Borrowed("要素ごとに 1 行書いてください。"),
+        single_row: Cow::Borrowed("1 行だけ書いてください。"),
+        csv_cells: Cow::Borrowed("カンマを含むセルは引用符で囲み、入れ子の値は JSON で書いてください。"),
+        markdown_cells: Cow::Borrowed("セル内のパイプは `\\|` とエスケープし、入れ子の値は JSON で書いてください。"),
+        xml_empty_array: Cow::Borrowed("空の配列 - 要素なし"),
+        xml_more_items: Cow::Borrowed("ここに追加の要素"),
+        examples: Cow::Borrowed("例："),
+        example_input: Cow::Borrowed("入力："),
+        example_output: Cow::Borrowed("出力："),
+        one_of: Cow::Borrowed("{field} は次のいずれかにしてください：{values}"),
+        answer_one_of: Cow::Borrowed("回答は次のいずれかにしてください：{values}"),
+        required: Cow::Borrowed("必須"),
+        optional: Cow::Borrowed("任意"),
+        repair_intro: Cow::Borrowed("前回のレスポンスは使用できませんでした。以下の問題を修正し、完全な {format} 出力をもう一度返してください："),
+        repair_missing: Cow::Borrowed("{field} がありません"),
+        repair_invalid: Cow::Borrowed("{field}：{error}"),
+        repair_syntax: Cow::Borrowed("レスポンスは有効な {format} ではありません：{error}"),
+        repair_near: Cow::Borrowed("問題はこの付近にあります：{fragment}"),
+        region: Cow::Borrowed("{field} は値を読み取った画像の領域です：左上隅の x と y、幅と高さを、画像サイズに対する 0 から 1 の割合で示します"),
+        proto_message: Cow::Borrowed("レスポンスは次の protobuf メッセージのインスタンスである必要があります："),
+        item_count: Cow::Borrowed("{min} 個から {max} 個の要素を返してください。"),
+        unique_by: Cow::Borrowed("`{field}` が同じ要素を二つ含めないでください。"),
+        tuple_items: Cow::Borrowed("{count} 要素の配列：[{items}]"),
+        single_value: Cow::Borrowed("単一の {type} 型の値"),
+    };
+
+    /// Spanish phrases
+    pub const SPANISH: Self = Self {
+        return_in_format: Cow::Borrowed("Devuelve la respuesta en formato {format}."),
+        return_json_array: Cow::Borrowed("Devuelve la respuesta como un array JSON de elementos."),
+        return_table: Cow::Borrowed("Devuelve la respuesta como una tabla {format} con una fila de encabezado."),
+        example_format: Cow::Borrowed("Formato de ejemplo:"),
+        response_should_include: Cow::Borrowed("La respuesta debe incluir:"),
+        array_of_items: Cow::Borrowed("Un array de elementos de tipo {type}"),
+        each_item_should_have: Cow::Borrowed("Cada elemento debe tener:"),
+        empty_array: Cow::Borrowed("Un array vacío"),
+        json_schema_information: Cow::Borrowed("Información del JSON Schema:"),
+        typescript_type: Cow::Borrowed("La respuesta debe ajustarse a este tipo de TypeScript:"),
+        array_of_objects_note: Cow::Borrowed("Es un array de objetos. Cada elemento debe seguir la estructura anterior."),
+        array_of_values_note: Cow::Borrowed("Es un array de valores de tipo {type}."),
+        empty_array_note: Cow::Borrowed("Es un array vacío."),
+        toml_items: Cow::Borrowed("Pon todos los elementos del array en `items`."),
+        one_row_per_item: Cow::Borrowed("Escribe una fila por elemento."),
+        single_row: Cow::Borrowed("Escribe una sola fila."),
+        csv_cells: Cow::Borrowed("Entrecomilla las celdas que contengan comas y escribe los valores anidados como JSON."),
+        markdown_cells: Cow::Borrowed("Escapa las barras verticales de las celdas como `\\|` y escribe los valores anidados como JSON."),
+        xml_empty_array: Cow::Borrowed("Array vacío - sin elementos"),
+        xml_more_items: Cow::Borrowed("Más elementos aquí"),
+        examples: Cow::Borrowed("Ejemplos:"),
+        example_input: Cow::Borrowed("Entrada:"),
+        example_output: Cow::Borrowed("Salida:"),
+        one_of: Cow::Borrowed("{field} debe ser uno de: {values}"),
+        answer_one_of: Cow::Borrowed("La respuesta debe ser uno de: {values}"),
+        required: Cow::Borrowed("obligatorio"),
+        optional: Cow::Borrowed("opcional"),
+        repair_intro: Cow::Borrowed("No se pudo usar tu respuesta anterior. Corrige estos problemas y responde de nuevo con la salida {format} completa:"),
+        repair_missing: Cow::Borrowed("Falta {field}"),
+        repair_invalid: Cow::Borrowed("{field}: {error}"),
+        repair_syntax: Cow::Borrowed("La respuesta no es {format} válido: {error}"),
+        repair_near: Cow::Borrowed("El problema está cerca de: {fragment}"),
+        region: Cow::Borrowed("{field} es la región de la imagen de la que se leyó el valor: x e y de su esquina superior izquierda, ancho y alto, como fracciones del tamaño de la imagen entre 0 y 1"),
+        proto_message: Cow::Borrowed("La respuesta debe ser una instancia de este mensaje protobuf:"),
+        item_count: Cow::Borrowed("Devuelve entre {min} y {max} elementos."),
+        unique_by: Cow::Borrowed("Ningún par de elementos puede tener el mismo `{field}`."),
+        tuple_items: Cow::Borrowed("Un array de {count} elementos: [{items}]"),
+        single_value: Cow::Borrowed("Un único valor de tipo {type}"),
+    };
+
+    /// German phrases
+    pub const GERMAN: Self = Self {
+        return_in_format: Cow::Borrowed("Bitte gib die Antwort im {format}-Format zurück."),
+        return_json_array: Cow::Borrowed("Bitte gib die Antwort als JSON-Array von Elementen zurück."),
+        return_table: Cow::Borrowed("Bitte gib die Antwort als {format}-Tabelle mit einer Kopfzeile zurück."),
+        example_format: Cow::Borrowed("Beispielformat:"),
+        response_should_include: Cow::Borrowed("Die Antwort sollte Folgendes enthalten:"),
+        array_of_items: Cow::Borrowed("Ein Array von Elementen vom Typ {type}"),
+        each_item_should_have: Cow::Borrowed("Jedes Element sollte Folgendes enthalten:"),
+        empty_array: Cow::Borrowed("Ein leeres Array"),
+        json_schema_information: Cow::Borrowed("JSON-Schema-Informationen:"),
+        typescript_type: Cow::Borrowed("Die Antwort muss diesem TypeScript-Typ entsprechen:"),
+        array_of_objects_note: Cow::Borrowed("Dies ist ein Array von Objekten. Jedes Element sollte der obigen Struktur folgen."),
+        array_of_values_note: Cow::Borrowed("Dies ist ein Array von Werten vom Typ {type}."),
+        empty_array_note: Cow::Borrowed("Dies ist ein leeres Array."),
+        toml_items: Cow::Borrowed("Lege alle Elemente des Arrays in `items` ab."),
+        one_row_per_item: Cow::Borrowed("Schreibe eine Zeile pro Element."),
+        single_row: Cow::Borrowed("Schreibe eine einzige Zeile."),
+        csv_cells: Cow::Borrowed("Setze Zellen mit Kommas in Anführungszeichen und schreibe verschachtelte Werte als JSON."),
+        markdown_cells: Cow::Borrowed("Maskiere senkrechte Striche in Zellen als `\\|` und schreibe verschachtelte Werte als JSON."),
+        xml_empty_array: Cow::Borrowed("Leeres Array - keine Elemente"),
+        xml_more_items: Cow::Borrowed("Weitere Elemente hier"),
+        examples: Cow::Borrowed("Beispiele:"),
+        example_input: Cow::Borrowed("Eingabe:"),
+        example_output: Cow::Borrowed("Ausgabe:"),
+        one_of: Cow::Borrowed("{field} muss einer der folgenden Werte sein: {values}"),
+        answer_one_of: Cow::Borrowed("Die Antwort muss einer der folgenden Werte sein: {values}"),
+        required: Cow::Borrowed("erforderlich"),
+        optional: Cow::Borrowed("optional"),
+        repair_intro: Cow::Borrowed("Deine vorherige Antwort konnte nicht verwendet werden. Behebe diese Probleme und antworte erneut mit der vollständigen {format}-Ausgabe:"),
+        repair_missing: Cow::Borrowed("{field} fehlt"),
+        repair_invalid: Cow::Borrowed("{field}: {error}"),
+        repair_syntax: Cow::Borrowed("Die Antwort ist kein gültiges {format}: {error}"),
+        repair_near: Cow::Borrowed("Das Problem liegt in der Nähe von: {fragment}"),
+        region: Cow::Borrowed("{field} ist der Bildbereich, aus dem der Wert gelesen wurde: x und y der oberen linken Ecke, Breite und Höhe, als Anteile der Bildgröße von 0 bis 1"),
+        proto_message: Cow::Borrowed("Die Antwort muss eine Instanz dieser Protobuf-Nachricht sein:"),
+        item_count: Cow::Borrowed("Gib zwischen {min} und {max} Elemente zurück."),
+        unique_by: Cow::Borrowed("Keine zwei Elemente dürfen dasselbe `{field}` haben."),
+        tuple_items: Cow::Borrowed("Ein Array mit {count} Elementen: [{items}]"),
+        single_value: Cow::Borrowed("Ein einzelner Wert vom Typ {type}"),
+    };
+
+    fn default_one_of() -> Cow<'static, str> {
+        Self::ENGLISH.one_of
+    }
+
+    fn default_answer_one_of() -> Cow<'static, str> {
+        Self::ENGLISH.answer_one_of
+    }
+
+    fn default_required() -> Cow<'static, str> {
+        Self::ENGLISH.required
+    }
+
+    fn default_optional() -> Cow<'static, str> {
+        Self::ENGLISH.optional
+    }
+
+    fn default_repair_intro() -> Cow<'static, str> {
+        Self::ENGLISH.repair_intro
+    }
+
+    fn default_repair_missing() -> Cow<'static, str> {
+        Self::ENGLISH.repair_missing
+    }
+
+    fn default_repair_invalid() -> Cow<'static, str> {
+        Self::ENGLISH.repair_invalid
+    }
+
+    fn default_repair_syntax() -> Cow<'static, str> {
+        Self::ENGLISH.repair_syntax
+    }
+
+    fn default_repair_near() -> Cow<'static, str> {
+        Self::ENGLISH.repair_near
+    }
+
+    fn default_region() -> Cow<'static, str> {
+        Self::ENGLISH.region
+    }
+
+    fn default_proto_message() -> Cow<'static, str> {
+        Self::ENGLISH.proto_message
+    }
+
+    fn default_item_count() -> Cow<'static, str> {
+        Self::ENGLISH.item_count
+    }
+
+    fn default_unique_by() -> Cow<'static, str> {
+        Self::ENGLISH.unique_by
+    }
+
+    fn default_tuple_items() -> Cow<'static, str> {
+        Self::ENGLISH.tuple_items
+    }
+
+    fn default_single_value() -> Cow<'static, str> {
+        Self::ENGLISH.single_value
+    }
 }
 
 /// Configuration for structured instructions
@@ -63,18 +925,85 @@ pub struct Config<T: Serialize + for<'a> Deserialize<'a> + Clone + std::fmt::Deb
     /// Output format for the structured data
     pub format: OutputFormat,
 
+    /// Clean-ups applied to the strings of the parsed data
+    #[serde(default)]
+    pub sanitize: Option<Sanitizer>,
+
     /// Sample schema (example)
     pub schema: Option<T>,
 
     /// Optional descriptions for schema fields (ordered by insertion)
     pub descriptions: Option<IndexMap<String, String>>,
 
+    /// Paths of the fields shown first, in this order, in the example, the field
+    /// descriptions and the schema, see [Config::field_order]
+    #[serde(default)]
+    pub field_order: Vec<String>,
+
+    /// Descriptions of the positions of a tuple answer, or of the value of a newtype at
+    /// position 0, see [Config::describe_index]
+    #[serde(default)]
+    pub index_descriptions: BTreeMap<usize, String>,
+
+    /// Source of the JSON Schema block
+    #[serde(default)]
+    pub schema_source: SchemaSource,
+
+    /// Presentation of the schema
+    #[serde(default)]
+    pub schema_dialect: SchemaDialect,
+
+    /// JSON Schema of the type, rendered with [SchemaSource::JsonSchema] and filled in by
+    /// [crate::structured::Generator::new]. Without it the example is used.
+    #[serde(default)]
+    pub json_schema: Option<serde_json::Value>,
+
+    /// How JSON answers are located in the response
+    #[serde(default)]
+    pub extraction: ExtractionStrategy,
+
+    /// Reasoning stripped from the response before the answer is extracted
+    pub reasoning_filter: ReasoningFilter,
+
     /// Whether to validate the response against the schema
     pub validate: bool,
 
     /// Validation options
     pub validation_options: Option<ValidationOptions>,
 
+    /// Input and output demonstrations, rendered after the format description
+    #[serde(default)]
+    pub examples: Vec<FewShotExample<T>>,
+
+    /// Language of the phrases of the instruction
+    #[serde(default)]
+    pub locale: Locale,
+
+    /// Whether the JSON formats show the schema minified and without the example
+    #[serde(default)]
+    pub compact_schema: bool,
+
+    /// Root element, attributes and namespaces of the XML format
+    #[serde(default)]
+    pub xml_mapping: XmlMapping,
+
+    /// Number of items and uniqueness of an array output
+    #[serde(default)]
+    pub array_constraints: Option<ArrayConstraints>,
+
+    /// Phrases used instead of those of the locale
+    #[serde(default)]
+    pub instruction_template: Option<InstructionTemplate>,
+
+    /// Layout of the instruction with `{section}` placeholders, see [Config::template].
+    /// Without it the sections follow each other in the order of the placeholders
+    #[serde(default)]
+    pub template: Option<String>,
+
+    /// Format of [OutputFormat::Custom], see [Config::custom_format]. Not serialized
+    #[serde(skip)]
+    pub custom_format: Option<CustomFormat>,
+
     /// Phantom data for T
     pub _marker: PhantomData<T>,
 }
@@ -85,10 +1014,26 @@ impl<T: Serialize + for<'a> Deserialize<'a> + Clone + std::fmt::Debug> Default f
             prefix: None,
             suffix: None,
             format: OutputFormat::default(),
+            sanitize: None,
             schema: None,
             descriptions: None,
+            field_order: Vec::new(),
+            index_descriptions: BTreeMap::new(),
+            schema_source: SchemaSource::default(),
+            schema_dialect: SchemaDialect::default(),
+            json_schema: None,
+            extraction: ExtractionStrategy::default(),
+            reasoning_filter: ReasoningFilter::default(),
             validate: false,
             validation_options: None,
+            examples: Vec::new(),
+            locale: Locale::default(),
+            compact_schema: false,
+            xml_mapping: XmlMapping::default(),
+            array_constraints: None,
+            instruction_template: None,
+            template: None,
+            custom_format: None,
             _marker: PhantomData,
         }
     }
@@ -130,13 +1075,69 @@ impl<T: Serialize + for<'a> Deserialize<'a> + Clone + std::fmt::Debug> Config<T>
         self
     }
 
-    /// Add a field description
+    /// Clean up every string of the parsed data with `sanitizer`, e.g. to unescape HTML
+    /// entities or normalize smart quotes
+    pub fn sanitize(mut self, sanitizer: Sanitizer) -> Self {
+        self.sanitize = Some(sanitizer);
+        self
+    }
+
+    /// Answer in `format`, a format defined outside the crate, as [OutputFormat::Custom]
+    pub fn custom_format(mut self, format: Box<dyn Format>) -> Self {
+        self.format = OutputFormat::Custom;
+        self.custom_format = Some(format.into());
+        self
+    }
+
+    /// Add a field description. Nested fields are addressed by their path, e.g. `author.name`,
+    /// and fields of array items with `[]`, e.g. `items[].price`
     pub fn describe(mut self, field: impl Into<String>, description: impl Into<String>) -> Self {
         let descriptions = self.descriptions.get_or_insert_with(IndexMap::new);
         descriptions.insert(field.into(), description.into());
         self
     }
 
+    /// Describe the position `index` of a tuple answer, e.g. `(String, u32)`, shown in the
+    /// instruction as `[name (string), age (integer)]`. Position 0 of a newtype answer, e.g.
+    /// `struct Age(u32)`, is its value
+    pub fn describe_index(mut self, index: usize, description: impl Into<String>) -> Self {
+        self.index_descriptions.insert(index, description.into());
+        self
+    }
+
+    /// Set the order of the fields, by path as with [Config::describe]. The listed fields
+    /// come first, in this order, in the example, the field descriptions and the schema, and
+    /// the others follow in the order of their declaration. Needs the `structured-field-order`
+    /// feature, without which the example and the schema list fields sorted by name
+    pub fn field_order<S: Into<String>>(mut self, fields: impl IntoIterator<Item = S>) -> Self {
+        self.field_order = fields.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Set the source of the JSON Schema block
+    pub fn schema_source(mut self, source: SchemaSource) -> Self {
+        self.schema_source = source;
+        self
+    }
+
+    /// Set how the expected structure is presented
+    pub fn schema_dialect(mut self, dialect: SchemaDialect) -> Self {
+        self.schema_dialect = dialect;
+        self
+    }
+
+    /// Set how JSON answers are located in the response
+    pub fn extraction(mut self, strategy: ExtractionStrategy) -> Self {
+        self.extraction = strategy;
+        self
+    }
+
+    /// Set the reasoning stripped from the response before the answer is extracted
+    pub fn reasoning_filter(mut self, filter: ReasoningFilter) -> Self {
+        self.reasoning_filter = filter;
+        self
+    }
+
     /// Enable validation
     pub fn validate(mut self, enable: bool) -> Self {
         self.validate = enable;
@@ -149,6 +1150,102 @@ impl<T: Serialize + for<'a> Deserialize<'a> + Clone + std::fmt::Debug> Config<T>
         self
     }
 
+    /// Add a demonstration of the `output` expected for `input`
+    pub fn example(mut self, input: impl Into<String>, output: T) -> Self {
+        self.examples.push(FewShotExample {
+            input: input.into(),
+            output,
+        });
+        self
+    }
+
+    /// Add several demonstrations of `(input, output)` pairs
+    pub fn examples<S: Into<String>>(mut self, examples: impl IntoIterator<Item = (S, T)>) -> Self {
+        for (input, output) in examples {
+            self = self.example(input, output);
+        }
+        self
+    }
+
+    /// Set the language of the phrases of the instruction
+    pub fn locale(mut self, locale: Locale) -> Self {
+        self.locale = locale;
+        self
+    }
+
+    /// Show the schema of the JSON formats minified, without the example it duplicates.
+    /// Combine with [SchemaDialect::TypeScript] for the shortest instructions
+    pub fn compact_schema(mut self, compact: bool) -> Self {
+        self.compact_schema = compact;
+        self
+    }
+
+    /// Set how data maps to XML
+    pub fn xml_mapping(mut self, mapping: XmlMapping) -> Self {
+        self.xml_mapping = mapping;
+        self
+    }
+
+    /// Ask for between `min` and `max` items that differ in the field `unique_by`, e.g. `id`,
+    /// and check them when an array output is parsed. An empty `unique_by` allows duplicates
+    pub fn array_constraints(mut self, min: usize, max: usize, unique_by: &str) -> Self {
+        let mut constraints = ArrayConstraints::new(min, max);
+        if !unique_by.is_empty() {
+            constraints = constraints.unique_by(unique_by);
+        }
+        constraints.trim = self.array_constraints.as_ref().is_some_and(|c| c.trim);
+        self.array_constraints = Some(constraints);
+        self
+    }
+
+    /// Drop duplicates and the items past the maximum of the array constraints instead of
+    /// failing the response
+    pub fn trim_array(mut self, trim: bool) -> Self {
+        self.array_constraints.get_or_insert_with(ArrayConstraints::default).trim = trim;
+        self
+    }
+
+    /// Set the phrases of the instruction, overriding those of the locale
+    pub fn instruction_template(mut self, template: InstructionTemplate) -> Self {
+        self.instruction_template = Some(template);
+        self
+    }
+
+    /// Set the layout of the instruction. The placeholders are replaced by the sections of
+    /// the instruction:
+    ///
+    /// | Placeholder | Section |
+    /// |-------------|---------|
+    /// | `{prefix}` | The prefix |
+    /// | `{descriptions}` | The field descriptions |
+    /// | `{format_note}` | The request for an answer in the output format |
+    /// | `{example}` | The example of the answer, with notes on the format |
+    /// | `{schema}` | The JSON Schema or TypeScript block, for the JSON formats |
+    /// | `{examples}` | The demonstrations |
+    /// | `{suffix}` | The suffix |
+    ///
+    /// Lines holding only placeholders of empty sections are left out, and other text in
+    /// braces is kept as it is.
+    ///
+    /// ```
+    /// use async_openai::types::structured::Config;
+    ///
+    /// let config = Config::with_schema(serde_json::json!({"answer": "..."}))
+    ///     .prefix("You are a helpful assistant.")
+    ///     .template("{prefix}\n\n{example}\n{format_note}\n{suffix}");
+    /// let instruction = config.to_instruction();
+    /// assert!(instruction.content().starts_with("You are a helpful assistant.\n\nExample format:"));
+    /// ```
+    pub fn template(mut self, template: impl Into<String>) -> Self {
+        self.template = Some(template.into());
+        self
+    }
+
+    /// Phrases of the instruction
+    pub(crate) fn phrases(&self) -> &InstructionTemplate {
+        self.instruction_template.as_ref().unwrap_or_else(|| self.locale.template())
+    }
+
     /// Helper function to determine if a schema value is an array
     fn is_array_schema(value: &serde_json::Value) -> bool {
         matches!(value, serde_json::Value::Array(_))
@@ -191,111 +1288,620 @@ impl<T: Serialize + for<'a> Deserialize<'a> + Clone + std::fmt::Debug> Config<T>
 
     /// Convert the configuration to an instruction
     pub fn to_instruction(&self) -> Instruction {
-        let mut content = String::new();
+        let mut sections = Sections::default();
 
         // Add prefix if available
         if let Some(ref prefix) = self.prefix {
-            content.push_str(prefix);
-            content.push_str("\n\n");
+            sections.prefix.push_str(prefix);
+            sections.prefix.push_str("\n\n");
         }
 
         // Process schema if available
         if let Some(schema) = &self.schema {
-            self.process_schema(schema, &mut content);
+            self.process_schema(schema, &mut sections);
         }
 
+        // Add demonstrations if available
+        self.add_examples(&mut sections.examples);
+
         // Add suffix if available
         if let Some(ref suffix) = self.suffix {
-            content.push_str("\n");
-            content.push_str(suffix);
+            sections.suffix.push('\n');
+            sections.suffix.push_str(suffix);
+        }
+
+        let content = match &self.template {
+            Some(template) => sections.render(template),
+            None => sections.concat(),
+        };
+        Instruction { content: content.into() }
+    }
+
+    /// Process schema and add to instruction content
+    fn process_schema(&self, schema: &T, sections: &mut Sections) {
+        // Serialize schema to determine its type
+        let mut schema_value = match serde_json::to_value(schema) {
+            Ok(value) => value,
+            Err(_) => return, // Can't process if serialization fails
+        };
+        self.order_fields(&mut schema_value, "");
+
+        let is_array = Self::is_array_schema(&schema_value);
+        
+        // Process field descriptions if available
+        if let Some(types) = self.tuple_types(&schema_value) {
+            self.add_tuple_items(&types, &mut sections.descriptions);
+        } else if let Some(description) = self.index_descriptions.get(&0).filter(|_| Self::is_single_value(&schema_value)) {
+            self.add_single_value(&schema_value, description, &mut sections.descriptions);
+        } else if let Some(descriptions) = &self.descriptions {
+            self.add_field_descriptions(&schema_value, descriptions, is_array, &mut sections.descriptions);
+        }
+        self.add_allowed_values(&mut sections.descriptions);
+        self.add_regions(&mut sections.descriptions);
+        if is_array {
+            self.add_array_constraints(&mut sections.descriptions);
+        }
+
+        // Add format-specific content
+        match self.format {
+            OutputFormat::Json => self.add_json_format(&schema_value, is_array, sections),
+            OutputFormat::JsonArray => self.add_json_array_format(&schema_value, is_array, sections),
+            #[cfg(feature = "yaml")]
+            OutputFormat::Yaml => self.add_yaml_format(&schema_value, is_array, sections),
+            #[cfg(feature = "xml")]
+            OutputFormat::Xml => self.add_xml_format(&schema_value, is_array, sections),
+            #[cfg(feature = "toml")]
+            OutputFormat::Toml => self.add_toml_format(&schema_value, is_array, sections),
+            #[cfg(feature = "csv")]
+            OutputFormat::Csv => self.add_csv_format(&schema_value, is_array, sections),
+            #[cfg(feature = "protobuf")]
+            OutputFormat::TextProto => self.add_textproto_format(&schema_value, sections),
+            OutputFormat::MarkdownTable => self.add_markdown_table_format(&schema_value, is_array, sections),
+            OutputFormat::Custom => self.add_custom_format(&schema_value, sections),
+        }
+    }
+
+    /// Add the demonstrations, each output rendered in the output format
+    fn add_examples(&self, content: &mut String) {
+        if self.examples.is_empty() {
+            return;
+        }
+
+        let phrases = self.phrases();
+        content.push_str(&format!("\n{}\n", phrases.examples));
+        for example in &self.examples {
+            let Some(output) = self.render_example(&example.output) else {
+                continue;
+            };
+            content.push_str(&format!("\n{}\n{}\n\n{}\n{}", phrases.example_input, example.input.trim(), phrases.example_output, output));
+        }
+    }
+
+    /// `output` as it should be answered in the output format
+    pub(crate) fn render_example(&self, output: &T) -> Option<String> {
+        let mut value = serde_json::to_value(output).ok()?;
+        self.order_fields(&mut value, "");
+        let is_array = Self::is_array_schema(&value);
+
+        match self.format {
+            OutputFormat::Json => {
+                Some(format!("```json\n{}\n```\n", serde_json::to_string_pretty(&value).ok()?))
+            }
+            OutputFormat::JsonArray => {
+                let value = if is_array { value } else { serde_json::Value::Array(vec![value]) };
+                Some(format!("```json\n{}\n```\n", serde_json::to_string_pretty(&value).ok()?))
+            }
+            #[cfg(feature = "yaml")]
+            OutputFormat::Yaml => Some(format!("```yaml\n{}```\n", serde_yaml::to_string(&value).ok()?)),
+            #[cfg(feature = "xml")]
+            OutputFormat::Xml => Some(format!("```xml\n{}```\n", self.xml_document(&value))),
+            #[cfg(feature = "toml")]
+            OutputFormat::Toml => {
+                let document = if is_array { serde_json::json!({ "items": value }) } else { value };
+                Some(format!("```toml\n{}```\n", toml::to_string(&document).ok()?))
+            }
+            #[cfg(feature = "csv")]
+            OutputFormat::Csv => Some(format!("```csv\n{}```\n", crate::structured::tabular::render_csv(&value)?)),
+            #[cfg(feature = "protobuf")]
+            OutputFormat::TextProto => Some(format!("```textproto\n{}```\n", crate::structured::proto::render_text(&value))),
+            OutputFormat::MarkdownTable => crate::structured::tabular::render_markdown(&value),
+            OutputFormat::Custom => self.custom_format.as_ref()?.render_example(&value),
+        }
+    }
+
+    #[cfg(feature = "xml")]
+    /// `value` as an XML document with the root element of the mapping, array items in `item`
+    /// elements and attribute fields in the tags
+    fn xml_document(&self, value: &serde_json::Value) -> String {
+        fn write(mapping: &XmlMapping, value: &serde_json::Value, name: &str, indent: usize, content: &mut String) {
+            let indent_str = "  ".repeat(indent);
+            match value {
+                serde_json::Value::Object(map) => {
+                    let (tag, children) = xml_tag(mapping, name, map);
+                    content.push_str(&format!("{}<{}>\n", indent_str, tag));
+                    for (field, value) in children {
+                        write(mapping, value, field, indent + 1, content);
+                    }
+                    content.push_str(&format!("{}</{}>\n", indent_str, name));
+                }
+                serde_json::Value::Array(items) => {
+                    content.push_str(&format!("{}<{}>\n", indent_str, name));
+                    for item in items {
+                        write(mapping, item, "item", indent + 1, content);
+                    }
+                    content.push_str(&format!("{}</{}>\n", indent_str, name));
+                }
+                serde_json::Value::Null => content.push_str(&format!("{}<{} />\n", indent_str, name)),
+                serde_json::Value::String(s) => content.push_str(&format!("{}<{}>{}</{}>\n", indent_str, name, s, name)),
+                _ => content.push_str(&format!("{}<{}>{}</{}>\n", indent_str, name, value, name)),
+            }
+        }
+
+        let mut content = String::new();
+        write(&self.xml_mapping, value, &self.xml_mapping.root, 0, &mut content);
+        content
+    }
+
+    /// Add field descriptions based on schema type
+    fn add_field_descriptions(
+        &self,
+        schema_value: &serde_json::Value,
+        descriptions: &IndexMap<String, String>,
+        is_array: bool,
+        content: &mut String
+    ) {
+        let phrases = self.phrases();
+        content.push_str(&format!("{}\n", phrases.response_should_include));
+
+        if !is_array {
+            // Object type handling
+            if let serde_json::Value::Object(map) = schema_value {
+                self.add_object_fields(map, "", descriptions, 0, true, content);
+            }
+        } else {
+            // Array type handling
+            if let serde_json::Value::Array(array) = schema_value {
+                if let Some(first) = array.first() {
+                    let item_type = Self::get_type_str(first);
+                    content.push_str(&format!("- {}\n", phrases.array_of_items.replace("{type}", item_type)));
+                    
+                    // If first item is an object, describe its structure
+                    if let serde_json::Value::Object(map) = first {
+                        content.push_str(&format!("  {}\n", phrases.each_item_should_have));
+                        self.add_object_fields(map, "", descriptions, 2, false, content);
+                    }
+                } else {
+                    content.push_str(&format!("- {}\n", phrases.empty_array));
+                }
+            }
+        }
+        
+        content.push_str("\n");
+    }
+
+    /// Types of the positions of a tuple answer, from the `items` (or `prefixItems`) array of
+    /// the JSON Schema, or, without a JSON Schema, of an example whose items differ in type
+    fn tuple_types(&self, value: &serde_json::Value) -> Option<Vec<String>> {
+        let serde_json::Value::Array(items) = value else {
+            return None;
+        };
+        let Some(schema) = &self.json_schema else {
+            return Self::example_tuple_types(items);
+        };
+        let positions = ["items", "prefixItems"].into_iter()
+            .find_map(|keyword| schema.get(keyword)?.as_array())?;
+        Some(positions.iter()
+            .map(|position| match Self::schema_type(position) {
+                Some(kind) => kind.to_string(),
+                None if position.get("$ref").is_some() || position.get("properties").is_some() => "object".to_string(),
+                None => "any".to_string(),
+            })
+            .collect())
+    }
+
+    /// Types of the items of an example array that holds values of several types
+    fn example_tuple_types(items: &[serde_json::Value]) -> Option<Vec<String>> {
+        let types: Vec<_> = items.iter().map(Self::get_type_str).collect();
+        if types.iter().all(|kind| *kind == types[0]) {
+            return None;
+        }
+        Some(types.into_iter().map(str::to_string).collect())
+    }
+
+    /// Whether an answer is a single value rather than an object or an array
+    fn is_single_value(value: &serde_json::Value) -> bool {
+        !matches!(value, serde_json::Value::Object(_) | serde_json::Value::Array(_))
+    }
+
+    /// Describe a tuple answer as its positions, e.g. `[name (string), age (integer)]`
+    fn add_tuple_items(&self, types: &[String], content: &mut String) {
+        let phrases = self.phrases();
+        let items = types.iter().enumerate()
+            .map(|(index, kind)| match self.index_descriptions.get(&index) {
+                Some(description) => format!("{} ({})", description, kind),
+                None => kind.clone(),
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        let line = phrases.tuple_items
+            .replace("{count}", &types.len().to_string())
+            .replace("{items}", &items);
+        content.push_str(&format!("{}\n- {}\n\n", phrases.response_should_include, line));
+    }
+
+    /// Describe a single value answer, such as a newtype of a number or a string
+    fn add_single_value(&self, value: &serde_json::Value, description: &str, content: &mut String) {
+        let phrases = self.phrases();
+        let kind = self.json_schema.as_ref()
+            .and_then(Self::schema_type)
+            .unwrap_or_else(|| Self::get_type_str(value));
+        let line = phrases.single_value.replace("{type}", kind);
+        content.push_str(&format!("{}\n- {}: {}\n\n", phrases.response_should_include, line, description));
+    }
+
+    /// List the values of the enums of the JSON Schema, which the example shows only one of
+    fn add_allowed_values(&self, content: &mut String) {
+        let Some(schema) = &self.json_schema else {
+            return;
+        };
+        let fields = crate::structured::enums::allowed_values(schema);
+        if fields.is_empty() {
+            return;
+        }
+
+        let phrases = self.phrases();
+        for (field, values) in fields {
+            let values = values.iter()
+                .map(|value| match value {
+                    serde_json::Value::String(s) => s.clone(),
+                    _ => value.to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            let line = if field.is_empty() {
+                phrases.answer_one_of.replace("{values}", &values)
+            } else {
+                phrases.one_of.replace("{field}", &field).replace("{values}", &values)
+            };
+            content.push_str(&format!("- {}\n", line));
+        }
+        content.push('\n');
+    }
+
+    /// Ask for the coordinates of the bounding box fields
+    fn add_regions(&self, content: &mut String) {
+        let Some(schema) = &self.json_schema else {
+            return;
+        };
+        let fields = crate::structured::regions::region_fields(schema);
+        if fields.is_empty() {
+            return;
+        }
+
+        let phrases = self.phrases();
+        for field in fields {
+            content.push_str(&format!("- {}\n", phrases.region.replace("{field}", &field)));
+        }
+        content.push('\n');
+    }
+
+    /// Ask for the number of items and the field they differ in
+    fn add_array_constraints(&self, content: &mut String) {
+        let Some(constraints) = &self.array_constraints else {
+            return;
+        };
+        let phrases = self.phrases();
+        // Only set by `trim_array` alone, which asks for nothing
+        let bounded = constraints.max_items < usize::MAX;
+        if bounded {
+            let line = phrases.item_count
+                .replace("{min}", &constraints.min_items.to_string())
+                .replace("{max}", &constraints.max_items.to_string());
+            content.push_str(&format!("- {}\n", line));
+        }
+        if let Some(field) = &constraints.unique_by {
+            content.push_str(&format!("- {}\n", phrases.unique_by.replace("{field}", field)));
+        }
+        if bounded || constraints.unique_by.is_some() {
+            content.push('\n');
+        }
+    }
+
+    /// List the fields of the object at `path`, followed by the fields of nested objects and
+    /// array items that have descriptions
+    fn add_object_fields(
+        &self,
+        map: &serde_json::Map<String, serde_json::Value>,
+        path: &str,
+        descriptions: &IndexMap<String, String>,
+        indent: usize,
+        described_first: bool,
+        content: &mut String
+    ) {
+        // Requirements and types from the JSON Schema, and the fields the example leaves out
+        let object_schema = self.json_schema.clone()
+            .and_then(|mut schema| Self::object_schema_at(&mut schema, path).map(serde_json::Value::take));
+        let mut object_schema = object_schema.unwrap_or_default();
+        let required = Self::subschema(&mut object_schema, "required").and_then(|required| required.as_array().cloned());
+        let properties = Self::subschema(&mut object_schema, "properties").and_then(|properties| properties.as_object().cloned()).unwrap_or_default();
+
+        static MISSING: serde_json::Value = serde_json::Value::Null;
+        let mut fields: Vec<_> = map.iter().collect();
+        fields.extend(properties.keys().filter(|field| !map.contains_key(*field)).map(|field| (field, &MISSING)));
+        if !self.field_order.is_empty() {
+            fields.sort_by_key(|(field, _)| self.field_position(&Self::field_path(path, field)));
+        } else if described_first {
+            // Described fields in the order they were described, then the others
+            fields.sort_by_key(|(field, _)| {
+                descriptions.get_index_of(&Self::field_path(path, field)).unwrap_or(usize::MAX)
+            });
+        }
+
+        let indent_str = " ".repeat(indent);
+        for (field, value) in fields {
+            let field_path = Self::field_path(path, field);
+            let is_required = required.as_ref().map(|required| required.iter().any(|name| name == field.as_str()));
+            let type_info = self.field_type_info(value, properties.get(field), is_required);
+            match descriptions.get(&field_path) {
+                Some(description) => content.push_str(&format!("{}- {}{}: {}\n", indent_str, field, type_info, description)),
+                None => content.push_str(&format!("{}- {}{}\n", indent_str, field, type_info)),
+            }
+
+            match value {
+                serde_json::Value::Object(nested) if Self::has_nested_descriptions(descriptions, &field_path) => {
+                    self.add_object_fields(nested, &field_path, descriptions, indent + 2, false, content);
+                },
+                serde_json::Value::Array(items) => {
+                    let items_path = Self::items_path(&field_path);
+                    if let Some(serde_json::Value::Object(nested)) = items.first() {
+                        if Self::has_nested_descriptions(descriptions, &items_path) {
+                            content.push_str(&format!("{}  {}\n", indent_str, self.phrases().each_item_should_have));
+                            self.add_object_fields(nested, &items_path, descriptions, indent + 2, false, content);
+                        }
+                    }
+                },
+                _ => {}
+            }
         }
+    }
+
+    /// Type info of a field for display, e.g. ` (string, optional)`. The JSON Schema of the
+    /// field gives the type of fields that are null in the example
+    fn field_type_info(&self, value: &serde_json::Value, property: Option<&serde_json::Value>, is_required: Option<bool>) -> String {
+        let type_info = Self::get_type_info(value);
+        let kind = match (value, property.and_then(Self::schema_type)) {
+            (serde_json::Value::Null, Some(kind)) => kind,
+            _ => &type_info[2..type_info.len() - 1],
+        };
+
+        let phrases = self.phrases();
+        match is_required {
+            Some(true) => format!(" ({}, {})", kind, phrases.required),
+            Some(false) => format!(" ({}, {})", kind, phrases.optional),
+            None => format!(" ({})", kind),
+        }
+    }
+
+    /// The type of a JSON Schema other than `null`, looking into the variants of unions
+    fn schema_type(schema: &serde_json::Value) -> Option<&str> {
+        match schema.get("type") {
+            Some(serde_json::Value::String(kind)) if kind != "null" => return Some(kind),
+            Some(serde_json::Value::Array(kinds)) => {
+                if let Some(kind) = kinds.iter().filter_map(serde_json::Value::as_str).find(|kind| *kind != "null") {
+                    return Some(kind);
+                }
+            },
+            _ => {}
+        }
+        ["anyOf", "oneOf"].into_iter()
+            .filter_map(|combinator| schema.get(combinator)?.as_array())
+            .flatten()
+            .find_map(Self::schema_type)
+    }
+
+    /// The schema of position `index` of a tuple, or of a single value for index 0
+    fn schema_at_index(schema: &mut serde_json::Value, index: usize) -> Option<&mut serde_json::Value> {
+        let keyword = ["items", "prefixItems"].into_iter()
+            .find(|keyword| schema.get(*keyword).is_some_and(serde_json::Value::is_array));
+        match keyword {
+            Some(keyword) => schema[keyword].get_mut(index),
+            None if index == 0 && Self::schema_type(schema).is_some_and(|kind| kind != "object" && kind != "array") => Some(schema),
+            None => None,
+        }
+    }
 
-        Instruction { content }
+    /// The schema of the object at a description path, the items of a top level array for
+    /// the empty path
+    fn object_schema_at<'s>(schema: &'s mut serde_json::Value, path: &str) -> Option<&'s mut serde_json::Value> {
+        if !path.is_empty() {
+            return Self::schema_at_path(schema, path);
+        }
+        if schema.get("type").and_then(serde_json::Value::as_str) == Some("array") {
+            return Self::subschema(schema, "items");
+        }
+        Some(schema)
+    }
+
+    /// Path of `field` in the object at `path`, e.g. `author.name`
+    fn field_path(path: &str, field: &str) -> String {
+        if path.is_empty() {
+            field.to_string()
+        } else {
+            format!("{}.{}", path, field)
+        }
+    }
+
+    /// Path of the items of the array at `path`, e.g. `items[]`. Items of a top level array
+    /// are described like top level fields.
+    fn items_path(path: &str) -> String {
+        if path.is_empty() {
+            String::new()
+        } else {
+            format!("{}[]", path)
+        }
+    }
+
+    /// Position of the field at `path` in the field order, after the listed fields when it
+    /// isn't listed
+    fn field_position(&self, path: &str) -> usize {
+        self.field_order.iter().position(|field| field == path).unwrap_or(usize::MAX)
+    }
+
+    /// Sort the fields of `map`, the object at `path`, after the field order. The sort is
+    /// stable, so fields that aren't listed keep their order
+    fn sort_fields(&self, map: &mut serde_json::Map<String, serde_json::Value>, path: &str) {
+        let mut fields: Vec<_> = std::mem::take(map).into_iter().collect();
+        fields.sort_by_key(|(field, _)| self.field_position(&Self::field_path(path, field)));
+        *map = fields.into_iter().collect();
+    }
+
+    /// Reorder the fields of the objects in `value`, at `path`, after the field order
+    pub(crate) fn order_fields(&self, value: &mut serde_json::Value, path: &str) {
+        if self.field_order.is_empty() {
+            return;
+        }
+        match value {
+            serde_json::Value::Object(map) => {
+                self.sort_fields(map, path);
+                for (field, value) in map.iter_mut() {
+                    self.order_fields(value, &Self::field_path(path, field));
+                }
+            }
+            serde_json::Value::Array(items) => {
+                let items_path = Self::items_path(path);
+                items.iter_mut().for_each(|item| self.order_fields(item, &items_path));
+            }
+            _ => {}
+        }
+    }
+
+    /// Reorder the `properties` and `required` fields of `schema`, the JSON Schema of the
+    /// value at `path`, after the field order
+    pub(crate) fn order_schema_fields(&self, schema: &mut serde_json::Value, path: &str) {
+        if self.field_order.is_empty() {
+            return;
+        }
+        let Some(map) = schema.as_object_mut() else {
+            return;
+        };
+
+        if let Some(serde_json::Value::Object(properties)) = map.get_mut("properties") {
+            self.sort_fields(properties, path);
+            for (field, schema) in properties.iter_mut() {
+                self.order_schema_fields(schema, &Self::field_path(path, field));
+            }
+        }
+        if let Some(serde_json::Value::Array(required)) = map.get_mut("required") {
+            required.sort_by_key(|field| {
+                field.as_str().map_or(usize::MAX, |field| self.field_position(&Self::field_path(path, field)))
+            });
+        }
+        if let Some(items) = map.get_mut("items") {
+            let items_path = Self::items_path(path);
+            match items {
+                serde_json::Value::Array(schemas) => {
+                    schemas.iter_mut().for_each(|schema| self.order_schema_fields(schema, &items_path));
+                }
+                schema => self.order_schema_fields(schema, &items_path),
+            }
+        }
+        for combinator in ["anyOf", "oneOf", "allOf"] {
+            if let Some(serde_json::Value::Array(variants)) = map.get_mut(combinator) {
+                variants.iter_mut().for_each(|variant| self.order_schema_fields(variant, path));
+            }
+        }
+    }
+
+    /// Whether a field below `path` has a description
+    fn has_nested_descriptions(descriptions: &IndexMap<String, String>, path: &str) -> bool {
+        descriptions.keys().any(|key| {
+            key.strip_prefix(path)
+                .is_some_and(|rest| rest.starts_with('.') || rest.starts_with('['))
+        })
+    }
+
+    /// The request for an answer in `format`, followed by a blank line
+    fn return_in_format(&self, format: &str) -> String {
+        format!("{}\n\n", self.phrases().return_in_format.replace("{format}", format))
+    }
+
+    /// Add the schema as minified JSON, in place of the example
+    fn add_minified_schema(&self, schema: &serde_json::Value, content: &mut String) {
+        if let Ok(schema) = serde_json::to_string(schema) {
+            content.push_str(&format!("{}\n```json\n{}\n```\n", self.phrases().json_schema_information, schema));
+        }
+    }
+
+    /// Add the schema as TypeScript type declarations
+    fn add_typescript_type(&self, schema: &serde_json::Value, content: &mut String) {
+        content.push_str(&format!("\n{}\n```typescript\n", self.phrases().typescript_type));
+        content.push_str(&crate::structured::typescript::render(schema));
+        content.push_str("\n```\n");
+    }
+
+    /// The JSON Schema of the type with the field descriptions added, `None` when the
+    /// example is the schema source
+    fn rendered_json_schema(&self) -> Option<serde_json::Value> {
+        if self.schema_source != SchemaSource::JsonSchema {
+            return None;
+        }
+        let mut schema = self.json_schema.clone()?;
+        if let serde_json::Value::Object(map) = &mut schema {
+            map.remove("$schema");
+        }
+
+        for (path, description) in self.descriptions.iter().flatten() {
+            if let Some(serde_json::Value::Object(field)) = Self::schema_at_path(&mut schema, path) {
+                field.insert("description".to_string(), serde_json::Value::String(description.clone()));
+            }
+        }
+        for (index, description) in &self.index_descriptions {
+            if let Some(serde_json::Value::Object(position)) = Self::schema_at_index(&mut schema, *index) {
+                position.insert("description".to_string(), serde_json::Value::String(description.clone()));
+            }
+        }
+        self.order_schema_fields(&mut schema, "");
+        Some(schema)
     }
 
-    /// Process schema and add to instruction content
-    fn process_schema(&self, schema: &T, content: &mut String) {
-        // Serialize schema to determine its type
-        let schema_value = match serde_json::to_value(schema) {
-            Ok(value) => value,
-            Err(_) => return, // Can't process if serialization fails
-        };
+    /// The schema presented in instructions, from the configured source
+    pub(crate) fn presented_json_schema(&self) -> Option<serde_json::Value> {
+        self.rendered_json_schema().or_else(|| {
+            let mut example = serde_json::to_value(self.schema.as_ref()?).ok()?;
+            self.order_fields(&mut example, "");
+            Some(self.generate_schema_json(&example))
+        })
+    }
 
-        let is_array = Self::is_array_schema(&schema_value);
-        
-        // Process field descriptions if available
-        if let Some(descriptions) = &self.descriptions {
-            self.add_field_descriptions(&schema_value, descriptions, is_array, content);
+    /// The schema of the field at a description path such as `items[].price`
+    fn schema_at_path<'s>(schema: &'s mut serde_json::Value, path: &str) -> Option<&'s mut serde_json::Value> {
+        let mut node = schema;
+        // Items of a top level array are described like top level fields
+        if node.get("type").and_then(serde_json::Value::as_str) == Some("array") {
+            node = Self::subschema(node, "items")?;
         }
 
-        // Add format-specific content
-        match self.format {
-            OutputFormat::Json => self.add_json_format(&schema_value, schema, is_array, content),
-            OutputFormat::JsonArray => self.add_json_array_format(&schema_value, schema, is_array, content),
-            #[cfg(feature = "yaml")]
-            OutputFormat::Yaml => self.add_yaml_format(&schema_value, schema, is_array, content),
-            #[cfg(feature = "xml")]
-            OutputFormat::Xml => self.add_xml_format(&schema_value, is_array, content),
+        for segment in path.split('.') {
+            let name = segment.trim_end_matches("[]");
+            node = Self::subschema(node, "properties")?.get_mut(name)?;
+            for _ in 0..(segment.len() - name.len()) / 2 {
+                node = Self::subschema(node, "items")?;
+            }
         }
+        Some(node)
     }
 
-    /// Add field descriptions based on schema type
-    fn add_field_descriptions(
-        &self,
-        schema_value: &serde_json::Value,
-        descriptions: &IndexMap<String, String>,
-        is_array: bool,
-        content: &mut String
-    ) {
-        content.push_str("The response should include:\n");
-
-        if !is_array {
-            // Object type handling
-            if let serde_json::Value::Object(map) = schema_value {
-                // Process fields with descriptions first
-                for (field, description) in descriptions {
-                    if let Some(value) = map.get(field) {
-                        let type_info = Self::get_type_info(value);
-                        content.push_str(&format!("- {}{}: {}\n", field, type_info, description));
-                    }
-                }
-                
-                // Add remaining fields without descriptions
-                for (field, value) in map {
-                    if !descriptions.contains_key(field) {
-                        let type_info = Self::get_type_info(value);
-                        content.push_str(&format!("- {}{}\n", field, type_info));
-                    }
-                }
-            }
-        } else {
-            // Array type handling
-            if let serde_json::Value::Array(array) = schema_value {
-                if let Some(first) = array.first() {
-                    let item_type = Self::get_type_str(first);
-                    content.push_str(&format!("- An array of {} items\n", item_type));
-                    
-                    // If first item is an object, describe its structure
-                    if let serde_json::Value::Object(map) = first {
-                        content.push_str("  Each item should have:\n");
-                        
-                        for (field, value) in map {
-                            let type_info = Self::get_type_info(value);
-                            
-                            if let Some(desc) = descriptions.get(field) {
-                                content.push_str(&format!("  - {}{}: {}\n", field, type_info, desc));
-                            } else {
-                                content.push_str(&format!("  - {}{}\n", field, type_info));
-                            }
-                        }
-                    }
-                } else {
-                    content.push_str("- An empty array\n");
-                }
-            }
+    /// The `key` of a schema, looking into the variants of `Option` and other unions
+    fn subschema<'s>(node: &'s mut serde_json::Value, key: &str) -> Option<&'s mut serde_json::Value> {
+        if node.get(key).is_some() {
+            return node.get_mut(key);
         }
-        
-        content.push_str("\n");
+        let (combinator, index) = ["anyOf", "oneOf", "allOf"].into_iter().find_map(|combinator| {
+            let variants = node.get(combinator)?.as_array()?;
+            let index = variants.iter().position(|variant| variant.get(key).is_some())?;
+            Some((combinator, index))
+        })?;
+        node.get_mut(combinator)?.get_mut(index)?.get_mut(key)
     }
 
     /// Add properties schema with proper indentation
@@ -355,6 +1961,35 @@ impl<T: Serialize + for<'a> Deserialize<'a> + Clone + std::fmt::Debug> Config<T>
 
     /// Generate nested schema structure directly using serde_json
     fn generate_schema_json(&self, value: &serde_json::Value) -> serde_json::Value {
+        // The example holds one variant of each enum, the values come from the JSON Schema
+        let enums = self.json_schema.as_ref().map(crate::structured::enums::allowed_values).unwrap_or_default();
+        self.generate_schema_json_at(value, "", &enums)
+    }
+
+    /// Generate the schema of the value at `path`, with the descriptions of its fields and the
+    /// values of its enums
+    fn generate_schema_json_at(
+        &self,
+        value: &serde_json::Value,
+        path: &str,
+        enums: &[(String, Vec<serde_json::Value>)]
+    ) -> serde_json::Value {
+        let mut schema = self.generate_value_schema(value, path, enums);
+        if !value.is_null() {
+            if let Some((_, values)) = enums.iter().find(|(field, _)| field == path) {
+                schema["enum"] = serde_json::Value::Array(values.clone());
+            }
+        }
+        schema
+    }
+
+    /// Schema of the type of `value`, inferred from it
+    fn generate_value_schema(
+        &self,
+        value: &serde_json::Value,
+        path: &str,
+        enums: &[(String, Vec<serde_json::Value>)]
+    ) -> serde_json::Value {
         match value {
             serde_json::Value::Object(map) => {
                 let mut schema = serde_json::json!({
@@ -365,16 +2000,37 @@ impl<T: Serialize + for<'a> Deserialize<'a> + Clone + std::fmt::Debug> Config<T>
                 let properties = schema["properties"].as_object_mut().unwrap();
                 
                 for (field, val) in map {
-                    properties.insert(field.clone(), self.generate_schema_json(val));
+                    let field_path = Self::field_path(path, field);
+                    let mut property = self.generate_schema_json_at(val, &field_path, enums);
+                    if let Some(description) = self.descriptions.as_ref().and_then(|descriptions| descriptions.get(&field_path)) {
+                        property["description"] = serde_json::Value::String(description.clone());
+                    }
+                    properties.insert(field.clone(), property);
                 }
                 
                 schema
             },
+            serde_json::Value::Array(array) if Self::example_tuple_types(array).is_some() => {
+                let mut schema = serde_json::json!({
+                    "type": "array",
+                    "items": array.iter().map(|item| self.generate_schema_json_at(item, &Self::items_path(path), enums)).collect::<Vec<_>>(),
+                    "minItems": array.len(),
+                    "maxItems": array.len()
+                });
+                if path.is_empty() {
+                    for (index, description) in &self.index_descriptions {
+                        if let Some(position) = schema["items"].get_mut(*index) {
+                            position["description"] = serde_json::Value::String(description.clone());
+                        }
+                    }
+                }
+                schema
+            },
             serde_json::Value::Array(array) => {
                 if let Some(first) = array.first() {
                     serde_json::json!({
                         "type": "array",
-                        "items": self.generate_schema_json(first)
+                        "items": self.generate_schema_json_at(first, &Self::items_path(path), enums)
                     })
                 } else {
                     serde_json::json!({
@@ -510,19 +2166,34 @@ impl<T: Serialize + for<'a> Deserialize<'a> + Clone + std::fmt::Debug> Config<T>
     fn add_json_format(
         &self,
         schema_value: &serde_json::Value,
-        schema: &T,
         is_array: bool,
-        content: &mut String
+        sections: &mut Sections
     ) {
-        content.push_str("Please return the response in JSON format.\n\n");
+        sections.format_note.push_str(&self.return_in_format("JSON"));
 
-        if let Ok(json) = serde_json::to_string_pretty(schema) {
-            content.push_str(&format!("Example format:\n```json\n{}\n```\n", json));
+        if let Ok(json) = serde_json::to_string_pretty(schema_value) {
+            if !self.compact_schema {
+                sections.example.push_str(&format!("{}\n```json\n{}\n```\n", self.phrases().example_format, json));
+            }
             
+            let content = &mut sections.schema;
+            if self.schema_dialect == SchemaDialect::TypeScript {
+                let schema = self.rendered_json_schema().unwrap_or_else(|| self.generate_schema_json(schema_value));
+                self.add_typescript_type(&schema, content);
+                return;
+            }
+            if self.compact_schema {
+                let schema = self.rendered_json_schema().unwrap_or_else(|| self.generate_schema_json(schema_value));
+                self.add_minified_schema(&schema, content);
+                return;
+            }
+
             // Add JSON Schema information
-            content.push_str("\nJSON Schema information:\n```json\n");
+            content.push_str(&format!("\n{}\n```json\n", self.phrases().json_schema_information));
             
-            if is_array {
+            if let Some(schema) = self.rendered_json_schema().and_then(|schema| serde_json::to_string_pretty(&schema).ok()) {
+                content.push_str(&schema);
+            } else if is_array {
                 self.add_array_schema(schema_value, content);
             } else {
                 self.add_object_schema(schema_value, content);
@@ -536,23 +2207,33 @@ impl<T: Serialize + for<'a> Deserialize<'a> + Clone + std::fmt::Debug> Config<T>
     fn add_json_array_format(
         &self,
         schema_value: &serde_json::Value,
-        schema: &T,
         is_array: bool,
-        content: &mut String
+        sections: &mut Sections
     ) {
-        content.push_str("Please return the response as a JSON array of items.\n\n");
+        sections.format_note.push_str(&format!("{}\n\n", self.phrases().return_json_array));
 
-        if let Ok(json) = serde_json::to_string_pretty(schema) {
+        if let Ok(json) = serde_json::to_string_pretty(schema_value) {
             // Format the example based on whether schema is already an array
-            if is_array {
-                content.push_str(&format!("Example format:\n```json\n{}\n```\n", json));
+            if self.compact_schema {
+                // The schema says it all
+            } else if is_array {
+                sections.example.push_str(&format!("{}\n```json\n{}\n```\n", self.phrases().example_format, json));
             } else {
                 // Wrap the object in an array
-                content.push_str(&format!("Example format:\n```json\n[\n  {}\n]\n```\n", json));
+                sections.example.push_str(&format!("{}\n```json\n[\n  {}\n]\n```\n", self.phrases().example_format, json));
             }
             
             // Create array schema directly using serde_json
-            let array_schema = if is_array {
+            let array_schema = if let Some(schema) = self.rendered_json_schema() {
+                if is_array {
+                    schema
+                } else {
+                    serde_json::json!({
+                        "type": "array",
+                        "items": schema
+                    })
+                }
+            } else if is_array {
                 // Already an array, just use it
                 self.generate_schema_json(schema_value)
             } else {
@@ -563,8 +2244,18 @@ impl<T: Serialize + for<'a> Deserialize<'a> + Clone + std::fmt::Debug> Config<T>
                 })
             };
             
+            let content = &mut sections.schema;
+            if self.schema_dialect == SchemaDialect::TypeScript {
+                self.add_typescript_type(&array_schema, content);
+                return;
+            }
+            if self.compact_schema {
+                self.add_minified_schema(&array_schema, content);
+                return;
+            }
+
             // Print the schema
-            content.push_str("\nJSON Schema information:\n```json\n");
+            content.push_str(&format!("\n{}\n```json\n", self.phrases().json_schema_information));
             if let Ok(schema_str) = serde_json::to_string_pretty(&array_schema) {
                 content.push_str(&schema_str);
             } else {
@@ -600,66 +2291,176 @@ impl<T: Serialize + for<'a> Deserialize<'a> + Clone + std::fmt::Debug> Config<T>
     fn add_yaml_format(
         &self,
         schema_value: &serde_json::Value,
-        schema: &T,
         is_array: bool,
-        content: &mut String
+        sections: &mut Sections
     ) {
-        content.push_str("Please return the response in YAML format.\n\n");
+        sections.format_note.push_str(&self.return_in_format("YAML"));
+        let content = &mut sections.example;
 
-        if let Ok(yaml) = serde_yaml::to_string(schema) {
-            content.push_str(&format!("Example format:\n```yaml\n{}\n```\n", yaml));
+        if let Ok(yaml) = serde_yaml::to_string(schema_value) {
+            content.push_str(&format!("{}\n```yaml\n{}\n```\n", self.phrases().example_format, yaml));
             
-            // Add a note about the structure type for arrays
-            if is_array {
+            // Add a note about the structure type for arrays, tuples are described with the fields
+            if is_array && self.tuple_types(schema_value).is_none() {
                 if let serde_json::Value::Array(array) = schema_value {
                     if let Some(first) = array.first() {
                         if matches!(first, serde_json::Value::Object(_)) {
-                            content.push_str("\nThis is an array of objects. Each item should follow the above structure.\n");
+                            content.push_str(&format!("\n{}\n", self.phrases().array_of_objects_note));
                         } else {
                             let item_type = Self::get_type_str(first);
-                            content.push_str(&format!("\nThis is an array of {} values.\n", item_type));
+                            content.push_str(&format!("\n{}\n", self.phrases().array_of_values_note.replace("{type}", item_type)));
                         }
                     } else {
-                        content.push_str("\nThis is an empty array.\n");
+                        content.push_str(&format!("\n{}\n", self.phrases().empty_array_note));
                     }
                 }
             }
         }
     }
 
+    #[cfg(feature = "toml")]
+    /// Add TOML format information to content
+    fn add_toml_format(
+        &self,
+        schema_value: &serde_json::Value,
+        is_array: bool,
+        sections: &mut Sections
+    ) {
+        sections.format_note.push_str(&self.return_in_format("TOML"));
+        let content = &mut sections.example;
+
+        // A TOML document is always a table, so arrays are written as an `items` array
+        let document = if is_array {
+            serde_json::json!({ "items": schema_value })
+        } else {
+            schema_value.clone()
+        };
+        if let Ok(toml) = toml::to_string(&document) {
+            content.push_str(&format!("{}\n```toml\n{}```\n", self.phrases().example_format, toml));
+
+            if is_array {
+                content.push_str(&format!("\n{}\n", self.phrases().toml_items));
+            }
+        }
+    }
+
+    #[cfg(feature = "csv")]
+    /// Add CSV format information to content
+    fn add_csv_format(
+        &self,
+        schema_value: &serde_json::Value,
+        is_array: bool,
+        sections: &mut Sections
+    ) {
+        sections.format_note.push_str(&format!("{}\n\n", self.phrases().return_table.replace("{format}", "CSV")));
+        let content = &mut sections.example;
+
+        if let Some(table) = crate::structured::tabular::render_csv(schema_value) {
+            content.push_str(&format!("{}\n```csv\n{}```\n", self.phrases().example_format, table));
+            self.add_table_notes(&self.phrases().csv_cells, is_array, content);
+        }
+    }
+
+    #[cfg(feature = "protobuf")]
+    /// Add protobuf text format information to content, with the schema as a `.proto` message
+    fn add_textproto_format(
+        &self,
+        schema_value: &serde_json::Value,
+        sections: &mut Sections
+    ) {
+        sections.format_note.push_str(&self.return_in_format("protobuf text format"));
+
+        sections.example.push_str(&format!(
+            "{}\n```textproto\n{}```\n",
+            self.phrases().example_format,
+            crate::structured::proto::render_text(schema_value)
+        ));
+
+        let schema = self.rendered_json_schema().unwrap_or_else(|| self.generate_schema_json(schema_value));
+        sections.schema.push_str(&format!("\n{}\n```proto\n", self.phrases().proto_message));
+        sections.schema.push_str(&crate::structured::proto::render(&schema));
+        sections.schema.push_str("\n```\n");
+    }
+
+    /// Add the number of rows expected and how to write `cells` after a table example
+    fn add_table_notes(&self, cells: &str, is_array: bool, content: &mut String) {
+        let phrases = self.phrases();
+        let rows = if is_array { &phrases.one_row_per_item } else { &phrases.single_row };
+        content.push_str(&format!("\n{} {}\n", rows, cells));
+    }
+
+    /// Add markdown table format information to content
+    fn add_markdown_table_format(
+        &self,
+        schema_value: &serde_json::Value,
+        is_array: bool,
+        sections: &mut Sections
+    ) {
+        sections.format_note.push_str(&format!("{}\n\n", self.phrases().return_table.replace("{format}", "markdown")));
+        let content = &mut sections.example;
+
+        if let Some(table) = crate::structured::tabular::render_markdown(schema_value) {
+            content.push_str(&format!("{}\n{}", self.phrases().example_format, table));
+            self.add_table_notes(&self.phrases().markdown_cells, is_array, content);
+        }
+    }
+
+    /// Add the example and the schema as rendered by the custom format
+    fn add_custom_format(&self, schema_value: &serde_json::Value, sections: &mut Sections) {
+        let Some(format) = &self.custom_format else {
+            return;
+        };
+        sections.format_note.push_str(&self.return_in_format(format.name()));
+
+        if let Some(example) = format.render_example(schema_value) {
+            sections.example.push_str(&format!("{}\n{}\n", self.phrases().example_format, example.trim_end()));
+        }
+        let schema = self.rendered_json_schema().unwrap_or_else(|| self.generate_schema_json(schema_value));
+        if let Some(schema) = format.render_schema(&schema) {
+            sections.schema.push_str(&format!("\n{}\n", schema.trim_end()));
+        }
+    }
+
     #[cfg(feature = "xml")]
     /// Add XML format information to content
     fn add_xml_format(
         &self,
         schema_value: &serde_json::Value,
         is_array: bool,
-        content: &mut String
+        sections: &mut Sections
     ) {
-        content.push_str("Please return the response in XML format.\n\n");
-        content.push_str("Example format:\n```xml\n<root>\n");
+        let phrases = self.phrases();
+        let mapping = &self.xml_mapping;
+        sections.format_note.push_str(&self.return_in_format("XML"));
+        let content = &mut sections.example;
+        content.push_str(&format!("{}\n```xml\n", phrases.example_format));
+
+        // A child element per field, at `indent`
+        let push_fields = |content: &mut String, fields: Vec<(&str, &serde_json::Value)>, indent: &str| {
+            for (field, value) in fields {
+                let value_str = match value {
+                    serde_json::Value::String(s) => s.clone(),
+                    _ => value.to_string(),
+                };
+                content.push_str(&format!("{}<{}>{}</{}>\n", indent, field, value_str, field));
+            }
+        };
 
         if is_array {
+            content.push_str(&format!("<{}>\n", mapping.root));
             if let serde_json::Value::Array(array) = schema_value {
                 // Find the first item, if any
-                array.first().map_or_else(
+                match array.first() {
                     // No items - empty array
-                    || content.push_str("  <!-- Empty array - no items -->\n"),
-                    |first| match first {
+                    None => content.push_str(&format!("  <!-- {} -->\n", phrases.xml_empty_array)),
+                    Some(first) => match first {
                         // Object array
                         serde_json::Value::Object(map) => {
-                            content.push_str("  <item>\n");
-                            
-                            // Add fields from the object
-                            for (field, value) in map {
-                                let value_str = match value {
-                                    serde_json::Value::String(s) => s.clone(),
-                                    _ => value.to_string(),
-                                };
-                                content.push_str(&format!("    <{}>{}</{}>\n", field, value_str, field));
-                            }
-                            
+                            let (tag, fields) = xml_tag(mapping, "item", map);
+                            content.push_str(&format!("  <{}>\n", tag));
+                            push_fields(content, fields, "    ");
                             content.push_str("  </item>\n");
-                            content.push_str("  <!-- Additional items here -->\n");
+                            content.push_str(&format!("  <!-- {} -->\n", phrases.xml_more_items));
                         },
                         // Simple value array
                         _ => {
@@ -668,41 +2469,137 @@ impl<T: Serialize + for<'a> Deserialize<'a> + Clone + std::fmt::Debug> Config<T>
                                 _ => first.to_string(),
                             };
                             content.push_str(&format!("  <item>{}</item>\n", value_str));
-                            content.push_str("  <!-- Additional items here -->\n");
+                            content.push_str(&format!("  <!-- {} -->\n", phrases.xml_more_items));
                         }
                     }
-                );
+                }
             }
         } else if let serde_json::Value::Object(map) = schema_value {
-            // Just add the object fields
-            map.iter().for_each(|(field, value)| {
-                let value_str = match value {
-                    serde_json::Value::String(s) => s.clone(),
-                    _ => value.to_string(),
-                };
-                content.push_str(&format!("  <{}>{}</{}>\n", field, value_str, field));
-            });
+            let (tag, fields) = xml_tag(mapping, &mapping.root, map);
+            content.push_str(&format!("<{}>\n", tag));
+            push_fields(content, fields, "  ");
+        } else {
+            content.push_str(&format!("<{}>\n", mapping.root));
         }
-        
-        content.push_str("</root>\n```\n");
+
+        content.push_str(&format!("</{}>\n```\n", mapping.root));
+    }
+}
+
+#[cfg(feature = "xml")]
+/// Opening tag of an element `name` for `map`, with the scalar fields mapped to attributes,
+/// and the fields left for child elements
+fn xml_tag<'a>(
+    mapping: &XmlMapping,
+    name: &str,
+    map: &'a serde_json::Map<String, serde_json::Value>,
+) -> (String, Vec<(&'a str, &'a serde_json::Value)>) {
+    let mut tag = name.to_string();
+    let mut children = Vec::new();
+    for (field, value) in map {
+        let scalar = !matches!(value, serde_json::Value::Object(_) | serde_json::Value::Array(_));
+        if scalar && mapping.node(field) == XmlNode::Attribute {
+            let value = match value {
+                serde_json::Value::String(s) => s.clone(),
+                _ => value.to_string(),
+            };
+            tag.push_str(&format!(" {}=\"{}\"", field, quick_xml::escape::escape(&value)));
+        } else {
+            children.push((field.as_str(), value));
+        }
+    }
+    (tag, children)
+}
+
+/// Sections of an instruction, each with the blank lines around it in the default layout
+#[derive(Default)]
+struct Sections {
+    prefix: String,
+    descriptions: String,
+    format_note: String,
+    example: String,
+    schema: String,
+    examples: String,
+    suffix: String,
+}
+
+impl Sections {
+    /// The sections in the default layout
+    fn concat(self) -> String {
+        [self.prefix, self.descriptions, self.format_note, self.example, self.schema, self.examples, self.suffix].concat()
+    }
+
+    /// `template` with the `{section}` placeholders replaced by the sections
+    fn render(&self, template: &str) -> String {
+        let mut rendered = String::new();
+        for line in template.split_inclusive('\n') {
+            let mut output = String::new();
+            let mut placeholders = 0;
+            let mut filled = false;
+            let mut rest = line;
+            while let Some(start) = rest.find('{') {
+                output.push_str(&rest[..start]);
+                let after = &rest[start + 1..];
+                let section = after.find('}').and_then(|end| Some((end, self.section(&after[..end])?)));
+                match section {
+                    Some((end, section)) => {
+                        placeholders += 1;
+                        filled |= !section.is_empty();
+                        output.push_str(section);
+                        rest = &after[end + 1..];
+                    },
+                    None => {
+                        output.push('{');
+                        rest = after;
+                    }
+                }
+            }
+            output.push_str(rest);
+
+            if placeholders > 0 && !filled && output.trim().is_empty() {
+                continue;
+            }
+            rendered.push_str(&output);
+        }
+        rendered
+    }
+
+    /// The section named `name` without the blank lines around it
+    fn section(&self, name: &str) -> Option<&str> {
+        let section = match name {
+            "prefix" => &self.prefix,
+            "descriptions" => &self.descriptions,
+            "format_note" => &self.format_note,
+            "example" => &self.example,
+            "schema" => &self.schema,
+            "examples" => &self.examples,
+            "suffix" => &self.suffix,
+            _ => return None,
+        };
+        Some(section.trim())
     }
 }
 
-/// Structured instruction
+/// Structured instruction. The content is shared, so clones are cheap
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Instruction {
     /// Instruction content
-    pub content: String,
+    pub content: Arc<str>,
 }
 
 impl Instruction {
     /// Create a new instruction
     pub fn new(content: impl Into<String>) -> Self {
         Self {
-            content: content.into(),
+            content: content.into().into(),
         }
     }
 
+    /// Get the shared instruction text
+    pub fn shared_text(&self) -> Arc<str> {
+        self.content.clone()
+    }
+
     /// Get the instruction text
     pub fn text(&self) -> &str {
         &self.content
@@ -712,6 +2609,12 @@ impl Instruction {
     pub fn content(&self) -> &str {
         &self.content
     }
+
+    /// Number of tokens of the instruction for `model`, exact with the `tiktoken` feature
+    /// and estimated otherwise, see [crate::tokens]
+    pub fn estimated_tokens(&self, model: &str) -> usize {
+        crate::tokens::count_tokens(&self.content, model)
+    }
 }
 
 impl<S: Into<String>> From<S> for Instruction {
@@ -733,6 +2636,273 @@ pub struct Response<T: Serialize + for<'a> Deserialize<'a> + Clone + std::fmt::D
     /// Validation messages (if validation was performed)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub validation_messages: Option<Vec<String>>,
+
+    /// The issues behind [Response::validation_messages], with their keyword and severity
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub validation_issues: Vec<ValidationIssue>,
+
+    /// How the response was parsed
+    #[serde(default)]
+    pub metadata: ResponseMetadata,
+}
+
+/// Item of [crate::structured::Generator::parse_stream]
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamedOutput<T: Serialize + for<'a> Deserialize<'a> + Clone + std::fmt::Debug> {
+    /// The JSON received so far, with open strings, arrays and objects closed. `data` is set
+    /// once `value` deserializes into `T`, e.g. when the missing fields are optional
+    Partial { value: serde_json::Value, data: Option<T> },
+
+    /// The whole response, parsed and validated like [crate::structured::Generator::parse_response]
+    Complete(Response<T>),
+}
+
+/// Details about how a [Response] was parsed
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ResponseMetadata {
+    /// Format the data was extracted as
+    pub format: OutputFormat,
+
+    /// Whether `format` was detected from the response rather than configured
+    pub auto_detected: bool,
+
+    /// Confidence of each parsed leaf field, keyed by path such as `author.name` or
+    /// `items[0].price`. Only filled when parsing with token logprobs
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub field_confidence: BTreeMap<String, FieldConfidence>,
+
+    /// Reasoning removed by the [ReasoningFilter] of the config, in order
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning: Option<String>,
+
+    /// How the block the data was read from was found in the response
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extraction: Option<ExtractionMethod>,
+
+    /// Byte range of that block in [Response::raw_response]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub span: Option<std::ops::Range<usize>>,
+
+    /// Follow-up requests asking the model to fix its output before this response, see
+    /// [crate::structured::Generator::parse_with_retry]
+    #[serde(default)]
+    pub repairs: usize,
+
+    /// Geometric mean of the probabilities of the tokens of the block, between 0 and 1. Only
+    /// filled when parsing with token logprobs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f32>,
+}
+
+/// How the block holding the answer was found in a response
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExtractionMethod {
+    /// Located by the [ExtractionStrategy] of the config, for the JSON formats
+    Strategy,
+    /// The content of a code fence
+    CodeFence,
+    /// The whole response, markdown tables included
+    WholeResponse,
+}
+
+/// Confidence of a parsed field, derived from the log probabilities of the tokens its value
+/// was generated from
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FieldConfidence {
+    /// Geometric mean of the token probabilities, between 0 and 1
+    pub confidence: f32,
+
+    /// Probability of the least likely token
+    pub min_probability: f32,
+
+    /// Number of tokens the value spans
+    pub tokens: usize,
+}
+
+impl<T: Serialize + for<'a> Deserialize<'a> + Clone + std::fmt::Debug> Response<T> {
+    /// Validation issues of at least `severity`, e.g. `issues(Severity::Warning)` for the
+    /// warnings and errors
+    pub fn issues(&self, severity: Severity) -> impl Iterator<Item = &ValidationIssue> {
+        self.validation_issues
+            .iter()
+            .filter(move |issue| issue.severity >= severity)
+    }
+
+    /// Paths of the fields whose confidence is below `threshold`, least confident first
+    pub fn low_confidence_fields(&self, threshold: f32) -> Vec<&str> {
+        let mut fields: Vec<_> = self
+            .metadata
+            .field_confidence
+            .iter()
+            .filter(|(_, field)| field.confidence < threshold)
+            .collect();
+        fields.sort_by(|a, b| a.1.confidence.total_cmp(&b.1.confidence));
+        fields.into_iter().map(|(path, _)| path.as_str()).collect()
+    }
+
+    /// Field level differences of the parsed data from `expected`, e.g. gold labels in an
+    /// evaluation. Both are compared as JSON: fields are addressed by paths such as
+    /// `author.name` or `items[0].price`, absent fields equal `null` ones, and numbers are
+    /// compared by value. Empty when the data matches
+    pub fn diff_against(&self, expected: &T) -> Vec<FieldDiff> {
+        match (serde_json::to_value(expected), serde_json::to_value(&self.data)) {
+            (Ok(expected), Ok(actual)) => crate::structured::diff::diff(&expected, &actual),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// A choice of a completion parsed by [crate::structured::Generator::parse_choices]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate<T: Serialize + for<'a> Deserialize<'a> + Clone + std::fmt::Debug> {
+    /// Index of the choice
+    pub index: u32,
+
+    /// The parsed choice
+    pub response: Response<T>,
+}
+
+impl<T: Serialize + for<'a> Deserialize<'a> + Clone + std::fmt::Debug> Candidate<T> {
+    /// Number of validation errors of the choice, 0 when it is valid
+    pub fn validation_errors(&self) -> usize {
+        self.response.validation_messages.as_ref().map_or(0, Vec::len)
+    }
+}
+
+/// How [Candidates::best_by] picks a candidate. The first choice wins ties
+#[derive(Debug, Clone, Default)]
+pub enum CandidateSelection<T: Serialize + for<'a> Deserialize<'a> + Clone + std::fmt::Debug> {
+    /// The candidate with the fewest validation errors
+    #[default]
+    FewestValidationErrors,
+    /// The candidate with the longest response
+    LongestContent,
+    /// The candidate a function gives the highest score
+    Custom(fn(&Response<T>) -> f64),
+}
+
+/// Every choice of a completion, parsed by [crate::structured::Generator::parse_choices]
+#[derive(Debug)]
+pub struct Candidates<T: Serialize + for<'a> Deserialize<'a> + Clone + std::fmt::Debug> {
+    /// The choices that were parsed, in the order of the completion
+    pub parsed: Vec<Candidate<T>>,
+
+    /// The choices that couldn't be parsed, by index
+    pub failed: Vec<(u32, ParseError)>,
+}
+
+impl<T: Serialize + for<'a> Deserialize<'a> + Clone + std::fmt::Debug> Candidates<T> {
+    /// The candidate with the fewest validation errors, `None` when no choice was parsed
+    pub fn best(&self) -> Option<&Candidate<T>> {
+        self.best_by(&CandidateSelection::default())
+    }
+
+    /// The candidate picked by `selection`, `None` when no choice was parsed
+    pub fn best_by(&self, selection: &CandidateSelection<T>) -> Option<&Candidate<T>> {
+        let score = |candidate: &Candidate<T>| match selection {
+            CandidateSelection::FewestValidationErrors => -(candidate.validation_errors() as f64),
+            CandidateSelection::LongestContent => candidate.response.raw_response.chars().count() as f64,
+            CandidateSelection::Custom(score) => score(&candidate.response),
+        };
+        let mut best: Option<(&Candidate<T>, f64)> = None;
+        for candidate in &self.parsed {
+            let score = score(candidate);
+            if best.map_or(true, |(_, best)| score > best) {
+                best = Some((candidate, score));
+            }
+        }
+        best.map(|(candidate, _)| candidate)
+    }
+
+    /// Consume the candidates, keeping the one picked by `selection`
+    pub fn into_best_by(self, selection: &CandidateSelection<T>) -> Option<Candidate<T>> {
+        let index = self.best_by(selection)?.index;
+        self.parsed.into_iter().find(|candidate| candidate.index == index)
+    }
+}
+
+/// Parsed output that went through moderation and a validation function, see
+/// [crate::structured::Generator::check]. The data is only safe to use when there are no
+/// flags
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(bound(deserialize = "T: for<'a> Deserialize<'a>"))]
+pub struct Checked<T: Serialize + for<'a> Deserialize<'a> + Clone + std::fmt::Debug> {
+    /// The parsed response
+    pub response: Response<T>,
+
+    /// Everything the checks found wrong, empty when the output passed them all
+    pub flags: Vec<CheckFlag>,
+}
+
+impl<T: Serialize + for<'a> Deserialize<'a> + Clone + std::fmt::Debug> Checked<T> {
+    /// Whether the output passed every check
+    pub fn is_clean(&self) -> bool {
+        self.flags.is_empty()
+    }
+
+    /// The data when the output passed every check, the flags otherwise
+    pub fn into_data(self) -> Result<T, Vec<CheckFlag>> {
+        if self.flags.is_empty() {
+            Ok(self.response.data)
+        } else {
+            Err(self.flags)
+        }
+    }
+}
+
+/// Why a [Checked] output should not be used
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CheckFlag {
+    /// The data doesn't match the JSON Schema, from [Response::validation_messages]
+    Schema { message: String },
+    /// The validation function rejected the data
+    Validation { message: String },
+    /// Moderation flagged the raw response in these categories
+    Moderation { categories: Vec<String> },
+}
+
+impl std::fmt::Display for CheckFlag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CheckFlag::Schema { message } => write!(f, "schema: {}", message),
+            CheckFlag::Validation { message } => write!(f, "validation: {}", message),
+            CheckFlag::Moderation { categories } => write!(f, "moderation: {}", categories.join(", ")),
+        }
+    }
+}
+
+/// Difference between a field of parsed data and the value expected for it, see
+/// [Response::diff_against]. The path of the top level value is empty
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FieldDiff {
+    /// The field is expected but was not parsed
+    Missing { path: String, expected: serde_json::Value },
+    /// The field was parsed but is not expected
+    Extra { path: String, actual: serde_json::Value },
+    /// The field was parsed with another value, or another type
+    Mismatch { path: String, expected: serde_json::Value, actual: serde_json::Value },
+}
+
+impl FieldDiff {
+    /// Path of the field
+    pub fn path(&self) -> &str {
+        match self {
+            FieldDiff::Missing { path, .. } | FieldDiff::Extra { path, .. } | FieldDiff::Mismatch { path, .. } => path,
+        }
+    }
+}
+
+impl std::fmt::Display for FieldDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let path = if self.path().is_empty() { "(root)" } else { self.path() };
+        match self {
+            FieldDiff::Missing { expected, .. } => write!(f, "{}: missing, expected {}", path, expected),
+            FieldDiff::Extra { actual, .. } => write!(f, "{}: unexpected {}", path, actual),
+            FieldDiff::Mismatch { expected, actual, .. } => write!(f, "{}: expected {}, got {}", path, expected, actual),
+        }
+    }
 }
 
 /// Error types for parsing structured data
@@ -746,6 +2916,10 @@ pub enum ParseError {
     #[error("Validation error: {0}")]
     ValidationError(String),
 
+    /// The model declined to answer, with its refusal message
+    #[error("Model refused: {0}")]
+    Refusal(String),
+
     /// XML parsing error
     #[cfg(feature = "xml")]
     #[error("XML parsing error: {0}")]
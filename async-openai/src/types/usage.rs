@@ -0,0 +1,207 @@
+use std::{
+    iter::Sum,
+    ops::{Add, AddAssign},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    CompletionUsage, CreateBase64EmbeddingResponse, CreateChatCompletionResponse,
+    CreateChatCompletionStreamResponse, CreateCompletionResponse, CreateEmbeddingResponse,
+    EmbeddingUsage, RunCompletionUsage, RunObject, RunStepCompletionUsage, RunStepObject,
+};
+
+/// Token usage of any endpoint in one shape, so that accounting code doesn't depend on the
+/// endpoint. Breakdowns an endpoint doesn't report are 0.
+///
+/// Usages add up:
+///
+/// ```
+/// use async_openai::types::{CompletionUsage, EmbeddingUsage, Usage};
+/// # let chat: CompletionUsage = serde_json::from_str(r#"{"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}"#).unwrap();
+/// # let embedding: EmbeddingUsage = serde_json::from_str(r#"{"prompt_tokens": 8, "total_tokens": 8}"#).unwrap();
+///
+/// let total: Usage = [Usage::from(&chat), Usage::from(&embedding)].into_iter().sum();
+/// assert_eq!(total.prompt_tokens, 18);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Usage {
+    /// Input tokens, including cached and audio tokens.
+    pub prompt_tokens: u64,
+    /// Output tokens, including reasoning and audio tokens.
+    pub completion_tokens: u64,
+    pub total_tokens: u64,
+    /// Prompt tokens served from the prompt cache.
+    pub cached_tokens: u64,
+    /// Completion tokens spent on reasoning.
+    pub reasoning_tokens: u64,
+    pub prompt_audio_tokens: u64,
+    pub completion_audio_tokens: u64,
+    /// Predicted output tokens that appeared in the completion.
+    pub accepted_prediction_tokens: u64,
+    /// Predicted output tokens that did not appear in the completion, billed nonetheless.
+    pub rejected_prediction_tokens: u64,
+}
+
+impl Usage {
+    /// Prompt tokens that were not served from the cache.
+    pub fn uncached_prompt_tokens(&self) -> u64 {
+        self.prompt_tokens.saturating_sub(self.cached_tokens)
+    }
+}
+
+impl Add for Usage {
+    type Output = Usage;
+
+    fn add(mut self, other: Usage) -> Usage {
+        self += other;
+        self
+    }
+}
+
+impl AddAssign for Usage {
+    fn add_assign(&mut self, other: Usage) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.total_tokens += other.total_tokens;
+        self.cached_tokens += other.cached_tokens;
+        self.reasoning_tokens += other.reasoning_tokens;
+        self.prompt_audio_tokens += other.prompt_audio_tokens;
+        self.completion_audio_tokens += other.completion_audio_tokens;
+        self.accepted_prediction_tokens += other.accepted_prediction_tokens;
+        self.rejected_prediction_tokens += other.rejected_prediction_tokens;
+    }
+}
+
+impl Sum for Usage {
+    fn sum<I: Iterator<Item = Usage>>(iter: I) -> Usage {
+        iter.fold(Usage::default(), Add::add)
+    }
+}
+
+impl From<&CompletionUsage> for Usage {
+    fn from(usage: &CompletionUsage) -> Self {
+        let prompt = usage.prompt_tokens_details.as_ref();
+        let completion = usage.completion_tokens_details.as_ref();
+        let detail = |value: Option<u32>| u64::from(value.unwrap_or_default());
+
+        Usage {
+            prompt_tokens: usage.prompt_tokens.into(),
+            completion_tokens: usage.completion_tokens.into(),
+            total_tokens: usage.total_tokens.into(),
+            cached_tokens: detail(prompt.and_then(|details| details.cached_tokens)),
+            reasoning_tokens: detail(completion.and_then(|details| details.reasoning_tokens)),
+            prompt_audio_tokens: detail(prompt.and_then(|details| details.audio_tokens)),
+            completion_audio_tokens: detail(completion.and_then(|details| details.audio_tokens)),
+            accepted_prediction_tokens: detail(
+                completion.and_then(|details| details.accepted_prediction_tokens),
+            ),
+            rejected_prediction_tokens: detail(
+                completion.and_then(|details| details.rejected_prediction_tokens),
+            ),
+        }
+    }
+}
+
+impl From<&EmbeddingUsage> for Usage {
+    fn from(usage: &EmbeddingUsage) -> Self {
+        Usage {
+            prompt_tokens: usage.prompt_tokens.into(),
+            total_tokens: usage.total_tokens.into(),
+            ..Default::default()
+        }
+    }
+}
+
+impl From<&RunCompletionUsage> for Usage {
+    fn from(usage: &RunCompletionUsage) -> Self {
+        Usage {
+            prompt_tokens: usage.prompt_tokens.into(),
+            completion_tokens: usage.completion_tokens.into(),
+            total_tokens: usage.total_tokens.into(),
+            ..Default::default()
+        }
+    }
+}
+
+impl From<&RunStepCompletionUsage> for Usage {
+    fn from(usage: &RunStepCompletionUsage) -> Self {
+        Usage {
+            prompt_tokens: usage.prompt_tokens.into(),
+            completion_tokens: usage.completion_tokens.into(),
+            total_tokens: usage.total_tokens.into(),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(feature = "realtime")]
+impl From<&super::realtime::Usage> for Usage {
+    fn from(usage: &super::realtime::Usage) -> Self {
+        Usage {
+            prompt_tokens: usage.input_tokens.into(),
+            completion_tokens: usage.output_tokens.into(),
+            total_tokens: usage.total_tokens.into(),
+            ..Default::default()
+        }
+    }
+}
+
+macro_rules! impl_from_owned {
+    ($($usage:ty),*) => {
+        $(impl From<$usage> for Usage {
+            fn from(usage: $usage) -> Self {
+                Usage::from(&usage)
+            }
+        })*
+    };
+}
+
+impl_from_owned!(
+    CompletionUsage,
+    EmbeddingUsage,
+    RunCompletionUsage,
+    RunStepCompletionUsage
+);
+
+#[cfg(feature = "realtime")]
+impl_from_owned!(super::realtime::Usage);
+
+/// Responses that report token usage.
+pub trait HasUsage {
+    /// Usage of the request, `None` when not reported, e.g. on stream chunks other than the
+    /// last one.
+    fn unified_usage(&self) -> Option<Usage>;
+}
+
+macro_rules! impl_has_usage {
+    ($($response:ty => optional),*; $($always:ty),*) => {
+        $(impl HasUsage for $response {
+            fn unified_usage(&self) -> Option<Usage> {
+                self.usage.as_ref().map(Usage::from)
+            }
+        })*
+        $(impl HasUsage for $always {
+            fn unified_usage(&self) -> Option<Usage> {
+                Some(Usage::from(&self.usage))
+            }
+        })*
+    };
+}
+
+impl_has_usage!(
+    CreateChatCompletionResponse => optional,
+    CreateChatCompletionStreamResponse => optional,
+    CreateCompletionResponse => optional,
+    RunObject => optional,
+    RunStepObject => optional;
+    CreateEmbeddingResponse,
+    CreateBase64EmbeddingResponse
+);
+
+#[cfg(feature = "realtime")]
+impl HasUsage for super::realtime::ResponseResource {
+    fn unified_usage(&self) -> Option<Usage> {
+        self.usage.as_ref().map(Usage::from)
+    }
+}
@@ -7,6 +7,34 @@ use tokio_util::codec::{BytesCodec, FramedRead};
 use crate::error::OpenAIError;
 use crate::types::InputSource;
 
+/// Check that a raw request is a JSON object. For endpoints that can stream, `stream` holds
+/// whether this method streams and the method to use otherwise: a conflicting `stream` field
+/// is rejected and a missing one is set.
+pub(crate) fn prepare_raw_request(
+    request: &mut serde_json::Value,
+    stream: Option<(bool, &str)>,
+) -> Result<(), OpenAIError> {
+    let object = request
+        .as_object_mut()
+        .ok_or_else(|| OpenAIError::InvalidArgument("Raw request must be a JSON object".into()))?;
+
+    if let Some((stream, alternative)) = stream {
+        match object.get("stream") {
+            Some(serde_json::Value::Bool(value)) if *value != stream => {
+                return Err(OpenAIError::InvalidArgument(format!(
+                    "When stream is {value}, use {alternative}"
+                )));
+            }
+            _ => {
+                if stream {
+                    object.insert("stream".into(), true.into());
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
 pub(crate) async fn file_stream_body(source: InputSource) -> Result<Body, OpenAIError> {
     let body = match source {
         InputSource::Path { path } => {
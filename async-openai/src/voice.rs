@@ -0,0 +1,379 @@
+//! Speech in, speech out: a [VoicePipeline] transcribes the user's audio, answers it with a
+//! chat completion that keeps the conversation history and reads the answer out loud.
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), async_openai::error::OpenAIError> {
+//! use async_openai::{types::AudioInput, voice::VoicePipeline, Client};
+//!
+//! let client = Client::new();
+//! let mut assistant = VoicePipeline::new(&client)
+//!     .with_instructions("You are a helpful assistant. Answer in one or two sentences.");
+//!
+//! let turn = assistant
+//!     .respond(AudioInput::from_vec_u8("question.mp3".into(), std::fs::read("question.mp3").unwrap()))
+//!     .await?;
+//! println!("> {}\n{}", turn.transcript, turn.reply);
+//! std::fs::write("answer.mp3", &turn.speech).unwrap();
+//! # Ok(())
+//! # }
+//! ```
+use std::{collections::VecDeque, pin::Pin};
+
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+
+use crate::{
+    config::Config,
+    context_length::ContextLengthPolicy,
+    error::OpenAIError,
+    memory::MemoryStrategy,
+    types::{
+        AudioInput, ChatCompletionRequestAssistantMessage, ChatCompletionRequestMessage,
+        ChatCompletionRequestSystemMessage, ChatCompletionRequestUserMessage,
+        ChatCompletionResponseStream, CreateChatCompletionRequest, CreateSpeechRequest,
+        CreateTranscriptionRequest, HasUsage, SpeechModel, SpeechResponseFormat, Usage, Voice,
+    },
+    Client,
+};
+
+/// One exchange of a [VoicePipeline].
+#[derive(Debug, Clone, PartialEq)]
+pub struct VoiceTurn {
+    /// What the user said.
+    pub transcript: String,
+    /// Text of the answer.
+    pub reply: String,
+    /// The answer read out loud, in the pipeline's speech format.
+    pub speech: Bytes,
+    /// Usage of the chat completion, when reported.
+    pub usage: Option<Usage>,
+}
+
+/// Events of [VoicePipeline::respond_stream], in the order they happen.
+#[derive(Debug, Clone, PartialEq)]
+pub enum VoiceEvent {
+    /// The user's audio was transcribed.
+    Transcript(String),
+    /// Text generated for the answer.
+    ReplyDelta(String),
+    /// Audio of the next sentence of the answer.
+    Speech { text: String, audio: Bytes },
+}
+
+/// Events of one turn of a [VoicePipeline], ending with the last sentence of the answer.
+pub type VoiceEventStream<'p> = Pin<Box<dyn Stream<Item = Result<VoiceEvent, OpenAIError>> + 'p>>;
+
+/// Chains transcription, chat completion and speech synthesis, keeping the conversation
+/// history between turns.
+///
+/// Defaults to `whisper-1`, `gpt-4o-mini` and `tts-1` with the `alloy` voice.
+pub struct VoicePipeline<'c, C: Config> {
+    client: &'c Client<C>,
+    transcription_model: String,
+    language: Option<String>,
+    chat_model: String,
+    speech_model: SpeechModel,
+    voice: Voice,
+    speech_format: Option<SpeechResponseFormat>,
+    context_policy: Option<ContextLengthPolicy>,
+    memory: Option<Box<dyn MemoryStrategy + 'c>>,
+    messages: Vec<ChatCompletionRequestMessage>,
+}
+
+impl<'c, C: Config> VoicePipeline<'c, C> {
+    pub fn new(client: &'c Client<C>) -> Self {
+        Self {
+            client,
+            transcription_model: "whisper-1".into(),
+            language: None,
+            chat_model: "gpt-4o-mini".into(),
+            speech_model: SpeechModel::Tts1,
+            voice: Voice::Alloy,
+            speech_format: None,
+            context_policy: None,
+            memory: None,
+            messages: Vec::new(),
+        }
+    }
+
+    /// Add a system message to the conversation.
+    pub fn with_instructions(mut self, instructions: impl Into<String>) -> Self {
+        self.messages
+            .push(ChatCompletionRequestSystemMessage::from(instructions.into()).into());
+        self
+    }
+
+    pub fn with_transcription_model(mut self, model: impl Into<String>) -> Self {
+        self.transcription_model = model.into();
+        self
+    }
+
+    /// Language of the user's audio in ISO-639-1 format, detected otherwise.
+    pub fn with_language(mut self, language: impl Into<String>) -> Self {
+        self.language = Some(language.into());
+        self
+    }
+
+    pub fn with_chat_model(mut self, model: impl Into<String>) -> Self {
+        self.chat_model = model.into();
+        self
+    }
+
+    pub fn with_speech_model(mut self, model: SpeechModel) -> Self {
+        self.speech_model = model;
+        self
+    }
+
+    pub fn with_voice(mut self, voice: Voice) -> Self {
+        self.voice = voice;
+        self
+    }
+
+    pub fn with_speech_format(mut self, format: SpeechResponseFormat) -> Self {
+        self.speech_format = Some(format);
+        self
+    }
+
+    /// Trim (or summarize) the oldest turns when the conversation outgrows the context window
+    /// of the chat model. Only applies to [VoicePipeline::respond].
+    pub fn with_context_policy(mut self, policy: ContextLengthPolicy) -> Self {
+        self.context_policy = Some(policy);
+        self
+    }
+
+    /// Compress the history with `memory` before every turn, e.g. to keep long conversations
+    /// within a token budget. See [crate::memory].
+    pub fn with_memory(mut self, memory: impl MemoryStrategy + 'c) -> Self {
+        self.memory = Some(Box::new(memory));
+        self
+    }
+
+    /// The conversation so far, including the instructions.
+    pub fn messages(&self) -> &[ChatCompletionRequestMessage] {
+        &self.messages
+    }
+
+    /// Forget every turn, keeping the instructions.
+    pub fn clear(&mut self) {
+        self.messages
+            .retain(|message| matches!(message, ChatCompletionRequestMessage::System(_)));
+    }
+
+    /// Transcribe `audio`, answer it and synthesize the answer. The turn is added to the
+    /// conversation only when every stage succeeds.
+    pub async fn respond(&mut self, audio: AudioInput) -> Result<VoiceTurn, OpenAIError> {
+        let transcript = self.transcribe(audio).await?;
+
+        self.compact().await?;
+        let request = self.chat_request(&transcript, None);
+        let response = match &self.context_policy {
+            Some(policy) => {
+                self.client
+                    .chat()
+                    .create_with_context_policy(request, policy)
+                    .await?
+            }
+            None => self.client.chat().create(request).await?,
+        };
+        let usage = response.unified_usage();
+        let reply = response
+            .choices
+            .into_iter()
+            .next()
+            .and_then(|choice| choice.message.content)
+            .unwrap_or_default();
+
+        let speech = self.synthesize(&reply).await?;
+        self.push_turn(&transcript, &reply);
+
+        Ok(VoiceTurn {
+            transcript,
+            reply,
+            speech,
+            usage,
+        })
+    }
+
+    /// Same as [VoicePipeline::respond], but streams the answer and synthesizes it sentence by
+    /// sentence, so playback can start before the answer is complete.
+    ///
+    /// The turn is added to the conversation once the answer is complete.
+    pub fn respond_stream(&mut self, audio: AudioInput) -> VoiceEventStream<'_> {
+        let state = StreamState {
+            pipeline: self,
+            stage: Stage::Transcribe(audio),
+            events: VecDeque::new(),
+        };
+
+        Box::pin(futures::stream::unfold(Some(state), |state| async move {
+            let mut state = state?;
+            loop {
+                if let Some(event) = state.events.pop_front() {
+                    return Some((Ok(event), Some(state)));
+                }
+                match state.advance().await {
+                    Ok(true) => {}
+                    Ok(false) => return None,
+                    Err(e) => return Some((Err(e), None)),
+                }
+            }
+        }))
+    }
+
+    async fn transcribe(&self, audio: AudioInput) -> Result<String, OpenAIError> {
+        let request = CreateTranscriptionRequest {
+            file: audio,
+            model: self.transcription_model.clone(),
+            language: self.language.clone(),
+            ..Default::default()
+        };
+        Ok(self.client.audio().transcribe(request).await?.text)
+    }
+
+    async fn synthesize(&self, text: &str) -> Result<Bytes, OpenAIError> {
+        let request = CreateSpeechRequest {
+            input: text.to_string(),
+            model: self.speech_model.clone(),
+            voice: self.voice.clone(),
+            response_format: self.speech_format,
+            speed: None,
+        };
+        Ok(self.client.audio().speech(request).await?.bytes)
+    }
+
+    /// Apply the memory strategy to the conversation so far.
+    async fn compact(&mut self) -> Result<(), OpenAIError> {
+        match &mut self.memory {
+            Some(memory) => memory.compact(&mut self.messages).await,
+            None => Ok(()),
+        }
+    }
+
+    fn chat_request(&self, transcript: &str, stream: Option<bool>) -> CreateChatCompletionRequest {
+        let mut messages = self.messages.clone();
+        messages.push(ChatCompletionRequestUserMessage::from(transcript).into());
+        CreateChatCompletionRequest {
+            model: self.chat_model.clone(),
+            messages,
+            stream,
+            ..Default::default()
+        }
+    }
+
+    fn push_turn(&mut self, transcript: &str, reply: &str) {
+        self.messages
+            .push(ChatCompletionRequestUserMessage::from(transcript).into());
+        self.messages
+            .push(ChatCompletionRequestAssistantMessage::from(reply).into());
+    }
+}
+
+enum Stage {
+    Transcribe(AudioInput),
+    Reply {
+        transcript: String,
+        stream: ChatCompletionResponseStream,
+        reply: String,
+        // Text not synthesized yet
+        pending: String,
+    },
+    Done,
+}
+
+struct StreamState<'p, 'c, C: Config> {
+    pipeline: &'p mut VoicePipeline<'c, C>,
+    stage: Stage,
+    events: VecDeque<VoiceEvent>,
+}
+
+impl<'p, 'c, C: Config> StreamState<'p, 'c, C> {
+    /// Run the pipeline until it queues events, returning `false` once it is done.
+    async fn advance(&mut self) -> Result<bool, OpenAIError> {
+        match std::mem::replace(&mut self.stage, Stage::Done) {
+            Stage::Transcribe(audio) => {
+                let transcript = self.pipeline.transcribe(audio).await?;
+                self.pipeline.compact().await?;
+                let request = self.pipeline.chat_request(&transcript, Some(true));
+                let stream = self.pipeline.client.chat().create_stream(request).await?;
+                self.events
+                    .push_back(VoiceEvent::Transcript(transcript.clone()));
+                self.stage = Stage::Reply {
+                    transcript,
+                    stream,
+                    reply: String::new(),
+                    pending: String::new(),
+                };
+                Ok(true)
+            }
+            Stage::Reply {
+                transcript,
+                mut stream,
+                mut reply,
+                mut pending,
+            } => {
+                match stream.next().await {
+                    Some(chunk) => {
+                        let delta = chunk?
+                            .choices
+                            .into_iter()
+                            .next()
+                            .and_then(|choice| choice.delta.content)
+                            .unwrap_or_default();
+                        if !delta.is_empty() {
+                            reply.push_str(&delta);
+                            pending.push_str(&delta);
+                            self.events.push_back(VoiceEvent::ReplyDelta(delta));
+                        }
+                        while let Some(sentence) = take_sentence(&mut pending) {
+                            self.speak(sentence).await?;
+                        }
+                        self.stage = Stage::Reply {
+                            transcript,
+                            stream,
+                            reply,
+                            pending,
+                        };
+                    }
+                    None => {
+                        let rest = pending.trim();
+                        if !rest.is_empty() {
+                            self.speak(rest.to_string()).await?;
+                        }
+                        self.pipeline.push_turn(&transcript, &reply);
+                    }
+                }
+                Ok(true)
+            }
+            Stage::Done => Ok(false),
+        }
+    }
+
+    async fn speak(&mut self, text: String) -> Result<(), OpenAIError> {
+        let audio = self.pipeline.synthesize(&text).await?;
+        self.events.push_back(VoiceEvent::Speech { text, audio });
+        Ok(())
+    }
+}
+
+/// Remove the first complete sentence from `text`, ending at `.`, `!`, `?` or a line break
+/// followed by whitespace.
+fn take_sentence(text: &mut String) -> Option<String> {
+    let mut chars = text.char_indices().peekable();
+    while let Some((index, c)) = chars.next() {
+        let next = chars.peek().map(|(_, next)| *next);
+        let ends =
+            c == '\n' || matches!(c, '.' | '!' | '?') && next.is_some_and(char::is_whitespace);
+        if !ends {
+            continue;
+        }
+
+        let end = index + c.len_utf8();
+        let sentence = text[..end].trim().to_string();
+        text.replace_range(..end, "");
+        if !sentence.is_empty() {
+            return Some(sentence);
+        }
+        return take_sentence(text);
+    }
+    None
+}
@@ -0,0 +1,205 @@
+use std::{
+    io::{BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+};
+
+use async_openai::{
+    audit_trail::{self, AuditRecord, AuditRecorder, REDACTED},
+    config::OpenAIConfig,
+    types::{ChatCompletionRequestUserMessage, CreateChatCompletionRequestArgs},
+    Client,
+};
+
+/// Read a request up to the end of its JSON body.
+fn read_body(stream: &mut TcpStream) {
+    let mut request = String::new();
+    let mut buf = [0; 16384];
+    loop {
+        let n = stream.read(&mut buf).unwrap();
+        request.push_str(&String::from_utf8_lossy(&buf[..n]));
+        if let Some((_, body)) = request.split_once("\r\n\r\n") {
+            if serde_json::from_str::<serde_json::Value>(body).is_ok() {
+                return;
+            }
+        }
+    }
+}
+
+/// Answer every request with a completion saying `content`.
+fn serve(content: &'static str) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            read_body(&mut stream);
+            let body = serde_json::json!({
+                "id": "chatcmpl-1",
+                "object": "chat.completion",
+                "created": 0,
+                "model": "gpt-4o-mini",
+                "choices": [{
+                    "index": 0,
+                    "message": { "role": "assistant", "content": content },
+                    "finish_reason": "stop"
+                }]
+            })
+            .to_string();
+            let head = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+                body.len()
+            );
+            stream.write_all(head.as_bytes()).unwrap();
+            stream.write_all(body.as_bytes()).unwrap();
+        }
+    });
+
+    format!("http://{addr}/v1")
+}
+
+fn log_path() -> std::path::PathBuf {
+    std::env::temp_dir().join(format!(
+        "async-openai-audit-{}-{}/chat.jsonl",
+        std::process::id(),
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos()
+    ))
+}
+
+fn records(path: &std::path::Path) -> Vec<AuditRecord> {
+    std::fs::read_to_string(path)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn exchanges_are_recorded_in_a_hash_chain() {
+    let api_base = serve("Your SSN 123-45-6789 is on file.");
+    let path = log_path();
+    let client = |recorder| {
+        Client::with_config(
+            OpenAIConfig::new()
+                .with_api_base(&api_base)
+                .with_api_key("sk-test"),
+        )
+        .with_audit_recorder(recorder)
+    };
+    let request = CreateChatCompletionRequestArgs::default()
+        .model("gpt-4o-mini")
+        .messages([ChatCompletionRequestUserMessage::from("Is 123-45-6789 on file?").into()])
+        .user("alice")
+        .build()
+        .unwrap();
+
+    let recorder = AuditRecorder::open(&path)
+        .unwrap()
+        .redact_field("user")
+        .redact_pattern(regex::Regex::new(r"\d{3}-\d{2}-\d{4}").unwrap());
+    let response = client(recorder)
+        .chat()
+        .create(request.clone())
+        .await
+        .unwrap();
+    assert_eq!(
+        response.choices[0].message.content.as_deref(),
+        Some("Your SSN 123-45-6789 is on file.")
+    );
+
+    // Reopening continues the chain
+    let recorder = AuditRecorder::open(&path).unwrap();
+    client(recorder)
+        .chat()
+        .create_raw(serde_json::to_value(&request).unwrap())
+        .await
+        .unwrap();
+
+    let records = records(&path);
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].path, "/chat/completions");
+    assert_eq!(records[0].request["user"], REDACTED);
+    assert_eq!(
+        records[0].request["messages"][0]["content"],
+        format!("Is {REDACTED} on file?")
+    );
+    assert_eq!(
+        records[0].response.as_ref().unwrap()["choices"][0]["message"]["content"],
+        format!("Your SSN {REDACTED} is on file.")
+    );
+    assert_eq!(records[1].sequence, 1);
+    assert_eq!(records[1].previous_hash, records[0].hash);
+    assert_eq!(records[1].request["user"], "alice");
+
+    let file = || BufReader::new(std::fs::File::open(&path).unwrap());
+    assert_eq!(audit_trail::verify(file()).unwrap(), 2);
+
+    let tampered = std::fs::read_to_string(&path)
+        .unwrap()
+        .replacen(REDACTED, "[hidden]", 1);
+    std::fs::write(&path, tampered).unwrap();
+    assert!(audit_trail::verify(file()).is_err());
+
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+/// A log that can't be written to.
+struct BrokenLog;
+
+impl Write for BrokenLog {
+    fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+        Err(std::io::Error::other("disk full"))
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn unwritable_records_keep_the_response() {
+    let client = Client::with_config(
+        OpenAIConfig::new()
+            .with_api_base(serve("Paid for"))
+            .with_api_key("sk-test"),
+    )
+    .with_audit_recorder(AuditRecorder::new(BrokenLog));
+    let request = CreateChatCompletionRequestArgs::default()
+        .model("gpt-4o-mini")
+        .messages([ChatCompletionRequestUserMessage::from("Hello").into()])
+        .build()
+        .unwrap();
+
+    let response = client.chat().create(request).await.unwrap();
+    assert_eq!(
+        response.choices[0].message.content.as_deref(),
+        Some("Paid for")
+    );
+}
+
+#[tokio::test]
+async fn failed_calls_are_recorded() {
+    let path = log_path();
+    let client = Client::with_config(
+        OpenAIConfig::new()
+            .with_api_base("http://127.0.0.1:1/v1")
+            .with_api_key("sk-test"),
+    )
+    .with_audit_recorder(AuditRecorder::open(&path).unwrap());
+
+    let result = client
+        .chat()
+        .create_raw(serde_json::json!({ "model": "gpt-4o-mini", "messages": [] }))
+        .await;
+    assert!(result.is_err());
+
+    let records = records(&path);
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].response, None);
+    assert!(records[0].error.is_some());
+
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
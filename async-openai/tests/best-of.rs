@@ -0,0 +1,169 @@
+use std::{
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use async_openai::{
+    best_of::{BestOf, Sampling},
+    config::OpenAIConfig,
+    types::{
+        ChatCompletionRequestUserMessage, CreateChatCompletionRequest,
+        CreateChatCompletionRequestArgs,
+    },
+    Client,
+};
+use serde_json::{json, Value};
+
+/// Read a request up to the end of its JSON body.
+fn read_body(stream: &mut TcpStream) -> Value {
+    let mut request = String::new();
+    let mut buf = [0; 16384];
+    loop {
+        let n = stream.read(&mut buf).unwrap();
+        request.push_str(&String::from_utf8_lossy(&buf[..n]));
+        if let Some((_, body)) = request.split_once("\r\n\r\n") {
+            if let Ok(body) = serde_json::from_str(body) {
+                return body;
+            }
+        }
+    }
+}
+
+/// Answer `count` requests with the completion `respond` gives for their body.
+fn serve(count: usize, respond: fn(&Value) -> Value) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    std::thread::spawn(move || {
+        for _ in 0..count {
+            let (mut stream, _) = listener.accept().unwrap();
+            let body = respond(&read_body(&mut stream)).to_string();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+
+    format!("http://{addr}/v1")
+}
+
+fn completion(contents: &[&str]) -> Value {
+    let choices: Vec<Value> = contents
+        .iter()
+        .enumerate()
+        .map(|(index, content)| {
+            json!({
+                "index": index,
+                "message": { "role": "assistant", "content": content },
+                "finish_reason": "stop"
+            })
+        })
+        .collect();
+    json!({
+        "id": "chatcmpl-1",
+        "object": "chat.completion",
+        "created": 1,
+        "model": "gpt-4o",
+        "choices": choices,
+        "usage": { "prompt_tokens": 5, "completion_tokens": 3, "total_tokens": 8 }
+    })
+}
+
+fn client(api_base: String) -> Client<OpenAIConfig> {
+    Client::with_config(
+        OpenAIConfig::new()
+            .with_api_base(api_base)
+            .with_api_key("sk-test"),
+    )
+}
+
+fn request() -> CreateChatCompletionRequest {
+    CreateChatCompletionRequestArgs::default()
+        .model("gpt-4o")
+        .messages([ChatCompletionRequestUserMessage::from("Name a colour").into()])
+        .build()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn candidates_are_requested_with_n_and_scored_by_function() {
+    let api_base = serve(1, |body| {
+        assert_eq!(body["n"], json!(3));
+        completion(&["red", "turquoise", "blue"])
+    });
+    let policy = BestOf::scored_by(3, |choice| {
+        choice.message.content.as_deref().map_or(0, str::len) as f64
+    });
+
+    let best = client(api_base)
+        .chat()
+        .create_best_of(request(), &policy)
+        .await
+        .unwrap();
+
+    assert_eq!(best.choice.message.content.as_deref(), Some("turquoise"));
+    assert_eq!(best.score, 9.0);
+    let scores: Vec<f64> = best.candidates.iter().map(|c| c.score).collect();
+    assert_eq!(scores, [3.0, 9.0, 4.0]);
+}
+
+static SAMPLED: AtomicUsize = AtomicUsize::new(0);
+
+#[tokio::test]
+async fn parallel_candidates_are_scored_by_grader() {
+    let api_base = serve(4, |body| {
+        if body["model"] == "grader" {
+            // The candidate is the last assistant message before the grading prompt
+            let messages = body["messages"].as_array().unwrap();
+            let candidate = &messages[messages.len() - 2]["content"];
+            let score = if candidate == "green" {
+                "8"
+            } else {
+                "Score: 2/10"
+            };
+            completion(&[score])
+        } else {
+            assert!(body.get("n").is_none());
+            let colour = ["green", "grey"][SAMPLED.fetch_add(1, Ordering::SeqCst)];
+            completion(&[colour])
+        }
+    });
+    let policy = BestOf::graded(2, "grader").sampling(Sampling::Parallel);
+
+    let best = client(api_base)
+        .chat()
+        .create_best_of(request(), &policy)
+        .await
+        .unwrap();
+
+    assert_eq!(best.choice.message.content.as_deref(), Some("green"));
+    assert_eq!(best.score, 8.0);
+    assert_eq!(best.candidates.len(), 2);
+    // Requests may reach the server in any order, candidates are indexed as returned
+    let mut colours: Vec<_> = best
+        .candidates
+        .iter()
+        .map(|c| (c.choice.message.content.clone().unwrap(), c.score))
+        .collect();
+    colours.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(colours, [("green".into(), 8.0), ("grey".into(), 2.0)]);
+    let indexes: Vec<u32> = best.candidates.iter().map(|c| c.choice.index).collect();
+    assert_eq!(indexes, [0, 1]);
+    assert_eq!(best.usage.unwrap().total_tokens, 16);
+}
+
+#[tokio::test]
+async fn zero_candidates_is_an_error() {
+    let policy = BestOf::scored_by(0, |_| 0.0);
+
+    let error = client("http://127.0.0.1:1/v1".into())
+        .chat()
+        .create_best_of(request(), &policy)
+        .await
+        .unwrap_err();
+
+    assert!(error.to_string().contains("at least one candidate"));
+}
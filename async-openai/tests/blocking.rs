@@ -0,0 +1,85 @@
+use std::{
+    io::{Read, Write},
+    net::TcpListener,
+};
+
+use async_openai::{
+    blocking::Client,
+    config::OpenAIConfig,
+    types::{ChatCompletionRequestUserMessage, CreateChatCompletionRequestArgs},
+};
+
+/// Serve a single HTTP response on a local port and return its base url.
+fn serve_once(content_type: &'static str, body: String) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut buf = [0; 8192];
+        let _ = stream.read(&mut buf).unwrap();
+        let response = format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: {content_type}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+            body.len()
+        );
+        stream.write_all(response.as_bytes()).unwrap();
+    });
+
+    format!("http://{addr}/v1")
+}
+
+fn client(api_base: String) -> Client<OpenAIConfig> {
+    Client::with_config(
+        OpenAIConfig::new()
+            .with_api_base(api_base)
+            .with_api_key("sk-test"),
+    )
+    .unwrap()
+}
+
+#[test]
+fn blocking_chat_stream_iterates_chunks() {
+    let chunk = |content: &str| {
+        serde_json::json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion.chunk",
+            "created": 1700000000,
+            "model": "gpt-4o-mini",
+            "choices": [{ "index": 0, "delta": { "content": content }, "finish_reason": null }]
+        })
+    };
+    let body = format!(
+        "data: {}\n\ndata: {}\n\ndata: [DONE]\n\n",
+        chunk("Hel"),
+        chunk("lo")
+    );
+    let client = client(serve_once("text/event-stream", body));
+
+    let request = CreateChatCompletionRequestArgs::default()
+        .model("gpt-4o-mini")
+        .messages([ChatCompletionRequestUserMessage::from("Hi").into()])
+        .build()
+        .unwrap();
+
+    let text: String = client
+        .chat()
+        .create_stream(request)
+        .unwrap()
+        .map(|chunk| chunk.unwrap().choices[0].delta.content.clone().unwrap())
+        .collect();
+
+    assert_eq!(text, "Hello");
+}
+
+#[test]
+fn blocking_models_list() {
+    let body = serde_json::json!({
+        "object": "list",
+        "data": [{ "id": "gpt-4o", "object": "model", "created": 1700000000, "owned_by": "openai" }]
+    })
+    .to_string();
+    let client = client(serve_once("application/json", body));
+
+    let models = client.models().list().unwrap();
+    assert_eq!(models.data[0].id, "gpt-4o");
+}
@@ -73,6 +73,15 @@ async fn test_byot_chat() {
     let _r: Result<Value, OpenAIError> = client.chat().create_byot(json!({})).await;
     let _r: Result<MyStreamingType, OpenAIError> =
         client.chat().create_stream_byot(json!({})).await;
+    let _r: Result<Value, OpenAIError> = client.chat().list_byot([("limit", "2")]).await;
+    let _r: Result<Value, OpenAIError> = client.chat().retrieve_byot("completion_id").await;
+    let _r: Result<Value, OpenAIError> = client
+        .chat()
+        .messages_byot("completion_id", [("limit", "2")])
+        .await;
+    let _r: Result<Value, OpenAIError> =
+        client.chat().update_byot("completion_id", json!({})).await;
+    let _r: Result<Value, OpenAIError> = client.chat().delete_byot("completion_id").await;
 }
 
 #[tokio::test]
@@ -0,0 +1,46 @@
+use async_openai::{
+    canonical::{canonical_hash, canonical_json},
+    types::{ChatCompletionRequestUserMessage, CreateChatCompletionRequestArgs},
+};
+use serde_json::json;
+
+#[test]
+fn canonical_json_sorts_keys_and_normalizes_numbers() {
+    let value = json!({
+        "temperature": 0.7_f32,
+        "model": "gpt-4o-mini",
+        "max_tokens": 100.0,
+        "top_p": null,
+        "user": "user-1234",
+        "messages": [{ "role": "user", "content": "Hi", "name": null }],
+    });
+    assert_eq!(
+        canonical_json(&value),
+        r#"{"max_tokens":100,"messages":[{"content":"Hi","role":"user"}],"model":"gpt-4o-mini","temperature":0.7}"#
+    );
+}
+
+#[test]
+fn requests_built_differently_share_a_hash() {
+    let built = CreateChatCompletionRequestArgs::default()
+        .model("gpt-4o-mini")
+        .messages([ChatCompletionRequestUserMessage::from("Hi").into()])
+        .temperature(0.2)
+        .metadata(json!({ "trace": "abc" }))
+        .build()
+        .unwrap();
+    let raw = json!({
+        "messages": [{ "content": "Hi", "role": "user" }],
+        "temperature": 0.2,
+        "model": "gpt-4o-mini",
+    });
+    assert_eq!(canonical_hash(&built), canonical_hash(&raw));
+    assert_eq!(canonical_hash(&built).len(), 64);
+
+    let warmer = json!({
+        "messages": [{ "content": "Hi", "role": "user" }],
+        "temperature": 0.3,
+        "model": "gpt-4o-mini",
+    });
+    assert_ne!(canonical_hash(&built), canonical_hash(&warmer));
+}
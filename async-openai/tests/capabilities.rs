@@ -0,0 +1,91 @@
+use async_openai::{
+    capabilities::{ModelCapabilities, ModelRegistry},
+    error::OpenAIError,
+    types::{
+        ChatCompletionRequestMessageContentPartImageArgs, ChatCompletionRequestUserMessageArgs,
+        ChatCompletionToolArgs, CreateChatCompletionRequestArgs, FunctionObjectArgs,
+    },
+};
+
+#[test]
+fn reports_every_violation_for_reasoning_model() {
+    let image = ChatCompletionRequestMessageContentPartImageArgs::default()
+        .image_url("https://example.com/receipt.png")
+        .build()
+        .unwrap();
+
+    let result = CreateChatCompletionRequestArgs::default()
+        .model("o1-mini")
+        .temperature(0.2)
+        .tools(vec![ChatCompletionToolArgs::default()
+            .function(
+                FunctionObjectArgs::default()
+                    .name("lookup")
+                    .build()
+                    .unwrap(),
+            )
+            .build()
+            .unwrap()])
+        .messages([ChatCompletionRequestUserMessageArgs::default()
+            .content(vec![image.into()])
+            .build()
+            .unwrap()
+            .into()])
+        .build_validated();
+
+    match result {
+        Err(OpenAIError::UnsupportedByModel { model, violations }) => {
+            assert_eq!(model, "o1-mini");
+            assert_eq!(violations.len(), 3, "{violations:?}");
+        }
+        other => panic!("expected UnsupportedByModel, got {other:?}"),
+    }
+}
+
+#[test]
+fn unknown_models_and_custom_entries() {
+    let args = CreateChatCompletionRequestArgs::default()
+        .model("my-local-model")
+        .temperature(0.2)
+        .messages([ChatCompletionRequestUserMessageArgs::default()
+            .content("hi")
+            .build()
+            .unwrap()
+            .into()])
+        .clone();
+
+    assert!(args.build_validated().is_ok());
+
+    let registry = ModelRegistry::default().register(
+        "my-local",
+        ModelCapabilities {
+            sampling: false,
+            tools: false,
+            vision: false,
+            audio: false,
+            reasoning_effort: false,
+            max_tokens: true,
+            structured_outputs: false,
+        },
+    );
+    assert!(args.build_validated_with(&registry).is_err());
+}
+
+#[test]
+fn previews_resolve_to_their_own_entries() {
+    let registry = ModelRegistry::default();
+    let lookup = |model| registry.lookup(model).unwrap();
+
+    assert!(lookup("gpt-4o-mini-audio-preview").audio);
+    assert!(!lookup("gpt-4o-mini-audio-preview").vision);
+    assert!(lookup("gpt-4o-audio-preview-2024-12-17").audio);
+    assert!(!lookup("gpt-4o-mini").audio);
+
+    assert!(lookup("gpt-4.5-preview").vision);
+    assert!(lookup("gpt-4-vision-preview").vision);
+    assert!(!lookup("gpt-4-vision-preview").tools);
+    assert!(lookup("gpt-4-1106-vision-preview").vision);
+    assert!(!lookup("gpt-4-turbo-preview").vision);
+    assert!(lookup("gpt-4-turbo-2024-04-09").vision);
+    assert!(!lookup("gpt-4-0613").vision);
+}
@@ -0,0 +1,46 @@
+use async_openai::types::{Embedding, HalfEmbedding, QuantizationError, QuantizedEmbedding};
+
+fn embedding() -> Embedding {
+    serde_json::from_value(serde_json::json!({
+        "index": 0,
+        "object": "embedding",
+        "embedding": [0.12, -0.5, 0.031, 0.9, -0.0007, 0.25]
+    }))
+    .unwrap()
+}
+
+#[test]
+fn f16_round_trip() {
+    let embedding = embedding();
+    let (half, error) = embedding.to_f16_with_error();
+
+    assert_eq!(half.len(), 6);
+    assert!(error.max_abs < 1e-3, "{error:?}");
+    assert!(error.cosine_similarity > 0.9999);
+
+    let restored = HalfEmbedding::from_le_bytes(&half.to_le_bytes());
+    assert_eq!(restored, half);
+}
+
+#[test]
+fn int8_round_trip_and_dot() {
+    let embedding = embedding();
+    let (quantized, error) = embedding.quantize_i8_with_error();
+
+    assert_eq!(quantized.values[3], 127);
+    assert!(
+        error.max_abs <= quantized.scale / 2.0 + f32::EPSILON,
+        "{error:?}"
+    );
+    assert!(error.cosine_similarity > 0.999);
+
+    let exact: f32 = embedding.embedding.iter().map(|v| v * v).sum();
+    assert!((quantized.dot(&quantized) - exact).abs() < 0.01);
+
+    let zeros = QuantizedEmbedding::from_f32(&[0.0; 4]);
+    assert_eq!(zeros.to_f32(), vec![0.0; 4]);
+    assert_eq!(
+        QuantizationError::between(&[0.0; 4], &zeros.to_f32()).cosine_similarity,
+        1.0
+    );
+}
@@ -0,0 +1,226 @@
+use std::{
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::mpsc,
+};
+
+use async_openai::{
+    config::OpenAIConfig,
+    context_length::ContextLengthPolicy,
+    error::OpenAIError,
+    types::{
+        ChatCompletionRequestAssistantMessage, ChatCompletionRequestMessage,
+        ChatCompletionRequestSystemMessage, ChatCompletionRequestUserMessage,
+        CreateChatCompletionRequest, CreateChatCompletionRequestArgs,
+    },
+    Client,
+};
+use serde_json::{json, Value};
+
+/// Read a request up to the end of its JSON body.
+fn read_body(stream: &mut TcpStream) -> Value {
+    let mut request = String::new();
+    let mut buf = [0; 16384];
+    loop {
+        let n = stream.read(&mut buf).unwrap();
+        request.push_str(&String::from_utf8_lossy(&buf[..n]));
+        if let Some((_, body)) = request.split_once("\r\n\r\n") {
+            if let Ok(body) = serde_json::from_str(body) {
+                return body;
+            }
+        }
+    }
+}
+
+/// Answer requests with `responses` in order, sending each request body to the returned
+/// receiver.
+fn serve(responses: Vec<(u16, String)>) -> (String, mpsc::Receiver<Value>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (tx, rx) = mpsc::channel();
+
+    std::thread::spawn(move || {
+        for (status, body) in responses {
+            let (mut stream, _) = listener.accept().unwrap();
+            let _ = tx.send(read_body(&mut stream));
+            let response = format!(
+                "HTTP/1.1 {status} Status\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+
+    (format!("http://{addr}/v1"), rx)
+}
+
+fn exceeded() -> (u16, String) {
+    let error = json!({
+        "error": {
+            "message": "This model's maximum context length is 128000 tokens.",
+            "type": "invalid_request_error",
+            "param": "messages",
+            "code": "context_length_exceeded"
+        }
+    });
+    (400, error.to_string())
+}
+
+fn completion(content: &str) -> (u16, String) {
+    let completion = json!({
+        "id": "chatcmpl-1",
+        "object": "chat.completion",
+        "created": 1,
+        "model": "gpt-4o",
+        "choices": [{
+            "index": 0,
+            "message": { "role": "assistant", "content": content },
+            "finish_reason": "stop"
+        }]
+    });
+    (200, completion.to_string())
+}
+
+fn client(api_base: String) -> Client<OpenAIConfig> {
+    Client::with_config(
+        OpenAIConfig::new()
+            .with_api_base(api_base)
+            .with_api_key("sk-test"),
+    )
+}
+
+fn request() -> CreateChatCompletionRequest {
+    let messages: Vec<ChatCompletionRequestMessage> = vec![
+        ChatCompletionRequestSystemMessage::from("Be brief").into(),
+        ChatCompletionRequestUserMessage::from("u1").into(),
+        ChatCompletionRequestAssistantMessage::from("a1").into(),
+        ChatCompletionRequestUserMessage::from("u2").into(),
+        ChatCompletionRequestAssistantMessage::from("a2").into(),
+        ChatCompletionRequestUserMessage::from("u3").into(),
+    ];
+    CreateChatCompletionRequestArgs::default()
+        .model("gpt-4o")
+        .messages(messages)
+        .build()
+        .unwrap()
+}
+
+/// Contents of the messages of a request body.
+fn contents(body: &Value) -> Vec<String> {
+    body["messages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|message| message["content"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn trims_oldest_unprotected_messages() {
+    let (api_base, requests) = serve(vec![exceeded(), completion("Done.")]);
+
+    let response = client(api_base)
+        .chat()
+        .create_with_context_policy(request(), &ContextLengthPolicy::trim_oldest())
+        .await
+        .unwrap();
+
+    assert_eq!(
+        response.choices[0].message.content.as_deref(),
+        Some("Done.")
+    );
+    assert_eq!(contents(&requests.recv().unwrap()).len(), 6);
+    assert_eq!(
+        contents(&requests.recv().unwrap()),
+        ["Be brief", "u2", "a2", "u3"]
+    );
+}
+
+#[tokio::test]
+async fn protected_messages_are_kept() {
+    let (api_base, requests) = serve(vec![exceeded(), completion("Done.")]);
+    let policy = ContextLengthPolicy::trim_oldest()
+        .protect(|message| matches!(message, ChatCompletionRequestMessage::User(_)));
+
+    client(api_base)
+        .chat()
+        .create_with_context_policy(request(), &policy)
+        .await
+        .unwrap();
+
+    requests.recv().unwrap();
+    assert_eq!(
+        contents(&requests.recv().unwrap()),
+        ["u1", "u2", "a2", "u3"]
+    );
+}
+
+#[tokio::test]
+async fn gives_up_after_max_retries() {
+    let (api_base, _requests) = serve(vec![exceeded(), exceeded()]);
+    let policy = ContextLengthPolicy::trim_oldest().max_retries(1);
+
+    let error = client(api_base)
+        .chat()
+        .create_with_context_policy(request(), &policy)
+        .await
+        .unwrap_err();
+
+    assert!(matches!(error, OpenAIError::ContextLengthExceeded(_)));
+}
+
+#[tokio::test]
+async fn summaries_replace_each_other() {
+    let (api_base, requests) = serve(vec![
+        exceeded(),
+        completion("S1"),
+        exceeded(),
+        completion("S2"),
+        completion("Done."),
+    ]);
+    let policy = ContextLengthPolicy::summarize("gpt-4o-mini");
+
+    let response = client(api_base)
+        .chat()
+        .create_with_context_policy(request(), &policy)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        response.choices[0].message.content.as_deref(),
+        Some("Done.")
+    );
+    requests.recv().unwrap();
+
+    let summary_request = requests.recv().unwrap();
+    assert_eq!(summary_request["model"], "gpt-4o-mini");
+    let transcript = &contents(&summary_request)[0];
+    assert!(
+        transcript.contains("user: u1\nassistant: a1"),
+        "{transcript}"
+    );
+
+    assert_eq!(
+        contents(&requests.recv().unwrap()),
+        [
+            "Be brief",
+            "Summary of the earlier conversation:\nS1",
+            "u2",
+            "a2",
+            "u3"
+        ]
+    );
+
+    // The first summary is summarized again with the next evicted messages
+    let transcript = &contents(&requests.recv().unwrap())[0];
+    assert!(transcript.contains("S1"), "{transcript}");
+    assert!(
+        transcript.contains("user: u2\nassistant: a2"),
+        "{transcript}"
+    );
+
+    assert_eq!(
+        contents(&requests.recv().unwrap()),
+        ["Be brief", "Summary of the earlier conversation:\nS2", "u3"]
+    );
+}
@@ -0,0 +1,104 @@
+use async_openai::{
+    degradation::{Degradation, DegradationMatrix, DegradationPolicy, Parameter},
+    error::OpenAIError,
+    types::{
+        ChatCompletionRequestMessage, ChatCompletionRequestSystemMessageArgs,
+        ChatCompletionRequestUserMessageArgs, ChatCompletionToolArgs,
+        CreateChatCompletionRequestArgs, FunctionObjectArgs, ResponseFormat,
+        ResponseFormatJsonSchema,
+    },
+};
+use serde_json::json;
+
+fn args(model: &str) -> CreateChatCompletionRequestArgs {
+    CreateChatCompletionRequestArgs::default()
+        .model(model)
+        .messages([
+            ChatCompletionRequestSystemMessageArgs::default()
+                .content("You extract contacts.")
+                .build()
+                .unwrap()
+                .into(),
+            ChatCompletionRequestUserMessageArgs::default()
+                .content("Ada Lovelace, ada@example.com")
+                .build()
+                .unwrap()
+                .into(),
+        ])
+        .response_format(ResponseFormat::JsonSchema {
+            json_schema: ResponseFormatJsonSchema {
+                description: None,
+                name: "Contact".into(),
+                schema: Some(json!({
+                    "type": "object",
+                    "properties": { "email": { "type": "string" } },
+                })),
+                strict: Some(true),
+            },
+        })
+        .temperature(0.2)
+        .clone()
+}
+
+#[test]
+fn converts_structured_outputs_for_providers_without_them() {
+    let matrix = DegradationMatrix::default();
+    let policy = matrix.policy("deepseek").unwrap();
+
+    let request = args("deepseek-v4").build_degraded(policy).unwrap();
+
+    assert_eq!(request.response_format, Some(ResponseFormat::JsonObject));
+    assert_eq!(request.temperature, Some(0.2));
+    assert_eq!(request.messages.len(), 3);
+    let ChatCompletionRequestMessage::System(instruction) = &request.messages[1] else {
+        panic!("expected the schema instruction after the system message");
+    };
+    let text = serde_json::to_string(&instruction.content).unwrap();
+    assert!(text.contains("Contact") && text.contains("email"), "{text}");
+
+    // Unknown models of other providers are sent as is
+    let request = args("deepseek-v4")
+        .build_degraded(matrix.policy("openai").unwrap())
+        .unwrap();
+    assert!(matches!(
+        request.response_format,
+        Some(ResponseFormat::JsonSchema { .. })
+    ));
+}
+
+#[test]
+fn strips_errors_and_strictness() {
+    let policy = DegradationPolicy::default();
+    let request = args("o3-mini").build_degraded(&policy).unwrap();
+    assert_eq!(request.temperature, None);
+
+    let tool = ChatCompletionToolArgs::default()
+        .function(
+            FunctionObjectArgs::default()
+                .name("lookup")
+                .build()
+                .unwrap(),
+        )
+        .build()
+        .unwrap();
+    let with_tools = args("o1-mini").tools(vec![tool]).clone();
+    match with_tools.build_degraded(&policy) {
+        Err(OpenAIError::UnsupportedByModel { model, violations }) => {
+            assert_eq!(model, "o1-mini");
+            assert_eq!(violations.len(), 1, "{violations:?}");
+        }
+        other => panic!("expected UnsupportedByModel, got {other:?}"),
+    }
+
+    let lenient = DegradationPolicy::default().on(Parameter::Tools, Degradation::Strip);
+    let request = with_tools.build_degraded(&lenient).unwrap();
+    assert!(request.tools.is_none());
+    assert_eq!(request.response_format, Some(ResponseFormat::JsonObject));
+
+    match args("o1-mini").build_degraded(&lenient.strict(true)) {
+        Err(OpenAIError::UnsupportedByModel { violations, .. }) => {
+            assert_eq!(violations.len(), 2, "{violations:?}");
+        }
+        other => panic!("expected UnsupportedByModel, got {other:?}"),
+    }
+}
@@ -0,0 +1,145 @@
+use std::{
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::mpsc,
+};
+
+use async_openai::{
+    config::OpenAIConfig,
+    embedding::EmbeddingPreflight,
+    error::OpenAIError,
+    tokens::{count_tokens, TruncationStrategy},
+    types::{CreateEmbeddingRequest, CreateEmbeddingRequestArgs, EmbeddingInput},
+    Client,
+};
+use serde_json::json;
+
+const MODEL: &str = "text-embedding-3-small";
+
+/// Read a request up to the end of its JSON body.
+fn read_body(stream: &mut TcpStream) -> serde_json::Value {
+    let mut request = String::new();
+    let mut buf = [0; 16384];
+    loop {
+        let n = stream.read(&mut buf).unwrap();
+        request.push_str(&String::from_utf8_lossy(&buf[..n]));
+        if let Some((_, body)) = request.split_once("\r\n\r\n") {
+            if let Ok(body) = serde_json::from_str(body) {
+                return body;
+            }
+        }
+    }
+}
+
+/// Answer a single embeddings request, sending its body to the returned receiver.
+fn serve() -> (String, mpsc::Receiver<serde_json::Value>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (tx, rx) = mpsc::channel();
+
+    std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let request = read_body(&mut stream);
+        let inputs = request["input"].as_array().map_or(1, Vec::len);
+        let _ = tx.send(request);
+
+        let data: Vec<_> = (0..inputs)
+            .map(|index| json!({"index": index, "object": "embedding", "embedding": [0.1, 0.2]}))
+            .collect();
+        let body = json!({
+            "object": "list",
+            "model": MODEL,
+            "data": data,
+            "usage": {"prompt_tokens": 8, "total_tokens": 8}
+        })
+        .to_string();
+        let response = format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+            body.len()
+        );
+        stream.write_all(response.as_bytes()).unwrap();
+    });
+
+    (format!("http://{addr}/v1"), rx)
+}
+
+fn request(inputs: Vec<String>) -> CreateEmbeddingRequest {
+    CreateEmbeddingRequestArgs::default()
+        .model(MODEL)
+        .input(inputs)
+        .build()
+        .unwrap()
+}
+
+fn long_text() -> String {
+    "The quick brown fox jumps over the lazy dog. ".repeat(20)
+}
+
+#[tokio::test]
+async fn oversized_inputs_are_rejected_before_sending() {
+    // Nothing listens on the discard port, so the test fails if the request is sent
+    let client = Client::with_config(OpenAIConfig::new().with_api_base("http://127.0.0.1:9/v1"));
+    let request = request(vec![
+        "short".into(),
+        long_text(),
+        "short".into(),
+        long_text(),
+    ]);
+
+    let error = client
+        .embeddings()
+        .create_with_preflight(request, &EmbeddingPreflight::reject().max_tokens(20))
+        .await
+        .unwrap_err();
+
+    match error {
+        OpenAIError::EmbeddingInputTooLong {
+            max_tokens,
+            indices,
+        } => {
+            assert_eq!(max_tokens, 20);
+            assert_eq!(indices, vec![1, 3]);
+        }
+        error => panic!("unexpected error: {error:?}"),
+    }
+}
+
+#[tokio::test]
+async fn oversized_inputs_are_truncated() {
+    let (api_base, requests) = serve();
+    let client = Client::with_config(OpenAIConfig::new().with_api_base(api_base));
+    let request = request(vec!["short".into(), long_text()]);
+    let preflight = EmbeddingPreflight::truncate(TruncationStrategy::Head).max_tokens(20);
+
+    let response = client
+        .embeddings()
+        .create_with_preflight(request, &preflight)
+        .await
+        .unwrap();
+    assert_eq!(response.data.len(), 2);
+
+    let sent = requests.recv().unwrap();
+    assert_eq!(sent["input"][0], "short");
+    let truncated = sent["input"][1].as_str().unwrap();
+    assert!(long_text().starts_with(truncated));
+    assert!(count_tokens(truncated, MODEL) <= 20);
+}
+
+#[test]
+fn token_arrays_are_counted_by_length() {
+    let mut request = CreateEmbeddingRequest {
+        model: MODEL.into(),
+        input: EmbeddingInput::ArrayOfIntegerArray(vec![vec![1, 2, 3], (0..10).collect()]),
+        ..Default::default()
+    };
+
+    let preflight = EmbeddingPreflight::truncate(TruncationStrategy::MiddleEllipsis).max_tokens(4);
+    assert_eq!(preflight.apply(&mut request).unwrap(), vec![1]);
+    assert_eq!(
+        request.input,
+        EmbeddingInput::ArrayOfIntegerArray(vec![vec![1, 2, 3], vec![0, 1, 8, 9]])
+    );
+
+    let preflight = EmbeddingPreflight::reject().max_tokens(4);
+    assert!(preflight.apply(&mut request).unwrap().is_empty());
+}
@@ -0,0 +1,67 @@
+use async_openai::{
+    config::Config,
+    profile::{EnvProfile, Provider},
+};
+use secrecy::ExposeSecret;
+
+#[test]
+fn profile_from_env() {
+    std::env::set_var(
+        "OPENAI_PROFILE_LOCAL_LLAMA_API_BASE",
+        "http://localhost:8080/v1",
+    );
+    std::env::set_var("OPENAI_PROFILE_LOCAL_LLAMA_API_KEY", "none");
+    std::env::set_var("OPENAI_PROFILE_LOCAL_LLAMA_MODEL", "llama-3.1-8b-instruct");
+
+    let profile = EnvProfile::from_env_named("local-llama").unwrap();
+    assert_eq!(profile.provider, Provider::OpenAI);
+    assert_eq!(profile.default_model(), Some("llama-3.1-8b-instruct"));
+
+    let config = profile.config();
+    assert_eq!(
+        config.url("/chat/completions"),
+        "http://localhost:8080/v1/chat/completions"
+    );
+    assert_eq!(config.api_key().expose_secret(), "none");
+    assert_eq!(config.headers()["authorization"], "Bearer none");
+
+    std::env::set_var("OPENAI_PROFILE_BROKEN_PROVIDER", "bedrock");
+    assert!(EnvProfile::from_env_named("broken").is_err());
+}
+
+#[cfg(feature = "toml")]
+#[test]
+fn profile_from_toml() {
+    let profiles = r#"
+        [staging]
+        api_base = "https://staging.example.com/v1"
+        default_model = "gpt-4o-mini"
+        beta_header = false
+        headers = { "X-Tenant" = "dev" }
+
+        [azure]
+        provider = "azure"
+        api_base = "https://example.openai.azure.com"
+        api_key = "azure-key"
+        api_version = "2024-06-01"
+        deployment_id = "gpt-4o"
+    "#;
+
+    let staging = EnvProfile::from_toml_str(profiles, "staging").unwrap();
+    assert_eq!(staging.name, "staging");
+    let headers = staging.config().headers();
+    assert_eq!(headers["x-tenant"], "dev");
+    assert!(headers.get("openai-beta").is_none());
+
+    let azure = EnvProfile::from_toml_str(profiles, "azure")
+        .unwrap()
+        .config();
+    assert_eq!(
+        azure.url("/chat/completions"),
+        "https://example.openai.azure.com/openai/deployments/gpt-4o/chat/completions"
+    );
+    assert_eq!(azure.query(), [("api-version", "2024-06-01")]);
+    assert_eq!(azure.headers()["api-key"], "azure-key");
+
+    assert!(EnvProfile::from_toml_str(profiles, "production").is_err());
+}
@@ -0,0 +1,193 @@
+use std::{
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    time::Duration,
+};
+
+use async_openai::{
+    config::OpenAIConfig,
+    events::{ClientEvent, EventSubscription},
+    types::{ChatCompletionRequestUserMessage, CreateChatCompletionRequestArgs},
+    Client,
+};
+use futures::{FutureExt, StreamExt};
+use serde_json::json;
+
+/// Read a request up to the end of its JSON body.
+fn read_body(stream: &mut TcpStream) {
+    let mut request = String::new();
+    let mut buf = [0; 16384];
+    loop {
+        let n = stream.read(&mut buf).unwrap();
+        request.push_str(&String::from_utf8_lossy(&buf[..n]));
+        if let Some((_, body)) = request.split_once("\r\n\r\n") {
+            if serde_json::from_str::<serde_json::Value>(body).is_ok() {
+                return;
+            }
+        }
+    }
+}
+
+/// Answer requests with `(status, content type, body)` in order.
+fn serve(responses: Vec<(u16, &'static str, String)>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    std::thread::spawn(move || {
+        for (status, content_type, body) in responses {
+            let (mut stream, _) = listener.accept().unwrap();
+            read_body(&mut stream);
+            let response = format!(
+                "HTTP/1.1 {status} Status\r\ncontent-type: {content_type}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+
+    format!("http://{addr}/v1")
+}
+
+fn rate_limited() -> (u16, &'static str, String) {
+    let error = json!({
+        "error": {
+            "message": "Rate limit reached",
+            "type": "requests",
+            "param": null,
+            "code": "rate_limit_exceeded"
+        }
+    });
+    (429, "application/json", error.to_string())
+}
+
+fn completion(total_tokens: u32) -> (u16, &'static str, String) {
+    let completion = json!({
+        "id": "chatcmpl-1",
+        "object": "chat.completion",
+        "created": 1,
+        "model": "gpt-4o-mini",
+        "choices": [{
+            "index": 0,
+            "message": { "role": "assistant", "content": "Done." },
+            "finish_reason": "stop"
+        }],
+        "usage": {
+            "prompt_tokens": total_tokens - 1,
+            "completion_tokens": 1,
+            "total_tokens": total_tokens
+        }
+    });
+    (200, "application/json", completion.to_string())
+}
+
+fn client(api_base: String) -> Client<OpenAIConfig> {
+    let backoff = backoff::ExponentialBackoffBuilder::new()
+        .with_initial_interval(Duration::from_millis(10))
+        .with_max_elapsed_time(Some(Duration::from_secs(5)))
+        .build();
+    Client::with_config(
+        OpenAIConfig::new()
+            .with_api_base(api_base)
+            .with_api_key("sk-test"),
+    )
+    .with_backoff(backoff)
+}
+
+/// Events received so far.
+fn received(events: &mut EventSubscription) -> Vec<ClientEvent> {
+    let mut received = vec![];
+    while let Some(event) = events.recv().now_or_never().flatten() {
+        received.push(event);
+    }
+    received
+}
+
+#[tokio::test]
+async fn request_retry_and_budget_events() {
+    let api_base = serve(vec![rate_limited(), completion(60), completion(60)]);
+    let client = client(api_base).with_token_budget_thresholds([50, 100, 1000]);
+    let mut events = client.events().subscribe();
+
+    let request = CreateChatCompletionRequestArgs::default()
+        .model("gpt-4o-mini")
+        .messages([ChatCompletionRequestUserMessage::from("Hi").into()])
+        .build()
+        .unwrap();
+    client.chat().create(request.clone()).await.unwrap();
+    client.chat().create(request).await.unwrap();
+
+    let events = received(&mut events);
+    assert_eq!(events.len(), 8, "{events:#?}");
+    let ClientEvent::RequestStarted { id, method, path } = &events[0] else {
+        panic!("unexpected events: {events:#?}");
+    };
+    assert_eq!(method, "POST");
+    assert_eq!(path, "/v1/chat/completions");
+    assert!(matches!(events[1], ClientEvent::RateLimited { .. }));
+    assert!(matches!(
+        events[2],
+        ClientEvent::RetryScheduled { retry: 1, .. }
+    ));
+    assert_eq!(
+        events[3],
+        ClientEvent::BudgetThresholdCrossed {
+            threshold: 50,
+            total_tokens: 60
+        }
+    );
+    assert!(
+        matches!(&events[4], ClientEvent::RequestFinished { id: finished, error: None, .. } if finished == id)
+    );
+    assert!(matches!(&events[5], ClientEvent::RequestStarted { id: second, .. } if second != id));
+    assert_eq!(
+        events[6],
+        ClientEvent::BudgetThresholdCrossed {
+            threshold: 100,
+            total_tokens: 120
+        }
+    );
+    assert!(matches!(events[7], ClientEvent::RequestFinished { .. }));
+}
+
+#[tokio::test]
+async fn stream_events() {
+    let body = concat!(
+        "data: {\"id\":\"1\",\"object\":\"chat.completion.chunk\",\"created\":1,\"model\":\"m\",",
+        "\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Hi\"},\"finish_reason\":null}]}\n\n",
+        "data: [DONE]\n\n",
+    );
+    let api_base = serve(vec![(200, "text/event-stream", body.to_string())]);
+    let client = client(api_base);
+    let mut events = client.events().subscribe();
+
+    let request = CreateChatCompletionRequestArgs::default()
+        .model("gpt-4o-mini")
+        .messages([ChatCompletionRequestUserMessage::from("Hi").into()])
+        .build()
+        .unwrap();
+    let chunks: Vec<_> = client
+        .chat()
+        .create_stream(request)
+        .await
+        .unwrap()
+        .collect()
+        .await;
+    assert_eq!(chunks.len(), 1);
+
+    let opened = events.recv().await.unwrap();
+    let closed = events.recv().await.unwrap();
+    match (opened, closed) {
+        (
+            ClientEvent::StreamOpened { id, path },
+            ClientEvent::StreamClosed {
+                id: closed,
+                error: None,
+                ..
+            },
+        ) => {
+            assert_eq!(path, "/v1/chat/completions");
+            assert_eq!(id, closed);
+        }
+        events => panic!("unexpected events: {events:#?}"),
+    }
+}
@@ -0,0 +1,81 @@
+use async_openai::types::CreateChatCompletionResponse;
+#[cfg(feature = "extra-fields")]
+use async_openai::types::CreateChatCompletionStreamResponse;
+use serde_json::json;
+
+#[cfg(feature = "extra-fields")]
+#[test]
+fn chat_response_preserves_unknown_fields() {
+    let value = json!({
+        "id": "chatcmpl-123",
+        "object": "chat.completion",
+        "created": 1700000000,
+        "model": "llama-3.1-8b-instant",
+        "choices": [{
+            "index": 0,
+            "message": { "role": "assistant", "content": "Hello!" },
+            "finish_reason": "stop",
+            "logprobs": null
+        }],
+        "usage": {
+            "prompt_tokens": 10,
+            "completion_tokens": 2,
+            "total_tokens": 12,
+            "queue_time": 0.02,
+            "prompt_time": 0.001
+        },
+        "system_fingerprint": "fp_abc",
+        "x_groq": { "id": "req_01" }
+    });
+
+    let response: CreateChatCompletionResponse = serde_json::from_value(value).unwrap();
+    assert_eq!(response.extra["x_groq"], json!({ "id": "req_01" }));
+
+    let usage = response.usage.as_ref().unwrap();
+    assert_eq!(usage.extra["queue_time"], json!(0.02));
+
+    let serialized = serde_json::to_value(&response).unwrap();
+    let deserialized: CreateChatCompletionResponse = serde_json::from_value(serialized).unwrap();
+    assert_eq!(response, deserialized);
+}
+
+#[cfg(feature = "extra-fields")]
+#[test]
+fn chat_stream_chunk_preserves_unknown_fields() {
+    let chunk: CreateChatCompletionStreamResponse = serde_json::from_value(json!({
+        "id": "gen-1",
+        "object": "chat.completion.chunk",
+        "created": 1700000000,
+        "model": "openai/gpt-4o",
+        "provider": "OpenAI",
+        "choices": [{
+            "index": 0,
+            "delta": { "content": "Hi" },
+            "finish_reason": null,
+            "native_finish_reason": null
+        }]
+    }))
+    .unwrap();
+
+    assert_eq!(chunk.extra["provider"], json!("OpenAI"));
+    assert_eq!(chunk.choices[0].extra["native_finish_reason"], json!(null));
+}
+
+#[cfg(not(feature = "extra-fields"))]
+#[test]
+fn unknown_fields_are_dropped_without_feature() {
+    let response: CreateChatCompletionResponse = serde_json::from_value(json!({
+        "id": "chatcmpl-123",
+        "object": "chat.completion",
+        "created": 1700000000,
+        "model": "llama-3.1-8b-instant",
+        "choices": [],
+        "x_groq": { "id": "req_01" }
+    }))
+    .unwrap();
+
+    assert!(response.extra.is_empty());
+    let serialized = serde_json::to_value(&response).unwrap();
+    assert!(serialized.get("x_groq").is_none());
+    assert!(serialized.get("extra").is_none());
+}
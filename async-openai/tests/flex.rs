@@ -0,0 +1,168 @@
+use std::{
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::mpsc,
+    time::Duration,
+};
+
+use async_openai::{
+    config::OpenAIConfig,
+    error::OpenAIError,
+    flex::FlexPolicy,
+    types::{
+        ChatCompletionRequestUserMessage, CreateChatCompletionRequest,
+        CreateChatCompletionRequestArgs, ServiceTier,
+    },
+    Client,
+};
+use serde_json::json;
+
+/// Read a request up to the end of its JSON body.
+fn read_body(stream: &mut TcpStream) -> serde_json::Value {
+    let mut request = String::new();
+    let mut buf = [0; 16384];
+    loop {
+        let n = stream.read(&mut buf).unwrap();
+        request.push_str(&String::from_utf8_lossy(&buf[..n]));
+        if let Some((_, body)) = request.split_once("\r\n\r\n") {
+            if let Ok(body) = serde_json::from_str(body) {
+                return body;
+            }
+        }
+    }
+}
+
+/// Answer requests with `responses` in order, sending each request body to the returned
+/// receiver.
+fn serve(responses: Vec<(u16, String)>) -> (String, mpsc::Receiver<serde_json::Value>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (tx, rx) = mpsc::channel();
+
+    std::thread::spawn(move || {
+        for (status, body) in responses {
+            let (mut stream, _) = listener.accept().unwrap();
+            let _ = tx.send(read_body(&mut stream));
+            let response = format!(
+                "HTTP/1.1 {status} Status\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+
+    (format!("http://{addr}/v1"), rx)
+}
+
+fn unavailable() -> (u16, String) {
+    let error = json!({
+        "error": {
+            "message": "Resource unavailable",
+            "type": "invalid_request_error",
+            "param": null,
+            "code": "resource_unavailable"
+        }
+    });
+    (429, error.to_string())
+}
+
+fn completion(tier: &str) -> (u16, String) {
+    let completion = json!({
+        "id": "chatcmpl-1",
+        "object": "chat.completion",
+        "created": 1,
+        "model": "o3",
+        "service_tier": tier,
+        "choices": [{
+            "index": 0,
+            "message": { "role": "assistant", "content": "Done." },
+            "finish_reason": "stop"
+        }]
+    });
+    (200, completion.to_string())
+}
+
+fn client(api_base: String) -> Client<OpenAIConfig> {
+    Client::with_config(
+        OpenAIConfig::new()
+            .with_api_base(api_base)
+            .with_api_key("sk-test"),
+    )
+}
+
+fn policy() -> FlexPolicy {
+    FlexPolicy::new()
+        .max_retries(1)
+        .initial_backoff(Duration::from_millis(10))
+}
+
+fn request() -> CreateChatCompletionRequest {
+    CreateChatCompletionRequestArgs::default()
+        .model("o3")
+        .service_tier(ServiceTier::Flex)
+        .messages([ChatCompletionRequestUserMessage::from("Hi").into()])
+        .build()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn unavailable_is_not_retried_by_client() {
+    let (api_base, requests) = serve(vec![unavailable()]);
+
+    let error = client(api_base).chat().create(request()).await.unwrap_err();
+
+    assert!(matches!(error, OpenAIError::ResourceUnavailable(_)));
+    assert_eq!(requests.try_iter().count(), 1);
+}
+
+#[tokio::test]
+async fn policy_retries_on_flex_tier() {
+    let (api_base, requests) = serve(vec![unavailable(), completion("flex")]);
+
+    let response = client(api_base)
+        .chat()
+        .create_with_flex_policy(request(), &policy())
+        .await
+        .unwrap();
+
+    assert_eq!(
+        response.choices[0].message.content.as_deref(),
+        Some("Done.")
+    );
+    let tiers: Vec<_> = requests
+        .try_iter()
+        .map(|body| body["service_tier"].clone())
+        .collect();
+    assert_eq!(tiers, [json!("flex"), json!("flex")]);
+}
+
+#[tokio::test]
+async fn policy_falls_back_to_default_tier() {
+    let (api_base, requests) = serve(vec![unavailable(), unavailable(), completion("default")]);
+
+    let response = client(api_base)
+        .chat()
+        .create_with_flex_policy(request(), &policy().fallback_to_default(true))
+        .await
+        .unwrap();
+
+    assert_eq!(response.id, "chatcmpl-1");
+    let tiers: Vec<_> = requests
+        .try_iter()
+        .map(|body| body["service_tier"].clone())
+        .collect();
+    assert_eq!(tiers, [json!("flex"), json!("flex"), json!("default")]);
+}
+
+#[tokio::test]
+async fn policy_returns_error_without_fallback() {
+    let (api_base, _requests) = serve(vec![unavailable(), unavailable()]);
+
+    let error = client(api_base)
+        .chat()
+        .create_with_flex_policy(request(), &policy())
+        .await
+        .unwrap_err();
+
+    assert!(matches!(error, OpenAIError::ResourceUnavailable(_)));
+}
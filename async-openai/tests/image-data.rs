@@ -0,0 +1,103 @@
+use async_openai::{
+    error::OpenAIError,
+    types::{ImageFormat, ImagesResponse},
+};
+use base64::{engine::general_purpose, Engine as _};
+
+/// Header of a 3x2 PNG.
+fn png() -> Vec<u8> {
+    let mut bytes = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+    bytes.extend(3u32.to_be_bytes());
+    bytes.extend(2u32.to_be_bytes());
+    bytes.extend([8, 6, 0, 0, 0]);
+    bytes
+}
+
+/// Header of a 640x480 JPEG with an APP0 segment before the frame.
+fn jpeg() -> Vec<u8> {
+    let mut bytes = vec![0xff, 0xd8, 0xff, 0xe0, 0x00, 0x04, 0x4a, 0x46];
+    bytes.extend([0xff, 0xc0, 0x00, 0x11, 0x08]);
+    bytes.extend(480u16.to_be_bytes());
+    bytes.extend(640u16.to_be_bytes());
+    bytes
+}
+
+/// Header of a 1000x700 extended WebP.
+fn webp() -> Vec<u8> {
+    let mut bytes = b"RIFF\0\0\0\0WEBPVP8X\x0a\0\0\0\0\0\0\0".to_vec();
+    bytes.extend(&999u32.to_le_bytes()[..3]);
+    bytes.extend(&699u32.to_le_bytes()[..3]);
+    bytes
+}
+
+fn response(images: &[&[u8]]) -> ImagesResponse {
+    let data: Vec<_> = images
+        .iter()
+        .map(|bytes| serde_json::json!({ "b64_json": general_purpose::STANDARD.encode(bytes) }))
+        .collect();
+    serde_json::from_value(serde_json::json!({ "created": 0, "data": data })).unwrap()
+}
+
+#[test]
+fn images_are_decoded_with_their_dimensions() {
+    let images = response(&[&png(), &jpeg(), &webp(), b"GIF89a\x10\0\x20\0", b"text"])
+        .decode_all()
+        .unwrap();
+
+    let formats: Vec<_> = images.iter().map(|image| image.format).collect();
+    assert_eq!(
+        formats,
+        [
+            Some(ImageFormat::Png),
+            Some(ImageFormat::Jpeg),
+            Some(ImageFormat::Webp),
+            Some(ImageFormat::Gif),
+            None
+        ]
+    );
+    let dimensions: Vec<_> = images.iter().map(|image| image.dimensions()).collect();
+    assert_eq!(
+        dimensions,
+        [
+            Some((3, 2)),
+            Some((640, 480)),
+            Some((1000, 700)),
+            Some((16, 32)),
+            None
+        ]
+    );
+    assert_eq!(images[0].bytes, png());
+    assert_eq!(images[0].sha256().len(), 64);
+    assert_ne!(images[0].sha256(), images[1].sha256());
+}
+
+#[test]
+fn url_images_are_not_decoded() {
+    let response: ImagesResponse = serde_json::from_value(serde_json::json!({
+        "created": 0,
+        "data": [{ "url": "https://example.com/image.png" }]
+    }))
+    .unwrap();
+
+    assert!(matches!(
+        response.decode_all(),
+        Err(OpenAIError::InvalidArgument(_))
+    ));
+}
+
+#[tokio::test]
+async fn images_are_saved_in_their_own_format() {
+    let dir = std::env::temp_dir().join(format!("async-openai-image-data-{}", std::process::id()));
+    let image = response(&[&png()]).decode_all().unwrap().remove(0);
+
+    image.save(dir.join("nested/image.png")).await.unwrap();
+    assert_eq!(std::fs::read(dir.join("nested/image.png")).unwrap(), png());
+
+    let error = image.save(dir.join("image.jpg")).await.unwrap_err();
+    assert!(
+        matches!(&error, OpenAIError::InvalidArgument(message) if message.contains("png image as jpg")),
+        "{error:?}"
+    );
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
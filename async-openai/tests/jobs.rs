@@ -0,0 +1,111 @@
+use std::{
+    io::{Read, Write},
+    net::TcpListener,
+};
+
+use async_openai::{
+    config::OpenAIConfig,
+    jobs::{JobKind, JobManager, JobStatus},
+    types::Batch,
+    Client,
+};
+
+/// Serve a single JSON response on a local port and return its base url.
+fn serve_once(body: String) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut buf = [0; 8192];
+        let _ = stream.read(&mut buf).unwrap();
+        let response = format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+            body.len()
+        );
+        stream.write_all(response.as_bytes()).unwrap();
+    });
+
+    format!("http://{addr}/v1")
+}
+
+fn batch(status: &str, output_file_id: Option<&str>) -> serde_json::Value {
+    serde_json::json!({
+        "id": "batch_abc123",
+        "object": "batch",
+        "endpoint": "/v1/chat/completions",
+        "errors": null,
+        "input_file_id": "file-in",
+        "completion_window": "24h",
+        "status": status,
+        "output_file_id": output_file_id,
+        "error_file_id": null,
+        "created_at": 1711471533,
+        "request_counts": { "total": 2, "completed": 0, "failed": 0 },
+        "metadata": null
+    })
+}
+
+#[tokio::test]
+async fn resumes_polling_from_persisted_state() {
+    let path = std::env::temp_dir().join(format!("async-openai-jobs-{}.json", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let submitted: Batch = serde_json::from_value(batch("validating", None)).unwrap();
+    let jobs = JobManager::open(Client::new(), &path).await.unwrap();
+    jobs.track_batch(&submitted).await.unwrap();
+    jobs.track(JobKind::Response, "resp_123").await.unwrap();
+    jobs.label("batch_abc123", "dataset", "eval-1")
+        .await
+        .unwrap();
+    drop(jobs);
+
+    // A new process picks up where the previous one stopped
+    let api_base = serve_once(batch("completed", Some("file-out")).to_string());
+    let client = Client::with_config(
+        OpenAIConfig::new()
+            .with_api_base(api_base)
+            .with_api_key("sk-test"),
+    );
+    let jobs = JobManager::open(client, &path).await.unwrap();
+    assert_eq!(jobs.pending().len(), 2);
+
+    let job = jobs.poll("batch_abc123").await.unwrap();
+    assert_eq!(job.status, JobStatus::Succeeded);
+    assert_eq!(job.result.as_deref(), Some("file-out"));
+    assert_eq!(job.labels["dataset"], "eval-1");
+
+    let saved: serde_json::Value = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+    assert_eq!(saved["jobs"][0]["status"], "succeeded");
+
+    jobs.forget("resp_123").await.unwrap();
+    assert!(jobs.pending().is_empty());
+    assert!(jobs.poll("resp_123").await.is_err());
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn concurrent_changes_are_all_persisted() {
+    let dir = std::env::temp_dir().join(format!("async-openai-jobs-dir-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("jobs.json");
+
+    let jobs = JobManager::open(Client::new(), &path).await.unwrap();
+    let tracking: Vec<_> = (0..20)
+        .map(|n| {
+            let jobs = jobs.clone();
+            tokio::spawn(async move { jobs.track(JobKind::Response, &format!("resp_{n}")).await })
+        })
+        .collect();
+    for task in tracking {
+        task.await.unwrap().unwrap();
+    }
+
+    let saved: serde_json::Value = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+    assert_eq!(saved["jobs"].as_array().unwrap().len(), 20);
+    // No temporary file is left behind
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
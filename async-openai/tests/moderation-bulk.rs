@@ -0,0 +1,130 @@
+use std::{
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+};
+
+use async_openai::{config::OpenAIConfig, moderation::MODERATION_BATCH_SIZE, Client};
+use serde_json::{json, Value};
+
+const CATEGORIES: [&str; 13] = [
+    "hate",
+    "hate/threatening",
+    "harassment",
+    "harassment/threatening",
+    "illicit",
+    "illicit/violent",
+    "self-harm",
+    "self-harm/intent",
+    "self-harm/instructions",
+    "sexual",
+    "sexual/minors",
+    "violence",
+    "violence/graphic",
+];
+
+/// Read a request up to the end of its body, returning the body.
+fn read_body(stream: &mut TcpStream) -> String {
+    let mut request = Vec::new();
+    let mut buf = [0; 16384];
+    loop {
+        let n = stream.read(&mut buf).unwrap();
+        request.extend_from_slice(&buf[..n]);
+        let text = String::from_utf8_lossy(&request).to_string();
+        let Some((head, body)) = text.split_once("\r\n\r\n") else {
+            continue;
+        };
+        let length: usize = head
+            .lines()
+            .find_map(|line| {
+                line.to_lowercase()
+                    .strip_prefix("content-length: ")?
+                    .parse()
+                    .ok()
+            })
+            .unwrap_or_default();
+        if n == 0 || body.len() >= length {
+            return body.to_string();
+        }
+    }
+}
+
+/// A moderation result flagging the texts containing "spam".
+fn result(text: &str) -> Value {
+    let flagged = text.contains("spam");
+    let field = |value: Value| -> Value {
+        CATEGORIES
+            .iter()
+            .map(|category| (category.to_string(), value.clone()))
+            .collect::<serde_json::Map<_, _>>()
+            .into()
+    };
+    json!({
+        "flagged": flagged,
+        "categories": field(json!(flagged)),
+        "category_scores": field(json!(if flagged { 0.9 } else { 0.01 })),
+        "category_applied_input_types": field(json!(["text"])),
+    })
+}
+
+/// Answer `requests` moderation requests, returning the number of inputs of each.
+fn serve(requests: usize) -> (String, std::thread::JoinHandle<Vec<usize>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let handle = std::thread::spawn(move || {
+        let mut sizes = Vec::new();
+        for _ in 0..requests {
+            let (mut stream, _) = listener.accept().unwrap();
+            let request: Value = serde_json::from_str(&read_body(&mut stream)).unwrap();
+            let inputs = request["input"].as_array().unwrap();
+            sizes.push(inputs.len());
+            let body = json!({
+                "id": "modr-1",
+                "model": "omni-moderation-latest",
+                "results": inputs.iter().map(|input| result(input.as_str().unwrap())).collect::<Vec<_>>(),
+            })
+            .to_string();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+        sizes
+    });
+
+    (format!("http://{addr}/v1"), handle)
+}
+
+#[tokio::test]
+async fn bulk_results_follow_input_order() {
+    let texts: Vec<String> = (0..70)
+        .map(|i| {
+            if i % 7 == 0 {
+                format!("spam {i}")
+            } else {
+                format!("post {i}")
+            }
+        })
+        .collect();
+    let (api_base, server) = serve(3);
+    let client = Client::with_config(
+        OpenAIConfig::new()
+            .with_api_base(api_base)
+            .with_api_key("sk-test"),
+    );
+
+    let results = client
+        .moderations()
+        .create_bulk(texts.clone(), 2)
+        .await
+        .unwrap();
+
+    assert_eq!(results.len(), texts.len());
+    for (text, result) in texts.iter().zip(&results) {
+        assert_eq!(result.flagged, text.contains("spam"), "{text}");
+    }
+    let mut sizes = server.join().unwrap();
+    sizes.sort();
+    assert_eq!(sizes, [6, MODERATION_BATCH_SIZE, MODERATION_BATCH_SIZE]);
+}
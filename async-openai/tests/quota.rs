@@ -0,0 +1,164 @@
+use std::{
+    io::{Read, Write},
+    net::TcpListener,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use async_openai::{
+    config::OpenAIConfig,
+    error::OpenAIError,
+    types::{ChatCompletionRequestUserMessage, CreateChatCompletionRequestArgs},
+    Client,
+};
+use serde_json::json;
+
+/// Answer requests with `responses` in order, counting the requests received.
+fn serve(responses: Vec<(u16, String)>) -> (String, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let requests = Arc::new(AtomicUsize::new(0));
+    let counter = requests.clone();
+
+    std::thread::spawn(move || {
+        for (status, body) in responses {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0; 16384];
+            let _ = stream.read(&mut buf).unwrap();
+            counter.fetch_add(1, Ordering::SeqCst);
+            let response = format!(
+                "HTTP/1.1 {status} Status\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+
+    (format!("http://{addr}/v1"), requests)
+}
+
+fn client(api_base: String) -> Client<OpenAIConfig> {
+    let backoff = backoff::ExponentialBackoffBuilder::new()
+        .with_initial_interval(Duration::from_millis(10))
+        .with_max_elapsed_time(Some(Duration::from_secs(5)))
+        .build();
+    Client::with_config(
+        OpenAIConfig::new()
+            .with_api_base(api_base)
+            .with_api_key("sk-test"),
+    )
+    .with_backoff(backoff)
+}
+
+async fn create(client: &Client<OpenAIConfig>) -> Result<(), OpenAIError> {
+    let request = CreateChatCompletionRequestArgs::default()
+        .model("gpt-4o-mini")
+        .messages([ChatCompletionRequestUserMessage::from("Hi").into()])
+        .build()?;
+    client.chat().create(request).await.map(|_| ())
+}
+
+fn completion() -> (u16, String) {
+    let completion = json!({
+        "id": "chatcmpl-1",
+        "object": "chat.completion",
+        "created": 1,
+        "model": "gpt-4o-mini",
+        "choices": [{
+            "index": 0,
+            "message": { "role": "assistant", "content": "Hello" },
+            "finish_reason": "stop"
+        }]
+    });
+    (200, completion.to_string())
+}
+
+#[tokio::test]
+async fn exhausted_quota_is_permanent() {
+    let error = json!({
+        "error": {
+            "message": "You exceeded your current quota, please check your plan and billing details. For more information on this error, read the docs: https://platform.openai.com/docs/guides/error-codes/api-errors.",
+            "type": "insufficient_quota",
+            "param": null,
+            "code": "insufficient_quota",
+            "plan_type": "free"
+        }
+    });
+    let (api_base, requests) = serve(vec![(429, error.to_string()), completion()]);
+
+    let error = create(&client(api_base)).await.unwrap_err();
+
+    assert!(error.is_permanent());
+    let OpenAIError::QuotaExceeded(quota) = error else {
+        panic!("expected an exhausted quota, got {error}");
+    };
+    assert_eq!(quota.plan.as_deref(), Some("free"));
+    assert_eq!(
+        quota.billing_url.as_deref(),
+        Some("https://platform.openai.com/docs/guides/error-codes/api-errors")
+    );
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn rate_limits_are_still_retried() {
+    for kind in ["requests", "tokens", "invalid_request_error"] {
+        let error = json!({
+            "error": {
+                "message": "Rate limit reached for requests",
+                "type": kind,
+                "param": null,
+                "code": "rate_limit_exceeded"
+            }
+        });
+        let (api_base, requests) = serve(vec![(429, error.to_string()), completion()]);
+
+        create(&client(api_base)).await.unwrap();
+
+        assert_eq!(requests.load(Ordering::SeqCst), 2, "{kind}");
+    }
+}
+
+#[tokio::test]
+async fn invalid_requests_are_permanent() {
+    let cases = [
+        (400, "invalid_request_error", None),
+        (429, "invalid_request_error", None),
+        (401, "invalid_request_error", Some("invalid_api_key")),
+        (404, "invalid_request_error", Some("model_not_found")),
+        (401, "authentication_error", None),
+    ];
+    for (status, kind, code) in cases {
+        let error = json!({
+            "error": {
+                "message": "Rejected",
+                "type": kind,
+                "param": null,
+                "code": code
+            }
+        });
+        let (api_base, requests) = serve(vec![(status, error.to_string()), completion()]);
+
+        let error = create(&client(api_base)).await.unwrap_err();
+
+        assert!(
+            matches!(error, OpenAIError::ApiError(_)) && error.is_permanent(),
+            "{status} {kind} {code:?}: {error}"
+        );
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    let error = json!({
+        "error": {
+            "message": "The server had an error while processing your request.",
+            "type": "server_error",
+            "param": null,
+            "code": null
+        }
+    });
+    let (api_base, _) = serve(vec![(500, error.to_string())]);
+    assert!(!create(&client(api_base)).await.unwrap_err().is_permanent());
+}
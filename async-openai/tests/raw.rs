@@ -0,0 +1,117 @@
+use std::{
+    io::{Read, Write},
+    net::TcpListener,
+    sync::mpsc,
+};
+
+use async_openai::{config::OpenAIConfig, error::OpenAIError, Client};
+use futures::StreamExt;
+use serde_json::json;
+
+/// Serve one response with `content_type` and `body` on a local port, sending the received
+/// request body to the returned receiver.
+fn serve_once(content_type: &'static str, body: String) -> (String, mpsc::Receiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (tx, rx) = mpsc::channel();
+
+    std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut buf = [0; 16384];
+        let n = stream.read(&mut buf).unwrap();
+        let request = String::from_utf8_lossy(&buf[..n]).to_string();
+        let _ = tx.send(
+            request
+                .split("\r\n\r\n")
+                .nth(1)
+                .unwrap_or_default()
+                .to_string(),
+        );
+        let response = format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: {content_type}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+            body.len()
+        );
+        stream.write_all(response.as_bytes()).unwrap();
+    });
+
+    (format!("http://{addr}/v1"), rx)
+}
+
+fn client(api_base: String) -> Client<OpenAIConfig> {
+    Client::with_config(
+        OpenAIConfig::new()
+            .with_api_base(api_base)
+            .with_api_key("sk-test"),
+    )
+}
+
+#[tokio::test]
+async fn create_raw_sends_unknown_fields() {
+    let (api_base, received) = serve_once(
+        "application/json",
+        json!({ "id": "chatcmpl-1", "choices": [], "brand_new_field": 1 }).to_string(),
+    );
+
+    let response = client(api_base)
+        .chat()
+        .create_raw(json!({
+            "model": "gpt-4o-mini",
+            "messages": [{ "role": "user", "content": "Hi" }],
+            "reasoning": { "effort": "low" },
+        }))
+        .await
+        .unwrap();
+    assert_eq!(response["brand_new_field"], 1);
+
+    let sent: serde_json::Value = serde_json::from_str(&received.recv().unwrap()).unwrap();
+    assert_eq!(sent["reasoning"]["effort"], "low");
+    assert!(sent.get("stream").is_none());
+}
+
+#[tokio::test]
+async fn create_stream_raw_sets_stream() {
+    let (api_base, received) = serve_once(
+        "text/event-stream",
+        "data: {\"choices\":[{\"delta\":{\"content\":\"Hel\"}}]}\n\ndata: {\"choices\":[{\"delta\":{\"content\":\"lo\"}}]}\n\ndata: [DONE]\n\n".to_string(),
+    );
+
+    let chunks: Vec<_> = client(api_base)
+        .chat()
+        .create_stream_raw(json!({
+            "model": "gpt-4o-mini",
+            "messages": [{ "role": "user", "content": "Hi" }],
+        }))
+        .await
+        .unwrap()
+        .collect()
+        .await;
+    let content: String = chunks
+        .iter()
+        .map(|chunk| {
+            chunk.as_ref().unwrap()["choices"][0]["delta"]["content"]
+                .as_str()
+                .unwrap()
+        })
+        .collect();
+    assert_eq!(content, "Hello");
+
+    let sent: serde_json::Value = serde_json::from_str(&received.recv().unwrap()).unwrap();
+    assert_eq!(sent["stream"], true);
+}
+
+#[tokio::test]
+async fn raw_requests_are_checked_before_sending() {
+    let client = client("http://127.0.0.1:9/v1".into());
+
+    let result = client
+        .chat()
+        .create_raw(json!({ "model": "gpt-4o-mini", "stream": true }))
+        .await;
+    assert!(matches!(result, Err(OpenAIError::InvalidArgument(_))));
+
+    let result = client
+        .embeddings()
+        .create_raw(json!(["not", "an", "object"]))
+        .await;
+    assert!(matches!(result, Err(OpenAIError::InvalidArgument(_))));
+}
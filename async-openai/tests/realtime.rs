@@ -0,0 +1,70 @@
+use std::time::Duration;
+
+use async_openai::{
+    error::OpenAIError,
+    realtime::{AudioCallback, AudioInputSink},
+};
+use bytes::Bytes;
+use futures::{SinkExt, StreamExt};
+use serde_json::Value;
+
+#[tokio::test]
+async fn audio_sink_chunks_and_commits() {
+    let (sender, receiver) = futures::channel::mpsc::unbounded();
+    let mut sink = AudioInputSink::new(sender.sink_map_err(|_| ()))
+        .frame_duration(Duration::from_millis(100))
+        .commit_every(Duration::from_millis(200));
+
+    // 250ms of pcm16 at 24kHz, in uneven chunks
+    let audio = vec![0u8; 12_000];
+    for chunk in audio.chunks(1_000) {
+        sink.send(Bytes::copy_from_slice(chunk)).await.unwrap();
+    }
+    sink.close().await.unwrap();
+    drop(sink);
+
+    let events: Vec<String> = receiver
+        .map(|message| {
+            let event: Value = serde_json::from_str(message.to_text().unwrap()).unwrap();
+            event["type"].as_str().unwrap().to_string()
+        })
+        .collect()
+        .await;
+
+    assert_eq!(
+        events,
+        [
+            "input_audio_buffer.append",
+            "input_audio_buffer.append",
+            "input_audio_buffer.commit",
+            "input_audio_buffer.append",
+            "input_audio_buffer.commit",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn audio_callback_downmixes_and_resamples() {
+    let (mut callback, receiver) = AudioCallback::new(48_000, 2).unwrap();
+
+    // 10ms of 48kHz stereo, split across two callbacks
+    let samples = vec![0.5f32; 960];
+    callback.push_f32(&samples[..480]);
+    callback.push_f32(&samples[480..]);
+    drop(callback);
+
+    let audio: Vec<u8> = receiver.map(|bytes| bytes.to_vec()).concat().await;
+
+    // 10ms of 24kHz mono pcm16
+    assert_eq!(audio.len(), 480);
+    let first = i16::from_le_bytes([audio[0], audio[1]]);
+    assert_eq!(first, (0.5 * i16::MAX as f32) as i16);
+}
+
+#[test]
+fn audio_callback_rejects_zero_sample_rate() {
+    assert!(matches!(
+        AudioCallback::new(0, 1),
+        Err(OpenAIError::InvalidArgument(_))
+    ));
+}
@@ -0,0 +1,70 @@
+use async_openai::{
+    reproducibility::{ReproducibilityContext, ReproducibilityRecord},
+    types::{
+        CreateChatCompletionRequest, CreateChatCompletionRequestArgs, CreateChatCompletionResponse,
+    },
+};
+
+fn response(id: &str, model: &str, fingerprint: Option<&str>) -> CreateChatCompletionResponse {
+    serde_json::from_value(serde_json::json!({
+        "id": id,
+        "object": "chat.completion",
+        "created": 1,
+        "model": model,
+        "system_fingerprint": fingerprint,
+        "choices": []
+    }))
+    .unwrap()
+}
+
+#[test]
+fn adopts_first_seed_and_stamps_later_requests() {
+    let context = ReproducibilityContext::new();
+    let mut first = CreateChatCompletionRequestArgs::default()
+        .model("gpt-4o-mini")
+        .messages(vec![])
+        .seed(7)
+        .build()
+        .unwrap();
+    context.stamp(&mut first);
+    assert_eq!(context.seed(), Some(7));
+
+    let mut second = CreateChatCompletionRequest {
+        seed: None,
+        ..first.clone()
+    };
+    context.stamp(&mut second);
+    assert_eq!(second.seed, Some(7));
+}
+
+#[test]
+fn reports_fingerprint_drift_per_model() {
+    let context = ReproducibilityContext::with_seed(1);
+
+    assert!(context
+        .record(&response("a", "gpt-4o", Some("fp_1")))
+        .is_none());
+    assert!(context
+        .record(&response("b", "gpt-4o-mini", Some("fp_9")))
+        .is_none());
+    assert!(context.record(&response("c", "gpt-4o", None)).is_none());
+    assert!(context
+        .record(&response("d", "gpt-4o", Some("fp_1")))
+        .is_none());
+    assert!(context.is_consistent());
+
+    let drift = context
+        .record(&response("e", "gpt-4o", Some("fp_2")))
+        .unwrap();
+    assert_eq!(
+        (drift.previous.as_str(), drift.current.as_str()),
+        ("fp_1", "fp_2")
+    );
+    assert!(!context.is_consistent());
+
+    let record: ReproducibilityRecord =
+        serde_json::from_str(&serde_json::to_string(&context.snapshot()).unwrap()).unwrap();
+    assert_eq!(record.seed, Some(1));
+    assert_eq!(record.observations.len(), 5);
+    assert_eq!(record.drifts, vec![drift]);
+}
@@ -1,6 +1,7 @@
 use async_openai::types::{
     ChatCompletionRequestSystemMessageArgs, ChatCompletionRequestUserMessageArgs,
-    CreateChatCompletionRequest, CreateChatCompletionRequestArgs,
+    ContentFilterSeverity, CreateChatCompletionRequest, CreateChatCompletionRequestArgs,
+    CreateChatCompletionResponse, FinishReason, HasUsage, ModelName,
 };
 
 #[tokio::test]
@@ -26,3 +27,142 @@ async fn chat_types_serde() {
     let deserialized: CreateChatCompletionRequest = serde_json::from_str(&serialized).unwrap();
     assert_eq!(request, deserialized);
 }
+
+#[test]
+fn model_name_serde() {
+    for model in ModelName::KNOWN {
+        let serialized = serde_json::to_string(model).unwrap();
+        let deserialized: ModelName = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(model, &deserialized);
+        assert!(!matches!(deserialized, ModelName::Custom(_)), "{model}");
+    }
+
+    let custom: ModelName = serde_json::from_str("\"ft:gpt-4o-mini:acme::abc123\"").unwrap();
+    assert_eq!(
+        custom,
+        ModelName::Custom("ft:gpt-4o-mini:acme::abc123".into())
+    );
+    assert_eq!(ModelName::Custom("gpt-4o".into()), ModelName::Gpt4o);
+    assert_eq!(
+        String::from(ModelName::DeepSeekReasoner),
+        "deepseek-reasoner"
+    );
+}
+
+#[test]
+fn finish_reason_serde() {
+    for (json, reason) in [
+        ("\"stop\"", FinishReason::Stop),
+        ("\"tool_calls\"", FinishReason::ToolCalls),
+        (
+            "\"insufficient_system_resource\"",
+            FinishReason::Other("insufficient_system_resource".into()),
+        ),
+    ] {
+        assert_eq!(serde_json::from_str::<FinishReason>(json).unwrap(), reason);
+        assert_eq!(serde_json::to_string(&reason).unwrap(), json);
+    }
+
+    let response: CreateChatCompletionResponse = serde_json::from_value(serde_json::json!({
+        "id": "chatcmpl-1",
+        "object": "chat.completion",
+        "created": 1700000000,
+        "model": "gpt-4o-mini",
+        "choices": [{
+            "index": 0,
+            "message": { "role": "assistant", "content": "{\"name\": \"Ad" },
+            "finish_reason": "length",
+            "logprobs": null
+        }]
+    }))
+    .unwrap();
+    assert!(response.was_truncated());
+}
+
+#[test]
+fn azure_content_filter_results() {
+    let response: CreateChatCompletionResponse = serde_json::from_value(serde_json::json!({
+        "id": "chatcmpl-1",
+        "object": "chat.completion",
+        "created": 1,
+        "model": "gpt-4o",
+        "prompt_filter_results": [{
+            "prompt_index": 0,
+            "content_filter_results": {
+                "hate": { "filtered": false, "severity": "safe" },
+                "self_harm": { "filtered": false, "severity": "low" },
+                "jailbreak": { "filtered": true, "detected": true }
+            }
+        }],
+        "choices": [{
+            "index": 0,
+            "message": { "role": "assistant", "content": "def f(): ..." },
+            "finish_reason": "stop",
+            "logprobs": null,
+            "content_filter_results": {
+                "violence": { "filtered": false, "severity": "medium" },
+                "protected_material_code": {
+                    "filtered": false,
+                    "detected": true,
+                    "citation": { "URL": "https://github.com/example/repo", "license": "MIT" }
+                }
+            }
+        }]
+    }))
+    .unwrap();
+
+    let prompt = &response.prompt_filter_results.as_ref().unwrap()[0];
+    assert!(prompt.content_filter_results.is_filtered());
+    assert_eq!(
+        prompt.content_filter_results.max_severity(),
+        Some(ContentFilterSeverity::Low)
+    );
+
+    let completion = response.choices[0].content_filter_results.as_ref().unwrap();
+    assert!(!completion.is_filtered());
+    assert_eq!(
+        completion.max_severity(),
+        Some(ContentFilterSeverity::Medium)
+    );
+    let citation = completion
+        .protected_material_code
+        .as_ref()
+        .and_then(|code| code.citation.as_ref())
+        .unwrap();
+    assert_eq!(citation.license.as_deref(), Some("MIT"));
+
+    let value = serde_json::to_value(&response).unwrap();
+    assert_eq!(
+        value["choices"][0]["content_filter_results"]["protected_material_code"]["citation"]["URL"],
+        "https://github.com/example/repo"
+    );
+}
+
+#[test]
+fn unified_usage() {
+    let response: CreateChatCompletionResponse = serde_json::from_value(serde_json::json!({
+        "id": "chatcmpl-1",
+        "object": "chat.completion",
+        "created": 1,
+        "model": "o3-mini",
+        "choices": [],
+        "usage": {
+            "prompt_tokens": 1200,
+            "completion_tokens": 300,
+            "total_tokens": 1500,
+            "prompt_tokens_details": { "cached_tokens": 1024, "audio_tokens": null },
+            "completion_tokens_details": { "reasoning_tokens": 256 }
+        }
+    }))
+    .unwrap();
+
+    let usage = response.unified_usage().unwrap();
+    assert_eq!(usage.cached_tokens, 1024);
+    assert_eq!(usage.uncached_prompt_tokens(), 176);
+    assert_eq!(usage.reasoning_tokens, 256);
+    assert_eq!(usage.prompt_audio_tokens, 0);
+
+    let doubled = usage + usage;
+    assert_eq!(doubled.total_tokens, 3000);
+    assert_eq!(doubled.reasoning_tokens, 512);
+}
@@ -0,0 +1,85 @@
+use std::{
+    io::{Read, Write},
+    net::TcpListener,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use async_openai::{config::OpenAIConfig, error::OpenAIError, shutdown::ShutdownOutcome, Client};
+
+/// Accept a single connection and answer it with a model list after `delay`.
+fn serve_once(delay: Duration) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut buf = [0; 8192];
+        let _ = stream.read(&mut buf).unwrap();
+        std::thread::sleep(delay);
+        let body = r#"{"object":"list","data":[]}"#;
+        let response = format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+            body.len()
+        );
+        let _ = stream.write_all(response.as_bytes());
+    });
+
+    format!("http://{addr}/v1")
+}
+
+fn client(api_base: String) -> Client<OpenAIConfig> {
+    Client::with_config(
+        OpenAIConfig::new()
+            .with_api_base(api_base)
+            .with_api_key("sk-test"),
+    )
+}
+
+#[tokio::test]
+async fn shutdown_drains_and_rejects_new_requests() {
+    let flushed = Arc::new(AtomicBool::new(false));
+    let hook_flushed = flushed.clone();
+    let client = client(serve_once(Duration::from_millis(100))).with_shutdown_hook(move || {
+        let flushed = hook_flushed.clone();
+        async move { flushed.store(true, Ordering::SeqCst) }
+    });
+
+    let in_flight = tokio::spawn({
+        let client = client.clone();
+        async move { client.models().list().await }
+    });
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    let outcome = client.shutdown(Duration::from_secs(5)).await;
+    assert_eq!(outcome, ShutdownOutcome::Drained);
+    assert!(in_flight.await.unwrap().is_ok());
+    assert!(flushed.load(Ordering::SeqCst));
+
+    assert!(client.is_shut_down());
+    assert!(matches!(
+        client.models().list().await,
+        Err(OpenAIError::ClientShutdown)
+    ));
+}
+
+#[tokio::test]
+async fn shutdown_aborts_at_deadline() {
+    let client = client(serve_once(Duration::from_secs(5)));
+
+    let in_flight = tokio::spawn({
+        let client = client.clone();
+        async move { client.models().list().await }
+    });
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    let outcome = client.shutdown(Duration::from_millis(50)).await;
+    assert_eq!(outcome, ShutdownOutcome::Aborted(1));
+    assert!(matches!(
+        in_flight.await.unwrap(),
+        Err(OpenAIError::ClientShutdown)
+    ));
+}
@@ -0,0 +1,126 @@
+use std::{
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use async_openai::{
+    config::OpenAIConfig,
+    events::ClientEvent,
+    speech_cache::{FileSpeechStore, SpeechCacheKey},
+    types::{CreateSpeechRequestArgs, SpeechModel, Voice},
+    Client,
+};
+
+/// Read a request up to the end of its JSON body.
+fn read_body(stream: &mut TcpStream) {
+    let mut request = String::new();
+    let mut buf = [0; 16384];
+    loop {
+        let n = stream.read(&mut buf).unwrap();
+        request.push_str(&String::from_utf8_lossy(&buf[..n]));
+        if let Some((_, body)) = request.split_once("\r\n\r\n") {
+            if serde_json::from_str::<serde_json::Value>(body).is_ok() {
+                return;
+            }
+        }
+    }
+}
+
+/// Answer every request with `audio`, counting the requests.
+fn serve(audio: &'static [u8], requests: Arc<AtomicUsize>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            read_body(&mut stream);
+            requests.fetch_add(1, Ordering::SeqCst);
+            let head = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: audio/mpeg\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+                audio.len()
+            );
+            stream.write_all(head.as_bytes()).unwrap();
+            stream.write_all(audio).unwrap();
+        }
+    });
+
+    format!("http://{addr}/v1")
+}
+
+fn cache_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "async-openai-{name}-{}-{}",
+        std::process::id(),
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos()
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+#[tokio::test]
+async fn repeated_phrases_are_synthesized_once() {
+    let requests = Arc::new(AtomicUsize::new(0));
+    let api_base = serve(b"ID3 audio", requests.clone());
+    let dir = cache_dir("speech-cache");
+    let client = Client::with_config(
+        OpenAIConfig::new()
+            .with_api_base(api_base)
+            .with_api_key("sk-test"),
+    )
+    .with_speech_cache(FileSpeechStore::new(&dir));
+    let mut events = client.events().subscribe();
+
+    let request = CreateSpeechRequestArgs::default()
+        .input("Welcome back!")
+        .model(SpeechModel::Tts1)
+        .voice(Voice::Alloy)
+        .build()
+        .unwrap();
+    let key = SpeechCacheKey::new(&request);
+    assert_eq!(key.model, "tts-1");
+    assert_eq!(key.voice, "alloy");
+    assert_eq!(key.format, "mp3");
+
+    let first = client.audio().speech(request.clone()).await.unwrap();
+    let second = client.audio().speech(request.clone()).await.unwrap();
+    assert_eq!(first.bytes, &b"ID3 audio"[..]);
+    assert_eq!(second.bytes, first.bytes);
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+    assert!(dir.join(key.file_name()).is_file());
+
+    assert_eq!(
+        events.recv().await.unwrap(),
+        ClientEvent::SpeechCacheMiss { key: key.clone() }
+    );
+    assert!(matches!(
+        events.recv().await.unwrap(),
+        ClientEvent::RequestStarted { .. }
+    ));
+    assert!(matches!(
+        events.recv().await.unwrap(),
+        ClientEvent::RequestFinished { error: None, .. }
+    ));
+    assert_eq!(
+        events.recv().await.unwrap(),
+        ClientEvent::SpeechCacheHit { key }
+    );
+
+    // Another voice is another key
+    let other_voice = CreateSpeechRequestArgs::default()
+        .input("Welcome back!")
+        .voice(Voice::Nova)
+        .build()
+        .unwrap();
+    client.audio().speech(other_voice).await.unwrap();
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,123 @@
+use std::{
+    io::{Read, Write},
+    net::TcpListener,
+    sync::mpsc,
+};
+
+use async_openai::{config::OpenAIConfig, types::Role, types::UpdateChatCompletionRequest, Client};
+use serde_json::json;
+
+/// Answer a single request with `body`, sending the request head and body to the returned
+/// receiver.
+fn serve_once(body: String) -> (String, mpsc::Receiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (tx, rx) = mpsc::channel();
+
+    std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = String::new();
+        let mut buf = [0; 16384];
+        // Read until the end of the body, which may come in a separate packet
+        while !request.contains("\r\n\r\n") || !body_complete(&request) {
+            let n = stream.read(&mut buf).unwrap();
+            request.push_str(&String::from_utf8_lossy(&buf[..n]));
+        }
+        let _ = tx.send(request);
+        let response = format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+            body.len()
+        );
+        stream.write_all(response.as_bytes()).unwrap();
+    });
+
+    (format!("http://{addr}/v1"), rx)
+}
+
+fn body_complete(request: &str) -> bool {
+    let (head, body) = request.split_once("\r\n\r\n").unwrap_or((request, ""));
+    let length = head.lines().find_map(|line| {
+        line.to_lowercase()
+            .strip_prefix("content-length: ")?
+            .parse::<usize>()
+            .ok()
+    });
+    body.len() >= length.unwrap_or(0)
+}
+
+fn client(api_base: String) -> Client<OpenAIConfig> {
+    Client::with_config(
+        OpenAIConfig::new()
+            .with_api_base(api_base)
+            .with_api_key("sk-test"),
+    )
+}
+
+#[tokio::test]
+async fn lists_messages_of_stored_completion() {
+    let body = json!({
+        "object": "list",
+        "data": [
+            { "id": "chatcmpl-1-0", "role": "user", "content": "Hi" },
+            { "id": "chatcmpl-1-1", "role": "assistant", "content": "Hello!", "refusal": null }
+        ],
+        "first_id": "chatcmpl-1-0",
+        "last_id": "chatcmpl-1-1",
+        "has_more": false
+    });
+    let (api_base, requests) = serve_once(body.to_string());
+
+    let messages = client(api_base)
+        .chat()
+        .messages("chatcmpl-1", &[("limit", "2")])
+        .await
+        .unwrap();
+
+    assert_eq!(messages.data.len(), 2);
+    assert_eq!(messages.data[0].message.role, Role::User);
+    assert_eq!(messages.data[1].message.content.as_deref(), Some("Hello!"));
+    assert!(requests
+        .recv()
+        .unwrap()
+        .starts_with("GET /v1/chat/completions/chatcmpl-1/messages?limit=2 "));
+}
+
+#[tokio::test]
+async fn updates_and_deletes_stored_completion() {
+    let (api_base, requests) = serve_once(
+        json!({ "id": "chatcmpl-1", "object": "chat.completion.deleted", "deleted": true })
+            .to_string(),
+    );
+    let deleted = client(api_base).chat().delete("chatcmpl-1").await.unwrap();
+    assert!(deleted.deleted);
+    assert!(requests
+        .recv()
+        .unwrap()
+        .starts_with("DELETE /v1/chat/completions/chatcmpl-1 "));
+
+    let completion = json!({
+        "id": "chatcmpl-1",
+        "object": "chat.completion",
+        "created": 1,
+        "model": "gpt-4o-mini",
+        "choices": [{
+            "index": 0,
+            "message": { "role": "assistant", "content": "Hello!" },
+            "finish_reason": "stop"
+        }],
+        "metadata": { "topic": "greeting" }
+    });
+    let (api_base, requests) = serve_once(completion.to_string());
+    let request = UpdateChatCompletionRequest {
+        metadata: Some([("topic".to_string(), "greeting".to_string())].into()),
+    };
+    let updated = client(api_base)
+        .chat()
+        .update("chatcmpl-1", request)
+        .await
+        .unwrap();
+    assert_eq!(updated.id, "chatcmpl-1");
+    let request = requests.recv().unwrap();
+    assert!(request.starts_with("POST /v1/chat/completions/chatcmpl-1 "));
+    assert!(request.ends_with(r#"{"metadata":{"topic":"greeting"}}"#));
+}
@@ -0,0 +1,223 @@
+use std::{
+    io::{Read, Write},
+    net::TcpListener,
+    time::{Duration, Instant},
+};
+
+use async_openai::{
+    config::OpenAIConfig,
+    error::OpenAIError,
+    stream::{
+        replay_chat_file, Pacing, ReplayTiming, StreamDeadlineExt, StreamPacingExt, StreamRecorder,
+        StreamTapExt,
+    },
+    types::{CreateChatCompletionRequestArgs, CreateChatCompletionStreamResponse},
+    Client,
+};
+use futures::StreamExt;
+
+#[tokio::test]
+async fn fork_skips_errors_and_ends_with_stream() {
+    let items = vec![Ok(1), Err(OpenAIError::StreamError("boom".into())), Ok(2)];
+
+    let (stream, receiver) = futures::stream::iter(items).fork();
+
+    let primary: Vec<_> = stream.collect().await;
+    assert_eq!(primary.len(), 3);
+    assert!(primary[1].is_err());
+
+    let observed: Vec<i32> = receiver.collect().await;
+    assert_eq!(observed, vec![1, 2]);
+}
+
+#[tokio::test]
+async fn tap_observes_without_consuming() {
+    let mut seen = vec![];
+    let total: i32 = futures::stream::iter([1, 2, 3])
+        .tap(|item| seen.push(*item))
+        .fold(0, |acc, item| async move { acc + item })
+        .await;
+
+    assert_eq!(total, 6);
+    assert_eq!(seen, vec![1, 2, 3]);
+}
+
+#[tokio::test]
+async fn recorded_stream_replays_from_fixture() {
+    let chunk = |content: &str| -> CreateChatCompletionStreamResponse {
+        serde_json::from_value(serde_json::json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion.chunk",
+            "created": 1700000000,
+            "model": "gpt-4o-mini",
+            "choices": [{ "index": 0, "delta": { "content": content }, "finish_reason": null }]
+        }))
+        .unwrap()
+    };
+
+    let mut recorder = StreamRecorder::new();
+    recorder.record(&chunk("Hel"));
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    recorder.record(&chunk("lo"));
+
+    let path = std::env::temp_dir().join(format!("async-openai-replay-{}.sse", std::process::id()));
+    recorder.save(&path).unwrap();
+
+    let started = Instant::now();
+    let replayed: Vec<_> = replay_chat_file(&path, ReplayTiming::Recorded)
+        .await
+        .unwrap()
+        .map(|chunk| chunk.unwrap().choices[0].delta.content.clone().unwrap())
+        .collect()
+        .await;
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(replayed, vec!["Hel", "lo"]);
+    assert!(started.elapsed() >= Duration::from_millis(50));
+}
+
+#[tokio::test]
+async fn deadline_aborts_with_partial_content() {
+    // A chunk every 30ms, forever
+    let chunks = futures::stream::unfold(0, |n| async move {
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        Some((Ok::<_, OpenAIError>(format!("{n} ")), n + 1))
+    })
+    .boxed();
+
+    let items: Vec<_> = chunks.deadline(Duration::from_millis(100)).collect().await;
+
+    let (last, received) = items.split_last().unwrap();
+    assert!(!received.is_empty() && received.iter().all(Result::is_ok));
+    let expected: String = received
+        .iter()
+        .map(|item| item.as_ref().unwrap().as_str())
+        .collect();
+    match last {
+        Err(OpenAIError::StreamDeadlineExceeded {
+            limit,
+            partial_content,
+        }) => {
+            assert_eq!(*limit, Duration::from_millis(100));
+            assert_eq!(partial_content, &expected);
+        }
+        other => panic!("expected a deadline error, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn deadline_aborts_stream_that_is_always_ready() {
+    let chunks = futures::stream::iter(0..).map(|n| {
+        std::thread::sleep(Duration::from_millis(1));
+        Ok::<_, OpenAIError>(format!("{n} "))
+    });
+
+    let items: Vec<_> = chunks.deadline(Duration::from_millis(20)).collect().await;
+
+    assert!(matches!(
+        items.last(),
+        Some(Err(OpenAIError::StreamDeadlineExceeded { .. }))
+    ));
+    assert!(items[..items.len() - 1].iter().all(Result::is_ok));
+}
+
+#[tokio::test]
+async fn pace_spreads_out_bursts() {
+    let chunks = ["Hel", "", "lo", " there"].map(|c| Ok::<_, OpenAIError>(c.to_string()));
+
+    let started = Instant::now();
+    let paced: Vec<_> = futures::stream::iter(chunks)
+        .pace(Pacing::Interval(Duration::from_millis(100)))
+        .map(Result::unwrap)
+        .collect()
+        .await;
+
+    assert_eq!(paced, ["Hel", "", "lo", " there"]);
+    // No delay before the first chunk, nor for the empty one
+    let elapsed = started.elapsed();
+    assert!(elapsed >= Duration::from_millis(200), "{elapsed:?}");
+    assert!(elapsed < Duration::from_millis(300), "{elapsed:?}");
+}
+
+#[tokio::test]
+async fn pace_limits_tokens_per_second() {
+    let chunks = futures::stream::iter(vec![
+        Ok("a".to_string()),
+        Ok("b".to_string()),
+        Ok("c".to_string()),
+        Err(OpenAIError::StreamError("boom".into())),
+    ]);
+
+    let started = Instant::now();
+    let paced: Vec<_> = chunks.pace(Pacing::TokensPerSecond(50.0)).collect().await;
+
+    assert_eq!(paced.len(), 4);
+    assert!(paced[3].is_err());
+    assert!(started.elapsed() >= Duration::from_millis(40));
+}
+
+#[tokio::test]
+async fn pace_holds_back_chunks_without_overflowing() {
+    for pacing in [
+        Pacing::TokensPerSecond(f64::MIN_POSITIVE),
+        Pacing::Interval(Duration::MAX),
+    ] {
+        let chunks = ["a", "b"].map(|c| Ok::<_, OpenAIError>(c.to_string()));
+        let mut paced = futures::stream::iter(chunks).pace(pacing);
+
+        assert_eq!(paced.next().await.unwrap().unwrap(), "a");
+        let next = tokio::time::timeout(Duration::from_millis(50), paced.next()).await;
+        assert!(next.is_err(), "{pacing:?}");
+    }
+}
+
+#[tokio::test]
+async fn error_event_ends_stream_with_api_error() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let _ = stream.read(&mut [0; 8192]).unwrap();
+        let body = concat!(
+            "data: {\"id\":\"1\",\"object\":\"chat.completion.chunk\",\"created\":1,\"model\":\"m\",",
+            "\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Hel\"},\"finish_reason\":null}]}\n\n",
+            "data: {\"error\":{\"message\":\"This model's maximum context length is 8192 tokens\",",
+            "\"code\":\"context_length_exceeded\"},",
+            "\"usage\":{\"prompt_tokens\":8000,\"completion_tokens\":1,\"total_tokens\":8001}}\n\n",
+            "data: {\"id\":\"1\",\"object\":\"chat.completion.chunk\",\"created\":1,\"model\":\"m\",\"choices\":[]}\n\n",
+        );
+        let response = format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+            body.len()
+        );
+        stream.write_all(response.as_bytes()).unwrap();
+    });
+
+    let client = Client::with_config(
+        OpenAIConfig::new()
+            .with_api_base(format!("http://{addr}/v1"))
+            .with_api_key("sk-test"),
+    );
+    let request = CreateChatCompletionRequestArgs::default()
+        .model("gpt-4o-mini")
+        .messages(vec![])
+        .build()
+        .unwrap();
+    let items: Vec<_> = client
+        .chat()
+        .create_stream(request)
+        .await
+        .unwrap()
+        .collect()
+        .await;
+
+    assert_eq!(items.len(), 2, "{items:?}");
+    assert!(items[0].is_ok());
+    match &items[1] {
+        Err(OpenAIError::StreamApiError { error, usage }) => {
+            assert!(matches!(**error, OpenAIError::ContextLengthExceeded(_)));
+            assert_eq!(usage.as_ref().unwrap().prompt_tokens, 8000);
+        }
+        other => panic!("expected a stream api error, got {other:?}"),
+    }
+}
@@ -0,0 +1,2489 @@
+use std::{
+    io::{Read, Write},
+    net::TcpListener,
+};
+
+use async_openai::{
+    config::OpenAIConfig,
+    error::OpenAIError,
+    response_cache::{FileResponseCache, InMemoryResponseCache},
+    structured::{
+        extract, BatchGenerator, CompositeGenerator, DynGenerator, ExperimentRunner, Format,
+        Generator, Sample, SchemaRegistry, StructuredOutput, StructuredSession,
+        ToolCallAccumulator,
+    },
+    types::{
+        structured::{
+            CandidateSelection, CheckFlag, ExtractionMethod, ExtractionStrategy, FieldDiff,
+            InstructionTemplate, Locale, Located, OutputFormat, ParseError, ReasoningFilter,
+            SchemaDialect, SchemaSource, Severity, StreamedOutput, ValidationOptions,
+        },
+        ChatCompletionMessageToolCall, ChatCompletionRequestUserMessage,
+        ChatCompletionTokenLogprob, ChatCompletionToolType, CreateChatCompletionRequestArgs,
+        CreateChatCompletionResponse, CreateChatCompletionStreamResponse, FunctionCall,
+        ResponseFormat,
+    },
+    Client,
+};
+use futures::StreamExt;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+struct City {
+    name: String,
+    population: u64,
+}
+
+/// `declared` with the `structured-field-order` feature, where fields keep the order of their
+/// declaration, `sorted` without it, where they are sorted by name
+fn by_field_order<T>(declared: T, sorted: T) -> T {
+    if cfg!(feature = "structured-field-order") {
+        declared
+    } else {
+        sorted
+    }
+}
+
+fn berlin() -> City {
+    City {
+        name: "Berlin".into(),
+        population: 3_700_000,
+    }
+}
+
+#[test]
+fn sanitizer_cleans_parsed_strings() -> Result<(), ParseError> {
+    use async_openai::structured::sanitize::{self, Sanitizer};
+
+    assert_eq!(sanitize::strip_fences("```text\nHello\n```"), "Hello");
+    assert_eq!(
+        sanitize::unescape_html("Tom &amp; Jerry &lt;3 &#x263A; &#9731; &bogus;"),
+        "Tom & Jerry <3 \u{263A} \u{2603} &bogus;"
+    );
+    assert_eq!(
+        sanitize::collapse_whitespace("  a   b \n\n\n c\t\td  \n\n"),
+        "a b\n\nc d"
+    );
+    assert_eq!(
+        sanitize::remove_zero_width("Ber\u{200B}lin\u{FEFF}"),
+        "Berlin"
+    );
+
+    let response = "{\"name\": \"\u{201C}Berlin\u{201D} &amp;  Brandenburg\u{200D}\", \"population\": 3700000}";
+    let parsed = Generator::json(City::default())
+        .sanitize(Sanitizer::all())
+        .parse_response(response)?;
+    assert_eq!(parsed.data.name, "\"Berlin\" & Brandenburg");
+
+    // Off unless set
+    let parsed = Generator::json(City::default()).parse_response(response)?;
+    assert!(parsed.data.name.contains("&amp;"));
+    Ok(())
+}
+
+#[test]
+fn detects_format_from_fence_and_leading_characters() {
+    assert_eq!(
+        OutputFormat::detect("```json\n{\"a\": 1}\n```"),
+        OutputFormat::Json
+    );
+    assert_eq!(OutputFormat::detect("  [1, 2]"), OutputFormat::JsonArray);
+    assert_eq!(
+        OutputFormat::detect("```\n{\"a\": 1}\n```"),
+        OutputFormat::Json
+    );
+    assert_eq!(
+        OutputFormat::detect("no structure at all"),
+        OutputFormat::Json
+    );
+
+    #[cfg(feature = "yaml")]
+    {
+        assert_eq!(
+            OutputFormat::detect("```yml\na: 1\n```"),
+            OutputFormat::Yaml
+        );
+        assert_eq!(
+            OutputFormat::detect("name: Berlin\npopulation: 1"),
+            OutputFormat::Yaml
+        );
+        assert_eq!(OutputFormat::detect("---\n- 1"), OutputFormat::Yaml);
+    }
+    #[cfg(feature = "xml")]
+    {
+        assert_eq!(OutputFormat::detect("```xml\n<a/>\n```"), OutputFormat::Xml);
+        assert_eq!(
+            OutputFormat::detect("<?xml version=\"1.0\"?><a/>"),
+            OutputFormat::Xml
+        );
+    }
+    #[cfg(feature = "toml")]
+    {
+        assert_eq!(
+            OutputFormat::detect("```toml\na = 1\n```"),
+            OutputFormat::Toml
+        );
+        assert_eq!(
+            OutputFormat::detect("name = \"Berlin\"\npopulation = 1"),
+            OutputFormat::Toml
+        );
+    }
+    #[cfg(feature = "csv")]
+    assert_eq!(
+        OutputFormat::detect("```csv\na,b\n1,2\n```"),
+        OutputFormat::Csv
+    );
+}
+
+#[cfg(feature = "csv")]
+#[test]
+fn csv_format_round_trip() -> Result<(), ParseError> {
+    #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+    struct Row {
+        code: String,
+        name: String,
+        population: u64,
+        capital: Option<bool>,
+    }
+
+    let generator = Generator::csv(vec![Row {
+        code: "049".into(),
+        name: "Berlin, Germany".into(),
+        population: 3700000,
+        capital: Some(true),
+    }]);
+    let instruction = generator.build_instruction_text();
+    assert!(instruction.contains(by_field_order(
+        "```csv\ncode,name,population,capital\n049,\"Berlin, Germany\",3700000,true\n```",
+        "```csv\ncapital,code,name,population\ntrue,049,\"Berlin, Germany\",3700000\n```"
+    )));
+
+    let parsed = generator.parse_response(
+        "```csv\ncode,name,population,capital\n049,\"Berlin, Germany\",3700000,true\n033,Paris,2100000,\n```",
+    )?;
+    assert_eq!(parsed.data.len(), 2);
+    // Digits in a string column stay a string
+    assert_eq!(parsed.data[0].code, "049");
+    assert_eq!(parsed.data[0].name, "Berlin, Germany");
+    assert_eq!(parsed.data[1].population, 2100000);
+    assert_eq!(parsed.data[1].capital, None);
+
+    let single = Generator::csv(berlin()).parse_response("name,population\nBerlin,3700000")?;
+    assert_eq!(single.data, berlin());
+
+    assert!(matches!(
+        generator.parse_response("code,name,population\n049,Berlin,many"),
+        Err(ParseError::Extraction(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn extraction_strategy_handles_commentary() -> Result<(), ParseError> {
+    let response = "{\"name\": \"Berlin\", \"population\": 3700000}\n\n\
+        Note: population from {source}.";
+
+    let parsed = Generator::json(City::default()).parse_response(response)?;
+    assert_eq!(parsed.data, berlin());
+
+    let first_fence =
+        Generator::json(City::default()).extraction(ExtractionStrategy::FirstCodeFence);
+    assert!(matches!(
+        first_fence.parse_response(response),
+        Err(ParseError::Extraction(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn rendered_values_match_the_instruction_and_parse_back() -> Result<(), ParseError> {
+    let generator = Generator::json(City::default()).example("The capital of Germany", berlin());
+    let rendered = generator.render_value(&berlin());
+    assert!(rendered.starts_with("```json\n"));
+    assert!(generator.build_instruction_text().contains(&rendered));
+    assert_eq!(generator.parse_data(&rendered)?, berlin());
+
+    let array = Generator::json_array(vec![City::default()]);
+    assert_eq!(
+        array.parse_data(&array.render_value(&vec![berlin()]))?,
+        vec![berlin()]
+    );
+
+    #[cfg(feature = "yaml")]
+    {
+        let yaml = Generator::yaml(City::default());
+        let rendered = yaml.render_value(&berlin());
+        assert!(
+            rendered.starts_with("```yaml\nname: Berlin\n"),
+            "{rendered}"
+        );
+        assert_eq!(yaml.parse_data(&rendered)?, berlin());
+    }
+    Ok(())
+}
+
+#[test]
+fn reasoning_is_stripped_before_extraction() -> Result<(), ParseError> {
+    let thinking =
+        "<think>Maybe {\"name\": \"Bonn\", \"population\": 1}? No, the capital.</think>\n\
+        {\"name\": \"Berlin\", \"population\": 3700000}";
+    let generator = Generator::json(City::default()).reasoning_filter(ReasoningFilter::BuiltIn);
+
+    let parsed = generator.parse_response(thinking)?;
+    assert_eq!(parsed.data, berlin());
+    assert_eq!(parsed.raw_response, thinking);
+    assert!(parsed.metadata.reasoning.unwrap().contains("Bonn"));
+    assert_ne!(
+        Generator::json(City::default()).parse_data(thinking)?,
+        berlin()
+    );
+
+    // Opening tag in the prompt, as with DeepSeek-R1
+    let unopened = "Bonn was the capital until 1990.\n</think>\n\n\
+        ```json\n{\"name\": \"Berlin\", \"population\": 3700000}\n```";
+    assert_eq!(generator.parse_data(unopened)?, berlin());
+
+    let prefixed = "Reasoning: Bonn {\"name\": \"Bonn\"} is out.\n\n\
+        {\"name\": \"Berlin\", \"population\": 3700000}";
+    let custom =
+        Generator::json(City::default()).reasoning_filter(ReasoningFilter::Patterns(vec![
+            r"(?s)^Reasoning:.*?\n\n".into(),
+        ]));
+    assert_eq!(custom.parse_data(prefixed)?, berlin());
+
+    let invalid = Generator::json(City::default())
+        .reasoning_filter(ReasoningFilter::Patterns(vec!["(".into()]));
+    assert!(matches!(
+        invalid.parse_data(prefixed),
+        Err(ParseError::Other(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn extract_module_reads_arbitrary_text() -> Result<(), ParseError> {
+    let text = "Two cities:\n```json\n{\"name\": \"Berlin\", \"population\": 3700000}\n```\n\
+        ```\n{\"name\": \"Paris\", \"population\": 2100000}\n```\n```python\nprint(1)\n```";
+
+    let city: City = extract::json(text)?;
+    assert_eq!(city, berlin());
+    assert_eq!(extract::fences(text).len(), 3);
+    assert_eq!(extract::fences(text)[2].0, "python");
+    assert_eq!(extract::blocks(OutputFormat::Json, text).len(), 2);
+    assert_eq!(
+        extract::locate_json(ExtractionStrategy::FirstCodeFence, text),
+        "{\"name\": \"Berlin\", \"population\": 3700000}"
+    );
+
+    let (format, value) =
+        extract::auto("| name | population |\n| --- | --- |\n| Rome | 2800000 |")?;
+    assert_eq!(format, OutputFormat::MarkdownTable);
+    assert_eq!(value[0]["population"], 2800000);
+
+    #[cfg(feature = "xml")]
+    {
+        let xml = "<root><name>Berlin</name><population>3700000</population></root>";
+        let city: City = extract::typed(OutputFormat::Xml, xml)?;
+        assert_eq!(city, berlin());
+        let value = extract::value(OutputFormat::Xml, xml)?;
+        assert_eq!(value["population"], "3700000", "{value}");
+    }
+    Ok(())
+}
+
+#[test]
+fn dyn_generator_works_from_runtime_schemas() -> Result<(), ParseError> {
+    let schema = serde_json::json!({
+        "title": "Ticket",
+        "type": "object",
+        "properties": {
+            "subject": { "type": "string" },
+            "priority": { "enum": ["low", "high"] },
+            "tags": { "type": "array", "items": { "$ref": "#/$defs/tag" } }
+        },
+        "required": ["subject", "priority"],
+        "$defs": { "tag": { "type": "string" } }
+    });
+
+    let mut registry = SchemaRegistry::new();
+    registry.register("ticket", schema)?;
+    assert!(registry
+        .register("broken", serde_json::json!({ "type": 12 }))
+        .is_err());
+    assert_eq!(registry.names().collect::<Vec<_>>(), ["ticket"]);
+
+    let generator = registry.get("ticket").unwrap();
+    let instruction = generator.build_instruction_text();
+    assert!(
+        instruction.contains("\"priority\": \"low\""),
+        "{instruction}"
+    );
+    assert!(instruction.contains("\"tags\": [\n"), "{instruction}");
+
+    let parsed = generator.parse_response("{\"subject\": \"VPN down\", \"priority\": \"high\"}")?;
+    assert_eq!(parsed.data["priority"], "high");
+    assert!(parsed.validation_issues.is_empty());
+
+    let invalid = "{\"subject\": \"VPN down\", \"priority\": \"urgent\"}";
+    assert!(!generator
+        .parse_response(invalid)?
+        .validation_issues
+        .is_empty());
+    let strict = DynGenerator::try_new(generator.schema().clone())?
+        .validation_options(ValidationOptions::default());
+    assert!(matches!(
+        strict.parse_response(invalid),
+        Err(ParseError::ValidationError(_))
+    ));
+
+    match generator.to_response_format() {
+        ResponseFormat::JsonSchema { json_schema } => {
+            assert_eq!(json_schema.name, "Ticket");
+            assert_eq!(json_schema.schema.unwrap()["additionalProperties"], false);
+        }
+        other => panic!("unexpected response format {other:?}"),
+    }
+    Ok(())
+}
+
+#[test]
+fn dyn_generator_imports_openapi_components() -> Result<(), ParseError> {
+    let spec = serde_json::json!({
+        "openapi": "3.0.3",
+        "components": {
+            "schemas": {
+                "Invoice": {
+                    "type": "object",
+                    "properties": {
+                        "number": { "type": "string" },
+                        "note": { "type": "string", "nullable": true },
+                        "lines": { "type": "array", "items": { "$ref": "#/components/schemas/Line" } },
+                        "category": { "$ref": "#/components/schemas/Category" }
+                    },
+                    "required": ["number", "lines"]
+                },
+                "Line": {
+                    "type": "object",
+                    "properties": { "amount": { "type": "number" } },
+                    "required": ["amount"]
+                },
+                "Category": {
+                    "type": "object",
+                    "properties": {
+                        "name": { "type": "string" },
+                        "parent": { "$ref": "#/components/schemas/Category" }
+                    }
+                },
+                "Remote": { "$ref": "common.yaml#/Money" }
+            }
+        }
+    });
+
+    let generator = DynGenerator::from_openapi(&spec, "#/components/schemas/Invoice")?;
+    let schema = generator.schema();
+    assert_eq!(schema["title"], "Invoice");
+    assert_eq!(
+        schema["properties"]["lines"]["items"]["required"][0],
+        "amount"
+    );
+    assert_eq!(schema["properties"]["note"]["type"][1], "null");
+    assert_eq!(
+        schema["definitions"]["Category"]["properties"]["parent"]["$ref"],
+        "#/definitions/Category"
+    );
+    assert!(generator.build_instruction_text().contains("\"amount\""));
+
+    let parsed = generator.parse_response(
+        "{\"number\": \"F-17\", \"note\": null, \"lines\": [{\"amount\": 12.5}], \
+         \"category\": {\"name\": \"Travel\", \"parent\": {\"name\": \"Expenses\"}}}",
+    )?;
+    assert!(
+        parsed.validation_issues.is_empty(),
+        "{:?}",
+        parsed.validation_issues
+    );
+    let parsed = generator.parse_response("{\"number\": \"F-17\", \"lines\": [{}]}")?;
+    assert!(!parsed.validation_issues.is_empty());
+
+    assert!(DynGenerator::from_openapi(&spec, "#/components/schemas/Remote").is_err());
+    assert!(DynGenerator::from_openapi(&spec, "#/components/schemas/Missing").is_err());
+    Ok(())
+}
+
+#[test]
+fn response_metadata_records_provenance() -> Result<(), ParseError> {
+    let json = "{\"name\": \"Berlin\", \"population\": 3700000}";
+    let response = format!("Here you go: {json} Anything else?");
+
+    let parsed = Generator::json(City::default()).parse_response(&response)?;
+    assert_eq!(parsed.metadata.extraction, Some(ExtractionMethod::Strategy));
+    assert_eq!(&parsed.raw_response[parsed.metadata.span.unwrap()], json);
+    assert_eq!(parsed.metadata.repairs, 0);
+
+    let fenced = format!("<think>Berlin, surely.</think>\n```json\n{json}\n```");
+    let parsed = Generator::json(City::default())
+        .extraction(ExtractionStrategy::FirstCodeFence)
+        .reasoning_filter(ReasoningFilter::BuiltIn)
+        .parse_response(&fenced)?;
+    assert_eq!(
+        parsed.metadata.extraction,
+        Some(ExtractionMethod::CodeFence)
+    );
+    assert_eq!(&parsed.raw_response[parsed.metadata.span.unwrap()], json);
+
+    let parsed = Generator::json(City::default())
+        .extraction(ExtractionStrategy::FirstCodeFence)
+        .parse_response(json)?;
+    assert_eq!(
+        parsed.metadata.extraction,
+        Some(ExtractionMethod::WholeResponse)
+    );
+    assert_eq!(parsed.metadata.span, Some(0..json.len()));
+    Ok(())
+}
+
+#[test]
+fn parse_all_finds_every_answer() -> Result<(), ParseError> {
+    let generator = Generator::json(City::default());
+
+    let fenced = "First:\n```json\n{\"name\": \"Berlin\", \"population\": 3700000}\n```\n\
+        A shell command, ignored:\n```sh\necho {}\n```\n\
+        Second:\n```\n{\"name\": \"Paris\", \"population\": 2100000}\n```";
+    let parsed = generator.parse_all(fenced)?;
+    let names: Vec<_> = parsed.iter().map(|city| city.data.name.as_str()).collect();
+    assert_eq!(names, ["Berlin", "Paris"]);
+
+    let inline = "Berlin is {\"name\": \"Berlin\", \"population\": 3700000} and [not json] \
+        Paris is {\"name\": \"Paris\", \"population\": 2100000}.";
+    let parsed = generator.parse_all(inline)?;
+    assert_eq!(parsed.len(), 2);
+    assert_eq!(parsed[1].data.population, 2100000);
+    assert_eq!(
+        parsed[0].raw_response,
+        "{\"name\": \"Berlin\", \"population\": 3700000}"
+    );
+
+    assert!(matches!(
+        generator.parse_all("```json\n{\"name\": \"Berlin\"}\n```"),
+        Err(ParseError::Extraction(_))
+    ));
+    assert!(generator.parse_all("nothing").is_err());
+    Ok(())
+}
+
+#[test]
+fn custom_format_renders_and_extracts() -> Result<(), ParseError> {
+    /// `field := value` lines, values in JSON
+    struct Assignments;
+
+    impl Format for Assignments {
+        fn name(&self) -> &str {
+            "assignments"
+        }
+
+        fn render_example(&self, value: &serde_json::Value) -> Option<String> {
+            let fields = value.as_object()?;
+            Some(
+                fields
+                    .iter()
+                    .map(|(field, value)| format!("{field} := {value}\n"))
+                    .collect(),
+            )
+        }
+
+        fn render_schema(&self, schema: &serde_json::Value) -> Option<String> {
+            let properties = schema["properties"].as_object()?;
+            let fields = properties.iter().map(|(field, property)| {
+                format!("{field}: {}\n", property["type"].as_str().unwrap_or("any"))
+            });
+            Some(format!("Fields:\n{}", fields.collect::<String>()))
+        }
+
+        fn extract(&self, response: &str) -> Result<serde_json::Value, ParseError> {
+            let mut fields = serde_json::Map::new();
+            for line in response.lines() {
+                let Some((field, value)) = line.split_once(":=") else {
+                    continue;
+                };
+                let value = serde_json::from_str(value.trim())
+                    .map_err(|e| ParseError::Extraction(e.to_string()))?;
+                fields.insert(field.trim().to_string(), value);
+            }
+            Ok(serde_json::Value::Object(fields))
+        }
+    }
+
+    let generator = Generator::with_validation(berlin()).custom_format(Box::new(Assignments));
+    let instruction = generator.build_instruction_text();
+    assert!(instruction.contains("assignments"));
+    assert!(instruction.contains("name := \"Berlin\"\npopulation := 3700000\n"));
+    assert!(instruction.contains("Fields:\nname: string\npopulation: integer\n"));
+
+    let parsed = generator.parse_response("Sure:\nname := \"Berlin\"\npopulation := 3700000")?;
+    assert_eq!(parsed.data, berlin());
+    assert_eq!(parsed.metadata.format, OutputFormat::Custom);
+
+    // Validated like the built-in formats
+    assert!(matches!(
+        generator.parse_response("name := \"Berlin\""),
+        Err(ParseError::ValidationError(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn tuple_and_newtype_answers_describe_their_positions() -> Result<(), ParseError> {
+    let generator = Generator::with_validation(("Ada".to_string(), 36u32))
+        .describe_index(0, "name")
+        .describe_index(1, "age");
+    let instruction = generator.build_instruction_text();
+    assert!(instruction.contains("- A 2-element array: [name (string), age (integer)]\n"));
+    assert!(!instruction.contains("An array of string items"));
+    assert!(instruction.contains("\"description\": \"age\""));
+
+    let parsed = generator.parse_response("[\"Grace\", 85]")?;
+    assert_eq!(parsed.data, ("Grace".to_string(), 85));
+
+    // Positions without a description show their type only
+    let instruction = Generator::json(("Ada".to_string(), 36u32)).build_instruction_text();
+    assert!(instruction.contains("- A 2-element array: [string, integer]\n"));
+
+    #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+    struct Age(u32);
+
+    let generator = Generator::json(Age(36)).describe_index(0, "age in years");
+    assert!(generator
+        .build_instruction_text()
+        .contains("- A single integer value: age in years\n"));
+    assert_eq!(generator.parse_response("41")?.data, Age(41));
+    Ok(())
+}
+
+#[test]
+fn markdown_table_format_round_trip() -> Result<(), ParseError> {
+    #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+    #[serde(rename_all = "camelCase")]
+    struct Row {
+        city_name: String,
+        postal_code: String,
+        population: u64,
+    }
+
+    let generator = Generator::markdown_table(vec![Row {
+        city_name: "Berlin".into(),
+        postal_code: "10115".into(),
+        population: 3700000,
+    }]);
+    assert!(generator.build_instruction_text().contains(by_field_order(
+        "| cityName | postalCode | population |\n| --- | --- | --- |\n| Berlin | 10115 | 3700000 |\n",
+        "| cityName | population | postalCode |\n| --- | --- | --- |\n| Berlin | 3700000 | 10115 |\n"
+    )));
+
+    let response = "Here are the cities:\n\n\
+        | City Name | Postal_Code | POPULATION |\n\
+        |:----------|------------:|-----------:|\n\
+        | Berlin | 10115 | 3700000 |\n\
+        | Rio \\| Brazil | 20000 | 6700000 |\n\n\
+        Let me know if you need more.";
+    let parsed = generator.parse_response(response)?;
+    assert_eq!(parsed.data.len(), 2);
+    assert_eq!(parsed.data[0].postal_code, "10115");
+    assert_eq!(parsed.data[1].city_name, "Rio | Brazil");
+    assert_eq!(parsed.data[1].population, 6700000);
+
+    // Detected even when JSON was asked for
+    let parsed = Generator::json(vec![Row::default()]).parse_auto(response)?;
+    assert_eq!(parsed.metadata.format, OutputFormat::MarkdownTable);
+    assert_eq!(parsed.data[0].city_name, "Berlin");
+
+    assert!(matches!(
+        generator.parse_response("No table here"),
+        Err(ParseError::Extraction(_))
+    ));
+    Ok(())
+}
+
+#[cfg(feature = "xml")]
+#[test]
+fn xml_mapping_reads_attributes_and_namespaces() -> Result<(), ParseError> {
+    use async_openai::types::structured::XmlMapping;
+
+    let generator =
+        Generator::xml(berlin()).xml_mapping(XmlMapping::default().root("city").attribute("name"));
+    let instruction = generator.build_instruction_text();
+    assert!(
+        instruction.contains(
+            "```xml\n<city name=\"Berlin\">\n  <population>3700000</population>\n</city>\n```"
+        ),
+        "{instruction}"
+    );
+
+    let response = r#"<c:city xmlns:c="urn:cities" name="Berlin"><c:population>3700000</c:population></c:city>"#;
+    assert_eq!(generator.parse_response(response)?.data, berlin());
+    // Fields are read the same when written as elements
+    let parsed = generator.parse_response(
+        "```xml\n<city><name>Berlin</name><population>3700000</population></city>\n```",
+    )?;
+    assert_eq!(parsed.data, berlin());
+    Ok(())
+}
+
+#[cfg(feature = "toml")]
+#[test]
+fn toml_format_round_trip() -> Result<(), ParseError> {
+    let generator = Generator::toml(berlin());
+    let instruction = generator.build_instruction_text();
+    assert!(instruction.contains("```toml\nname = \"Berlin\"\npopulation = 3700000\n```"));
+
+    let parsed = generator
+        .parse_response("Here it is:\n```toml\nname = \"Berlin\"\npopulation = 3700000\n```")?;
+    assert_eq!(parsed.data, berlin());
+
+    let generator = Generator::toml(vec![berlin()]);
+    assert!(generator
+        .build_instruction_text()
+        .contains("[[items]]\nname = \"Berlin\""));
+    let parsed = generator.parse_response(
+        "```toml\n[[items]]\nname = \"Berlin\"\npopulation = 3700000\n\n[[items]]\nname = \"Paris\"\npopulation = 2100000\n```",
+    )?;
+    assert_eq!(parsed.data.len(), 2);
+    assert_eq!(parsed.data[1].name, "Paris");
+
+    assert!(matches!(
+        generator.parse_response("```toml\nname = \n```"),
+        Err(ParseError::Extraction(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn parse_auto_ignores_configured_format() -> Result<(), ParseError> {
+    let generator = Generator::json(City::default());
+
+    let parsed =
+        generator.parse_auto("```json\n{\"name\": \"Berlin\", \"population\": 3700000}\n```")?;
+    assert_eq!(parsed.data, berlin());
+    assert_eq!(parsed.metadata.format, OutputFormat::Json);
+    assert!(parsed.metadata.auto_detected);
+
+    let configured = generator.parse_response("{\"name\": \"Berlin\", \"population\": 3700000}")?;
+    assert!(!configured.metadata.auto_detected);
+
+    #[cfg(feature = "yaml")]
+    {
+        let parsed = generator.parse_auto("```yaml\nname: Berlin\npopulation: 3700000\n```")?;
+        assert_eq!(parsed.data, berlin());
+        assert_eq!(parsed.metadata.format, OutputFormat::Yaml);
+    }
+    #[cfg(feature = "xml")]
+    {
+        let parsed = generator
+            .parse_auto("<City><name>Berlin</name><population>3700000</population></City>")?;
+        assert_eq!(parsed.data, berlin());
+        assert_eq!(parsed.metadata.format, OutputFormat::Xml);
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "yaml")]
+#[test]
+fn yaml_front_matter_and_documents() -> Result<(), ParseError> {
+    let article = "---\nname: Berlin\npopulation: 3700000\n---\n\n# Berlin\n\nThe capital.\n\n```yaml\nname: Paris\n```\n";
+    assert_eq!(
+        Generator::yaml(City::default()).parse_data(article)?,
+        berlin()
+    );
+
+    let documents =
+        "```yaml\n---\nname: Berlin\npopulation: 3700000\n---\nname: Paris\npopulation: 2100000\n```";
+    let cities = Generator::yaml(vec![City::default()])
+        .validate(true)
+        .parse_response(documents)?;
+    assert_eq!(cities.data.len(), 2);
+    assert_eq!(cities.data[1].name, "Paris");
+    // The first document is the answer when it is one
+    assert_eq!(
+        Generator::yaml(City::default()).parse_data(documents)?,
+        berlin()
+    );
+
+    let answers = Generator::yaml(City::default()).parse_all(documents)?;
+    assert_eq!(answers.len(), 2);
+    assert_eq!(answers[0].data, berlin());
+    let cities: Vec<City> = extract::yaml_documents(documents)?;
+    assert_eq!(cities[1].population, 2100000);
+    Ok(())
+}
+
+fn logprob(token: &str, logprob: f32) -> ChatCompletionTokenLogprob {
+    ChatCompletionTokenLogprob {
+        token: token.to_string(),
+        logprob,
+        bytes: Some(token.as_bytes().to_vec()),
+        top_logprobs: vec![],
+    }
+}
+
+#[test]
+fn field_confidence_from_logprobs() -> Result<(), ParseError> {
+    let generator = Generator::json(City::default());
+    let tokens = [
+        logprob("{\"", 0.0),
+        logprob("name", 0.0),
+        logprob("\":", 0.0),
+        logprob(" \"", 0.0),
+        logprob("Ber", -0.1),
+        logprob("lin", -0.3),
+        logprob("\",", 0.0),
+        logprob(" \"population\":", 0.0),
+        logprob(" 370", -1.5),
+        logprob("0000", -2.5),
+        logprob("}", 0.0),
+    ];
+    let response: String = tokens.iter().map(|t| t.token.as_str()).collect();
+
+    let parsed = generator.parse_with_logprobs(&response, &tokens)?;
+    assert_eq!(parsed.data, berlin());
+
+    let name = parsed.metadata.field_confidence["name"];
+    assert_eq!(name.tokens, 2);
+    assert!((name.confidence - (-0.2f32).exp()).abs() < 1e-6);
+    assert!((name.min_probability - (-0.3f32).exp()).abs() < 1e-6);
+
+    let population = parsed.metadata.field_confidence["population"];
+    assert_eq!(population.tokens, 2);
+    assert!((population.confidence - (-2.0f32).exp()).abs() < 1e-6);
+
+    assert_eq!(parsed.low_confidence_fields(0.5), vec!["population"]);
+    let mean = (-0.1 - 0.3 - 1.5 - 2.5) / tokens.len() as f32;
+    assert!((parsed.metadata.confidence.unwrap() - mean.exp()).abs() < 1e-6);
+
+    // Logprobs of another response are ignored
+    let parsed = generator.parse_with_logprobs(&response, &tokens[..3])?;
+    assert!(parsed.metadata.field_confidence.is_empty());
+    assert_eq!(parsed.metadata.confidence, None);
+
+    Ok(())
+}
+
+/// Answer consecutive chat completion requests with `contents`, returning the api base.
+fn serve_completions(contents: Vec<&'static str>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    std::thread::spawn(move || {
+        for content in contents {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0; 16384];
+            let _ = stream.read(&mut buf).unwrap();
+            let body = serde_json::json!({
+                "id": "chatcmpl-1",
+                "object": "chat.completion",
+                "created": 1,
+                "model": "gpt-4o-mini",
+                "choices": [{
+                    "index": 0,
+                    "message": { "role": "assistant", "content": content },
+                    "finish_reason": "stop"
+                }],
+                "usage": { "prompt_tokens": 100, "completion_tokens": 20, "total_tokens": 120 }
+            })
+            .to_string();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+
+    format!("http://{addr}/v1")
+}
+
+#[tokio::test]
+async fn experiment_reports_each_variant() {
+    let api_base = serve_completions(vec![
+        // terse
+        "{\"name\": \"Berlin\", \"population\": 3700000}",
+        "Sorry, I can't help with that.",
+        // guided
+        "```json\n{\"name\": \"Berlin\", \"population\": 3700000}\n```",
+        "{\"name\": \"Paris\", \"population\": 2100000}",
+    ]);
+    let client = Client::with_config(
+        OpenAIConfig::new()
+            .with_api_base(api_base)
+            .with_api_key("sk-test"),
+    );
+
+    let report = ExperimentRunner::new(&client, "gpt-4o-mini")
+        .with_variant("terse", Generator::json(City::default()))
+        .with_variant(
+            "guided",
+            Generator::json(City::default()).prefix("Extract the city."),
+        )
+        .with_samples([
+            Sample::labeled("Berlin has 3.7 million inhabitants", berlin()),
+            Sample::new("Paris has 2.1 million inhabitants"),
+        ])
+        .run()
+        .await;
+
+    let terse = report.variant("terse").unwrap();
+    assert_eq!(
+        (terse.samples, terse.parsed, terse.parse_failures),
+        (2, 1, 1)
+    );
+    assert_eq!(terse.parse_success_rate(), 0.5);
+    assert_eq!(terse.accuracy(), Some(1.0));
+    assert_eq!((terse.prompt_tokens, terse.completion_tokens), (200, 40));
+    assert!((terse.cost(0.15, 0.6) - 0.000054).abs() < 1e-12);
+
+    let guided = report.variant("guided").unwrap();
+    assert_eq!(guided.parse_success_rate(), 1.0);
+    assert_eq!(guided.request_failures, 0);
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+struct Article {
+    title: String,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+#[tokio::test]
+async fn parse_stream_yields_partial_values() {
+    let generator = Generator::json(Article::default());
+    let chunks = [
+        "```json\n{\"ti",
+        "tle\": \"Rust",
+        " 2024\", \"tags\"",
+        ": [\"lang",
+        "\"]}\n```",
+    ];
+    let stream = futures::stream::iter(chunks.map(|chunk| Ok(chunk.to_string())));
+
+    let outputs: Vec<_> = generator.parse_stream(stream).collect().await;
+
+    let partial_titles: Vec<_> = outputs[..outputs.len() - 1]
+        .iter()
+        .map(|output| match output {
+            Ok(StreamedOutput::Partial { data, .. }) => data.as_ref().map(|a| a.title.as_str()),
+            other => panic!("expected a partial value, got {other:?}"),
+        })
+        .collect();
+    assert_eq!(
+        partial_titles,
+        vec![None, Some("Rust"), Some("Rust 2024"), Some("Rust 2024")]
+    );
+
+    match outputs.last().unwrap() {
+        Ok(StreamedOutput::Complete(response)) => {
+            assert_eq!(response.data.tags, vec!["lang".to_string()])
+        }
+        other => panic!("expected the complete response, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn parse_stream_reports_unparsable_response() {
+    let generator = Generator::json(City::default());
+    let stream = futures::stream::iter([Ok("I can't help with that.".to_string())]);
+
+    let outputs: Vec<_> = generator.parse_stream(stream).collect().await;
+    assert!(matches!(
+        outputs.as_slice(),
+        [Err(OpenAIError::StructuredOutput(_))]
+    ));
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema, StructuredOutput)]
+#[serde(rename_all = "camelCase")]
+struct Invoice {
+    /// Name of the
+    /// issuing company
+    issuer: String,
+    #[describe("Total amount in cents")]
+    total_amount: u64,
+    #[serde(rename = "due")]
+    #[describe("Due date as YYYY-MM-DD")]
+    due_date: String,
+    notes: String,
+}
+
+#[test]
+fn derived_field_descriptions() {
+    assert_eq!(
+        Invoice::field_descriptions(),
+        vec![
+            ("issuer", "Name of the issuing company"),
+            ("totalAmount", "Total amount in cents"),
+            ("due", "Due date as YYYY-MM-DD"),
+        ]
+    );
+
+    let instruction = Generator::json(Invoice::default())
+        .describe("issuer", "Legal name of the seller")
+        .with_field_descriptions()
+        .build_instruction_text();
+    assert!(instruction.contains("Legal name of the seller"));
+    assert!(!instruction.contains("issuing company"));
+    assert!(instruction.contains("Total amount in cents"));
+    assert!(!instruction.contains("notes:"));
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+struct Author {
+    name: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+struct LineItem {
+    sku: String,
+    price: f64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+struct Order {
+    author: Author,
+    items: Vec<LineItem>,
+}
+
+#[test]
+fn describes_nested_fields_by_path() {
+    let order = Order {
+        author: Author::default(),
+        items: vec![LineItem::default()],
+    };
+    let instruction = Generator::json(order)
+        .describe("author.name", "Full name of the buyer")
+        .describe("items[].price", "Unit price in EUR")
+        .build_instruction_text();
+
+    assert!(instruction.contains(by_field_order(
+        "- author (object, required)\n  - name (string, required): Full name of the buyer\n- items (array, required)\n  Each item should have:\n  - sku (string, required)\n  - price (float, required): Unit price in EUR\n",
+        "- author (object, required)\n  - name (string, required): Full name of the buyer\n- items (array, required)\n  Each item should have:\n  - price (float, required): Unit price in EUR\n  - sku (string, required)\n"
+    )));
+
+    let schema = instruction
+        .split("JSON Schema information:\n```json\n")
+        .nth(1)
+        .and_then(|rest| rest.split("```").next())
+        .unwrap();
+    let schema: serde_json::Value = serde_json::from_str(schema).unwrap();
+    assert_eq!(
+        schema["properties"]["author"]["properties"]["name"]["description"],
+        "Full name of the buyer"
+    );
+    assert_eq!(
+        schema["properties"]["items"]["items"]["properties"]["price"]["description"],
+        "Unit price in EUR"
+    );
+    assert!(schema["properties"]["author"].get("description").is_none());
+}
+
+#[cfg(feature = "structured-field-order")]
+#[test]
+fn field_order_applies_to_example_descriptions_and_schema() {
+    let order = Order {
+        author: Author::default(),
+        items: vec![LineItem::default()],
+    };
+    let generator = Generator::json(order)
+        .describe("author.name", "Full name of the buyer")
+        .describe("items[].sku", "Stock keeping unit")
+        .field_order(["items", "items[].price"]);
+    let instruction = generator.build_instruction_text();
+
+    assert!(
+        instruction.contains(
+            "- items (array, required)\n  Each item should have:\n  - price (float, required)\n  - sku (string, required): Stock keeping unit\n- author (object, required)\n"
+        ),
+        "{instruction}"
+    );
+    let example = instruction
+        .split("```json\n")
+        .nth(1)
+        .and_then(|rest| rest.split("```").next())
+        .unwrap();
+    assert!(
+        example.find("\"items\"") < example.find("\"author\"")
+            && example.find("\"price\"") < example.find("\"sku\""),
+        "{example}"
+    );
+
+    let schema = schema_block(&instruction);
+    let fields: Vec<_> = schema["properties"].as_object().unwrap().keys().collect();
+    assert_eq!(fields, ["items", "author"]);
+    let fields: Vec<_> = schema["properties"]["items"]["items"]["properties"]
+        .as_object()
+        .unwrap()
+        .keys()
+        .collect();
+    assert_eq!(fields, ["price", "sku"]);
+
+    let ResponseFormat::JsonSchema { json_schema } = generator.to_response_format() else {
+        panic!("expected a json_schema response format");
+    };
+    assert_eq!(
+        json_schema.schema.unwrap()["required"],
+        serde_json::json!(["items", "author"])
+    );
+}
+
+#[test]
+fn responses_api_text_format_and_output() -> Result<(), ParseError> {
+    let generator = Generator::json(berlin());
+
+    let format = generator.into_text_format();
+    assert_eq!(format["type"], "json_schema");
+    assert_eq!(format["name"], "City");
+    assert_eq!(
+        format["schema"]["properties"]["population"]["type"],
+        "integer"
+    );
+
+    let response = serde_json::json!({
+        "id": "resp_1",
+        "object": "response",
+        "status": "completed",
+        "output": [
+            { "type": "reasoning", "id": "rs_1", "summary": [] },
+            {
+                "type": "message",
+                "id": "msg_1",
+                "role": "assistant",
+                "content": [{
+                    "type": "output_text",
+                    "text": "{\"name\": \"Berlin\", \"population\": 3700000}",
+                    "annotations": []
+                }]
+            }
+        ]
+    });
+    assert_eq!(generator.parse_response_output(&response)?.data, berlin());
+
+    let refused = serde_json::json!({
+        "output": [{
+            "type": "message",
+            "content": [{ "type": "refusal", "refusal": "I can't help with that." }]
+        }]
+    });
+    match generator.parse_response_output(&refused) {
+        Err(ParseError::Refusal(message)) => assert_eq!(message, "I can't help with that."),
+        other => panic!("expected a refusal, got {other:?}"),
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+enum Priority {
+    #[default]
+    Low,
+    High,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+struct Ticket {
+    /// One line summary
+    title: String,
+    priority: Priority,
+    assignee: Option<Author>,
+}
+
+fn schema_block(instruction: &str) -> serde_json::Value {
+    let schema = instruction
+        .split("JSON Schema information:\n```json\n")
+        .nth(1)
+        .and_then(|rest| rest.split("```").next())
+        .unwrap();
+    serde_json::from_str(schema).unwrap()
+}
+
+#[test]
+fn renders_schemars_schema_unless_example_requested() {
+    let generator = Generator::json(Ticket::default()).describe("assignee.name", "Login name");
+
+    let schema = schema_block(&generator.build_instruction_text());
+    assert_eq!(schema["required"], serde_json::json!(["priority", "title"]));
+    assert_eq!(
+        schema["properties"]["title"]["description"],
+        "One line summary"
+    );
+    assert_eq!(
+        schema["properties"]["priority"]["enum"],
+        serde_json::json!(["low", "high"])
+    );
+    assert_eq!(
+        schema["properties"]["assignee"]["properties"]["name"]["description"],
+        "Login name"
+    );
+    assert!(schema.get("$schema").is_none());
+
+    let generator = generator.schema_source(SchemaSource::Example);
+    let schema = schema_block(&generator.build_instruction_text());
+    assert!(schema.get("required").is_none());
+    assert_eq!(
+        schema["properties"]["priority"],
+        serde_json::json!({ "type": "string", "enum": ["low", "high"] })
+    );
+}
+
+#[tokio::test]
+async fn refusals_are_reported_apart_from_parse_errors() {
+    let generator = Generator::json(berlin());
+
+    let completion: CreateChatCompletionResponse = serde_json::from_value(serde_json::json!({
+        "id": "chatcmpl-1",
+        "object": "chat.completion",
+        "created": 1,
+        "model": "gpt-4o-mini",
+        "choices": [{
+            "index": 0,
+            "message": { "role": "assistant", "content": null, "refusal": "I can't help with that." },
+            "finish_reason": "stop"
+        }]
+    }))
+    .unwrap();
+    assert!(matches!(
+        generator.parse_completion(&completion),
+        Err(ParseError::Refusal(message)) if message == "I can't help with that."
+    ));
+
+    let chunks = ["I can't", " help with that."].map(|refusal| {
+        serde_json::from_value::<CreateChatCompletionStreamResponse>(serde_json::json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion.chunk",
+            "created": 1,
+            "model": "gpt-4o-mini",
+            "choices": [{ "index": 0, "delta": { "refusal": refusal }, "finish_reason": null }]
+        }))
+        .map_err(OpenAIError::JSONDeserialize)
+    });
+    let outputs: Vec<_> = generator
+        .parse_stream(futures::stream::iter(chunks))
+        .collect()
+        .await;
+    assert!(matches!(
+        outputs.as_slice(),
+        [Err(OpenAIError::StructuredOutput(error))] if matches!(&**error, ParseError::Refusal(message) if message == "I can't help with that.")
+    ));
+}
+
+#[test]
+fn renders_typescript_dialect() {
+    let instruction = Generator::json(Ticket::default())
+        .schema_dialect(SchemaDialect::TypeScript)
+        .describe("assignee.name", "Login name")
+        .build_instruction_text();
+
+    assert!(!instruction.contains("JSON Schema information"));
+    assert!(instruction.contains(by_field_order(
+        r#"```typescript
+type Ticket = {
+  // One line summary
+  title: string;
+  priority: "low" | "high";
+  assignee?: {
+    // Login name
+    name: string;
+  } | null;
+};
+```"#,
+        r#"```typescript
+type Ticket = {
+  assignee?: {
+    // Login name
+    name: string;
+  } | null;
+  priority: "low" | "high";
+  // One line summary
+  title: string;
+};
+```"#
+    )));
+}
+
+#[test]
+fn response_format_schema_is_strict() {
+    let ResponseFormat::JsonSchema { json_schema } =
+        Generator::json(Ticket::default()).to_response_format()
+    else {
+        panic!("expected a json_schema response format");
+    };
+
+    assert_eq!(json_schema.name, "Ticket");
+    assert_eq!(json_schema.strict, Some(true));
+    let schema = json_schema.schema.unwrap();
+    assert!(schema.get("$schema").is_none());
+    assert_eq!(schema["additionalProperties"], false);
+    assert_eq!(
+        schema["required"],
+        by_field_order(
+            serde_json::json!(["title", "priority", "assignee"]),
+            serde_json::json!(["assignee", "priority", "title"])
+        )
+    );
+    let author = &schema["definitions"]["Author"];
+    assert_eq!(author["additionalProperties"], false);
+    assert_eq!(author["required"], serde_json::json!(["name"]));
+
+    let format = Generator::json(berlin()).into_text_format();
+    assert_eq!(format["strict"], true);
+    assert!(format["schema"]["properties"]["population"]
+        .get("format")
+        .is_none());
+}
+
+#[test]
+fn composite_splits_sections() {
+    let composite = CompositeGenerator::new()
+        .with(
+            "city",
+            Generator::json(berlin()).prefix("The city described"),
+        )
+        .with("ticket", Generator::json(Ticket::default()));
+
+    let instruction = composite.build_instruction_text();
+    assert!(instruction.contains("- city: The city described\n- ticket\n"));
+    let schema = schema_block(&instruction);
+    assert_eq!(schema["required"], serde_json::json!(["city", "ticket"]));
+    assert_eq!(
+        schema["properties"]["ticket"]["properties"]["priority"]["enum"],
+        serde_json::json!(["low", "high"])
+    );
+
+    let mut parsed = composite
+        .parse_response(
+            "```json\n{\"city\": {\"name\": \"Berlin\", \"population\": 3700000}, \"other\": 1}\n```",
+        )
+        .unwrap();
+    assert_eq!(parsed.keys().collect::<Vec<_>>(), ["city", "ticket"]);
+    assert!(matches!(
+        parsed.take::<Ticket>("city"),
+        Err(ParseError::Other(_))
+    ));
+
+    let mut parsed = composite
+        .parse_response("{\"city\": {\"name\": \"Berlin\", \"population\": 3700000}}")
+        .unwrap();
+    assert_eq!(parsed.take::<City>("city").unwrap().data, berlin());
+    assert!(matches!(
+        parsed.take::<Ticket>("ticket"),
+        Err(ParseError::Extraction(_))
+    ));
+}
+
+#[test]
+fn generator_pair_parses_both_sections() {
+    let pair = Generator::<City>::default().and::<Ticket>();
+    let instruction = pair.build_instruction_text();
+    assert!(instruction.contains("- city\n- ticket\n"), "{instruction}");
+
+    let pair = pair.keys("place", "issue");
+    let (city, ticket) = pair
+        .parse_composite(
+            r#"{"place": {"name": "Berlin", "population": 3700000}, "issue": {"title": "Flooding", "priority": "high", "assignee": null}}"#,
+        )
+        .unwrap();
+    assert_eq!(city.data, berlin());
+    assert_eq!(ticket.data.title, "Flooding");
+
+    let error = pair
+        .parse_composite(r#"{"place": {"name": "Berlin", "population": 3700000}}"#)
+        .unwrap_err();
+    assert!(matches!(error, ParseError::Extraction(_)), "{error:?}");
+}
+
+#[tokio::test]
+async fn create_structured_parses_first_choice() {
+    let api_base = serve_completions(vec![
+        "```json\n{\"name\": \"Berlin\", \"population\": 3700000}\n```",
+        "Sorry, I don't know.",
+    ]);
+    let client = Client::with_config(
+        OpenAIConfig::new()
+            .with_api_base(api_base)
+            .with_api_key("sk-test"),
+    );
+    let generator = Generator::json(City::default());
+    let request = CreateChatCompletionRequestArgs::default()
+        .model("gpt-4o-mini")
+        .messages([ChatCompletionRequestUserMessage::from("Largest city in Germany?").into()])
+        .build()
+        .unwrap();
+
+    let response = client
+        .chat()
+        .create_structured(request.clone(), &generator)
+        .await
+        .unwrap();
+    assert_eq!(response.data, berlin());
+
+    let error = client
+        .chat()
+        .create_structured(request, &generator)
+        .await
+        .unwrap_err();
+    assert!(matches!(error, OpenAIError::StructuredOutput(_)));
+}
+
+#[tokio::test]
+async fn create_structured_cached_skips_repeated_requests() {
+    let api_base = serve_completions(vec![
+        "{\"name\": \"Berlin\", \"population\": 3700000}",
+        "{\"name\": \"Paris\", \"population\": 2100000}",
+        "{\"name\": \"Rome\", \"population\": 2800000}",
+    ]);
+    let client = Client::with_config(
+        OpenAIConfig::new()
+            .with_api_base(api_base)
+            .with_api_key("sk-test"),
+    );
+    let generator = Generator::json(City::default());
+    let ask = |question: &str| {
+        CreateChatCompletionRequestArgs::default()
+            .model("gpt-4o-mini")
+            .messages([ChatCompletionRequestUserMessage::from(question).into()])
+            .build()
+            .unwrap()
+    };
+
+    let cache = InMemoryResponseCache::new();
+    for _ in 0..2 {
+        let response = client
+            .chat()
+            .create_structured_cached(ask("Largest city in Germany?"), &generator, &cache)
+            .await
+            .unwrap();
+        assert_eq!(response.data, berlin());
+    }
+    assert_eq!(cache.len(), 1);
+
+    // Another input is a miss
+    let response = client
+        .chat()
+        .create_structured_cached(ask("Largest city in France?"), &generator, &cache)
+        .await
+        .unwrap();
+    assert_eq!(response.data.name, "Paris");
+    assert_eq!(cache.len(), 2);
+
+    // Cached files outlive the cache that wrote them
+    let dir = std::env::temp_dir().join(format!("async-openai-structured-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let question = ask("Largest city in Italy?");
+    for _ in 0..2 {
+        let response = client
+            .chat()
+            .create_structured_cached(question.clone(), &generator, &FileResponseCache::new(&dir))
+            .await
+            .unwrap();
+        assert_eq!(response.data.name, "Rome");
+    }
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn structured_session_keeps_parsed_turns() {
+    let api_base = serve_completions(vec![
+        "{\"name\": \"Berlin\", \"population\": 3600000}",
+        "Which census?",
+        "{\"name\": \"Berlin\", \"population\": 3700000}",
+        "{\"name\": \"Paris\", \"population\": 2100000}",
+        "{\"name\": \"Rome\", \"population\": 2800000}",
+    ]);
+    let client = Client::with_config(
+        OpenAIConfig::new()
+            .with_api_base(api_base)
+            .with_api_key("sk-test"),
+    );
+
+    let mut session =
+        StructuredSession::new(&client, "gpt-4o-mini", Generator::json(City::default()));
+    let draft = session.ask("Largest city in Germany?").await.unwrap();
+    assert_eq!(draft.data.population, 3_600_000);
+    assert_eq!(session.messages().len(), 3);
+
+    // A turn whose answer doesn't parse is not kept
+    let error = session.ask("Use the latest census").await.unwrap_err();
+    assert!(matches!(error, OpenAIError::StructuredOutput(_)));
+    assert_eq!(session.messages().len(), 3);
+
+    let fixed = session.ask("Use the 2023 census").await.unwrap();
+    assert_eq!(fixed.data, berlin());
+    assert_eq!(session.messages().len(), 5);
+    assert_eq!(
+        session.messages()[0].text(),
+        session.generator().build_instruction_text()
+    );
+
+    // Older turns are dropped to fit the context window, the instruction is kept
+    let mut session =
+        StructuredSession::new(&client, "gpt-4o-mini", Generator::json(City::default()))
+            .with_context_window(1);
+    session.ask("Largest city in France?").await.unwrap();
+    let response = session.ask("Largest city in Italy?").await.unwrap();
+    assert_eq!(response.data.name, "Rome");
+    assert_eq!(session.messages().len(), 3);
+    assert_eq!(session.messages()[1].text(), "Largest city in Italy?");
+
+    session.clear();
+    assert_eq!(session.messages().len(), 1);
+}
+
+#[tokio::test]
+async fn generate_from_image_parses_extraction() {
+    let api_base = serve_completions(vec!["{\"name\": \"Berlin\", \"population\": 3700000}"]);
+    let client = Client::with_config(
+        OpenAIConfig::new()
+            .with_api_base(api_base)
+            .with_api_key("sk-test"),
+    );
+
+    let response = Generator::json(City::default())
+        .generate_from_image(
+            &client,
+            "gpt-4o-mini",
+            "https://example.com/sign.png",
+            "Which city is on this sign?",
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.data, berlin());
+}
+
+#[tokio::test]
+async fn parse_with_retry_sends_error_back() {
+    let api_base = serve_completions(vec![
+        "The city is Berlin.",
+        "{\"name\": \"Berlin\", \"population\": 3700000}",
+        "still not JSON",
+        "nor this",
+    ]);
+    let client = Client::with_config(
+        OpenAIConfig::new()
+            .with_api_base(api_base)
+            .with_api_key("sk-test"),
+    );
+    let generator = Generator::json(City::default());
+    let request = CreateChatCompletionRequestArgs::default()
+        .model("gpt-4o-mini")
+        .messages([ChatCompletionRequestUserMessage::from("Largest city in Germany?").into()])
+        .build()
+        .unwrap();
+
+    let (response, attempts) = generator
+        .parse_with_retry(&client, request.clone(), 3)
+        .await
+        .unwrap();
+    assert_eq!(response.data, berlin());
+    assert_eq!(attempts.len(), 1);
+    assert_eq!(attempts[0].content, "The city is Berlin.");
+    assert_eq!(response.metadata.repairs, 1);
+
+    let error = generator
+        .parse_with_retry(&client, request, 2)
+        .await
+        .unwrap_err();
+    assert!(matches!(error, OpenAIError::StructuredOutput(_)));
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+struct Review {
+    #[schemars(range(min = 1, max = 5))]
+    stars: u8,
+    #[schemars(length(max = 12))]
+    title: String,
+    #[schemars(regex(pattern = r"^[A-Z]{3}$"))]
+    airport: String,
+}
+
+#[test]
+fn validation_honors_schema_attributes() {
+    let generator = Generator::with_validation(Review::default());
+
+    let valid = generator
+        .parse_response(r#"{"stars": 4, "title": "Smooth", "airport": "BER"}"#)
+        .unwrap();
+    assert_eq!(valid.validation_messages, None);
+
+    let invalid = generator
+        .parse_response(r#"{"stars": 4, "title": "A very long title", "airport": "ber"}"#)
+        .unwrap();
+    let messages = invalid.validation_messages.unwrap();
+    assert_eq!(messages.len(), 2, "{messages:?}");
+    assert!(
+        messages.iter().any(|m| m.starts_with("/title")),
+        "{messages:?}"
+    );
+    assert!(
+        messages.iter().any(|m| m.starts_with("/airport")),
+        "{messages:?}"
+    );
+
+    let strict = Generator::with_validation(Review::default())
+        .validation_options(ValidationOptions::default().max_errors(1));
+    let error = strict
+        .parse_response(r#"{"stars": 9, "title": "A very long title", "airport": "BER"}"#)
+        .unwrap_err();
+    match error {
+        ParseError::ValidationError(message) => {
+            assert_eq!(message.matches("/stars").count(), 1, "{message}");
+            assert!(!message.contains("/title"), "{message}");
+        }
+        error => panic!("unexpected error: {error:?}"),
+    }
+}
+
+#[test]
+fn validation_options_coerce_and_deny_additional_properties() {
+    let response = r#"{"name": "Berlin", "population": "3700000", "country": "DE"}"#;
+
+    let lenient = Generator::with_validation(City::default())
+        .validation_options(ValidationOptions::default().coerce_numeric_strings(true));
+    let parsed = lenient.parse_response(response).unwrap();
+    assert_eq!(parsed.data, berlin());
+    assert_eq!(parsed.validation_messages, None);
+
+    let closed = Generator::with_validation(City::default()).validation_options(
+        ValidationOptions::default()
+            .coerce_numeric_strings(true)
+            .allow_additional_properties(false),
+    );
+    let error = closed.parse_response(response).unwrap_err();
+    assert!(
+        matches!(&error, ParseError::ValidationError(message) if message.contains("country")),
+        "{error:?}"
+    );
+
+    let error = Generator::with_validation(City::default())
+        .parse_response(response)
+        .unwrap_err();
+    assert!(matches!(error, ParseError::ValidationError(_)), "{error:?}");
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+struct Category {
+    #[schemars(length(min = 1))]
+    name: String,
+    children: Vec<Category>,
+}
+
+#[test]
+fn validation_resolves_references_of_recursive_types() {
+    let generator = Generator::with_validation(Category::default());
+    let schema = serde_json::to_string(generator.config().json_schema.as_ref().unwrap()).unwrap();
+    assert!(schema.contains("\"$ref\""), "{schema}");
+
+    let parsed = generator
+        .parse_response(
+            r#"{"name": "Books", "children": [{"name": "", "children": []}, {"name": "Poetry", "children": [{"name": 7, "children": []}]}]}"#,
+        )
+        .unwrap_err();
+    let ParseError::ValidationError(message) = parsed else {
+        panic!("unexpected error: {parsed:?}");
+    };
+    assert!(message.contains("/children/0/name"), "{message}");
+    assert!(message.contains("/children/1/children/0/name"), "{message}");
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+struct Receipt {
+    merchant: String,
+    total: Located<f64>,
+}
+
+#[test]
+fn region_fields_ask_for_normalized_coordinates() {
+    let generator = Generator::with_validation(Receipt::default());
+    let instruction = generator.build_instruction_text();
+    assert!(
+        instruction.contains("- total.region is the region of the image the value was read from"),
+        "{instruction}"
+    );
+
+    let parsed = generator
+        .parse_response(
+            r#"{"merchant": "Cafe", "total": {"value": 4.5, "region": {"x": 0.7, "y": 0.9, "width": 0.2, "height": 0.05}}}"#,
+        )
+        .unwrap();
+    assert_eq!(parsed.data.total.value, 4.5);
+    assert!(parsed.data.total.region.fits());
+    assert_eq!(parsed.validation_messages, None);
+
+    let parsed = generator
+        .parse_response(
+            r#"{"merchant": "Cafe", "total": {"value": 4.5, "region": {"x": 0.8, "y": 1.5, "width": 0.4, "height": 0.05}}}"#,
+        )
+        .unwrap();
+    let keywords: Vec<_> = parsed
+        .validation_issues
+        .iter()
+        .map(|issue| (issue.path.as_str(), issue.keyword.as_str()))
+        .collect();
+    assert_eq!(
+        keywords,
+        [
+            ("/total/region/y", "maximum"),
+            ("/total/region", "boundingBox")
+        ]
+    );
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+enum ShipmentStatus {
+    #[default]
+    Open,
+    Shipped,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+struct ShipmentLine {
+    sku: String,
+    quantity: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+struct Shipment {
+    /// Order number
+    id: u64,
+    customer: Option<String>,
+    status: ShipmentStatus,
+    lines: Vec<ShipmentLine>,
+}
+
+#[test]
+fn proto_schema_declares_nested_messages() {
+    let proto = Generator::json(Shipment::default()).to_proto_schema();
+    assert!(proto.starts_with("syntax = \"proto3\";"), "{proto}");
+    assert!(proto.contains("message Shipment {\n"), "{proto}");
+    let [id, customer, status, lines] = by_field_order([1, 2, 3, 4], [2, 1, 4, 3]);
+    assert!(
+        proto.contains(&format!("  // Order number\n  uint64 id = {id};")),
+        "{proto}"
+    );
+    assert!(
+        proto.contains(&format!("  optional string customer = {customer};")),
+        "{proto}"
+    );
+    assert!(
+        proto.contains(&format!("  Status status = {status};")),
+        "{proto}"
+    );
+    assert!(
+        proto.contains(&format!("  repeated Lines lines = {lines};")),
+        "{proto}"
+    );
+    assert!(
+        proto.contains(by_field_order(
+            "  message Lines {\n    string sku = 1;\n    uint32 quantity = 2;",
+            "  message Lines {\n    uint32 quantity = 1;\n    string sku = 2;"
+        )),
+        "{proto}"
+    );
+    assert!(proto.contains("  enum Status {\n    OPEN = 0;"), "{proto}");
+}
+
+#[cfg(feature = "protobuf")]
+#[test]
+fn textproto_format_round_trip() -> Result<(), ParseError> {
+    let generator = Generator::textproto(Shipment::default());
+    let instruction = generator.build_instruction_text();
+    assert!(
+        instruction.contains("```proto\nsyntax = \"proto3\";"),
+        "{instruction}"
+    );
+    assert!(
+        instruction.contains("```textproto\nid: 0\n"),
+        "{instruction}"
+    );
+
+    let parsed = generator.parse_response(
+        "```textproto\n# one line\nid: 42\nstatus: SHIPPED\nlines { sku: \"A-1\" quantity: 2 }\n```",
+    )?;
+    assert_eq!(
+        parsed.data,
+        Shipment {
+            id: 42,
+            customer: None,
+            status: ShipmentStatus::Shipped,
+            lines: vec![ShipmentLine {
+                sku: "A-1".into(),
+                quantity: 2,
+            }],
+        }
+    );
+    Ok(())
+}
+
+#[test]
+fn array_constraints_are_asked_for_and_enforced() {
+    let generator = Generator::json(vec![berlin()]).array_constraints(2, 3, "name");
+    let instruction = generator.build_instruction_text();
+    assert!(
+        instruction.contains(
+            "- Return between 2 and 3 items.\n- No two items may have the same `name`.\n"
+        ),
+        "{instruction}"
+    );
+
+    let duplicated = r#"[
+        {"name": "Berlin", "population": 3700000},
+        {"name": "Paris", "population": 2100000},
+        {"name": "Berlin", "population": 3600000},
+        {"name": "Rome", "population": 2800000},
+        {"name": "Madrid", "population": 3300000}
+    ]"#;
+    let error = generator.parse_response(duplicated).unwrap_err();
+    assert!(
+        matches!(&error, ParseError::ValidationError(message) if message.contains("/2: `name`")),
+        "{error:?}"
+    );
+
+    let parsed = generator
+        .trim_array(true)
+        .parse_response(duplicated)
+        .unwrap();
+    let names: Vec<_> = parsed.data.iter().map(|city| city.name.as_str()).collect();
+    assert_eq!(names, ["Berlin", "Paris", "Rome"]);
+
+    let error = Generator::json(vec![berlin()])
+        .array_constraints(2, 3, "")
+        .trim_array(true)
+        .parse_response(r#"[{"name": "Berlin", "population": 3700000}]"#)
+        .unwrap_err();
+    assert!(matches!(error, ParseError::ValidationError(_)), "{error:?}");
+}
+
+#[test]
+fn field_and_struct_validators_run_after_parsing() {
+    let capitalized = |value: &serde_json::Value| match value.as_str() {
+        Some(name) if name.starts_with(char::is_uppercase) => Ok(()),
+        _ => Err("must be capitalized".to_string()),
+    };
+    let populated = |city: &City| match city.population {
+        0 => Err("population must not be zero".to_string()),
+        _ => Ok(()),
+    };
+    let response = r#"{"name": "berlin", "population": 0}"#;
+
+    let parsed = Generator::json(City::default())
+        .validate_field("name", capitalized)
+        .validate_struct(populated)
+        .parse_response(response)
+        .unwrap();
+    assert_eq!(parsed.data.name, "berlin");
+    assert_eq!(
+        parsed.validation_messages.unwrap(),
+        ["/name: must be capitalized", "population must not be zero"]
+    );
+    let keywords: Vec<_> = parsed
+        .validation_issues
+        .iter()
+        .map(|issue| issue.keyword.as_str())
+        .collect();
+    assert_eq!(keywords, ["validateField", "validateStruct"]);
+
+    let strict = Generator::json(City::default())
+        .validation_options(ValidationOptions::default())
+        .validate_field("name", capitalized)
+        .validate_struct(populated);
+    let error = strict.parse_response(response).unwrap_err();
+    assert!(matches!(error, ParseError::ValidationError(_)), "{error:?}");
+
+    let lenient = Generator::json(City::default())
+        .validation_options(
+            ValidationOptions::default().severity("validateStruct", Severity::Warning),
+        )
+        .validate_struct(populated);
+    assert!(lenient.parse_response(response).is_ok());
+}
+
+#[test]
+fn validation_severity_is_set_per_keyword() {
+    let generator = Generator::with_validation(City::default()).validation_options(
+        ValidationOptions::default()
+            .allow_additional_properties(false)
+            .severity("additionalProperties", Severity::Warning),
+    );
+
+    let parsed = generator
+        .parse_response(r#"{"name": "Berlin", "population": 3700000, "country": "DE"}"#)
+        .unwrap();
+    assert_eq!(parsed.data, berlin());
+    assert_eq!(parsed.validation_issues.len(), 1);
+    let issue = &parsed.validation_issues[0];
+    assert_eq!(
+        (issue.keyword.as_str(), issue.severity),
+        ("additionalProperties", Severity::Warning)
+    );
+    assert!(issue.message.contains("country"), "{}", issue.message);
+    assert_eq!(parsed.issues(Severity::Warning).count(), 1);
+    assert_eq!(parsed.issues(Severity::Error).count(), 0);
+
+    // A wrong type still has the default severity and fails the parse
+    let error = generator
+        .parse_response(r#"{"name": "Berlin", "population": "many", "country": "DE"}"#)
+        .unwrap_err();
+    assert!(
+        matches!(&error, ParseError::ValidationError(message) if message.contains("many") && !message.contains("country")),
+        "{error:?}"
+    );
+}
+
+#[test]
+fn schema_compile_errors_are_surfaced() {
+    let config = Generator::with_schema(berlin()).config().clone();
+    let mut config = config.validate(true);
+    config.json_schema = Some(serde_json::json!({"type": 42}));
+
+    let error = Generator::try_new(config.clone()).err().unwrap();
+    assert!(
+        matches!(&error, ParseError::ValidationError(message) if message.contains("Invalid JSON Schema")),
+        "{error:?}"
+    );
+
+    let error = Generator::new(config)
+        .parse_response(r#"{"name": "Berlin", "population": 3700000}"#)
+        .unwrap_err();
+    assert!(matches!(error, ParseError::ValidationError(_)), "{error:?}");
+}
+
+#[test]
+fn instruction_is_cached_until_config_changes() {
+    let generator = Generator::json(berlin());
+    let first = generator.build_instruction();
+    let second = generator.build_instruction();
+    assert!(std::sync::Arc::ptr_eq(&first.content, &second.content));
+
+    let generator = generator.prefix("Answer briefly.");
+    let third = generator.build_instruction();
+    assert!(!std::sync::Arc::ptr_eq(&first.content, &third.content));
+    assert!(
+        third.text().starts_with("Answer briefly."),
+        "{}",
+        third.text()
+    );
+    assert_eq!(generator.build_instruction_text(), third.text());
+}
+
+#[test]
+fn examples_are_rendered_in_output_format() {
+    let paris = City {
+        name: "Paris".into(),
+        population: 2_100_000,
+    };
+    let generator = Generator::json(City::default())
+        .example("Largest city in France?", paris.clone())
+        .suffix("Answer with JSON only.");
+    let instruction = generator.build_instruction_text();
+
+    let examples = instruction.find("Examples:").unwrap();
+    assert!(instruction[examples..].contains(
+        "Input:\nLargest city in France?\n\nOutput:\n```json\n{\n  \"name\": \"Paris\",\n  \"population\": 2100000\n}\n```"
+    ));
+    assert!(examples < instruction.find("Answer with JSON only.").unwrap());
+
+    let generator = Generator::json(City::default())
+        .format(OutputFormat::MarkdownTable)
+        .examples([
+            ("Largest city in France?", paris),
+            ("And Germany?", berlin()),
+        ]);
+    let instruction = generator.build_instruction_text();
+    assert!(
+        instruction.contains("Output:\n| name | population |\n| --- | --- |\n| Berlin | 3700000 |")
+    );
+    assert_eq!(instruction.matches("Input:").count(), 2);
+}
+
+#[test]
+fn instruction_phrases_follow_the_locale() {
+    let generator = || Generator::json(City::default()).describe("name", "Name of the city");
+    let english = generator().build_instruction_text();
+    assert!(english.contains("Please return the response in JSON format."));
+    assert!(english.contains("The response should include:"));
+
+    let german = generator().locale(Locale::German).build_instruction_text();
+    assert!(german.contains("Bitte gib die Antwort im JSON-Format zurück."));
+    assert!(german.contains("Die Antwort sollte Folgendes enthalten:"));
+    assert!(!german.contains("Please return"));
+
+    let chinese = generator().locale(Locale::Chinese).build_instruction_text();
+    assert!(chinese.contains("请以 JSON 格式返回响应。"));
+
+    let template = InstructionTemplate {
+        example_format: "Answer like this:".into(),
+        ..Locale::English.template().clone()
+    };
+    let custom = generator()
+        .instruction_template(template)
+        .build_instruction_text();
+    assert!(custom.contains("Answer like this:\n```json"));
+    assert!(!custom.contains("Example format:"));
+    assert_eq!(
+        custom.replace("Answer like this:", "Example format:"),
+        english
+    );
+}
+
+#[test]
+fn template_controls_the_layout() {
+    let default = Generator::json(City::default())
+        .prefix("Extract the city.")
+        .describe("name", "Name of the city")
+        .suffix("Answer with JSON only.")
+        .build_instruction_text();
+
+    let generator = Generator::json(City::default())
+        .prefix("Extract the city.")
+        .describe("name", "Name of the city")
+        .suffix("Answer with JSON only.")
+        .template("### Task\n{prefix}\n\n### Output {format_note}\n{example}\n{examples}\n{descriptions}\n{suffix} {unknown}");
+    let instruction = generator.build_instruction_text();
+
+    assert!(instruction.starts_with(
+        "### Task\nExtract the city.\n\n### Output Please return the response in JSON format.\nExample format:\n```json"
+    ));
+    let example = instruction.find("Example format:").unwrap();
+    let descriptions = instruction.find("The response should include:").unwrap();
+    assert!(example < descriptions);
+    assert!(!instruction.contains("JSON Schema information:"));
+    // The line of the empty `{examples}` is left out
+    assert!(instruction.contains("```\nThe response should include:"));
+    assert!(instruction.ends_with("Answer with JSON only. {unknown}"));
+
+    let concatenated = Generator::json(City::default())
+        .prefix("Extract the city.")
+        .describe("name", "Name of the city")
+        .suffix("Answer with JSON only.")
+        .template("{prefix}\n\n{descriptions}\n\n{format_note}\n\n{example}\n{schema}\n{examples}\n{suffix}")
+        .build_instruction_text();
+    assert_eq!(
+        concatenated.replace("\n\n", "\n"),
+        default.replace("\n\n", "\n")
+    );
+}
+
+#[test]
+fn parsed_data_is_diffed_against_gold_labels() {
+    let generator = Generator::json(City::default());
+    let response = generator
+        .parse_response(r#"{"name": "Berlin", "population": 3500000}"#)
+        .unwrap();
+
+    assert!(generator
+        .parse_response(r#"{"name": "Berlin", "population": 3700000}"#)
+        .unwrap()
+        .diff_against(&berlin())
+        .is_empty());
+
+    let diffs = response.diff_against(&berlin());
+    assert_eq!(
+        diffs,
+        [FieldDiff::Mismatch {
+            path: "population".into(),
+            expected: 3_700_000.into(),
+            actual: 3_500_000.into(),
+        }]
+    );
+    assert_eq!(
+        diffs[0].to_string(),
+        "population: expected 3700000, got 3500000"
+    );
+}
+
+#[test]
+fn instruction_tokens_are_estimated() {
+    let generator = Generator::json(City::default());
+    let tokens = generator.build_instruction().estimated_tokens("gpt-4o");
+    assert!(tokens > 10, "{tokens}");
+    assert!(generator.fits_within("gpt-4o", tokens));
+    assert!(!generator.fits_within("gpt-4o", tokens - 1));
+
+    let with_examples = Generator::json(City::default()).examples([
+        ("Largest city in Germany?", berlin()),
+        ("And the second largest?", berlin()),
+    ]);
+    assert!(!with_examples.fits_within("gpt-4o", tokens));
+}
+
+#[test]
+fn compact_schema_is_minified_without_the_example() {
+    let full = Generator::json(City::default()).build_instruction_text();
+    let compact = Generator::json(City::default())
+        .compact_schema(true)
+        .build_instruction_text();
+
+    assert!(full.contains("Example format:"));
+    assert!(!compact.contains("Example format:"));
+    assert!(compact.contains("JSON Schema information:\n```json\n{\""));
+    assert!(compact.contains(r#""required":["name","population"]"#));
+    assert!(compact.len() < full.len(), "{compact}");
+    assert!(compact.contains("format.\n\nJSON Schema information:"));
+
+    let array = Generator::json(City::default())
+        .format(OutputFormat::JsonArray)
+        .compact_schema(true)
+        .build_instruction_text();
+    assert!(
+        array.contains(by_field_order(
+            r#"{"type":"array","items":{"#,
+            r#"{"items":{"#
+        )),
+        "{array}"
+    );
+    assert!(!array.contains("Example format:"));
+
+    let typescript = Generator::json(City::default())
+        .compact_schema(true)
+        .schema_dialect(SchemaDialect::TypeScript)
+        .build_instruction_text();
+    assert!(typescript.contains("```typescript"));
+    assert!(!typescript.contains("Example format:"));
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    #[default]
+    Pending,
+    Active,
+    Closed,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+struct Issue {
+    title: String,
+    status: Status,
+    labels: Vec<Status>,
+}
+
+#[test]
+fn enum_variants_are_listed_and_validated() {
+    let issue = Issue {
+        labels: vec![Status::Active],
+        ..Default::default()
+    };
+    let instruction = Generator::json(issue.clone()).build_instruction_text();
+    assert!(
+        instruction.contains("- labels[] must be one of: pending, active, closed\n"),
+        "{instruction}"
+    );
+    assert!(instruction.contains("- status must be one of: pending, active, closed\n"));
+
+    let example = Generator::json(issue.clone())
+        .schema_source(SchemaSource::Example)
+        .build_instruction_text();
+    assert!(example.contains(r#""enum": ["#), "{example}");
+
+    let answer = Generator::json(Status::default()).build_instruction_text();
+    assert!(answer.contains("- The answer must be one of: pending, active, closed\n"));
+
+    let generator = Generator::with_validation(issue);
+    let error = generator
+        .parse_response(r#"{"title": "Login fails", "status": "done", "labels": []}"#)
+        .unwrap_err();
+    assert!(
+        matches!(&error, ParseError::ValidationError(message) if message.contains("/status")),
+        "{error:?}"
+    );
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+struct Contact {
+    name: String,
+    email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    phone: Option<String>,
+}
+
+#[test]
+fn optional_fields_are_marked() {
+    let instruction = Generator::json(Contact::default())
+        .describe("phone", "With the country code")
+        .build_instruction_text();
+
+    assert!(
+        instruction.contains(by_field_order(
+            "- phone (string, optional): With the country code\n- name (string, required)\n- email (string, optional)\n",
+            "- phone (string, optional): With the country code\n- email (string, optional)\n- name (string, required)\n"
+        )),
+        "{instruction}"
+    );
+
+    let generator = Generator::with_validation(Contact::default());
+    let parsed = generator.parse_response(r#"{"name": "Ada"}"#).unwrap();
+    assert_eq!(parsed.validation_messages, None);
+    assert!(generator.parse_response(r#"{"email": null}"#).is_err());
+}
+
+#[test]
+fn answers_are_read_from_tool_calls() {
+    let generator = Generator::json(City::default());
+    let tool = generator.to_tool();
+    assert_eq!(tool.function.name, "City");
+    assert_eq!(tool.function.strict, Some(true));
+    let parameters = tool.function.parameters.unwrap();
+    assert_eq!(parameters["additionalProperties"], false);
+    assert_eq!(
+        parameters["required"],
+        serde_json::json!(["name", "population"])
+    );
+
+    let call = |name: &str| ChatCompletionMessageToolCall {
+        id: "call_1".into(),
+        r#type: ChatCompletionToolType::Function,
+        function: FunctionCall {
+            name: name.into(),
+            arguments: r#"{"name": "Berlin", "population": 3700000}"#.into(),
+        },
+    };
+    // Arguments are JSON whatever the output format
+    let parsed = Generator::json(City::default())
+        .format(OutputFormat::MarkdownTable)
+        .parse_tool_call(&call("City"))
+        .unwrap();
+    assert_eq!(parsed.data, berlin());
+
+    assert!(matches!(
+        generator.parse_tool_call(&call("lookup_weather")),
+        Err(ParseError::Extraction(_))
+    ));
+}
+
+#[test]
+fn streamed_tool_calls_are_parsed_as_they_finish() {
+    let chunk = |tool_calls: serde_json::Value, finish_reason: Option<&str>| {
+        serde_json::from_value::<CreateChatCompletionStreamResponse>(serde_json::json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion.chunk",
+            "created": 1,
+            "model": "gpt-4o",
+            "choices": [{
+                "index": 0,
+                "delta": {"tool_calls": tool_calls},
+                "finish_reason": finish_reason
+            }]
+        }))
+        .unwrap()
+    };
+    let generator = Generator::json(City::default());
+    let mut calls = ToolCallAccumulator::new(&generator);
+
+    let first = calls.push(&chunk(
+        serde_json::json!([{"index": 0, "id": "call_1", "type": "function", "function": {"name": "City", "arguments": "{\"name\": \"Ber"}}]),
+        None,
+    ));
+    assert!(first.is_empty());
+    assert!(calls.is_pending());
+
+    // A fragment of the next call finishes the first one
+    let second = calls.push(&chunk(
+        serde_json::json!([
+            {"index": 0, "function": {"arguments": "lin\", \"population\": 3700000}"}},
+            {"index": 1, "id": "call_2", "type": "function", "function": {"name": "lookup_weather", "arguments": "{}"}}
+        ]),
+        None,
+    ));
+    assert_eq!(second.len(), 1);
+    assert_eq!(second[0].index, 0);
+    assert_eq!(second[0].call.id, "call_1");
+    assert_eq!(second[0].parsed.as_ref().unwrap().data, berlin());
+
+    let last = calls.push(&chunk(serde_json::json!(null), Some("tool_calls")));
+    assert_eq!(last.len(), 1);
+    assert_eq!(last[0].call.function.name, "lookup_weather");
+    assert!(matches!(last[0].parsed, Err(ParseError::Extraction(_))));
+    assert!(!calls.is_pending());
+    assert!(calls.finish().is_empty());
+}
+
+#[test]
+fn every_choice_is_parsed_and_the_best_one_picked() {
+    let contents = [
+        r#"{"stars": 4, "title": "A very long title", "airport": "ber"}"#,
+        "I can't rate this flight",
+        r#"{"stars": 5, "title": "Smooth", "airport": "BER"}"#,
+        r#"{"stars": 3, "title": "Late", "airport": "TXL"}"#,
+    ];
+    let choices: Vec<_> = contents
+        .iter()
+        .enumerate()
+        .map(|(index, content)| {
+            serde_json::json!({
+                "index": index,
+                "message": {"role": "assistant", "content": content},
+                "finish_reason": "stop"
+            })
+        })
+        .collect();
+    let completion: CreateChatCompletionResponse = serde_json::from_value(serde_json::json!({
+        "id": "chatcmpl-1",
+        "object": "chat.completion",
+        "created": 1,
+        "model": "gpt-4o",
+        "choices": choices
+    }))
+    .unwrap();
+
+    let candidates = Generator::with_validation(Review::default()).parse_choices(&completion);
+
+    let indexes: Vec<_> = candidates.parsed.iter().map(|c| c.index).collect();
+    assert_eq!(indexes, [0, 2, 3]);
+    let errors: Vec<_> = candidates
+        .parsed
+        .iter()
+        .map(|c| c.validation_errors())
+        .collect();
+    assert_eq!(errors, [2, 0, 0]);
+    assert_eq!(candidates.failed.len(), 1);
+    assert_eq!(candidates.failed[0].0, 1);
+
+    // The first valid choice wins the tie
+    assert_eq!(candidates.best().unwrap().index, 2);
+    let longest = candidates.best_by(&CandidateSelection::LongestContent);
+    assert_eq!(longest.unwrap().index, 0);
+    let worst = candidates
+        .into_best_by(&CandidateSelection::Custom(|review| {
+            -f64::from(review.data.stars)
+        }))
+        .unwrap();
+    assert_eq!(worst.response.data.title, "Late");
+}
+
+/// Serve moderation results flagging each of `flagged` in turn.
+fn serve_moderations(flagged: Vec<Option<&'static str>>) -> String {
+    const CATEGORIES: [&str; 13] = [
+        "hate",
+        "hate/threatening",
+        "harassment",
+        "harassment/threatening",
+        "illicit",
+        "illicit/violent",
+        "self-harm",
+        "self-harm/intent",
+        "self-harm/instructions",
+        "sexual",
+        "sexual/minors",
+        "violence",
+        "violence/graphic",
+    ];
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    std::thread::spawn(move || {
+        for category in flagged {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0; 16384];
+            let _ = stream.read(&mut buf).unwrap();
+            let map = |value: &dyn Fn(&str) -> serde_json::Value| {
+                CATEGORIES
+                    .iter()
+                    .map(|name| (name.to_string(), value(name)))
+                    .collect::<serde_json::Map<_, _>>()
+            };
+            let body = serde_json::json!({
+                "id": "modr-1",
+                "model": "omni-moderation-latest",
+                "results": [{
+                    "flagged": category.is_some(),
+                    "categories": map(&|name| (Some(name) == category).into()),
+                    "category_scores": map(&|_| 0.5.into()),
+                    "category_applied_input_types": map(&|_| serde_json::json!(["text"]))
+                }]
+            })
+            .to_string();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+
+    format!("http://{addr}/v1")
+}
+
+#[tokio::test]
+async fn checked_output_collects_validation_and_moderation_flags() {
+    let api_base = serve_moderations(vec![None, Some("violence")]);
+    let client = Client::with_config(
+        OpenAIConfig::new()
+            .with_api_base(api_base)
+            .with_api_key("sk-test"),
+    );
+    let generator = Generator::with_validation(Review::default());
+    let no_lowercase = |review: &Review| {
+        if review.title.chars().any(char::is_lowercase) {
+            Err(format!("title `{}` is not upper case", review.title))
+        } else {
+            Ok(())
+        }
+    };
+
+    let clean = generator
+        .parse_response(r#"{"stars": 4, "title": "SMOOTH", "airport": "BER"}"#)
+        .unwrap();
+    let checked = generator.check(&client, clean, no_lowercase).await.unwrap();
+    assert!(checked.is_clean());
+    assert_eq!(checked.into_data().unwrap().title, "SMOOTH");
+
+    let flagged = generator
+        .parse_response(r#"{"stars": 9, "title": "Brutal", "airport": "BER"}"#)
+        .unwrap();
+    let checked = generator
+        .check(&client, flagged, no_lowercase)
+        .await
+        .unwrap();
+    assert!(!checked.is_clean());
+    let flags = checked.into_data().unwrap_err();
+    assert_eq!(flags.len(), 3, "{flags:?}");
+    assert!(matches!(&flags[0], CheckFlag::Schema { message } if message.contains("/stars")));
+    assert_eq!(
+        flags[1],
+        CheckFlag::Validation {
+            message: "title `Brutal` is not upper case".into()
+        }
+    );
+    assert_eq!(
+        flags[2],
+        CheckFlag::Moderation {
+            categories: vec!["violence".into()]
+        }
+    );
+    assert_eq!(flags[2].to_string(), "moderation: violence");
+}
+
+#[test]
+fn repair_instruction_lists_broken_fields_only() {
+    let generator = Generator::with_validation(Review::default());
+
+    let output = r#"{"stars": 9, "title": "Smooth"}"#;
+    let error = generator.parse_response(output).unwrap_err();
+    assert_eq!(
+        generator.build_repair_instruction(&error, output),
+        by_field_order(
+            "Your previous response could not be used. Fix these problems and answer again with the complete JSON output:\n\
+             - airport is missing\n\
+             - stars: 9 is greater than the maximum of 5.0",
+            "Your previous response could not be used. Fix these problems and answer again with the complete JSON output:\n\
+             - stars: 9 is greater than the maximum of 5.0\n\
+             - airport is missing"
+        )
+    );
+
+    let truncated = "```json\n{\"stars\": 4, \"title\": \"Smooth\", \"airport\": \"BE";
+    let error = generator.parse_response(truncated).unwrap_err();
+    let repair = generator.build_repair_instruction(&error, truncated);
+    assert!(
+        repair.contains("\n- The response is not valid JSON: EOF while parsing a string"),
+        "{repair}"
+    );
+    assert!(
+        repair.ends_with("\n- The problem is near: `tle\": \"Smooth\", \"airport\": \"BE`"),
+        "{repair}"
+    );
+
+    let german = Generator::with_validation(Review::default()).locale(Locale::German);
+    let output = r#"{"stars": 4, "title": "Smooth"}"#;
+    let error = german.parse_response(output).unwrap_err();
+    assert!(german
+        .build_repair_instruction(&error, output)
+        .ends_with("\n- airport fehlt"));
+}
+
+/// Answer `requests` chat completion requests with the JSON of a city named after the user
+/// message, or with text when the message is "nowhere".
+fn serve_city_per_input(requests: usize) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    std::thread::spawn(move || {
+        for _ in 0..requests {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 16384];
+            let body = loop {
+                let read = stream.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..read]);
+                let text = String::from_utf8_lossy(&request).to_string();
+                let Some((head, body)) = text.split_once("\r\n\r\n") else {
+                    continue;
+                };
+                let length: usize = head
+                    .lines()
+                    .find_map(|line| {
+                        line.to_ascii_lowercase()
+                            .strip_prefix("content-length: ")
+                            .map(|value| value.trim().parse().unwrap())
+                    })
+                    .unwrap_or_default();
+                if body.len() >= length {
+                    break body.to_string();
+                }
+            };
+            let request: serde_json::Value = serde_json::from_str(&body).unwrap();
+            let input = request["messages"][1]["content"]
+                .as_str()
+                .unwrap()
+                .to_string();
+            let content = if input == "nowhere" {
+                "There is no city in this text.".to_string()
+            } else {
+                serde_json::json!({ "name": input, "population": 1 }).to_string()
+            };
+            let body = serde_json::json!({
+                "id": "chatcmpl-1",
+                "object": "chat.completion",
+                "created": 1,
+                "model": "gpt-4o-mini",
+                "choices": [{
+                    "index": 0,
+                    "message": { "role": "assistant", "content": content },
+                    "finish_reason": "stop"
+                }]
+            })
+            .to_string();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+
+    format!("http://{addr}/v1")
+}
+
+#[tokio::test]
+async fn batch_results_follow_input_order() {
+    let inputs = ["Berlin", "Paris", "nowhere", "Rome", "Madrid"];
+    let api_base = serve_city_per_input(inputs.len());
+    let client = Client::with_config(
+        OpenAIConfig::new()
+            .with_api_base(api_base)
+            .with_api_key("sk-test"),
+    );
+
+    let results = BatchGenerator::new(&client, "gpt-4o-mini", Generator::json(City::default()))
+        .concurrency(2)
+        .run(inputs)
+        .await;
+
+    assert_eq!(results.len(), inputs.len());
+    for (input, result) in inputs.iter().zip(&results) {
+        match result {
+            Ok(response) => assert_eq!(&response.data.name, input),
+            Err(OpenAIError::StructuredOutput(_)) => assert_eq!(*input, "nowhere"),
+            Err(e) => panic!("unexpected error for {input}: {e}"),
+        }
+    }
+    assert!(results[2].is_err());
+}
@@ -0,0 +1,76 @@
+use async_openai::{
+    error::OpenAIError,
+    template::{placeholders, RequestTemplate, TemplateError},
+    types::{
+        ChatCompletionRequestMessageContentPartText, ChatCompletionRequestSystemMessage,
+        ChatCompletionRequestUserMessage, ChatCompletionRequestUserMessageContent,
+        ChatCompletionRequestUserMessageContentPart, CreateChatCompletionRequest,
+        CreateChatCompletionRequestArgs,
+    },
+};
+
+fn request() -> CreateChatCompletionRequest {
+    let parts: Vec<ChatCompletionRequestUserMessageContentPart> = vec![
+        ChatCompletionRequestMessageContentPartText::from("Summarize {{document}}").into(),
+        ChatCompletionRequestMessageContentPartText::from("in {{ words }} words.").into(),
+    ];
+    CreateChatCompletionRequestArgs::default()
+        .model("gpt-4o-mini")
+        .messages([
+            ChatCompletionRequestSystemMessage::from("You write for {{audience}}.").into(),
+            ChatCompletionRequestUserMessage::from(ChatCompletionRequestUserMessageContent::from(
+                parts,
+            ))
+            .into(),
+        ])
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn collects_placeholders_of_all_messages() {
+    let template = RequestTemplate::new(request());
+    let names: Vec<_> = template.placeholders().iter().map(String::as_str).collect();
+    assert_eq!(names, ["audience", "document", "words"]);
+
+    assert!(placeholders("JSON like {\"a\": 1} or {single} braces").is_empty());
+}
+
+#[test]
+fn renders_every_text_part() {
+    let template = RequestTemplate::new(request());
+
+    let request = template
+        .render([
+            ("audience", "children"),
+            ("document", "the {{report}}"),
+            ("words", "50"),
+        ])
+        .unwrap();
+
+    assert_eq!(request.messages[0].text(), "You write for children.");
+    assert_eq!(
+        request.messages[1].text(),
+        "Summarize the {{report}}\nin 50 words."
+    );
+    assert_eq!(request.model, "gpt-4o-mini");
+}
+
+#[test]
+fn missing_and_unused_values_fail() {
+    let template = RequestTemplate::new(request());
+
+    let error = template
+        .render([("audience", "children"), ("document", "x"), ("word", "50")])
+        .unwrap_err();
+    assert_eq!(
+        error,
+        TemplateError {
+            missing: vec!["words".into()],
+            unused: vec!["word".into()],
+        }
+    );
+
+    let error: OpenAIError = error.into();
+    assert!(matches!(error, OpenAIError::InvalidArgument(_)));
+}
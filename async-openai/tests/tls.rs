@@ -0,0 +1,26 @@
+use async_openai::{error::OpenAIError, tls::TlsConfig, Client};
+
+#[test]
+fn rejects_malformed_pins() {
+    let result = TlsConfig::new().with_pinned_public_key_sha256("not-a-digest");
+    assert!(matches!(result, Err(OpenAIError::InvalidArgument(_))));
+
+    // 16 bytes is not a SHA-256 digest
+    let result = TlsConfig::new().with_pinned_certificate_sha256("AAAAAAAAAAAAAAAAAAAAAA==");
+    assert!(matches!(result, Err(OpenAIError::InvalidArgument(_))));
+}
+
+#[test]
+fn builds_client_with_pins() {
+    let tls = TlsConfig::new()
+        .with_pinned_public_key_sha256("sha256/r/mIkG3eEpVdm+u/ko/cwxzOMo1bk4TyHIlByibiA5E=")
+        .unwrap();
+
+    assert!(Client::new().with_tls(&tls).is_ok());
+}
+
+#[test]
+fn reports_unreadable_ca_bundle() {
+    let result = TlsConfig::new().with_ca_bundle_file("/nonexistent/ca.pem");
+    assert!(matches!(result, Err(OpenAIError::FileReadError(_))));
+}
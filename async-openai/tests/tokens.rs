@@ -0,0 +1,54 @@
+use async_openai::tokens::{
+    count_tokens, truncate_to_tokens, Tokenizer, TruncationStrategy, ELLIPSIS,
+};
+
+const MODEL: &str = "gpt-4o";
+
+#[test]
+fn short_text_is_borrowed_unchanged() {
+    let text = "Hello, world!";
+    let truncated = truncate_to_tokens(text, MODEL, 100, TruncationStrategy::Head);
+    assert!(matches!(truncated, std::borrow::Cow::Borrowed(t) if t == text));
+}
+
+#[test]
+fn strategies_keep_the_right_part() {
+    let text = (1..=200)
+        .map(|n| n.to_string())
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let head = truncate_to_tokens(&text, MODEL, 20, TruncationStrategy::Head);
+    assert!(text.starts_with(head.as_ref()));
+    assert!(count_tokens(&head, MODEL) <= 20);
+
+    let tail = truncate_to_tokens(&text, MODEL, 20, TruncationStrategy::Tail);
+    assert!(text.ends_with(tail.as_ref()));
+    assert!(count_tokens(&tail, MODEL) <= 20);
+
+    let middle = truncate_to_tokens(&text, MODEL, 20, TruncationStrategy::MiddleEllipsis);
+    let (start, end) = middle.split_once(ELLIPSIS).unwrap();
+    assert!(text.starts_with(start) && text.ends_with(end));
+    assert!(count_tokens(&middle, MODEL) <= 20);
+}
+
+#[test]
+fn never_splits_grapheme_clusters() {
+    // Family emoji (ZWJ sequence) and a flag, each a single grapheme of several code points
+    let text = "👨‍👩‍👧‍👦🇯🇵".repeat(50);
+    let tokenizer = Tokenizer::for_model(MODEL);
+
+    for max_tokens in [1, 5, 17, 40] {
+        for strategy in [TruncationStrategy::Head, TruncationStrategy::Tail] {
+            let truncated = tokenizer.truncate(&text, max_tokens, strategy);
+            let clusters = truncated.replace("👨‍👩‍👧‍👦", "").replace("🇯🇵", "");
+            assert!(clusters.is_empty(), "split a grapheme: {truncated:?}");
+            assert!(tokenizer.count(&truncated) <= max_tokens);
+        }
+    }
+
+    assert_eq!(
+        truncate_to_tokens(&text, MODEL, 0, TruncationStrategy::MiddleEllipsis),
+        ""
+    );
+}
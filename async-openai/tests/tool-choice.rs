@@ -0,0 +1,93 @@
+use async_openai::{
+    error::OpenAIError,
+    types::{
+        ChatCompletionAllowedToolsMode, ChatCompletionRequestUserMessage, ChatCompletionTool,
+        ChatCompletionToolChoiceOption, CreateChatCompletionRequestArgs, FunctionObject,
+    },
+};
+
+fn tool(name: &str) -> ChatCompletionTool {
+    ChatCompletionTool {
+        r#type: Default::default(),
+        function: FunctionObject {
+            name: name.into(),
+            description: None,
+            parameters: None,
+            strict: None,
+        },
+    }
+}
+
+fn request(
+    tools: Vec<ChatCompletionTool>,
+    tool_choice: ChatCompletionToolChoiceOption,
+) -> Result<serde_json::Value, OpenAIError> {
+    let mut args = CreateChatCompletionRequestArgs::default();
+    args.model("gpt-4o-mini")
+        .messages([ChatCompletionRequestUserMessage::from("Weather in Oslo?").into()])
+        .tool_choice(tool_choice);
+    if !tools.is_empty() {
+        args.tools(tools);
+    }
+    Ok(serde_json::to_value(args.build()?).unwrap())
+}
+
+#[test]
+fn tool_choice_serializes_each_variant() {
+    let tools = || vec![tool("get_weather"), tool("get_time")];
+
+    let required = request(tools(), "required".into()).unwrap();
+    assert_eq!(required["tool_choice"], "required");
+
+    let named = request(
+        tools(),
+        ChatCompletionToolChoiceOption::function("get_time"),
+    )
+    .unwrap();
+    assert_eq!(
+        named["tool_choice"],
+        serde_json::json!({"type": "function", "function": {"name": "get_time"}})
+    );
+
+    let allowed = ChatCompletionToolChoiceOption::allowed_tools(
+        ["get_weather"],
+        ChatCompletionAllowedToolsMode::Required,
+    );
+    let request = request(tools(), allowed.clone()).unwrap();
+    assert_eq!(
+        request["tool_choice"],
+        serde_json::json!({
+            "type": "allowed_tools",
+            "allowed_tools": {
+                "mode": "required",
+                "tools": [{"type": "function", "function": {"name": "get_weather"}}]
+            }
+        })
+    );
+    let deserialized: ChatCompletionToolChoiceOption =
+        serde_json::from_value(request["tool_choice"].clone()).unwrap();
+    assert_eq!(deserialized, allowed);
+}
+
+#[test]
+fn tool_choice_is_checked_against_the_tools() {
+    let unknown = request(
+        vec![tool("get_weather")],
+        ChatCompletionToolChoiceOption::function("get_wether"),
+    )
+    .unwrap_err();
+    assert!(
+        matches!(&unknown, OpenAIError::InvalidArgument(message) if message.contains("get_wether")),
+        "{unknown:?}"
+    );
+
+    let no_tools = request(vec![], ChatCompletionToolChoiceOption::Required).unwrap_err();
+    assert!(matches!(no_tools, OpenAIError::InvalidArgument(_)));
+
+    let allowed = ChatCompletionToolChoiceOption::allowed_tools(
+        ["get_weather", "get_time"],
+        ChatCompletionAllowedToolsMode::Auto,
+    );
+    assert!(request(vec![tool("get_weather")], allowed).is_err());
+    assert!(request(vec![], ChatCompletionToolChoiceOption::None).is_ok());
+}
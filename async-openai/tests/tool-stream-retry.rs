@@ -0,0 +1,197 @@
+use std::{
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use async_openai::{
+    config::OpenAIConfig,
+    error::OpenAIError,
+    events::ClientEvent,
+    types::{
+        ChatCompletionRequestUserMessage, CreateChatCompletionRequest,
+        CreateChatCompletionRequestArgs, FinishReason,
+    },
+    Client,
+};
+use futures::StreamExt;
+
+/// Read a request up to the end of its JSON body.
+fn read_body(stream: &mut TcpStream) {
+    let mut request = String::new();
+    let mut buf = [0; 16384];
+    loop {
+        let n = stream.read(&mut buf).unwrap();
+        request.push_str(&String::from_utf8_lossy(&buf[..n]));
+        if let Some((_, body)) = request.split_once("\r\n\r\n") {
+            if serde_json::from_str::<serde_json::Value>(body).is_ok() {
+                return;
+            }
+        }
+    }
+}
+
+fn chunk(delta: &str, finish_reason: &str) -> String {
+    format!(
+        "data: {{\"id\":\"1\",\"object\":\"chat.completion.chunk\",\"created\":1,\"model\":\"m\",\"choices\":[{{\"index\":0,\"delta\":{delta},\"finish_reason\":{finish_reason}}}]}}\n\n"
+    )
+}
+
+fn tool_call_start() -> String {
+    chunk(
+        r#"{"role":"assistant","tool_calls":[{"index":0,"id":"call_1","type":"function","function":{"name":"get_weather","arguments":""}}]}"#,
+        "null",
+    )
+}
+
+/// Serve `first_connections` streams that drop in the middle of a tool call, then complete
+/// ones. Returns the API base and the number of connections.
+fn serve(first_connections: usize, body: String) -> (String, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let connections = Arc::new(AtomicUsize::new(0));
+    let counter = connections.clone();
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            read_body(&mut stream);
+            let connection = counter.fetch_add(1, Ordering::SeqCst);
+            let response = if connection < first_connections {
+                // Announce more than is sent, so that the connection drops mid-stream
+                let partial = tool_call_start();
+                format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\ncontent-length: {}\r\n\r\n{partial}",
+                    partial.len() + 100
+                )
+            } else {
+                format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                    body.len()
+                )
+            };
+            let _ = stream.write_all(response.as_bytes());
+        }
+    });
+
+    (format!("http://{addr}/v1"), connections)
+}
+
+fn request() -> CreateChatCompletionRequest {
+    CreateChatCompletionRequestArgs::default()
+        .model("gpt-4o-mini")
+        .messages([ChatCompletionRequestUserMessage::from("Weather in Paris?").into()])
+        .build()
+        .unwrap()
+}
+
+fn client(api_base: String) -> Client<OpenAIConfig> {
+    Client::with_config(
+        OpenAIConfig::new()
+            .with_api_base(api_base)
+            .with_api_key("sk-test"),
+    )
+}
+
+#[tokio::test]
+async fn dropped_tool_turn_is_retried() {
+    let body = [
+        tool_call_start(),
+        chunk(
+            r#"{"tool_calls":[{"index":0,"function":{"arguments":"{\"city\":\"Paris\"}"}}]}"#,
+            "null",
+        ),
+        chunk("{}", "\"tool_calls\""),
+        "data: [DONE]\n\n".to_string(),
+    ]
+    .concat();
+    let (api_base, connections) = serve(1, body);
+    let client = client(api_base);
+    let mut events = client.events().subscribe();
+
+    let chunks: Vec<_> = client
+        .chat()
+        .create_stream_with_tool_retry(request(), 2)
+        .await
+        .unwrap()
+        .collect()
+        .await;
+
+    // The chunks of the dropped attempt are not yielded
+    assert_eq!(chunks.len(), 3, "{chunks:#?}");
+    let chunks: Vec<_> = chunks.into_iter().map(Result::unwrap).collect();
+    assert_eq!(
+        chunks[2].choices[0].finish_reason,
+        Some(FinishReason::ToolCalls)
+    );
+    assert!(connections.load(Ordering::SeqCst) >= 2);
+
+    let mut retries = vec![];
+    while let Ok(Some(event)) =
+        tokio::time::timeout(std::time::Duration::from_millis(100), events.recv()).await
+    {
+        if let ClientEvent::StreamRetried { retry, .. } = event {
+            retries.push(retry);
+        }
+    }
+    assert_eq!(retries, [1]);
+}
+
+#[tokio::test]
+async fn retries_are_capped() {
+    let (api_base, _) = serve(usize::MAX, String::new());
+    let client = client(api_base);
+
+    let items: Vec<_> = client
+        .chat()
+        .create_stream_with_tool_retry(request(), 1)
+        .await
+        .unwrap()
+        .collect()
+        .await;
+
+    // The tool call chunk received before the last drop, then the error
+    assert!(items[0].is_ok(), "{items:#?}");
+    assert!(items
+        .iter()
+        .any(|item| matches!(item, Err(OpenAIError::StreamDisconnected(_)))));
+}
+
+#[tokio::test]
+async fn error_status_on_open_is_not_retried() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let connections = Arc::new(AtomicUsize::new(0));
+    let counter = connections.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            read_body(&mut stream);
+            counter.fetch_add(1, Ordering::SeqCst);
+            let body = r#"{"error":{"message":"bad tools","type":"invalid_request_error","param":null,"code":null}}"#;
+            let response = format!(
+                "HTTP/1.1 400 Bad Request\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            let _ = stream.write_all(response.as_bytes());
+        }
+    });
+    let client = client(format!("http://{addr}/v1"));
+
+    let items: Vec<_> = client
+        .chat()
+        .create_stream_with_tool_retry(request(), 3)
+        .await
+        .unwrap()
+        .collect()
+        .await;
+
+    assert!(
+        matches!(items.as_slice(), [Err(OpenAIError::StreamError(_))]),
+        "{items:#?}"
+    );
+    assert_eq!(connections.load(Ordering::SeqCst), 1);
+}
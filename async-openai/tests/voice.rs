@@ -0,0 +1,272 @@
+use std::{
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::mpsc,
+};
+
+use async_openai::{
+    config::OpenAIConfig,
+    memory::RollingSummary,
+    types::{AudioInput, ChatCompletionRequestMessage},
+    voice::{VoiceEvent, VoicePipeline},
+    Client,
+};
+use futures::StreamExt;
+use serde_json::json;
+
+/// Read a request up to the end of its body, returning the request line and the body.
+fn read_request(stream: &mut TcpStream) -> (String, String) {
+    let mut request = Vec::new();
+    let mut buf = [0; 16384];
+    loop {
+        let n = stream.read(&mut buf).unwrap();
+        request.extend_from_slice(&buf[..n]);
+        let text = String::from_utf8_lossy(&request).to_string();
+        let (head, body) = match text.split_once("\r\n\r\n") {
+            Some(parts) => parts,
+            None if n == 0 => (text.as_str(), ""),
+            None => continue,
+        };
+        let length = head.lines().find_map(|line| {
+            line.to_lowercase()
+                .strip_prefix("content-length: ")?
+                .parse()
+                .ok()
+        });
+        let complete = match length {
+            Some(length) => body.len() >= length,
+            None => body.ends_with("0\r\n\r\n"),
+        };
+        if n == 0 || complete {
+            let line = head.lines().next().unwrap_or_default().to_string();
+            return (line, body.to_string());
+        }
+    }
+}
+
+/// Answer transcription, chat and speech requests in order, sending each request line and
+/// body to the returned receiver.
+fn serve(responses: Vec<(&'static str, String)>) -> (String, mpsc::Receiver<(String, String)>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (tx, rx) = mpsc::channel();
+
+    std::thread::spawn(move || {
+        for (content_type, body) in responses {
+            let (mut stream, _) = listener.accept().unwrap();
+            let _ = tx.send(read_request(&mut stream));
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: {content_type}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+
+    (format!("http://{addr}/v1"), rx)
+}
+
+fn client(api_base: String) -> Client<OpenAIConfig> {
+    Client::with_config(
+        OpenAIConfig::new()
+            .with_api_base(api_base)
+            .with_api_key("sk-test"),
+    )
+}
+
+fn audio() -> AudioInput {
+    AudioInput::from_vec_u8("question.wav".into(), b"RIFF".to_vec())
+}
+
+fn transcription(text: &str) -> (&'static str, String) {
+    ("application/json", json!({ "text": text }).to_string())
+}
+
+fn speech(audio: &str) -> (&'static str, String) {
+    ("audio/mpeg", audio.to_string())
+}
+
+#[tokio::test]
+async fn respond_runs_every_stage_and_keeps_history() {
+    let completion = json!({
+        "id": "chatcmpl-1",
+        "object": "chat.completion",
+        "created": 1,
+        "model": "gpt-4o-mini",
+        "choices": [{
+            "index": 0,
+            "message": { "role": "assistant", "content": "It is sunny." },
+            "finish_reason": "stop"
+        }],
+        "usage": { "prompt_tokens": 12, "completion_tokens": 4, "total_tokens": 16 }
+    });
+    let (api_base, requests) = serve(vec![
+        transcription("How is the weather?"),
+        ("application/json", completion.to_string()),
+        speech("mp3-bytes"),
+    ]);
+    let client = client(api_base);
+    let mut pipeline = VoicePipeline::new(&client)
+        .with_instructions("Be brief.")
+        .with_chat_model("gpt-4o");
+
+    let turn = pipeline.respond(audio()).await.unwrap();
+
+    assert_eq!(turn.transcript, "How is the weather?");
+    assert_eq!(turn.reply, "It is sunny.");
+    assert_eq!(&turn.speech[..], b"mp3-bytes");
+    assert_eq!(turn.usage.unwrap().total_tokens, 16);
+
+    let paths: Vec<_> = requests.try_iter().collect();
+    assert!(paths[0].0.contains("/audio/transcriptions"));
+    assert!(paths[0].1.contains("whisper-1"));
+    assert!(paths[1].0.contains("/chat/completions"));
+    let chat: serde_json::Value = serde_json::from_str(&paths[1].1).unwrap();
+    assert_eq!(chat["model"], "gpt-4o");
+    assert_eq!(chat["messages"][1]["content"], "How is the weather?");
+    assert!(paths[2].0.contains("/audio/speech"));
+    let speech: serde_json::Value = serde_json::from_str(&paths[2].1).unwrap();
+    assert_eq!(speech["input"], "It is sunny.");
+
+    assert_eq!(pipeline.messages().len(), 3);
+    assert!(matches!(
+        pipeline.messages()[2],
+        ChatCompletionRequestMessage::Assistant(_)
+    ));
+    pipeline.clear();
+    assert_eq!(pipeline.messages().len(), 1);
+}
+
+#[tokio::test]
+async fn rolling_summary_replaces_evicted_turns() {
+    let completion = |content: &str| {
+        let completion = json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion",
+            "created": 1,
+            "model": "gpt-4o-mini",
+            "choices": [{
+                "index": 0,
+                "message": { "role": "assistant", "content": content },
+                "finish_reason": "stop"
+            }]
+        });
+        ("application/json", completion.to_string())
+    };
+    let question = "Tell me about the weather in Berlin tomorrow ".repeat(10);
+    let (api_base, requests) = serve(vec![
+        transcription(&question),
+        completion("It is sunny."),
+        speech("first"),
+        transcription("And on Sunday?"),
+        completion("The user asked about the weather in Berlin."),
+        completion("It rains."),
+        speech("second"),
+    ]);
+    let client = client(api_base);
+    let mut pipeline = VoicePipeline::new(&client)
+        .with_instructions("Be brief.")
+        .with_memory(
+            RollingSummary::new(client.clone(), 40)
+                .model("gpt-4o-mini")
+                .reserved_tokens(0),
+        );
+
+    pipeline.respond(audio()).await.unwrap();
+    let turn = pipeline.respond(audio()).await.unwrap();
+    assert_eq!(turn.reply, "It rains.");
+
+    let requests: Vec<_> = requests.try_iter().collect();
+    let summary: serde_json::Value = serde_json::from_str(&requests[4].1).unwrap();
+    assert_eq!(summary["model"], "gpt-4o-mini");
+    let prompt = summary["messages"][0]["content"].as_str().unwrap();
+    assert!(
+        prompt.contains("user: Tell me about the weather"),
+        "{prompt}"
+    );
+    assert!(!prompt.contains("assistant: It is sunny."), "{prompt}");
+
+    let chat: serde_json::Value = serde_json::from_str(&requests[5].1).unwrap();
+    let contents: Vec<_> = chat["messages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|message| message["content"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        contents,
+        [
+            "Be brief.",
+            "Summary of the earlier conversation:\nThe user asked about the weather in Berlin.",
+            "It is sunny.",
+            "And on Sunday?",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn respond_stream_speaks_sentence_by_sentence() {
+    let chunk = |content: &str| {
+        json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion.chunk",
+            "created": 1,
+            "model": "gpt-4o-mini",
+            "choices": [{ "index": 0, "delta": { "content": content }, "finish_reason": null }]
+        })
+    };
+    let body = ["Hello", " there. How", " can I help?"]
+        .into_iter()
+        .map(|content| format!("data: {}\n\n", chunk(content)))
+        .chain(["data: [DONE]\n\n".to_string()])
+        .collect::<String>();
+    let (api_base, requests) = serve(vec![
+        transcription("Hi"),
+        ("text/event-stream", body),
+        speech("first"),
+        speech("second"),
+    ]);
+    let client = client(api_base);
+    let mut pipeline = VoicePipeline::new(&client);
+
+    let events: Vec<_> = pipeline
+        .respond_stream(audio())
+        .map(Result::unwrap)
+        .collect()
+        .await;
+
+    let spoken: Vec<_> = events
+        .iter()
+        .filter_map(|event| match event {
+            VoiceEvent::Speech { text, audio } => Some((text.as_str(), &audio[..])),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        spoken,
+        [
+            ("Hello there.", &b"first"[..]),
+            ("How can I help?", &b"second"[..])
+        ]
+    );
+    assert_eq!(events[0], VoiceEvent::Transcript("Hi".into()));
+    let reply: String = events
+        .iter()
+        .filter_map(|event| match event {
+            VoiceEvent::ReplyDelta(delta) => Some(delta.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(reply, "Hello there. How can I help?");
+
+    let speech_inputs: Vec<_> = requests
+        .try_iter()
+        .filter(|(line, _)| line.contains("/audio/speech"))
+        .map(|(_, body)| serde_json::from_str::<serde_json::Value>(&body).unwrap()["input"].clone())
+        .collect();
+    assert_eq!(
+        speech_inputs,
+        [json!("Hello there."), json!("How can I help?")]
+    );
+    assert_eq!(pipeline.messages().len(), 2);
+}